@@ -0,0 +1,270 @@
+//! Synthetic PDFs for the benchmarks, each stressing a different part of
+//! the pipeline. They're generated with the crate's own builder rather than
+//! checked in, so the corpus is reproducible and costs nothing in the repo.
+
+use inpdf::pdf::builder::DocumentBuilder;
+use inpdf::pdf::PdfDocument;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// A generated fixture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixture {
+    /// 2,000 pages with a few lines of Helvetica text each.
+    ManySmallPages,
+    /// A few pages, each covered by a large RGB image.
+    HugeImages,
+    /// 500 pages with an outline nested 64 levels deep and 5,000 entries.
+    DeepOutline,
+    /// 200 pages of text in a Type 0 font with 2-byte codes and a
+    /// `ToUnicode` map, as CJK documents use.
+    CidFonts,
+}
+
+impl Fixture {
+    pub const ALL: [Fixture; 4] = [
+        Fixture::ManySmallPages,
+        Fixture::HugeImages,
+        Fixture::DeepOutline,
+        Fixture::CidFonts,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Fixture::ManySmallPages => "many-small-pages",
+            Fixture::HugeImages => "huge-images",
+            Fixture::DeepOutline => "deep-outline",
+            Fixture::CidFonts => "cid-fonts",
+        }
+    }
+
+    /// Write the fixture under `dir` (once per benchmark run) and return
+    /// its path.
+    pub fn path(self, dir: &Path) -> PathBuf {
+        let path = dir.join(format!("{}.pdf", self.name()));
+        if !path.exists() {
+            let mut doc = match self {
+                Fixture::ManySmallPages => many_small_pages(2000),
+                Fixture::HugeImages => huge_images(4, 3000, 2000),
+                Fixture::DeepOutline => deep_outline(500, 64, 5000),
+                Fixture::CidFonts => cid_fonts(200),
+            };
+            std::fs::create_dir_all(dir).expect("create fixture directory");
+            PdfDocument::save(&mut doc, &path).expect("write fixture");
+        }
+        path
+    }
+}
+
+/// Where fixtures are written: cargo's scratch directory for benchmarks.
+pub fn fixture_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("fixtures")
+}
+
+const LETTER: [f32; 4] = [0.0, 0.0, 612.0, 792.0];
+
+/// Words pages are made of, so searches have realistic hit rates.
+const WORDS: &[&str] = &[
+    "the",
+    "quarterly",
+    "report",
+    "covers",
+    "revenue",
+    "and",
+    "the",
+    "outlook",
+    "for",
+    "each",
+    "region",
+    "with",
+    "notes",
+    "on",
+    "methodology",
+    "invoice",
+    "total",
+];
+
+fn line(page: usize, line: usize) -> String {
+    (0..12)
+        .map(|i| WORDS[(page * 7 + line * 3 + i) % WORDS.len()])
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn helvetica(doc: &mut Document) -> ObjectId {
+    doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    })
+}
+
+fn text_page(builder: &mut DocumentBuilder, font_id: ObjectId, page: usize, lines: usize) {
+    let mut content = String::from("BT /F1 11 Tf 14 TL 72 720 Td\n");
+    for i in 0..lines {
+        let _ = writeln!(content, "({}) '", line(page, i));
+    }
+    content.push_str("ET");
+    builder.add_page(
+        LETTER,
+        dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        content.into_bytes(),
+    );
+}
+
+fn many_small_pages(pages: usize) -> Document {
+    let mut builder = DocumentBuilder::new();
+    let font_id = helvetica(builder.doc_mut());
+    for page in 0..pages {
+        text_page(&mut builder, font_id, page, 5);
+    }
+    builder.finish().expect("build fixture")
+}
+
+fn huge_images(pages: usize, width: usize, height: usize) -> Document {
+    let mut builder = DocumentBuilder::new();
+    for page in 0..pages {
+        // A gradient with some noise, so compression has real work to do.
+        let mut pixels = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                let noise = (x * 31 + y * 17 + page * 13) % 23;
+                pixels.push((x * 255 / width) as u8 ^ noise as u8);
+                pixels.push((y * 255 / height) as u8);
+                pixels.push(((x + y) % 256) as u8 ^ noise as u8);
+            }
+        }
+        let mut image = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => width as i64,
+                "Height" => height as i64,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+            },
+            pixels,
+        );
+        let _ = image.compress();
+        let image_id = builder.doc_mut().add_object(image);
+        builder.add_page(
+            LETTER,
+            dictionary! { "XObject" => dictionary! { "Im1" => image_id } },
+            b"q 612 0 0 792 0 0 cm /Im1 Do Q".to_vec(),
+        );
+    }
+    builder.finish().expect("build fixture")
+}
+
+fn deep_outline(pages: usize, depth: usize, entries: usize) -> Document {
+    let mut builder = DocumentBuilder::new();
+    let font_id = helvetica(builder.doc_mut());
+    for page in 0..pages {
+        text_page(&mut builder, font_id, page, 3);
+    }
+    let mut doc = builder.finish().expect("build fixture");
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+
+    // Entry i sits at level i % depth: a chain `depth` deep, then back to
+    // the top and down again.
+    let levels: Vec<usize> = (0..entries).map(|i| i % depth).collect();
+    let outlines_id = doc.new_object_id();
+    let ids: Vec<ObjectId> = levels.iter().map(|_| doc.new_object_id()).collect();
+    let parent = |i: usize| {
+        if levels[i] == 0 {
+            outlines_id
+        } else {
+            ids[i - 1]
+        }
+    };
+    let mut items: Vec<Dictionary> = (0..entries)
+        .map(|i| {
+            dictionary! {
+                "Title" => Object::string_literal(format!("Section {} (level {})", i + 1, levels[i] + 1)),
+                "Parent" => parent(i),
+                "Dest" => vec![page_ids[i * pages / entries].into(), "Fit".into()],
+            }
+        })
+        .collect();
+    // Only top-level entries have siblings; everything else is an only child.
+    let top: Vec<usize> = (0..entries).filter(|&i| levels[i] == 0).collect();
+    for pair in top.windows(2) {
+        items[pair[0]].set("Next", ids[pair[1]]);
+        items[pair[1]].set("Prev", ids[pair[0]]);
+    }
+    for i in 0..entries - 1 {
+        if levels[i + 1] == levels[i] + 1 {
+            items[i].set("First", ids[i + 1]);
+            items[i].set("Last", ids[i + 1]);
+        }
+    }
+    for (id, item) in ids.iter().zip(items) {
+        doc.objects.insert(*id, Object::Dictionary(item));
+    }
+    doc.objects.insert(
+        outlines_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => ids[top[0]],
+            "Last" => ids[*top.last().expect("entries")],
+            "Count" => top.len() as i64,
+        }),
+    );
+    doc.catalog_mut()
+        .expect("catalog")
+        .set("Outlines", outlines_id);
+    doc
+}
+
+fn cid_fonts(pages: usize) -> Document {
+    let mut builder = DocumentBuilder::new();
+    let doc = builder.doc_mut();
+
+    // Codes are the characters' UTF-16 units, mapped back by ToUnicode.
+    let to_unicode = "/CIDInit /ProcSet findresource begin 12 dict begin begincmap\n\
+        /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+        /CMapName /Adobe-Identity-UCS def /CMapType 2 def\n\
+        1 begincodespacerange <0000> <FFFF> endcodespacerange\n\
+        1 beginbfrange <0000> <FFFF> <0000> endbfrange\n\
+        endcmap CMapName currentdict /CMap defineresource pop end end";
+    let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode.into()));
+    let descendant_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "CIDFontType2",
+        "BaseFont" => "NotoSansCJK",
+        "CIDSystemInfo" => dictionary! {
+            "Registry" => Object::string_literal("Adobe"),
+            "Ordering" => Object::string_literal("Identity"),
+            "Supplement" => 0,
+        },
+        "DW" => 1000,
+        "CIDToGIDMap" => "Identity",
+    });
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type0",
+        "BaseFont" => "NotoSansCJK",
+        "Encoding" => "Identity-H",
+        "DescendantFonts" => vec![descendant_id.into()],
+        "ToUnicode" => to_unicode_id,
+    });
+
+    for page in 0..pages {
+        let mut content = String::from("BT /F1 11 Tf 14 TL 72 720 Td\n");
+        for i in 0..40 {
+            // Mix CJK with the Latin words so ASCII patterns still match.
+            let text = format!("{} 報告書 第{}頁", line(page, i), page + 1);
+            let hex: String = text.encode_utf16().map(|u| format!("{:04X}", u)).collect();
+            let _ = writeln!(content, "<{}> '", hex);
+        }
+        content.push_str("ET");
+        builder.add_page(
+            LETTER,
+            dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            content.into_bytes(),
+        );
+    }
+    builder.finish().expect("build fixture")
+}
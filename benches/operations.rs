@@ -0,0 +1,107 @@
+//! Extraction, search, outline, and merge timings over the synthetic
+//! fixtures in [`fixtures`]. Run with `cargo bench`; compare against a saved
+//! baseline with `cargo bench -- --save-baseline before` and
+//! `cargo bench -- --baseline before`.
+
+mod fixtures;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use fixtures::{fixture_dir, Fixture};
+use inpdf::pdf::builder::DocumentBuilder;
+use inpdf::pdf::cache::cache;
+use inpdf::pdf::import::Importer;
+use inpdf::pdf::text::grep_cached;
+use inpdf::pdf::PdfDocument;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> Vec<(Fixture, PathBuf)> {
+    let dir = fixture_dir();
+    Fixture::ALL
+        .iter()
+        .map(|&fixture| (fixture, fixture.path(&dir)))
+        .collect()
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).expect("fixture exists").len()
+}
+
+/// Parse the file and copy every page into a new, serialized document.
+fn extract(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract");
+    group.sample_size(10);
+    for (fixture, path) in fixtures() {
+        group.throughput(Throughput::Bytes(file_size(&path)));
+        group.bench_function(fixture.name(), |b| {
+            b.iter(|| {
+                cache().evict(&path);
+                let doc = PdfDocument::open(&path).unwrap();
+                let pages: Vec<u32> = (1..=doc.page_count()).collect();
+                let mut new_doc = doc.extract_pages(&pages).unwrap();
+                PdfDocument::to_bytes(&mut new_doc).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Extract every page's text from a cold cache and search it.
+fn grep(c: &mut Criterion) {
+    let regex = Regex::new(r"invoice\s+total|報告書").unwrap();
+    let mut group = c.benchmark_group("grep");
+    group.sample_size(10);
+    for (fixture, path) in fixtures() {
+        let pages = PdfDocument::open(&path).unwrap().page_count();
+        group.throughput(Throughput::Elements(pages as u64));
+        group.bench_function(fixture.name(), |b| {
+            b.iter(|| {
+                cache().evict(&path);
+                let doc = PdfDocument::open_for_text(&path).unwrap();
+                grep_cached(doc.cached(), &regex, usize::MAX).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Walk the outline of an already parsed document.
+fn toc(c: &mut Criterion) {
+    let path = Fixture::DeepOutline.path(&fixture_dir());
+    let doc = PdfDocument::open(&path).unwrap();
+    c.bench_function("toc/deep-outline", |b| b.iter(|| doc.toc().unwrap()));
+}
+
+/// Import every page of every fixture into one document and serialize it.
+fn merge(c: &mut Criterion) {
+    let docs: Vec<PdfDocument> = fixtures()
+        .iter()
+        .map(|(_, path)| PdfDocument::open(path).unwrap())
+        .collect();
+    let mut group = c.benchmark_group("merge");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(
+        docs.iter().map(|doc| doc.page_count() as u64).sum(),
+    ));
+    group.bench_function("all-fixtures", |b| {
+        b.iter_batched(
+            DocumentBuilder::new,
+            |mut builder| {
+                for doc in &docs {
+                    let mut importer = Importer::new(doc.doc());
+                    for (_, page_id) in doc.page_ids() {
+                        let new_id = importer.import_page(builder.doc_mut(), page_id).unwrap();
+                        builder.push_page(new_id).unwrap();
+                    }
+                }
+                let mut merged = builder.finish().unwrap();
+                PdfDocument::to_bytes(&mut merged).unwrap()
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, extract, grep, toc, merge);
+criterion_main!(benches);
@@ -0,0 +1,20 @@
+#![no_main]
+
+use inpdf::pdf::toc::{extract_toc_from_doc, write_toc};
+use inpdf::pdf::PdfDocument;
+use libfuzzer_sys::fuzz_target;
+
+// Whole files, so seed the corpus with real PDFs that have outlines.
+fuzz_target!(|data: &[u8]| {
+    let Ok(doc) = PdfDocument::open_bytes(data) else {
+        return;
+    };
+    let Ok(toc) = doc.toc() else {
+        return;
+    };
+    // Rewriting the outline reads the old one again to remove it.
+    let mut copy = doc.doc().clone();
+    if write_toc(&mut copy, &toc).is_ok() {
+        let _ = extract_toc_from_doc(&copy);
+    }
+});
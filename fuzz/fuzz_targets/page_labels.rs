@@ -0,0 +1,20 @@
+#![no_main]
+
+use inpdf::pdf::page_labels::{extract_page_labels_from_doc, prepend_label_range};
+use inpdf::pdf::PdfDocument;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(doc) = PdfDocument::open_bytes(data) else {
+        return;
+    };
+    let Ok(labels) = extract_page_labels_from_doc(doc.doc()) else {
+        return;
+    };
+    assert_eq!(labels.len() as u32, doc.page_count());
+
+    let mut copy = doc.doc().clone();
+    if prepend_label_range(&mut copy, 1).is_ok() {
+        let _ = extract_page_labels_from_doc(&copy);
+    }
+});
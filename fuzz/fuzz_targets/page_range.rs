@@ -0,0 +1,11 @@
+#![no_main]
+
+use inpdf::page_range::expand_page_ranges;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (&str, u16)| {
+    let (spec, total) = input;
+    if let Ok(pages) = expand_page_ranges(spec, u32::from(total)) {
+        assert!(pages.iter().all(|&page| page >= 1 && page <= u32::from(total)));
+    }
+});
@@ -1,11 +1,54 @@
-use clap::{Parser, Subcommand};
+use crate::commands;
+use crate::commands::bench::BenchOp;
+use crate::commands::collection_stats::StatsFormat;
+use crate::commands::fingerprint::FingerprintFormat;
+use crate::commands::form::DataFormat;
+use crate::commands::from_text::TextFormat;
+use crate::commands::grep::{GroupBy, SortBy};
+use crate::commands::marks::Mark;
+use crate::commands::merge::Separator;
+use crate::commands::ocr::OcrFormat;
+use crate::commands::pii::PiiFormat;
+use crate::commands::read_pages::ReadFormat;
+use crate::commands::split::SectionBoundary;
+use crate::commands::textify::SidecarFormat;
+use crate::commands::{ColorChoice, LinkPolicy};
+use crate::mcp::ToolLimits;
+use crate::page_range::{parse_definition, PageSets};
+use crate::pdf::document::{Compression, DocumentId, SaveOptions};
+use crate::pdf::layout::{parse_length, PageSize};
+use crate::remote::DownloadOptions;
+use crate::semantic::EmbedderConfig;
+use clap::{Args, Parser, Subcommand};
+use clap_complete::Shell;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(name = "inpdf")]
 #[command(about = "PDF navigation and manipulation tool with MCP server support")]
 #[command(version)]
 pub struct Cli {
+    /// Print elapsed time and peak memory to stderr when the command ends
+    #[arg(long, global = true)]
+    pub timing: bool,
+
+    /// Don't print status lines ("Wrote x.pdf"); data and errors still print
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
+
+    /// When to color grep matches and diff output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Name a page selection for use in page ranges, e.g. body=9-end (repeatable)
+    #[arg(long, global = true, value_name = "NAME=RANGES", value_parser = parse_page_set)]
+    pub define: Vec<(String, String)>,
+
+    /// Read NAME = RANGES page set definitions from a file (one per line)
+    #[arg(long, global = true, value_name = "FILE")]
+    pub page_sets_file: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -13,27 +56,213 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Run as MCP server (primary mode)
-    Mcp,
+    Mcp {
+        /// Directory of PDFs to advertise to clients (repeatable)
+        #[arg(long = "root")]
+        roots: Vec<PathBuf>,
+
+        /// Listen on a Unix domain socket instead of stdin/stdout
+        #[arg(long)]
+        socket: Option<PathBuf>,
+
+        /// Permission bits (octal) applied to the socket file
+        #[arg(long, default_value = "600", value_parser = parse_octal_mode)]
+        socket_mode: u32,
+
+        /// Print the tools (with their JSON schemas), instructions, and limits
+        /// as JSON instead of starting the server
+        #[arg(long, conflicts_with = "socket")]
+        describe: bool,
+
+        /// Append a JSON line per tool call (time, session, tool, path,
+        /// duration, outcome) to this file
+        #[arg(long, value_name = "FILE")]
+        audit_log: Option<PathBuf>,
+
+        #[command(flatten)]
+        embedder: EmbedderArgs,
+
+        #[command(flatten)]
+        limits: LimitArgs,
+    },
 
     /// Display PDF metadata
     Info {
-        /// PDF file to inspect
+        /// PDF file (or http(s):// URL) to inspect
         path: PathBuf,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Guess the software (and scanner) that produced PDFs, from metadata,
+    /// XMP history, fonts, and file structure
+    Fingerprint {
+        /// PDF files, or directories to check the PDFs under
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Output format; json writes one record per line
+        #[arg(long, value_enum, default_value_t = FingerprintFormat::Text)]
+        format: FingerprintFormat,
     },
 
     /// Print table of contents / bookmarks
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Toc {
+        #[command(subcommand)]
+        command: Option<TocCommands>,
+
+        /// PDF file (or http(s):// URL) to inspect
+        #[arg(required = true)]
+        path: Option<PathBuf>,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
+
+        /// Only show entries this many levels deep (1 = top level only)
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_depth: Option<u32>,
+
+        /// Only show entries whose title matches this regex, with their
+        /// parents and sub-entries
+        #[arg(long = "match", value_name = "REGEX")]
+        pattern: Option<String>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Explore a PDF in the terminal: outline, page text, search, and extraction
+    Browse {
+        /// PDF file to browse
+        path: PathBuf,
+    },
+
+    /// Audit tagging and metadata relevant to accessibility
+    A11y {
         /// PDF file to inspect
         path: PathBuf,
     },
 
+    /// Check a PDF's structure: decode every stream and walk the page tree,
+    /// outline, and name trees
+    ///
+    /// Exits with an error if any errors are found, so it can gate a
+    /// pipeline after inpdf (or anything else) writes a file.
+    Verify {
+        /// PDF file to check
+        path: PathBuf,
+
+        /// Fail on warnings too
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Print a shell completion script, generated from these definitions
+    Completions {
+        /// Shell to complete for
+        shell: Shell,
+
+        /// Write the script into this directory (under the name the shell
+        /// expects) instead of printing it
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+
+    /// Print the man page
+    Man {
+        /// Write inpdf.1 and a page per subcommand into this directory instead
+        /// of printing inpdf.1
+        #[arg(short, long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+    },
+
+    /// Export or fill interactive form fields
+    Form {
+        #[command(subcommand)]
+        command: FormCommands,
+    },
+
+    /// Export, import, or strip highlights and comments
+    Annotations {
+        #[command(subcommand)]
+        command: AnnotationCommands,
+    },
+
+    /// Report the color spaces each page uses and flag spot colors
+    Colors {
+        /// PDF file to inspect
+        path: PathBuf,
+    },
+
+    /// List figures per page with their alt text and captions
+    Figures {
+        /// PDF file to inspect
+        path: PathBuf,
+    },
+
+    /// Label each page as text, table, figure, scan, or blank (JSON), from
+    /// text density, image coverage, and ruling lines
+    Classify {
+        /// PDF file to inspect
+        path: PathBuf,
+
+        /// Pages to classify (default: all)
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+    },
+
+    /// Build indexes for search
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+
+    /// Search a PDF by exact matches and meaning together, most relevant
+    /// pages first (needs an index from `inpdf index embed`)
+    Search {
+        /// PDF file to search
+        path: PathBuf,
+
+        /// What to look for, in plain language
+        query: String,
+
+        /// Rank passages by embedding similarity alone, without exact matches
+        #[arg(long)]
+        semantic: bool,
+
+        /// Regular expression for exact matches (default: the query as
+        /// case-insensitive literal text)
+        #[arg(long, value_name = "REGEX", conflicts_with = "semantic")]
+        pattern: Option<String>,
+
+        /// Number of pages (or passages, with --semantic) to show
+        #[arg(short = 'n', long, default_value = "5")]
+        top: usize,
+
+        /// Index file (default: FILE.pdf.embeddings.json)
+        #[arg(long, value_name = "FILE")]
+        index: Option<PathBuf>,
+
+        #[command(flatten)]
+        embedder: EmbedderArgs,
+    },
+
     /// Search text in PDF with regex
     Grep {
         /// Regular expression pattern to search for
         pattern: String,
 
-        /// PDF file to search
-        path: PathBuf,
+        /// PDF files (or http(s):// URLs, or directories to search the PDFs
+        /// under) to search, in order
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
 
         /// Case insensitive search
         #[arg(short, long)]
@@ -42,6 +271,148 @@ pub enum Commands {
         /// Maximum number of results
         #[arg(short, long, default_value = "100")]
         max_results: usize,
+
+        /// Keep at most this many matches from any one page, so a page of
+        /// boilerplate doesn't use up --max-results
+        #[arg(long, value_name = "N")]
+        max_per_page: Option<usize>,
+
+        /// Keep at most this many matches from any one file
+        #[arg(long, value_name = "N")]
+        max_per_file: Option<usize>,
+
+        /// Group matches under a heading per page or per file
+        #[arg(long, value_enum, default_value = "none")]
+        group_by: GroupBy,
+
+        /// Order of pages or files; count puts those with the most matches
+        /// first
+        #[arg(long, value_enum, default_value = "page")]
+        sort: SortBy,
+
+        /// Characters of the line to show either side of each match
+        #[arg(short = 'C', long, default_value = "60", value_name = "N")]
+        context_chars: usize,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
+
+        /// Use a PDF packaged in this portfolio (by item name or file name)
+        #[arg(long, value_name = "NAME")]
+        portfolio_item: Option<String>,
+
+        /// Also search attached PDFs and text/XML attachments
+        #[arg(long)]
+        include_attachments: bool,
+
+        /// OCR pages without a text layer and search them too, marking those
+        /// matches with their recognition confidence; also OCR the glyphs
+        /// that text recovered from glyph names left unknown (requires
+        /// pdftoppm and tesseract)
+        #[arg(long, conflicts_with_all = ["at_version", "portfolio_item"])]
+        ocr: bool,
+
+        /// Drop OCR matches whose least certain word is below this
+        /// confidence (0-100)
+        #[arg(long, value_name = "PERCENT", requires = "ocr")]
+        min_confidence: Option<f32>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Find personal data (email addresses, phone numbers, IBANs, and card
+    /// numbers) in PDF text, as a review report for redaction
+    Pii {
+        /// PDF file (or http(s):// URL) to scan
+        path: PathBuf,
+
+        /// TOML file of extra patterns ([[patterns]] with kind and regex),
+        /// checked before the built-in ones
+        #[arg(long, value_name = "FILE")]
+        patterns: Option<PathBuf>,
+
+        /// OCR pages without a text layer and scan them too (requires
+        /// pdftoppm and tesseract)
+        #[arg(long)]
+        ocr: bool,
+
+        /// Output format; json is the report redact --from-report applies
+        #[arg(long, value_enum, default_value_t = PiiFormat::Text)]
+        format: PiiFormat,
+
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Time operations on a PDF and report throughput and peak memory
+    Bench {
+        /// PDF file to benchmark
+        path: PathBuf,
+
+        /// Operations to time, comma-separated (default: all)
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_value = "extract,grep,toc"
+        )]
+        op: Vec<BenchOp>,
+
+        /// Runs per operation; the median is reported
+        #[arg(short = 'n', long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+
+        /// Pattern for the grep operation
+        #[arg(long, default_value = "the")]
+        pattern: String,
+    },
+
+    /// Compare the text of two PDFs, marking where each side's pages start
+    Diff {
+        /// Original PDF
+        old: PathBuf,
+
+        /// Revised PDF
+        new: PathBuf,
+
+        /// Show the two documents in columns instead of a unified listing
+        #[arg(long)]
+        side_by_side: bool,
+
+        /// Also write a side-by-side HTML report to this file
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+
+        /// Unchanged lines to show around each change
+        #[arg(short = 'C', long, value_name = "N", default_value = "3")]
+        context: usize,
+
+        /// Compare rendered pages pixel by pixel instead of text (requires pdftoppm)
+        #[arg(long, conflicts_with_all = ["side_by_side", "report"])]
+        visual: bool,
+
+        /// With --visual, the fraction of differing pixels above which a page is reported
+        #[arg(
+            long,
+            value_name = "RATIO",
+            default_value = "0.02",
+            requires = "visual"
+        )]
+        threshold: f64,
+
+        /// With --visual, write highlighted difference images here
+        #[arg(short, long, value_name = "DIR", requires = "visual")]
+        output_dir: Option<PathBuf>,
+
+        /// With --visual, rendering resolution
+        #[arg(long, default_value = "72", requires = "visual")]
+        dpi: u32,
     },
 
     /// Extract page ranges to a new PDF
@@ -54,8 +425,29 @@ pub enum Commands {
         pages: String,
 
         /// Output file
-        #[arg(short, long)]
-        output: PathBuf,
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        /// Extract from a PDF packaged in this portfolio (by item name or file name)
+        #[arg(long, value_name = "NAME", conflicts_with = "in_place")]
+        portfolio_item: Option<String>,
+
+        /// What to do with links to pages that are left out
+        #[arg(long, value_enum, default_value = "drop")]
+        dangling_links: LinkPolicy,
+
+        /// Print the files that would be written, with pages and sizes, without writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 
     /// Combine multiple PDFs into one
@@ -67,9 +459,407 @@ pub enum Commands {
         /// Output file
         #[arg(short, long)]
         output: PathBuf,
+
+        /// Insert a generated page before each input document
+        #[arg(long, value_enum, value_name = "STYLE")]
+        separators: Option<Separator>,
+
+        /// Print which output pages each input's pages became
+        #[arg(long)]
+        map: bool,
+
+        /// Write the input-to-output page mapping as JSON to this file
+        #[arg(long, value_name = "FILE")]
+        map_out: Option<PathBuf>,
+
+        /// What to do with links whose targets can't be found in the
+        /// merged file
+        #[arg(long, value_enum, default_value = "drop")]
+        dangling_links: LinkPolicy,
+
+        /// Print the files that would be written, with pages and sizes, without writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        metadata: MetadataArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Rewrite a PDF linearized for fast web view (requires qpdf)
+    Linearize {
+        /// PDF file to linearize
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Show or set MediaBox, CropBox, BleedBox, TrimBox, and ArtBox
+    ///
+    /// Without --set, prints each page's boxes and where their values come
+    /// from. Edits are applied in order, e.g. `--set trim=media-9,bleed=trim+3mm`;
+    /// a box can be LLX,LLY,URX,URY (points, or with mm/cm/in units), another
+    /// box grown (+) or shrunk (-) on every side, or `none` to remove it.
+    Boxes {
+        /// PDF file
+        path: PathBuf,
+
+        /// Pages to show or change (default: all)
+        #[arg(long, visible_alias = "page", value_name = "RANGE")]
+        pages: Option<String>,
+
+        /// Box edits, comma-separated (repeatable)
+        #[arg(long, value_name = "BOX=VALUE,...")]
+        set: Vec<String>,
+
+        /// Output file (with --set)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Add printer's marks and bleed around each page, setting TrimBox and BleedBox
+    Marks {
+        /// PDF file to mark
+        path: PathBuf,
+
+        /// Marks to draw, comma-separated
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "crop")]
+        marks: Vec<Mark>,
+
+        /// How far artwork extends past the trim, e.g. "3mm" or "0.125in"
+        #[arg(long, default_value = "0", value_parser = parse_length)]
+        bleed: f32,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Remove the approved items of a reviewed `pii --format json` report
+    /// from a PDF, covering each with a black box
+    ///
+    /// Items with "approved": false are kept. Every item is listed with what
+    /// happened to it and where; the command fails (after saving) if an
+    /// approved item couldn't be removed, e.g. one found only by OCR.
+    Redact {
+        /// PDF file to redact (default: the file the report was made from)
+        path: Option<PathBuf>,
+
+        /// Report from `inpdf pii --format json`, after review
+        #[arg(long, value_name = "REPORT")]
+        from_report: PathBuf,
+
+        /// Also write the audit trail to this file, as JSON
+        #[arg(long, value_name = "FILE")]
+        audit: Option<PathBuf>,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Replace text in the content stream, keeping the rest of the line in
+    /// place
+    ///
+    /// Matching ignores whitespace. An occurrence is replaced only if its
+    /// font can show the replacement (a simple font with codes, and for
+    /// subsets glyphs, for every character); others are left and reported.
+    Replace {
+        /// PDF file
+        path: PathBuf,
+
+        /// Text to look for
+        #[arg(long)]
+        find: String,
+
+        /// Text to put in its place
+        #[arg(long, value_name = "TEXT")]
+        with: String,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Embed fonts the PDF only names, using TrueType/OpenType files from a
+    /// directory, so it prints and archives the same everywhere
+    ///
+    /// Fonts are matched by name; the standard 14 (Helvetica, Times, Courier)
+    /// also match metric-compatible families such as Liberation, Arial,
+    /// Arimo, and Nimbus. Subsetting requires pyftsubset (fonttools).
+    EmbedFonts {
+        /// PDF file
+        path: PathBuf,
+
+        /// Directory to look for .ttf and .otf files in (recursively)
+        #[arg(long, value_name = "DIR")]
+        font_dir: PathBuf,
+
+        /// Embed whole fonts instead of the glyphs the document uses
+        #[arg(long)]
+        no_subset: bool,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 
-    /// Split PDF into individual pages
+    /// Draw another PDF's pages beneath or above each page (letterhead, stamp)
+    Overlay {
+        /// PDF whose pages receive the overlay
+        path: PathBuf,
+
+        /// PDF drawn beneath each page (e.g., letterhead or background)
+        #[arg(long)]
+        under: Option<PathBuf>,
+
+        /// PDF drawn above each page (e.g., a stamp)
+        #[arg(long)]
+        over: Option<PathBuf>,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Recognize the text of scanned pages (requires pdftoppm and tesseract)
+    Ocr {
+        /// PDF file to recognize
+        path: PathBuf,
+
+        /// Pages to recognize (default: all)
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+
+        /// Output format; alto, hocr, and tsv keep word coordinates (in
+        /// pixels at --dpi) and confidences
+        #[arg(long, value_enum, default_value = "text")]
+        ocr_format: OcrFormat,
+
+        /// Write one file per page here instead of printing
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Tesseract language(s), e.g. "eng" or "deu+eng"
+        #[arg(long, default_value = "eng")]
+        lang: String,
+
+        /// Resolution to render pages at for recognition
+        #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u32).range(1..))]
+        dpi: u32,
+    },
+
+    /// Find sideways or upside-down pages from their text direction and fix /Rotate
+    ///
+    /// Prints the pages that need turning; with -o or --in-place, writes the
+    /// corrected document. Scans need an OCR text layer to be measured.
+    DeskewOrientation {
+        /// PDF file to check
+        path: PathBuf,
+
+        /// Write a copy with corrected page rotation
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Lay out thumbnails of every page in a grid, for triage at a glance
+    ContactSheet {
+        /// PDF file to summarize
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Thumbnails per row
+        #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+        cols: u32,
+
+        /// Rows of thumbnails per sheet
+        #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..))]
+        rows: u32,
+
+        /// Sheet size: a3, a4, a5, letter, legal (optionally "-landscape"), or
+        /// WIDTHxHEIGHT in points
+        #[arg(long, value_parser = PageSize::parse, default_value = "letter")]
+        page_size: PageSize,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Build a PDF with one page per image (JPEG or PNG)
+    FromImages {
+        /// Image files, in page order
+        #[arg(required = true)]
+        images: Vec<PathBuf>,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Page size: a3, a4, a5, letter, legal (optionally "-landscape"), or
+        /// WIDTHxHEIGHT in points. Defaults to each image's own size.
+        #[arg(long, value_parser = PageSize::parse)]
+        page_size: Option<PageSize>,
+
+        /// Scale each image to fill the page, keeping its aspect ratio
+        #[arg(long)]
+        fit: bool,
+
+        /// Image resolution used to size pages and unscaled images
+        #[arg(long, default_value_t = 72.0)]
+        dpi: f32,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Convert a page's vector graphics, text, and images to SVG
+    ToSvg {
+        /// PDF file to convert
+        path: PathBuf,
+
+        /// Page number (1-indexed)
+        #[arg(short, long, default_value_t = 1)]
+        page: u32,
+
+        /// Output SVG file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Typeset plain text or basic Markdown into a paginated PDF
+    FromText {
+        /// Text or Markdown file
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Input format
+        #[arg(long, value_enum, default_value_t = TextFormat::Auto)]
+        format: TextFormat,
+
+        /// Page size: a3, a4, a5, letter, legal (optionally "-landscape"), or
+        /// WIDTHxHEIGHT in points
+        #[arg(long, value_parser = PageSize::parse, default_value = "letter")]
+        page_size: PageSize,
+
+        /// Body text size in points
+        #[arg(long, default_value_t = 11.0)]
+        font_size: f32,
+
+        /// Page margin in points
+        #[arg(long, default_value_t = 72.0)]
+        margin: f32,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Repeat selected pages in place (forms, label sheets)
+    Duplicate {
+        /// PDF file to read
+        path: PathBuf,
+
+        /// Pages to duplicate (e.g., "1", "2-3")
+        #[arg(short, long, default_value = "1-end")]
+        pages: String,
+
+        /// Number of times each selected page appears in the output
+        #[arg(short, long, default_value_t = 2)]
+        count: u32,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Rearrange pages into a new order (e.g., "5,1-4,6-end")
+    Reorder {
+        /// PDF file to reorder
+        path: PathBuf,
+
+        /// New page order; must list every page exactly once
+        #[arg(required_unless_present = "reverse")]
+        order: Option<String>,
+
+        /// Reverse the page order (applied after ORDER, if given)
+        #[arg(long)]
+        reverse: bool,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Split PDF into individual pages, or one file per outline section
     #[command(alias = "burst")]
     Split {
         /// PDF file to split
@@ -78,20 +868,645 @@ pub enum Commands {
         /// Output directory
         #[arg(short, long)]
         output_dir: PathBuf,
+
+        /// Write one file per bookmark instead of one per page, plus a
+        /// manifest.json listing each file's title and pages
+        #[arg(long)]
+        by_section: bool,
+
+        /// Write front matter, body, and back matter (appendices, index) as
+        /// three files, found from page labels and bookmarks, plus a
+        /// manifest.json
+        #[arg(long, conflicts_with = "by_section")]
+        logical: bool,
+
+        /// Split at bookmarks this many levels deep or shallower (1 = chapters)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..), requires = "by_section")]
+        level: u32,
+
+        /// Also write the pages before the first bookmark as 00-front-matter.pdf
+        #[arg(long, requires = "by_section")]
+        include_front_matter: bool,
+
+        /// Where to cut when a section starts partway down a page: `page`
+        /// gives that page to the new section, `heading` puts it in both
+        #[arg(long, value_enum, default_value = "page", requires = "by_section")]
+        boundary: SectionBoundary,
+
+        /// What to do with links to pages outside each output file
+        #[arg(long, value_enum, default_value = "drop")]
+        dangling_links: LinkPolicy,
+
+        /// Print the files that would be written, with pages and sizes, without writing them
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// List incremental-update revisions of a PDF
+    Versions {
+        /// PDF file to inspect
+        path: PathBuf,
+    },
+
+    /// Show which outline entries contain a page (e.g., "Part II > Chapter 5")
+    OutlinePath {
+        /// PDF file to inspect
+        path: PathBuf,
+
+        /// Physical page number (1-indexed)
+        page: u32,
+    },
+
+    /// Inspect raw PDF internals
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommands,
     },
 
     /// Show page label mapping (logical vs physical page numbers)
     PageLabels {
         /// PDF file to inspect
         path: PathBuf,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
     },
 
     /// Extract text from specific pages
     ReadPages {
-        /// PDF file to read
+        /// PDF file (or http(s):// URL) to read
         path: PathBuf,
 
         /// Page ranges (e.g., "1-5,10")
         pages: String,
+
+        /// Prefix each page with its logical label and enclosing TOC section
+        #[arg(long)]
+        annotate_sections: bool,
+
+        /// Write all pages to this file instead of stdout
+        #[arg(long, conflicts_with = "out_dir")]
+        out: Option<PathBuf>,
+
+        /// Write one text file per page into this directory
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+
+        /// File name template for --out-dir ({stem}, {page}, {page:04}, {label})
+        #[arg(long, default_value = commands::read_pages::DEFAULT_TEMPLATE, requires = "out_dir")]
+        template: String,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
+
+        /// Use a PDF packaged in this portfolio (by item name or file name)
+        #[arg(long, value_name = "NAME")]
+        portfolio_item: Option<String>,
+
+        /// Output format; json gives each page's label, section, and size
+        #[arg(long, value_enum, default_value_t = ReadFormat::Text)]
+        format: ReadFormat,
+
+        /// Cut each page's text to at most N characters
+        #[arg(long, value_name = "N")]
+        max_chars: Option<usize>,
+
+        /// Clean up the text for a kind of document: academic, legal,
+        /// invoice, book, or a profile from the config file
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Read the page an outline heading starts on and N pages after it
+    ReadAfter {
+        /// PDF file (or http(s):// URL) to read
+        path: PathBuf,
+
+        /// Outline heading to start at (case-insensitive; a unique part of
+        /// the title is enough)
+        #[arg(long)]
+        heading: String,
+
+        /// Number of pages to read after the heading's page
+        #[arg(long, value_name = "N", default_value_t = 1)]
+        pages: u32,
+
+        /// Prefix each page with its logical label and enclosing TOC section
+        #[arg(long)]
+        annotate_sections: bool,
+
+        /// Read the document as of an earlier incremental-update revision
+        #[arg(long, value_name = "N")]
+        at_version: Option<usize>,
+
+        /// Use a PDF packaged in this portfolio (by item name or file name)
+        #[arg(long, value_name = "NAME")]
+        portfolio_item: Option<String>,
+
+        /// Output format; json gives each page's label, section, and size
+        #[arg(long, value_enum, default_value_t = ReadFormat::Text)]
+        format: ReadFormat,
+
+        /// Cut each page's text to at most N characters
+        #[arg(long, value_name = "N")]
+        max_chars: Option<usize>,
+
+        /// Clean up the text for a kind of document: academic, legal,
+        /// invoice, book, or a profile from the config file
+        #[arg(long, value_name = "NAME")]
+        profile: Option<String>,
+
+        #[command(flatten)]
+        download: DownloadArgs,
+    },
+
+    /// Summarize a folder of PDFs: pages, words, encryption, scans, tagging, producers, sizes
+    CollectionStats {
+        /// Directory to search (recursively) for PDFs
+        dir: PathBuf,
+
+        /// Report format; csv has one row per file
+        #[arg(long, value_enum, default_value_t = StatsFormat::Json)]
+        format: StatsFormat,
+
+        /// Write the report to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Write a text sidecar (file.pdf.txt) next to each PDF in a directory
+    ///
+    /// Only PDFs whose sidecar is missing or older than the PDF are
+    /// processed, so rerunning after adding files is cheap.
+    Textify {
+        /// Directory of PDFs
+        dir: PathBuf,
+
+        /// Include subdirectories
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Sidecar format
+        #[arg(long, value_enum, default_value_t = SidecarFormat::Text)]
+        format: SidecarFormat,
+
+        /// Rewrite sidecars even if they are up to date
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Options shared by every command that writes PDF files.
+#[derive(Args)]
+pub struct WriteArgs {
+    /// Produce byte-identical output for identical inputs (honors SOURCE_DATE_EPOCH)
+    #[arg(long)]
+    pub deterministic: bool,
+
+    /// Keep the input's permanent document ID instead of writing a new one
+    #[arg(long)]
+    pub keep_id: bool,
+
+    /// Compress streams and use object/xref streams (PDF 1.5+) for smaller output
+    #[arg(long, conflicts_with = "uncompressed")]
+    pub compress: bool,
+
+    /// Decompress all streams so the output is human-readable (for debugging)
+    #[arg(long)]
+    pub uncompressed: bool,
+
+    /// Linearize output for fast web view (requires qpdf)
+    #[arg(long)]
+    pub linearize: bool,
+
+    /// Write a JSON manifest of the inputs, page selection, and files written, with SHA-256 hashes
+    #[arg(long, value_name = "FILE")]
+    pub manifest_out: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The page sets from `--page-sets-file` and `--define`, later
+    /// definitions replacing earlier ones.
+    pub fn page_sets(&self) -> anyhow::Result<PageSets> {
+        let mut sets = PageSets::default();
+        if let Some(path) = &self.page_sets_file {
+            sets.load(path)?;
+        }
+        for (name, ranges) in &self.define {
+            sets.define(name, ranges)?;
+        }
+        Ok(sets)
+    }
+}
+
+impl WriteArgs {
+    pub fn save_options(&self) -> SaveOptions {
+        let compression = if self.compress {
+            Compression::Compress
+        } else if self.uncompressed {
+            Compression::Uncompressed
+        } else {
+            Compression::Preserve
+        };
+        let document_id = if self.keep_id {
+            DocumentId::Keep
+        } else {
+            DocumentId::Regenerate
+        };
+        SaveOptions {
+            deterministic: self.deterministic,
+            document_id,
+            compression,
+            linearize: self.linearize,
+        }
+    }
+}
+
+/// The output's document metadata (`/Info`). ModDate and Producer are
+/// always updated.
+#[derive(Args)]
+pub struct MetadataArgs {
+    /// Replace the output's metadata with another PDF's
+    #[arg(long, value_name = "from:FILE", value_parser = parse_metadata_source, conflicts_with = "clear_metadata")]
+    pub copy_metadata: Option<PathBuf>,
+
+    /// Set the output's title
+    #[arg(long, value_name = "TITLE")]
+    pub set_title: Option<String>,
+
+    /// Write no metadata besides ModDate, Producer, and any --set-title
+    #[arg(long)]
+    pub clear_metadata: bool,
+}
+
+/// Per-call limits for the MCP server's text tools.
+#[derive(Args)]
+pub struct LimitArgs {
+    /// Most pages pdf_read_pages returns per call
+    #[arg(long, default_value = "200", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_pages: u32,
+
+    /// Most bytes of text pdf_read_pages, pdf_grep, and pdf_grep_workspace return per call
+    #[arg(long, default_value = "4194304")]
+    pub max_text_bytes: usize,
+
+    /// Seconds a tool call may take (pdf_extract, which writes files, isn't timed out)
+    #[arg(long, value_name = "SECS", default_value = "60")]
+    pub tool_timeout: u64,
+
+    /// Most tool calls each session may make per minute (default: no limit)
+    #[arg(long, value_name = "CALLS", value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit: Option<u32>,
+}
+
+impl LimitArgs {
+    pub fn limits(&self) -> ToolLimits {
+        ToolLimits {
+            max_pages: self.max_pages as usize,
+            max_text_bytes: self.max_text_bytes,
+            timeout: Duration::from_secs(self.tool_timeout),
+            calls_per_minute: self.rate_limit,
+        }
+    }
+}
+
+/// Where semantic search gets embeddings (needs the `semantic` feature).
+#[derive(Args)]
+pub struct EmbedderArgs {
+    /// OpenAI-compatible embeddings URL, e.g. http://localhost:11434/v1/embeddings
+    /// (sends INPDF_EMBEDDING_API_KEY as a bearer token if set)
+    #[arg(long, value_name = "URL", conflicts_with = "onnx_model")]
+    pub embedding_endpoint: Option<String>,
+
+    /// Model to request from --embedding-endpoint
+    #[arg(long, value_name = "NAME", default_value = "nomic-embed-text")]
+    pub embedding_model: String,
+
+    /// Directory with a local ONNX embedding model (model.onnx and tokenizer.json)
+    #[arg(long, value_name = "DIR")]
+    pub onnx_model: Option<PathBuf>,
+}
+
+impl EmbedderArgs {
+    pub fn config(&self) -> EmbedderConfig {
+        EmbedderConfig {
+            endpoint: self.embedding_endpoint.clone(),
+            model: self.embedding_model.clone(),
+            onnx_model: self.onnx_model.clone(),
+        }
+    }
+
+    /// Whether an embedding model was given at all.
+    pub fn is_set(&self) -> bool {
+        self.embedding_endpoint.is_some() || self.onnx_model.is_some()
+    }
+}
+
+/// Options for read-only commands that accept an `http(s)://` URL in place
+/// of a file.
+#[derive(Args)]
+pub struct DownloadArgs {
+    /// Largest document to download, in megabytes
+    #[arg(long, value_name = "MB", default_value = "100")]
+    pub max_download: u64,
+
+    /// Download again even if a copy fetched in the last day is cached
+    #[arg(long)]
+    pub refresh: bool,
+}
+
+impl DownloadArgs {
+    pub fn options(&self) -> DownloadOptions {
+        DownloadOptions {
+            max_bytes: self.max_download.saturating_mul(1 << 20),
+            refresh: self.refresh,
+        }
+    }
+}
+
+/// Options for commands that can rewrite their input file.
+#[derive(Args)]
+pub struct InPlaceArgs {
+    /// Overwrite the input file (atomically) instead of writing to -o
+    #[arg(long, conflicts_with = "output")]
+    pub in_place: bool,
+
+    /// With --in-place, keep a copy of the original with this suffix (e.g. ".bak")
+    #[arg(long, value_name = "SUFFIX", requires = "in_place")]
+    pub backup: Option<String>,
+}
+
+#[derive(Subcommand)]
+pub enum TocCommands {
+    /// Typeset the outline as printable contents pages with dot leaders
+    Render {
+        /// PDF file whose outline to render
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        /// Put the contents pages in front of the document, with links to
+        /// each entry (otherwise only the contents pages are written)
+        #[arg(long, required_if_eq("in_place", "true"))]
+        prepend: bool,
+
+        /// Heading above the entries
+        #[arg(long, default_value = "Contents")]
+        title: String,
+
+        /// Page size: a3, a4, a5, letter, legal (optionally "-landscape"), or
+        /// WIDTHxHEIGHT in points
+        #[arg(long, value_parser = PageSize::parse, default_value = "letter")]
+        page_size: PageSize,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Build an outline from the document's printed contents pages
+    ///
+    /// Prints the entries found; with -o or --in-place, installs them as
+    /// bookmarks. Printed page numbers are looked up in the page labels.
+    Detect {
+        /// PDF file with printed contents pages
+        path: PathBuf,
+
+        /// Pages to search for the contents (default: the first 30)
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+
+        /// Write a copy with the detected outline installed
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Rename, delete, or re-point outline entries
+    ///
+    /// Titles are matched as regular expressions against the whole title.
+    /// Deleting an entry also deletes its sub-entries.
+    Edit {
+        /// PDF file whose outline to edit
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        /// Change a title; NEW may use $1 etc. for groups in OLD (repeatable)
+        #[arg(long, value_name = "OLD=NEW")]
+        rename: Vec<String>,
+
+        /// Remove matching entries (repeatable)
+        #[arg(long, value_name = "TITLE")]
+        delete: Vec<String>,
+
+        /// Point matching entries at a physical page (repeatable)
+        #[arg(long, value_name = "TITLE=PAGE")]
+        retarget: Vec<String>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
     },
 }
+
+#[derive(Subcommand)]
+pub enum IndexCommands {
+    /// Split each page's text into chunks and embed them for `search --semantic`
+    Embed {
+        /// PDF file to index
+        path: PathBuf,
+
+        /// Index file to write (default: FILE.pdf.embeddings.json)
+        #[arg(long, value_name = "FILE")]
+        index: Option<PathBuf>,
+
+        /// Longest chunk, in characters
+        #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u32).range(100..))]
+        chunk_chars: u32,
+
+        #[command(flatten)]
+        embedder: EmbedderArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum FormCommands {
+    /// Write field values as XFDF, FDF, or JSON (to stdout without -o)
+    Export {
+        /// PDF file with a form
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Data format
+        #[arg(long, value_enum, default_value_t = DataFormat::Auto)]
+        format: DataFormat,
+    },
+
+    /// Write one CSV row of field values per form (to stdout without -o)
+    ExportBatch {
+        /// PDF files or directories of PDFs
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output CSV file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Set field values from an XFDF, FDF, or JSON file
+    Fill {
+        /// PDF file with a form
+        path: PathBuf,
+
+        /// Field values to fill in
+        #[arg(long, value_name = "FILE")]
+        data: PathBuf,
+
+        /// Format of the --data file
+        #[arg(long, value_enum, default_value_t = DataFormat::Auto)]
+        format: DataFormat,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AnnotationCommands {
+    /// Write highlights and comments as Markdown notes, grouped by outline
+    /// section, with page references and colors (to stdout without -o)
+    ExportMd {
+        /// PDF file with annotations
+        path: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Remove annotations, all but links and form fields by default
+    Strip {
+        /// PDF file with annotations
+        path: PathBuf,
+
+        /// Only remove these types (e.g. highlight,popup,link); links and
+        /// form fields are kept unless listed
+        #[arg(long, value_delimiter = ',', value_name = "TYPES")]
+        types: Vec<String>,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+
+    /// Add highlights and notes from a notes file at the text they quote
+    Import {
+        /// PDF file to annotate
+        path: PathBuf,
+
+        /// Notes as JSON (a list of {page, type, text, comment, color,
+        /// author}) or Markdown from export-md
+        #[arg(long, value_name = "FILE")]
+        from: PathBuf,
+
+        /// Output file
+        #[arg(short, long, required_unless_present = "in_place")]
+        output: Option<PathBuf>,
+
+        #[command(flatten)]
+        in_place: InPlaceArgs,
+
+        #[command(flatten)]
+        write: WriteArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DebugCommands {
+    /// Pretty-print a raw object by its ID
+    Object {
+        /// PDF file to inspect
+        path: PathBuf,
+
+        /// Object number
+        id: u32,
+
+        /// Generation number
+        #[arg(default_value = "0")]
+        generation: u16,
+
+        /// Also dump the decoded stream data
+        #[arg(long)]
+        stream: bool,
+    },
+
+    /// Report version, xref type, object/filter counts, revisions, and IDs
+    Stats {
+        /// PDF file to inspect
+        path: PathBuf,
+    },
+
+    /// Print a page's decoded content stream operators
+    Content {
+        /// PDF file to inspect
+        path: PathBuf,
+
+        /// Physical page number (1-indexed)
+        #[arg(short, long)]
+        page: u32,
+    },
+}
+
+fn parse_octal_mode(s: &str) -> Result<u32, String> {
+    u32::from_str_radix(s, 8).map_err(|_| format!("invalid octal mode: {}", s))
+}
+
+fn parse_metadata_source(s: &str) -> Result<PathBuf, String> {
+    match s.strip_prefix("from:") {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Err(format!("expected from:FILE, got {}", s)),
+    }
+}
+
+fn parse_page_set(s: &str) -> Result<(String, String), String> {
+    let (name, ranges) = parse_definition(s).map_err(|e| e.to_string())?;
+    Ok((name.to_string(), ranges.to_string()))
+}
@@ -0,0 +1,39 @@
+use crate::pdf::accessibility::check_accessibility;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let report = check_accessibility(doc.doc());
+
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+
+    println!("File: {}", path.as_ref().display());
+    println!("Tagged: {}", yes_no(report.marked));
+    if report.has_struct_tree {
+        println!("Structure tree: yes ({} elements)", report.struct_elements);
+    } else {
+        println!("Structure tree: no");
+    }
+    println!("Language: {}", report.lang.as_deref().unwrap_or("missing"));
+    println!("Title: {}", report.title.as_deref().unwrap_or("missing"));
+    println!("Display title: {}", yes_no(report.display_doc_title));
+    println!(
+        "Figures: {} ({} without alt text)",
+        report.figures, report.figures_without_alt
+    );
+
+    let issues = report.issues();
+    println!();
+    if issues.is_empty() {
+        println!("No issues found.");
+    } else {
+        println!("Issues ({}):", issues.len());
+        for (count, issue) in issues {
+            println!("  {:>4}  {}", count, issue);
+        }
+    }
+
+    Ok(())
+}
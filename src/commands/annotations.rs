@@ -0,0 +1,472 @@
+use crate::commands::status;
+use crate::pdf::annotations::{
+    add_annotation, color_name, document_annotations, parse_color, parse_subtype,
+    strip_annotations, subtype_for_kind, Annotation, NewAnnotation, COLORS, KEPT_BY_DEFAULT,
+    TEXT_MARKUP,
+};
+use crate::pdf::document::{format_pdf_date_utc, SaveOptions};
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::toc::{extract_toc_from_doc, outline_path, TocEntry};
+use crate::pdf::PdfDocument;
+use anyhow::{bail, Context, Result};
+use lopdf::ObjectId;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::Path;
+
+/// A note to import. JSON notes files hold a list of these, either bare or
+/// under `"notes"`:
+///
+/// ```json
+/// {"notes": [{"page": 9, "type": "highlight", "text": "two sites",
+///             "comment": "Check the sample sizes", "color": "yellow"}]}
+/// ```
+///
+/// The text is looked for on the page given first, then on the others.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+struct NoteEntry {
+    page: Option<u32>,
+    /// `highlight`, `underline`, `squiggly`, `strikeout`, or `note`;
+    /// defaults to a highlight if there is text, else a note.
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    text: Option<String>,
+    comment: Option<String>,
+    /// A color name or `#rrggbb`; defaults to yellow.
+    color: Option<String>,
+    author: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NotesFile {
+    Wrapped { notes: Vec<NoteEntry> },
+    Bare(Vec<NoteEntry>),
+}
+
+/// Annotations that are not notes: pop-ups show their parent's comment,
+/// links and form widgets aren't commentary.
+const NOT_NOTES: &[&str] = &["Popup", "Link", "Widget"];
+
+/// Write highlights and comments as Markdown notes, grouped under the
+/// outline sections they fall in (or by page without an outline).
+pub fn export_md<P: AsRef<Path>>(path: P, output: Option<&Path>) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let annotations = document_annotations(doc.doc());
+    let toc = extract_toc_from_doc(doc.doc())?;
+    let labels: Vec<String> = extract_page_labels_from_doc(doc.doc())?
+        .into_iter()
+        .map(|label| label.logical_label)
+        .collect();
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let (markdown, count) = notes_markdown(&name, &annotations, &toc, &labels);
+
+    if let Some(output) = output {
+        std::fs::write(output, &markdown)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        status!("Exported {} note(s) to {}", count, output.display());
+    } else {
+        std::io::stdout().write_all(markdown.as_bytes())?;
+    }
+    if count == 0 {
+        status!("No highlights or comments found");
+    }
+    Ok(())
+}
+
+/// Render the notes among `annotations`; returns the Markdown and how many
+/// notes it has. Each note is the marked text as a quote, its comment and
+/// replies, and a reference line such as `*p. xii (page 12) · yellow
+/// highlight · Jane*`.
+fn notes_markdown(
+    name: &str,
+    annotations: &[Annotation],
+    toc: &[TocEntry],
+    labels: &[String],
+) -> (String, usize) {
+    let mut replies: HashMap<ObjectId, Vec<&Annotation>> = HashMap::new();
+    for annotation in annotations {
+        if let Some(parent) = annotation.in_reply_to {
+            replies.entry(parent).or_default().push(annotation);
+        }
+    }
+    let notes = annotations.iter().filter(|a| {
+        a.in_reply_to.is_none()
+            && !NOT_NOTES.contains(&a.subtype.as_str())
+            && (TEXT_MARKUP.contains(&a.subtype.as_str()) || a.contents.is_some())
+    });
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# Notes on {}", name);
+    let mut headings: Vec<String> = Vec::new();
+    let mut count = 0;
+    for note in notes {
+        let label = labels
+            .get(note.page as usize - 1)
+            .filter(|label| !label.is_empty())
+            .cloned()
+            .unwrap_or_else(|| note.page.to_string());
+
+        let path: Vec<String> = if toc.is_empty() {
+            vec![format!("Page {}", note.page)]
+        } else {
+            outline_path(toc, note.page)
+                .into_iter()
+                .map(|entry| entry.title)
+                .collect()
+        };
+        let same = headings
+            .iter()
+            .zip(&path)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, title) in path.iter().enumerate().skip(same) {
+            let _ = writeln!(out, "\n{} {}", "#".repeat((depth + 2).min(6)), title);
+        }
+        headings = path;
+
+        out.push('\n');
+        if let Some(text) = note.text.as_deref().filter(|text| !text.is_empty()) {
+            let _ = writeln!(out, "> {}\n", text);
+        }
+        if let Some(contents) = &note.contents {
+            let _ = writeln!(out, "{}\n", contents.trim());
+        }
+        let note_replies = note.id.and_then(|id| replies.get(&id));
+        for reply in note_replies.into_iter().flatten() {
+            if let Some(contents) = &reply.contents {
+                let who = reply.author.as_deref().unwrap_or("Reply");
+                let _ = writeln!(out, "**{}:** {}\n", who, contents.trim());
+            }
+        }
+
+        let mut reference = if label == note.page.to_string() {
+            format!("p. {}", label)
+        } else {
+            format!("p. {} (page {})", label, note.page)
+        };
+        reference.push_str(" · ");
+        if let Some(color) = note.color {
+            let _ = write!(reference, "{} ", color_name(color));
+        }
+        reference.push_str(&note.kind());
+        if let Some(author) = &note.author {
+            let _ = write!(reference, " · {}", author);
+        }
+        let _ = writeln!(out, "*{}*", reference);
+        count += 1;
+    }
+    (out, count)
+}
+
+/// Recreate highlights and notes from a JSON notes file or Markdown written
+/// by `export-md`, at the text they refer to.
+pub fn import<P: AsRef<Path>>(
+    path: P,
+    from: &Path,
+    output: &Path,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let text = std::fs::read_to_string(from)
+        .with_context(|| format!("Failed to read {}", from.display()))?;
+    let notes = match from.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            let file: NotesFile = serde_json::from_str(&text)
+                .with_context(|| format!("Failed to parse {}", from.display()))?;
+            match file {
+                NotesFile::Wrapped { notes } | NotesFile::Bare(notes) => notes,
+            }
+        }
+        Some("md" | "markdown") => parse_markdown_notes(&text),
+        _ => bail!(
+            "Cannot tell the format of {} from its extension; use .json or .md",
+            from.display()
+        ),
+    };
+
+    let doc = PdfDocument::open(&path)?;
+    let mut new_doc = doc.doc().clone();
+    let pages = new_doc.get_pages();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let modified = format_pdf_date_utc(now);
+
+    let mut added = 0;
+    for (i, note) in notes.iter().enumerate() {
+        let Some(annotation) = new_annotation(note) else {
+            eprintln!(
+                "Warning: note {}: nothing to import for type '{}'",
+                i + 1,
+                note.kind.as_deref().unwrap_or_default()
+            );
+            continue;
+        };
+        // The page given first, then the others in case the text moved
+        // (as between versions of a document).
+        let mut candidates: Vec<_> = pages.values().copied().collect();
+        if let Some(page_id) = note.page.and_then(|page| pages.get(&page)) {
+            candidates.retain(|id| id != page_id);
+            candidates.insert(0, *page_id);
+        }
+        let mut placed = false;
+        for page_id in candidates {
+            if add_annotation(&mut new_doc, page_id, &annotation, &modified)? {
+                placed = true;
+                break;
+            }
+        }
+        if placed {
+            added += 1;
+        } else {
+            eprintln!(
+                "Warning: note {}: text not found: {}",
+                i + 1,
+                annotation.text.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+    status!(
+        "Added {} of {} note(s) to {}",
+        added,
+        notes.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// The annotation for a note, or `None` if there is nothing to add: an
+/// unknown type without a comment, or markup without text.
+fn new_annotation(note: &NoteEntry) -> Option<NewAnnotation> {
+    let text = note.text.clone().filter(|t| !t.trim().is_empty());
+    let subtype = match (&note.kind, &text) {
+        (Some(kind), _) => match subtype_for_kind(kind) {
+            Some(subtype) => subtype,
+            // Other kinds (text boxes, shapes) come back as notes.
+            None if note.comment.is_some() => "Text",
+            None => return None,
+        },
+        (None, Some(_)) => "Highlight",
+        (None, None) => "Text",
+    };
+    if subtype != "Text" && text.is_none() {
+        return None;
+    }
+    let color = match &note.color {
+        Some(color) => parse_color(color).unwrap_or_else(|| {
+            eprintln!("Warning: unknown color '{}', using yellow", color);
+            COLORS[0].1
+        }),
+        None => COLORS[0].1,
+    };
+    Some(NewAnnotation {
+        subtype,
+        text,
+        contents: note.comment.clone(),
+        color,
+        author: note.author.clone(),
+    })
+}
+
+/// Read notes back from [`notes_markdown`] output: quoted lines are the
+/// marked text, other paragraphs the comment, and each reference line ends
+/// a note. Headings are skipped.
+fn parse_markdown_notes(markdown: &str) -> Vec<NoteEntry> {
+    let mut notes = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    for line in markdown.lines().map(str::trim_end) {
+        let reference = line
+            .strip_prefix('*')
+            .and_then(|l| l.strip_suffix('*'))
+            .filter(|l| l.starts_with("p. "));
+        if line.starts_with('#') {
+            continue;
+        } else if let Some(quoted) = line.strip_prefix('>') {
+            quote.push(quoted.trim());
+        } else if line.trim().is_empty() {
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph.join("\n"));
+                paragraph.clear();
+            }
+        } else if let Some(reference) = reference {
+            if !paragraph.is_empty() {
+                paragraphs.push(paragraph.join("\n"));
+                paragraph.clear();
+            }
+            let mut note = parse_reference(reference);
+            note.text = Some(quote.join(" ")).filter(|t| !t.is_empty());
+            note.comment = Some(paragraphs.join("\n\n"))
+                .map(|c| c.replace("**", ""))
+                .filter(|c| !c.is_empty());
+            notes.push(note);
+            quote.clear();
+            paragraphs.clear();
+        } else {
+            paragraph.push(line.trim());
+        }
+    }
+    notes
+}
+
+/// Parse `p. xii (page 12) · yellow highlight · Jane`.
+fn parse_reference(reference: &str) -> NoteEntry {
+    let mut parts = reference.split(" · ");
+    let location = parts.next().unwrap_or_default();
+    let page = match location.split_once("(page ") {
+        Some((_, rest)) => rest.trim_end_matches(')').parse().ok(),
+        None => location.trim_start_matches("p. ").parse().ok(),
+    };
+
+    let description = parts.next().unwrap_or_default();
+    let (color, kind) = match description.split_once(' ') {
+        Some((color, kind)) if parse_color(color).is_some() => (Some(color), kind),
+        _ => (None, description),
+    };
+    NoteEntry {
+        page,
+        kind: Some(kind.to_string()).filter(|k| !k.is_empty()),
+        color: color.map(str::to_string),
+        author: parts.next().map(str::to_string),
+        ..Default::default()
+    }
+}
+
+/// Remove annotations of the given types (names like `highlight`, `note`,
+/// `popup`, or PDF subtypes), or all but links and form fields.
+pub fn strip<P: AsRef<Path>>(
+    path: P,
+    types: &[String],
+    output: &Path,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let subtypes = types
+        .iter()
+        .map(|name| {
+            parse_subtype(name).with_context(|| {
+                format!(
+                    "Unknown annotation type '{}' (e.g. highlight, underline, note, popup, \
+                     link, ink, freetext, widget)",
+                    name
+                )
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let doc = PdfDocument::open(&path)?;
+    let mut new_doc = doc.doc().clone();
+    let removed = strip_annotations(
+        &mut new_doc,
+        (!subtypes.is_empty()).then_some(subtypes.as_slice()),
+    );
+    // Removed annotations and their appearances would otherwise still be
+    // written out.
+    new_doc.prune_objects();
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+
+    status!(
+        "Removed {} annotation(s) into {}",
+        removed,
+        output.display()
+    );
+    if subtypes.is_empty() {
+        status!(
+            "Kept {} annotations; name them in --types to remove them too",
+            KEPT_BY_DEFAULT.join(" and ").to_lowercase()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotation(page: u32, subtype: &str) -> Annotation {
+        Annotation {
+            page,
+            id: None,
+            subtype: subtype.to_string(),
+            rect: [0.0; 4],
+            quads: Vec::new(),
+            color: None,
+            contents: None,
+            author: None,
+            modified: None,
+            in_reply_to: None,
+            text: None,
+        }
+    }
+
+    #[test]
+    fn test_notes_markdown() {
+        let highlight = Annotation {
+            id: Some((7, 0)),
+            color: Some([1.0, 1.0, 0.0]),
+            text: Some("ACME Corp".to_string()),
+            contents: Some("Check the name".to_string()),
+            author: Some("Jane".to_string()),
+            ..annotation(3, "Highlight")
+        };
+        let reply = Annotation {
+            in_reply_to: Some((7, 0)),
+            contents: Some("Agreed".to_string()),
+            author: Some("Bob".to_string()),
+            ..annotation(3, "Text")
+        };
+        let note = Annotation {
+            contents: Some("Follow up".to_string()),
+            ..annotation(5, "Text")
+        };
+        let annotations = [highlight, reply, note, annotation(5, "Link")];
+        let labels: Vec<String> = ["i", "ii", "1", "2", "3"].map(String::from).to_vec();
+
+        let (markdown, count) = notes_markdown("a.pdf", &annotations, &[], &labels);
+        assert_eq!(count, 2);
+        assert_eq!(
+            markdown,
+            "# Notes on a.pdf\n\
+             \n## Page 3\n\
+             \n> ACME Corp\n\
+             \nCheck the name\n\
+             \n**Bob:** Agreed\n\
+             \n*p. 1 (page 3) · yellow highlight · Jane*\n\
+             \n## Page 5\n\
+             \nFollow up\n\
+             \n*p. 3 (page 5) · note*\n"
+        );
+
+        let notes = parse_markdown_notes(&markdown);
+        assert_eq!(
+            notes,
+            [
+                NoteEntry {
+                    page: Some(3),
+                    kind: Some("highlight".to_string()),
+                    text: Some("ACME Corp".to_string()),
+                    comment: Some("Check the name\n\nBob: Agreed".to_string()),
+                    color: Some("yellow".to_string()),
+                    author: Some("Jane".to_string()),
+                },
+                NoteEntry {
+                    page: Some(5),
+                    kind: Some("note".to_string()),
+                    comment: Some("Follow up".to_string()),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(new_annotation(&notes[0]).unwrap().subtype, "Highlight");
+        assert_eq!(new_annotation(&notes[1]).unwrap().subtype, "Text");
+    }
+}
@@ -0,0 +1,119 @@
+use crate::pdf::cache::cache;
+use crate::pdf::text::grep_cached;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// An operation to benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BenchOp {
+    /// Copy every page into a new document and serialize it
+    Extract,
+    /// Extract all page text and search it
+    Grep,
+    /// Parse the outline
+    Toc,
+}
+
+pub struct BenchOptions {
+    pub ops: Vec<BenchOp>,
+    pub iterations: u32,
+    /// Pattern searched by the grep operation.
+    pub pattern: String,
+}
+
+/// Time each operation `iterations` times from a cold cache and report
+/// throughput and the process's peak memory.
+pub fn run(path: &Path, options: &BenchOptions) -> Result<()> {
+    let bytes = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+    let pages = PdfDocument::open(path)?.page_count();
+    let regex = Regex::new(&options.pattern)?;
+
+    println!(
+        "{}: {} pages, {:.1} MB, {} iteration(s)",
+        path.display(),
+        pages,
+        bytes as f64 / 1e6,
+        options.iterations
+    );
+    for &op in &options.ops {
+        let mut times = Vec::new();
+        for _ in 0..options.iterations {
+            // Every run parses the file again rather than hitting the cache.
+            cache().evict(path);
+            let started = Instant::now();
+            run_op(path, op, &regex)?;
+            times.push(started.elapsed());
+        }
+        cache().evict(path);
+        times.sort();
+
+        let median = times[times.len() / 2];
+        let seconds = median.as_secs_f64().max(1e-9);
+        println!(
+            "{:<8} median {} (min {}, max {}), {:.0} pages/s, {:.1} MB/s",
+            format!("{:?}", op).to_lowercase(),
+            millis(median),
+            millis(times[0]),
+            millis(times[times.len() - 1]),
+            pages as f64 / seconds,
+            bytes as f64 / 1e6 / seconds
+        );
+    }
+    match peak_memory() {
+        Some(peak) => println!("Peak memory: {:.1} MB", peak as f64 / 1e6),
+        None => println!("Peak memory: not available on this platform"),
+    }
+    Ok(())
+}
+
+fn run_op(path: &Path, op: BenchOp, regex: &Regex) -> Result<()> {
+    match op {
+        BenchOp::Extract => {
+            let doc = PdfDocument::open(path)?;
+            let pages: Vec<u32> = (1..=doc.page_count()).collect();
+            let mut new_doc = doc.extract_pages(&pages)?;
+            new_doc
+                .save_to(&mut std::io::sink())
+                .context("Failed to serialize document")?;
+        }
+        BenchOp::Grep => {
+            let doc = PdfDocument::open_for_text(path)?;
+            grep_cached(doc.cached(), regex, usize::MAX)?;
+        }
+        BenchOp::Toc => {
+            PdfDocument::open(path)?.toc()?;
+        }
+    }
+    Ok(())
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// The process's peak resident memory in bytes, where the OS reports it.
+pub fn peak_memory() -> Option<u64> {
+    // Linux: "VmHWM:    123456 kB" (the resident set's high-water mark).
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// Print how long a command took, and its peak memory, for `--timing`.
+pub fn report_timing(started: Instant) {
+    let elapsed = millis(started.elapsed());
+    match peak_memory() {
+        Some(peak) => eprintln!(
+            "Elapsed: {}, peak memory: {:.1} MB",
+            elapsed,
+            peak as f64 / 1e6
+        ),
+        None => eprintln!("Elapsed: {}", elapsed),
+    }
+}
@@ -0,0 +1,92 @@
+use crate::commands::status;
+use crate::page_range::expand_page_ranges;
+use crate::pdf::boxes::{apply_box_edit, page_boxes, parse_box_edits, BoxEdit, BoxSource, PageBox};
+use crate::pdf::document::SaveOptions;
+use crate::pdf::pages::Rect;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+/// Print the page boxes of `pages` (default: all), or with `edits`, change
+/// them and write the result to `output`.
+pub fn run(
+    path: &Path,
+    pages: Option<&str>,
+    edits: &[String],
+    output: Option<&Path>,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(path)?;
+    let page_list = expand_page_ranges(pages.unwrap_or("1-end"), doc.page_count())?;
+    let page_ids = doc.page_ids();
+
+    if edits.is_empty() {
+        if output.is_some() {
+            anyhow::bail!("Nothing to change: give the new boxes with --set");
+        }
+        for (i, &page) in page_list.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("Page {}:", page);
+            print_boxes(&page_boxes(doc.doc(), page_ids[page as usize - 1].1));
+        }
+        return Ok(());
+    }
+
+    let Some(output) = output else {
+        anyhow::bail!("--set needs an output path (-o) or --in-place");
+    };
+    let mut parsed: Vec<BoxEdit> = Vec::new();
+    for edit in edits {
+        parsed.extend(parse_box_edits(edit)?);
+    }
+
+    let mut new_doc = doc.doc().clone();
+    for &page in &page_list {
+        let page_id = page_ids[page as usize - 1].1;
+        for edit in &parsed {
+            apply_box_edit(&mut new_doc, page_id, edit)?;
+        }
+    }
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+
+    status!(
+        "Updated boxes on {} page(s) in {}",
+        page_list.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn print_boxes(boxes: &[(PageBox, Rect, BoxSource)]) {
+    let media = boxes[0].1;
+    for &(page_box, rect, source) in boxes {
+        let [llx, lly, urx, ury] = rect;
+        let mut notes = Vec::new();
+        match source {
+            BoxSource::Page => {}
+            BoxSource::Inherited => notes.push("inherited".to_string()),
+            BoxSource::Default(fallback) => notes.push(format!("defaults to {}", fallback.key())),
+            BoxSource::Missing => notes.push("missing; US Letter assumed".to_string()),
+        }
+        if llx < media[0] || lly < media[1] || urx > media[2] || ury > media[3] {
+            notes.push("extends past MediaBox".to_string());
+        }
+        println!(
+            "  {:<9} [{} {} {} {}]  {} x {} pt{}",
+            page_box.key(),
+            llx,
+            lly,
+            urx,
+            ury,
+            urx - llx,
+            ury - lly,
+            if notes.is_empty() {
+                String::new()
+            } else {
+                format!("  ({})", notes.join(", "))
+            }
+        );
+    }
+}
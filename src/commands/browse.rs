@@ -0,0 +1,390 @@
+use crate::pdf::text::{grep_cached, GrepMatch};
+use crate::pdf::toc::{flatten_toc, FlatTocEntry};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use regex::RegexBuilder;
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+/// Most search matches kept for `n`/`N` to step through.
+const MAX_MATCHES: usize = 1000;
+
+const HELP: &str = "q quit  Tab switch pane  ↑↓ move  [ ] page  / search  n/N next/prev match  \
+                    space mark page  e extract";
+
+/// Browse a PDF in the terminal: the outline on the left, the selected
+/// page's text on the right.
+pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let outline = flatten_toc(&doc.toc()?);
+    let mut browser = Browser::new(doc, outline, path);
+
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Outline,
+    Text,
+}
+
+/// What typed characters go to.
+enum Prompt {
+    None,
+    Search(String),
+    Extract(String),
+}
+
+struct Browser {
+    doc: PdfDocument,
+    outline: Vec<FlatTocEntry>,
+    outline_state: ListState,
+    focus: Focus,
+    page: u32,
+    scroll: u16,
+    marked: BTreeSet<u32>,
+    prompt: Prompt,
+    matches: Vec<GrepMatch>,
+    match_index: usize,
+    /// Suggested output file for `e`.
+    default_output: String,
+    status: String,
+}
+
+impl Browser {
+    fn new(doc: PdfDocument, outline: Vec<FlatTocEntry>, path: &Path) -> Self {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut outline_state = ListState::default();
+        if !outline.is_empty() {
+            outline_state.select(Some(0));
+        }
+        Browser {
+            doc,
+            outline,
+            outline_state,
+            focus: Focus::Outline,
+            page: 1,
+            scroll: 0,
+            marked: BTreeSet::new(),
+            prompt: Prompt::None,
+            matches: Vec::new(),
+            match_index: 0,
+            default_output: format!("{}-pages.pdf", stem),
+            status: HELP.to_string(),
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Returns false to quit.
+    fn handle_key(&mut self, key: KeyCode) -> bool {
+        match &mut self.prompt {
+            Prompt::Search(input) | Prompt::Extract(input) => {
+                match key {
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Enter => match std::mem::replace(&mut self.prompt, Prompt::None) {
+                        Prompt::Search(query) => self.search(&query),
+                        Prompt::Extract(output) => self.extract(&output),
+                        Prompt::None => unreachable!(),
+                    },
+                    KeyCode::Esc => {
+                        self.prompt = Prompt::None;
+                        self.status = HELP.to_string();
+                    }
+                    _ => {}
+                }
+                return true;
+            }
+            Prompt::None => {}
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Outline => Focus::Text,
+                    Focus::Text => Focus::Outline,
+                };
+            }
+            KeyCode::Down | KeyCode::Char('j') => match self.focus {
+                Focus::Outline => self.select_entry(1),
+                Focus::Text => self.scroll = self.scroll.saturating_add(1),
+            },
+            KeyCode::Up | KeyCode::Char('k') => match self.focus {
+                Focus::Outline => self.select_entry(-1),
+                Focus::Text => self.scroll = self.scroll.saturating_sub(1),
+            },
+            KeyCode::PageDown | KeyCode::Char(']') => self.go_to_page(self.page + 1),
+            KeyCode::PageUp | KeyCode::Char('[') => self.go_to_page(self.page.saturating_sub(1)),
+            KeyCode::Home | KeyCode::Char('g') => self.go_to_page(1),
+            KeyCode::End | KeyCode::Char('G') => self.go_to_page(self.doc.page_count()),
+            KeyCode::Char(' ') => {
+                if !self.marked.remove(&self.page) {
+                    self.marked.insert(self.page);
+                }
+                self.status = format!("{} page(s) marked", self.marked.len());
+            }
+            KeyCode::Char('/') => self.prompt = Prompt::Search(String::new()),
+            KeyCode::Char('n') => self.step_match(1),
+            KeyCode::Char('N') => self.step_match(-1),
+            KeyCode::Char('e') => self.prompt = Prompt::Extract(self.default_output.clone()),
+            _ => {}
+        }
+        true
+    }
+
+    fn select_entry(&mut self, delta: isize) {
+        if self.outline.is_empty() {
+            return;
+        }
+        let current = self.outline_state.selected().unwrap_or(0);
+        let next = current
+            .saturating_add_signed(delta)
+            .min(self.outline.len() - 1);
+        self.outline_state.select(Some(next));
+        if let Some(page) = self.outline[next].page {
+            self.page = page;
+            self.scroll = 0;
+        }
+    }
+
+    fn go_to_page(&mut self, page: u32) {
+        self.page = page.clamp(1, self.doc.page_count().max(1));
+        self.scroll = 0;
+        if let Some(index) = entry_for_page(&self.outline, self.page) {
+            self.outline_state.select(Some(index));
+        }
+    }
+
+    fn search(&mut self, query: &str) {
+        // Smart case: lowercase queries match either case.
+        let regex = RegexBuilder::new(query)
+            .case_insensitive(!query.chars().any(char::is_uppercase))
+            .build();
+        let found = match regex {
+            Ok(regex) => grep_cached(self.doc.cached(), &regex, MAX_MATCHES),
+            Err(e) => Err(e.into()),
+        };
+        match found {
+            Ok(matches) if matches.is_empty() => {
+                self.matches = matches;
+                self.status = format!("No matches for /{}", query);
+            }
+            Ok(matches) => {
+                // Start from the first match at or after the current page.
+                self.match_index = matches
+                    .iter()
+                    .position(|m| m.page >= self.page)
+                    .unwrap_or(0);
+                self.matches = matches;
+                self.show_match();
+            }
+            Err(e) => self.status = format!("Search failed: {:#}", e),
+        }
+    }
+
+    fn step_match(&mut self, delta: isize) {
+        if self.matches.is_empty() {
+            self.status = "No search results (press / to search)".to_string();
+            return;
+        }
+        let len = self.matches.len() as isize;
+        self.match_index = (self.match_index as isize + delta).rem_euclid(len) as usize;
+        self.show_match();
+    }
+
+    fn show_match(&mut self) {
+        let found = &self.matches[self.match_index];
+        let (page, line) = (found.page, found.line_number);
+        self.status = format!(
+            "Match {}/{}{}: page {}, line {}",
+            self.match_index + 1,
+            self.matches.len(),
+            if self.matches.len() == MAX_MATCHES {
+                "+"
+            } else {
+                ""
+            },
+            page,
+            line
+        );
+        self.go_to_page(page);
+        self.scroll = line.saturating_sub(1).try_into().unwrap_or(u16::MAX);
+    }
+
+    /// The pages `e` writes: the marked pages, or else the selected
+    /// outline entry's section, or else the current page.
+    fn pages_to_extract(&self) -> Vec<u32> {
+        if !self.marked.is_empty() {
+            return self.marked.iter().copied().collect();
+        }
+        match self.outline_state.selected() {
+            Some(index) if entry_for_page(&self.outline, self.page) == Some(index) => {
+                section_pages(&self.outline, index, self.doc.page_count()).collect()
+            }
+            _ => vec![self.page],
+        }
+    }
+
+    fn extract(&mut self, output: &str) {
+        let pages = self.pages_to_extract();
+        let written = self.doc.extract_pages(&pages).and_then(|mut new_doc| {
+            PdfDocument::save(&mut new_doc, output)
+                .with_context(|| format!("Failed to write {}", output))
+        });
+        self.status = match written {
+            Ok(()) => {
+                self.default_output = output.to_string();
+                format!("Wrote {} page(s) to {}", pages.len(), output)
+            }
+            Err(e) => format!("Extract failed: {:#}", e),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let border = |focus| {
+            if self.focus == focus {
+                Style::new().fg(Color::Yellow)
+            } else {
+                Style::new()
+            }
+        };
+
+        let items: Vec<ListItem> = if self.outline.is_empty() {
+            vec![ListItem::new("(no bookmarks)")]
+        } else {
+            self.outline
+                .iter()
+                .map(|entry| {
+                    let page = entry.page.map(|p| format!("  {}", p)).unwrap_or_default();
+                    ListItem::new(format!(
+                        "{}{}{}",
+                        "  ".repeat(entry.level as usize),
+                        entry.title,
+                        page
+                    ))
+                })
+                .collect()
+        };
+        let outline = List::new(items)
+            .block(
+                Block::bordered()
+                    .title(" Outline ")
+                    .border_style(border(Focus::Outline)),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(outline, left, &mut self.outline_state);
+
+        let text = match self.doc.cached().page_text(self.page) {
+            Ok(text) => text.to_string(),
+            Err(e) => format!("(could not extract text: {})", e),
+        };
+        let title = format!(
+            " Page {}/{}{} ",
+            self.page,
+            self.doc.page_count(),
+            if self.marked.contains(&self.page) {
+                " [marked]"
+            } else {
+                ""
+            }
+        );
+        let page = Paragraph::new(text)
+            .block(
+                Block::bordered()
+                    .title(title)
+                    .border_style(border(Focus::Text)),
+            )
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(page, right);
+
+        let line = match &self.prompt {
+            Prompt::Search(input) => format!("/{}", input),
+            Prompt::Extract(input) => format!(
+                "Extract {} page(s) to: {}",
+                self.pages_to_extract().len(),
+                input
+            ),
+            Prompt::None => self.status.clone(),
+        };
+        frame.render_widget(Paragraph::new(line), status);
+    }
+}
+
+/// The last outline entry starting at or before `page`, i.e. the section
+/// the page is in.
+fn entry_for_page(outline: &[FlatTocEntry], page: u32) -> Option<usize> {
+    outline
+        .iter()
+        .rposition(|e| e.page.is_some_and(|p| p <= page))
+}
+
+/// Pages of the section started by `outline[index]`: up to the page before
+/// the next entry at the same or a higher level.
+fn section_pages(outline: &[FlatTocEntry], index: usize, total: u32) -> RangeInclusive<u32> {
+    let entry = &outline[index];
+    let start = entry.page.unwrap_or(1);
+    let end = outline[index + 1..]
+        .iter()
+        .filter(|e| e.level <= entry.level)
+        .find_map(|e| e.page)
+        .map_or(total, |next| next.saturating_sub(1));
+    start..=end.max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, page: u32, level: u32) -> FlatTocEntry {
+        FlatTocEntry {
+            title: title.to_string(),
+            page: Some(page),
+            external: None,
+            level,
+        }
+    }
+
+    #[test]
+    fn test_section_pages() {
+        let outline = [
+            entry("1 Intro", 1, 0),
+            entry("1.1 Scope", 2, 1),
+            entry("1.2 Terms", 4, 1),
+            entry("2 Method", 7, 0),
+        ];
+        assert_eq!(section_pages(&outline, 0, 20), 1..=6);
+        assert_eq!(section_pages(&outline, 1, 20), 2..=3);
+        assert_eq!(section_pages(&outline, 2, 20), 4..=6);
+        assert_eq!(section_pages(&outline, 3, 20), 7..=20);
+        assert_eq!(entry_for_page(&outline, 5), Some(2));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::page_range::expand_page_ranges;
+use crate::pdf::classify::{classify_pages, PageKind};
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct ClassifyRecord<'a> {
+    path: &'a str,
+    page_count: u32,
+    /// Pages per label.
+    counts: BTreeMap<String, usize>,
+    pages: Vec<PageRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    page: u32,
+    label: &'a str,
+    /// `text`, `table`, `figure`, `scan`, or `blank`.
+    kind: String,
+    chars: usize,
+    invisible_chars: usize,
+    /// Visible characters per 100x100 pt.
+    text_density: f32,
+    /// Share of the page covered by images.
+    image_coverage: f32,
+    horizontal_rules: usize,
+    vertical_rules: usize,
+    paths: usize,
+}
+
+pub fn run<P: AsRef<Path>>(path: P, pages: Option<&str>) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let page_list = expand_page_ranges(pages.unwrap_or("1-end"), doc.page_count())?;
+    let labels = extract_page_labels_from_doc(doc.doc())?;
+    let classes = classify_pages(doc.doc(), &page_list);
+
+    let mut counts: BTreeMap<String, usize> = [
+        PageKind::Text,
+        PageKind::Table,
+        PageKind::Figure,
+        PageKind::Scan,
+        PageKind::Blank,
+    ]
+    .iter()
+    .map(|kind| (kind.to_string(), 0))
+    .collect();
+    let pages = classes
+        .iter()
+        .map(|class| {
+            *counts.entry(class.kind.to_string()).or_default() += 1;
+            let features = &class.features;
+            PageRecord {
+                page: class.page,
+                label: labels
+                    .get(class.page as usize - 1)
+                    .map(|l| l.logical_label.as_str())
+                    .unwrap_or_default(),
+                kind: class.kind.to_string(),
+                chars: features.chars,
+                invisible_chars: features.invisible_chars,
+                // Two decimals are plenty for a heuristic.
+                text_density: (features.text_density * 100.0).round() / 100.0,
+                image_coverage: (features.image_coverage * 100.0).round() / 100.0,
+                horizontal_rules: features.horizontal_rules,
+                vertical_rules: features.vertical_rules,
+                paths: features.paths,
+            }
+        })
+        .collect();
+
+    let json = serde_json::to_string_pretty(&ClassifyRecord {
+        path: &doc.path,
+        page_count: doc.page_count(),
+        counts,
+        pages,
+    })?;
+    println!("{}", json);
+    Ok(())
+}
@@ -0,0 +1,293 @@
+use crate::commands::push_csv_row;
+use crate::pdf::cache::cache;
+use crate::pdf::files::find_pdfs;
+use crate::pdf::quality::{text_quality, LOW_QUALITY};
+use crate::pdf::structure::struct_tree_root;
+use crate::pdf::text::extract_text_pages;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use lopdf::Object;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Report format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum StatsFormat {
+    /// Collection totals followed by one record per file
+    Json,
+    /// One row per file
+    Csv,
+}
+
+/// Average words per page below which a document with images counts as
+/// scanned (no text layer, or only stray running heads).
+const SCANNED_WORDS_PER_PAGE: u64 = 5;
+
+/// File size buckets, as (upper bound in bytes, label).
+const SIZE_BUCKETS: [(u64, &str); 5] = [
+    (100 << 10, "<100 KB"),
+    (1 << 20, "100 KB-1 MB"),
+    (10 << 20, "1-10 MB"),
+    (100 << 20, "10-100 MB"),
+    (u64::MAX, ">=100 MB"),
+];
+
+#[derive(Debug, Serialize)]
+struct FileStats {
+    path: String,
+    bytes: u64,
+    pages: u32,
+    words: u64,
+    encrypted: bool,
+    scanned: bool,
+    tagged: bool,
+    producer: Option<String>,
+    /// Mean text quality score (0-1) of pages with text; `None` if none
+    /// have any.
+    text_quality: Option<f32>,
+    /// Pages whose text scores below `quality::LOW_QUALITY`, which likely
+    /// need OCR or another extraction backend.
+    low_quality_pages: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct FailedFile {
+    path: String,
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SizeStats {
+    total_bytes: u64,
+    min_bytes: u64,
+    median_bytes: u64,
+    max_bytes: u64,
+    buckets: Vec<SizeBucket>,
+}
+
+#[derive(Debug, Serialize)]
+struct SizeBucket {
+    label: &'static str,
+    files: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ProducerCount {
+    producer: String,
+    files: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct CollectionReport {
+    files: usize,
+    pages: u64,
+    words: u64,
+    encrypted: usize,
+    scanned: usize,
+    tagged: usize,
+    /// Most common first.
+    producers: Vec<ProducerCount>,
+    sizes: SizeStats,
+    failed: Vec<FailedFile>,
+    per_file: Vec<FileStats>,
+}
+
+/// Summarize every PDF under `dir`: page and word counts, how many are
+/// encrypted, scanned, or tagged, which software produced them, and how
+/// large they are.
+///
+/// Files that cannot be read are listed in the report rather than stopping
+/// the run.
+pub fn run<P: AsRef<Path>>(dir: P, format: StatsFormat, output: Option<&Path>) -> Result<()> {
+    let mut files = Vec::new();
+    let mut failed = Vec::new();
+    for path in find_pdfs(dir) {
+        match file_stats(&path) {
+            Ok(stats) => files.push(stats),
+            Err(err) => {
+                eprintln!("Skipping {}: {:#}", path.display(), err);
+                failed.push(FailedFile {
+                    path: path.display().to_string(),
+                    error: format!("{:#}", err),
+                });
+            }
+        }
+        // Each file is visited once; don't keep them all parsed.
+        cache().evict(&path);
+    }
+
+    let report = match format {
+        StatsFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&summarize(files, failed))?;
+            json.push('\n');
+            json
+        }
+        StatsFormat::Csv => to_csv(&files),
+    };
+
+    if let Some(output) = output {
+        std::fs::write(output, report)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        println!("Wrote collection report to {}", output.display());
+    } else {
+        print!("{}", report);
+    }
+
+    Ok(())
+}
+
+fn file_stats(path: &Path) -> Result<FileStats> {
+    let bytes = std::fs::metadata(path)
+        .with_context(|| format!("stat {}", path.display()))?
+        .len();
+    let doc = PdfDocument::open_for_text(path)?;
+    let pdf = doc.doc();
+
+    let pages: Vec<u32> = (1..=doc.page_count()).collect();
+    let texts = extract_text_pages(&doc, &pages)?;
+    let words: u64 = texts
+        .iter()
+        .map(|page| page.text.split_whitespace().count() as u64)
+        .sum();
+    let scores: Vec<f32> = texts
+        .iter()
+        .filter_map(|page| text_quality(&page.text).score)
+        .collect();
+    let text_quality = (!scores.is_empty())
+        .then(|| (scores.iter().sum::<f32>() / scores.len() as f32 * 100.0).round() / 100.0);
+    let low_quality_pages = scores.iter().filter(|&&s| s < LOW_QUALITY).count() as u32;
+    // Image data may have been dropped for text-only loading, but the
+    // image dictionaries are still there.
+    let has_images = pdf.objects.values().any(|obj| match obj {
+        Object::Stream(stream) => stream
+            .dict
+            .get(b"Subtype")
+            .is_ok_and(|subtype| subtype.as_name().is_ok_and(|n| n == b"Image")),
+        _ => false,
+    });
+    let page_count = u64::from(doc.page_count().max(1));
+    let scanned = has_images && words < SCANNED_WORDS_PER_PAGE * page_count;
+
+    Ok(FileStats {
+        path: path.display().to_string(),
+        bytes,
+        pages: doc.page_count(),
+        words,
+        encrypted: pdf.trailer.has(b"Encrypt"),
+        scanned,
+        tagged: struct_tree_root(pdf).is_some(),
+        producer: doc
+            .get_info()
+            .producer
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty()),
+        text_quality,
+        low_quality_pages,
+    })
+}
+
+fn summarize(files: Vec<FileStats>, failed: Vec<FailedFile>) -> CollectionReport {
+    let mut producers: HashMap<String, usize> = HashMap::new();
+    for file in &files {
+        let producer = file.producer.as_deref().unwrap_or("(none)");
+        *producers.entry(producer.to_string()).or_default() += 1;
+    }
+    let mut producers: Vec<ProducerCount> = producers
+        .into_iter()
+        .map(|(producer, files)| ProducerCount { producer, files })
+        .collect();
+    producers.sort_by(|a, b| {
+        b.files
+            .cmp(&a.files)
+            .then_with(|| a.producer.cmp(&b.producer))
+    });
+
+    CollectionReport {
+        files: files.len(),
+        pages: files.iter().map(|f| u64::from(f.pages)).sum(),
+        words: files.iter().map(|f| f.words).sum(),
+        encrypted: files.iter().filter(|f| f.encrypted).count(),
+        scanned: files.iter().filter(|f| f.scanned).count(),
+        tagged: files.iter().filter(|f| f.tagged).count(),
+        producers,
+        sizes: size_stats(&files.iter().map(|f| f.bytes).collect::<Vec<_>>()),
+        failed,
+        per_file: files,
+    }
+}
+
+fn size_stats(sizes: &[u64]) -> SizeStats {
+    let mut sorted = sizes.to_vec();
+    sorted.sort_unstable();
+    let buckets = SIZE_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, &(upper, label))| {
+            let lower = i.checked_sub(1).map_or(0, |prev| SIZE_BUCKETS[prev].0);
+            SizeBucket {
+                label,
+                files: sorted.iter().filter(|&&s| s >= lower && s < upper).count(),
+            }
+        })
+        .collect();
+    SizeStats {
+        total_bytes: sorted.iter().sum(),
+        min_bytes: sorted.first().copied().unwrap_or(0),
+        median_bytes: sorted.get(sorted.len() / 2).copied().unwrap_or(0),
+        max_bytes: sorted.last().copied().unwrap_or(0),
+        buckets,
+    }
+}
+
+fn to_csv(files: &[FileStats]) -> String {
+    let mut csv = String::new();
+    push_csv_row(
+        &mut csv,
+        &[
+            "file",
+            "bytes",
+            "pages",
+            "words",
+            "encrypted",
+            "scanned",
+            "tagged",
+            "producer",
+            "text_quality",
+            "low_quality_pages",
+        ],
+    );
+    for file in files {
+        push_csv_row(
+            &mut csv,
+            &[
+                &file.path,
+                &file.bytes.to_string(),
+                &file.pages.to_string(),
+                &file.words.to_string(),
+                &file.encrypted.to_string(),
+                &file.scanned.to_string(),
+                &file.tagged.to_string(),
+                file.producer.as_deref().unwrap_or_default(),
+                &file.text_quality.map(|q| q.to_string()).unwrap_or_default(),
+                &file.low_quality_pages.to_string(),
+            ],
+        );
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_stats_buckets() {
+        let stats = size_stats(&[5 << 20, 10, 200 << 10, 300 << 20, 1 << 20]);
+        assert_eq!(stats.min_bytes, 10);
+        assert_eq!(stats.median_bytes, 1 << 20);
+        assert_eq!(stats.max_bytes, 300 << 20);
+        let counts: Vec<usize> = stats.buckets.iter().map(|b| b.files).collect();
+        assert_eq!(counts, vec![1, 1, 2, 0, 1]);
+    }
+}
@@ -0,0 +1,44 @@
+use crate::pdf::colors::page_colors;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let pages = page_colors(doc.doc());
+
+    println!("{:>6}  {:<40}  Spot colors", "Page", "Color spaces");
+    let mut spots: BTreeMap<&str, Vec<u32>> = BTreeMap::new();
+    for page in &pages {
+        let spaces: Vec<String> = page.spaces.iter().map(|s| s.to_string()).collect();
+        let spaces = if spaces.is_empty() {
+            "-".to_string()
+        } else {
+            spaces.join(", ")
+        };
+        let spot_colors: Vec<&str> = page.spot_colors.iter().map(String::as_str).collect();
+        println!(
+            "{:>6}  {:<40}  {}",
+            page.page,
+            spaces,
+            spot_colors.join(", ")
+        );
+        for name in spot_colors {
+            spots.entry(name).or_default().push(page.page);
+        }
+    }
+
+    println!();
+    if spots.is_empty() {
+        println!("No spot colors.");
+    } else {
+        println!("Spot colors ({}):", spots.len());
+        for (name, pages) in spots {
+            let pages: Vec<String> = pages.iter().map(|p| p.to_string()).collect();
+            println!("  {} (pages {})", name, pages.join(", "));
+        }
+    }
+
+    Ok(())
+}
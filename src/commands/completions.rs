@@ -0,0 +1,64 @@
+use crate::cli::Cli;
+use crate::commands::status;
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::path::Path;
+
+/// Print a completion script for `shell`, or write it into `output_dir`
+/// under the name that shell expects (`inpdf.bash`, `_inpdf`, ...).
+pub fn completions(shell: Shell, output_dir: Option<&Path>) -> Result<()> {
+    let mut command = Cli::command();
+    match output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            let path = clap_complete::generate_to(shell, &mut command, "inpdf", dir)
+                .with_context(|| format!("Failed to write completions to {}", dir.display()))?;
+            status!("Wrote {}", path.display());
+        }
+        None => clap_complete::generate(shell, &mut command, "inpdf", &mut std::io::stdout()),
+    }
+    Ok(())
+}
+
+/// Print the man page for `inpdf`, or write it and one page per subcommand
+/// (`inpdf-extract.1`, ...) into `output_dir`.
+pub fn man(output_dir: Option<&Path>) -> Result<()> {
+    let command = Cli::command();
+    match output_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            clap_mangen::generate_to(command, dir)
+                .with_context(|| format!("Failed to write man pages to {}", dir.display()))?;
+            status!("Wrote man pages to {}", dir.display());
+        }
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout())?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_from_cli_definitions() {
+        Cli::command().debug_assert();
+
+        let mut command = Cli::command();
+
+        let mut script = Vec::new();
+        clap_complete::generate(Shell::Bash, &mut command, "inpdf", &mut script);
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("read-pages"));
+
+        let mut page = Vec::new();
+        clap_mangen::Man::new(Cli::command())
+            .render(&mut page)
+            .unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(page.contains("inpdf"));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::commands::status;
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::import::Importer;
+use crate::pdf::layout::{PageSize, StandardFont};
+use crate::pdf::pages::{inherited_attribute, Rect};
+use crate::pdf::xobject::page_to_form;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use lopdf::{dictionary, Dictionary, Object};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Space around the grid and between cells, in points.
+const MARGIN: f32 = 36.0;
+const GAP: f32 = 12.0;
+
+/// Height reserved under each thumbnail for its page number.
+const LABEL_HEIGHT: f32 = 12.0;
+const LABEL_SIZE: f32 = 8.0;
+
+pub struct ContactSheetOptions {
+    pub cols: u32,
+    pub rows: u32,
+    pub page_size: PageSize,
+}
+
+/// Draw every page of `input` as a thumbnail in a grid, `cols` x `rows` per
+/// sheet.
+///
+/// Thumbnails are the pages themselves, scaled down as Form XObjects, so
+/// they stay sharp when zoomed and share fonts and images with each other.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    options: &ContactSheetOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(&input)?;
+    let page_ids = doc.page_ids();
+    if page_ids.is_empty() {
+        anyhow::bail!("{} has no pages", input.as_ref().display());
+    }
+    let cells = grid_cells(options);
+    if cells.is_empty() {
+        anyhow::bail!(
+            "{} x {} thumbnails don't fit on the page",
+            options.cols,
+            options.rows
+        );
+    }
+
+    let mut builder = DocumentBuilder::new();
+    let font_id = builder.doc_mut().add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => StandardFont::Helvetica.base_font(),
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let mut importer = Importer::new(doc.doc());
+
+    for sheet in page_ids.chunks(cells.len()) {
+        let mut xobjects = Dictionary::new();
+        let mut content = String::new();
+        for (i, (&(page_number, page_id), &cell)) in sheet.iter().zip(&cells).enumerate() {
+            let (form_id, bbox) = page_to_form(&mut importer, builder.doc_mut(), page_id)?;
+            let rotate = match inherited_attribute(doc.doc(), page_id, b"Rotate") {
+                Some(Object::Integer(rotate)) => rotate,
+                _ => 0,
+            };
+            let (matrix, placed) = fit(bbox, rotate, cell);
+            let name = format!("P{}", i);
+            xobjects.set(name.as_bytes().to_vec(), form_id);
+
+            let [x1, y1, x2, y2] = placed;
+            let _ = writeln!(
+                content,
+                "q {} {} {} {} re W n {} {} {} {} {} {} cm /{} Do Q",
+                x1,
+                y1,
+                x2 - x1,
+                y2 - y1,
+                matrix[0],
+                matrix[1],
+                matrix[2],
+                matrix[3],
+                matrix[4],
+                matrix[5],
+                name
+            );
+            let _ = writeln!(
+                content,
+                "q 0.5 w 0.6 G {} {} {} {} re S Q",
+                x1,
+                y1,
+                x2 - x1,
+                y2 - y1
+            );
+            let label = page_number.to_string();
+            let width = StandardFont::Helvetica.text_width(label.as_bytes(), LABEL_SIZE);
+            let _ = writeln!(
+                content,
+                "BT /F1 {} Tf {} {} Td ({}) Tj ET",
+                LABEL_SIZE,
+                (cell[0] + cell[2] - width) / 2.0,
+                cell[1] - LABEL_HEIGHT + 3.0,
+                label
+            );
+        }
+
+        let resources = dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+            "XObject" => xobjects,
+        };
+        let size = options.page_size;
+        builder.add_page(
+            [0.0, 0.0, size.width, size.height],
+            resources,
+            content.into_bytes(),
+        );
+    }
+
+    let sheets = builder.page_count();
+    let mut new_doc = builder.finish()?;
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Wrote {} page(s) as thumbnails on {} sheet(s) to {}",
+        page_ids.len(),
+        sheets,
+        output.as_ref().display()
+    );
+    Ok(())
+}
+
+/// The thumbnail area of each cell, left to right and top to bottom, with
+/// room for a label below each. Empty if the cells would have no area.
+fn grid_cells(options: &ContactSheetOptions) -> Vec<Rect> {
+    let PageSize { width, height } = options.page_size;
+    let (cols, rows) = (options.cols as f32, options.rows as f32);
+    let cell_width = (width - 2.0 * MARGIN - (cols - 1.0) * GAP) / cols;
+    let cell_height = (height - 2.0 * MARGIN - (rows - 1.0) * GAP) / rows - LABEL_HEIGHT;
+    if cell_width <= 0.0 || cell_height <= 0.0 {
+        return Vec::new();
+    }
+
+    let mut cells = Vec::new();
+    for row in 0..options.rows {
+        let top = height - MARGIN - row as f32 * (cell_height + LABEL_HEIGHT + GAP);
+        for col in 0..options.cols {
+            let left = MARGIN + col as f32 * (cell_width + GAP);
+            cells.push([left, top - cell_height, left + cell_width, top]);
+        }
+    }
+    cells
+}
+
+/// The matrix that draws a page with bounding box `bbox` and `/Rotate`
+/// `rotate` as large as fits in `cell`, centered, and the rectangle it
+/// covers.
+fn fit(bbox: Rect, rotate: i64, cell: Rect) -> ([f32; 6], Rect) {
+    let [llx, lly, urx, ury] = bbox;
+    let (w, h) = (urx - llx, ury - lly);
+    let rotate = rotate.rem_euclid(360);
+    let (shown_w, shown_h) = if rotate == 90 || rotate == 270 {
+        (h, w)
+    } else {
+        (w, h)
+    };
+
+    let (cell_w, cell_h) = (cell[2] - cell[0], cell[3] - cell[1]);
+    let s = (cell_w / shown_w).min(cell_h / shown_h);
+    let ox = cell[0] + (cell_w - shown_w * s) / 2.0;
+    let oy = cell[1] + (cell_h - shown_h * s) / 2.0;
+
+    // Move the box's corner to the origin, turn it clockwise as a viewer
+    // would, then scale and place it.
+    let matrix = match rotate {
+        90 => [0.0, -s, s, 0.0, ox - s * lly, oy + s * (w + llx)],
+        180 => [-s, 0.0, 0.0, -s, ox + s * (w + llx), oy + s * (h + lly)],
+        270 => [0.0, s, -s, 0.0, ox + s * (h + lly), oy - s * llx],
+        _ => [s, 0.0, 0.0, s, ox - s * llx, oy - s * lly],
+    };
+    (matrix, [ox, oy, ox + shown_w * s, oy + shown_h * s])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(m: [f32; 6], (x, y): (f32, f32)) -> (f32, f32) {
+        (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+    }
+
+    #[test]
+    fn test_grid_cells() {
+        let options = ContactSheetOptions {
+            cols: 4,
+            rows: 5,
+            page_size: PageSize::LETTER,
+        };
+        let cells = grid_cells(&options);
+        assert_eq!(cells.len(), 20);
+        // Reading order: the second cell is to the right of the first.
+        assert_eq!(cells[0][1], cells[1][1]);
+        assert!(cells[1][0] > cells[0][2]);
+        assert!(cells[4][3] < cells[0][1]);
+        assert_eq!(cells[0][0], MARGIN);
+        assert!((cells[3][2] - (612.0 - MARGIN)).abs() < 0.01);
+
+        let options = ContactSheetOptions {
+            cols: 100,
+            ..options
+        };
+        assert!(grid_cells(&options).is_empty());
+    }
+
+    #[test]
+    fn test_fit_rotated_page() {
+        // A portrait page turned 90 degrees fills a landscape cell.
+        let (matrix, placed) = fit([0.0, 0.0, 100.0, 200.0], 90, [0.0, 0.0, 20.0, 10.0]);
+        assert_eq!(placed, [0.0, 0.0, 20.0, 10.0]);
+        // The page's lower-left corner ends up top-left, as viewers show it.
+        assert_eq!(apply(matrix, (0.0, 0.0)), (0.0, 10.0));
+        assert_eq!(apply(matrix, (100.0, 200.0)), (20.0, 0.0));
+
+        let (matrix, placed) = fit([10.0, 10.0, 110.0, 210.0], 0, [0.0, 0.0, 20.0, 20.0]);
+        assert_eq!(placed, [5.0, 0.0, 15.0, 20.0]);
+        assert_eq!(apply(matrix, (10.0, 10.0)), (5.0, 0.0));
+    }
+}
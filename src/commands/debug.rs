@@ -0,0 +1,74 @@
+use crate::pdf::inspect::{
+    document_stats, format_object, format_operations, page_content, stream_bytes, XrefKind,
+};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use lopdf::Object;
+use std::path::Path;
+
+/// Print a raw object, optionally followed by its decoded stream data.
+pub fn object<P: AsRef<Path>>(path: P, id: u32, generation: u16, show_stream: bool) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let obj = doc
+        .doc()
+        .get_object((id, generation))
+        .with_context(|| format!("Object {} {} not found", id, generation))?;
+
+    println!("{} {} obj", id, generation);
+    println!("{}", format_object(obj));
+
+    if show_stream {
+        if let Object::Stream(stream) = obj {
+            let bytes = stream_bytes(stream)?;
+            println!("--- stream data ({} bytes decoded) ---", bytes.len());
+            println!("{}", String::from_utf8_lossy(&bytes));
+        }
+    }
+
+    Ok(())
+}
+
+/// Print a page's decoded content stream, one operator per line.
+pub fn content<P: AsRef<Path>>(path: P, page: u32) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let content = page_content(doc.doc(), page)?;
+    print!("{}", format_operations(&content.operations));
+    Ok(())
+}
+
+/// Print structural statistics useful for triaging odd files.
+pub fn stats<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let raw = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let stats = document_stats(doc.doc(), &raw);
+
+    println!("File: {}", path.display());
+    println!("Version: {}", stats.version);
+    let xref = match stats.xref_kind {
+        Some(XrefKind::Table) => "table",
+        Some(XrefKind::Stream) => "stream",
+        None => "unknown",
+    };
+    println!("Xref: {}", xref);
+    println!("Incremental updates: {}", stats.incremental_updates);
+    if stats.trailer_ids.is_empty() {
+        println!("Trailer ID: (none)");
+    } else {
+        println!("Trailer ID: [{}]", stats.trailer_ids.join(", "));
+    }
+
+    println!("\nObjects: {}", stats.object_count);
+    let mut by_type: Vec<_> = stats.objects_by_type.iter().collect();
+    by_type.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (type_name, count) in by_type {
+        println!("  {:>8}  {}", count, type_name);
+    }
+
+    println!("\nStream filters:");
+    for (filter, count) in &stats.filters {
+        println!("  {:>8}  {}", count, filter);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use crate::commands::{compact_pages, status};
+use crate::pdf::document::SaveOptions;
+use crate::pdf::orientation::{page_orientation, Orientation};
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+/// Report pages whose text runs sideways or upside down, and with `output`,
+/// write a copy with their `/Rotate` corrected.
+pub fn run(path: &Path, output: Option<&Path>, save_options: &SaveOptions) -> Result<()> {
+    let doc = PdfDocument::open(path)?;
+    let mut new_doc = doc.doc().clone();
+
+    let mut corrected = Vec::new();
+    let mut no_text = Vec::new();
+    for (page, page_id) in doc.page_ids() {
+        match page_orientation(doc.doc(), page_id)? {
+            Orientation::Upright => {}
+            Orientation::Rotate(rotate) => {
+                println!("Page {}: text reads upright with /Rotate {}", page, rotate);
+                new_doc.get_dictionary_mut(page_id)?.set("Rotate", rotate);
+                corrected.push(page);
+            }
+            Orientation::Unsure { chars: 0, .. } => no_text.push(page),
+            Orientation::Unsure { chars, share } => println!(
+                "Page {}: unsure ({} characters, {:.0}% in the main direction)",
+                page,
+                chars,
+                share * 100.0
+            ),
+        }
+    }
+    if !no_text.is_empty() {
+        println!(
+            "{} page(s) have no text layer to measure (run OCR first): {}",
+            no_text.len(),
+            compact_pages(&no_text)
+        );
+    }
+
+    match output {
+        Some(output) if !corrected.is_empty() => {
+            PdfDocument::save_with(&mut new_doc, output, save_options)?;
+            status!(
+                "Corrected {} page(s) in {}",
+                corrected.len(),
+                output.display()
+            );
+        }
+        Some(_) => status!("All pages with text already read upright; nothing written"),
+        None if corrected.is_empty() => println!("All pages with text already read upright"),
+        None => println!(
+            "{} page(s) to correct; write them with -o or --in-place",
+            corrected.len()
+        ),
+    }
+    Ok(())
+}
@@ -0,0 +1,578 @@
+use crate::commands::{color, status};
+use crate::pdf::render::{render_page, Raster};
+use crate::pdf::text::extract_text_pages;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use similar::{Algorithm, ChangeTag, TextDiff};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+pub struct DiffOptions {
+    /// Show the documents in two columns instead of a unified listing.
+    pub side_by_side: bool,
+    /// Also write a side-by-side HTML report here.
+    pub report: Option<PathBuf>,
+    /// Unchanged lines shown around each change.
+    pub context: usize,
+}
+
+pub struct VisualOptions {
+    /// Fraction of differing pixels above which a page counts as changed.
+    pub threshold: f64,
+    /// Where to write highlighted difference images.
+    pub output_dir: Option<PathBuf>,
+    pub dpi: u32,
+}
+
+/// Per-channel difference up to which pixels still count as equal, so
+/// anti-aliasing noise doesn't register as a change.
+const PIXEL_TOLERANCE: u8 = 48;
+
+/// A non-blank line of extracted text and the page it is on.
+#[derive(Debug, Clone, PartialEq)]
+struct Line {
+    page: u32,
+    text: String,
+}
+
+/// One row of the comparison. A changed row pairs a removed line with the
+/// line that replaced it, when there is one.
+#[derive(Debug, PartialEq)]
+enum Row {
+    Same(Line, Line),
+    Changed(Option<Line>, Option<Line>),
+}
+
+impl Row {
+    fn pages(&self) -> (Option<u32>, Option<u32>) {
+        match self {
+            Row::Same(old, new) => (Some(old.page), Some(new.page)),
+            Row::Changed(old, new) => (old.as_ref().map(|l| l.page), new.as_ref().map(|l| l.page)),
+        }
+    }
+}
+
+/// What to print: a row, or a run of unchanged rows left out.
+enum Shown<'a> {
+    Row(&'a Row),
+    Skipped(usize),
+}
+
+/// Compare the text of two PDFs line by line, marking where each side's
+/// pages start so changes can be found in both documents.
+pub fn run(old_path: &Path, new_path: &Path, options: &DiffOptions) -> Result<()> {
+    let old = document_lines(old_path)?;
+    let new = document_lines(new_path)?;
+    let rows = diff_rows(&old, &new);
+    let shown = with_context(&rows, options.context);
+
+    let (removed, added) = rows.iter().fold((0, 0), |(removed, added), row| match row {
+        Row::Changed(old, new) => (
+            removed + usize::from(old.is_some()),
+            added + usize::from(new.is_some()),
+        ),
+        Row::Same(..) => (removed, added),
+    });
+    let summary = format!("{} line(s) removed, {} line(s) added", removed, added);
+
+    if removed + added == 0 {
+        println!("No text differences");
+    } else {
+        let color = color();
+        let (old_name, new_name) = (old_path.display(), new_path.display());
+        if options.side_by_side {
+            print!("{}", side_by_side(&shown, terminal_width(), color));
+        } else {
+            println!("--- {}\n+++ {}", old_name, new_name);
+            print!("{}", unified(&shown, color));
+        }
+        println!("{}", summary);
+    }
+
+    if let Some(report) = &options.report {
+        let html = html_report(
+            &old_path.display().to_string(),
+            &new_path.display().to_string(),
+            &shown,
+            &summary,
+        );
+        std::fs::write(report, html)
+            .with_context(|| format!("Failed to write {}", report.display()))?;
+        status!("Wrote report to {}", report.display());
+    }
+    Ok(())
+}
+
+/// Compare two PDFs as rendered: rasterize corresponding pages, report
+/// those whose share of differing pixels exceeds the threshold, and write
+/// an image of each with the differences highlighted.
+pub fn run_visual(old_path: &Path, new_path: &Path, options: &VisualOptions) -> Result<()> {
+    let old_pages = PdfDocument::open_for_text(old_path)?.page_count();
+    let new_pages = PdfDocument::open_for_text(new_path)?.page_count();
+    let total = old_pages.max(new_pages);
+    if let Some(dir) = &options.output_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    let mut changed = 0;
+    for page in 1..=total {
+        if page > old_pages || page > new_pages {
+            let side = if page > old_pages { "new" } else { "old" };
+            println!("page {}: only in the {} document", page, side);
+            changed += 1;
+            continue;
+        }
+        let old = render_page(old_path, page, options.dpi)?;
+        let new = render_page(new_path, page, options.dpi)?;
+        let (ratio, highlighted) = compare_rasters(&old, &new);
+        if ratio <= options.threshold {
+            continue;
+        }
+        changed += 1;
+        match &options.output_dir {
+            Some(dir) => {
+                let file = dir.join(format!("page-{:04}.png", page));
+                std::fs::write(&file, highlighted.to_png()?)
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+                println!(
+                    "page {}: {:.2}% different -> {}",
+                    page,
+                    ratio * 100.0,
+                    file.display()
+                );
+            }
+            None => println!("page {}: {:.2}% different", page, ratio * 100.0),
+        }
+    }
+    println!(
+        "{} of {} page(s) differ by more than {}%",
+        changed,
+        total,
+        options.threshold * 100.0
+    );
+    Ok(())
+}
+
+/// The fraction of pixels that differ, and the new page with those pixels
+/// painted red over a faded copy. Pages of different sizes are compared
+/// over the larger area, where pixels missing on one side differ.
+fn compare_rasters(old: &Raster, new: &Raster) -> (f64, Raster) {
+    let (width, height) = (old.width.max(new.width), old.height.max(new.height));
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 3);
+    let mut differing = 0u64;
+    for y in 0..height {
+        for x in 0..width {
+            match (old.get(x, y), new.get(x, y)) {
+                (Some(a), Some(b))
+                    if a.iter()
+                        .zip(b)
+                        .all(|(&a, b)| a.abs_diff(b) <= PIXEL_TOLERANCE) =>
+                {
+                    // Fade toward white so the red stands out.
+                    pixels.extend(b.map(|c| 255 - (255 - c) / 4));
+                }
+                _ => {
+                    differing += 1;
+                    pixels.extend([255, 0, 0]);
+                }
+            }
+        }
+    }
+    let area = u64::from(width) * u64::from(height);
+    let ratio = if area == 0 {
+        0.0
+    } else {
+        differing as f64 / area as f64
+    };
+    (
+        ratio,
+        Raster {
+            width,
+            height,
+            pixels,
+        },
+    )
+}
+
+fn document_lines(path: &Path) -> Result<Vec<Line>> {
+    let doc = PdfDocument::open_for_text(path)?;
+    let pages: Vec<u32> = (1..=doc.page_count()).collect();
+    let mut lines = Vec::new();
+    for page in extract_text_pages(&doc, &pages)? {
+        if let Some(error) = &page.error {
+            eprintln!("Warning: {}: {}", path.display(), error);
+        }
+        // Extraction leaves plenty of blank lines whose count varies with
+        // layout; they would only add noise.
+        lines.extend(
+            page.text
+                .lines()
+                .map(str::trim_end)
+                .filter(|text| !text.trim().is_empty())
+                .map(|text| Line {
+                    page: page.page,
+                    text: text.to_string(),
+                }),
+        );
+    }
+    Ok(lines)
+}
+
+fn diff_rows(old: &[Line], new: &[Line]) -> Vec<Row> {
+    let old_text: Vec<&str> = old.iter().map(|l| l.text.as_str()).collect();
+    let new_text: Vec<&str> = new.iter().map(|l| l.text.as_str()).collect();
+    let diff = TextDiff::configure()
+        .algorithm(Algorithm::Patience)
+        .diff_slices(&old_text, &new_text);
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for change in diff.iter_all_changes() {
+        let old_line = || old[change.old_index().expect("old side has an index")].clone();
+        let new_line = || new[change.new_index().expect("new side has an index")].clone();
+        match change.tag() {
+            ChangeTag::Equal => {
+                pair_changes(&mut rows, &mut removed, &mut added);
+                rows.push(Row::Same(old_line(), new_line()));
+            }
+            ChangeTag::Delete => removed.push(old_line()),
+            ChangeTag::Insert => added.push(new_line()),
+        }
+    }
+    pair_changes(&mut rows, &mut removed, &mut added);
+    rows
+}
+
+/// Turn a run of removed and added lines into rows, side by side.
+fn pair_changes(rows: &mut Vec<Row>, removed: &mut Vec<Line>, added: &mut Vec<Line>) {
+    let mut removed = removed.drain(..);
+    let mut added = added.drain(..);
+    loop {
+        match (removed.next(), added.next()) {
+            (None, None) => break,
+            (old, new) => rows.push(Row::Changed(old, new)),
+        }
+    }
+}
+
+fn with_context(rows: &[Row], context: usize) -> Vec<Shown<'_>> {
+    let mut keep = vec![false; rows.len()];
+    for (i, row) in rows.iter().enumerate() {
+        if matches!(row, Row::Changed(..)) {
+            let end = (i + context).min(rows.len() - 1);
+            keep[i.saturating_sub(context)..=end].fill(true);
+        }
+    }
+
+    let mut shown = Vec::new();
+    let mut skipped = 0;
+    for (row, keep) in rows.iter().zip(keep) {
+        if !keep {
+            skipped += 1;
+            continue;
+        }
+        if skipped > 0 {
+            shown.push(Shown::Skipped(skipped));
+            skipped = 0;
+        }
+        shown.push(Shown::Row(row));
+    }
+    if skipped > 0 {
+        shown.push(Shown::Skipped(skipped));
+    }
+    shown
+}
+
+/// Tracks the page each side is on, to mark where pages start.
+#[derive(Default)]
+struct PageTracker {
+    current: (u32, u32),
+    /// Mark the next row's pages even if unchanged (after skipped lines).
+    pending: bool,
+}
+
+impl PageTracker {
+    /// The pages to mark before `row`, if either side moved to a new page
+    /// (sides without a line stay on their previous page) or lines were
+    /// skipped.
+    fn next(&mut self, row: &Row) -> Option<(String, String)> {
+        let (old, new) = row.pages();
+        let next = (old.unwrap_or(self.current.0), new.unwrap_or(self.current.1));
+        let mark = next != self.current || std::mem::take(&mut self.pending);
+        self.current = next;
+        // Page 0: nothing on that side yet.
+        let name = |page: u32| match page {
+            0 => "-".to_string(),
+            page => page.to_string(),
+        };
+        mark.then(|| (name(next.0), name(next.1)))
+    }
+
+    fn skipped(&mut self) {
+        self.pending = true;
+    }
+}
+
+fn paint(text: &str, code: &str, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn unified(shown: &[Shown], color: bool) -> String {
+    let mut out = String::new();
+    let mut pages = PageTracker::default();
+    for item in shown {
+        let row = match item {
+            Shown::Row(row) => row,
+            Shown::Skipped(count) => {
+                pages.skipped();
+                let note = format!("  ... {} unchanged line(s) ...", count);
+                let _ = writeln!(out, "{}", paint(&note, "2", color));
+                continue;
+            }
+        };
+        if let Some((old_page, new_page)) = pages.next(row) {
+            let marker = format!("@@ page {} | page {} @@", old_page, new_page);
+            let _ = writeln!(out, "{}", paint(&marker, "36", color));
+        }
+        match row {
+            Row::Same(old, _) => {
+                let _ = writeln!(out, "  {}", old.text);
+            }
+            Row::Changed(old, new) => {
+                if let Some(old) = old {
+                    let _ = writeln!(out, "{}", paint(&format!("- {}", old.text), "31", color));
+                }
+                if let Some(new) = new {
+                    let _ = writeln!(out, "{}", paint(&format!("+ {}", new.text), "32", color));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .unwrap_or(160)
+}
+
+/// Cut or pad `text` to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let len = fitted.chars().count();
+    fitted.extend(std::iter::repeat_n(' ', width.saturating_sub(len)));
+    fitted
+}
+
+/// `label` followed by a horizontal line, `width` characters in all.
+fn rule(label: &str, width: usize) -> String {
+    let len = label.chars().count();
+    let mut rule = label.to_string();
+    rule.extend(std::iter::repeat_n('─', width.saturating_sub(len)));
+    rule
+}
+
+fn side_by_side(shown: &[Shown], width: usize, color: bool) -> String {
+    // Each side gets a two-character gutter ("- " / "+ ").
+    let column = (width.saturating_sub(3) / 2).saturating_sub(2).max(10);
+    let mut out = String::new();
+    let mut pages = PageTracker::default();
+    for item in shown {
+        let row = match item {
+            Shown::Row(row) => row,
+            Shown::Skipped(count) => {
+                pages.skipped();
+                let note = fit(
+                    &format!("  ... {} unchanged line(s) ...", count),
+                    column + 2,
+                );
+                let _ = writeln!(out, "{}", paint(&note, "2", color));
+                continue;
+            }
+        };
+        if let Some((old_page, new_page)) = pages.next(row) {
+            let left = rule(&format!("── page {} ", old_page), column + 2);
+            let right = rule(&format!("── page {} ", new_page), column + 2);
+            let _ = writeln!(
+                out,
+                "{}",
+                paint(&format!("{}─┼─{}", left, right), "36", color)
+            );
+        }
+        let (left, right) = match row {
+            Row::Same(old, new) => (
+                format!("  {}", fit(&old.text, column)),
+                format!("  {}", fit(&new.text, column)),
+            ),
+            Row::Changed(old, new) => (
+                old.as_ref().map_or(fit("", column + 2), |old| {
+                    paint(&format!("- {}", fit(&old.text, column)), "31", color)
+                }),
+                new.as_ref().map_or(String::new(), |new| {
+                    paint(&format!("+ {}", fit(&new.text, column)), "32", color)
+                }),
+            ),
+        };
+        let _ = writeln!(out, "{} │ {}", left, right.trim_end());
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn html_report(old_name: &str, new_name: &str, shown: &[Shown], summary: &str) -> String {
+    let (old_name, new_name) = (escape_html(old_name), escape_html(new_name));
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{old} vs {new}</title>\n<style>\n\
+         body {{ font-family: sans-serif; margin: 2em; }}\n\
+         table {{ border-collapse: collapse; width: 100%; table-layout: fixed; }}\n\
+         td {{ font-family: monospace; white-space: pre-wrap; padding: 0 0.5em; \
+         vertical-align: top; width: 50%; }}\n\
+         tr.page td {{ background: #e8eef7; color: #345; font-family: sans-serif; \
+         padding: 0.3em 0.5em; }}\n\
+         tr.skip td {{ color: #888; font-style: italic; font-family: sans-serif; }}\n\
+         td.del {{ background: #fdd; }}\n\
+         td.ins {{ background: #dfd; }}\n\
+         </style>\n</head>\n<body>\n<h1>{old} &rarr; {new}</h1>\n<p>{summary}</p>\n\
+         <table>\n<tr><th>{old}</th><th>{new}</th></tr>\n",
+        old = old_name,
+        new = new_name,
+        summary = escape_html(summary),
+    );
+
+    let mut pages = PageTracker::default();
+    for item in shown {
+        let row = match item {
+            Shown::Row(row) => row,
+            Shown::Skipped(count) => {
+                pages.skipped();
+                let _ = writeln!(
+                    html,
+                    "<tr class=\"skip\"><td colspan=\"2\">{} unchanged line(s)</td></tr>",
+                    count
+                );
+                continue;
+            }
+        };
+        if let Some((old_page, new_page)) = pages.next(row) {
+            let _ = writeln!(
+                html,
+                "<tr class=\"page\"><td>Page {}</td><td>Page {}</td></tr>",
+                old_page, new_page
+            );
+        }
+        let cell = |line: &Option<Line>, class: &str| match line {
+            Some(line) => format!("<td class=\"{}\">{}</td>", class, escape_html(&line.text)),
+            None => "<td></td>".to_string(),
+        };
+        match row {
+            Row::Same(old, new) => {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape_html(&old.text),
+                    escape_html(&new.text)
+                );
+            }
+            Row::Changed(old, new) => {
+                let _ = writeln!(html, "<tr>{}{}</tr>", cell(old, "del"), cell(new, "ins"));
+            }
+        }
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(page: u32, texts: &[&str]) -> Vec<Line> {
+        texts
+            .iter()
+            .map(|text| Line {
+                page,
+                text: text.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_rows_pairs_replacements() {
+        let old = lines(1, &["Term: 12 months", "Fee: $100", "Governing law: NY"]);
+        let mut new = lines(1, &["Term: 24 months", "Fee: $100"]);
+        new.extend(lines(2, &["Governing law: NY", "Arbitration applies"]));
+
+        let rows = diff_rows(&old, &new);
+        assert_eq!(rows.len(), 4);
+        assert!(matches!(
+            &rows[0],
+            Row::Changed(Some(o), Some(n)) if o.text == "Term: 12 months" && n.text == "Term: 24 months"
+        ));
+        assert!(matches!(&rows[1], Row::Same(..)));
+        assert_eq!(rows[2].pages(), (Some(1), Some(2)));
+        assert!(matches!(&rows[3], Row::Changed(None, Some(n)) if n.page == 2));
+    }
+
+    #[test]
+    fn test_compare_rasters_highlights_changes() {
+        let white = [255, 255, 255];
+        let old = Raster {
+            width: 2,
+            height: 2,
+            pixels: [white, white, white, [0, 0, 0]].concat(),
+        };
+        // Slight anti-aliasing difference in one pixel, a real one in another.
+        let new = Raster {
+            width: 2,
+            height: 2,
+            pixels: [white, [240, 240, 240], [0, 0, 0], [0, 0, 0]].concat(),
+        };
+        let (ratio, highlighted) = compare_rasters(&old, &new);
+        assert_eq!(ratio, 0.25);
+        assert_eq!(highlighted.get(0, 1), Some([255, 0, 0]));
+        assert_eq!(highlighted.get(0, 0), Some(white));
+
+        let (ratio, _) = compare_rasters(
+            &old,
+            &Raster {
+                width: 1,
+                height: 2,
+                pixels: [white, white].concat(),
+            },
+        );
+        assert_eq!(ratio, 0.5);
+    }
+
+    #[test]
+    fn test_with_context_skips_unchanged_runs() {
+        let old = lines(1, &["a", "b", "c", "d", "e", "f"]);
+        let new = lines(1, &["a", "b", "c", "d", "e", "F"]);
+        let rows = diff_rows(&old, &new);
+        let shown = with_context(&rows, 1);
+        assert!(matches!(shown[0], Shown::Skipped(4)));
+        assert_eq!(shown.len(), 3);
+    }
+}
@@ -0,0 +1,72 @@
+use crate::commands::status;
+use crate::page_range::expand_page_ranges;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    pages: &str,
+    count: u32,
+    output: Q,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    if count == 0 {
+        anyhow::bail!("--count must be at least 1");
+    }
+
+    let doc = PdfDocument::open(&input)?;
+    let total_pages = doc.page_count();
+
+    let selected = expand_page_ranges(pages, total_pages)?;
+    if selected.is_empty() {
+        anyhow::bail!("No pages specified");
+    }
+
+    let page_list = duplicated_order(&selected, count, total_pages);
+
+    // Copies share the original's content and resources, so this only adds
+    // one small page dictionary (and its annotations) per extra copy.
+    let mut new_doc = doc.arrange_pages(&page_list)?;
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Wrote {} page(s) ({} copies of {} selected page(s)) to {}",
+        page_list.len(),
+        count,
+        selected.len(),
+        output.as_ref().display()
+    );
+
+    Ok(())
+}
+
+/// Every page in document order, with each selected page repeated `count`
+/// times in place.
+fn duplicated_order(selected: &[u32], count: u32, total_pages: u32) -> Vec<u32> {
+    let mut order = Vec::new();
+    for page in 1..=total_pages {
+        let copies = if selected.contains(&page) { count } else { 1 };
+        order.extend(std::iter::repeat_n(page, copies as usize));
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicated_order_repeats_in_place() {
+        assert_eq!(
+            duplicated_order(&[1, 3], 3, 4),
+            vec![1, 1, 1, 2, 3, 3, 3, 4]
+        );
+    }
+
+    #[test]
+    fn test_duplicated_order_ignores_repeated_selection() {
+        assert_eq!(duplicated_order(&[2, 2], 2, 2), vec![1, 2, 2]);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::embed::{embed_font, missing_fonts, subset_font, FontDir};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Embed every non-embedded font of `input` that has a replacement in
+/// `font_dir`, subset to the characters used unless `subset` is false.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    font_dir: &Path,
+    subset: bool,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let fonts = FontDir::scan(font_dir)?;
+    let doc = PdfDocument::open(&input)?;
+    let mut new_doc = doc.doc().clone();
+
+    let missing = missing_fonts(&new_doc);
+    let mut embedded = 0;
+    let mut left = Vec::new();
+    for font in &missing {
+        if let Some(reason) = font.unsupported {
+            eprintln!("Warning: {} not embedded: {}", font.base_font, reason);
+            left.push(font.base_font.as_str());
+            continue;
+        }
+        let Some(file) = fonts.find(&font.base_font) else {
+            eprintln!(
+                "Warning: {} not embedded: no replacement in {}",
+                font.base_font,
+                font_dir.display()
+            );
+            left.push(font.base_font.as_str());
+            continue;
+        };
+        let program = if subset {
+            subset_font(file, &font.chars)?
+        } else {
+            std::fs::read(file).with_context(|| format!("Failed to read {}", file.display()))?
+        };
+        let name = embed_font(&mut new_doc, font, program, subset)
+            .with_context(|| format!("Failed to embed {}", file.display()))?;
+        println!(
+            "{} -> {} ({}, {} characters)",
+            font.base_font,
+            name,
+            file.display(),
+            font.chars.len()
+        );
+        embedded += 1;
+    }
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    if missing.is_empty() {
+        status!("All fonts were already embedded");
+    } else {
+        status!(
+            "Embedded {} of {} font(s) into {}",
+            embedded,
+            missing.len(),
+            output.as_ref().display()
+        );
+    }
+    if !left.is_empty() {
+        status!("Still not embedded: {}", left.join(", "));
+    }
+    Ok(())
+}
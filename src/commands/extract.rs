@@ -1,10 +1,22 @@
+use crate::commands::{
+    apply_metadata, compact_pages, format_size, output_size, report_links, status, CopyOptions,
+};
 use crate::page_range::expand_page_ranges;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::links::fix_extracted_links;
 use crate::pdf::PdfDocument;
 use anyhow::Result;
 use std::path::Path;
 
-pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(input: P, pages: &str, output: Q) -> Result<()> {
-    let doc = PdfDocument::open(&input)?;
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    pages: &str,
+    output: Q,
+    portfolio_item: Option<&str>,
+    copy: &CopyOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(&input)?.select_portfolio_item(portfolio_item)?;
     let total_pages = doc.page_count();
 
     let page_list = expand_page_ranges(pages, total_pages)?;
@@ -14,13 +26,30 @@ pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(input: P, pages: &str, output: Q) ->
     }
 
     let mut new_doc = doc.extract_pages(&page_list)?;
-    PdfDocument::save(&mut new_doc, &output)?;
+    let fixup = fix_extracted_links(&mut new_doc, copy.links.dangling());
+    apply_metadata(&mut new_doc, copy.metadata)?;
 
-    println!(
+    if copy.dry_run {
+        let size = output_size(&mut new_doc, save_options)?;
+        println!(
+            "Would extract {} page(s) ({}) to {} ({})",
+            page_list.len(),
+            compact_pages(&page_list),
+            output.as_ref().display(),
+            format_size(size)
+        );
+        report_links(&fixup, output.as_ref());
+        return Ok(());
+    }
+
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
         "Extracted {} page(s) to {}",
         page_list.len(),
         output.as_ref().display()
     );
+    report_links(&fixup, output.as_ref());
 
     Ok(())
 }
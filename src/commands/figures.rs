@@ -0,0 +1,35 @@
+use crate::pdf::cache::get_cached_pdf;
+use crate::pdf::figures::find_figures;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
+    let cached = get_cached_pdf(&path)?;
+    let figures = find_figures(&cached)?;
+
+    if figures.is_empty() {
+        println!("No figures found.");
+        return Ok(());
+    }
+
+    for figure in &figures {
+        print!("Page {}", figure.page);
+        if let Some(image) = &figure.image {
+            print!(" [{}]", image);
+        }
+        println!();
+        if let Some(alt) = &figure.alt {
+            println!("  Alt: {}", alt);
+        }
+        if let Some(caption) = &figure.caption {
+            println!("  Caption: {}", caption);
+        }
+        if figure.alt.is_none() && figure.caption.is_none() {
+            println!("  (no description)");
+        }
+    }
+    println!();
+    println!("{} figures", figures.len());
+
+    Ok(())
+}
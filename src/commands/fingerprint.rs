@@ -0,0 +1,166 @@
+use crate::pdf::cache::cache;
+use crate::pdf::files::find_pdfs;
+use crate::pdf::fingerprint::{fingerprint, Fingerprint, GuessKind};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Output format for fingerprints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FingerprintFormat {
+    /// A block of evidence and guesses per file
+    Text,
+    /// One JSON record per line, for collections
+    Json,
+}
+
+#[derive(Serialize)]
+struct FingerprintRecord<'a> {
+    path: String,
+    producer: Option<&'a str>,
+    creator: Option<&'a str>,
+    xmp_tools: &'a [String],
+    xmp_device: &'a [String],
+    fonts: &'a [String],
+    subset_fonts: usize,
+    traits: &'a [String],
+    /// Most likely first; scanner guesses after generator ones.
+    guesses: Vec<GuessRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct GuessRecord<'a> {
+    /// "generator" or "scanner".
+    kind: &'static str,
+    tool: &'a str,
+    /// True when a Producer, Creator, or XMP string names the tool; false
+    /// for guesses from fonts or structure alone.
+    named: bool,
+    evidence: &'a [String],
+}
+
+/// Most fonts listed in text output.
+const MAX_FONTS_SHOWN: usize = 8;
+
+/// Guess the software (and scanner) that produced each PDF in `paths`
+/// (directories for the PDFs under them). Files that cannot be read are
+/// skipped with a message when there are several.
+pub fn run<P: AsRef<Path>>(paths: &[P], format: FingerprintFormat) -> Result<()> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            files.extend(find_pdfs(path));
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    let several = files.len() > 1;
+
+    for (i, path) in files.iter().enumerate() {
+        let fp = match fingerprint_file(path) {
+            Ok(fp) => fp,
+            Err(err) if several => {
+                eprintln!("Skipping {}: {:#}", path.display(), err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        match format {
+            FingerprintFormat::Text => {
+                if i > 0 {
+                    println!();
+                }
+                print!("{}", format_text(path, &fp));
+            }
+            FingerprintFormat::Json => {
+                println!("{}", serde_json::to_string(&record(path, &fp))?);
+            }
+        }
+        if several {
+            cache().evict(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn fingerprint_file(path: &Path) -> Result<Fingerprint> {
+    let doc = PdfDocument::open(path)?;
+    let raw = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(fingerprint(&doc, &raw))
+}
+
+fn kind_name(kind: GuessKind) -> &'static str {
+    match kind {
+        GuessKind::Generator => "generator",
+        GuessKind::Scanner => "scanner",
+    }
+}
+
+fn record<'a>(path: &Path, fp: &'a Fingerprint) -> FingerprintRecord<'a> {
+    FingerprintRecord {
+        path: path.display().to_string(),
+        producer: fp.producer.as_deref(),
+        creator: fp.creator.as_deref(),
+        xmp_tools: &fp.xmp_tools,
+        xmp_device: &fp.xmp_device,
+        fonts: &fp.fonts,
+        subset_fonts: fp.subset_fonts,
+        traits: &fp.traits,
+        guesses: fp
+            .guesses
+            .iter()
+            .map(|guess| GuessRecord {
+                kind: kind_name(guess.kind),
+                tool: &guess.tool,
+                named: guess.strong,
+                evidence: &guess.evidence,
+            })
+            .collect(),
+    }
+}
+
+fn format_text(path: &Path, fp: &Fingerprint) -> String {
+    let mut out = format!("File: {}\n", path.display());
+    if let Some(producer) = &fp.producer {
+        out.push_str(&format!("Producer: {}\n", producer));
+    }
+    if let Some(creator) = &fp.creator {
+        out.push_str(&format!("Creator: {}\n", creator));
+    }
+    if !fp.xmp_tools.is_empty() {
+        out.push_str(&format!("XMP tools: {}\n", fp.xmp_tools.join(", ")));
+    }
+    if !fp.xmp_device.is_empty() {
+        out.push_str(&format!("XMP device: {}\n", fp.xmp_device.join(" ")));
+    }
+    if !fp.fonts.is_empty() {
+        let mut shown = fp.fonts[..fp.fonts.len().min(MAX_FONTS_SHOWN)].join(", ");
+        if fp.fonts.len() > MAX_FONTS_SHOWN {
+            shown.push_str(", ...");
+        }
+        out.push_str(&format!(
+            "Fonts: {} ({} subset): {}\n",
+            fp.fonts.len(),
+            fp.subset_fonts,
+            shown
+        ));
+    }
+    out.push_str(&format!("Structure: {}\n", fp.traits.join(", ")));
+
+    if fp.guesses.is_empty() {
+        out.push_str("Likely source: unknown\n");
+    }
+    for guess in &fp.guesses {
+        out.push_str(&format!(
+            "Likely {}: {}{} ({})\n",
+            kind_name(guess.kind),
+            guess.tool,
+            if guess.strong { "" } else { "?" },
+            guess.evidence.join("; ")
+        ));
+    }
+    out
+}
@@ -0,0 +1,216 @@
+use crate::commands::{push_csv_row, status};
+use crate::pdf::document::SaveOptions;
+use crate::pdf::fdf::{parse_fdf, parse_xfdf, write_fdf, write_xfdf};
+use crate::pdf::files::find_pdfs;
+use crate::pdf::forms::{fill_fields, form_fields, FieldValue};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Form data file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DataFormat {
+    /// By file extension (.xfdf, .fdf, .json); XFDF when writing to stdout
+    Auto,
+    /// XML Forms Data Format
+    Xfdf,
+    /// Forms Data Format (PDF object syntax)
+    Fdf,
+    /// A JSON object of field name to value (an array for multi-select)
+    Json,
+}
+
+impl DataFormat {
+    fn resolve(self, path: Option<&Path>) -> Result<Self> {
+        if self != DataFormat::Auto {
+            return Ok(self);
+        }
+        let Some(path) = path else {
+            return Ok(DataFormat::Xfdf);
+        };
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("xfdf") | Some("xml") => Ok(DataFormat::Xfdf),
+            Some("fdf") => Ok(DataFormat::Fdf),
+            Some("json") => Ok(DataFormat::Json),
+            _ => anyhow::bail!(
+                "Cannot tell the format of {} from its extension; use --format",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Write the form's field values as XFDF, FDF, or JSON.
+pub fn export<P: AsRef<Path>>(path: P, output: Option<&Path>, format: DataFormat) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let fields: Vec<(String, Option<FieldValue>)> = form_fields(doc.doc())
+        .into_iter()
+        .map(|field| (field.name, field.value))
+        .collect();
+    if fields.is_empty() {
+        anyhow::bail!("{} has no fillable form fields", path.display());
+    }
+
+    let file_name = path.file_name().and_then(|name| name.to_str());
+    let bytes = match format.resolve(output)? {
+        DataFormat::Xfdf => write_xfdf(&fields, file_name).into_bytes(),
+        DataFormat::Fdf => write_fdf(&fields, file_name),
+        _ => {
+            let mut json = serde_json::to_string_pretty(&fields_to_json(&fields))?;
+            json.push('\n');
+            json.into_bytes()
+        }
+    };
+
+    if let Some(output) = output {
+        std::fs::write(output, bytes)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        status!("Exported {} field(s) to {}", fields.len(), output.display());
+    } else {
+        std::io::stdout().write_all(&bytes)?;
+    }
+
+    Ok(())
+}
+
+/// Fill form fields from a data file and write the result.
+pub fn fill<P: AsRef<Path>>(
+    path: P,
+    data: &Path,
+    format: DataFormat,
+    output: &Path,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let bytes =
+        std::fs::read(data).with_context(|| format!("Failed to read {}", data.display()))?;
+    let values = match format.resolve(Some(data))? {
+        DataFormat::Xfdf => parse_xfdf(std::str::from_utf8(&bytes).context("XFDF is not UTF-8")?)?,
+        DataFormat::Fdf => parse_fdf(&bytes)?,
+        _ => json_to_fields(&serde_json::from_slice(&bytes).context("parse JSON form data")?)?,
+    };
+
+    let doc = PdfDocument::open(&path)?;
+    let mut new_doc = doc.doc().clone();
+    let filled = fill_fields(&mut new_doc, &values)?;
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+    status!("Filled {} field(s) into {}", filled, output.display());
+
+    Ok(())
+}
+
+/// Write one CSV row of field values per form, with a column per field
+/// name seen in any of them. Directories are searched for PDFs.
+///
+/// Files that cannot be read are reported and skipped, so one damaged
+/// upload does not stop a batch.
+pub fn export_batch(inputs: &[PathBuf], output: Option<&Path>) -> Result<()> {
+    let mut paths = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            paths.extend(find_pdfs(input));
+        } else {
+            paths.push(input.clone());
+        }
+    }
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut rows: Vec<(PathBuf, HashMap<String, String>)> = Vec::new();
+    for path in paths {
+        let doc = match PdfDocument::open(&path) {
+            Ok(doc) => doc,
+            Err(err) => {
+                eprintln!("Skipping {}: {:#}", path.display(), err);
+                continue;
+            }
+        };
+        let mut values = HashMap::new();
+        for field in form_fields(doc.doc()) {
+            if !columns.contains(&field.name) {
+                columns.push(field.name.clone());
+            }
+            let value = field.value.map(|v| v.to_text("; ")).unwrap_or_default();
+            values.insert(field.name, value);
+        }
+        rows.push((path, values));
+    }
+
+    let mut csv = String::new();
+    let header: Vec<&str> = std::iter::once("file")
+        .chain(columns.iter().map(String::as_str))
+        .collect();
+    push_csv_row(&mut csv, &header);
+    for (path, values) in &rows {
+        let file = path.display().to_string();
+        let row: Vec<&str> = std::iter::once(file.as_str())
+            .chain(
+                columns
+                    .iter()
+                    .map(|column| values.get(column).map_or("", String::as_str)),
+            )
+            .collect();
+        push_csv_row(&mut csv, &row);
+    }
+
+    if let Some(output) = output {
+        std::fs::write(output, csv)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        status!(
+            "Exported {} form(s) with {} field(s) to {}",
+            rows.len(),
+            columns.len(),
+            output.display()
+        );
+    } else {
+        print!("{}", csv);
+    }
+
+    Ok(())
+}
+
+fn fields_to_json(fields: &[(String, Option<FieldValue>)]) -> Value {
+    let mut map = Map::new();
+    for (name, value) in fields {
+        let value = match value {
+            Some(FieldValue::Text(text) | FieldValue::Name(text)) => Value::from(text.as_str()),
+            Some(FieldValue::List(items)) => Value::from(items.clone()),
+            None => Value::Null,
+        };
+        map.insert(name.clone(), value);
+    }
+    Value::Object(map)
+}
+
+fn json_to_fields(json: &Value) -> Result<Vec<(String, FieldValue)>> {
+    let map = json
+        .as_object()
+        .context("JSON form data must be an object of field name to value")?;
+    let mut fields = Vec::new();
+    for (name, value) in map {
+        let value = match value {
+            Value::Null => continue,
+            Value::String(text) => FieldValue::Text(text.clone()),
+            Value::Bool(checked) => FieldValue::Name(if *checked { "Yes" } else { "Off" }.into()),
+            Value::Number(number) => FieldValue::Text(number.to_string()),
+            Value::Array(items) => FieldValue::List(
+                items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(text) => Ok(text.clone()),
+                        _ => anyhow::bail!("Field '{}': list items must be strings", name),
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(_) => anyhow::bail!("Field '{}': unsupported value", name),
+        };
+        fields.push((name.clone(), value));
+    }
+    Ok(fields)
+}
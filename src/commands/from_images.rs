@@ -0,0 +1,75 @@
+use crate::commands::status;
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::images::add_image;
+use crate::pdf::layout::PageSize;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use lopdf::dictionary;
+use std::path::{Path, PathBuf};
+
+pub struct FromImagesOptions {
+    /// Fixed page size; when absent, each page matches its image.
+    pub page_size: Option<PageSize>,
+    /// Scale images to fill the page (keeping aspect ratio) instead of
+    /// drawing them at their natural size.
+    pub fit: bool,
+    /// Resolution used to convert pixels to points.
+    pub dpi: f32,
+}
+
+pub fn run<Q: AsRef<Path>>(
+    images: &[PathBuf],
+    output: Q,
+    options: &FromImagesOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    if options.dpi <= 0.0 {
+        anyhow::bail!("--dpi must be positive");
+    }
+
+    let mut builder = DocumentBuilder::new();
+    for path in images {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let image = add_image(builder.doc_mut(), &bytes)
+            .with_context(|| format!("Failed to embed {}", path.display()))?;
+
+        let natural_width = image.width as f32 * 72.0 / options.dpi;
+        let natural_height = image.height as f32 * 72.0 / options.dpi;
+        let (page_width, page_height) = match options.page_size {
+            Some(size) => (size.width, size.height),
+            None => (natural_width, natural_height),
+        };
+        let scale = if options.fit {
+            (page_width / natural_width).min(page_height / natural_height)
+        } else {
+            1.0
+        };
+        let (width, height) = (natural_width * scale, natural_height * scale);
+        let x = (page_width - width) / 2.0;
+        let y = (page_height - height) / 2.0;
+
+        let content = format!("q {} 0 0 {} {} {} cm /Im0 Do Q\n", width, height, x, y);
+        let resources = dictionary! {
+            "XObject" => dictionary! { "Im0" => image.id },
+        };
+        builder.add_page(
+            [0.0, 0.0, page_width, page_height],
+            resources,
+            content.into_bytes(),
+        );
+    }
+
+    let page_count = builder.page_count();
+    let mut doc = builder.finish()?;
+    PdfDocument::save_with(&mut doc, &output, save_options)?;
+
+    status!(
+        "Wrote {} page(s) to {}",
+        page_count,
+        output.as_ref().display()
+    );
+
+    Ok(())
+}
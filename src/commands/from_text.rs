@@ -0,0 +1,315 @@
+use crate::commands::status;
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::typeset::{typeset, Block, TypesetOptions};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// How to interpret the input text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TextFormat {
+    /// Markdown for .md/.markdown files, plain text otherwise
+    Auto,
+    /// Keep lines as written, wrapping those that are too long
+    Plain,
+    /// Headings, lists, code blocks, and paragraphs
+    Markdown,
+}
+
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    format: TextFormat,
+    options: &TypesetOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    if options.font_size <= 0.0 || options.margin < 0.0 {
+        anyhow::bail!("--font-size must be positive and --margin non-negative");
+    }
+    if 2.0 * options.margin >= options.page_size.width.min(options.page_size.height) {
+        anyhow::bail!("--margin leaves no room for text on this page size");
+    }
+
+    let input = input.as_ref();
+    let text = std::fs::read_to_string(input)
+        .with_context(|| format!("Failed to read {}", input.display()))?;
+
+    let markdown = match format {
+        TextFormat::Markdown => true,
+        TextFormat::Plain => false,
+        TextFormat::Auto => input
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| {
+                ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+            }),
+    };
+    let blocks = if markdown {
+        parse_markdown(&text)
+    } else {
+        parse_plain(&text)
+    };
+
+    let mut builder = DocumentBuilder::new();
+    let page_count = typeset(&mut builder, &blocks, options)?;
+    let mut doc = builder.finish()?;
+    PdfDocument::save_with(&mut doc, &output, save_options)?;
+
+    status!(
+        "Wrote {} page(s) to {}",
+        page_count,
+        output.as_ref().display()
+    );
+
+    Ok(())
+}
+
+/// One block per line, with empty lines kept as vertical space.
+pub fn parse_plain(text: &str) -> Vec<Block> {
+    text.lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                Block::Blank
+            } else {
+                Block::Line(line.trim_end().to_string())
+            }
+        })
+        .collect()
+}
+
+/// Parse the basic Markdown subset: ATX headings, bullet and numbered lists,
+/// fenced code blocks, and paragraphs. Inline emphasis and code markers are
+/// dropped, since the output uses a single body font.
+pub fn parse_markdown(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut code: Option<Vec<String>> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Block>| {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Paragraph(strip_inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match code.take() {
+                Some(lines) => blocks.push(Block::Code(lines)),
+                None => {
+                    flush(&mut paragraph, &mut blocks);
+                    code = Some(Vec::new());
+                }
+            }
+            continue;
+        }
+        if let Some(lines) = code.as_mut() {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        if let Some((level, heading)) = parse_heading(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::Heading {
+                level,
+                text: strip_inline(heading),
+            });
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+        if let Some((marker, item)) = parse_list_marker(trimmed) {
+            flush(&mut paragraph, &mut blocks);
+            blocks.push(Block::ListItem {
+                depth: indent / 2,
+                marker,
+                text: strip_inline(item),
+            });
+            continue;
+        }
+
+        // An indented line right after a list item continues that item.
+        if indent > 0 && paragraph.is_empty() {
+            if let Some(Block::ListItem { text, .. }) = blocks.last_mut() {
+                text.push(' ');
+                text.push_str(&strip_inline(trimmed));
+                continue;
+            }
+        }
+
+        paragraph.push(trimmed);
+    }
+
+    if let Some(lines) = code {
+        blocks.push(Block::Code(lines));
+    }
+    flush(&mut paragraph, &mut blocks);
+    blocks
+}
+
+fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((level as u8, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+fn parse_list_marker(line: &str) -> Option<(String, &str)> {
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(bullet) {
+            return Some(("\u{2022}".to_string(), item.trim()));
+        }
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        for delimiter in [". ", ") "] {
+            if let Some(item) = rest.strip_prefix(delimiter) {
+                return Some((format!("{}.", &line[..digits]), item.trim()));
+            }
+        }
+    }
+    None
+}
+
+/// Drop emphasis and inline code markers.
+///
+/// Emphasis delimiters (`*`, `**`, `_`, `__`) only count in pairs that open
+/// at the start of a word and close at the end of one, so `a*b*c` and
+/// `snake_case` are kept as written. So is a word in double underscores
+/// (`__init__`), which is far more likely a Python name than bold text.
+fn strip_inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '`' {
+            if let Some(len) = chars[i + 1..].iter().position(|&c| c == '`') {
+                out.extend(&chars[i + 1..i + 1 + len]);
+                i += len + 2;
+                continue;
+            }
+        } else if c == '*' || c == '_' {
+            let run = if chars.get(i + 1) == Some(&c) { 2 } else { 1 };
+            if let Some(end) = closing_delimiter(&chars, i, run) {
+                let inner: String = chars[i + run..end].iter().collect();
+                out.push_str(&strip_inline(&inner));
+                i = end + run;
+            } else {
+                out.extend(&chars[i..i + run]);
+                i += run;
+            }
+            continue;
+        }
+        out.push(c);
+        i += 1;
+    }
+    out
+}
+
+/// The position of the delimiter run closing the `run`-character one at
+/// `start`, if that opens emphasis.
+fn closing_delimiter(chars: &[char], start: usize, run: usize) -> Option<usize> {
+    let c = chars[start];
+    let opens = (start == 0 || !chars[start - 1].is_alphanumeric() && chars[start - 1] != c)
+        && chars
+            .get(start + run)
+            .is_some_and(|&next| !next.is_whitespace() && next != c);
+    if !opens {
+        return None;
+    }
+    let end = (start + run + 1..chars.len()).find(|&k| {
+        chars[k..].starts_with(&[c, c][..run])
+            && chars.get(k + run) != Some(&c)
+            && !chars[k - 1].is_whitespace()
+            && chars[k - 1] != c
+            && chars
+                .get(k + run)
+                .is_none_or(|after| !after.is_alphanumeric())
+    })?;
+    let inner = &chars[start + run..end];
+    let dunder = c == '_' && run == 2 && inner.iter().all(|&c| c.is_alphanumeric() || c == '_');
+    (!dunder).then_some(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markdown_blocks() {
+        let md = "# Title\n\nSome *text*\nacross lines.\n\n- one\n  continued\n  - nested\n2. two\n\n```\nfn main() {}\n```\n";
+        assert_eq!(
+            parse_markdown(md),
+            vec![
+                Block::Heading {
+                    level: 1,
+                    text: "Title".to_string()
+                },
+                Block::Paragraph("Some text across lines.".to_string()),
+                Block::ListItem {
+                    depth: 0,
+                    marker: "\u{2022}".to_string(),
+                    text: "one continued".to_string()
+                },
+                Block::ListItem {
+                    depth: 1,
+                    marker: "\u{2022}".to_string(),
+                    text: "nested".to_string()
+                },
+                Block::ListItem {
+                    depth: 0,
+                    marker: "2.".to_string(),
+                    text: "two".to_string()
+                },
+                Block::Code(vec!["fn main() {}".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_inline() {
+        assert_eq!(
+            strip_inline("**Bold**, *it*, _it_, __bold text__ and `code`"),
+            "Bold, it, it, bold text and code"
+        );
+        assert_eq!(
+            strip_inline("call __init__ on snake_case"),
+            "call __init__ on snake_case"
+        );
+        assert_eq!(strip_inline("`__init__` and 2*3*4"), "__init__ and 2*3*4");
+        assert_eq!(strip_inline("a * b * c"), "a * b * c");
+    }
+
+    #[test]
+    fn test_heading_requires_space() {
+        assert_eq!(parse_heading("## Usage ##"), Some((2, "Usage")));
+        assert_eq!(parse_heading("#hashtag"), None);
+    }
+
+    #[test]
+    fn test_parse_plain_keeps_blank_lines() {
+        assert_eq!(
+            parse_plain("a\n\nb"),
+            vec![
+                Block::Line("a".to_string()),
+                Block::Blank,
+                Block::Line("b".to_string())
+            ]
+        );
+    }
+}
@@ -1,13 +1,39 @@
-use crate::pdf::text::grep_pdf;
+use crate::commands::{color, status};
+use crate::pdf::files::find_pdfs;
+use crate::pdf::ocr::{grep_ocr, OcrOptions};
+use crate::pdf::recover::{ocr_glyphs, recover_page_text};
+use crate::pdf::text::{
+    grep_attachments, grep_cached_until, grep_page, GrepMatch, PageTextProvider,
+};
+use crate::pdf::PdfDocument;
 use anyhow::Result;
-use regex::RegexBuilder;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
 use std::path::Path;
 
 pub struct GrepOptions {
     pub pattern: String,
     pub case_insensitive: bool,
     pub max_results: usize,
+    /// Keep at most this many matches from any one page.
+    pub max_per_page: Option<usize>,
+    /// Keep at most this many matches from any one file.
+    pub max_per_file: Option<usize>,
+    pub group_by: GroupBy,
+    pub sort: SortBy,
     pub context_chars: usize,
+    /// Search the document as of an earlier revision (1-based).
+    pub at_version: Option<usize>,
+    /// Search a PDF packaged in the portfolio instead (by name or file name).
+    pub portfolio_item: Option<String>,
+    /// Also search attached PDFs and text files (e.g. invoice XML).
+    pub include_attachments: bool,
+    /// OCR pages with no extractable text and search the result too, and
+    /// recognize glyphs recovered pages couldn't name.
+    pub ocr: Option<OcrOptions>,
+    /// Drop OCR matches whose least certain word is below this confidence
+    /// (0-100).
+    pub min_confidence: Option<f32>,
 }
 
 impl Default for GrepOptions {
@@ -16,50 +42,357 @@ impl Default for GrepOptions {
             pattern: String::new(),
             case_insensitive: false,
             max_results: 100,
+            max_per_page: None,
+            max_per_file: None,
+            group_by: GroupBy::None,
+            sort: SortBy::Page,
             context_chars: 60,
+            at_version: None,
+            portfolio_item: None,
+            include_attachments: false,
+            ocr: None,
+            min_confidence: None,
         }
     }
 }
 
-pub fn run<P: AsRef<Path>>(path: P, options: &GrepOptions) -> Result<()> {
+/// How grep output is grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum GroupBy {
+    /// One line per match
+    None,
+    /// A heading per page with its match count, then its matches
+    Page,
+    /// A heading per file with its match count, then its matches
+    File,
+}
+
+/// Order of grep output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortBy {
+    /// Files in the order given, pages in document order
+    Page,
+    /// Groups with the most matches first (pages, without --group-by)
+    Count,
+}
+
+/// A match and the index of the file it was found in.
+struct Found {
+    file: usize,
+    m: GrepMatch,
+}
+
+/// Search `paths` (directories for the PDFs under them) in order until
+/// `max_results` matches are found, prefixing each match with its file
+/// when there is more than one.
+pub fn run<P: AsRef<Path>>(paths: &[P], options: &GrepOptions) -> Result<()> {
     let regex = RegexBuilder::new(&options.pattern)
         .case_insensitive(options.case_insensitive)
         .build()?;
 
-    let matches = grep_pdf(&path, &regex, options.max_results)?;
+    let mut files = Vec::new();
+    for path in paths {
+        let path = path.as_ref();
+        if path.is_dir() {
+            files.extend(find_pdfs(path));
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    let show_files = files.len() > 1 || paths.iter().any(|p| p.as_ref().is_dir());
+
+    let mut found = Vec::new();
+    let mut remaining = options.max_results;
+    for (file, path) in files.iter().enumerate() {
+        if remaining == 0 {
+            break;
+        }
+        let budget = options.max_per_file.map_or(remaining, |n| n.min(remaining));
+        let matches = match search(path, &regex, budget, options) {
+            Ok(matches) => matches,
+            Err(err) if show_files => {
+                eprintln!("Skipping {}: {:#}", path.display(), err);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        remaining -= matches.len();
+        found.extend(matches.into_iter().map(|m| Found { file, m }));
+    }
 
-    if matches.is_empty() {
-        println!("No matches found.");
+    if found.is_empty() {
+        status!("No matches found.");
         return Ok(());
     }
 
-    for m in &matches {
-        // Truncate long lines for display
-        let display_text = if m.text.len() > options.context_chars * 2 {
-            let start = m.match_start as usize;
-            let end = m.match_end as usize;
+    let mut groups = match options.group_by {
+        GroupBy::File => group(&found, |f| f.file),
+        GroupBy::Page | GroupBy::None => {
+            group(&found, |f| (f.file, f.m.attachment.clone(), f.m.page))
+        }
+    };
+    if options.sort == SortBy::Count {
+        groups.sort_by_key(|g| std::cmp::Reverse(g.len()));
+    }
+
+    let color = color();
+    let file_prefix = |f: &Found| match show_files {
+        true => format!("{}:", files[f.file].display()),
+        false => String::new(),
+    };
+    for matches in &groups {
+        let first = matches[0];
+        match options.group_by {
+            GroupBy::None => {}
+            GroupBy::Page => println!(
+                "{}{} ({} match(es))",
+                file_prefix(first),
+                page_location(&first.m),
+                matches.len()
+            ),
+            GroupBy::File => println!(
+                "{} ({} match(es))",
+                files[first.file].display(),
+                matches.len()
+            ),
+        }
+        for f in matches {
+            let m = &f.m;
+            let display_text = display_match(
+                &m.text,
+                m.match_start as usize,
+                m.match_end as usize,
+                options.context_chars,
+                color,
+            );
+            let ocr = match (m.ocr_confidence, m.recovered) {
+                (Some(confidence), _) => format!(" [ocr {:.0}%]", confidence),
+                (None, true) => " [recovered]".to_string(),
+                (None, false) => String::new(),
+            };
+            match options.group_by {
+                GroupBy::None => println!(
+                    "{}{}:L{}{}: {}",
+                    file_prefix(f),
+                    page_location(m),
+                    m.line_number,
+                    ocr,
+                    display_text
+                ),
+                GroupBy::Page => println!("  L{}{}: {}", m.line_number, ocr, display_text),
+                GroupBy::File => println!(
+                    "  {}:L{}{}: {}",
+                    page_location(m),
+                    m.line_number,
+                    ocr,
+                    display_text
+                ),
+            }
+        }
+    }
 
-            // Show context around the match
-            let ctx_start = start.saturating_sub(options.context_chars);
-            let ctx_end = (end + options.context_chars).min(m.text.len());
+    status!("\n{} match(es) found.", found.len());
 
-            let mut display = String::new();
-            if ctx_start > 0 {
-                display.push_str("...");
+    Ok(())
+}
+
+/// `p12`, or for matches in attachments `name` or `name:p2`.
+fn page_location(m: &GrepMatch) -> String {
+    match (&m.attachment, m.page) {
+        (None, page) => format!("p{}", page),
+        (Some(name), 0) => name.clone(),
+        (Some(name), page) => format!("{}:p{}", name, page),
+    }
+}
+
+/// Split `found` into groups of equal `key`, in order of first appearance.
+fn group<K: Eq + std::hash::Hash>(found: &[Found], key: impl Fn(&Found) -> K) -> Vec<Vec<&Found>> {
+    let mut index = HashMap::new();
+    let mut groups: Vec<Vec<&Found>> = Vec::new();
+    for f in found {
+        let i = *index.entry(key(f)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[i].push(f);
+    }
+    groups
+}
+
+/// Up to `max_results` matches in one file.
+fn search(
+    path: &Path,
+    regex: &Regex,
+    max_results: usize,
+    options: &GrepOptions,
+) -> Result<Vec<GrepMatch>> {
+    let doc = match options.at_version {
+        Some(_) => PdfDocument::open_at_version(path, options.at_version)?,
+        None => PdfDocument::open_for_text(path)?,
+    };
+    let doc = doc.select_portfolio_item(options.portfolio_item.as_deref())?;
+    let mut matches =
+        grep_cached_until(doc.cached(), regex, max_results, options.max_per_page, None)?;
+    if options.include_attachments && matches.len() < max_results {
+        let remaining = max_results - matches.len();
+        matches.extend(grep_attachments(doc.cached(), regex, remaining)?);
+    }
+    if let Some(ocr) = &options.ocr {
+        // Scans have no text layer; everything else was searched above.
+        let mut scanned = Vec::new();
+        let mut unrecognized = Vec::new();
+        for page in PageTextProvider::new(doc.cached()).iter() {
+            if page.error.is_none() && page.text.trim().is_empty() {
+                scanned.push(page.page);
+            } else if doc
+                .cached()
+                .recovery(page.page)
+                .is_some_and(|stats| stats.unmapped > 0)
+            {
+                unrecognized.push(page.page);
             }
-            display.push_str(&m.text[ctx_start..ctx_end]);
-            if ctx_end < m.text.len() {
-                display.push_str("...");
+        }
+        // Recovered pages with glyphs no name identified: recognize those
+        // glyphs and search the page again.
+        for &page in &unrecognized {
+            let glyphs = ocr_glyphs(path, doc.doc(), page, ocr)?;
+            let page_id = doc.doc().get_pages()[&page];
+            let Some(recovered) = recover_page_text(doc.doc(), page_id, &glyphs) else {
+                continue;
+            };
+            matches.retain(|m| m.attachment.is_some() || m.page != page);
+            let limit = options.max_per_page.unwrap_or(usize::MAX);
+            matches.extend(grep_page(page, &recovered.text, regex, limit, true));
+        }
+        if !unrecognized.is_empty() {
+            matches.sort_by_key(|m| (m.attachment.is_some(), m.page));
+            matches.truncate(max_results);
+        }
+        if matches.len() < max_results && !scanned.is_empty() {
+            let remaining = max_results - matches.len();
+            let mut found = grep_ocr(path, &scanned, regex, remaining, options.max_per_page, ocr)?;
+            if let Some(min) = options.min_confidence {
+                found.retain(|m| m.ocr_confidence.is_some_and(|c| c >= min));
             }
-            display
-        } else {
-            m.text.clone()
+            matches.extend(found);
+            matches.sort_by_key(|m| (m.attachment.is_some(), m.page));
+        }
+    }
+    Ok(matches)
+}
+
+/// The line a match was found on, trimmed, cut to `context_chars` on either
+/// side of the match if it's long, and with the match in bold red if `color`.
+fn display_match(
+    text: &str,
+    start: usize,
+    end: usize,
+    context_chars: usize,
+    color: bool,
+) -> String {
+    let start = floor_char_boundary(text, start.min(text.len()));
+    let end = floor_char_boundary(text, end.clamp(start, text.len()));
+    let (ctx_start, ctx_end) = if text.len() > context_chars * 2 {
+        (
+            floor_char_boundary(text, start.saturating_sub(context_chars)),
+            ceil_char_boundary(text, (end + context_chars).min(text.len())),
+        )
+    } else {
+        (0, text.len())
+    };
+
+    let mut display = String::new();
+    if ctx_start > 0 {
+        display.push_str("...");
+    }
+    let before = &text[ctx_start..start];
+    let after = &text[end..ctx_end];
+    let matched = &text[start..end];
+    display.push_str(if display.is_empty() {
+        before.trim_start()
+    } else {
+        before
+    });
+    if color && !matched.is_empty() {
+        display.push_str(&format!("\x1b[1;31m{}\x1b[0m", matched));
+    } else {
+        display.push_str(matched);
+    }
+    if ctx_end < text.len() {
+        display.push_str(after);
+        display.push_str("...");
+    } else {
+        display.push_str(after.trim_end());
+    }
+    display
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group() {
+        let found = |file, page| Found {
+            file,
+            m: GrepMatch {
+                page,
+                line_number: 1,
+                text: String::new(),
+                match_start: 0,
+                match_end: 0,
+                page_start: 0,
+                page_end: 0,
+                attachment: None,
+                ocr_confidence: None,
+                recovered: false,
+            },
         };
+        let found = [found(0, 1), found(0, 3), found(1, 2), found(0, 3)];
 
-        println!("p{}:L{}: {}", m.page, m.line_number, display_text.trim());
+        let pages = group(&found, |f| (f.file, f.m.page));
+        let pages: Vec<Vec<u32>> = pages
+            .iter()
+            .map(|g| g.iter().map(|f| f.m.page).collect())
+            .collect();
+        assert_eq!(pages, [vec![1], vec![3, 3], vec![2]]);
+
+        let files = group(&found, |f| f.file);
+        assert_eq!(files.iter().map(Vec::len).collect::<Vec<_>>(), [3, 1]);
     }
 
-    println!("\n{} match(es) found.", matches.len());
+    #[test]
+    fn test_display_match() {
+        let text = "  Total due: $1,240.00  ";
+        assert_eq!(
+            display_match(text, 2, 11, 80, false),
+            "Total due: $1,240.00"
+        );
+        assert_eq!(
+            display_match(text, 2, 11, 80, true),
+            "\x1b[1;31mTotal due\x1b[0m: $1,240.00"
+        );
 
-    Ok(())
+        // Context is cut on character boundaries.
+        let text = "ééééé match ééééé";
+        let start = text.find("match").unwrap();
+        assert_eq!(
+            display_match(text, start, start + 5, 3, false),
+            "...é match é..."
+        );
+    }
 }
@@ -0,0 +1,25 @@
+use crate::commands::status;
+use crate::semantic::{build_index, index_path, EmbedderConfig};
+use anyhow::Result;
+use std::path::Path;
+
+/// Chunk and embed `path`'s text, and write the index next to it (or to
+/// `index`).
+pub fn embed(
+    path: &Path,
+    index: Option<&Path>,
+    embedder: &EmbedderConfig,
+    chunk_chars: usize,
+) -> Result<()> {
+    let embedder = embedder.connect()?;
+    let built = build_index(path, embedder.as_ref(), chunk_chars)?;
+    let index = index.map_or_else(|| index_path(path), Path::to_path_buf);
+    built.save(&index)?;
+    status!(
+        "Embedded {} chunk(s) with {} into {}",
+        built.chunks.len(),
+        built.model,
+        index.display()
+    );
+    Ok(())
+}
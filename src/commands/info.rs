@@ -1,9 +1,10 @@
+use crate::pdf::portfolio::{is_portfolio, portfolio_items};
 use crate::pdf::PdfDocument;
 use anyhow::Result;
 use std::path::Path;
 
-pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
-    let doc = PdfDocument::open(&path)?;
+pub fn run<P: AsRef<Path>>(path: P, at_version: Option<usize>) -> Result<()> {
+    let doc = PdfDocument::open_at_version(&path, at_version)?;
     let info = doc.get_info();
 
     println!("File: {}", path.as_ref().display());
@@ -27,32 +28,40 @@ pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
     if let Some(producer) = &info.producer {
         println!("Producer: {}", producer);
     }
-    if let Some(creation_date) = &info.creation_date {
-        println!("Created: {}", format_pdf_date(creation_date));
+    // Dates that don't parse are shown as written.
+    match (&info.created, &info.creation_date) {
+        (Some(date), _) => println!("Created: {}", date),
+        (None, Some(raw)) => println!("Created: {}", raw),
+        (None, None) => {}
     }
-    if let Some(mod_date) = &info.mod_date {
-        println!("Modified: {}", format_pdf_date(mod_date));
+    match (&info.modified, &info.mod_date) {
+        (Some(date), _) => println!("Modified: {}", date),
+        (None, Some(raw)) => println!("Modified: {}", raw),
+        (None, None) => {}
     }
 
-    Ok(())
-}
-
-fn format_pdf_date(date: &str) -> String {
-    // PDF date format: D:YYYYMMDDHHmmSSOHH'mm
-    // Try to make it more readable
-    if date.starts_with("D:") && date.len() >= 10 {
-        let d = &date[2..];
-        if d.len() >= 8 {
-            let year = &d[0..4];
-            let month = &d[4..6];
-            let day = &d[6..8];
-            let time = if d.len() >= 14 {
-                format!(" {}:{}:{}", &d[8..10], &d[10..12], &d[12..14])
-            } else {
-                String::new()
-            };
-            return format!("{}-{}-{}{}", year, month, day, time);
+    if is_portfolio(doc.doc()) {
+        let items = portfolio_items(doc.doc());
+        println!();
+        println!("Portfolio: {} item(s)", items.len());
+        for item in &items {
+            let mut line = format!("  {}", item.name);
+            if item.file_name != item.name {
+                line.push_str(&format!(" ({})", item.file_name));
+            }
+            if let Some(size) = item.size {
+                line.push_str(&format!(", {} bytes", size));
+            }
+            if !item.is_pdf() {
+                line.push_str(", not a PDF");
+            }
+            println!("{}", line);
+            if let Some(description) = &item.description {
+                println!("    {}", description);
+            }
         }
+        println!("Use --portfolio-item NAME with extract, read-pages, or grep to open an item.");
     }
-    date.to_string()
+
+    Ok(())
 }
@@ -0,0 +1,19 @@
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(&input)?;
+    let mut new_doc = doc.doc().clone();
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!("Linearized {}", output.as_ref().display());
+
+    Ok(())
+}
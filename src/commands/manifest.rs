@@ -0,0 +1,132 @@
+//! `--manifest-out`: a JSON record of what a command read and wrote, so
+//! pipelines can audit a run and check its outputs before using them.
+
+use crate::pdf::document::write_atomic;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// An operation whose inputs have been recorded, waiting for its outputs.
+pub struct Operation {
+    manifest_path: PathBuf,
+    manifest: Manifest,
+}
+
+#[derive(Serialize)]
+struct Manifest {
+    command: &'static str,
+    inputs: Vec<FileRecord>,
+    /// The page selection the command was given, as typed.
+    selection: Option<String>,
+    outputs: Vec<FileRecord>,
+}
+
+#[derive(Debug, Serialize)]
+struct FileRecord {
+    path: String,
+    bytes: u64,
+    sha256: String,
+    /// `null` for files that aren't PDFs.
+    pages: Option<u32>,
+}
+
+/// Record `inputs` before `command` runs, if a manifest was asked for.
+///
+/// Inputs are hashed now because `--in-place` replaces them.
+pub fn begin<P: AsRef<Path>>(
+    manifest_path: Option<&Path>,
+    command: &'static str,
+    inputs: &[P],
+    selection: Option<&str>,
+) -> Result<Option<Operation>> {
+    let Some(manifest_path) = manifest_path else {
+        return Ok(None);
+    };
+    let inputs = inputs
+        .iter()
+        .map(|input| FileRecord::of(input.as_ref()))
+        .collect::<Result<_>>()?;
+    Ok(Some(Operation {
+        manifest_path: manifest_path.to_path_buf(),
+        manifest: Manifest {
+            command,
+            inputs,
+            selection: selection.map(str::to_string),
+            outputs: Vec::new(),
+        },
+    }))
+}
+
+/// Record the files `operation` wrote and write its manifest.
+pub fn finish<P: AsRef<Path>>(operation: Option<Operation>, outputs: &[P]) -> Result<()> {
+    let Some(mut operation) = operation else {
+        return Ok(());
+    };
+    for output in outputs {
+        operation
+            .manifest
+            .outputs
+            .push(FileRecord::of(output.as_ref())?);
+    }
+    let mut json = serde_json::to_string_pretty(&operation.manifest)?;
+    json.push('\n');
+    write_atomic(&operation.manifest_path, json.as_bytes())?;
+    Ok(())
+}
+
+impl FileRecord {
+    fn of(path: &Path) -> Result<FileRecord> {
+        let (bytes, sha256) = sha256_file(path)?;
+        let pages = PdfDocument::open(path).ok().map(|doc| doc.page_count());
+        Ok(FileRecord {
+            path: path.display().to_string(),
+            bytes,
+            sha256,
+            pages,
+        })
+    }
+}
+
+/// A file's length and SHA-256 (lowercase hex).
+pub(crate) fn sha256_file(path: &Path) -> Result<(u64, String)> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let bytes = std::io::copy(&mut file, &mut hasher)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok((bytes, format!("{:x}", hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_records_inputs_before_they_change() {
+        let dir = std::env::temp_dir().join(format!("inpdf-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("notes.txt");
+        let manifest_path = dir.join("ops.json");
+        std::fs::write(&input, "abc").unwrap();
+
+        let operation = begin(Some(&manifest_path), "test", &[&input], Some("1-2")).unwrap();
+        std::fs::write(&input, "changed").unwrap();
+        finish(operation, &[&input]).unwrap();
+
+        let manifest: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest["command"], "test");
+        assert_eq!(manifest["selection"], "1-2");
+        assert_eq!(
+            manifest["inputs"][0]["sha256"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(manifest["inputs"][0]["pages"], serde_json::Value::Null);
+        assert_eq!(manifest["outputs"][0]["bytes"], 7);
+
+        assert!(begin(None, "test", &[&input], None).unwrap().is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,44 @@
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::marks::{add_marks, Marks};
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+/// A kind of printer's mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Mark {
+    /// Lines at each corner showing where to trim
+    Crop,
+    /// Targets centered on each edge for aligning the ink separations
+    Registration,
+}
+
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    kinds: &[Mark],
+    bleed: f32,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let marks = Marks {
+        crop: kinds.contains(&Mark::Crop),
+        registration: kinds.contains(&Mark::Registration),
+        bleed,
+    };
+
+    let doc = PdfDocument::open(&input)?;
+    let mut new_doc = doc.doc().clone();
+    let page_ids: Vec<_> = new_doc.get_pages().into_values().collect();
+    for &page_id in &page_ids {
+        add_marks(&mut new_doc, page_id, &marks)?;
+    }
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Added marks to {} page(s) in {}",
+        page_ids.len(),
+        output.as_ref().display()
+    );
+    Ok(())
+}
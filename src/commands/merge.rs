@@ -1,81 +1,357 @@
+use crate::commands::{
+    apply_metadata, format_size, output_size, report_links, status, CopyOptions,
+};
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::document::{format_date_utc, write_atomic, SaveOptions};
+use crate::pdf::import::Importer;
+use crate::pdf::layout::PageSize;
+use crate::pdf::links::{fix_links, LinkFixup, LinkSource};
+use crate::pdf::pages::media_box;
+use crate::pdf::typeset::{typeset, Block, TypesetOptions};
+use crate::pdf::PdfDocument;
 use anyhow::{Context, Result};
-use lopdf::Document;
-use std::path::Path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
-pub fn run<P: AsRef<Path>>(inputs: &[P], output: P) -> Result<()> {
+/// Pages inserted before each input document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Separator {
+    /// A page with the input's file name, page count, and modification date
+    TitlePage,
+}
+
+/// Where to report which output page each input page became.
+pub struct MapOptions {
+    /// Print the mapping after merging.
+    pub print: bool,
+    /// Write the mapping as JSON to this file.
+    pub json: Option<PathBuf>,
+}
+
+/// Where one input's pages ended up in the merged file.
+#[derive(Debug, PartialEq, Serialize)]
+struct InputMap {
+    source: String,
+    /// Output pages of the separator inserted before this input, if any.
+    separator_pages: Vec<usize>,
+    /// Output page of each source page; `pages[0]` is source page 1.
+    pages: Vec<PageMap>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct PageMap {
+    source_page: usize,
+    output_page: usize,
+}
+
+#[derive(Serialize)]
+struct MergeMap<'a> {
+    output: String,
+    page_count: usize,
+    inputs: &'a [InputMap],
+}
+
+pub fn run<P: AsRef<Path>>(
+    inputs: &[P],
+    output: P,
+    separators: Option<Separator>,
+    map: &MapOptions,
+    copy: &CopyOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
     if inputs.is_empty() {
         anyhow::bail!("No input files specified");
     }
 
-    if inputs.len() == 1 {
-        // Just copy the single file
-        std::fs::copy(&inputs[0], &output).with_context(|| {
-            format!(
-                "Failed to copy {} to {}",
-                inputs[0].as_ref().display(),
-                output.as_ref().display()
-            )
-        })?;
-        println!("Copied 1 file to {}", output.as_ref().display());
+    let edits_metadata = copy.metadata.copy_metadata.is_some()
+        || copy.metadata.set_title.is_some()
+        || copy.metadata.clear_metadata;
+    if inputs.len() == 1
+        && separators.is_none()
+        && !map.print
+        && map.json.is_none()
+        && !edits_metadata
+        && *save_options == SaveOptions::default()
+    {
+        // Just copy the single file, unchanged
+        if copy.dry_run {
+            let input = inputs[0].as_ref();
+            let size = std::fs::metadata(input)
+                .with_context(|| format!("Failed to read {}", input.display()))?
+                .len();
+            println!(
+                "Would copy 1 file to {} ({})",
+                output.as_ref().display(),
+                format_size(size)
+            );
+            return Ok(());
+        }
+        let input = inputs[0].as_ref();
+        let bytes =
+            std::fs::read(input).with_context(|| format!("Failed to read {}", input.display()))?;
+        write_atomic(output.as_ref(), &bytes)?;
+        status!("Copied 1 file to {}", output.as_ref().display());
         return Ok(());
     }
 
-    // Load first document as base
-    let mut merged = Document::load(&inputs[0])
-        .with_context(|| format!("Failed to load PDF: {}", inputs[0].as_ref().display()))?;
-
-    let mut total_pages = merged.get_pages().len();
-
-    // Merge remaining documents by copying their pages
-    for input in &inputs[1..] {
-        let doc = Document::load(input)
-            .with_context(|| format!("Failed to load PDF: {}", input.as_ref().display()))?;
-
-        let pages = doc.get_pages().len();
-        total_pages += pages;
-
-        // Get the page count and merge objects
-        for (_, page_id) in doc.get_pages() {
-            // Renumber object IDs to avoid conflicts
-            let new_id = (merged.max_id + 1, 0);
-            merged.max_id += 1;
-
-            // Copy the page object
-            if let Ok(page_obj) = doc.get_object(page_id) {
-                merged.objects.insert(new_id, page_obj.clone());
-
-                // Add to pages tree (simplified - may not work for all PDFs)
-                if let Ok(catalog) = merged.catalog() {
-                    if let Ok(pages_ref) = catalog.get(b"Pages") {
-                        if let lopdf::Object::Reference(pages_id) = pages_ref {
-                            if let Ok(pages_dict) = merged.get_dictionary_mut(*pages_id) {
-                                if let Ok(lopdf::Object::Array(kids)) = pages_dict.get_mut(b"Kids")
-                                {
-                                    kids.push(lopdf::Object::Reference(new_id));
-                                }
-                                if let Ok(lopdf::Object::Integer(count)) =
-                                    pages_dict.get_mut(b"Count")
-                                {
-                                    *count += 1;
-                                }
-                            }
-                        }
+    let mut builder = DocumentBuilder::new();
+    let mut total_pages = 0;
+    let mut separator_pages = 0;
+    let mut input_maps = Vec::new();
+    let mut fixup = LinkFixup::default();
+
+    for input in inputs {
+        let input = input.as_ref();
+        let doc = PdfDocument::open(input)?;
+        let page_ids = doc.page_ids();
+
+        // Keep the newest header version so features of any input stay valid.
+        if doc.doc().version > builder.doc_mut().version {
+            builder.doc_mut().version = doc.doc().version.clone();
+        }
+
+        let start = total_pages + separator_pages;
+        if separators == Some(Separator::TitlePage) {
+            // Match the document's own page size so binders stay uniform.
+            let page_size = page_ids
+                .first()
+                .map(|&(_, id)| {
+                    let [llx, lly, urx, ury] = media_box(doc.doc(), id);
+                    PageSize {
+                        width: urx - llx,
+                        height: ury - lly,
                     }
-                }
-            }
+                })
+                .unwrap_or(PageSize::LETTER);
+            let options = TypesetOptions {
+                page_size,
+                margin: 72.0_f32.min(page_size.width.min(page_size.height) / 8.0),
+                ..Default::default()
+            };
+            separator_pages +=
+                typeset(&mut builder, &title_page(input, page_ids.len())?, &options)?;
         }
+
+        let first = total_pages + separator_pages;
+        let mut importer = Importer::new(doc.doc());
+        let mut imported = Vec::with_capacity(page_ids.len());
+        for &(_, page_id) in &page_ids {
+            let new_id = importer.import_page(builder.doc_mut(), page_id)?;
+            builder.push_page(new_id)?;
+            imported.push((first as u32 + imported.len() as u32 + 1, new_id));
+        }
+        // Page references were remapped by the import; named destinations
+        // stay behind in the input's catalog and need resolving here.
+        let source = LinkSource {
+            doc: doc.doc(),
+            ids: importer.id_map(),
+        };
+        fixup.extend(fix_links(
+            builder.doc_mut(),
+            &imported,
+            Some(&source),
+            copy.links.dangling(),
+        ));
+        total_pages += page_ids.len();
+        input_maps.push(input_map(input, start, first, page_ids.len()));
     }
 
-    merged
-        .save(&output)
-        .with_context(|| format!("Failed to save merged PDF: {}", output.as_ref().display()))?;
+    let mut merged = builder.finish()?;
+    apply_metadata(&mut merged, copy.metadata)?;
+    let (verb, size) = if copy.dry_run {
+        let size = output_size(&mut merged, save_options)?;
+        ("Would merge", format!(" ({})", format_size(size)))
+    } else {
+        PdfDocument::save_with(&mut merged, &output, save_options)?;
+        ("Merged", String::new())
+    };
 
-    println!(
-        "Merged {} files ({} pages) into {}",
+    let pages = if separator_pages > 0 {
+        format!("{} pages, {} separator pages", total_pages, separator_pages)
+    } else {
+        format!("{} pages", total_pages)
+    };
+    let summary = format!(
+        "{} {} files ({}) into {}{}",
+        verb,
         inputs.len(),
-        total_pages,
-        output.as_ref().display()
+        pages,
+        output.as_ref().display(),
+        size
     );
+    // A dry run's plan is its output; a real merge's summary is status.
+    if copy.dry_run {
+        println!("{}", summary);
+    } else {
+        status!("{}", summary);
+    }
+
+    report_links(&fixup, output.as_ref());
+
+    if map.print {
+        for input_map in &input_maps {
+            print_input_map(input_map);
+        }
+    }
+    if let Some(json_path) = &map.json {
+        let merge_map = MergeMap {
+            output: output.as_ref().display().to_string(),
+            page_count: total_pages + separator_pages,
+            inputs: &input_maps,
+        };
+        if copy.dry_run {
+            println!("Would write page map to {}", json_path.display());
+            return Ok(());
+        }
+        let mut json = serde_json::to_string_pretty(&merge_map)?;
+        json.push('\n');
+        std::fs::write(json_path, json)
+            .with_context(|| format!("Failed to write {}", json_path.display()))?;
+        status!("Wrote page map to {}", json_path.display());
+    }
 
     Ok(())
 }
+
+/// The map for an input appended after `start` output pages, whose own
+/// pages follow `first` output pages (any pages between are its separator).
+fn input_map(input: &Path, start: usize, first: usize, page_count: usize) -> InputMap {
+    InputMap {
+        source: input.display().to_string(),
+        separator_pages: (start + 1..=first).collect(),
+        pages: (1..=page_count)
+            .map(|source_page| PageMap {
+                source_page,
+                output_page: first + source_page,
+            })
+            .collect(),
+    }
+}
+
+fn print_input_map(map: &InputMap) {
+    let (Some(first), Some(last)) = (map.pages.first(), map.pages.last()) else {
+        println!("{}: no pages", map.source);
+        return;
+    };
+    print!(
+        "{}: pages {}-{} -> {}-{}",
+        map.source, first.source_page, last.source_page, first.output_page, last.output_page
+    );
+    if let (Some(first), Some(last)) = (map.separator_pages.first(), map.separator_pages.last()) {
+        if first == last {
+            print!(" (separator on {})", first);
+        } else {
+            print!(" (separator on {}-{})", first, last);
+        }
+    }
+    println!();
+}
+
+/// Blocks for a separator page describing one input file.
+fn title_page(input: &Path, page_count: usize) -> Result<Vec<Block>> {
+    let name = input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| input.display().to_string());
+
+    let mut blocks = vec![
+        Block::Heading {
+            level: 1,
+            text: name,
+        },
+        Block::Paragraph(format!(
+            "{} page{}",
+            page_count,
+            if page_count == 1 { "" } else { "s" }
+        )),
+    ];
+
+    let modified = std::fs::metadata(input)
+        .and_then(|meta| meta.modified())
+        .with_context(|| format!("Failed to read metadata for {}", input.display()))?;
+    if let Ok(since_epoch) = modified.duration_since(UNIX_EPOCH) {
+        blocks.push(Block::Paragraph(format!(
+            "Modified {}",
+            format_date_utc(since_epoch.as_secs() as i64)
+        )));
+    }
+
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::MetadataArgs;
+    use crate::commands::LinkPolicy;
+    use crate::pdf::testing::text_document;
+    use lopdf::{Document, Object};
+
+    #[test]
+    fn test_single_input_applies_save_options() {
+        let dir = std::env::temp_dir().join(format!("inpdf-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("in.pdf");
+        let output = dir.join("out.pdf");
+
+        let (mut doc, _) = text_document(&["one"]);
+        let id = Object::string_literal("abc");
+        doc.trailer.set("ID", vec![id.clone(), id]);
+        doc.save(&input).unwrap();
+
+        let map = MapOptions {
+            print: false,
+            json: None,
+        };
+        let metadata = MetadataArgs {
+            copy_metadata: None,
+            set_title: None,
+            clear_metadata: false,
+        };
+        let copy = CopyOptions {
+            links: LinkPolicy::Flag,
+            metadata: &metadata,
+            dry_run: false,
+        };
+        let options = SaveOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        run(&[&input], &output, None, &map, &copy, &options).unwrap();
+
+        // The output was rewritten with a content-derived ID, not copied.
+        let merged = Document::load(&output).unwrap();
+        let ids = merged.trailer.get(b"ID").unwrap().as_array().unwrap();
+        assert_eq!(ids[0], ids[1]);
+        assert_ne!(ids[0], Object::string_literal("abc"));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_input_map() {
+        // A 3-page input after 10 pages, with a 1-page separator.
+        let map = input_map(Path::new("b.pdf"), 10, 11, 3);
+        assert_eq!(map.separator_pages, [11]);
+        assert_eq!(
+            map.pages,
+            [
+                PageMap {
+                    source_page: 1,
+                    output_page: 12
+                },
+                PageMap {
+                    source_page: 2,
+                    output_page: 13
+                },
+                PageMap {
+                    source_page: 3,
+                    output_page: 14
+                },
+            ]
+        );
+        assert!(input_map(Path::new("a.pdf"), 0, 0, 2)
+            .separator_pages
+            .is_empty());
+    }
+}
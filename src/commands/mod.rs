@@ -1,6 +1,286 @@
+pub mod a11y;
+pub mod annotations;
+pub mod bench;
+pub mod boxes;
+pub mod browse;
+pub mod classify;
+pub mod collection_stats;
+pub mod colors;
+pub mod completions;
+pub mod contact_sheet;
+pub mod debug;
+pub mod deskew_orientation;
+pub mod diff;
+pub mod duplicate;
+pub mod embed_fonts;
 pub mod extract;
+pub mod figures;
+pub mod fingerprint;
+pub mod form;
+pub mod from_images;
+pub mod from_text;
 pub mod grep;
+pub mod index;
 pub mod info;
+pub mod linearize;
+pub mod manifest;
+pub mod marks;
 pub mod merge;
+pub mod ocr;
+pub mod outline_path;
+pub mod overlay;
+pub mod pii;
+pub mod profiles;
+pub mod read_after;
+pub mod read_pages;
+pub mod redact;
+pub mod reorder;
+pub mod replace;
+pub mod search;
 pub mod split;
+pub mod textify;
+pub mod to_svg;
 pub mod toc;
+pub mod verify;
+pub mod versions;
+
+use crate::cli::{InPlaceArgs, MetadataArgs};
+use crate::pdf::document::SaveOptions;
+use crate::pdf::links::{DanglingLinks, LinkFixup};
+use crate::pdf::metadata::MetadataPolicy;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use lopdf::Document;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When to color output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Apply the global `--quiet` and `--color` flags.
+pub fn configure_output(quiet: bool, color: ColorChoice) {
+    let color = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+        }
+    };
+    QUIET.store(quiet, Ordering::Relaxed);
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+pub(crate) fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Whether to color what goes to stdout.
+pub(crate) fn color() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+/// Print a status line ("Wrote x.pdf") to stderr, unless `--quiet` was given.
+///
+/// Status goes to stderr so that stdout only carries a command's data.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::commands::quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// What to do with links to pages an output leaves out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LinkPolicy {
+    /// Remove the links
+    Drop,
+    /// Keep the links and warn about them
+    Flag,
+}
+
+impl LinkPolicy {
+    pub(crate) fn dangling(self) -> DanglingLinks {
+        match self {
+            LinkPolicy::Drop => DanglingLinks::Drop,
+            LinkPolicy::Flag => DanglingLinks::Flag,
+        }
+    }
+}
+
+/// How `extract` and `merge` carry links and metadata into their output,
+/// and whether they only report what they would write.
+pub struct CopyOptions<'a> {
+    pub links: LinkPolicy,
+    pub metadata: &'a MetadataArgs,
+    pub dry_run: bool,
+}
+
+/// Decide where a command that rewrites a PDF should write its result.
+///
+/// With `--in-place` this is the input itself, which is first copied to a
+/// backup if `--backup SUFFIX` was given; the rewrite then replaces it
+/// atomically via [`crate::pdf::document::write_atomic`].
+pub fn output_path(input: &Path, output: Option<&Path>, in_place: &InPlaceArgs) -> Result<PathBuf> {
+    if !in_place.in_place {
+        return output
+            .map(Path::to_path_buf)
+            .context("An output path (-o) or --in-place is required");
+    }
+
+    if let Some(suffix) = &in_place.backup {
+        let mut backup = input.as_os_str().to_owned();
+        backup.push(suffix);
+        std::fs::copy(input, &backup).with_context(|| {
+            format!(
+                "Failed to back up {} to {}",
+                input.display(),
+                Path::new(&backup).display()
+            )
+        })?;
+    }
+
+    Ok(input.to_path_buf())
+}
+
+/// Say what fixing the links of pages taken from or written to `path`
+/// changed, if anything.
+pub(crate) fn report_links(fixup: &LinkFixup, path: &Path) {
+    if fixup.rewritten > 0 {
+        status!(
+            "Rewrote {} link(s) that used named destinations",
+            fixup.rewritten
+        );
+    }
+    if fixup.dropped > 0 {
+        status!(
+            "Dropped {} link(s) to pages that were left out",
+            fixup.dropped
+        );
+    }
+    if !fixup.flagged.is_empty() {
+        let pages: Vec<String> = fixup.flagged.iter().map(u32::to_string).collect();
+        eprintln!(
+            "Warning: links on page(s) {} of {} point to pages that were left out",
+            pages.join(", "),
+            path.display()
+        );
+    }
+}
+
+/// Set an output's metadata as `--copy-metadata`, `--set-title`, and
+/// `--clear-metadata` ask, and stamp its ModDate and Producer.
+pub(crate) fn apply_metadata(doc: &mut Document, args: &MetadataArgs) -> Result<()> {
+    let source = args
+        .copy_metadata
+        .as_deref()
+        .map(PdfDocument::open)
+        .transpose()?;
+    let policy = MetadataPolicy {
+        copy_from: source.as_ref().map(PdfDocument::doc),
+        clear: args.clear_metadata,
+        title: args.set_title.clone(),
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64);
+    crate::pdf::metadata::apply_metadata(doc, &policy, now);
+    Ok(())
+}
+
+/// How many bytes `doc` would take if saved with `save_options`, for
+/// `--dry-run`. Linearization (which needs qpdf) is left out of the estimate.
+pub(crate) fn output_size(doc: &mut Document, save_options: &SaveOptions) -> Result<u64> {
+    let options = SaveOptions {
+        linearize: false,
+        ..save_options.clone()
+    };
+    Ok(PdfDocument::to_bytes_with(doc, &options)?.len() as u64)
+}
+
+/// "512 bytes", "12.3 KB", "4.1 MB".
+pub(crate) fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{} bytes", bytes),
+        1_000..=999_999 => format!("{:.1} KB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+/// "1-3,7" for [1, 2, 3, 7].
+pub(crate) fn compact_pages(pages: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &page in pages {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == page => *end = page,
+            _ => ranges.push((page, page)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Append a CSV record (RFC 4180), quoting fields that need it.
+pub(crate) fn push_csv_row(out: &mut String, fields: &[&str]) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains(['"', ',', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_pages_and_format_size() {
+        assert_eq!(compact_pages(&[1, 2, 3, 7, 9, 10]), "1-3,7,9-10");
+        assert_eq!(compact_pages(&[5, 4]), "5,4");
+        assert_eq!(format_size(512), "512 bytes");
+        assert_eq!(format_size(12_345), "12.3 KB");
+        assert_eq!(format_size(4_100_000), "4.1 MB");
+    }
+
+    #[test]
+    fn test_push_csv_row_quotes_when_needed() {
+        let mut csv = String::new();
+        push_csv_row(
+            &mut csv,
+            &["a.pdf", "Smith, Ada", "say \"hi\"", "two\nlines", ""],
+        );
+        assert_eq!(
+            csv,
+            "a.pdf,\"Smith, Ada\",\"say \"\"hi\"\"\",\"two\nlines\",\r\n"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use crate::commands::status;
+use crate::page_range::expand_page_ranges;
+use crate::pdf::ocr::{ocr_page, OcrOptions, OcrOutput};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Format of OCR results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OcrFormat {
+    /// Plain text
+    Text,
+    /// ALTO XML, with word coordinates and confidences
+    Alto,
+    /// hOCR (HTML), with word coordinates and confidences
+    Hocr,
+    /// Tab-separated words with coordinates and confidences
+    Tsv,
+}
+
+impl OcrFormat {
+    fn output(self) -> OcrOutput {
+        match self {
+            OcrFormat::Text => OcrOutput::Text,
+            OcrFormat::Alto => OcrOutput::Alto,
+            OcrFormat::Hocr => OcrOutput::Hocr,
+            OcrFormat::Tsv => OcrOutput::Tsv,
+        }
+    }
+}
+
+/// OCR `pages` (default: all) and print the results, or with `output_dir`,
+/// write one `page-NNNN.<ext>` file per page.
+pub fn run(
+    path: &Path,
+    pages: Option<&str>,
+    format: OcrFormat,
+    output_dir: Option<&Path>,
+    options: &OcrOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(path)?;
+    let page_list = expand_page_ranges(pages.unwrap_or("1-end"), doc.page_count())?;
+    let output = format.output();
+
+    if let Some(dir) = output_dir {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    }
+    for &page in &page_list {
+        let result = ocr_page(path, page, output, options)?;
+        match output_dir {
+            Some(dir) => {
+                let file = dir.join(format!("page-{:04}.{}", page, output.extension()));
+                std::fs::write(&file, result)
+                    .with_context(|| format!("Failed to write {}", file.display()))?;
+            }
+            None => {
+                println!("--- Page {} ---", page);
+                println!("{}", result.trim_end());
+            }
+        }
+    }
+
+    if let Some(dir) = output_dir {
+        status!(
+            "Wrote OCR results for {} page(s) to {}",
+            page_list.len(),
+            dir.display()
+        );
+    }
+    Ok(())
+}
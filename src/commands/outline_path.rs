@@ -0,0 +1,24 @@
+use crate::pdf::toc::{extract_toc, outline_path};
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P, page: u32) -> Result<()> {
+    let total_pages = PdfDocument::open(&path)?.page_count();
+    if page == 0 || page > total_pages {
+        anyhow::bail!("Page {} is out of range (1-{})", page, total_pages);
+    }
+
+    let entries = extract_toc(&path)?;
+    let chain = outline_path(&entries, page);
+
+    if chain.is_empty() {
+        println!("Page {} is not inside any outline entry.", page);
+        return Ok(());
+    }
+
+    let titles: Vec<_> = chain.iter().map(|e| e.title.as_str()).collect();
+    println!("{}", titles.join(" > "));
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::xobject::{overlay_document, Layer};
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(
+    input: P,
+    under: Option<&Path>,
+    over: Option<&Path>,
+    output: &Path,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    if under.is_none() && over.is_none() {
+        anyhow::bail!("Specify --under and/or --over");
+    }
+
+    let doc = PdfDocument::open(&input)?;
+    let mut new_doc = doc.doc().clone();
+
+    for (overlay_path, layer) in [(under, Layer::Under), (over, Layer::Over)] {
+        let Some(overlay_path) = overlay_path else {
+            continue;
+        };
+        let overlay = PdfDocument::open(overlay_path)?;
+        let modified = overlay_document(&mut new_doc, overlay.doc(), layer)?;
+        status!(
+            "Placed {} {} {} page(s)",
+            overlay_path.display(),
+            if layer == Layer::Under {
+                "under"
+            } else {
+                "over"
+            },
+            modified
+        );
+    }
+
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+    status!("Wrote {}", output.display());
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use crate::commands::status;
+use crate::pdf::ocr::{grep_ocr, OcrOptions};
+use crate::pdf::pii::{builtin_patterns, classify, combined_regex, PiiPattern};
+use crate::pdf::text::{grep_cached, PageTextProvider};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Output format for PII reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum PiiFormat {
+    /// One line per item: page, line, type, and value
+    Text,
+    /// A report `inpdf redact --from-report` can apply after review
+    Json,
+}
+
+pub struct PiiOptions {
+    /// TOML file with extra patterns, checked before the built-in ones.
+    pub patterns: Option<PathBuf>,
+    /// OCR pages with no extractable text and scan the result too.
+    pub ocr: Option<OcrOptions>,
+    pub format: PiiFormat,
+    /// Write the report here instead of stdout.
+    pub output: Option<PathBuf>,
+}
+
+/// Extra patterns, as in
+///
+/// ```toml
+/// [[patterns]]
+/// kind = "employee_id"
+/// regex = 'EMP-\d{6}'
+/// ```
+#[derive(Deserialize)]
+struct PatternFile {
+    #[serde(default)]
+    patterns: Vec<PatternEntry>,
+}
+
+#[derive(Deserialize)]
+struct PatternEntry {
+    kind: String,
+    regex: String,
+}
+
+/// Personal data found in a PDF.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PiiReport {
+    pub path: String,
+    pub items: Vec<PiiItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PiiItem {
+    pub id: usize,
+    pub kind: String,
+    pub value: String,
+    pub page: u32,
+    pub line: u32,
+    /// Character offsets of the value in the page's text (for OCR items,
+    /// the recognized lines joined by newlines).
+    pub start: u32,
+    pub end: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ocr_confidence: Option<f32>,
+    /// Whether redaction should remove the item; set to false in review to
+    /// keep it.
+    #[serde(default = "approved_by_default")]
+    pub approved: bool,
+}
+
+fn approved_by_default() -> bool {
+    true
+}
+
+/// Scan `path` for email addresses, phone numbers, IBANs, card numbers, and
+/// any custom patterns, and report each hit's type, value, and position.
+pub fn run<P: AsRef<Path>>(path: P, options: &PiiOptions) -> Result<()> {
+    let path = path.as_ref();
+    let mut patterns = match &options.patterns {
+        Some(file) => load_patterns(file)?,
+        None => Vec::new(),
+    };
+    patterns.extend(builtin_patterns());
+    let report = scan(path, &patterns, options.ocr.as_ref())?;
+
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for item in &report.items {
+        *counts.entry(&item.kind).or_default() += 1;
+    }
+
+    let output = match options.format {
+        PiiFormat::Text => format_text(&report),
+        PiiFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&report)?;
+            json.push('\n');
+            json
+        }
+    };
+    match &options.output {
+        Some(out) => {
+            std::fs::write(out, output)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+            println!("Wrote {} item(s) to {}", report.items.len(), out.display());
+        }
+        None => print!("{}", output),
+    }
+
+    if report.items.is_empty() {
+        status!("No personal data found.");
+    } else {
+        let summary: Vec<String> = counts
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        status!("Found {}", summary.join(", "));
+    }
+    Ok(())
+}
+
+fn load_patterns(file: &Path) -> Result<Vec<PiiPattern>> {
+    let text = std::fs::read_to_string(file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let parsed: PatternFile =
+        toml::from_str(&text).with_context(|| format!("Failed to parse {}", file.display()))?;
+    parsed
+        .patterns
+        .iter()
+        .map(|entry| PiiPattern::new(&entry.kind, &entry.regex))
+        .collect()
+}
+
+fn scan(path: &Path, patterns: &[PiiPattern], ocr: Option<&OcrOptions>) -> Result<PiiReport> {
+    let regex = combined_regex(patterns)?;
+    let doc = PdfDocument::open_for_text(path)?;
+    let mut matches = grep_cached(doc.cached(), &regex, usize::MAX)?;
+    if let Some(ocr) = ocr {
+        // Scans have no text layer; everything else was searched above.
+        let scanned: Vec<u32> = PageTextProvider::new(doc.cached())
+            .iter()
+            .filter(|page| page.error.is_none() && page.text.trim().is_empty())
+            .map(|page| page.page)
+            .collect();
+        if !scanned.is_empty() {
+            matches.extend(grep_ocr(path, &scanned, &regex, usize::MAX, None, ocr)?);
+            matches.sort_by_key(|m| m.page);
+        }
+    }
+
+    let mut items = Vec::new();
+    for m in matches {
+        let value = &m.text[m.match_start as usize..m.match_end as usize];
+        let Some(pattern) = classify(patterns, value) else {
+            continue;
+        };
+        items.push(PiiItem {
+            id: items.len() + 1,
+            kind: pattern.kind.clone(),
+            value: value.to_string(),
+            page: m.page,
+            line: m.line_number,
+            start: m.page_start,
+            end: m.page_end,
+            ocr_confidence: m.ocr_confidence,
+            approved: true,
+        });
+    }
+    Ok(PiiReport {
+        path: path.display().to_string(),
+        items,
+    })
+}
+
+fn format_text(report: &PiiReport) -> String {
+    let mut out = String::new();
+    for item in &report.items {
+        let ocr = match item.ocr_confidence {
+            Some(confidence) => format!(" [ocr {:.0}%]", confidence),
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "p{}:L{}{} {}: {}\n",
+            item.page, item.line, ocr, item.kind, item.value
+        ));
+    }
+    out
+}
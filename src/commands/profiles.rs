@@ -0,0 +1,154 @@
+use crate::pdf::cleanup::{ExtractSettings, PROFILES};
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Extraction profiles in the config file, e.g.
+///
+/// ```toml
+/// [profiles.statements]
+/// base = "invoice"
+/// boilerplate = true
+///
+/// [profiles.academic]
+/// footnotes = "strip"
+/// ```
+///
+/// A profile named like a built-in one replaces it; unset fields come from
+/// `base`, or from the built-in profile of the same name.
+#[derive(Deserialize, Default)]
+struct ConfigFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, ProfileEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfileEntry {
+    base: Option<String>,
+    columns: Option<bool>,
+    boilerplate: Option<bool>,
+    footnotes: Option<String>,
+    tables: Option<String>,
+}
+
+/// `$INPDF_CONFIG`, or `inpdf/config.toml` in the user's config directory.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = std::env::var_os("INPDF_CONFIG") {
+        return PathBuf::from(path);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("inpdf")
+        .join("config.toml")
+}
+
+fn load_config(path: &Path) -> Result<ConfigFile> {
+    if !path.exists() {
+        return Ok(ConfigFile::default());
+    }
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// The settings of the profile `name`, from the config file or built in.
+pub fn resolve_profile(name: &str) -> Result<ExtractSettings> {
+    let path = config_path();
+    let config = load_config(&path)?;
+    resolve(&config, name, &mut Vec::new())
+        .with_context(|| format!("Profiles are read from {}", path.display()))
+}
+
+fn resolve(config: &ConfigFile, name: &str, seen: &mut Vec<String>) -> Result<ExtractSettings> {
+    let Some(entry) = config.profiles.get(name) else {
+        return match ExtractSettings::profile(name) {
+            Some(settings) => Ok(settings),
+            None => {
+                let mut known: Vec<&str> = PROFILES.to_vec();
+                known.extend(
+                    config
+                        .profiles
+                        .keys()
+                        .map(String::as_str)
+                        .filter(|name| !PROFILES.contains(name)),
+                );
+                bail!("Unknown profile '{}' (known: {})", name, known.join(", "))
+            }
+        };
+    };
+    if seen.iter().any(|s| s == name) {
+        bail!("Profile '{}' is its own base", name);
+    }
+    seen.push(name.to_string());
+
+    let mut settings = match &entry.base {
+        Some(base) => resolve(config, base, seen)?,
+        // Overriding a built-in profile only changes what it sets.
+        None => ExtractSettings::profile(name).unwrap_or_default(),
+    };
+    if let Some(columns) = entry.columns {
+        settings.columns = columns;
+    }
+    if let Some(boilerplate) = entry.boilerplate {
+        settings.boilerplate = boilerplate;
+    }
+    if let Some(footnotes) = &entry.footnotes {
+        settings.footnotes = footnotes
+            .parse()
+            .map_err(|e| anyhow::anyhow!("profile '{}': {}", name, e))?;
+    }
+    if let Some(tables) = &entry.tables {
+        settings.tables = tables
+            .parse()
+            .map_err(|e| anyhow::anyhow!("profile '{}': {}", name, e))?;
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::cleanup::{Footnotes, Tables};
+
+    fn config(text: &str) -> ConfigFile {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn test_config_profiles() {
+        let config = config(
+            r#"
+            [profiles.statements]
+            base = "invoice"
+            boilerplate = true
+
+            [profiles.academic]
+            footnotes = "strip"
+
+            [profiles.loop]
+            base = "loop"
+            "#,
+        );
+
+        let statements = resolve(&config, "statements", &mut Vec::new()).unwrap();
+        assert!(statements.boilerplate);
+        assert_eq!(statements.tables, Tables::Tsv);
+
+        let academic = resolve(&config, "academic", &mut Vec::new()).unwrap();
+        assert!(academic.columns);
+        assert_eq!(academic.footnotes, Footnotes::Strip);
+
+        assert_eq!(
+            resolve(&config, "book", &mut Vec::new()).unwrap(),
+            ExtractSettings::profile("book").unwrap()
+        );
+        assert!(resolve(&config, "loop", &mut Vec::new()).is_err());
+        let err = resolve(&config, "poetry", &mut Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("statements"));
+    }
+}
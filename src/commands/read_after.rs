@@ -0,0 +1,48 @@
+use crate::commands::read_pages::{self, ReadPagesOptions};
+use crate::pdf::toc::{extract_toc_from_doc, find_heading};
+use crate::pdf::PdfDocument;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Read the page an outline heading points at and the `following` pages
+/// after it, for when a section's end isn't known.
+pub fn run<P: AsRef<Path>>(
+    path: P,
+    heading: &str,
+    following: u32,
+    options: &ReadPagesOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+    let doc = match options.at_version {
+        Some(_) => PdfDocument::open_at_version(path, options.at_version)?,
+        None => PdfDocument::open_for_text(path)?,
+    };
+    let doc = doc.select_portfolio_item(options.portfolio_item.as_deref())?;
+    let toc = extract_toc_from_doc(doc.doc())?;
+    if toc.is_empty() {
+        bail!("No table of contents found, so headings can't be located; use read-pages instead");
+    }
+
+    let found = find_heading(&toc, heading);
+    let Some(entry) = found.first() else {
+        bail!(
+            "No outline entry matches \"{}\"; run `inpdf toc` to list them",
+            heading
+        );
+    };
+    let start = entry
+        .page
+        .expect("find_heading only returns entries with pages");
+    if found.len() > 1 {
+        eprintln!(
+            "Warning: {} outline entries match \"{}\"; using \"{}\" on page {}",
+            found.len(),
+            heading,
+            entry.title,
+            start
+        );
+    }
+    let end = start.saturating_add(following).min(doc.page_count());
+
+    read_pages::run(path, &format!("{}-{}", start, end), options)
+}
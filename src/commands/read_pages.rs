@@ -0,0 +1,367 @@
+use crate::page_range::expand_page_ranges;
+use crate::pdf::cleanup::{self, ExtractSettings};
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::pages::{find_inherited, page_box, resolve};
+use crate::pdf::quality::{text_quality, TextQuality};
+use crate::pdf::text::{annotate_sections, extract_text_pages, PageText};
+use crate::pdf::toc::{extract_toc_from_doc, outline_path};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_TEMPLATE: &str = "{stem}-p{page:04}.txt";
+
+/// Output format for page text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReadFormat {
+    /// Page text under "--- Page N ---" separators
+    Text,
+    /// One record per page with its label, section, size, and text
+    Json,
+}
+
+pub struct ReadPagesOptions {
+    pub annotate_sections: bool,
+    /// Write all pages into this single file instead of stdout.
+    pub out: Option<PathBuf>,
+    /// Write one file per page into this directory.
+    pub out_dir: Option<PathBuf>,
+    /// File name template used with `out_dir`.
+    pub template: String,
+    /// Read the document as of an earlier revision (1-based).
+    pub at_version: Option<usize>,
+    /// Read a PDF packaged in the portfolio instead (by name or file name).
+    pub portfolio_item: Option<String>,
+    pub format: ReadFormat,
+    /// Cut each page's text to at most this many characters.
+    pub max_chars: Option<usize>,
+    /// Cleanup to apply to the extracted text.
+    pub profile: Option<ExtractSettings>,
+}
+
+impl Default for ReadPagesOptions {
+    fn default() -> Self {
+        ReadPagesOptions {
+            annotate_sections: false,
+            out: None,
+            out_dir: None,
+            template: DEFAULT_TEMPLATE.to_string(),
+            at_version: None,
+            portfolio_item: None,
+            format: ReadFormat::Text,
+            max_chars: None,
+            profile: None,
+        }
+    }
+}
+
+pub fn run<P: AsRef<Path>>(path: P, pages: &str, options: &ReadPagesOptions) -> Result<()> {
+    let path = path.as_ref();
+    if options.format == ReadFormat::Json {
+        if options.out_dir.is_some() {
+            anyhow::bail!("--format json writes a single document; use --out instead of --out-dir");
+        }
+        if options.annotate_sections {
+            anyhow::bail!(
+                "--annotate-sections only applies to text output; JSON records already \
+                 include the label and section"
+            );
+        }
+    }
+
+    let doc = match options.at_version {
+        Some(_) => PdfDocument::open_at_version(path, options.at_version)?,
+        None => PdfDocument::open_for_text(path)?,
+    };
+    let doc = doc.select_portfolio_item(options.portfolio_item.as_deref())?;
+    let total = doc.page_count();
+    let page_list = expand_page_ranges(pages, total)?;
+    let mut texts = extract_text_pages(&doc, &page_list)?;
+    if let Some(settings) = &options.profile {
+        cleanup::apply(settings, &mut texts);
+    }
+    let truncated: Vec<bool> = texts
+        .iter_mut()
+        .map(|t| {
+            options
+                .max_chars
+                .is_some_and(|max| truncate(&mut t.text, max))
+        })
+        .collect();
+    if options.annotate_sections {
+        annotate_sections(doc.doc(), &mut texts)?;
+    }
+    for page_text in &texts {
+        if let Some(error) = &page_text.error {
+            eprintln!("Warning: {}", error);
+        }
+    }
+
+    if let Some(out_dir) = &options.out_dir {
+        std::fs::create_dir_all(out_dir)
+            .with_context(|| format!("Failed to create directory: {}", out_dir.display()))?;
+
+        let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("page");
+        let labels = extract_page_labels_from_doc(doc.doc())?;
+
+        let mut written = 0;
+        for page_text in texts.iter().filter(|t| t.error.is_none()) {
+            let label = labels
+                .get(page_text.page as usize - 1)
+                .map(|l| l.logical_label.as_str())
+                .unwrap_or_default();
+            let name = render_template(&options.template, stem, page_text.page, label)?;
+            let output_path = out_dir.join(name);
+            std::fs::write(&output_path, &page_text.text)
+                .with_context(|| format!("Failed to write {}", output_path.display()))?;
+            written += 1;
+        }
+
+        println!("Wrote {} page(s) to {}", written, out_dir.display());
+    } else {
+        let output = match options.format {
+            ReadFormat::Text => format_pages(&texts, &truncated),
+            ReadFormat::Json => format_json(&doc, &texts, &truncated)?,
+        };
+        if let Some(out) = &options.out {
+            std::fs::write(out, output)
+                .with_context(|| format!("Failed to write {}", out.display()))?;
+            println!("Wrote {} page(s) to {}", texts.len(), out.display());
+        } else {
+            print!("{}", output);
+        }
+    }
+
+    Ok(())
+}
+
+/// Cut `text` to at most `max_chars` characters; returns whether it was cut.
+fn truncate(text: &mut String, max_chars: usize) -> bool {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => {
+            text.truncate(end);
+            true
+        }
+        None => false,
+    }
+}
+
+#[derive(Serialize)]
+struct PagesRecord<'a> {
+    path: &'a str,
+    page_count: u32,
+    pages: Vec<PageRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct PageRecord<'a> {
+    page: u32,
+    label: &'a str,
+    /// Innermost TOC entry containing the page.
+    section: Option<String>,
+    /// TOC entries containing the page, outermost first.
+    section_path: Vec<String>,
+    /// Displayed size in points (crop box, after rotation).
+    width: f32,
+    height: f32,
+    text: &'a str,
+    truncated: bool,
+    /// How usable the extracted text looks; see `pdf::quality`.
+    quality: QualityRecord,
+    /// The text was recovered from glyph names of fonts without a Unicode
+    /// mapping, and may be incomplete or wrong.
+    recovered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct QualityRecord {
+    /// 0 (garbage) to 1 (clean), or null for pages without text.
+    score: Option<f32>,
+    recognizable: f32,
+    replacement_chars: usize,
+    garbled: usize,
+}
+
+impl From<TextQuality> for QualityRecord {
+    fn from(quality: TextQuality) -> Self {
+        QualityRecord {
+            // Two decimals are plenty for a heuristic.
+            score: quality.score.map(|score| (score * 100.0).round() / 100.0),
+            recognizable: (quality.recognizable * 100.0).round() / 100.0,
+            replacement_chars: quality.replacement_chars,
+            garbled: quality.garbled,
+        }
+    }
+}
+
+fn format_json(doc: &PdfDocument, texts: &[PageText], truncated: &[bool]) -> Result<String> {
+    let pdf = doc.doc();
+    let toc = extract_toc_from_doc(pdf)?;
+    let labels = extract_page_labels_from_doc(pdf)?;
+    let page_ids = pdf.get_pages();
+
+    let mut pages = Vec::new();
+    for (page_text, &truncated) in texts.iter().zip(truncated) {
+        let page_id = page_ids[&page_text.page];
+        let crop = page_box(pdf, page_id, b"CropBox");
+        let rotate = find_inherited(pdf, page_id, b"Rotate")
+            .and_then(|obj| resolve(pdf, obj).as_i64().ok())
+            .unwrap_or(0);
+        let (mut width, mut height) = (crop[2] - crop[0], crop[3] - crop[1]);
+        if rotate.rem_euclid(180) == 90 {
+            std::mem::swap(&mut width, &mut height);
+        }
+        let section_path: Vec<String> = outline_path(&toc, page_text.page)
+            .into_iter()
+            .map(|entry| entry.title)
+            .collect();
+
+        pages.push(PageRecord {
+            page: page_text.page,
+            label: labels
+                .get(page_text.page as usize - 1)
+                .map(|l| l.logical_label.as_str())
+                .unwrap_or_default(),
+            section: section_path.last().cloned(),
+            section_path,
+            width,
+            height,
+            text: &page_text.text,
+            truncated,
+            quality: text_quality(&page_text.text).into(),
+            recovered: page_text.recovered,
+            error: page_text.error.as_deref(),
+        });
+    }
+    let mut json = serde_json::to_string_pretty(&PagesRecord {
+        path: &doc.path,
+        page_count: doc.page_count(),
+        pages,
+    })?;
+    json.push('\n');
+    Ok(json)
+}
+
+fn format_pages(texts: &[PageText], truncated: &[bool]) -> String {
+    let mut output = String::new();
+    for (page_text, &truncated) in texts.iter().zip(truncated) {
+        let _ = writeln!(output, "--- Page {} ---", page_text.page);
+        match &page_text.error {
+            Some(error) => {
+                let _ = writeln!(output, "[error: {}]", error);
+            }
+            None if truncated => {
+                let _ = writeln!(output, "{}\n[... truncated]", page_text.text);
+            }
+            None => {
+                let _ = writeln!(output, "{}", page_text.text);
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// Expand a per-page file name template.
+///
+/// Supports `{stem}`, `{page}`, and `{label}` placeholders. Numeric fields
+/// accept a zero-padded width, e.g. `{page:04}`.
+fn render_template(template: &str, stem: &str, page: u32, label: &str) -> Result<String> {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .map(|i| open + i)
+            .with_context(|| format!("Unclosed '{{' in template: {}", template))?;
+
+        let field = &rest[open + 1..close];
+        let (name, width) = match field.split_once(':') {
+            Some((name, spec)) => {
+                let width: usize = spec
+                    .parse()
+                    .with_context(|| format!("Invalid width '{}' in template", spec))?;
+                (name, Some(width))
+            }
+            None => (field, None),
+        };
+
+        match (name, width) {
+            ("stem", _) => result.push_str(stem),
+            ("label", _) => result.push_str(label),
+            ("page", Some(width)) => {
+                let _ = write!(result, "{:0width$}", page, width = width);
+            }
+            ("page", None) => {
+                let _ = write!(result, "{}", page);
+            }
+            _ => anyhow::bail!("Unknown template field '{{{}}}'", name),
+        }
+
+        rest = &rest[close + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_template() {
+        let name = render_template(DEFAULT_TEMPLATE, "book", 7, "vii").unwrap();
+        assert_eq!(name, "book-p0007.txt");
+    }
+
+    #[test]
+    fn test_template_fields() {
+        let name = render_template("{label}_{page}_{stem}.txt", "a", 12, "xii").unwrap();
+        assert_eq!(name, "xii_12_a.txt");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(render_template("{stem", "a", 1, "1").is_err());
+        assert!(render_template("{nope}", "a", 1, "1").is_err());
+        assert!(render_template("{page:x}", "a", 1, "1").is_err());
+    }
+
+    #[test]
+    fn test_format_pages_reports_failed_pages() {
+        let texts = vec![
+            PageText {
+                page: 1,
+                text: "hello".to_string(),
+                error: None,
+                recovered: false,
+            },
+            PageText {
+                page: 2,
+                text: String::new(),
+                error: Some("bad stream".to_string()),
+                recovered: false,
+            },
+        ];
+        assert_eq!(
+            format_pages(&texts, &[false, false]),
+            "--- Page 1 ---\nhello\n\n--- Page 2 ---\n[error: bad stream]\n\n"
+        );
+    }
+
+    #[test]
+    fn test_truncate_on_char_boundary() {
+        let mut text = "héllo wörld".to_string();
+        assert!(truncate(&mut text, 7));
+        assert_eq!(text, "héllo w");
+        assert!(!truncate(&mut text, 7));
+        assert_eq!(text, "héllo w");
+    }
+}
@@ -0,0 +1,215 @@
+use crate::commands::pii::PiiReport;
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::pages::Rect;
+use crate::pdf::redact::{redact_page, Redaction};
+use crate::pdf::text::PageTextProvider;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What happened to one item of the report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Removed,
+    /// The reviewer set `approved` to false.
+    Kept,
+    /// Found by OCR, so there is no text to remove; the page image still
+    /// shows it.
+    OcrOnly,
+    /// The value wasn't found in the page's text.
+    NotFound,
+}
+
+/// A record of what was removed where.
+#[derive(Debug, Serialize)]
+pub struct Audit {
+    pub input: String,
+    pub output: String,
+    pub items: Vec<AuditItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditItem {
+    pub id: usize,
+    pub kind: String,
+    pub value: String,
+    pub page: u32,
+    pub outcome: Outcome,
+    /// Boxes drawn over the removed text, as [llx, lly, urx, ury] in points.
+    pub boxes: Vec<Rect>,
+}
+
+/// The PDF a report was made from.
+pub fn report_source(report: &Path) -> Result<PathBuf> {
+    Ok(PathBuf::from(read_report(report)?.path))
+}
+
+fn read_report(report: &Path) -> Result<PiiReport> {
+    let text = std::fs::read_to_string(report)
+        .with_context(|| format!("Failed to read {}", report.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Failed to parse {}", report.display()))
+}
+
+/// Remove the approved items of a `pii` report from `input`, writing the
+/// result to `output` and an audit of each item to stdout (and `audit`, as
+/// JSON). Fails after saving if any approved item couldn't be removed.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    report: &Path,
+    audit: Option<&Path>,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let input = input.as_ref();
+    let output = output.as_ref();
+    let report = read_report(report)?;
+
+    let doc = PdfDocument::open(input)?;
+    let texts = PageTextProvider::new(doc.cached());
+    let page_ids: BTreeMap<u32, _> = doc.page_ids().into_iter().collect();
+
+    // Items to remove, by page, as indexes into the report.
+    let mut by_page: BTreeMap<u32, Vec<usize>> = BTreeMap::new();
+    let mut items: Vec<AuditItem> = report
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let outcome = if !item.approved {
+                Outcome::Kept
+            } else if item.ocr_confidence.is_some() {
+                Outcome::OcrOnly
+            } else {
+                by_page.entry(item.page).or_default().push(i);
+                Outcome::NotFound
+            };
+            AuditItem {
+                id: item.id,
+                kind: item.kind.clone(),
+                value: item.value.clone(),
+                page: item.page,
+                outcome,
+                boxes: Vec::new(),
+            }
+        })
+        .collect();
+
+    let mut new_doc = doc.doc().clone();
+    for (page, indexes) in &by_page {
+        let Some(&page_id) = page_ids.get(page) else {
+            continue;
+        };
+        let text = texts.page(*page).unwrap_or_default();
+        let redactions: Vec<Redaction> = indexes
+            .iter()
+            .map(|&i| {
+                let item = &report.items[i];
+                Redaction {
+                    value: item.value.clone(),
+                    occurrence: occurrence(&text, &item.value, item.start as usize),
+                }
+            })
+            .collect();
+        let boxes = redact_page(&mut new_doc, page_id, &redactions)
+            .with_context(|| format!("Failed to redact page {}", page))?;
+        for (&i, boxes) in indexes.iter().zip(boxes) {
+            if !boxes.is_empty() {
+                items[i].outcome = Outcome::Removed;
+                items[i].boxes = boxes;
+            }
+        }
+    }
+    // Replaced content streams would otherwise still be written out.
+    new_doc.prune_objects();
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+
+    for item in &items {
+        println!("{}", format_item(item));
+    }
+    let audit_record = Audit {
+        input: input.display().to_string(),
+        output: output.display().to_string(),
+        items,
+    };
+    if let Some(audit) = audit {
+        let mut json = serde_json::to_string_pretty(&audit_record)?;
+        json.push('\n');
+        std::fs::write(audit, json)
+            .with_context(|| format!("Failed to write {}", audit.display()))?;
+    }
+
+    let count = |outcome| {
+        audit_record
+            .items
+            .iter()
+            .filter(|item| item.outcome == outcome)
+            .count()
+    };
+    status!(
+        "Redacted {} item(s) into {} ({} kept)",
+        count(Outcome::Removed),
+        output.display(),
+        count(Outcome::Kept)
+    );
+    let missed = count(Outcome::OcrOnly) + count(Outcome::NotFound);
+    if missed > 0 {
+        anyhow::bail!(
+            "{} approved item(s) could not be redacted; {} may still show them",
+            missed,
+            output.display()
+        );
+    }
+    Ok(())
+}
+
+/// How many times `value` occurs in `text` before character `start`,
+/// ignoring whitespace as [`redact_page`] does.
+fn occurrence(text: &str, value: &str, start: usize) -> usize {
+    let needle: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if needle.is_empty() {
+        return 0;
+    }
+    let before: String = text
+        .chars()
+        .take(start)
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    before.matches(needle.as_str()).count()
+}
+
+fn format_item(item: &AuditItem) -> String {
+    let outcome = match item.outcome {
+        Outcome::Removed => {
+            let boxes: Vec<String> = item
+                .boxes
+                .iter()
+                .map(|[x0, y0, x1, y1]| format!("[{:.1} {:.1} {:.1} {:.1}]", x0, y0, x1, y1))
+                .collect();
+            format!("removed at {}", boxes.join(" "))
+        }
+        Outcome::Kept => "kept (not approved)".to_string(),
+        Outcome::OcrOnly => "NOT REMOVED (found by OCR; no text layer)".to_string(),
+        Outcome::NotFound => "NOT REMOVED (not found in page text)".to_string(),
+    };
+    format!(
+        "#{} p{} {}: {} {}",
+        item.id, item.page, item.kind, item.value, outcome
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occurrence() {
+        let text = "Call 555-0199\nor 555 - 0199, or 555-0199.";
+        assert_eq!(occurrence(text, "555-0199", 5), 0);
+        assert_eq!(occurrence(text, "555-0199", 17), 1);
+        assert_eq!(occurrence(text, "555-0199", 32), 2);
+    }
+}
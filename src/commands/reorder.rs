@@ -0,0 +1,100 @@
+use crate::commands::status;
+use crate::page_range::expand_page_ranges;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    order: Option<&str>,
+    reverse: bool,
+    output: Q,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(&input)?;
+    let total_pages = doc.page_count();
+
+    let mut page_list = match order {
+        Some(spec) => expand_page_ranges(spec, total_pages)?,
+        None if reverse => (1..=total_pages).collect(),
+        None => anyhow::bail!("Specify a page order, --reverse, or both"),
+    };
+    if reverse {
+        page_list.reverse();
+    }
+
+    validate_order(&page_list, total_pages)?;
+
+    let mut new_doc = doc.arrange_pages(&page_list)?;
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Reordered {} page(s) to {}",
+        page_list.len(),
+        output.as_ref().display()
+    );
+
+    Ok(())
+}
+
+/// Check that an ordering mentions every page exactly once.
+///
+/// Dropping or repeating pages is almost always a typo in the spec; `extract`
+/// and `duplicate` exist for doing that on purpose.
+fn validate_order(pages: &[u32], total_pages: u32) -> Result<()> {
+    let mut seen = vec![0u32; total_pages as usize];
+    for &page in pages {
+        seen[page as usize - 1] += 1;
+    }
+
+    let repeated: Vec<String> = seen
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 1)
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+    let missing: Vec<String> = seen
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count == 0)
+        .map(|(i, _)| (i + 1).to_string())
+        .collect();
+
+    let mut problems = Vec::new();
+    if !repeated.is_empty() {
+        problems.push(format!(
+            "page(s) listed more than once: {}",
+            repeated.join(", ")
+        ));
+    }
+    if !missing.is_empty() {
+        problems.push(format!("page(s) not listed: {}", missing.join(", ")));
+    }
+    if !problems.is_empty() {
+        anyhow::bail!(
+            "Order must list each of the {} pages exactly once; {}",
+            total_pages,
+            problems.join("; ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_order_accepts_permutation() {
+        assert!(validate_order(&[5, 1, 2, 3, 4, 6], 6).is_ok());
+    }
+
+    #[test]
+    fn test_validate_order_reports_repeats_and_gaps() {
+        let err = validate_order(&[1, 2, 2, 4], 4).unwrap_err().to_string();
+        assert!(err.contains("more than once: 2"), "{err}");
+        assert!(err.contains("not listed: 3"), "{err}");
+    }
+}
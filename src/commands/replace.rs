@@ -0,0 +1,54 @@
+use crate::commands::status;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::replace::replace_page;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// Replace `find` with `with` in the text of every page, where the font
+/// allows, and warn about each occurrence left alone.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output: Q,
+    find: &str,
+    with: &str,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    if find.trim().is_empty() {
+        anyhow::bail!("--find must contain some non-whitespace text");
+    }
+    let doc = PdfDocument::open(&input)?;
+    let mut new_doc = doc.doc().clone();
+
+    let mut replaced = 0;
+    let mut pages_replaced = 0;
+    let mut unsafe_pages = Vec::new();
+    for (page, page_id) in doc.page_ids() {
+        let result = replace_page(&mut new_doc, page_id, find, with)
+            .with_context(|| format!("Failed to replace text on page {}", page))?;
+        replaced += result.replaced;
+        if result.replaced > 0 {
+            pages_replaced += 1;
+        }
+        for reason in &result.skipped {
+            eprintln!("Warning: page {}: not replaced: {}", page, reason);
+        }
+        if !result.skipped.is_empty() {
+            unsafe_pages.push(page.to_string());
+        }
+    }
+    // Replaced content streams would otherwise still be written out.
+    new_doc.prune_objects();
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Replaced {} occurrence(s) on {} page(s) in {}",
+        replaced,
+        pages_replaced,
+        output.as_ref().display()
+    );
+    if !unsafe_pages.is_empty() {
+        status!("Left unchanged on page(s) {}", unsafe_pages.join(", "));
+    }
+    Ok(())
+}
@@ -0,0 +1,64 @@
+use crate::semantic::{index_path, EmbedderConfig, Index};
+use anyhow::{Context, Result};
+use regex::RegexBuilder;
+use std::path::Path;
+
+/// Longest passage shown per hit, in characters.
+const SNIPPET_CHARS: usize = 200;
+
+/// Print the pages of `path` most relevant to `query`.
+///
+/// With `semantic`, passages are ranked by embedding similarity alone.
+/// Otherwise pages with exact matches for `pattern` (default: the query as
+/// literal, case-insensitive text) are merged with the similarity ranking.
+pub fn run(
+    path: &Path,
+    query: &str,
+    semantic: bool,
+    pattern: Option<&str>,
+    top: usize,
+    index: Option<&Path>,
+    embedder: &EmbedderConfig,
+) -> Result<()> {
+    let embedder = embedder.connect()?;
+    let index = index.map_or_else(|| index_path(path), Path::to_path_buf);
+    let index = Index::load(&index, path)?;
+    if semantic {
+        for hit in index.search(embedder.as_ref(), query, top)? {
+            println!("p{} ({:.2}): {}", hit.page, hit.score, snippet(&hit.text));
+        }
+        return Ok(());
+    }
+
+    let pattern = match pattern {
+        Some(pattern) => RegexBuilder::new(pattern).build(),
+        None => RegexBuilder::new(&regex::escape(query))
+            .case_insensitive(true)
+            .build(),
+    }
+    .context("Invalid pattern")?;
+    for hit in index.hybrid_search(embedder.as_ref(), path, query, &pattern, top)? {
+        let mut why = match hit.exact_matches {
+            0 => String::new(),
+            1 => "1 match".to_string(),
+            n => format!("{} matches", n),
+        };
+        if let Some(similarity) = hit.similarity {
+            if !why.is_empty() {
+                why.push_str(", ");
+            }
+            why.push_str(&format!("similarity {:.2}", similarity));
+        }
+        println!("p{} ({}): {}", hit.page, why, snippet(&hit.snippet));
+    }
+    Ok(())
+}
+
+/// A passage on one line, cut to [`SNIPPET_CHARS`].
+fn snippet(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line,
+    }
+}
@@ -1,14 +1,121 @@
+use crate::commands::{format_size, output_size, report_links, status, LinkPolicy};
+use crate::pdf::document::SaveOptions;
+use crate::pdf::links::{fix_extracted_links, LinkFixup};
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::toc::{flatten_toc, FlatTocEntry};
 use crate::pdf::PdfDocument;
 use anyhow::{Context, Result};
-use std::path::Path;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
 
-pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output_dir: Q) -> Result<()> {
+/// Where a section ends when the next one starts partway down a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionBoundary {
+    /// The shared page goes to the section that starts on it
+    Page,
+    /// The shared page goes to both sections, so each file has all of its
+    /// section's text
+    Heading,
+}
+
+pub struct SectionOptions {
+    /// Split at outline entries this many levels deep or shallower
+    /// (1 = top level only).
+    pub level: u32,
+    pub boundary: SectionBoundary,
+    /// Also write the pages before the first section.
+    pub include_front_matter: bool,
+}
+
+/// A file to write: a section's title (none for front matter) and pages.
+#[derive(Debug, PartialEq)]
+struct SectionPlan {
+    title: Option<String>,
+    level: u32,
+    start: u32,
+    end: u32,
+}
+
+/// A file to write, as listed in `manifest.json`.
+#[derive(Debug, Serialize)]
+struct OutputFile {
+    file: String,
+    /// `null` for front matter before the first section.
+    title: Option<String>,
+    level: Option<u32>,
+    start_page: u32,
+    end_page: u32,
+}
+
+#[derive(Serialize)]
+struct Manifest<'a> {
+    source: String,
+    /// Outline depth split at; absent for `--logical`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    level: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    boundary: Option<SectionBoundary>,
+    files: &'a [OutputFile],
+}
+
+/// Where a book's body and back matter start, as physical pages.
+#[derive(Debug, PartialEq)]
+struct LogicalParts {
+    body: u32,
+    back: Option<u32>,
+}
+
+/// Outline titles (lowercase prefixes) of pages before the body.
+const FRONT_MATTER_TITLES: &[&str] = &[
+    "abstract",
+    "acknowledg",
+    "contents",
+    "copyright",
+    "cover",
+    "dedication",
+    "foreword",
+    "front matter",
+    "half title",
+    "list of",
+    "preface",
+    "table of contents",
+    "title page",
+];
+
+/// Outline titles (lowercase prefixes) that start the back matter.
+const BACK_MATTER_TITLES: &[&str] = &[
+    "about the author",
+    "afterword",
+    "appendices",
+    "appendix",
+    "back matter",
+    "bibliography",
+    "colophon",
+    "endnotes",
+    "glossary",
+    "index",
+    "notes",
+    "references",
+    "works cited",
+];
+
+/// Split a PDF into one file per page, returning the files written.
+pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output_dir: Q,
+    links: LinkPolicy,
+    dry_run: bool,
+    save_options: &SaveOptions,
+) -> Result<Vec<PathBuf>> {
     let input = input.as_ref();
     let output_dir = output_dir.as_ref();
 
     // Create output directory if it doesn't exist
-    std::fs::create_dir_all(output_dir)
-        .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+    if !dry_run {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+    }
 
     let doc = PdfDocument::open(input)?;
     let total_pages = doc.page_count();
@@ -16,14 +123,459 @@ pub fn run<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output_dir: Q) -> Result<()
     // Get the base name of the input file
     let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("page");
 
+    let mut fixup = LinkFixup::default();
+    let mut total_size = 0;
+    let mut written = Vec::new();
     for page_num in 1..=total_pages {
         let output_path = output_dir.join(format!("{}_{:04}.pdf", stem, page_num));
 
         let mut new_doc = doc.extract_pages(&[page_num])?;
-        PdfDocument::save(&mut new_doc, &output_path)?;
+        let mut page_fixup = fix_extracted_links(&mut new_doc, links.dangling());
+        // Report pages of the input rather than of each one-page file.
+        for page in &mut page_fixup.flagged {
+            *page += page_num - 1;
+        }
+        fixup.extend(page_fixup);
+        if dry_run {
+            let size = output_size(&mut new_doc, save_options)?;
+            println!(
+                "Would write {} ({})",
+                output_path.display(),
+                format_size(size)
+            );
+            total_size += size;
+        } else {
+            PdfDocument::save_with(&mut new_doc, &output_path, save_options)?;
+            written.push(output_path);
+        }
+    }
+
+    if dry_run {
+        println!(
+            "Would split {} pages into {} ({} total)",
+            total_pages,
+            output_dir.display(),
+            format_size(total_size)
+        );
+    } else {
+        status!("Split {} pages into {}", total_pages, output_dir.display());
+    }
+    report_links(&fixup, input);
+
+    Ok(written)
+}
+
+/// Split a PDF into one file per outline section, plus a `manifest.json`
+/// mapping each file to its section title and page range.
+///
+/// Returns the files written, `manifest.json` last.
+pub fn by_section<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output_dir: Q,
+    options: &SectionOptions,
+    links: LinkPolicy,
+    dry_run: bool,
+    save_options: &SaveOptions,
+) -> Result<Vec<PathBuf>> {
+    let input = input.as_ref();
+    let output_dir = output_dir.as_ref();
+
+    let doc = PdfDocument::open(input)?;
+    let outline = flatten_toc(&doc.toc()?);
+    let plans = plan_sections(&outline, doc.page_count(), options);
+    if plans.iter().all(|plan| plan.title.is_none()) {
+        anyhow::bail!(
+            "{} has no bookmarks to split at (try `inpdf toc detect` to add them)",
+            input.display()
+        );
+    }
+
+    let mut files = Vec::new();
+    for (i, plan) in plans.iter().enumerate() {
+        // Front matter is 00; sections count from 01 either way.
+        let number = if plans[0].title.is_none() { i } else { i + 1 };
+        files.push(OutputFile {
+            file: match &plan.title {
+                Some(title) => format!("{:02}-{}.pdf", number, slug(title)),
+                None => format!("{:02}-front-matter.pdf", number),
+            },
+            title: plan.title.clone(),
+            level: plan.title.as_ref().map(|_| plan.level + 1),
+            start_page: plan.start,
+            end_page: plan.end,
+        });
     }
+    let manifest = Manifest {
+        source: input.display().to_string(),
+        level: Some(options.level),
+        boundary: Some(options.boundary),
+        files: &files,
+    };
+    write_files(
+        &doc,
+        input,
+        output_dir,
+        &manifest,
+        links,
+        dry_run,
+        save_options,
+    )
+}
 
-    println!("Split {} pages into {}", total_pages, output_dir.display());
+/// Split a book into front matter (before the body), body, and back matter
+/// (appendices, bibliography, index), plus a `manifest.json`.
+///
+/// The body starts where decimal page labels take over from roman ones, or
+/// failing that at the first top-level bookmark that isn't a preface,
+/// contents and the like. The back matter starts at the first top-level
+/// bookmark after that titled like an appendix or index, or failing that
+/// where decimal page labels stop.
+///
+/// Returns the files written, `manifest.json` last.
+pub fn logical<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P,
+    output_dir: Q,
+    links: LinkPolicy,
+    dry_run: bool,
+    save_options: &SaveOptions,
+) -> Result<Vec<PathBuf>> {
+    let input = input.as_ref();
+    let output_dir = output_dir.as_ref();
 
-    Ok(())
+    let doc = PdfDocument::open(input)?;
+    let total = doc.page_count();
+    let labels: Vec<String> = extract_page_labels_from_doc(doc.doc())?
+        .into_iter()
+        .map(|label| label.logical_label)
+        .collect();
+    let outline = flatten_toc(&doc.toc()?);
+    let parts = plan_logical(&labels, &outline, total);
+    if parts.body == 1 && parts.back.is_none() {
+        anyhow::bail!(
+            "Found no front or back matter in {}: it has no roman page labels or \
+             bookmarks like Preface or Appendix",
+            input.display()
+        );
+    }
+
+    let mut files = Vec::new();
+    let mut add = |name: &str, title: &str, start: u32, end: u32| {
+        let number = files.len() + 1;
+        files.push(OutputFile {
+            file: format!("{:02}-{}.pdf", number, name),
+            title: Some(title.to_string()),
+            level: None,
+            start_page: start,
+            end_page: end,
+        })
+    };
+    let body_end = parts.back.map_or(total, |back| back - 1);
+    if parts.body > 1 {
+        add("front-matter", "Front matter", 1, parts.body - 1);
+    }
+    add("body", "Body", parts.body, body_end);
+    if let Some(back) = parts.back {
+        add("back-matter", "Back matter", back, total);
+    }
+    let manifest = Manifest {
+        source: input.display().to_string(),
+        level: None,
+        boundary: None,
+        files: &files,
+    };
+    write_files(
+        &doc,
+        input,
+        output_dir,
+        &manifest,
+        links,
+        dry_run,
+        save_options,
+    )
+}
+
+/// Write the files listed in `manifest`, then the manifest itself.
+fn write_files(
+    doc: &PdfDocument,
+    input: &Path,
+    output_dir: &Path,
+    manifest: &Manifest,
+    links: LinkPolicy,
+    dry_run: bool,
+    save_options: &SaveOptions,
+) -> Result<Vec<PathBuf>> {
+    if !dry_run {
+        std::fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+    }
+
+    let mut fixup = LinkFixup::default();
+    let mut total_size = 0;
+    let mut written = Vec::new();
+    for file in manifest.files {
+        let pages: Vec<u32> = (file.start_page..=file.end_page).collect();
+        let mut new_doc = doc.extract_pages(&pages)?;
+        let mut file_fixup = fix_extracted_links(&mut new_doc, links.dangling());
+        // Report pages of the input rather than of each output file.
+        for page in &mut file_fixup.flagged {
+            *page += file.start_page - 1;
+        }
+        fixup.extend(file_fixup);
+        let path = output_dir.join(&file.file);
+        if dry_run {
+            let size = output_size(&mut new_doc, save_options)?;
+            println!(
+                "Would write {} (pages {}-{}, {})",
+                path.display(),
+                file.start_page,
+                file.end_page,
+                format_size(size)
+            );
+            total_size += size;
+        } else {
+            PdfDocument::save_with(&mut new_doc, &path, save_options)?;
+            written.push(path);
+        }
+    }
+
+    let manifest_path = output_dir.join("manifest.json");
+    let mut json = serde_json::to_string_pretty(manifest)?;
+    json.push('\n');
+    if dry_run {
+        println!(
+            "Would write {} file(s) ({} total) and {}",
+            manifest.files.len(),
+            format_size(total_size),
+            manifest_path.display()
+        );
+        report_links(&fixup, input);
+        return Ok(written);
+    }
+    std::fs::write(&manifest_path, json)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    status!(
+        "Split {} into {} file(s) in {} (see manifest.json)",
+        input.display(),
+        manifest.files.len(),
+        output_dir.display()
+    );
+    report_links(&fixup, input);
+    written.push(manifest_path);
+    Ok(written)
+}
+
+/// See [`logical`]. `labels` holds each page's label in order.
+fn plan_logical(labels: &[String], outline: &[FlatTocEntry], total: u32) -> LogicalParts {
+    let is_decimal = |label: &str| !label.is_empty() && label.bytes().all(|b| b.is_ascii_digit());
+    let titled = |title: &str, prefixes: &[&str]| {
+        let title = title.trim().to_lowercase();
+        prefixes.iter().any(|prefix| title.starts_with(prefix))
+    };
+    let mut top: Vec<(&str, u32)> = outline
+        .iter()
+        .filter(|entry| entry.level == 0)
+        .filter_map(|entry| Some((entry.title.as_str(), entry.page?)))
+        .filter(|&(_, page)| (1..=total).contains(&page))
+        .collect();
+    top.sort_by_key(|&(_, page)| page);
+
+    let by_labels = labels
+        .iter()
+        .position(|label| is_decimal(label))
+        .map(|i| i as u32 + 1)
+        .filter(|&page| page > 1);
+    let body = by_labels
+        .or_else(|| {
+            top.iter()
+                .find(|&&(title, _)| !titled(title, FRONT_MATTER_TITLES))
+                .map(|&(_, page)| page)
+        })
+        .unwrap_or(1);
+
+    let back = top
+        .iter()
+        .find(|&&(title, page)| page > body && titled(title, BACK_MATTER_TITLES))
+        .map(|&(_, page)| page)
+        .or_else(|| {
+            labels
+                .iter()
+                .enumerate()
+                .skip(body as usize)
+                .find(|(_, label)| !is_decimal(label))
+                .map(|(i, _)| i as u32 + 1)
+        });
+    LogicalParts { body, back }
+}
+
+fn plan_sections(
+    outline: &[FlatTocEntry],
+    total: u32,
+    options: &SectionOptions,
+) -> Vec<SectionPlan> {
+    let mut starts: Vec<(&FlatTocEntry, u32)> = outline
+        .iter()
+        .filter(|entry| entry.level < options.level)
+        .filter_map(|entry| Some((entry, entry.page?)))
+        .filter(|&(_, page)| (1..=total).contains(&page))
+        .collect();
+    // Outlines are nearly always in page order, but don't produce negative
+    // ranges for those that aren't.
+    starts.sort_by_key(|&(_, page)| page);
+
+    let mut plans = Vec::new();
+    if let Some(&(_, first)) = starts.first() {
+        if options.include_front_matter && first > 1 {
+            plans.push(SectionPlan {
+                title: None,
+                level: 0,
+                start: 1,
+                end: first - 1,
+            });
+        }
+    }
+    for (i, &(entry, start)) in starts.iter().enumerate() {
+        let end = match starts.get(i + 1) {
+            Some(&(_, next)) => match options.boundary {
+                SectionBoundary::Page => next.saturating_sub(1),
+                SectionBoundary::Heading => next,
+            },
+            None => total,
+        };
+        plans.push(SectionPlan {
+            title: Some(entry.title.clone()),
+            level: entry.level,
+            start,
+            // Sections starting on the same page each get that page.
+            end: end.max(start),
+        });
+    }
+    plans
+}
+
+/// A file-name-friendly form of a section title: "2.1 Data & Methods" ->
+/// "2-1-data-methods".
+fn slug(title: &str) -> String {
+    let mut slug = String::new();
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        if slug.chars().count() >= 60 {
+            break;
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "section".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, page: u32, level: u32) -> FlatTocEntry {
+        FlatTocEntry {
+            title: title.to_string(),
+            page: Some(page),
+            external: None,
+            level,
+        }
+    }
+
+    fn ranges(plans: &[SectionPlan]) -> Vec<(Option<&str>, u32, u32)> {
+        plans
+            .iter()
+            .map(|p| (p.title.as_deref(), p.start, p.end))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_sections() {
+        let outline = [
+            entry("1 Intro", 3, 0),
+            entry("1.1 Scope", 4, 1),
+            entry("1.2 Terms", 4, 1),
+            entry("2 Method", 7, 0),
+        ];
+        let mut options = SectionOptions {
+            level: 1,
+            boundary: SectionBoundary::Page,
+            include_front_matter: true,
+        };
+        assert_eq!(
+            ranges(&plan_sections(&outline, 10, &options)),
+            [
+                (None, 1, 2),
+                (Some("1 Intro"), 3, 6),
+                (Some("2 Method"), 7, 10)
+            ]
+        );
+
+        options.level = 2;
+        options.boundary = SectionBoundary::Heading;
+        options.include_front_matter = false;
+        assert_eq!(
+            ranges(&plan_sections(&outline, 10, &options)),
+            [
+                (Some("1 Intro"), 3, 4),
+                (Some("1.1 Scope"), 4, 4),
+                (Some("1.2 Terms"), 4, 7),
+                (Some("2 Method"), 7, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_logical() {
+        let labels =
+            |labels: &str| -> Vec<String> { labels.split(' ').map(str::to_string).collect() };
+
+        // Roman front matter, decimal body, lettered appendix.
+        let book = labels("i ii iii 1 2 3 4 A-1 A-2");
+        assert_eq!(
+            plan_logical(&book, &[], 9),
+            LogicalParts {
+                body: 4,
+                back: Some(8)
+            }
+        );
+
+        // No labels: the outline decides.
+        let plain = labels("1 2 3 4 5 6 7 8 9 10");
+        let outline = [
+            entry("Contents", 2, 0),
+            entry("Preface", 3, 0),
+            entry("1 Intro", 4, 0),
+            entry("1.1 Scope", 5, 1),
+            entry("Index", 9, 1),
+            entry("Appendix A", 8, 0),
+        ];
+        assert_eq!(
+            plan_logical(&plain, &outline, 10),
+            LogicalParts {
+                body: 4,
+                back: Some(8)
+            }
+        );
+        assert_eq!(
+            plan_logical(&plain, &[], 10),
+            LogicalParts {
+                body: 1,
+                back: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("2.1 Data & Methods"), "2-1-data-methods");
+        assert_eq!(slug("  Über-Résumé! "), "über-résumé");
+        assert_eq!(slug("***"), "section");
+    }
 }
@@ -0,0 +1,145 @@
+use crate::commands::status;
+use crate::pdf::cache::cache;
+use crate::pdf::document::write_atomic;
+use crate::pdf::files::{find_pdfs, find_pdfs_to_depth, is_up_to_date, sidecar_path};
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::text::extract_text_pages;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Sidecar file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SidecarFormat {
+    /// Plain text (`file.pdf.txt`), pages separated by form feeds
+    Text,
+    /// Markdown (`file.pdf.md`) with a heading per page
+    Md,
+}
+
+impl SidecarFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SidecarFormat::Text => "txt",
+            SidecarFormat::Md => "md",
+        }
+    }
+}
+
+pub struct TextifyOptions {
+    /// Also visit subdirectories.
+    pub recursive: bool,
+    pub format: SidecarFormat,
+    /// Rewrite sidecars even if they are newer than their PDF.
+    pub force: bool,
+}
+
+/// Write a text sidecar next to each PDF in `dir` whose sidecar is missing
+/// or older than the PDF.
+///
+/// PDFs that cannot be read are reported and skipped, so one damaged file
+/// does not stop the run.
+pub fn run<P: AsRef<Path>>(dir: P, options: &TextifyOptions) -> Result<()> {
+    let dir = dir.as_ref();
+    let pdfs = if options.recursive {
+        find_pdfs(dir)
+    } else {
+        find_pdfs_to_depth(dir, 1)
+    };
+
+    let (mut written, mut current, mut failed) = (0, 0, 0);
+    for pdf in &pdfs {
+        let sidecar = sidecar_path(pdf, options.format.extension());
+        if !options.force && is_up_to_date(pdf, &sidecar) {
+            current += 1;
+            continue;
+        }
+        match write_sidecar(pdf, &sidecar, options.format) {
+            Ok(()) => written += 1,
+            Err(err) => {
+                eprintln!("Skipping {}: {:#}", pdf.display(), err);
+                failed += 1;
+            }
+        }
+        // Each file is visited once; don't keep them all parsed.
+        cache().evict(pdf);
+    }
+
+    status!(
+        "Wrote {} sidecar(s), {} already up to date, {} failed",
+        written,
+        current,
+        failed
+    );
+    Ok(())
+}
+
+fn write_sidecar(pdf: &Path, sidecar: &Path, format: SidecarFormat) -> Result<()> {
+    let doc = PdfDocument::open_for_text(pdf)?;
+    let pages: Vec<u32> = (1..=doc.page_count()).collect();
+    let texts = extract_text_pages(&doc, &pages)?;
+    for page in &texts {
+        if let Some(error) = &page.error {
+            eprintln!("Warning: {}: {}", pdf.display(), error);
+        }
+    }
+
+    let mut out = String::new();
+    match format {
+        SidecarFormat::Text => {
+            for page in &texts {
+                out.push_str(page.text.trim_end());
+                out.push_str("\n\x0c");
+            }
+        }
+        SidecarFormat::Md => {
+            let labels = extract_page_labels_from_doc(doc.doc())?;
+            let title = doc.get_info().title.filter(|t| !t.trim().is_empty());
+            let stem = pdf.file_stem().unwrap_or_default().to_string_lossy();
+            let _ = writeln!(out, "# {}\n", title.as_deref().unwrap_or(&stem));
+            for page in &texts {
+                let label = labels
+                    .get(page.page as usize - 1)
+                    .map(|l| l.logical_label.as_str())
+                    .filter(|label| *label != page.page.to_string());
+                match label {
+                    Some(label) => {
+                        let _ = writeln!(out, "## Page {} ({})\n", page.page, label);
+                    }
+                    None => {
+                        let _ = writeln!(out, "## Page {}\n", page.page);
+                    }
+                }
+                let text = page.text.trim();
+                if !text.is_empty() {
+                    let _ = writeln!(out, "{}\n", text);
+                }
+            }
+        }
+    }
+
+    write_atomic(sidecar, out.as_bytes())
+        .with_context(|| format!("Failed to write {}", sidecar.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sidecar_path_keeps_pdf_name() {
+        assert_eq!(
+            sidecar_path(
+                Path::new("docs/report.PDF"),
+                SidecarFormat::Text.extension()
+            ),
+            PathBuf::from("docs/report.PDF.txt")
+        );
+        assert_eq!(
+            sidecar_path(Path::new("a.b.pdf"), SidecarFormat::Md.extension()),
+            PathBuf::from("a.b.pdf.md")
+        );
+    }
+}
@@ -0,0 +1,20 @@
+use crate::commands::status;
+use crate::pdf::svg::page_to_svg;
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P, page: u32, output: Option<&Path>) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let svg = page_to_svg(doc.doc(), page)?;
+
+    if let Some(output) = output {
+        std::fs::write(output, svg)
+            .with_context(|| format!("Failed to write {}", output.display()))?;
+        status!("Wrote page {} to {}", page, output.display());
+    } else {
+        print!("{}", svg);
+    }
+
+    Ok(())
+}
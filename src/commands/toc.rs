@@ -1,12 +1,42 @@
-use crate::pdf::toc::{extract_toc, flatten_toc};
-use anyhow::Result;
+use crate::commands::status;
+use crate::page_range::expand_page_ranges;
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::document::SaveOptions;
+use crate::pdf::page_labels::{extract_page_labels_from_doc, prepend_label_range};
+use crate::pdf::printed_toc::{detect_printed_toc, DEFAULT_SCAN_PAGES};
+use crate::pdf::toc::{
+    edit_toc, extract_toc_from_doc, filter_toc, flatten_toc, write_toc, TocEdit,
+};
+use crate::pdf::typeset::{typeset, Block, TypesetOptions};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use lopdf::Object;
+use regex::Regex;
+use std::collections::HashMap;
 use std::path::Path;
 
-pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
-    let entries = extract_toc(&path)?;
+pub fn run<P: AsRef<Path>>(
+    path: P,
+    at_version: Option<usize>,
+    max_depth: Option<u32>,
+    pattern: Option<&str>,
+) -> Result<()> {
+    let pattern = pattern
+        .map(|p| Regex::new(p).with_context(|| format!("Invalid --match pattern: {}", p)))
+        .transpose()?;
+    let doc = PdfDocument::open_at_version(&path, at_version)?;
+    let entries = extract_toc_from_doc(doc.doc())?;
 
     if entries.is_empty() {
-        println!("No table of contents found.");
+        status!(
+            "No table of contents found. Try `inpdf toc detect` to read printed contents pages."
+        );
+        return Ok(());
+    }
+
+    let entries = filter_toc(&entries, max_depth, pattern.as_ref());
+    if entries.is_empty() {
+        status!("No table of contents entries match.");
         return Ok(());
     }
 
@@ -14,12 +44,251 @@ pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
 
     for entry in flat {
         let indent = "  ".repeat(entry.level as usize);
-        let page_str = entry
+        let page_str = match (entry.page, &entry.external) {
+            (Some(p), _) => format!(" (p. {})", p),
+            (None, Some(target)) => format!(" (-> {})", target),
+            (None, None) => String::new(),
+        };
+        println!("{}{}{}", indent, entry.title, page_str);
+    }
+
+    Ok(())
+}
+
+/// Read the printed contents pages among `pages` (the first
+/// [`DEFAULT_SCAN_PAGES`] by default) and print the outline they describe,
+/// or install it as the document's bookmarks when `output` is given.
+pub fn detect<P: AsRef<Path>>(
+    path: P,
+    pages: Option<&str>,
+    output: Option<&Path>,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let total = doc.page_count();
+    let pages = match pages {
+        Some(pages) => expand_page_ranges(pages, total)?,
+        None => (1..=total.min(DEFAULT_SCAN_PAGES)).collect(),
+    };
+
+    let entries = detect_printed_toc(doc.cached(), &pages)?;
+    if entries.is_empty() {
+        anyhow::bail!(
+            "No printed table of contents found in {}; try --pages to point at it",
+            path.display()
+        );
+    }
+
+    let Some(output) = output else {
+        for entry in flatten_toc(&entries) {
+            let indent = "  ".repeat(entry.level as usize);
+            let page_str = entry
+                .page
+                .map(|p| format!(" (p. {})", p))
+                .unwrap_or_else(|| " (page not found)".to_string());
+            println!("{}{}{}", indent, entry.title, page_str);
+        }
+        return Ok(());
+    };
+
+    let flat = flatten_toc(&entries);
+    let unresolved = flat.iter().filter(|e| e.page.is_none()).count();
+    if unresolved > 0 {
+        eprintln!(
+            "Warning: {} entry(ies) matched no page label and will have no destination",
+            unresolved
+        );
+    }
+    let mut new_doc = doc.doc().clone();
+    write_toc(&mut new_doc, &entries)?;
+    PdfDocument::save_with(&mut new_doc, output, save_options)?;
+    status!(
+        "Installed {} outline entries in {}",
+        flat.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+pub struct RenderOptions {
+    /// Insert the rendered pages before the document instead of writing them
+    /// on their own.
+    pub prepend: bool,
+    pub title: String,
+    pub typeset: TypesetOptions,
+}
+
+/// Typeset the outline as contents pages, showing each entry's logical page
+/// label so the printed numbers match what a reader sees.
+pub fn render<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    output: Q,
+    options: &RenderOptions,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let doc = PdfDocument::open(&path)?;
+    let entries = extract_toc_from_doc(doc.doc())?;
+    if entries.is_empty() {
+        anyhow::bail!("No table of contents found in {}", path.as_ref().display());
+    }
+
+    let labels = extract_page_labels_from_doc(doc.doc())?;
+    let page_ids: HashMap<_, _> = doc.page_ids().into_iter().collect();
+
+    let mut blocks = vec![Block::Heading {
+        level: 1,
+        text: options.title.clone(),
+    }];
+    for entry in flatten_toc(&entries) {
+        let trailing = entry
             .page
-            .map(|p| format!(" (p. {})", p))
+            .and_then(|p| labels.get(p.checked_sub(1)? as usize))
+            .map(|label| label.logical_label.clone())
             .unwrap_or_default();
-        println!("{}{}{}", indent, entry.title, page_str);
+        // Links need the target pages in the same file.
+        let link = entry
+            .page
+            .filter(|_| options.prepend)
+            .and_then(|p| page_ids.get(&p))
+            .map(|&id| Object::Array(vec![id.into(), "Fit".into()]));
+        blocks.push(Block::Leader {
+            depth: entry.level as usize,
+            text: entry.title,
+            trailing,
+            link,
+        });
+    }
+
+    let (mut new_doc, page_count) = if options.prepend {
+        let mut builder = DocumentBuilder::prepend(doc.doc().clone())?;
+        let page_count = typeset(&mut builder, &blocks, &options.typeset)?;
+        let mut new_doc = builder.finish()?;
+        prepend_label_range(&mut new_doc, page_count as u32)?;
+        (new_doc, page_count)
+    } else {
+        let mut builder = DocumentBuilder::new();
+        let page_count = typeset(&mut builder, &blocks, &options.typeset)?;
+        (builder.finish()?, page_count)
+    };
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Rendered {} contents page(s) to {}",
+        page_count,
+        output.as_ref().display()
+    );
+
+    Ok(())
+}
+
+/// Edits for [`edit`], as given on the command line.
+pub struct EditArgs {
+    /// `OLD=NEW` title replacements.
+    pub rename: Vec<String>,
+    /// Title patterns of entries to remove.
+    pub delete: Vec<String>,
+    /// `TITLE=PAGE` new destinations.
+    pub retarget: Vec<String>,
+}
+
+/// Rename, delete, and re-point outline entries, keeping the rest of the
+/// document as is.
+pub fn edit<P: AsRef<Path>, Q: AsRef<Path>>(
+    path: P,
+    output: Q,
+    args: &EditArgs,
+    save_options: &SaveOptions,
+) -> Result<()> {
+    let edits = parse_edits(args)?;
+    if edits.is_empty() {
+        anyhow::bail!("Nothing to do; give at least one --rename, --delete, or --retarget");
     }
 
+    let doc = PdfDocument::open(&path)?;
+    let mut new_doc = doc.doc().clone();
+    let summary = edit_toc(&mut new_doc, &edits)?;
+    PdfDocument::save_with(&mut new_doc, &output, save_options)?;
+
+    status!(
+        "Renamed {}, deleted {}, and retargeted {} outline entries in {}",
+        summary.renamed,
+        summary.deleted,
+        summary.retargeted,
+        output.as_ref().display()
+    );
+
     Ok(())
 }
+
+/// Turn the command-line edits into [`TocEdit`]s. Patterns must match a
+/// whole title.
+fn parse_edits(args: &EditArgs) -> Result<Vec<TocEdit>> {
+    let title_pattern = |pattern: &str| {
+        Regex::new(&format!("^(?:{})$", pattern))
+            .with_context(|| format!("Invalid title pattern: {}", pattern))
+    };
+
+    let mut edits = Vec::new();
+    for rename in &args.rename {
+        let (old, new) = rename
+            .split_once('=')
+            .with_context(|| format!("--rename expects OLD=NEW, got '{}'", rename))?;
+        edits.push(TocEdit::Rename {
+            pattern: title_pattern(old)?,
+            title: new.to_string(),
+        });
+    }
+    for delete in &args.delete {
+        edits.push(TocEdit::Delete(title_pattern(delete)?));
+    }
+    for retarget in &args.retarget {
+        // Titles may contain '=', page numbers can't.
+        let (title, page) = retarget
+            .rsplit_once('=')
+            .with_context(|| format!("--retarget expects TITLE=PAGE, got '{}'", retarget))?;
+        let page: u32 = page
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid page number in --retarget '{}'", retarget))?;
+        edits.push(TocEdit::Retarget {
+            pattern: title_pattern(title)?,
+            page,
+        });
+    }
+    Ok(edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edits() {
+        let args = EditArgs {
+            rename: vec!["Ch (\\d+)=Chapter $1".to_string()],
+            delete: vec!["Appendix.*".to_string()],
+            retarget: vec!["x = y=45".to_string()],
+        };
+        let edits = parse_edits(&args).unwrap();
+        let TocEdit::Rename { pattern, title } = &edits[0] else {
+            panic!("expected a rename");
+        };
+        assert!(pattern.is_match("Ch 3"));
+        assert!(!pattern.is_match("Ch 3 notes"));
+        assert_eq!(title, "Chapter $1");
+        let TocEdit::Retarget { pattern, page } = &edits[2] else {
+            panic!("expected a retarget");
+        };
+        assert!(pattern.is_match("x = y"));
+        assert_eq!(*page, 45);
+
+        let bad = EditArgs {
+            rename: vec!["no separator".to_string()],
+            delete: vec![],
+            retarget: vec![],
+        };
+        assert!(parse_edits(&bad).is_err());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::pdf::verify::verify_document;
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use std::path::Path;
+
+/// Check a PDF's structure and print what's wrong with it. Fails if any
+/// errors were found (or warnings, with `strict`), so scripts can use it
+/// as a gate after writing a file.
+pub fn run<P: AsRef<Path>>(path: P, strict: bool) -> Result<()> {
+    let path = path.as_ref();
+    let doc = PdfDocument::open(path)?;
+    let report = verify_document(doc.doc());
+
+    println!("File: {}", path.display());
+    if report.streams_not_decoded > 0 {
+        println!(
+            "Objects: {} ({} streams, {} image streams not decoded)",
+            report.objects, report.streams, report.streams_not_decoded
+        );
+    } else {
+        println!("Objects: {} ({} streams)", report.objects, report.streams);
+    }
+    println!("Pages: {}", report.pages);
+    println!("Outline items: {}", report.outline_items);
+    println!("Name and number tree entries: {}", report.tree_entries);
+
+    println!();
+    if report.problems.is_empty() {
+        println!("No problems found.");
+        return Ok(());
+    }
+    println!("Problems ({}):", report.problems.len());
+    for problem in &report.problems {
+        println!("  {}", problem);
+    }
+
+    let errors = report.errors();
+    let warnings = report.problems.len() - errors;
+    if errors > 0 || (strict && warnings > 0) {
+        anyhow::bail!(
+            "{} error(s) and {} warning(s) in {}",
+            errors,
+            warnings,
+            path.display()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::pdf::revisions::{find_revisions, load_revision};
+use crate::pdf::PdfDocument;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
+    let path = path.as_ref();
+    let raw = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let revisions = find_revisions(&raw);
+
+    if revisions.is_empty() {
+        println!("No %%EOF markers found; the file may be truncated.");
+        return Ok(());
+    }
+
+    for revision in &revisions {
+        print!("Version {}: {} bytes", revision.number, revision.end_offset);
+
+        match load_revision(path, revision.number) {
+            Ok(cached) => {
+                let doc = PdfDocument::from_cached(cached, path);
+                let info = doc.get_info();
+                print!(
+                    ", {} page(s), {} object(s)",
+                    info.page_count,
+                    doc.doc().objects.len()
+                );
+                if let Some(modified) = &info.modified {
+                    print!(", modified {}", modified);
+                } else if let Some(mod_date) = &info.mod_date {
+                    print!(", modified {}", mod_date);
+                }
+                println!();
+            }
+            Err(e) => println!(" (unreadable: {:#})", e),
+        }
+    }
+
+    if revisions.len() > 1 {
+        println!(
+            "\nUse --at-version N with info, toc, grep, read-pages, or page-labels to inspect an earlier version."
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+//! The PDF layer behind the `inpdf` CLI and MCP server, as a library.
+//!
+//! [`pdf`] and [`page_range`] don't use tokio or the CLI dependencies, so
+//! with `--no-default-features` the library compiles to
+//! `wasm32-unknown-unknown`. There, open documents with
+//! [`pdf::PdfDocument::open_bytes`]; the path-based constructors and
+//! writers need a filesystem.
+
+pub mod page_range;
+pub mod pdf;
+#[cfg(feature = "python")]
+mod python;
@@ -3,72 +3,996 @@ mod commands;
 mod mcp;
 mod page_range;
 mod pdf;
+mod remote;
+mod semantic;
 
 use anyhow::Result;
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{
+    AnnotationCommands, Cli, Commands, DebugCommands, FormCommands, IndexCommands, TocCommands,
+};
+use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    page_range::set_page_sets(cli.page_sets()?);
+    commands::configure_output(cli.quiet, cli.color);
+    let started = std::time::Instant::now();
 
     match cli.command {
-        Commands::Mcp => {
-            mcp::run_server().await?;
+        Commands::Mcp {
+            roots,
+            describe: true,
+            limits,
+            ..
+        } => {
+            let options = mcp::ServerOptions {
+                roots,
+                limits: limits.limits(),
+                ..Default::default()
+            };
+            print!("{}", mcp::describe(options)?);
+        }
+        Commands::Mcp {
+            roots,
+            socket,
+            socket_mode,
+            describe: false,
+            audit_log,
+            embedder,
+            limits,
+        } => {
+            let options = mcp::ServerOptions {
+                roots,
+                limits: limits.limits(),
+                audit_log: audit_log
+                    .as_deref()
+                    .map(mcp::AuditLog::open)
+                    .transpose()?
+                    .map(Arc::new),
+                embedder: if embedder.is_set() {
+                    Some(embedder.config().connect()?)
+                } else {
+                    None
+                },
+            };
+            match socket {
+                #[cfg(unix)]
+                Some(socket) => mcp::run_socket_server(&socket, socket_mode, options).await?,
+                #[cfg(not(unix))]
+                Some(_) => {
+                    let _ = socket_mode;
+                    anyhow::bail!("--socket is only supported on Unix platforms");
+                }
+                None => mcp::run_server(options).await?,
+            }
+        }
+        Commands::Info {
+            path,
+            at_version,
+            download,
+        } => {
+            let path = remote::fetch_if_url(&path, &download.options())?;
+            commands::info::run(&path, at_version)?;
+        }
+        Commands::Fingerprint { paths, format } => {
+            commands::fingerprint::run(&paths, format)?;
         }
-        Commands::Info { path } => {
-            commands::info::run(&path)?;
+        Commands::Toc {
+            command:
+                Some(TocCommands::Render {
+                    path,
+                    output,
+                    prepend,
+                    title,
+                    page_size,
+                    in_place,
+                    write,
+                }),
+            ..
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "toc render",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            let options = commands::toc::RenderOptions {
+                prepend,
+                title,
+                typeset: pdf::typeset::TypesetOptions {
+                    page_size,
+                    ..Default::default()
+                },
+            };
+            commands::toc::render(&path, &output, &options, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Toc {
+            command:
+                Some(TocCommands::Detect {
+                    path,
+                    pages,
+                    output,
+                    in_place,
+                    write,
+                }),
+            ..
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "toc detect",
+                &[&path],
+                pages.as_deref(),
+            )?;
+            let output = if output.is_some() || in_place.in_place {
+                Some(commands::output_path(&path, output.as_deref(), &in_place)?)
+            } else {
+                None
+            };
+            commands::toc::detect(
+                &path,
+                pages.as_deref(),
+                output.as_deref(),
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, output.as_slice())?;
         }
-        Commands::Toc { path } => {
-            commands::toc::run(&path)?;
+        Commands::Toc {
+            command:
+                Some(TocCommands::Edit {
+                    path,
+                    output,
+                    rename,
+                    delete,
+                    retarget,
+                    in_place,
+                    write,
+                }),
+            ..
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "toc edit",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            let args = commands::toc::EditArgs {
+                rename,
+                delete,
+                retarget,
+            };
+            commands::toc::edit(&path, &output, &args, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Toc {
+            command: None,
+            path,
+            at_version,
+            max_depth,
+            pattern,
+            download,
+        } => {
+            let path = path.expect("clap requires a path without a subcommand");
+            let path = remote::fetch_if_url(&path, &download.options())?;
+            commands::toc::run(&path, at_version, max_depth, pattern.as_deref())?;
+        }
+        Commands::Browse { path } => {
+            commands::browse::run(&path)?;
+        }
+        Commands::A11y { path } => {
+            commands::a11y::run(&path)?;
+        }
+        Commands::Verify { path, strict } => {
+            commands::verify::run(&path, strict)?;
+        }
+        Commands::Completions { shell, output_dir } => {
+            commands::completions::completions(shell, output_dir.as_deref())?;
+        }
+        Commands::Man { output_dir } => {
+            commands::completions::man(output_dir.as_deref())?;
+        }
+        Commands::Form {
+            command:
+                FormCommands::Export {
+                    path,
+                    output,
+                    format,
+                },
+        } => {
+            commands::form::export(&path, output.as_deref(), format)?;
+        }
+        Commands::Form {
+            command: FormCommands::ExportBatch { inputs, output },
+        } => {
+            commands::form::export_batch(&inputs, output.as_deref())?;
+        }
+        Commands::Form {
+            command:
+                FormCommands::Fill {
+                    path,
+                    data,
+                    format,
+                    output,
+                    in_place,
+                    write,
+                },
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "form fill",
+                &[&path, &data],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::form::fill(&path, &data, format, &output, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Annotations {
+            command: AnnotationCommands::ExportMd { path, output },
+        } => {
+            commands::annotations::export_md(&path, output.as_deref())?;
+        }
+        Commands::Annotations {
+            command:
+                AnnotationCommands::Import {
+                    path,
+                    from,
+                    output,
+                    in_place,
+                    write,
+                },
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "annotations import",
+                &[&path, &from],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::annotations::import(&path, &from, &output, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Annotations {
+            command:
+                AnnotationCommands::Strip {
+                    path,
+                    types,
+                    output,
+                    in_place,
+                    write,
+                },
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "annotations strip",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::annotations::strip(&path, &types, &output, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Colors { path } => {
+            commands::colors::run(&path)?;
+        }
+        Commands::Figures { path } => {
+            commands::figures::run(&path)?;
+        }
+        Commands::Classify { path, pages } => {
+            commands::classify::run(&path, pages.as_deref())?;
         }
         Commands::Grep {
             pattern,
-            path,
+            paths,
             ignore_case,
             max_results,
+            max_per_page,
+            max_per_file,
+            group_by,
+            sort,
+            context_chars,
+            at_version,
+            portfolio_item,
+            include_attachments,
+            ocr,
+            min_confidence,
+            download,
         } => {
+            let paths = paths
+                .iter()
+                .map(|path| remote::fetch_if_url(path, &download.options()))
+                .collect::<Result<Vec<_>>>()?;
             let options = commands::grep::GrepOptions {
                 pattern,
                 case_insensitive: ignore_case,
                 max_results,
-                ..Default::default()
+                max_per_page,
+                max_per_file,
+                group_by,
+                sort,
+                context_chars,
+                at_version,
+                portfolio_item,
+                include_attachments,
+                ocr: ocr.then(pdf::ocr::OcrOptions::default),
+                min_confidence,
+            };
+            commands::grep::run(&paths, &options)?;
+        }
+        Commands::Pii {
+            path,
+            patterns,
+            ocr,
+            format,
+            output,
+            download,
+        } => {
+            let path = remote::fetch_if_url(&path, &download.options())?;
+            let options = commands::pii::PiiOptions {
+                patterns,
+                ocr: ocr.then(pdf::ocr::OcrOptions::default),
+                format,
+                output,
             };
-            commands::grep::run(&path, &options)?;
+            commands::pii::run(&path, &options)?;
+        }
+        Commands::Diff {
+            old,
+            new,
+            visual: true,
+            threshold,
+            output_dir,
+            dpi,
+            ..
+        } => {
+            let options = commands::diff::VisualOptions {
+                threshold,
+                output_dir,
+                dpi,
+            };
+            commands::diff::run_visual(&old, &new, &options)?;
+        }
+        Commands::Diff {
+            old,
+            new,
+            side_by_side,
+            report,
+            context,
+            ..
+        } => {
+            let options = commands::diff::DiffOptions {
+                side_by_side,
+                report,
+                context,
+            };
+            commands::diff::run(&old, &new, &options)?;
         }
         Commands::Extract {
             path,
             pages,
             output,
+            portfolio_item,
+            dangling_links,
+            dry_run,
+            metadata,
+            in_place,
+            write,
         } => {
-            commands::extract::run(&path, &pages, &output)?;
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref().filter(|_| !dry_run),
+                "extract",
+                &[&path],
+                Some(pages.as_str()),
+            )?;
+            // A dry run must not make --in-place's backup either.
+            let output = match output {
+                Some(output) if dry_run => output,
+                None if dry_run => path.clone(),
+                output => commands::output_path(&path, output.as_deref(), &in_place)?,
+            };
+            commands::extract::run(
+                &path,
+                &pages,
+                &output,
+                portfolio_item.as_deref(),
+                &commands::CopyOptions {
+                    links: dangling_links,
+                    metadata: &metadata,
+                    dry_run,
+                },
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &[&output])?;
         }
-        Commands::Merge { inputs, output } => {
+        Commands::Merge {
+            inputs,
+            output,
+            separators,
+            map,
+            map_out,
+            dangling_links,
+            dry_run,
+            metadata,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref().filter(|_| !dry_run),
+                "merge",
+                &inputs,
+                None,
+            )?;
             let input_refs: Vec<_> = inputs.iter().collect();
-            commands::merge::run(&input_refs, &output)?;
+            let map = commands::merge::MapOptions {
+                print: map,
+                json: map_out,
+            };
+            commands::merge::run(
+                &input_refs,
+                &output,
+                separators,
+                &map,
+                &commands::CopyOptions {
+                    links: dangling_links,
+                    metadata: &metadata,
+                    dry_run,
+                },
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Linearize {
+            path,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "linearize",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            let mut options = write.save_options();
+            options.linearize = true;
+            commands::linearize::run(&path, &output, &options)?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Boxes {
+            path,
+            pages,
+            set,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "boxes",
+                &[&path],
+                pages.as_deref(),
+            )?;
+            let output = if output.is_some() || in_place.in_place {
+                Some(commands::output_path(&path, output.as_deref(), &in_place)?)
+            } else {
+                None
+            };
+            commands::boxes::run(
+                &path,
+                pages.as_deref(),
+                &set,
+                output.as_deref(),
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, output.as_slice())?;
         }
-        Commands::Split { path, output_dir } => {
-            commands::split::run(&path, &output_dir)?;
+        Commands::Marks {
+            path,
+            marks,
+            bleed,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation =
+                commands::manifest::begin(write.manifest_out.as_deref(), "marks", &[&path], None)?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::marks::run(&path, &output, &marks, bleed, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Redact {
+            path,
+            from_report,
+            audit,
+            output,
+            in_place,
+            write,
+        } => {
+            let path = match path {
+                Some(path) => path,
+                None => commands::redact::report_source(&from_report)?,
+            };
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "redact",
+                &[&path, &from_report],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::redact::run(
+                &path,
+                &output,
+                &from_report,
+                audit.as_deref(),
+                &write.save_options(),
+            )?;
+            let mut outputs = vec![&output];
+            outputs.extend(audit.as_ref());
+            commands::manifest::finish(operation, &outputs)?;
+        }
+        Commands::Replace {
+            path,
+            find,
+            with,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "replace",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::replace::run(&path, &output, &find, &with, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::EmbedFonts {
+            path,
+            font_dir,
+            no_subset,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "embed-fonts",
+                &[&path],
+                None,
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::embed_fonts::run(
+                &path,
+                &output,
+                &font_dir,
+                !no_subset,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &[&output])?;
         }
-        Commands::PageLabels { path } => {
-            let labels = pdf::page_labels::extract_page_labels(&path)?;
+        Commands::Overlay {
+            path,
+            under,
+            over,
+            output,
+            in_place,
+            write,
+        } => {
+            let inputs: Vec<_> = [Some(&path), under.as_ref(), over.as_ref()]
+                .into_iter()
+                .flatten()
+                .collect();
+            let operation =
+                commands::manifest::begin(write.manifest_out.as_deref(), "overlay", &inputs, None)?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::overlay::run(
+                &path,
+                under.as_deref(),
+                over.as_deref(),
+                &output,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Ocr {
+            path,
+            pages,
+            ocr_format,
+            output_dir,
+            lang,
+            dpi,
+        } => {
+            let options = pdf::ocr::OcrOptions {
+                language: lang,
+                dpi,
+            };
+            commands::ocr::run(
+                &path,
+                pages.as_deref(),
+                ocr_format,
+                output_dir.as_deref(),
+                &options,
+            )?;
+        }
+        Commands::DeskewOrientation {
+            path,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "deskew-orientation",
+                &[&path],
+                None,
+            )?;
+            let output = if output.is_some() || in_place.in_place {
+                Some(commands::output_path(&path, output.as_deref(), &in_place)?)
+            } else {
+                None
+            };
+            commands::deskew_orientation::run(&path, output.as_deref(), &write.save_options())?;
+            commands::manifest::finish(operation, output.as_slice())?;
+        }
+        Commands::ContactSheet {
+            path,
+            output,
+            cols,
+            rows,
+            page_size,
+            write,
+        } => {
+            let options = commands::contact_sheet::ContactSheetOptions {
+                cols,
+                rows,
+                page_size,
+            };
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "contact-sheet",
+                &[&path],
+                None,
+            )?;
+            commands::contact_sheet::run(&path, &output, &options, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::FromImages {
+            images,
+            output,
+            page_size,
+            fit,
+            dpi,
+            write,
+        } => {
+            let options = commands::from_images::FromImagesOptions {
+                page_size,
+                fit,
+                dpi,
+            };
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "from-images",
+                &images,
+                None,
+            )?;
+            commands::from_images::run(&images, &output, &options, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::FromText {
+            path,
+            output,
+            format,
+            page_size,
+            font_size,
+            margin,
+            write,
+        } => {
+            let options = pdf::typeset::TypesetOptions {
+                page_size,
+                margin,
+                font_size,
+            };
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "from-text",
+                &[&path],
+                None,
+            )?;
+            commands::from_text::run(&path, &output, format, &options, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::ToSvg { path, page, output } => {
+            commands::to_svg::run(&path, page, output.as_deref())?;
+        }
+        Commands::Duplicate {
+            path,
+            pages,
+            count,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "duplicate",
+                &[&path],
+                Some(pages.as_str()),
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::duplicate::run(&path, &pages, count, &output, &write.save_options())?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Reorder {
+            path,
+            order,
+            reverse,
+            output,
+            in_place,
+            write,
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref(),
+                "reorder",
+                &[&path],
+                order.as_deref(),
+            )?;
+            let output = commands::output_path(&path, output.as_deref(), &in_place)?;
+            commands::reorder::run(
+                &path,
+                order.as_deref(),
+                reverse,
+                &output,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &[&output])?;
+        }
+        Commands::Split {
+            path,
+            output_dir,
+            logical: true,
+            dangling_links,
+            dry_run,
+            write,
+            ..
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref().filter(|_| !dry_run),
+                "split",
+                &[&path],
+                None,
+            )?;
+            let written = commands::split::logical(
+                &path,
+                &output_dir,
+                dangling_links,
+                dry_run,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &written)?;
+        }
+        Commands::Split {
+            path,
+            output_dir,
+            by_section: true,
+            level,
+            include_front_matter,
+            boundary,
+            dangling_links,
+            dry_run,
+            write,
+            ..
+        } => {
+            let options = commands::split::SectionOptions {
+                level,
+                boundary,
+                include_front_matter,
+            };
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref().filter(|_| !dry_run),
+                "split",
+                &[&path],
+                None,
+            )?;
+            let written = commands::split::by_section(
+                &path,
+                &output_dir,
+                &options,
+                dangling_links,
+                dry_run,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &written)?;
+        }
+        Commands::Split {
+            path,
+            output_dir,
+            dangling_links,
+            dry_run,
+            write,
+            ..
+        } => {
+            let operation = commands::manifest::begin(
+                write.manifest_out.as_deref().filter(|_| !dry_run),
+                "split",
+                &[&path],
+                None,
+            )?;
+            let written = commands::split::run(
+                &path,
+                &output_dir,
+                dangling_links,
+                dry_run,
+                &write.save_options(),
+            )?;
+            commands::manifest::finish(operation, &written)?;
+        }
+        Commands::OutlinePath { path, page } => {
+            commands::outline_path::run(&path, page)?;
+        }
+        Commands::Debug { command } => match command {
+            DebugCommands::Object {
+                path,
+                id,
+                generation,
+                stream,
+            } => {
+                commands::debug::object(&path, id, generation, stream)?;
+            }
+            DebugCommands::Content { path, page } => {
+                commands::debug::content(&path, page)?;
+            }
+            DebugCommands::Stats { path } => {
+                commands::debug::stats(&path)?;
+            }
+        },
+        Commands::Versions { path } => {
+            commands::versions::run(&path)?;
+        }
+        Commands::PageLabels { path, at_version } => {
+            let doc = pdf::PdfDocument::open_at_version(&path, at_version)?;
+            let labels = pdf::page_labels::extract_page_labels_from_doc(doc.doc())?;
             for label in labels {
                 println!("{}: {}", label.physical_page, label.logical_label);
             }
         }
-        Commands::ReadPages { path, pages } => {
-            let doc = pdf::PdfDocument::open(&path)?;
-            let total = doc.page_count();
-            let page_list = page_range::expand_page_ranges(&pages, total)?;
-            let texts = pdf::text::extract_text_pages(&path, &page_list)?;
-
-            for page_text in texts {
-                println!("--- Page {} ---", page_text.page);
-                println!("{}", page_text.text);
-                println!();
-            }
+        Commands::ReadPages {
+            path,
+            pages,
+            annotate_sections,
+            out,
+            out_dir,
+            template,
+            at_version,
+            portfolio_item,
+            format,
+            max_chars,
+            profile,
+            download,
+        } => {
+            let path = remote::fetch_if_url(&path, &download.options())?;
+            let profile = profile
+                .as_deref()
+                .map(commands::profiles::resolve_profile)
+                .transpose()?;
+            let options = commands::read_pages::ReadPagesOptions {
+                annotate_sections,
+                out,
+                out_dir,
+                template,
+                at_version,
+                portfolio_item,
+                format,
+                max_chars,
+                profile,
+            };
+            commands::read_pages::run(&path, &pages, &options)?;
+        }
+        Commands::ReadAfter {
+            path,
+            heading,
+            pages,
+            annotate_sections,
+            at_version,
+            portfolio_item,
+            format,
+            max_chars,
+            profile,
+            download,
+        } => {
+            let path = remote::fetch_if_url(&path, &download.options())?;
+            let profile = profile
+                .as_deref()
+                .map(commands::profiles::resolve_profile)
+                .transpose()?;
+            let options = commands::read_pages::ReadPagesOptions {
+                annotate_sections,
+                at_version,
+                portfolio_item,
+                format,
+                max_chars,
+                profile,
+                ..Default::default()
+            };
+            commands::read_after::run(&path, &heading, pages, &options)?;
+        }
+        Commands::CollectionStats {
+            dir,
+            format,
+            output,
+        } => {
+            commands::collection_stats::run(&dir, format, output.as_deref())?;
+        }
+        Commands::Index {
+            command:
+                IndexCommands::Embed {
+                    path,
+                    index,
+                    chunk_chars,
+                    embedder,
+                },
+        } => {
+            commands::index::embed(
+                &path,
+                index.as_deref(),
+                &embedder.config(),
+                chunk_chars as usize,
+            )?;
+        }
+        Commands::Search {
+            path,
+            query,
+            semantic,
+            pattern,
+            top,
+            index,
+            embedder,
+        } => {
+            commands::search::run(
+                &path,
+                &query,
+                semantic,
+                pattern.as_deref(),
+                top,
+                index.as_deref(),
+                &embedder.config(),
+            )?;
+        }
+        Commands::Textify {
+            dir,
+            recursive,
+            format,
+            force,
+        } => {
+            let options = commands::textify::TextifyOptions {
+                recursive,
+                format,
+                force,
+            };
+            commands::textify::run(&dir, &options)?;
+        }
+        Commands::Bench {
+            path,
+            op,
+            iterations,
+            pattern,
+        } => {
+            let options = commands::bench::BenchOptions {
+                ops: op,
+                iterations,
+                pattern,
+            };
+            commands::bench::run(&path, &options)?;
         }
     }
 
+    if cli.timing {
+        commands::bench::report_timing(started);
+    }
     Ok(())
 }
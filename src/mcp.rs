@@ -1,32 +1,83 @@
-use anyhow::Result;
-use regex::RegexBuilder;
+use anyhow::{Context, Result};
+use base64::Engine;
+use regex::{Regex, RegexBuilder};
 use rmcp::{
     handler::server::{
         router::tool::ToolRouter,
         wrapper::{Json, Parameters},
     },
-    model::{ServerCapabilities, ServerInfo},
-    schemars, tool, tool_handler, tool_router, ServerHandler, ServiceExt,
+    model::{Implementation, ServerCapabilities, ServerInfo},
+    schemars,
+    service::{NotificationContext, Peer},
+    tool, tool_handler, tool_router, RoleServer, ServerHandler, ServiceExt,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use crate::page_range::expand_page_ranges;
-use crate::pdf::page_labels::extract_page_labels;
-use crate::pdf::text::{extract_text_pages, grep_pdf};
-use crate::pdf::toc::{extract_toc, flatten_toc};
+use crate::pdf::cache::{get_cached_pdf, get_cached_pdf_for_text};
+use crate::pdf::document::{format_pdf_date_utc, PdfDate};
+use crate::pdf::figures::find_figures;
+use crate::pdf::files::{find_pdfs, glob_match, read_text_sidecar};
+use crate::pdf::links::{fix_extracted_links, DanglingLinks};
+use crate::pdf::page_labels::{extract_page_labels, extract_page_labels_from_doc, resolve_label};
+use crate::pdf::printed_toc::{detect_printed_toc, DEFAULT_SCAN_PAGES};
+use crate::pdf::text::{
+    annotate_sections, extract_text_pages, grep_attachments, grep_cached_until, grep_page_texts,
+    snippet, PageText,
+};
+use crate::pdf::toc::{extract_toc_from_doc, filter_toc, flatten_toc, outline_path};
 use crate::pdf::PdfDocument;
+use crate::remote::{fetch_if_s3, DownloadOptions};
+use crate::semantic::Embedder;
 
 // Request structs for tools
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PathRequest {
-    #[schemars(description = "Path to the PDF file")]
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfTocRequest {
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
+    pub path: String,
+    #[schemars(
+        description = "Only return entries this many levels deep, e.g. 1 for top-level chapters only (default: all)"
+    )]
+    pub max_depth: Option<u32>,
+    #[schemars(
+        description = "Only return entries whose title matches this regular expression (prefix with (?i) to ignore case), along with their parent and child entries"
+    )]
+    #[serde(rename = "match")]
+    pub pattern: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfOutlinePathRequest {
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
     pub path: String,
+    #[schemars(description = "Physical page number (1-indexed)")]
+    pub page: u32,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PdfGrepRequest {
-    #[schemars(description = "Path to the PDF file")]
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
     pub path: String,
     #[schemars(description = "Regular expression pattern to search for")]
     pub pattern: String,
@@ -36,154 +87,1144 @@ pub struct PdfGrepRequest {
     #[schemars(description = "Maximum number of results (default: 100)")]
     #[serde(default = "default_max_results")]
     pub max_results: i32,
+    #[schemars(
+        description = "Also search attached PDFs and text/XML attachments, e.g. portfolio members or invoice XML (default: false)"
+    )]
+    #[serde(default)]
+    pub include_attachments: bool,
+    #[schemars(
+        description = "Also return this many characters of page text either side of each match, across line breaks, as snippet (default: none)"
+    )]
+    #[serde(default)]
+    pub snippet_chars: Option<u32>,
+    #[schemars(
+        description = "Keep at most this many matches from any one page, so a page of boilerplate doesn't use up max_results (default: no limit)"
+    )]
+    #[serde(default)]
+    pub max_per_page: Option<usize>,
 }
 
 fn default_max_results() -> i32 {
     100
 }
 
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfGrepWorkspaceRequest {
+    #[schemars(description = "Regular expression pattern to search for")]
+    pub pattern: String,
+    #[schemars(
+        description = "Directory to search recursively (default: the client's workspace roots and any configured with --root)"
+    )]
+    #[serde(default)]
+    pub directory: Option<String>,
+    #[schemars(description = "Case insensitive search (default: false)")]
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[schemars(description = "Maximum number of results across all files (default: 100)")]
+    #[serde(default = "default_max_results")]
+    pub max_results: i32,
+    #[schemars(
+        description = "Keep at most this many matches from any one page (default: no limit)"
+    )]
+    #[serde(default)]
+    pub max_per_page: Option<usize>,
+    #[schemars(
+        description = "Keep at most this many matches from any one file, so one file doesn't use up max_results before the others are searched (default: no limit)"
+    )]
+    #[serde(default)]
+    pub max_per_file: Option<usize>,
+    #[schemars(
+        description = "Search the text sidecars (file.pdf.txt) written by `inpdf textify` instead of extracting text, for files whose sidecar is up to date; other files are extracted as usual (default: false)"
+    )]
+    #[serde(default)]
+    pub use_index: bool,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfListFilesRequest {
+    #[schemars(
+        description = "Only list files whose path relative to their root matches this glob, e.g. \"reports/**/*.pdf\" or \"*invoice*\" (a pattern without / matches file names; default: all)"
+    )]
+    #[serde(default)]
+    pub pattern: Option<String>,
+    #[schemars(
+        description = "Directory to list recursively (default: the client's workspace roots and any configured with --root)"
+    )]
+    #[serde(default)]
+    pub directory: Option<String>,
+    #[schemars(
+        description = "Open each listed file to count its pages; slower for many files (default: false)"
+    )]
+    #[serde(default)]
+    pub page_counts: bool,
+    #[schemars(description = "Maximum number of files to list (default: 1000)")]
+    #[serde(default = "default_max_files")]
+    pub max_results: i32,
+}
+
+fn default_max_files() -> i32 {
+    1000
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfResolveLabelRequest {
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
+    pub path: String,
+    #[schemars(description = "Logical page label to convert to physical page(s), e.g. \"xii\"")]
+    #[serde(default)]
+    pub label: Option<String>,
+    #[schemars(description = "Physical page number (1-indexed) to convert to its logical label")]
+    #[serde(default)]
+    pub page: Option<u32>,
+}
+
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PdfReadPagesRequest {
-    #[schemars(description = "Path to the PDF file")]
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute, or a scratch: handle from pdf_extract (or s3://bucket/key if the server was built with S3 support)"
+    )]
     pub path: String,
     #[schemars(description = "Page ranges (e.g., '1-5,10,15-end')")]
     pub pages: String,
+    #[schemars(
+        description = "Prefix each page's text with its logical page label and enclosing TOC section (default: false)"
+    )]
+    #[serde(default)]
+    pub annotate_sections: bool,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 pub struct PdfExtractRequest {
-    #[schemars(description = "Path to the source PDF file")]
+    #[schemars(
+        description = "Path to the source PDF file, relative to a workspace root or absolute"
+    )]
     pub path: String,
     #[schemars(description = "Page ranges (e.g., '1-5,10,15-end')")]
     pub pages: String,
-    #[schemars(description = "Output file path")]
-    pub output: String,
+    #[schemars(
+        description = "Output file path (omit when return_base64 is set and no file should be written)"
+    )]
+    #[serde(default)]
+    pub output: Option<String>,
+    #[schemars(
+        description = "Return the generated PDF as base64 in the result instead of requiring a shared filesystem (default: false)"
+    )]
+    #[serde(default)]
+    pub return_base64: bool,
+    #[schemars(
+        description = "Write the output to a scratch file the server manages for this session instead of a path, and return a handle (e.g. \"scratch:extract-1.pdf\") that other tools accept as a path. Scratch files are deleted when the session ends (default: false)"
+    )]
+    #[serde(default)]
+    pub scratch: bool,
+}
+
+/// Per-call limits on the text tools, so one careless request can't tie up
+/// the server or flood the client.
+#[derive(Debug, Clone, Copy)]
+pub struct ToolLimits {
+    /// Most pages `pdf_read_pages` returns per call.
+    pub max_pages: usize,
+    /// Most bytes of text `pdf_read_pages`, `pdf_grep`, and `pdf_grep_workspace` return per call.
+    pub max_text_bytes: usize,
+    /// Wall-clock time a call may take. Tools that write files aren't
+    /// timed out, since their work can't be cancelled and the client would
+    /// be told a write failed that then happens anyway.
+    pub timeout: Duration,
+    /// Most tool calls a session may make in any minute.
+    pub calls_per_minute: Option<u32>,
+}
+
+impl Default for ToolLimits {
+    fn default() -> Self {
+        ToolLimits {
+            max_pages: 200,
+            max_text_bytes: 4 * 1024 * 1024,
+            timeout: Duration::from_secs(60),
+            calls_per_minute: None,
+        }
+    }
+}
+
+impl ToolLimits {
+    /// Run a tool's blocking work on the blocking thread pool, failing the
+    /// call once the timeout passes.
+    ///
+    /// Work that times out is not cancelled (the tools check the deadline
+    /// between pages to stop early), but the session keeps serving requests.
+    async fn run<T, F>(&self, tool: &str, work: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, String> + Send + 'static,
+    {
+        match tokio::time::timeout(self.timeout, run_blocking(tool, work)).await {
+            Ok(result) => result,
+            Err(_) => Err(format!(
+                "Limit exceeded: {} took longer than {}s. Request fewer pages or a more \
+                 specific pattern.",
+                tool,
+                self.timeout.as_secs()
+            )),
+        }
+    }
+}
+
+/// Run a tool's blocking work on the blocking thread pool.
+async fn run_blocking<T, F>(tool: &str, work: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(e) => Err(format!("{} failed: {}", tool, e)),
+    }
+}
+
+/// Local file for a tool's `path` argument, fetching `s3://` objects first.
+fn local_path(path: &str) -> Result<PathBuf, String> {
+    fetch_if_s3(Path::new(path), &DownloadOptions::default()).map_err(|e| format!("{:#}", e))
+}
+
+/// How the operator configured the server. Each session gets its own
+/// [`PdfServer`] built from these.
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Directories from `--root`.
+    pub roots: Vec<PathBuf>,
+    pub limits: ToolLimits,
+    /// Where to record every tool call, if anywhere.
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// Embeds queries for `pdf_semantic_search`.
+    pub embedder: Option<Arc<dyn Embedder>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PdfServer {
     #[allow(dead_code)]
     tool_router: ToolRouter<Self>,
+    /// Directories the operator configured with `--root`.
+    roots: Vec<PathBuf>,
+    /// Workspace roots the client advertised (`file://` URIs only), shared
+    /// by the clones rmcp makes for each request in the session.
+    client_roots: Arc<RwLock<Vec<PathBuf>>>,
+    scratch: Arc<Scratch>,
+    limits: ToolLimits,
+    /// Numbers sessions in the audit log.
+    session: u64,
+    recent_calls: Arc<RecentCalls>,
+    audit_log: Option<Arc<AuditLog>>,
+    #[cfg_attr(not(feature = "semantic"), allow(dead_code))]
+    embedder: Option<Arc<dyn Embedder>>,
 }
 
 impl PdfServer {
-    pub fn new() -> Self {
+    pub fn new(options: ServerOptions) -> Self {
+        static SESSIONS: AtomicU64 = AtomicU64::new(1);
+
+        let tool_router = Self::tool_router();
+        #[cfg(feature = "semantic")]
+        let tool_router = tool_router + Self::semantic_tool_router();
         Self {
-            tool_router: Self::tool_router(),
+            tool_router,
+            roots: options.roots,
+            client_roots: Arc::default(),
+            scratch: Arc::default(),
+            limits: options.limits,
+            session: SESSIONS.fetch_add(1, Ordering::Relaxed),
+            recent_calls: Arc::default(),
+            audit_log: options.audit_log,
+            embedder: options.embedder,
+        }
+    }
+
+    /// Run a tool's blocking work under the session's rate limit and the
+    /// call limits (see [`ToolLimits::run`]), and record the call in the
+    /// audit log.
+    async fn call<T, F>(&self, tool: &str, path: Option<String>, work: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, String> + Send + 'static,
+    {
+        self.call_with(tool, path, true, work).await
+    }
+
+    /// Like [`PdfServer::call`], but without the timeout, for tools that
+    /// write files: the client hears whether the write happened.
+    async fn call_writing<T, F>(
+        &self,
+        tool: &str,
+        path: Option<String>,
+        work: F,
+    ) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, String> + Send + 'static,
+    {
+        self.call_with(tool, path, false, work).await
+    }
+
+    async fn call_with<T, F>(
+        &self,
+        tool: &str,
+        path: Option<String>,
+        timed: bool,
+        work: F,
+    ) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, String> + Send + 'static,
+    {
+        let started = Instant::now();
+        let (result, outcome) = match self
+            .recent_calls
+            .admit(self.limits.calls_per_minute, started)
+        {
+            Ok(()) => {
+                let result = if timed {
+                    self.limits.run(tool, work).await
+                } else {
+                    run_blocking(tool, work).await
+                };
+                let outcome = if result.is_ok() { "ok" } else { "error" };
+                (result, outcome)
+            }
+            Err(e) => (Err(e), "rate_limited"),
+        };
+        if let Some(audit_log) = &self.audit_log {
+            audit_log.record(AuditEntry {
+                timestamp: rfc3339_utc(SystemTime::now()),
+                session: self.session,
+                tool,
+                path: path.as_deref(),
+                duration_ms: started.elapsed().as_millis() as u64,
+                outcome,
+                error: result.as_ref().err().map(String::as_str),
+            });
+        }
+        result
+    }
+
+    /// The client's roots followed by the configured ones.
+    fn all_roots(&self) -> Vec<PathBuf> {
+        let mut roots = self.client_roots.read().unwrap().clone();
+        roots.extend(self.roots.iter().cloned());
+        roots
+    }
+
+    /// Resolve a tool's path argument: a scratch handle to its file, anything
+    /// else against the workspace roots (see [`resolve_in_roots`]).
+    fn resolve(&self, path: &str) -> Result<String, String> {
+        if let Some(name) = path.strip_prefix(SCRATCH_PREFIX) {
+            return self
+                .scratch
+                .path(name)
+                .map(|path| path.display().to_string());
+        }
+        let client_roots = self.client_roots.read().unwrap().clone();
+        resolve_in_roots(path, &client_roots, &self.roots)
+    }
+
+    /// Ask the client for its roots, if it has any.
+    async fn refresh_roots(&self, peer: &Peer<RoleServer>) {
+        let supported = peer
+            .peer_info()
+            .is_some_and(|info| info.capabilities.roots.is_some());
+        if !supported {
+            return;
+        }
+        match peer.list_roots().await {
+            Ok(result) => {
+                *self.client_roots.write().unwrap() = result
+                    .roots
+                    .iter()
+                    .filter_map(|root| root_path(&root.uri))
+                    .collect();
+            }
+            Err(e) => eprintln!("Failed to list the client's roots: {}", e),
+        }
+    }
+}
+
+/// The start times of a session's recent tool calls, for `--rate-limit`.
+#[derive(Debug, Default)]
+struct RecentCalls(Mutex<VecDeque<Instant>>);
+
+impl RecentCalls {
+    /// Count a call made at `now`, or refuse it if the session already made
+    /// `per_minute` calls in the minute before.
+    fn admit(&self, per_minute: Option<u32>, now: Instant) -> Result<(), String> {
+        const MINUTE: Duration = Duration::from_secs(60);
+
+        let Some(per_minute) = per_minute else {
+            return Ok(());
+        };
+        let mut calls = self.0.lock().unwrap();
+        while calls
+            .front()
+            .is_some_and(|&call| now.saturating_duration_since(call) >= MINUTE)
+        {
+            calls.pop_front();
+        }
+        if calls.len() >= per_minute as usize {
+            let wait = calls.front().map_or(MINUTE, |&oldest| {
+                MINUTE - now.saturating_duration_since(oldest)
+            });
+            return Err(format!(
+                "Rate limit exceeded: this server allows {} tool calls per minute. Try again in \
+                 {}s.",
+                per_minute,
+                wait.as_secs() + 1
+            ));
+        }
+        calls.push_back(now);
+        Ok(())
+    }
+}
+
+/// An append-only JSONL record of tool calls, shared by every session of a
+/// server, so operators can review what agents touched.
+#[derive(Debug)]
+pub struct AuditLog {
+    file: Mutex<std::fs::File>,
+}
+
+#[derive(Serialize)]
+struct AuditEntry<'a> {
+    timestamp: Option<String>,
+    session: u64,
+    tool: &'a str,
+    /// The file or directory the call was about, as returned by
+    /// `PdfServer::resolve`: scratch handles become their file and relative
+    /// paths are joined to a root. S3 URLs are logged as given rather than
+    /// as the local copy `local_path` downloads.
+    path: Option<&'a str>,
+    duration_ms: u64,
+    /// "ok", "error", or "rate_limited".
+    outcome: &'a str,
+    error: Option<&'a str>,
+}
+
+impl AuditLog {
+    /// Open `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> Result<AuditLog> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("open audit log: {}", path.display()))?;
+        Ok(AuditLog {
+            file: Mutex::new(file),
+        })
+    }
+
+    fn record(&self, entry: AuditEntry) {
+        let mut line = serde_json::to_string(&entry).expect("audit entries serialize");
+        line.push('\n');
+        // One write per line, so O_APPEND keeps concurrent entries whole.
+        if let Err(e) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            eprintln!("Failed to write to the audit log: {}", e);
+        }
+    }
+}
+
+/// A time as RFC 3339 in UTC, to the second.
+fn rfc3339_utc(time: SystemTime) -> Option<String> {
+    let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    PdfDate::parse(&format_pdf_date_utc(secs)).map(|date| date.to_rfc3339())
+}
+
+const SCRATCH_PREFIX: &str = "scratch:";
+
+/// A session's scratch directory for tool outputs, which tools name with
+/// opaque handles (`scratch:extract-1.pdf`) rather than paths the client
+/// makes up. Created on first use and removed, with everything in it, when
+/// the session's server is dropped.
+#[derive(Debug, Default)]
+struct Scratch {
+    dir: Mutex<Option<PathBuf>>,
+    written: AtomicU64,
+}
+
+impl Scratch {
+    /// A handle and path for a new output of `tool`.
+    fn allocate(&self, tool: &str) -> Result<(String, PathBuf), String> {
+        static SESSIONS: AtomicU64 = AtomicU64::new(0);
+
+        let mut dir = self.dir.lock().unwrap();
+        let dir = match &mut *dir {
+            Some(dir) => dir,
+            None => {
+                let created = std::env::temp_dir().join(format!(
+                    "inpdf-mcp-{}-{}",
+                    std::process::id(),
+                    SESSIONS.fetch_add(1, Ordering::Relaxed)
+                ));
+                std::fs::create_dir_all(&created).map_err(|e| {
+                    format!(
+                        "Failed to create scratch directory {}: {}",
+                        created.display(),
+                        e
+                    )
+                })?;
+                dir.insert(created)
+            }
+        };
+        let name = format!(
+            "{}-{}.pdf",
+            tool.trim_start_matches("pdf_"),
+            self.written.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        Ok((format!("{}{}", SCRATCH_PREFIX, name), dir.join(name)))
+    }
+
+    /// The file a handle (without its `scratch:` prefix) names.
+    fn path(&self, name: &str) -> Result<PathBuf, String> {
+        let unknown = || format!("Unknown scratch handle: {}{}", SCRATCH_PREFIX, name);
+        if Path::new(name).file_name() != Some(name.as_ref()) || name.contains(['/', '\\']) {
+            return Err(unknown());
+        }
+        let dir = self.dir.lock().unwrap();
+        let path = dir.as_ref().ok_or_else(unknown)?.join(name);
+        if !path.is_file() {
+            return Err(unknown());
+        }
+        Ok(path)
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Some(dir) = self.dir.get_mut().unwrap().take() {
+            std::fs::remove_dir_all(dir).ok();
         }
     }
 }
 
+/// Scope a tool's path argument to the client's workspace roots.
+///
+/// Once a client has advertised roots, a relative path resolves against the
+/// first root (client roots, then `--root` ones) that contains it, or the
+/// first client root if none does (e.g. for an output that doesn't exist
+/// yet), and paths that end up outside every root are refused. Without
+/// client roots, paths are used as given. Remote (`s3://`) paths are left
+/// alone.
+fn resolve_in_roots(
+    path: &str,
+    client_roots: &[PathBuf],
+    configured_roots: &[PathBuf],
+) -> Result<String, String> {
+    if client_roots.is_empty() || path.contains("://") {
+        return Ok(path.to_string());
+    }
+    let roots: Vec<&PathBuf> = client_roots.iter().chain(configured_roots).collect();
+    let requested = Path::new(path);
+    let resolved = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        roots
+            .iter()
+            .map(|root| root.join(requested))
+            .find(|candidate| candidate.exists())
+            .unwrap_or_else(|| client_roots[0].join(requested))
+    };
+    if !roots.iter().any(|root| is_within(&resolved, root)) {
+        let roots: Vec<_> = roots.iter().map(|r| r.display().to_string()).collect();
+        return Err(format!(
+            "{} is outside the workspace roots ({})",
+            path,
+            roots.join(", ")
+        ));
+    }
+    Ok(resolved.display().to_string())
+}
+
+/// Whether `path`, which may not exist yet, is inside `root` once symlinks
+/// and `..` are resolved.
+fn is_within(path: &Path, root: &Path) -> bool {
+    let Ok(root) = root.canonicalize() else {
+        return false;
+    };
+    // Canonicalize the deepest part that exists. What's left must be plain
+    // names: file_name() is None for a trailing `..`.
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            let full = rest
+                .iter()
+                .rev()
+                .fold(canonical, |full, name| full.join(name));
+            return full.starts_with(&root);
+        }
+        let (Some(name), Some(parent)) = (existing.file_name(), existing.parent()) else {
+            return false;
+        };
+        rest.push(name);
+        existing = parent;
+    }
+}
+
+/// The local directory a root's `file://` URI names.
+fn root_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let path = path.strip_prefix("localhost").unwrap_or(path);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // file:///C:/work names C:/work on Windows.
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if cfg!(windows) && drive.is_ascii_alphabetic() => &path[1..],
+        _ => &path[..],
+    };
+    Some(PathBuf::from(path))
+}
+
 impl Default for PdfServer {
     fn default() -> Self {
-        Self::new()
+        Self::new(ServerOptions::default())
     }
 }
 
 #[tool_router]
 impl PdfServer {
     #[tool(
-        description = "Get PDF metadata including title, author, creator, producer, creation date, and page count"
+        description = "Get PDF metadata including title, author, creator, producer, creation and modification dates (as RFC 3339), and page count",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
     )]
-    fn pdf_info(
+    async fn pdf_info(
         &self,
         Parameters(PathRequest { path }): Parameters<PathRequest>,
     ) -> Result<Json<PdfInfoResult>, String> {
-        let doc = PdfDocument::open(&path).map_err(|e| e.to_string())?;
-        let info = doc.get_info();
-        Ok(Json(PdfInfoResult {
-            path,
-            page_count: info.page_count,
-            title: info.title,
-            author: info.author,
-            creator: info.creator,
-            producer: info.producer,
-            creation_date: info.creation_date,
-            subject: info.subject,
-            keywords: info.keywords,
-        }))
+        let path = self.resolve(&path)?;
+        self.call("pdf_info", Some(path.clone()), move || {
+            let doc = PdfDocument::open(local_path(&path)?).map_err(|e| e.to_string())?;
+            let info = doc.get_info();
+            Ok(Json(PdfInfoResult {
+                path,
+                page_count: info.page_count,
+                title: info.title,
+                author: info.author,
+                creator: info.creator,
+                producer: info.producer,
+                creation_date: info.creation_date,
+                created: info.created.map(|date| date.to_rfc3339()),
+                modified: info.modified.map(|date| date.to_rfc3339()),
+                subject: info.subject,
+                keywords: info.keywords,
+            }))
+        })
+        .await
     }
 
     #[tool(
-        description = "Get the table of contents (bookmarks/outlines) from a PDF as structured data. Returns empty if the PDF has no embedded bookmarks. Note: Many PDFs lack bookmarks even if they have a visual table of contents in the document text."
+        description = "Get the table of contents (bookmarks/outlines) from a PDF as structured data. If the PDF has no bookmarks, falls back to parsing printed contents pages near the start of the document (source is then \"printed\"; those entries' pages are less reliable). Returns empty if neither is found. For very large outlines, use max_depth to get just the chapters or match to find the relevant sections.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
     )]
-    fn pdf_toc(
+    async fn pdf_toc(
         &self,
-        Parameters(PathRequest { path }): Parameters<PathRequest>,
+        Parameters(mut req): Parameters<PdfTocRequest>,
     ) -> Result<Json<TocResult>, String> {
-        let entries = extract_toc(&path).map_err(|e| e.to_string())?;
-        let flat = flatten_toc(&entries);
-        Ok(Json(TocResult {
-            entries: flat
-                .into_iter()
-                .map(|e| TocEntryResult {
-                    title: e.title,
-                    page: e.page,
-                    level: e.level,
-                })
-                .collect(),
-        }))
+        req.path = self.resolve(&req.path)?;
+        self.call("pdf_toc", Some(req.path.clone()), move || {
+            let pattern = req
+                .pattern
+                .as_deref()
+                .map(|p| Regex::new(p).map_err(|e| format!("Invalid regex: {}", e)))
+                .transpose()?;
+            let cached = get_cached_pdf(local_path(&req.path)?).map_err(|e| e.to_string())?;
+            let mut entries = extract_toc_from_doc(cached.document()).map_err(|e| e.to_string())?;
+            let mut source = "bookmarks";
+            if entries.is_empty() {
+                let total = cached.document().get_pages().len() as u32;
+                let pages: Vec<u32> = (1..=total.min(DEFAULT_SCAN_PAGES)).collect();
+                entries = detect_printed_toc(&cached, &pages).map_err(|e| e.to_string())?;
+                source = "printed";
+            }
+            let entries = filter_toc(&entries, req.max_depth, pattern.as_ref());
+            let flat = flatten_toc(&entries);
+            Ok(Json(TocResult {
+                source: source.to_string(),
+                entries: flat
+                    .into_iter()
+                    .map(|e| TocEntryResult {
+                        title: e.title,
+                        page: e.page,
+                        external: e.external.map(|target| target.to_string()),
+                        level: e.level,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
     }
 
     #[tool(
-        description = "Get the mapping between physical page numbers (1-indexed) and logical page labels. Logical labels are the printed page numbers shown in the document (e.g., \"i\", \"ii\", \"1\", \"2\", \"A-1\"), which often differ from the physical page position in the file."
+        description = "Find the chain of table-of-contents entries containing a physical page, from outermost to innermost (e.g. \"Part II > Chapter 5 > 5.3 Results\"). Use this to attribute extracted text to its section. Returns an empty path if the PDF has no bookmarks or the page precedes the first entry, and an error if the page is out of range.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
     )]
-    fn pdf_page_labels(
+    async fn pdf_outline_path(
+        &self,
+        Parameters(mut req): Parameters<PdfOutlinePathRequest>,
+    ) -> Result<Json<OutlinePathResult>, String> {
+        req.path = self.resolve(&req.path)?;
+        self.call("pdf_outline_path", Some(req.path.clone()), move || {
+            let doc = PdfDocument::open(local_path(&req.path)?).map_err(|e| e.to_string())?;
+            let total_pages = doc.page_count();
+            if req.page == 0 || req.page > total_pages {
+                return Err(format!(
+                    "Page {} is out of range (1-{})",
+                    req.page, total_pages
+                ));
+            }
+            let entries = doc.toc().map_err(|e| e.to_string())?;
+            let chain = outline_path(&entries, req.page);
+            let breadcrumb = chain
+                .iter()
+                .map(|e| e.title.as_str())
+                .collect::<Vec<_>>()
+                .join(" > ");
+
+            Ok(Json(OutlinePathResult {
+                page: req.page,
+                breadcrumb,
+                path: chain
+                    .into_iter()
+                    .map(|e| TocEntryResult {
+                        title: e.title,
+                        page: e.page,
+                        external: e.external.map(|target| target.to_string()),
+                        level: e.level,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "List the figures in a PDF with their page, alt text (from the structure tree of tagged PDFs), and caption (e.g. \"Figure 3: ...\"). Use this to reason about figures whose images you cannot see. Untagged PDFs report images drawn on each page paired with caption lines from that page's text.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    async fn pdf_figures(
+        &self,
+        Parameters(PathRequest { path }): Parameters<PathRequest>,
+    ) -> Result<Json<FiguresResult>, String> {
+        let path = self.resolve(&path)?;
+        self.call("pdf_figures", Some(path.clone()), move || {
+            let cached = get_cached_pdf(local_path(&path)?).map_err(|e| e.to_string())?;
+            let figures = find_figures(&cached).map_err(|e| e.to_string())?;
+            Ok(Json(FiguresResult {
+                figures: figures
+                    .into_iter()
+                    .map(|f| FigureResult {
+                        page: f.page,
+                        alt: f.alt,
+                        caption: f.caption,
+                        image: f.image,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Get the mapping between physical page numbers (1-indexed) and logical page labels. Logical labels are the printed page numbers shown in the document (e.g., \"i\", \"ii\", \"1\", \"2\", \"A-1\"), which often differ from the physical page position in the file.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    async fn pdf_page_labels(
         &self,
         Parameters(PathRequest { path }): Parameters<PathRequest>,
     ) -> Result<Json<PageLabelsResult>, String> {
-        let labels = extract_page_labels(&path).map_err(|e| e.to_string())?;
-        Ok(Json(PageLabelsResult {
-            labels: labels
-                .into_iter()
-                .map(|l| PageLabelResult {
-                    physical_page: l.physical_page,
-                    logical_label: l.logical_label,
-                })
-                .collect(),
-        }))
+        let path = self.resolve(&path)?;
+        self.call("pdf_page_labels", Some(path.clone()), move || {
+            let labels = extract_page_labels(local_path(&path)?).map_err(|e| e.to_string())?;
+            Ok(Json(PageLabelsResult {
+                labels: labels
+                    .into_iter()
+                    .map(|l| PageLabelResult {
+                        physical_page: l.physical_page,
+                        logical_label: l.logical_label,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Convert between logical page labels and physical page numbers. Pass label (e.g. \"xii\" or \"A-3\") to get the physical page(s) carrying it, or page to get its printed label. Use this to turn citations like \"see p. xii\" into pdf_read_pages calls.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    async fn pdf_resolve_label(
+        &self,
+        Parameters(mut req): Parameters<PdfResolveLabelRequest>,
+    ) -> Result<Json<ResolveLabelResult>, String> {
+        req.path = self.resolve(&req.path)?;
+        self.call("pdf_resolve_label", Some(req.path.clone()), move || {
+            let labels = extract_page_labels(local_path(&req.path)?).map_err(|e| e.to_string())?;
+
+            let matches = match (req.label, req.page) {
+                (Some(label), None) => resolve_label(&labels, &label)
+                    .into_iter()
+                    .map(|physical_page| PageLabelResult {
+                        physical_page,
+                        logical_label: labels[physical_page as usize - 1].logical_label.clone(),
+                    })
+                    .collect(),
+                (None, Some(page)) => {
+                    let label = labels.get((page as usize).wrapping_sub(1)).ok_or_else(|| {
+                        format!("Page {} is out of range (1-{})", page, labels.len())
+                    })?;
+                    vec![PageLabelResult {
+                        physical_page: label.physical_page,
+                        logical_label: label.logical_label.clone(),
+                    }]
+                }
+                _ => return Err("Specify exactly one of label or page".to_string()),
+            };
+
+            Ok(Json(ResolveLabelResult { matches }))
+        })
+        .await
     }
 
     #[tool(
-        description = "Search for text in a PDF using a regular expression pattern. Case-sensitive by default; set case_insensitive: true for case-insensitive matching. Examples: \"error.*failed\", \"section\\\\s+\\\\d+\", \"foo|bar\"."
+        description = "Search for text in a PDF using a regular expression pattern. Case-sensitive by default; set case_insensitive: true for case-insensitive matching. Examples: \"error.*failed\", \"section\\\\s+\\\\d+\", \"foo|bar\".",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
     )]
-    fn pdf_grep(
+    async fn pdf_grep(
         &self,
-        Parameters(req): Parameters<PdfGrepRequest>,
+        Parameters(mut req): Parameters<PdfGrepRequest>,
     ) -> Result<Json<GrepResult>, String> {
+        req.path = self.resolve(&req.path)?;
         let regex = RegexBuilder::new(&req.pattern)
             .case_insensitive(req.case_insensitive)
             .build()
             .map_err(|e| format!("Invalid regex: {}", e))?;
 
-        let matches =
-            grep_pdf(&req.path, &regex, req.max_results as usize).map_err(|e| e.to_string())?;
+        let limits = self.limits;
+        let deadline = Instant::now() + limits.timeout;
+        let matches = self
+            .call("pdf_grep", Some(req.path.clone()), move || {
+                let max_results = req.max_results.max(0) as usize;
+                let cached =
+                    get_cached_pdf_for_text(local_path(&req.path)?).map_err(|e| e.to_string())?;
+                let mut matches = grep_cached_until(
+                    &cached,
+                    &regex,
+                    max_results,
+                    req.max_per_page,
+                    Some(deadline),
+                )
+                .map_err(|e| format!("{:#}", e))?;
+                if req.include_attachments && matches.len() < max_results {
+                    let found = grep_attachments(&cached, &regex, max_results - matches.len())
+                        .map_err(|e| e.to_string())?;
+                    matches.extend(found);
+                }
 
-        Ok(Json(GrepResult {
-            matches: matches
-                .into_iter()
-                .map(|m| GrepMatchResult {
-                    page: m.page,
-                    line_number: m.line_number,
-                    text: m.text,
-                    match_start: m.match_start,
-                    match_end: m.match_end,
-                })
-                .collect(),
-        }))
+                let mut results = Vec::with_capacity(matches.len());
+                for m in matches {
+                    let snippet = match (req.snippet_chars, &m.attachment) {
+                        (Some(window), None) => {
+                            let text = cached.page_text(m.page).map_err(|e| e.to_string())?;
+                            Some(snippet(&text, m.page_start, m.page_end, window))
+                        }
+                        _ => None,
+                    };
+                    results.push(GrepMatchResult {
+                        page: m.page,
+                        line_number: m.line_number,
+                        text: m.text,
+                        match_start: m.match_start,
+                        match_end: m.match_end,
+                        page_start: m.page_start,
+                        page_end: m.page_end,
+                        snippet_start: snippet.as_ref().map(|s| s.start),
+                        snippet: snippet.map(|s| s.text),
+                        attachment: m.attachment,
+                        recovered: m.recovered,
+                    });
+                }
+
+                let bytes: usize = results
+                    .iter()
+                    .map(|m| m.text.len() + m.snippet.as_ref().map_or(0, String::len))
+                    .sum();
+                if bytes > limits.max_text_bytes {
+                    return Err(format!(
+                        "Limit exceeded: the {} matching lines total {} bytes, more than the {} \
+                         bytes this server returns per call. Lower max_results or use a more \
+                         specific pattern.",
+                        results.len(),
+                        bytes,
+                        limits.max_text_bytes
+                    ));
+                }
+                Ok(results)
+            })
+            .await?;
+
+        Ok(Json(GrepResult { matches }))
     }
 
     #[tool(
-        description = "Extract text content from specific pages of a PDF. Use page range syntax like '1-5,10,15-end'."
+        description = "Search every PDF under a directory (or the workspace roots) with a regular expression. Matches are grouped by file and include the logical page label, so one call can answer \"which of these documents mentions X\". Set use_index to search the text sidecars from `inpdf textify` where they are up to date.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
     )]
-    fn pdf_read_pages(
+    async fn pdf_grep_workspace(
         &self,
-        Parameters(req): Parameters<PdfReadPagesRequest>,
+        Parameters(mut req): Parameters<PdfGrepWorkspaceRequest>,
+    ) -> Result<Json<WorkspaceGrepResult>, String> {
+        req.directory = req.directory.map(|dir| self.resolve(&dir)).transpose()?;
+        let roots = self.all_roots();
+        let limits = self.limits;
+        let deadline = Instant::now() + limits.timeout;
+        self.call("pdf_grep_workspace", req.directory.clone(), move || {
+            let regex = RegexBuilder::new(&req.pattern)
+                .case_insensitive(req.case_insensitive)
+                .build()
+                .map_err(|e| format!("Invalid regex: {}", e))?;
+
+            let directories = match req.directory {
+                Some(dir) => vec![PathBuf::from(dir)],
+                None if !roots.is_empty() => roots,
+                None => return Err("No directory given and the server has no roots".to_string()),
+            };
+
+            let mut result = WorkspaceGrepResult {
+                files: Vec::new(),
+                files_searched: 0,
+                errors: Vec::new(),
+            };
+            let mut remaining = req.max_results.max(0) as usize;
+            let mut bytes = 0;
+            // Stop scanning once the call has timed out, rather than going on
+            // to search (and cache) the rest of the tree for nobody.
+            let timed_out = |searched: u32| {
+                format!(
+                    "Limit exceeded: searching timed out after {} files. Search a smaller \
+                     directory or use a more specific pattern.",
+                    searched
+                )
+            };
+
+            for path in directories.iter().flat_map(find_pdfs) {
+                if remaining == 0 {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    return Err(timed_out(result.files_searched));
+                }
+                result.files_searched += 1;
+                let path_str = path.display().to_string();
+
+                let budget = req.max_per_file.map_or(remaining, |n| n.min(remaining));
+                let sidecar = req.use_index.then(|| read_text_sidecar(&path)).flatten();
+                let searched = match sidecar {
+                    // The PDF itself is only opened for the labels of pages
+                    // that matched.
+                    Some(pages) => {
+                        let matches = grep_page_texts(
+                            &pages,
+                            &regex,
+                            budget,
+                            req.max_per_page,
+                            Some(deadline),
+                        );
+                        let labels = if matches.is_empty() {
+                            Ok(Vec::new())
+                        } else {
+                            PdfDocument::open_for_text(&path).map(|doc| {
+                                extract_page_labels_from_doc(doc.doc()).unwrap_or_default()
+                            })
+                        };
+                        labels.map(|labels| (matches, labels))
+                    }
+                    None => PdfDocument::open_for_text(&path).and_then(|doc| {
+                        let matches = grep_cached_until(
+                            doc.cached(),
+                            &regex,
+                            budget,
+                            req.max_per_page,
+                            Some(deadline),
+                        )?;
+                        let labels = extract_page_labels_from_doc(doc.doc()).unwrap_or_default();
+                        Ok((matches, labels))
+                    }),
+                };
+                let (matches, labels) = match searched {
+                    Ok(found) => found,
+                    Err(_) if Instant::now() >= deadline => {
+                        return Err(timed_out(result.files_searched));
+                    }
+                    Err(e) => {
+                        result.errors.push(FileErrorResult {
+                            path: path_str,
+                            error: e.to_string(),
+                        });
+                        continue;
+                    }
+                };
+                if matches.is_empty() {
+                    continue;
+                }
+                remaining -= matches.len();
+                bytes += matches.iter().map(|m| m.text.len()).sum::<usize>();
+                if bytes > limits.max_text_bytes {
+                    return Err(format!(
+                        "Limit exceeded: the matching lines up to {} total more than the {} \
+                         bytes this server returns per call. Lower max_results or use a more \
+                         specific pattern.",
+                        path_str, limits.max_text_bytes
+                    ));
+                }
+
+                result.files.push(FileGrepResult {
+                    path: path_str,
+                    matches: matches
+                        .into_iter()
+                        .map(|m| WorkspaceMatchResult {
+                            page: m.page,
+                            label: labels
+                                .get(m.page as usize - 1)
+                                .map(|l| l.logical_label.clone()),
+                            line_number: m.line_number,
+                            text: m.text,
+                            match_start: m.match_start,
+                            match_end: m.match_end,
+                            page_start: m.page_start,
+                            page_end: m.page_end,
+                        })
+                        .collect(),
+                });
+            }
+
+            Ok(Json(result))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "List the PDFs under a directory (or the workspace roots) with their size and modification time, and optionally page count. Filter with a glob. Use this to find out which documents exist before searching or reading them.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    async fn pdf_list_files(
+        &self,
+        Parameters(mut req): Parameters<PdfListFilesRequest>,
+    ) -> Result<Json<ListFilesResult>, String> {
+        req.directory = req.directory.map(|dir| self.resolve(&dir)).transpose()?;
+        let roots = self.all_roots();
+        let deadline = Instant::now() + self.limits.timeout;
+        self.call("pdf_list_files", req.directory.clone(), move || {
+            let directories = match req.directory {
+                Some(dir) => vec![PathBuf::from(dir)],
+                None if !roots.is_empty() => roots,
+                None => return Err("No directory given and the server has no roots".to_string()),
+            };
+
+            let max_results = req.max_results.max(0) as usize;
+            let mut result = ListFilesResult {
+                files: Vec::new(),
+                truncated: false,
+            };
+            for root in &directories {
+                for path in find_pdfs(root) {
+                    let relative = path
+                        .strip_prefix(root)
+                        .unwrap_or(&path)
+                        .components()
+                        .map(|part| part.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    if let Some(pattern) = &req.pattern {
+                        if !glob_match(pattern, &relative) {
+                            continue;
+                        }
+                    }
+                    if result.files.len() == max_results {
+                        result.truncated = true;
+                        return Ok(Json(result));
+                    }
+                    let Ok(metadata) = std::fs::metadata(&path) else {
+                        continue;
+                    };
+                    let modified = metadata.modified().ok().and_then(rfc3339_utc);
+                    // Past the deadline, list the rest without counting.
+                    let page_count = (req.page_counts && Instant::now() < deadline)
+                        .then(|| PdfDocument::open(&path).ok())
+                        .flatten()
+                        .map(|doc| doc.page_count());
+                    result.files.push(FileEntryResult {
+                        path: path.display().to_string(),
+                        root: root.display().to_string(),
+                        size: metadata.len(),
+                        modified,
+                        page_count,
+                    });
+                }
+            }
+            Ok(Json(result))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Extract text content from specific pages of a PDF. Use page range syntax like '1-5,10,15-end'. Pages whose text cannot be extracted come back with an `error` instead of failing the whole request.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = false)
+    )]
+    async fn pdf_read_pages(
+        &self,
+        Parameters(mut req): Parameters<PdfReadPagesRequest>,
     ) -> Result<Json<ReadPagesResult>, String> {
-        let doc = PdfDocument::open(&req.path).map_err(|e| e.to_string())?;
-        let total = doc.page_count();
-        let page_list = expand_page_ranges(&req.pages, total).map_err(|e| e.to_string())?;
-        let texts = extract_text_pages(&req.path, &page_list).map_err(|e| e.to_string())?;
+        req.path = self.resolve(&req.path)?;
+        let limits = self.limits;
+        let deadline = Instant::now() + limits.timeout;
+        let texts = self
+            .call("pdf_read_pages", Some(req.path.clone()), move || {
+                let doc = PdfDocument::open_for_text(local_path(&req.path)?)
+                    .map_err(|e| e.to_string())?;
+                let total = doc.page_count();
+                let page_list = expand_page_ranges(&req.pages, total).map_err(|e| e.to_string())?;
+                if page_list.len() > limits.max_pages {
+                    return Err(format!(
+                        "Limit exceeded: {} pages requested, but this server returns at most {} \
+                         per call. Read the range in smaller batches (e.g. '{}-{}').",
+                        page_list.len(),
+                        limits.max_pages,
+                        page_list[0],
+                        page_list[limits.max_pages - 1]
+                    ));
+                }
+
+                let mut texts =
+                    read_within_limits(&doc, &page_list, limits.max_text_bytes, deadline)?;
+                if req.annotate_sections {
+                    annotate_sections(doc.doc(), &mut texts).map_err(|e| e.to_string())?;
+                }
+                Ok(texts)
+            })
+            .await?;
 
         Ok(Json(ReadPagesResult {
             pages: texts
@@ -191,30 +1232,224 @@ impl PdfServer {
                 .map(|t| PageTextResult {
                     page: t.page,
                     text: t.text,
+                    error: t.error,
                 })
                 .collect(),
         }))
     }
 
     #[tool(
-        description = "Extract specific pages from a PDF and save them to a new file. Use page range syntax like '1-5,10,15-end'. The output directory must already exist."
+        description = "Extract specific pages from a PDF and save them to a new file. Use page range syntax like '1-5,10,15-end'. Links to pages left out are removed. The output directory must already exist. Set return_base64 to receive the PDF bytes in the response (useful when the server does not share a filesystem with the client), or scratch to have the server keep the file for this session and return a handle that other tools (e.g. pdf_read_pages) accept as a path.",
+        annotations(
+            read_only_hint = false,
+            destructive_hint = true,
+            idempotent_hint = true,
+            open_world_hint = false
+        )
     )]
-    fn pdf_extract(
+    async fn pdf_extract(
         &self,
-        Parameters(req): Parameters<PdfExtractRequest>,
+        Parameters(mut req): Parameters<PdfExtractRequest>,
     ) -> Result<Json<ExtractResult>, String> {
-        let doc = PdfDocument::open(&req.path).map_err(|e| e.to_string())?;
-        let total = doc.page_count();
-        let page_list = expand_page_ranges(&req.pages, total).map_err(|e| e.to_string())?;
-        let page_count = page_list.len() as u32;
+        req.path = self.resolve(&req.path)?;
+        req.output = req.output.map(|output| self.resolve(&output)).transpose()?;
+        let handle = if req.scratch {
+            if req.output.is_some() {
+                return Err("Specify only one of output and scratch".to_string());
+            }
+            let (handle, path) = self.scratch.allocate("pdf_extract")?;
+            req.output = Some(path.display().to_string());
+            Some(handle)
+        } else {
+            None
+        };
+        self.call_writing("pdf_extract", Some(req.path.clone()), move || {
+            let doc = PdfDocument::open(&req.path).map_err(|e| e.to_string())?;
+            let total = doc.page_count();
+            let page_list = expand_page_ranges(&req.pages, total).map_err(|e| e.to_string())?;
+            let page_count = page_list.len() as u32;
 
-        let mut new_doc = doc.extract_pages(&page_list).map_err(|e| e.to_string())?;
-        PdfDocument::save(&mut new_doc, &req.output).map_err(|e| e.to_string())?;
+            if req.output.is_none() && !req.return_base64 {
+                return Err(
+                    "One of output, scratch, or return_base64 must be specified".to_string()
+                );
+            }
 
-        Ok(Json(ExtractResult {
-            output_path: req.output,
-            page_count,
-        }))
+            let mut new_doc = doc.extract_pages(&page_list).map_err(|e| e.to_string())?;
+            fix_extracted_links(&mut new_doc, DanglingLinks::Drop);
+            if let Some(output) = &req.output {
+                PdfDocument::save(&mut new_doc, output).map_err(|e| e.to_string())?;
+            }
+            let data_base64 = if req.return_base64 {
+                let bytes = PdfDocument::to_bytes(&mut new_doc).map_err(|e| e.to_string())?;
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes))
+            } else {
+                None
+            };
+
+            Ok(Json(ExtractResult {
+                output_path: req.output,
+                handle,
+                page_count,
+                data_base64,
+            }))
+        })
+        .await
+    }
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfSemanticSearchRequest {
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute. It must have been indexed with `inpdf index embed`."
+    )]
+    pub path: String,
+    #[schemars(description = "What to look for, in plain language")]
+    pub query: String,
+    #[schemars(description = "Number of passages to return (default: 5)")]
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+}
+
+#[cfg(feature = "semantic")]
+fn default_top_k() -> usize {
+    5
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SemanticHitResult {
+    pub page: u32,
+    /// Cosine similarity to the query, from -1 to 1.
+    pub score: f32,
+    pub text: String,
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SemanticSearchResult {
+    pub hits: Vec<SemanticHitResult>,
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+pub struct PdfSearchRequest {
+    #[schemars(
+        description = "Path to the PDF file, relative to a workspace root or absolute. It must have been indexed with `inpdf index embed`."
+    )]
+    pub path: String,
+    #[schemars(description = "What to look for, in plain language")]
+    pub query: String,
+    #[schemars(
+        description = "Regular expression for exact matches (default: the query as case-insensitive literal text)"
+    )]
+    pub pattern: Option<String>,
+    #[schemars(description = "Number of pages to return (default: 5)")]
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SearchHitResult {
+    pub page: u32,
+    /// Relevance score; only meaningful relative to other hits.
+    pub score: f32,
+    /// Lines on the page that matched the pattern.
+    pub exact_matches: usize,
+    /// Similarity of the page's best passage to the query, if it was ranked.
+    pub similarity: Option<f32>,
+    /// The first matching line, or else the best passage.
+    pub snippet: String,
+}
+
+#[cfg(feature = "semantic")]
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SearchResult {
+    pub hits: Vec<SearchHitResult>,
+}
+
+#[cfg(feature = "semantic")]
+#[tool_router(router = semantic_tool_router)]
+impl PdfServer {
+    #[tool(
+        description = "Find the passages of a PDF closest in meaning to a plain-language query, ranked by embedding similarity, with their pages. Finds paraphrased content that pdf_grep's regular expressions miss. The PDF must have been indexed with `inpdf index embed`.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = true)
+    )]
+    async fn pdf_semantic_search(
+        &self,
+        Parameters(mut req): Parameters<PdfSemanticSearchRequest>,
+    ) -> Result<Json<SemanticSearchResult>, String> {
+        req.path = self.resolve(&req.path)?;
+        let embedder = self.embedder.clone().ok_or_else(|| {
+            "This server has no embedding model; start it with --embedding-endpoint or \
+             --onnx-model"
+                .to_string()
+        })?;
+        self.call("pdf_semantic_search", Some(req.path.clone()), move || {
+            let path = Path::new(&req.path);
+            let index = crate::semantic::Index::load(&crate::semantic::index_path(path), path)
+                .map_err(|e| format!("{:#}", e))?;
+            let hits = index
+                .search(embedder.as_ref(), &req.query, req.top_k)
+                .map_err(|e| format!("{:#}", e))?;
+            Ok(Json(SemanticSearchResult {
+                hits: hits
+                    .into_iter()
+                    .map(|hit| SemanticHitResult {
+                        page: hit.page,
+                        score: hit.score,
+                        text: hit.text,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
+    }
+
+    #[tool(
+        description = "Find the pages of a PDF most relevant to a query, one hit per page, most relevant first. Pages with exact matches for a pattern and pages with passages close in meaning to the query are merged into one ranking, so a page strong on either (or good on both) comes first. Each hit has a snippet, its exact match count and its similarity. The PDF must have been indexed with `inpdf index embed`.",
+        annotations(read_only_hint = true, idempotent_hint = true, open_world_hint = true)
+    )]
+    async fn pdf_search(
+        &self,
+        Parameters(mut req): Parameters<PdfSearchRequest>,
+    ) -> Result<Json<SearchResult>, String> {
+        req.path = self.resolve(&req.path)?;
+        let embedder = self.embedder.clone().ok_or_else(|| {
+            "This server has no embedding model; start it with --embedding-endpoint or \
+             --onnx-model"
+                .to_string()
+        })?;
+        let pattern = match &req.pattern {
+            Some(pattern) => RegexBuilder::new(pattern).build(),
+            None => RegexBuilder::new(&regex::escape(&req.query))
+                .case_insensitive(true)
+                .build(),
+        }
+        .map_err(|e| format!("Invalid pattern: {}", e))?;
+        self.call("pdf_search", Some(req.path.clone()), move || {
+            let path = Path::new(&req.path);
+            let index = crate::semantic::Index::load(&crate::semantic::index_path(path), path)
+                .map_err(|e| format!("{:#}", e))?;
+            let hits = index
+                .hybrid_search(embedder.as_ref(), path, &req.query, &pattern, req.top_k)
+                .map_err(|e| format!("{:#}", e))?;
+            Ok(Json(SearchResult {
+                hits: hits
+                    .into_iter()
+                    .map(|hit| SearchHitResult {
+                        page: hit.page,
+                        score: hit.score,
+                        exact_matches: hit.exact_matches,
+                        similarity: hit.similarity,
+                        snippet: hit.snippet,
+                    })
+                    .collect(),
+            }))
+        })
+        .await
     }
 }
 
@@ -228,7 +1463,12 @@ pub struct PdfInfoResult {
     pub author: Option<String>,
     pub creator: Option<String>,
     pub producer: Option<String>,
+    /// Creation date as written in the PDF (`D:YYYYMMDDHHmmSS...`).
     pub creation_date: Option<String>,
+    /// Creation date in RFC 3339; an offset of -00:00 means unknown.
+    pub created: Option<String>,
+    /// Last modification date in RFC 3339.
+    pub modified: Option<String>,
     pub subject: Option<String>,
     pub keywords: Option<String>,
 }
@@ -237,14 +1477,28 @@ pub struct PdfInfoResult {
 pub struct TocEntryResult {
     pub title: String,
     pub page: Option<u32>,
+    /// Target outside the document (another PDF, a file to launch, or a
+    /// URL) for entries without a page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external: Option<String>,
     pub level: u32,
 }
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct TocResult {
+    /// "bookmarks" for the document outline, "printed" for entries parsed
+    /// from contents pages.
+    pub source: String,
     pub entries: Vec<TocEntryResult>,
 }
 
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct OutlinePathResult {
+    pub page: u32,
+    pub breadcrumb: String,
+    pub path: Vec<TocEntryResult>,
+}
+
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct PageLabelResult {
     pub physical_page: u32,
@@ -256,13 +1510,35 @@ pub struct PageLabelsResult {
     pub labels: Vec<PageLabelResult>,
 }
 
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ResolveLabelResult {
+    pub matches: Vec<PageLabelResult>,
+}
+
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct GrepMatchResult {
+    /// Page number; 0 for matches in text attachments.
     pub page: u32,
     pub line_number: u32,
     pub text: String,
+    /// Byte offsets of the match in `text`.
     pub match_start: u32,
     pub match_end: u32,
+    /// Character offsets of the match in the page's full text, as
+    /// pdf_read_pages returns it without annotate_sections.
+    pub page_start: u32,
+    pub page_end: u32,
+    /// Page text around the match, when snippet_chars was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Character offset of the snippet in the page's full text.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet_start: Option<u32>,
+    /// Attachment the match was found in, if not the document itself.
+    pub attachment: Option<String>,
+    /// The page's text was recovered from glyph names of fonts without a
+    /// Unicode mapping, so the match may be incomplete or wrong.
+    pub recovered: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -270,10 +1546,79 @@ pub struct GrepResult {
     pub matches: Vec<GrepMatchResult>,
 }
 
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct WorkspaceMatchResult {
+    pub page: u32,
+    pub label: Option<String>,
+    pub line_number: u32,
+    pub text: String,
+    pub match_start: u32,
+    pub match_end: u32,
+    /// Character offsets of the match in the page's full text.
+    pub page_start: u32,
+    pub page_end: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FileGrepResult {
+    pub path: String,
+    pub matches: Vec<WorkspaceMatchResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FileErrorResult {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct WorkspaceGrepResult {
+    pub files: Vec<FileGrepResult>,
+    pub files_searched: u32,
+    pub errors: Vec<FileErrorResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FileEntryResult {
+    pub path: String,
+    /// The root (or directory) the file was found under.
+    pub root: String,
+    pub size: u64,
+    /// Modification time (RFC 3339, UTC).
+    pub modified: Option<String>,
+    /// Only with page_counts, and not for files that fail to open or that
+    /// come after the time limit.
+    pub page_count: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListFilesResult {
+    pub files: Vec<FileEntryResult>,
+    /// Whether more files matched than max_results.
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FigureResult {
+    pub page: u32,
+    pub alt: Option<String>,
+    pub caption: Option<String>,
+    /// Image XObject name, for figures found in untagged PDFs.
+    pub image: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FiguresResult {
+    pub figures: Vec<FigureResult>,
+}
+
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct PageTextResult {
     pub page: u32,
     pub text: String,
+    /// Why the page's text could not be extracted (`text` is then empty).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -283,28 +1628,86 @@ pub struct ReadPagesResult {
 
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ExtractResult {
-    pub output_path: String,
+    pub output_path: Option<String>,
+    /// The handle to pass other tools as a path, when `scratch` was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
     pub page_count: u32,
+    /// The generated PDF, base64-encoded, when `return_base64` was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_base64: Option<String>,
 }
 
 #[tool_handler]
 impl ServerHandler for PdfServer {
     fn get_info(&self) -> ServerInfo {
+        let mut instructions = String::from(
+            "PDF navigation and manipulation tools. Use pdf_info to get document metadata, \
+             pdf_toc for table of contents, pdf_grep to search text (pdf_grep_workspace to search \
+             a whole directory), pdf_list_files to see which PDFs exist, pdf_read_pages to extract \
+             text from specific pages, pdf_figures to list figures with their alt text and \
+             captions, and pdf_extract to create new PDFs from page ranges. \
+             pdf_extract is the only tool that writes files; all others are read-only.",
+        );
+        instructions.push_str(
+            " If the client advertises workspace roots, paths may be relative to them, and \
+             paths outside them are refused.",
+        );
+        if !self.roots.is_empty() {
+            let roots: Vec<_> = self.roots.iter().map(|r| r.display().to_string()).collect();
+            instructions.push_str(&format!(" Configured roots: {}.", roots.join(", ")));
+        }
+
         ServerInfo {
-            instructions: Some(
-                "PDF navigation and manipulation tools. Use pdf_info to get document metadata, \
-                 pdf_toc for table of contents, pdf_grep to search text, pdf_read_pages to extract \
-                 text from specific pages, and pdf_extract to create new PDFs from page ranges."
-                    .to_string(),
-            ),
+            instructions: Some(instructions),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
+            server_info: Implementation {
+                name: env!("CARGO_PKG_NAME").to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            },
             ..Default::default()
         }
     }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.refresh_roots(&context.peer).await;
+    }
+
+    async fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) {
+        self.refresh_roots(&context.peer).await;
+    }
+}
+
+/// Every tool the server registers (name, description, input and output
+/// schemas, annotations), with the server's instructions and limits, as
+/// JSON. Nothing is served; this is for generating client bindings and
+/// reviewing what a deployment exposes.
+pub fn describe(options: ServerOptions) -> Result<String> {
+    let limits = options.limits;
+    let server = PdfServer::new(options);
+    let info = server.get_info();
+    let mut tools = server.tool_router.list_all();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    let description = serde_json::json!({
+        "server": info.server_info,
+        "instructions": info.instructions,
+        "roots": server.roots,
+        "limits": {
+            "max_pages": limits.max_pages,
+            "max_text_bytes": limits.max_text_bytes,
+            "timeout_secs": limits.timeout.as_secs(),
+            "calls_per_minute": limits.calls_per_minute,
+        },
+        "tools": tools,
+    });
+    let mut json = serde_json::to_string_pretty(&description)?;
+    json.push('\n');
+    Ok(json)
 }
 
-pub async fn run_server() -> Result<()> {
-    let server = PdfServer::new();
+pub async fn run_server(options: ServerOptions) -> Result<()> {
+    let server = PdfServer::new(options);
 
     // Serve using stdin/stdout as a tuple
     let service = server
@@ -315,3 +1718,258 @@ pub async fn run_server() -> Result<()> {
 
     Ok(())
 }
+
+/// Serve MCP sessions over a Unix domain socket.
+///
+/// Each accepted connection gets its own session, so several local clients
+/// can share one long-running server process. Access control is left to the
+/// socket file's permission bits.
+#[cfg(unix)]
+pub async fn run_socket_server(socket: &Path, mode: u32, options: ServerOptions) -> Result<()> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // A socket left behind by a previous run would make bind() fail, but
+    // refuse to clobber anything that isn't a socket.
+    if let Ok(metadata) = std::fs::symlink_metadata(socket) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("Refusing to replace non-socket file: {}", socket.display());
+        }
+        std::fs::remove_file(socket)
+            .with_context(|| format!("remove stale socket: {}", socket.display()))?;
+    }
+
+    let listener = bind_private(socket, mode)?;
+
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                // Errors such as running out of file descriptors or a client
+                // hanging up early only affect one connection.
+                Err(e) => {
+                    eprintln!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        };
+
+        let server = PdfServer::new(options.clone());
+        tokio::spawn(async move {
+            let (read, write) = stream.into_split();
+            match server.serve((read, write)).await {
+                Ok(service) => {
+                    let _ = service.waiting().await;
+                }
+                Err(e) => eprintln!("MCP session failed to initialize: {}", e),
+            }
+        });
+    }
+
+    std::fs::remove_file(socket).ok();
+    Ok(())
+}
+
+/// Bind a listening socket at `socket` with permissions `mode`.
+///
+/// The socket is created inside a new directory only its owner can enter,
+/// given its permissions there, and then moved into place, so nobody can
+/// connect while it still has the permissions the umask gave it.
+#[cfg(unix)]
+fn bind_private(socket: &Path, mode: u32) -> Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let name = socket
+        .file_name()
+        .with_context(|| format!("Not a socket path: {}", socket.display()))?;
+    let mut dir_name = std::ffi::OsString::from(".");
+    dir_name.push(name);
+    dir_name.push(format!(".inpdf-{}", std::process::id()));
+    let dir = socket.with_file_name(dir_name);
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("create directory: {}", dir.display()))?;
+
+    let private = dir.join(name);
+    let bound = tokio::net::UnixListener::bind(&private)
+        .with_context(|| format!("bind socket: {}", socket.display()))
+        .and_then(|listener| {
+            std::fs::set_permissions(&private, std::fs::Permissions::from_mode(mode))
+                .with_context(|| format!("set permissions on socket: {}", socket.display()))?;
+            std::fs::rename(&private, socket)
+                .with_context(|| format!("move socket into place: {}", socket.display()))?;
+            Ok(listener)
+        });
+    std::fs::remove_file(&private).ok();
+    std::fs::remove_dir(&dir).ok();
+    bound
+}
+
+/// Extract `pages` in order, failing once their text exceeds `max_bytes`
+/// (naming the page that crossed it) or `deadline` passes.
+fn read_within_limits(
+    doc: &PdfDocument,
+    pages: &[u32],
+    max_bytes: usize,
+    deadline: Instant,
+) -> Result<Vec<PageText>, String> {
+    let mut texts = Vec::new();
+    let mut bytes = 0;
+    // Extract a batch of pages at a time (in parallel), checking
+    // the limits before and after each batch. A batch can't be
+    // interrupted, so it may overrun the deadline by one batch's
+    // worth of extraction, but its text is then not returned.
+    let batch_size = rayon::current_num_threads().max(1);
+    let timed_out = |done: usize| {
+        format!(
+            "Limit exceeded: reading timed out after {} of {} pages. Request fewer \
+             pages at a time.",
+            done,
+            pages.len()
+        )
+    };
+    for batch in pages.chunks(batch_size) {
+        if Instant::now() >= deadline {
+            return Err(timed_out(texts.len()));
+        }
+        let batch_texts = extract_text_pages(doc, batch).map_err(|e| e.to_string())?;
+        if Instant::now() >= deadline {
+            return Err(timed_out(texts.len()));
+        }
+        for text in batch_texts {
+            bytes += text.text.len();
+            if bytes > max_bytes {
+                return Err(format!(
+                    "Limit exceeded: the text up to page {} is more than the {} \
+                     bytes this server returns per call. Request fewer pages at a \
+                     time.",
+                    text.page, max_bytes
+                ));
+            }
+            texts.push(text);
+        }
+    }
+    Ok(texts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_bind_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("inpdf-socket-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("inpdf.sock");
+
+        let _listener = bind_private(&socket, 0o600).unwrap();
+        let mode = std::fs::metadata(&socket).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        tokio::net::UnixStream::connect(&socket).await.unwrap();
+        // Only the socket is left behind, not the private directory.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_read_within_limits() {
+        use crate::pdf::cache::CachedPdf;
+        use crate::pdf::testing::text_document;
+
+        let (doc, _) = text_document(&["one", "two", "three"]);
+        let doc = PdfDocument::from_cached(CachedPdf::from_document(doc), "test.pdf");
+        let later = Instant::now() + Duration::from_secs(60);
+
+        let texts = read_within_limits(&doc, &[1, 2, 3], 1024, later).unwrap();
+        assert_eq!(
+            texts.iter().map(|t| t.page).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        // The error names the page whose text crossed the limit, even when
+        // it was extracted in the same batch as the pages before it.
+        let budget = texts[0].text.len() + texts[1].text.len() - 1;
+        let error = read_within_limits(&doc, &[1, 2, 3], budget, later).unwrap_err();
+        assert!(error.contains("up to page 2 "), "{}", error);
+
+        let error = read_within_limits(&doc, &[1, 2, 3], 1024, Instant::now()).unwrap_err();
+        assert!(error.contains("timed out after 0 of 3 pages"), "{}", error);
+    }
+
+    #[test]
+    fn test_resolve_in_roots() {
+        let dir = std::env::temp_dir().join(format!("inpdf-roots-{}", std::process::id()));
+        let workspace = dir.join("workspace");
+        let other = dir.join("other");
+        std::fs::create_dir_all(workspace.join("docs")).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(workspace.join("docs/a.pdf"), "").unwrap();
+        let roots = [workspace.clone()];
+
+        // Without client roots, paths are used as given.
+        assert_eq!(
+            resolve_in_roots("docs/a.pdf", &[], &[]).unwrap(),
+            "docs/a.pdf"
+        );
+
+        let resolved = resolve_in_roots("docs/a.pdf", &roots, &[]).unwrap();
+        assert_eq!(Path::new(&resolved), workspace.join("docs/a.pdf"));
+        // Outputs that don't exist yet resolve under the first root.
+        let resolved = resolve_in_roots("out/b.pdf", &roots, &[]).unwrap();
+        assert_eq!(Path::new(&resolved), workspace.join("out/b.pdf"));
+
+        let outside = other.display().to_string();
+        assert!(resolve_in_roots(&outside, &roots, &[]).is_err());
+        assert!(resolve_in_roots("../other/x.pdf", &roots, &[]).is_err());
+        assert!(resolve_in_roots("docs/../../other", &roots, &[]).is_err());
+        assert!(resolve_in_roots(&outside, &roots, std::slice::from_ref(&other)).is_ok());
+        assert_eq!(
+            resolve_in_roots("s3://bucket/key.pdf", &roots, &[]).unwrap(),
+            "s3://bucket/key.pdf"
+        );
+
+        assert_eq!(
+            root_path("file:///home/me/My%20Docs"),
+            Some(PathBuf::from("/home/me/My Docs"))
+        );
+        assert_eq!(root_path("https://example.com"), None);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_scratch_handles() {
+        let scratch = Scratch::default();
+        let (handle, path) = scratch.allocate("pdf_extract").unwrap();
+        assert_eq!(handle, "scratch:extract-1.pdf");
+        assert!(scratch.path("extract-1.pdf").is_err());
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(scratch.path("extract-1.pdf").unwrap(), path);
+        assert!(scratch.path("../extract-1.pdf").is_err());
+
+        let dir = path.parent().unwrap().to_path_buf();
+        drop(scratch);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let calls = RecentCalls::default();
+        let start = Instant::now();
+        assert!(calls.admit(None, start).is_ok());
+        assert!(calls.admit(Some(2), start).is_ok());
+        assert!(calls
+            .admit(Some(2), start + Duration::from_secs(10))
+            .is_ok());
+        let refused = calls.admit(Some(2), start + Duration::from_secs(20));
+        assert!(refused.unwrap_err().contains("Try again in 41s"));
+        // The first call has aged out a minute later.
+        assert!(calls
+            .admit(Some(2), start + Duration::from_secs(60))
+            .is_ok());
+    }
+}
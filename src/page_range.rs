@@ -1,4 +1,7 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Rotation {
@@ -134,21 +137,137 @@ fn parse_page_ref(s: &str) -> Result<PageRef> {
     }
 }
 
-/// Parse a comma-separated list of page ranges like "1-5,10,15-end"
-pub fn parse_page_ranges(s: &str) -> Result<Vec<PageRange>> {
-    s.split(',')
-        .map(|part| PageRange::parse(part.trim()))
-        .collect()
-}
-
 /// Expand a page range string into a list of 1-based page numbers
+///
+/// Besides ranges, the list may name page sets installed with
+/// [`set_page_sets`] and exclude pages with `!`: `body,!12` is every page
+/// of `body` except 12. See [`PageSets::expand`].
 pub fn expand_page_ranges(s: &str, total_pages: u32) -> Result<Vec<u32>> {
-    let ranges = parse_page_ranges(s)?;
-    let mut pages = Vec::new();
-    for range in ranges {
-        pages.extend(range.expand(total_pages)?);
+    PAGE_SETS
+        .get_or_init(PageSets::default)
+        .expand(s, total_pages)
+}
+
+/// The process-wide page sets, installed once by the CLI.
+static PAGE_SETS: OnceLock<PageSets> = OnceLock::new();
+
+/// Make `sets` available to every later [`expand_page_ranges`] call.
+///
+/// Only the first call has any effect.
+pub fn set_page_sets(sets: PageSets) {
+    let _ = PAGE_SETS.set(sets);
+}
+
+/// How deeply page sets may refer to other page sets, which also stops
+/// definitions that refer to themselves.
+const MAX_SET_DEPTH: usize = 16;
+
+/// Named page selections, like `front=1-8` and `body=9-end`, that can be
+/// used wherever a page range is expected.
+#[derive(Debug, Clone, Default)]
+pub struct PageSets {
+    sets: HashMap<String, String>,
+}
+
+impl PageSets {
+    /// Define (or redefine) the set `name` as `ranges`.
+    ///
+    /// Names start with a letter and contain only letters, digits, `-`,
+    /// and `_`. `end` is reserved.
+    pub fn define(&mut self, name: &str, ranges: &str) -> Result<()> {
+        if !is_name(name) {
+            bail!("Invalid page set name: {:?}", name);
+        }
+        if ranges.trim().is_empty() {
+            bail!("Page set {} is empty", name);
+        }
+        self.sets
+            .insert(name.to_string(), ranges.trim().to_string());
+        Ok(())
+    }
+
+    /// Define a set from a `name=ranges` string.
+    pub fn define_str(&mut self, definition: &str) -> Result<()> {
+        let (name, ranges) = parse_definition(definition)?;
+        self.define(name, ranges)
+    }
+
+    /// Read definitions from a file of `name = ranges` lines. Blank lines
+    /// and lines starting with `#` are ignored.
+    pub fn load(&mut self, path: &Path) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read page sets: {}", path.display()))?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.define_str(line)
+                .with_context(|| format!("{}:{}", path.display(), i + 1))?;
+        }
+        Ok(())
+    }
+
+    /// Expand a comma-separated list of ranges and set names into 1-based
+    /// page numbers.
+    ///
+    /// Parts prefixed with `!` are removed from the result wherever they
+    /// appear in the list; a list of only exclusions starts from every page.
+    pub fn expand(&self, s: &str, total_pages: u32) -> Result<Vec<u32>> {
+        self.expand_nested(s, total_pages, 0)
+    }
+
+    fn expand_nested(&self, s: &str, total_pages: u32, depth: usize) -> Result<Vec<u32>> {
+        let mut pages = Vec::new();
+        let mut excluded = HashSet::new();
+        let mut selects = false;
+        for part in s.split(',') {
+            let part = part.trim();
+            let (exclude, spec) = match part.strip_prefix('!') {
+                Some(rest) => (true, rest.trim()),
+                None => (false, part),
+            };
+            let expanded = match self.sets.get(spec) {
+                Some(_) if depth >= MAX_SET_DEPTH => {
+                    bail!("Page set {} refers to itself or nests too deeply", spec)
+                }
+                Some(ranges) => self
+                    .expand_nested(ranges, total_pages, depth + 1)
+                    .with_context(|| format!("In page set {}", spec))?,
+                None => match PageRange::parse(spec) {
+                    Ok(range) => range.expand(total_pages)?,
+                    Err(_) if is_name(spec) => bail!("Unknown page set: {}", spec),
+                    Err(e) => return Err(e),
+                },
+            };
+            if exclude {
+                excluded.extend(expanded);
+            } else {
+                pages.extend(expanded);
+                selects = true;
+            }
+        }
+        if !selects {
+            pages = (1..=total_pages).collect();
+        }
+        pages.retain(|page| !excluded.contains(page));
+        Ok(pages)
     }
-    Ok(pages)
+}
+
+fn is_name(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic())
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !s.eq_ignore_ascii_case("end")
+}
+
+/// Split a `name=ranges` page set definition.
+pub fn parse_definition(definition: &str) -> Result<(&str, &str)> {
+    let (name, ranges) = definition
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected NAME=RANGES, got {:?}", definition))?;
+    Ok((name.trim(), ranges.trim()))
 }
 
 #[cfg(test)]
@@ -205,4 +324,76 @@ mod tests {
         let range = PageRange::parse("15").unwrap();
         assert!(range.expand(10).is_err());
     }
+
+    #[test]
+    fn test_page_sets() {
+        let mut sets = PageSets::default();
+        sets.define_str("front=1-3").unwrap();
+        sets.define_str("body = 4-end").unwrap();
+        sets.define("appendix-a", "9-10").unwrap();
+        sets.define("main", "front,body,!appendix-a").unwrap();
+
+        assert_eq!(sets.expand("front", 10).unwrap(), vec![1, 2, 3]);
+        assert_eq!(sets.expand("body,!6", 10).unwrap(), vec![4, 5, 7, 8, 9, 10]);
+        assert_eq!(sets.expand("!6,body", 10).unwrap(), vec![4, 5, 7, 8, 9, 10]);
+        assert_eq!(
+            sets.expand("main", 10).unwrap(),
+            (1..=8).collect::<Vec<_>>()
+        );
+        assert_eq!(sets.expand("!2-end", 10).unwrap(), vec![1]);
+        assert_eq!(sets.expand("end-9,1", 10).unwrap(), vec![10, 9, 1]);
+
+        let err = sets.expand("back", 10).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown page set: back");
+        assert!(sets.expand("x1-", 10).is_err());
+
+        assert!(sets.define("end", "1").is_err());
+        assert!(sets.define("2up", "1").is_err());
+        assert!(sets.define("empty", " ").is_err());
+        assert!(sets.define_str("front").is_err());
+
+        // Sets that refer to themselves fail instead of recursing forever.
+        sets.define("loop", "1,loop").unwrap();
+        assert!(sets.expand("loop", 10).is_err());
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_malformed_specs_dont_panic(
+            spec in "[0-9endERLDrld, -]{0,24}|\\PC{0,24}",
+            total in 0u32..100,
+        ) {
+            let _ = expand_page_ranges(&spec, total);
+        }
+
+        #[test]
+        fn prop_valid_specs_expand_in_range(
+            ranges in proptest::collection::vec(
+                (1u32..=50, proptest::option::of(1u32..=50), proptest::bool::ANY),
+                1..6,
+            ),
+            total in 50u32..100,
+        ) {
+            let spec = ranges
+                .iter()
+                .map(|&(start, end, to_end)| match (end, to_end) {
+                    (_, true) => format!("{}-end", start),
+                    (Some(end), false) => format!("{}-{}R", start, end),
+                    (None, false) => start.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            let expected: u32 = ranges
+                .iter()
+                .map(|&(start, end, to_end)| match (end, to_end) {
+                    (_, true) => total - start + 1,
+                    (Some(end), false) => start.abs_diff(end) + 1,
+                    (None, false) => 1,
+                })
+                .sum();
+            let pages = expand_page_ranges(&spec, total).unwrap();
+            proptest::prop_assert_eq!(pages.len() as u32, expected);
+            proptest::prop_assert!(pages.iter().all(|&p| (1..=total).contains(&p)));
+        }
+    }
 }
@@ -0,0 +1,138 @@
+//! A first-pass accessibility audit of a PDF's tagging and metadata.
+
+use crate::pdf::document::get_string_from_dict;
+use crate::pdf::pages::resolve;
+use crate::pdf::structure::{struct_tree, struct_tree_root};
+use lopdf::Document;
+
+/// Findings of an accessibility check.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AccessibilityReport {
+    /// `MarkInfo` declares the document as tagged.
+    pub marked: bool,
+    /// The catalog has a logical structure tree.
+    pub has_struct_tree: bool,
+    /// Number of structure elements in the tree.
+    pub struct_elements: usize,
+    /// Document language (`Lang` in the catalog), e.g. "en-US".
+    pub lang: Option<String>,
+    /// Title from the document information dictionary.
+    pub title: Option<String>,
+    /// Viewers are asked to show the title rather than the file name.
+    pub display_doc_title: bool,
+    /// Structure elements of type `Figure` (after role mapping).
+    pub figures: usize,
+    /// Figures with neither `Alt` nor `ActualText`.
+    pub figures_without_alt: usize,
+}
+
+impl AccessibilityReport {
+    /// Problems found, each with the number of occurrences.
+    pub fn issues(&self) -> Vec<(usize, &'static str)> {
+        let mut issues = Vec::new();
+        if !self.marked {
+            issues.push((1, "document is not marked as tagged (MarkInfo/Marked)"));
+        }
+        if !self.has_struct_tree {
+            issues.push((1, "no logical structure tree (StructTreeRoot)"));
+        } else if self.struct_elements == 0 {
+            issues.push((1, "structure tree has no elements"));
+        }
+        if self.lang.is_none() {
+            issues.push((1, "no document language (Lang)"));
+        }
+        if self.title.is_none() {
+            issues.push((1, "no title in document metadata"));
+        } else if !self.display_doc_title {
+            issues.push((
+                1,
+                "viewers will show the file name instead of the title (DisplayDocTitle)",
+            ));
+        }
+        if self.figures_without_alt > 0 {
+            issues.push((self.figures_without_alt, "figure(s) without alternate text"));
+        }
+        issues
+    }
+}
+
+pub fn check_accessibility(doc: &Document) -> AccessibilityReport {
+    let mut report = AccessibilityReport::default();
+
+    let dict = |obj| resolve(doc, obj).as_dict().ok();
+    if let Ok(catalog) = doc.catalog() {
+        report.marked = catalog
+            .get(b"MarkInfo")
+            .ok()
+            .and_then(dict)
+            .and_then(|info| info.get(b"Marked").ok())
+            .and_then(|marked| marked.as_bool().ok())
+            .unwrap_or(false);
+        report.lang = get_string_from_dict(catalog, b"Lang").filter(|lang| !lang.trim().is_empty());
+        report.display_doc_title = catalog
+            .get(b"ViewerPreferences")
+            .ok()
+            .and_then(dict)
+            .and_then(|prefs| prefs.get(b"DisplayDocTitle").ok())
+            .and_then(|display| display.as_bool().ok())
+            .unwrap_or(false);
+    }
+
+    report.title = doc
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(dict)
+        .and_then(|info| get_string_from_dict(info, b"Title"))
+        .filter(|title| !title.trim().is_empty());
+
+    report.has_struct_tree = struct_tree_root(doc).is_some();
+    for node in struct_tree(doc) {
+        node.walk(&mut |element, _| {
+            report.struct_elements += 1;
+            if element.is(b"Figure") {
+                report.figures += 1;
+                if element.alt.is_none() && element.actual_text.is_none() {
+                    report.figures_without_alt += 1;
+                }
+            }
+        });
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{add_struct_tree, text_document};
+    use lopdf::Object;
+
+    #[test]
+    fn test_untagged_document() {
+        let (doc, _) = text_document(&["one"]);
+        let report = check_accessibility(&doc);
+        assert!(!report.marked);
+        assert!(!report.has_struct_tree);
+        assert_eq!(report.issues().len(), 4);
+    }
+
+    #[test]
+    fn test_tagged_document_counts_figures() {
+        let (mut doc, _) = text_document(&["one"]);
+        add_struct_tree(&mut doc);
+        doc.catalog_mut()
+            .unwrap()
+            .set("Lang", Object::string_literal("en-US"));
+
+        let report = check_accessibility(&doc);
+        assert!(report.marked);
+        assert_eq!(report.struct_elements, 4);
+        assert_eq!(report.lang.as_deref(), Some("en-US"));
+        assert_eq!(report.figures, 2);
+        assert_eq!(report.figures_without_alt, 1);
+        assert!(report
+            .issues()
+            .contains(&(1, "figure(s) without alternate text")));
+    }
+}
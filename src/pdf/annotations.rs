@@ -0,0 +1,698 @@
+//! Reading markup annotations: highlights, notes, and the text they mark.
+//!
+//! Text markup annotations (highlights, underlines, strikeouts) only record
+//! the quadrilaterals they cover, so the marked text is recovered from the
+//! glyphs of the page's content stream that fall inside them. Replies are
+//! annotations pointing at another with `/IRT`; pop-ups only display their
+//! parent's contents.
+//!
+//! New text markup is placed by finding its text among the same glyphs,
+//! with an appearance stream so viewers that don't draw markup themselves
+//! still show it.
+
+use crate::pdf::glyphs::{find_all, page_glyphs, Glyph};
+use crate::pdf::pages::{number, page_box, rect_object, resolve, Rect};
+use crate::pdf::strings::{decode_text_string, encode_text_string};
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+/// Subtypes of annotations that mark up text.
+pub const TEXT_MARKUP: &[&str] = &["Highlight", "Underline", "Squiggly", "StrikeOut"];
+
+/// Annotation subtypes in the PDF specification.
+pub const SUBTYPES: &[&str] = &[
+    "Text",
+    "Link",
+    "FreeText",
+    "Line",
+    "Square",
+    "Circle",
+    "Polygon",
+    "PolyLine",
+    "Highlight",
+    "Underline",
+    "Squiggly",
+    "StrikeOut",
+    "Caret",
+    "Stamp",
+    "Ink",
+    "Popup",
+    "FileAttachment",
+    "Sound",
+    "Movie",
+    "Screen",
+    "Widget",
+    "PrinterMark",
+    "TrapNet",
+    "Watermark",
+    "3D",
+    "Redact",
+    "RichMedia",
+];
+
+/// Subtypes [`strip_annotations`] keeps unless asked to remove them: links
+/// are navigation and widgets are form fields, not markup.
+pub const KEPT_BY_DEFAULT: &[&str] = &["Link", "Widget"];
+
+/// Named highlight colors, close to what common readers offer.
+pub const COLORS: &[(&str, [f32; 3])] = &[
+    ("yellow", [1.0, 0.83, 0.0]),
+    ("green", [0.37, 0.7, 0.21]),
+    ("blue", [0.18, 0.66, 0.9]),
+    ("red", [1.0, 0.4, 0.4]),
+    ("purple", [0.64, 0.54, 0.9]),
+    ("pink", [0.9, 0.43, 0.93]),
+    ("orange", [0.95, 0.6, 0.22]),
+    ("gray", [0.67, 0.67, 0.67]),
+];
+
+/// An annotation on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    /// Physical page number (1-indexed).
+    pub page: u32,
+    /// Object ID, if the annotation is an indirect object (as it must be to
+    /// have replies).
+    pub id: Option<ObjectId>,
+    /// `Highlight`, `Text`, `Link`, ...
+    pub subtype: String,
+    pub rect: Rect,
+    /// Bounding boxes of the `QuadPoints` quadrilaterals of text markup.
+    pub quads: Vec<Rect>,
+    /// Color as RGB (gray and CMYK converted).
+    pub color: Option<[f32; 3]>,
+    /// The comment.
+    pub contents: Option<String>,
+    pub author: Option<String>,
+    /// Last modification date, as written (`D:20240102...`).
+    pub modified: Option<String>,
+    /// The annotation this one replies to.
+    pub in_reply_to: Option<ObjectId>,
+    /// Text under a text markup annotation.
+    pub text: Option<String>,
+}
+
+impl Annotation {
+    /// A short human name for the kind of annotation.
+    pub fn kind(&self) -> String {
+        match self.subtype.as_str() {
+            "Text" => "note".to_string(),
+            "FreeText" => "text box".to_string(),
+            "Squiggly" => "squiggly underline".to_string(),
+            other => other.to_lowercase(),
+        }
+    }
+}
+
+/// An annotation to add.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewAnnotation {
+    /// One of [`TEXT_MARKUP`], or `Text` for a note.
+    pub subtype: &'static str,
+    /// Text to mark up, or to place a note next to. Notes without it go in
+    /// the top left corner of the page.
+    pub text: Option<String>,
+    pub contents: Option<String>,
+    pub color: [f32; 3],
+    pub author: Option<String>,
+}
+
+/// The annotation subtype for a kind named as [`Annotation::kind`] does.
+pub fn subtype_for_kind(kind: &str) -> Option<&'static str> {
+    match kind.trim().to_lowercase().as_str() {
+        "highlight" => Some("Highlight"),
+        "underline" => Some("Underline"),
+        "squiggly" | "squiggly underline" => Some("Squiggly"),
+        "strikeout" | "strike-out" => Some("StrikeOut"),
+        "note" | "comment" => Some("Text"),
+        _ => None,
+    }
+}
+
+/// The subtype for a name given by a user: a kind as [`subtype_for_kind`]
+/// reads it, or a subtype in any case (`popup`, `freetext`).
+pub fn parse_subtype(name: &str) -> Option<&'static str> {
+    subtype_for_kind(name).or_else(|| {
+        SUBTYPES
+            .iter()
+            .find(|subtype| subtype.eq_ignore_ascii_case(name.trim()))
+            .copied()
+    })
+}
+
+/// Every annotation in the document, by page and from the top of each page
+/// down.
+pub fn document_annotations(doc: &Document) -> Vec<Annotation> {
+    doc.get_pages()
+        .into_iter()
+        .flat_map(|(page, page_id)| page_annotations(doc, page, page_id))
+        .collect()
+}
+
+/// The annotations of one page, from the top down. The marked text is only
+/// looked up if the page has text markup.
+pub fn page_annotations(doc: &Document, page: u32, page_id: ObjectId) -> Vec<Annotation> {
+    let Some(annots) = doc
+        .get_dictionary(page_id)
+        .ok()
+        .and_then(|dict| dict.get(b"Annots").ok())
+        .and_then(|obj| resolve(doc, obj).as_array().ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut found: Vec<Annotation> = annots
+        .iter()
+        .filter_map(|obj| {
+            let id = obj.as_reference().ok();
+            let dict = resolve(doc, obj).as_dict().ok()?;
+            Some(read_annotation(doc, page, id, dict))
+        })
+        .collect();
+
+    if found.iter().any(|a| !a.quads.is_empty()) {
+        let glyphs = doc
+            .get_and_decode_page_content(page_id)
+            .map(|content| page_glyphs(doc, page_id, &content.operations))
+            .unwrap_or_default();
+        for annotation in found.iter_mut().filter(|a| !a.quads.is_empty()) {
+            annotation.text = Some(marked_text(&glyphs, &annotation.quads));
+        }
+    }
+
+    found.sort_by(|a, b| {
+        b.rect[3]
+            .total_cmp(&a.rect[3])
+            .then(a.rect[0].total_cmp(&b.rect[0]))
+    });
+    found
+}
+
+fn read_annotation(
+    doc: &Document,
+    page: u32,
+    id: Option<ObjectId>,
+    dict: &Dictionary,
+) -> Annotation {
+    let string = |key: &[u8]| {
+        dict.get(key)
+            .ok()
+            .and_then(|obj| resolve(doc, obj).as_str().ok())
+            .map(decode_text_string)
+            .filter(|s| !s.trim().is_empty())
+    };
+    let numbers = |key: &[u8]| -> Vec<f32> {
+        dict.get(key)
+            .ok()
+            .and_then(|obj| resolve(doc, obj).as_array().ok())
+            .and_then(|items| {
+                items
+                    .iter()
+                    .map(|obj| number(resolve(doc, obj)))
+                    .collect::<Option<Vec<f32>>>()
+            })
+            .unwrap_or_default()
+    };
+
+    let subtype = dict
+        .get(b"Subtype")
+        .ok()
+        .and_then(|obj| resolve(doc, obj).as_name().ok())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .unwrap_or_default();
+    let rect = match numbers(b"Rect")[..] {
+        [x0, y0, x1, y1] => [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)],
+        _ => [0.0; 4],
+    };
+    let quads = if TEXT_MARKUP.contains(&subtype.as_str()) {
+        let points = numbers(b"QuadPoints");
+        let quads: Vec<Rect> = points.chunks_exact(8).map(quad_bounds).collect();
+        // Without quads, the whole rectangle is marked.
+        if quads.is_empty() {
+            vec![rect]
+        } else {
+            quads
+        }
+    } else {
+        Vec::new()
+    };
+    let color = match numbers(b"C")[..] {
+        [gray] => Some([gray; 3]),
+        [r, g, b] => Some([r, g, b]),
+        [c, m, y, k] => Some([c, m, y].map(|v| (1.0 - v) * (1.0 - k))),
+        _ => None,
+    };
+
+    Annotation {
+        page,
+        id,
+        subtype,
+        rect,
+        quads,
+        color,
+        contents: string(b"Contents"),
+        author: string(b"T"),
+        modified: string(b"M"),
+        in_reply_to: dict
+            .get(b"IRT")
+            .ok()
+            .and_then(|obj| obj.as_reference().ok()),
+        text: None,
+    }
+}
+
+/// The bounding box of one quadrilateral (x1 y1 ... x4 y4).
+fn quad_bounds(points: &[f32]) -> Rect {
+    let xs = points.iter().step_by(2);
+    let ys = points.iter().skip(1).step_by(2);
+    [
+        xs.clone().copied().fold(f32::INFINITY, f32::min),
+        ys.clone().copied().fold(f32::INFINITY, f32::min),
+        xs.copied().fold(f32::NEG_INFINITY, f32::max),
+        ys.copied().fold(f32::NEG_INFINITY, f32::max),
+    ]
+}
+
+/// The text of the glyphs whose centers fall inside `quads`, in content
+/// order. Line breaks become spaces, and words hyphenated across them are
+/// joined.
+pub(crate) fn marked_text(glyphs: &[Glyph], quads: &[Rect]) -> String {
+    let inside = |glyph: &Glyph| {
+        let (x, y) = (
+            (glyph.rect[0] + glyph.rect[2]) / 2.0,
+            (glyph.rect[1] + glyph.rect[3]) / 2.0,
+        );
+        quads
+            .iter()
+            .any(|q| x >= q[0] && x <= q[2] && y >= q[1] && y <= q[3])
+    };
+
+    let mut text = String::new();
+    let mut previous: Option<&Glyph> = None;
+    for glyph in glyphs.iter().filter(|g| inside(g)) {
+        if let Some(prev) = previous {
+            let height = (prev.rect[3] - prev.rect[1]).max(1.0);
+            let new_line = (glyph.rect[1] - prev.rect[1]).abs() > height / 2.0;
+            let gap = glyph.rect[0] - prev.rect[2];
+            if new_line {
+                if text.ends_with('-') {
+                    text.pop();
+                } else {
+                    text.push(' ');
+                }
+            } else if gap > height * 0.2 {
+                text.push(' ');
+            }
+        }
+        text.push_str(&glyph.text);
+        previous = Some(glyph);
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Add `annotation` to a page at the first occurrence of its text
+/// (whitespace ignored). Returns `false`, changing nothing, if the text isn't
+/// on the page. `modified` is a PDF date.
+pub fn add_annotation(
+    doc: &mut Document,
+    page_id: ObjectId,
+    annotation: &NewAnnotation,
+    modified: &str,
+) -> Result<bool> {
+    let lines = match &annotation.text {
+        Some(text) => {
+            let content = doc
+                .get_and_decode_page_content(page_id)
+                .context("Failed to decode page content")?;
+            let glyphs = page_glyphs(doc, page_id, &content.operations);
+            let Some(range) = find_all(&glyphs, text).into_iter().next() else {
+                return Ok(false);
+            };
+            line_boxes(&glyphs[range])
+        }
+        None => Vec::new(),
+    };
+    let markup = TEXT_MARKUP.contains(&annotation.subtype);
+    if markup && lines.is_empty() {
+        return Ok(false);
+    }
+
+    let rect = if markup {
+        lines.iter().skip(1).fold(lines[0], |a, b| {
+            [
+                a[0].min(b[0]),
+                a[1].min(b[1]),
+                a[2].max(b[2]),
+                a[3].max(b[3]),
+            ]
+        })
+    } else {
+        // A note icon just above where the text starts.
+        let (x, y) = match lines.first() {
+            Some(line) => (line[0], line[3]),
+            None => {
+                let crop = page_box(doc, page_id, b"CropBox");
+                (crop[0] + 10.0, crop[3] - 30.0)
+            }
+        };
+        [x, y, x + 20.0, y + 20.0]
+    };
+
+    let mut dict = dictionary! {
+        "Type" => "Annot",
+        "Subtype" => annotation.subtype,
+        "Rect" => rect_object(rect),
+        "P" => page_id,
+        "C" => annotation.color.map(Object::Real).to_vec(),
+        // Print
+        "F" => 4,
+        "M" => Object::string_literal(modified),
+    };
+    if markup {
+        let quads: Vec<Object> = lines
+            .iter()
+            .flat_map(|l| [l[0], l[3], l[2], l[3], l[0], l[1], l[2], l[1]])
+            .map(Object::Real)
+            .collect();
+        dict.set("QuadPoints", quads);
+        let appearance = markup_appearance(annotation.subtype, &lines, rect, annotation.color);
+        dict.set("AP", dictionary! { "N" => doc.add_object(appearance) });
+    } else {
+        dict.set("Name", "Comment");
+    }
+    if let Some(contents) = &annotation.contents {
+        dict.set("Contents", encode_text_string(contents));
+    }
+    if let Some(author) = &annotation.author {
+        dict.set("T", encode_text_string(author));
+    }
+    let annot_id = doc.add_object(dict);
+
+    let mut annots = doc
+        .get_dictionary(page_id)?
+        .get(b"Annots")
+        .ok()
+        .and_then(|obj| resolve(doc, obj).as_array().ok())
+        .cloned()
+        .unwrap_or_default();
+    annots.push(annot_id.into());
+    doc.get_dictionary_mut(page_id)?.set("Annots", annots);
+    Ok(true)
+}
+
+/// Remove the annotations of the given subtypes from every page (all but
+/// [`KEPT_BY_DEFAULT`] for `None`), along with the pop-ups and replies of
+/// removed ones. Returns how many were removed. The objects are left for
+/// [`Document::prune_objects`].
+pub fn strip_annotations(doc: &mut Document, subtypes: Option<&[&str]>) -> usize {
+    let strip = |subtype: &[u8]| match subtypes {
+        Some(subtypes) => subtypes.iter().any(|s| s.as_bytes() == subtype),
+        None => !KEPT_BY_DEFAULT.iter().any(|s| s.as_bytes() == subtype),
+    };
+
+    let mut removed = 0;
+    for page_id in doc.get_pages().into_values() {
+        let Some(annots) = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+            .and_then(|obj| resolve(doc, obj).as_array().ok())
+            .cloned()
+        else {
+            continue;
+        };
+        let dicts: Vec<Option<&Dictionary>> = annots
+            .iter()
+            .map(|obj| resolve(doc, obj).as_dict().ok())
+            .collect();
+        let reference = |dict: Option<&Dictionary>, key: &[u8]| {
+            dict.and_then(|d| d.get(key).ok())
+                .and_then(|obj| obj.as_reference().ok())
+        };
+
+        let mut gone: Vec<bool> = dicts
+            .iter()
+            .map(|dict| {
+                dict.and_then(|d| d.get(b"Subtype").ok())
+                    .and_then(|obj| obj.as_name().ok())
+                    .is_some_and(strip)
+            })
+            .collect();
+        // Pop-ups and replies go with what they belong to, which may itself
+        // be a reply.
+        loop {
+            let mut gone_ids: HashSet<ObjectId> = HashSet::new();
+            for (i, obj) in annots.iter().enumerate().filter(|(i, _)| gone[*i]) {
+                gone_ids.extend(obj.as_reference().ok());
+                gone_ids.extend(reference(dicts[i], b"Popup"));
+            }
+            let mut changed = false;
+            for (i, annot) in annots.iter().enumerate() {
+                let belongs = [b"Parent".as_slice(), b"IRT"]
+                    .iter()
+                    .any(|key| reference(dicts[i], key).is_some_and(|id| gone_ids.contains(&id)));
+                let is_popup = annot.as_reference().is_ok_and(|id| gone_ids.contains(&id));
+                if !gone[i] && (belongs || is_popup) {
+                    gone[i] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        let count = gone.iter().filter(|&&g| g).count();
+        if count == 0 {
+            continue;
+        }
+        removed += count;
+        let kept: Vec<Object> = annots
+            .into_iter()
+            .zip(gone)
+            .filter(|(_, gone)| !gone)
+            .map(|(annot, _)| annot)
+            .collect();
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            if kept.is_empty() {
+                page.remove(b"Annots");
+            } else {
+                page.set("Annots", kept);
+            }
+        }
+    }
+    removed
+}
+
+/// The bounds of each line of `glyphs`, whitespace left out.
+fn line_boxes(glyphs: &[Glyph]) -> Vec<Rect> {
+    let mut lines: Vec<Rect> = Vec::new();
+    for glyph in glyphs.iter().filter(|g| !g.text.trim().is_empty()) {
+        let r = glyph.rect;
+        match lines.last_mut() {
+            Some(line) if (r[1] - line[1]).abs() <= (line[3] - line[1]) / 2.0 => {
+                *line = [
+                    line[0].min(r[0]),
+                    line[1].min(r[1]),
+                    line[2].max(r[2]),
+                    line[3].max(r[3]),
+                ];
+            }
+            _ => lines.push(r),
+        }
+    }
+    lines
+}
+
+/// A normal appearance for text markup over `lines`: filled boxes blended
+/// with what's beneath for highlights, strokes for the others.
+fn markup_appearance(subtype: &str, lines: &[Rect], bbox: Rect, color: [f32; 3]) -> Stream {
+    let [r, g, b] = color;
+    let mut ops = String::new();
+    if subtype == "Highlight" {
+        let _ = writeln!(ops, "/GS0 gs {} {} {} rg", r, g, b);
+        for l in lines {
+            let _ = writeln!(
+                ops,
+                "{} {} {} {} re f",
+                l[0],
+                l[1],
+                l[2] - l[0],
+                l[3] - l[1]
+            );
+        }
+    } else {
+        let _ = writeln!(ops, "{} {} {} RG", r, g, b);
+        for l in lines {
+            let height = l[3] - l[1];
+            let y = if subtype == "StrikeOut" {
+                l[1] + height * 0.45
+            } else {
+                l[1] + height * 0.1
+            };
+            let _ = writeln!(
+                ops,
+                "{} w {} {} m {} {} l S",
+                height / 14.0,
+                l[0],
+                y,
+                l[2],
+                y
+            );
+        }
+    }
+    Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => rect_object(bbox),
+            "Resources" => dictionary! {
+                "ExtGState" => dictionary! {
+                    "GS0" => dictionary! { "BM" => "Multiply" },
+                },
+            },
+        },
+        ops.into_bytes(),
+    )
+}
+
+/// A color given as one of the names in [`COLORS`] or as `#rrggbb`.
+pub fn parse_color(value: &str) -> Option<[f32; 3]> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(hex.get(i..i + 2)?, 16)
+                .ok()
+                .map(|v| v as f32 / 255.0)
+        };
+        return Some([channel(0)?, channel(2)?, channel(4)?]);
+    }
+    COLORS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, color)| *color)
+}
+
+/// The name of the nearest of [`COLORS`].
+pub fn color_name(color: [f32; 3]) -> &'static str {
+    let distance = |other: &[f32; 3]| -> f32 {
+        color
+            .iter()
+            .zip(other)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum()
+    };
+    COLORS
+        .iter()
+        .min_by(|a, b| distance(&a.1).total_cmp(&distance(&b.1)))
+        .map(|(name, _)| *name)
+        .expect("COLORS is not empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_highlight_text() {
+        let (mut doc, pages) = text_document(&["Bill ACME Corp for the work"]);
+        // "ACME Corp" runs from about x = 91 to 155 in 12pt Helvetica.
+        let highlight = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Highlight",
+            "Rect" => vec![90.into(), 715.into(), 157.into(), 732.into()],
+            "QuadPoints" => [90, 732, 157, 732, 90, 715, 157, 715]
+                .into_iter()
+                .map(Object::from)
+                .collect::<Vec<_>>(),
+            "C" => vec![1.into(), 1.into(), 0.into()],
+            "Contents" => Object::string_literal("Check the name"),
+            "T" => Object::string_literal("Jane"),
+        });
+        let note = doc.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Text",
+            "Rect" => vec![72.into(), 750.into(), 90.into(), 768.into()],
+            "Contents" => Object::string_literal("Agreed"),
+            "IRT" => highlight,
+        });
+        doc.get_dictionary_mut(pages[0])
+            .unwrap()
+            .set("Annots", vec![highlight.into(), note.into()]);
+
+        let found = document_annotations(&doc);
+        assert_eq!(found.len(), 2);
+        // The note is higher on the page.
+        assert_eq!(found[0].kind(), "note");
+        assert_eq!(found[0].in_reply_to, Some(highlight));
+        assert_eq!(found[1].text.as_deref(), Some("ACME Corp"));
+        assert_eq!(found[1].contents.as_deref(), Some("Check the name"));
+        assert_eq!(found[1].author.as_deref(), Some("Jane"));
+        assert_eq!(color_name(found[1].color.unwrap()), "yellow");
+    }
+
+    #[test]
+    fn test_add_annotation_round_trip() {
+        let (mut doc, pages) = text_document(&["Bill ACME Corp for the work"]);
+        let highlight = NewAnnotation {
+            subtype: "Underline",
+            text: Some("ACME  Corp".to_string()),
+            contents: Some("Check the name".to_string()),
+            color: parse_color("#2ea8e5").unwrap(),
+            author: Some("Jane".to_string()),
+        };
+        assert!(add_annotation(&mut doc, pages[0], &highlight, "D:20240101000000Z").unwrap());
+        let missing = NewAnnotation {
+            text: Some("Globex".to_string()),
+            ..highlight.clone()
+        };
+        assert!(!add_annotation(&mut doc, pages[0], &missing, "D:20240101000000Z").unwrap());
+
+        let found = document_annotations(&doc);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind(), "underline");
+        assert_eq!(found[0].text.as_deref(), Some("ACME Corp"));
+        assert_eq!(color_name(found[0].color.unwrap()), "blue");
+        assert_eq!(subtype_for_kind("Squiggly underline"), Some("Squiggly"));
+        assert_eq!(parse_color("nope"), None);
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        let (mut doc, pages) = text_document(&["one"]);
+        let annot = |doc: &mut Document, subtype: &str| {
+            doc.add_object(dictionary! { "Type" => "Annot", "Subtype" => subtype })
+        };
+        let highlight = annot(&mut doc, "Highlight");
+        let popup = doc.add_object(dictionary! { "Subtype" => "Popup", "Parent" => highlight });
+        let reply = doc.add_object(dictionary! { "Subtype" => "Text", "IRT" => highlight });
+        let link = annot(&mut doc, "Link");
+        let ink = annot(&mut doc, "Ink");
+        let ids = [highlight, popup, reply, link, ink];
+        doc.get_dictionary_mut(pages[0]).unwrap().set(
+            "Annots",
+            ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+        );
+        let remaining = |doc: &Document| -> Vec<String> {
+            document_annotations(doc)
+                .into_iter()
+                .map(|a| a.subtype)
+                .collect()
+        };
+
+        let mut only = doc.clone();
+        assert_eq!(strip_annotations(&mut only, Some(&["Highlight"][..])), 3);
+        assert_eq!(remaining(&only), ["Link", "Ink"]);
+
+        assert_eq!(strip_annotations(&mut doc, None), 4);
+        assert_eq!(remaining(&doc), ["Link"]);
+        assert_eq!(parse_subtype("popup"), Some("Popup"));
+        assert_eq!(parse_subtype("note"), Some("Text"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Files attached to a document, for searching their contents.
+//!
+//! Attachments live in the catalog's `EmbeddedFiles` name tree (document
+//! attachments, portfolio members, e-invoice XML) or in the file
+//! specifications of `FileAttachment` annotations on pages.
+
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::pages::resolve;
+use crate::pdf::portfolio::{embedded_files, embedded_stream, file_name};
+use lopdf::{Dictionary, Document, Stream};
+
+/// The decoded contents of an attachment, by what can be searched in it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttachmentData {
+    Pdf(Vec<u8>),
+    Text(String),
+    /// Anything else (images, archives, ...).
+    Binary,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attachment {
+    pub file_name: String,
+    pub data: AttachmentData,
+}
+
+/// List the document's attachments, name tree entries first, then
+/// annotation attachments by page. A file referenced from both is listed
+/// once.
+pub fn attachments(doc: &Document) -> Vec<Attachment> {
+    let mut specs: Vec<(String, &Dictionary)> = embedded_files(doc)
+        .into_iter()
+        .map(|(key, spec)| (file_name(spec).unwrap_or(key), spec))
+        .collect();
+    for page_id in doc.get_pages().into_values() {
+        let annots = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+            .and_then(|annots| resolve(doc, annots).as_array().ok());
+        for annot in annots.into_iter().flatten() {
+            let Ok(annot) = resolve(doc, annot).as_dict() else {
+                continue;
+            };
+            if annot.get(b"Subtype").and_then(|s| s.as_name()).ok() != Some(b"FileAttachment") {
+                continue;
+            }
+            if let Some(spec) = annot
+                .get(b"FS")
+                .ok()
+                .and_then(|fs| resolve(doc, fs).as_dict().ok())
+            {
+                specs.push((file_name(spec).unwrap_or_default(), spec));
+            }
+        }
+    }
+
+    let mut seen: Vec<*const Stream> = Vec::new();
+    let mut out = Vec::new();
+    for (file_name, spec) in specs {
+        let Some(stream) = embedded_stream(doc, spec) else {
+            continue;
+        };
+        if seen.contains(&(stream as *const Stream)) {
+            continue;
+        }
+        seen.push(stream);
+        let data = match stream_bytes(stream) {
+            Ok(bytes) => classify(bytes),
+            Err(_) => AttachmentData::Binary,
+        };
+        out.push(Attachment { file_name, data });
+    }
+    out
+}
+
+/// Tell PDFs (by their header) and text (UTF-8 without NULs) from other
+/// data.
+fn classify(bytes: Vec<u8>) -> AttachmentData {
+    let head = &bytes[..bytes.len().min(1024)];
+    if head.windows(5).any(|window| window == b"%PDF-") {
+        return AttachmentData::Pdf(bytes);
+    }
+    let body = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(&bytes);
+    match std::str::from_utf8(body) {
+        Ok(text) if !text.contains('\0') => AttachmentData::Text(text.to_string()),
+        _ => AttachmentData::Binary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::{dictionary, Object};
+
+    #[test]
+    fn test_attachments_from_name_tree_and_annotations() {
+        let (mut inner, _) = text_document(&["nested"]);
+        let mut pdf = Vec::new();
+        inner.save_to(&mut pdf).unwrap();
+
+        let (mut doc, page_ids) = text_document(&["cover"]);
+        let xml = doc.add_object(Stream::new(dictionary! {}, b"<Invoice/>".to_vec()));
+        let xml_spec = doc.add_object(dictionary! {
+            "F" => Object::string_literal("invoice.xml"),
+            "EF" => dictionary! { "F" => xml },
+        });
+        let pdf = doc.add_object(Stream::new(dictionary! {}, pdf));
+        let image = doc.add_object(Stream::new(dictionary! {}, vec![0x89, b'P', 0, 0xFF]));
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set(
+            "Names",
+            dictionary! {
+                "EmbeddedFiles" => dictionary! {
+                    "Names" => vec![
+                        Object::string_literal("invoice.xml"),
+                        Object::Reference(xml_spec),
+                        Object::string_literal("pic"),
+                        dictionary! { "EF" => dictionary! { "F" => image } }.into(),
+                    ],
+                },
+            },
+        );
+        doc.get_dictionary_mut(page_ids[0]).unwrap().set(
+            "Annots",
+            vec![
+                dictionary! {
+                    "Subtype" => "FileAttachment",
+                    "FS" => dictionary! {
+                        "UF" => Object::string_literal("appendix.pdf"),
+                        "EF" => dictionary! { "F" => pdf },
+                    },
+                }
+                .into(),
+                dictionary! { "Subtype" => "FileAttachment", "FS" => xml_spec }.into(),
+            ],
+        );
+
+        let found = attachments(&doc);
+        let summary: Vec<_> = found
+            .iter()
+            .map(|a| (a.file_name.as_str(), std::mem::discriminant(&a.data)))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "invoice.xml",
+                    std::mem::discriminant(&AttachmentData::Text(String::new()))
+                ),
+                ("pic", std::mem::discriminant(&AttachmentData::Binary)),
+                (
+                    "appendix.pdf",
+                    std::mem::discriminant(&AttachmentData::Pdf(Vec::new()))
+                ),
+            ]
+        );
+        assert_eq!(
+            found[0].data,
+            AttachmentData::Text("<Invoice/>".to_string())
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Reading and editing the five page boxes.
+//!
+//! Edits are written as `trim=36,36,576,756` (coordinates in points or with
+//! units), `bleed=trim+3mm` / `crop=media-0.25in` (another box grown or
+//! shrunk on every side), or `art=none` to remove a box.
+
+use crate::pdf::layout::parse_length;
+use crate::pdf::pages::{find_inherited, media_box, page_box, rect_object, Rect};
+use anyhow::{bail, Context, Result};
+use lopdf::{Document, ObjectId};
+
+/// One of the page boundary boxes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageBox {
+    Media,
+    Crop,
+    Bleed,
+    Trim,
+    Art,
+}
+
+impl PageBox {
+    pub const ALL: [PageBox; 5] = [
+        PageBox::Media,
+        PageBox::Crop,
+        PageBox::Bleed,
+        PageBox::Trim,
+        PageBox::Art,
+    ];
+
+    /// The page dictionary key, e.g. `TrimBox`.
+    pub fn key(self) -> &'static str {
+        match self {
+            PageBox::Media => "MediaBox",
+            PageBox::Crop => "CropBox",
+            PageBox::Bleed => "BleedBox",
+            PageBox::Trim => "TrimBox",
+            PageBox::Art => "ArtBox",
+        }
+    }
+
+    /// The name used in edits, e.g. `trim`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PageBox::Media => "media",
+            PageBox::Crop => "crop",
+            PageBox::Bleed => "bleed",
+            PageBox::Trim => "trim",
+            PageBox::Art => "art",
+        }
+    }
+
+    /// The box this one defaults to when absent.
+    fn fallback(self) -> Option<PageBox> {
+        match self {
+            PageBox::Media => None,
+            PageBox::Crop => Some(PageBox::Media),
+            PageBox::Bleed | PageBox::Trim | PageBox::Art => Some(PageBox::Crop),
+        }
+    }
+
+    fn parse(s: &str) -> Result<PageBox> {
+        let lower = s.trim().to_ascii_lowercase();
+        let name = lower.strip_suffix("box").unwrap_or(&lower);
+        PageBox::ALL
+            .into_iter()
+            .find(|page_box| page_box.name() == name)
+            .with_context(|| {
+                format!(
+                    "Unknown page box '{}': expected media, crop, bleed, trim, or art",
+                    s.trim()
+                )
+            })
+    }
+}
+
+/// The new value of a box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxValue {
+    Rect(Rect),
+    /// Another box (as it stands when the edit is applied) grown by this
+    /// many points on every side; negative shrinks it.
+    Relative(PageBox, f32),
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoxEdit {
+    pub page_box: PageBox,
+    pub value: BoxValue,
+}
+
+/// Parse comma-separated edits such as `trim=0,0,612,792,bleed=trim+9`.
+///
+/// A new edit starts at each `name=`, so rectangles need no quoting.
+pub fn parse_box_edits(s: &str) -> Result<Vec<BoxEdit>> {
+    let mut assignments: Vec<(String, String)> = Vec::new();
+    for part in s.split(',') {
+        match (part.split_once('='), assignments.last_mut()) {
+            (Some((name, value)), _) => assignments.push((name.to_string(), value.to_string())),
+            (None, Some((_, value))) => {
+                value.push(',');
+                value.push_str(part);
+            }
+            (None, None) => bail!("Expected BOX=VALUE, got '{}'", s),
+        }
+    }
+
+    assignments
+        .iter()
+        .map(|(name, value)| {
+            let page_box = PageBox::parse(name)?;
+            let value = parse_box_value(value)
+                .with_context(|| format!("Invalid value for {}", page_box.key()))?;
+            if page_box == PageBox::Media && value == BoxValue::Remove {
+                bail!("MediaBox is required and can't be removed");
+            }
+            Ok(BoxEdit { page_box, value })
+        })
+        .collect()
+}
+
+fn parse_box_value(s: &str) -> Result<BoxValue> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("none") {
+        return Ok(BoxValue::Remove);
+    }
+
+    let parts: Vec<&str> = s.split(',').collect();
+    if parts.len() == 4 {
+        let mut rect = [0.0; 4];
+        for (value, part) in rect.iter_mut().zip(parts) {
+            *value = parse_coordinate(part)?;
+        }
+        if rect[0] >= rect[2] || rect[1] >= rect[3] {
+            bail!(
+                "'{}' is not LLX,LLY,URX,URY with the lower-left corner first",
+                s
+            );
+        }
+        return Ok(BoxValue::Rect(rect));
+    }
+
+    match s.find(['+', '-']) {
+        Some(at) => {
+            let offset = parse_length(&s[at + 1..])?;
+            let sign = if s[at..].starts_with('-') { -1.0 } else { 1.0 };
+            Ok(BoxValue::Relative(PageBox::parse(&s[..at])?, sign * offset))
+        }
+        None => Ok(BoxValue::Relative(PageBox::parse(s)?, 0.0)),
+    }
+}
+
+fn parse_coordinate(s: &str) -> Result<f32> {
+    match s.trim().strip_prefix('-') {
+        Some(magnitude) => Ok(-parse_length(magnitude)?),
+        None => parse_length(s),
+    }
+}
+
+/// Where a page's box value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoxSource {
+    /// Set on the page itself.
+    Page,
+    /// Inherited from an ancestor `Pages` node.
+    Inherited,
+    /// Absent, so it defaults to another box.
+    Default(PageBox),
+    /// A missing MediaBox, which the PDF spec requires; US Letter is
+    /// assumed.
+    Missing,
+}
+
+/// A page's effective value for each box, and where each comes from.
+pub fn page_boxes(doc: &Document, page_id: ObjectId) -> Vec<(PageBox, Rect, BoxSource)> {
+    PageBox::ALL
+        .into_iter()
+        .map(|page_box| {
+            let key = page_box.key().as_bytes();
+            let on_page = doc.get_dictionary(page_id).is_ok_and(|page| page.has(key));
+            let source = if on_page {
+                BoxSource::Page
+            } else if find_inherited(doc, page_id, key).is_some() {
+                BoxSource::Inherited
+            } else {
+                page_box
+                    .fallback()
+                    .map_or(BoxSource::Missing, BoxSource::Default)
+            };
+            (page_box, page_box_rect(doc, page_id, page_box), source)
+        })
+        .collect()
+}
+
+fn page_box_rect(doc: &Document, page_id: ObjectId, which: PageBox) -> Rect {
+    page_box(doc, page_id, which.key().as_bytes())
+}
+
+/// Apply one edit to a page.
+pub fn apply_box_edit(doc: &mut Document, page_id: ObjectId, edit: &BoxEdit) -> Result<()> {
+    let key = edit.page_box.key();
+    let rect = match edit.value {
+        BoxValue::Rect(rect) => Some(rect),
+        BoxValue::Relative(base, by) => {
+            let [llx, lly, urx, ury] = page_box_rect(doc, page_id, base);
+            let rect = [llx - by, lly - by, urx + by, ury + by];
+            if rect[0] >= rect[2] || rect[1] >= rect[3] {
+                bail!("Shrinking {} by {} pt leaves nothing", base.key(), -by);
+            }
+            Some(rect)
+        }
+        BoxValue::Remove => None,
+    };
+
+    let media = media_box(doc, page_id);
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .context("Page object is not a dictionary")?;
+    match rect {
+        Some(rect) => page.set(key, rect_object(rect)),
+        None => {
+            page.remove(key.as_bytes());
+            // An inherited CropBox would still apply, so pin it to the
+            // MediaBox instead.
+            if find_inherited(doc, page_id, key.as_bytes()).is_some() {
+                doc.get_dictionary_mut(page_id)?
+                    .set(key, rect_object(media));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_parse_box_edits() {
+        let edits = parse_box_edits("trim=36,36,576,756,bleed=trim+3mm, art = none").unwrap();
+        assert_eq!(
+            edits[0],
+            BoxEdit {
+                page_box: PageBox::Trim,
+                value: BoxValue::Rect([36.0, 36.0, 576.0, 756.0]),
+            }
+        );
+        assert!(
+            matches!(edits[1].value, BoxValue::Relative(PageBox::Trim, by) if (by - 8.504).abs() < 0.001)
+        );
+        assert_eq!(edits[2].page_box, PageBox::Art);
+        assert_eq!(edits[2].value, BoxValue::Remove);
+
+        assert_eq!(
+            parse_box_edits("CropBox=media-9").unwrap()[0].value,
+            BoxValue::Relative(PageBox::Media, -9.0)
+        );
+        assert_eq!(
+            parse_box_edits("media=-9,-9,621,801").unwrap()[0].value,
+            BoxValue::Rect([-9.0, -9.0, 621.0, 801.0])
+        );
+        assert!(parse_box_edits("trim=10,10,5,5").is_err());
+        assert!(parse_box_edits("media=none").is_err());
+        assert!(parse_box_edits("slug=0,0,1,1").is_err());
+        assert!(parse_box_edits("0,0,1,1").is_err());
+    }
+
+    #[test]
+    fn test_apply_box_edits() {
+        let (mut doc, pages) = text_document(&["page"]);
+        let page = pages[0];
+        for edit in parse_box_edits("trim=media-18,bleed=trim+9").unwrap() {
+            apply_box_edit(&mut doc, page, &edit).unwrap();
+        }
+        let [llx, lly, urx, ury] = media_box(&doc, page);
+        assert_eq!(
+            page_box(&doc, page, b"TrimBox"),
+            [llx + 18.0, lly + 18.0, urx - 18.0, ury - 18.0]
+        );
+        assert_eq!(
+            page_box(&doc, page, b"BleedBox"),
+            [llx + 9.0, lly + 9.0, urx - 9.0, ury - 9.0]
+        );
+
+        let boxes = page_boxes(&doc, page);
+        assert_eq!(boxes[3].2, BoxSource::Page);
+        assert_eq!(boxes[4].2, BoxSource::Default(PageBox::Crop));
+
+        apply_box_edit(&mut doc, page, &parse_box_edits("trim=none").unwrap()[0]).unwrap();
+        assert_eq!(
+            page_boxes(&doc, page)[3].2,
+            BoxSource::Default(PageBox::Crop)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Building new documents from scratch, one page at a time.
+
+use crate::pdf::pages::{rect_object, Rect};
+use anyhow::Result;
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+
+/// Accumulates pages under a single `Pages` node.
+///
+/// Shared objects such as fonts and images are added through [`doc_mut`]
+/// and referenced from each page's resources.
+///
+/// [`doc_mut`]: DocumentBuilder::doc_mut
+pub struct DocumentBuilder {
+    doc: Document,
+    pages_id: ObjectId,
+    kids: Vec<Object>,
+    /// Set when adding to an existing document rather than building one.
+    existing: bool,
+}
+
+impl DocumentBuilder {
+    pub fn new() -> Self {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        Self {
+            doc,
+            pages_id,
+            kids: Vec::new(),
+            existing: false,
+        }
+    }
+
+    /// Add pages to an existing document. New pages become direct children
+    /// of the root `Pages` node, before all existing pages.
+    ///
+    /// Building into the document itself lets generated pages refer to its
+    /// objects, e.g. links whose destinations are existing pages.
+    pub fn prepend(doc: Document) -> Result<Self> {
+        let pages_id = match doc.catalog()?.get(b"Pages") {
+            Ok(Object::Reference(id)) => *id,
+            _ => anyhow::bail!("Document catalog has no page tree"),
+        };
+        Ok(Self {
+            doc,
+            pages_id,
+            kids: Vec::new(),
+            existing: true,
+        })
+    }
+
+    pub fn doc_mut(&mut self) -> &mut Document {
+        &mut self.doc
+    }
+
+    /// Append a page with the given size, resources, and content stream.
+    pub fn add_page(
+        &mut self,
+        media_box: Rect,
+        resources: Dictionary,
+        content: Vec<u8>,
+    ) -> ObjectId {
+        let mut stream = Stream::new(Dictionary::new(), content);
+        // Compression is an optimization; keep the raw stream if it fails.
+        let _ = stream.compress();
+        let content_id = self.doc.add_object(stream);
+
+        let page_id = self.doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => self.pages_id,
+            "MediaBox" => rect_object(media_box),
+            "Resources" => resources,
+            "Contents" => content_id,
+        });
+        self.kids.push(Object::Reference(page_id));
+        page_id
+    }
+
+    /// Append a page object that is already in the document (e.g., one
+    /// copied from another document), making this builder its parent.
+    ///
+    /// The page should carry its own `Resources` and `MediaBox`, since it
+    /// inherits nothing from the new parent.
+    pub fn push_page(&mut self, page_id: ObjectId) -> Result<()> {
+        self.doc
+            .get_dictionary_mut(page_id)?
+            .set("Parent", self.pages_id);
+        self.kids.push(Object::Reference(page_id));
+        Ok(())
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.kids.len()
+    }
+
+    /// Write the page tree and catalog, returning the finished document.
+    pub fn finish(mut self) -> Result<Document> {
+        if self.existing {
+            let pages = self.doc.get_dictionary_mut(self.pages_id)?;
+            let mut kids = match pages.get(b"Kids") {
+                Ok(Object::Array(kids)) => kids.clone(),
+                _ => Vec::new(),
+            };
+            let count = pages.get(b"Count").and_then(Object::as_i64).unwrap_or(0);
+            let added = self.kids.len() as i64;
+            kids.splice(0..0, self.kids);
+            pages.set("Kids", kids);
+            pages.set("Count", count + added);
+            return Ok(self.doc);
+        }
+
+        let count = self.kids.len() as i64;
+        self.doc.objects.insert(
+            self.pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => self.kids,
+                "Count" => count,
+            }),
+        );
+        let catalog_id = self.doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => self.pages_id,
+        });
+        self.doc.trailer.set("Root", catalog_id);
+        Ok(self.doc)
+    }
+}
+
+impl Default for DocumentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_builder_produces_page_tree() {
+        let mut builder = DocumentBuilder::new();
+        builder.add_page([0.0, 0.0, 100.0, 200.0], Dictionary::new(), b"".to_vec());
+        builder.add_page([0.0, 0.0, 300.0, 400.0], Dictionary::new(), b"".to_vec());
+        let doc = builder.finish().unwrap();
+
+        let pages = doc.get_pages();
+        assert_eq!(pages.len(), 2);
+        let second = doc.get_dictionary(pages[&2]).unwrap();
+        assert!(second.has(b"MediaBox"));
+    }
+
+    #[test]
+    fn test_extend_prepends_pages() {
+        let (doc, page_ids) = text_document(&["one", "two"]);
+        let mut builder = DocumentBuilder::prepend(doc).unwrap();
+        let new_page = builder.add_page([0.0, 0.0, 612.0, 792.0], Dictionary::new(), Vec::new());
+        let doc = builder.finish().unwrap();
+
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        assert_eq!(pages, vec![new_page, page_ids[0], page_ids[1]]);
+    }
+}
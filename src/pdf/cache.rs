@@ -2,15 +2,24 @@
 //!
 //! This module provides a global cache for PDF files that:
 //! - Caches parsed `lopdf::Document` objects to avoid re-parsing
-//! - Lazily caches extracted text per page for repeated access
+//! - Lazily caches extracted text per page for repeated access, following the
+//!   structure tree of tagged PDFs and page layout otherwise, and recovering
+//!   text from glyph names where fonts lack a Unicode mapping
 //! - Validates cache entries by file mtime to detect stale data
+//! - Can skip image sample data for text-only work, so large scanned PDFs
+//!   don't hold every page image in memory
 //! - Uses canonical paths to handle symlinks and relative paths
 
+use crate::pdf::quality::{text_quality, LOW_QUALITY};
+use crate::pdf::recover::{recover_page_text, GlyphTexts, RecoveredText, RecoveryStats};
+use crate::pdf::tagged::extract_tagged_text;
 use anyhow::{Context, Result};
-use lopdf::Document;
+use lopdf::{Document, Object, ObjectId, Reader};
 use memmap2::Mmap;
 use papaya::HashMap;
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
 use std::time::SystemTime;
@@ -31,9 +40,63 @@ pub struct CachedPdf {
     mtime: SystemTime,
     /// Lazily cached extracted text per page (1-indexed).
     text_cache: Arc<HashMap<u32, Arc<String>>>,
+    /// Structure-tree text for all pages, computed on first use; `None` for
+    /// untagged documents.
+    tagged_text: Arc<OnceLock<Option<BTreeMap<u32, String>>>>,
+    /// Pages whose cached text was recovered from glyph names.
+    recovered: Arc<HashMap<u32, RecoveryStats>>,
+    mode: LoadMode,
+}
+
+// Entries are shared between MCP sessions and moved onto the blocking
+// thread pool, so they must stay thread-safe.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<CachedPdf>();
+    assert_send_sync::<PdfCache>();
+};
+
+/// How much of a PDF file to load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadMode {
+    /// Every object, as needed to inspect or rewrite the document.
+    Full,
+    /// Everything but the sample data of image XObjects, which text
+    /// extraction never reads. Image dictionaries are kept, with empty
+    /// content. Such documents must not be written back out.
+    TextOnly,
 }
 
 impl CachedPdf {
+    /// Build a standalone entry from PDF bytes held in memory.
+    ///
+    /// The result is not stored in the global cache, but still caches
+    /// extracted text per page for as long as it is kept around.
+    pub fn from_bytes(bytes: &[u8]) -> Result<CachedPdf> {
+        let doc = Document::load_mem(bytes).context("parse PDF")?;
+        Ok(Self::from_document(doc))
+    }
+
+    /// Build a standalone entry from a reader positioned at the start of a
+    /// PDF, reading it to the end.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<CachedPdf> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).context("read PDF")?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Build a standalone entry around an already-parsed document.
+    pub fn from_document(doc: Document) -> CachedPdf {
+        CachedPdf {
+            doc: Arc::new(doc),
+            mtime: SystemTime::UNIX_EPOCH,
+            text_cache: Arc::new(HashMap::new()),
+            tagged_text: Arc::new(OnceLock::new()),
+            recovered: Arc::new(HashMap::new()),
+            mode: LoadMode::Full,
+        }
+    }
+
     /// Get a reference to the cached parsed document.
     pub fn document(&self) -> &Arc<Document> {
         &self.doc
@@ -41,7 +104,12 @@ impl CachedPdf {
 
     /// Get extracted text for a page, using cache if available.
     ///
-    /// The page number is 1-indexed.
+    /// Tagged pages are read in the logical order of the structure tree;
+    /// other pages (and tagged pages without text) use layout-based
+    /// extraction. Where that gives little usable text and the page uses
+    /// fonts without a Unicode mapping, text recovered from glyph names is
+    /// used instead if it scores better (see [`CachedPdf::recovery`]). The
+    /// page number is 1-indexed.
     pub fn page_text(&self, page_num: u32) -> Result<Arc<String>, pdf_extract::OutputError> {
         let guard = self.text_cache.pin();
         if let Some(text) = guard.get(&page_num) {
@@ -49,11 +117,45 @@ impl CachedPdf {
         }
 
         // Extract and cache the text.
-        let text = extract_text_from_doc_page(&self.doc, page_num)?;
+        let mut text = match self.tagged_page_text(page_num) {
+            Some(text) => text,
+            None => extract_text_from_doc_page(&self.doc, page_num)?,
+        };
+        if let Some(recovered) = self.recovered_page_text(page_num, &text) {
+            self.recovered.pin().insert(page_num, recovered.stats);
+            text = recovered.text;
+        }
         let text = Arc::new(text);
         guard.insert(page_num, Arc::clone(&text));
         Ok(text)
     }
+
+    /// How much of a page's text was recovered from glyph names, or `None`
+    /// if it was extracted normally. Only known once the page's text has
+    /// been requested.
+    pub fn recovery(&self, page_num: u32) -> Option<RecoveryStats> {
+        self.recovered.pin().get(&page_num).copied()
+    }
+
+    fn recovered_page_text(&self, page_num: u32, extracted: &str) -> Option<RecoveredText> {
+        let quality = text_quality(extracted).score;
+        if quality.is_some_and(|score| score >= LOW_QUALITY) {
+            return None;
+        }
+        let page_id = *self.doc.get_pages().get(&page_num)?;
+        let recovered = recover_page_text(&self.doc, page_id, &GlyphTexts::new())?;
+        (recovered.stats.mapped > 0 && text_quality(&recovered.text).score > quality)
+            .then_some(recovered)
+    }
+
+    fn tagged_page_text(&self, page_num: u32) -> Option<String> {
+        self.tagged_text
+            .get_or_init(|| extract_tagged_text(&self.doc))
+            .as_ref()?
+            .get(&page_num)
+            .filter(|text| !text.trim().is_empty())
+            .cloned()
+    }
 }
 
 /// Extract text from a single page using pdf-extract's output_doc_page.
@@ -91,6 +193,25 @@ impl PdfCache {
     /// If the file is already cached and its mtime matches, returns the
     /// cached entry. Otherwise, loads the file fresh (via mmap) and parses it.
     pub fn get<P: AsRef<Path>>(&self, path: P) -> Result<CachedPdf> {
+        self.get_with(path, LoadMode::Full)
+    }
+
+    /// Get or load a PDF for text extraction only (see [`LoadMode::TextOnly`]).
+    ///
+    /// A fully loaded entry is reused if there is one.
+    pub fn get_for_text<P: AsRef<Path>>(&self, path: P) -> Result<CachedPdf> {
+        self.get_with(path, LoadMode::TextOnly)
+    }
+
+    /// Drop a file's entry, for batch jobs that visit each file once and
+    /// would otherwise keep every document in memory.
+    pub fn evict<P: AsRef<Path>>(&self, path: P) {
+        if let Ok(canonical) = std::fs::canonicalize(path) {
+            self.cache.pin().remove(&canonical);
+        }
+    }
+
+    fn get_with<P: AsRef<Path>>(&self, path: P, mode: LoadMode) -> Result<CachedPdf> {
         let path = path.as_ref();
 
         // Canonicalize to handle symlinks and relative paths consistently.
@@ -107,14 +228,20 @@ impl PdfCache {
         // Try to get from cache first.
         let cache_guard = self.cache.pin();
         if let Some(cached) = cache_guard.get(&canonical) {
-            if cached.mtime == current_mtime {
+            if cached.mtime == current_mtime
+                && (cached.mode == LoadMode::Full || mode == LoadMode::TextOnly)
+            {
                 return Ok(cached.clone());
             }
-            // Stale entry - will be replaced below.
+            // Stale entry, or text-only when the full document is wanted -
+            // will be replaced below.
         }
 
         // Load and cache the PDF.
-        let cached = load_pdf(&canonical, current_mtime)?;
+        let cached = match mode {
+            LoadMode::Full => load_pdf(&canonical, current_mtime)?,
+            LoadMode::TextOnly => load_pdf_for_text(&canonical, current_mtime)?,
+        };
         cache_guard.insert(canonical, cached.clone());
         Ok(cached)
     }
@@ -136,6 +263,11 @@ pub fn get_cached_pdf<P: AsRef<Path>>(path: P) -> Result<CachedPdf> {
     cache().get(path)
 }
 
+/// Get a cached PDF for text extraction only; see [`PdfCache::get_for_text`].
+pub fn get_cached_pdf_for_text<P: AsRef<Path>>(path: P) -> Result<CachedPdf> {
+    cache().get_for_text(path)
+}
+
 // ==============================================================================
 // PDF Loading
 // ==============================================================================
@@ -146,6 +278,27 @@ pub fn get_cached_pdf<P: AsRef<Path>>(path: P) -> Result<CachedPdf> {
 /// store it afterward since lopdf's Document owns all its data independently
 /// after parsing.
 fn load_pdf(path: &Path, mtime: SystemTime) -> Result<CachedPdf> {
+    let mmap = map_pdf(path)?;
+
+    // Parse the document from the memory-mapped bytes.
+    // After this, the Document owns all its data - we don't need the mmap anymore.
+    let doc =
+        Document::load_mem(&mmap).with_context(|| format!("parse PDF: {}", path.display()))?;
+    let doc = Arc::new(doc);
+
+    Ok(CachedPdf {
+        doc,
+        mtime,
+        text_cache: Arc::new(HashMap::new()),
+        tagged_text: Arc::new(OnceLock::new()),
+        recovered: Arc::new(HashMap::new()),
+        mode: LoadMode::Full,
+    })
+}
+
+/// Memory-map a PDF file for parsing, so the file is never copied whole
+/// into the heap.
+fn map_pdf(path: &Path) -> Result<Mmap> {
     // Open the file for memory mapping.
     let file = File::open(path).with_context(|| format!("open PDF file: {}", path.display()))?;
 
@@ -163,19 +316,59 @@ fn load_pdf(path: &Path, mtime: SystemTime) -> Result<CachedPdf> {
         let _ = mmap.advise(memmap2::Advice::Random);
     }
 
-    // Parse the document from the memory-mapped bytes.
-    // After this, the Document owns all its data - we don't need the mmap anymore.
-    let doc =
-        Document::load_mem(&mmap).with_context(|| format!("parse PDF: {}", path.display()))?;
-    let doc = Arc::new(doc);
+    Ok(mmap)
+}
+
+/// Load a PDF without image sample data (see [`LoadMode::TextOnly`]).
+///
+/// The file is memory-mapped like [`load_pdf`] does, and each image's
+/// samples are dropped as soon as its object is parsed, so the document
+/// holds only the non-image content even for scanned documents that are
+/// hundreds of megabytes of page images.
+fn load_pdf_for_text(path: &Path, mtime: SystemTime) -> Result<CachedPdf> {
+    let mmap = map_pdf(path)?;
+    let reader = Reader {
+        buffer: &mmap,
+        document: Document::new(),
+        encryption_state: None,
+        raw_objects: BTreeMap::new(),
+    };
+    let doc = reader
+        .read(Some(drop_image_data))
+        .with_context(|| format!("parse PDF: {}", path.display()))?;
 
     Ok(CachedPdf {
-        doc,
+        doc: Arc::new(doc),
         mtime,
         text_cache: Arc::new(HashMap::new()),
+        tagged_text: Arc::new(OnceLock::new()),
+        recovered: Arc::new(HashMap::new()),
+        mode: LoadMode::TextOnly,
     })
 }
 
+/// lopdf keeps `object` itself for top-level objects and the returned copy
+/// for objects inside object streams. Streams are always top level, so
+/// images are emptied in place and nothing is returned for them; only the
+/// small objects that may sit in object streams are copied.
+fn drop_image_data(id: ObjectId, object: &mut Object) -> Option<(ObjectId, Object)> {
+    let Object::Stream(stream) = object else {
+        return Some((id, object.clone()));
+    };
+    let is_image = stream
+        .dict
+        .get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|subtype| subtype == b"Image");
+    if is_image {
+        stream.content = Vec::new();
+        // Otherwise lopdf reads the samples in again if the stream's
+        // /Length couldn't be resolved while parsing.
+        stream.start_position = None;
+    }
+    Some((id, Object::Null))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -261,6 +454,125 @@ mod tests {
         std::fs::remove_file(&pdf_path).ok();
     }
 
+    #[test]
+    fn text_only_load_keeps_pages() {
+        use crate::pdf::testing::text_document;
+
+        let dir = std::env::temp_dir().join("inpdf_cache_test");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let pdf_path = dir.join("test_text_only_pages.pdf");
+        let (mut doc, _) = text_document(&["first page", "second page"]);
+        doc.save(&pdf_path).expect("save test PDF");
+
+        let text_only = get_cached_pdf_for_text(&pdf_path).expect("text-only get");
+        assert_eq!(text_only.document().get_pages().len(), 2);
+        assert!(text_only.page_text(2).unwrap().contains("second page"));
+
+        std::fs::remove_file(&pdf_path).ok();
+    }
+
+    #[test]
+    fn text_only_load_drops_images_and_full_load_replaces_it() {
+        use crate::pdf::testing::text_document;
+        use lopdf::{dictionary, Stream};
+
+        let dir = std::env::temp_dir().join("inpdf_cache_test");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let pdf_path = dir.join("test_text_only.pdf");
+
+        let (mut doc, _) = text_document(&["scanned page"]);
+        let image = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 2,
+                "Height" => 2,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0, 64, 128, 255],
+        ));
+        doc.save(&pdf_path).expect("save test PDF");
+
+        let text_only = get_cached_pdf_for_text(&pdf_path).expect("text-only get");
+        let stream = text_only
+            .document()
+            .get_object(image)
+            .unwrap()
+            .as_stream()
+            .unwrap();
+        assert!(stream.content.is_empty());
+        assert!(text_only.page_text(1).unwrap().contains("scanned page"));
+
+        // A text-only entry serves later text requests, but not full ones.
+        let again = get_cached_pdf_for_text(&pdf_path).expect("second text-only get");
+        assert!(Arc::ptr_eq(&text_only.doc, &again.doc));
+        let full = get_cached_pdf(&pdf_path).expect("full get");
+        let stream = full
+            .document()
+            .get_object(image)
+            .unwrap()
+            .as_stream()
+            .unwrap();
+        assert_eq!(stream.content, vec![0, 64, 128, 255]);
+        let after = get_cached_pdf_for_text(&pdf_path).expect("text get after full");
+        assert!(Arc::ptr_eq(&full.doc, &after.doc));
+
+        std::fs::remove_file(&pdf_path).ok();
+    }
+
+    #[test]
+    fn text_only_load_holds_only_non_image_content() {
+        use crate::pdf::testing::text_document;
+        use lopdf::{dictionary, Stream};
+
+        let dir = std::env::temp_dir().join("inpdf_cache_test");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let pdf_path = dir.join("test_text_only_size.pdf");
+
+        // Two 1 MiB page images, one of them with an indirect /Length.
+        let (mut doc, _) = text_document(&["scanned page"]);
+        let samples: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+        let image = || {
+            Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => 1024,
+                    "Height" => 1024,
+                    "ColorSpace" => "DeviceGray",
+                    "BitsPerComponent" => 8,
+                },
+                samples.clone(),
+            )
+        };
+        doc.add_object(image());
+        let length = doc.add_object(samples.len() as i64);
+        let mut indirect = image();
+        indirect.dict.set("Length", length);
+        doc.add_object(indirect);
+        doc.save(&pdf_path).expect("save test PDF");
+        let file_size = std::fs::metadata(&pdf_path).unwrap().len();
+        assert!(file_size > 2 << 20);
+
+        let text_only = load_pdf_for_text(&pdf_path, SystemTime::now()).expect("text-only load");
+        let stream_bytes: usize = text_only
+            .document()
+            .objects
+            .values()
+            .filter_map(|object| object.as_stream().ok())
+            .map(|stream| stream.content.len())
+            .sum();
+        assert!(
+            stream_bytes < 4096,
+            "{} bytes of streams kept",
+            stream_bytes
+        );
+        assert!(text_only.page_text(1).unwrap().contains("scanned page"));
+
+        std::fs::remove_file(&pdf_path).ok();
+    }
+
     #[test]
     fn symlink_and_real_path_return_same_arcs() {
         let dir = std::env::temp_dir().join("inpdf_cache_test");
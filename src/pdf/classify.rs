@@ -0,0 +1,513 @@
+//! Labeling pages by their dominant kind of content.
+//!
+//! Pipelines send running text, tables, figures, and scans to different
+//! extractors. This walks each page's content (including form XObjects)
+//! and measures how much text it shows, how much of the page images cover,
+//! and which straight rules it draws, then picks a label from those. It
+//! doesn't look at the text itself, so it is cheap enough for whole
+//! collections.
+
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::pages::{
+    find_inherited, number, page_box, resolve, resource, Rect, MAX_FORM_DEPTH,
+};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Shortest line, in points, that counts as a rule.
+const MIN_RULE_LENGTH: f32 = 20.0;
+
+/// Thickest filled rectangle, in points, that counts as a rule.
+const MAX_RULE_WIDTH: f32 = 2.0;
+
+/// Share of the page images must cover for a scan.
+const SCAN_COVERAGE: f32 = 0.6;
+
+/// Most visible characters a scan may show (page numbers, stamps); OCR
+/// text layers are invisible and don't count.
+const SCAN_MAX_CHARS: usize = 100;
+
+/// Share of the page images must cover for a figure page.
+const FIGURE_COVERAGE: f32 = 0.3;
+
+/// Visible characters per 100×100 pt below which a page with many drawn
+/// shapes is a (vector) figure rather than text.
+const FIGURE_MAX_DENSITY: f32 = 10.0;
+
+/// Drawn shapes, other than rules, that make a sparse page a figure.
+const FIGURE_MIN_PATHS: usize = 50;
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// The dominant kind of content on a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageKind {
+    /// Nothing is drawn.
+    Blank,
+    /// Running text.
+    Text,
+    /// Ruled rows and columns.
+    Table,
+    /// Images or vector drawings with little text.
+    Figure,
+    /// A page-sized image without visible text, OCRed or not.
+    Scan,
+}
+
+impl fmt::Display for PageKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PageKind::Blank => "blank",
+            PageKind::Text => "text",
+            PageKind::Table => "table",
+            PageKind::Figure => "figure",
+            PageKind::Scan => "scan",
+        })
+    }
+}
+
+/// What a page's content draws.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageFeatures {
+    /// Non-space characters shown in a visible text rendering mode.
+    pub chars: usize,
+    /// Characters shown invisibly (text render mode 3), as in OCR layers.
+    pub invisible_chars: usize,
+    /// Visible characters per 100×100 pt of the crop box.
+    pub text_density: f32,
+    /// Share of the crop box covered by images (0-1; overlaps count once
+    /// per image).
+    pub image_coverage: f32,
+    /// Distinct heights of horizontal rules.
+    pub horizontal_rules: usize,
+    /// Distinct positions of vertical rules.
+    pub vertical_rules: usize,
+    /// Painted paths other than rules.
+    pub paths: usize,
+}
+
+/// The label of one page and what it was based on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageClass {
+    /// Physical page number (1-indexed).
+    pub page: u32,
+    pub kind: PageKind,
+    pub features: PageFeatures,
+}
+
+/// Label the given pages (1-indexed) of `doc`; pages that don't exist are
+/// skipped.
+pub fn classify_pages(doc: &Document, pages: &[u32]) -> Vec<PageClass> {
+    let page_ids = doc.get_pages();
+    pages
+        .iter()
+        .filter_map(|page| Some((*page, *page_ids.get(page)?)))
+        .map(|(page, page_id)| {
+            let features = page_features(doc, page_id);
+            PageClass {
+                page,
+                kind: classify(&features),
+                features,
+            }
+        })
+        .collect()
+}
+
+/// Measure what a page draws. Pages whose content can't be decoded come out
+/// blank.
+pub fn page_features(doc: &Document, page_id: ObjectId) -> PageFeatures {
+    let crop = page_box(doc, page_id, b"CropBox");
+    let mut scanner = Scanner {
+        doc,
+        crop,
+        ctm: IDENTITY,
+        stack: Vec::new(),
+        render_mode: 0,
+        path: Vec::new(),
+        current: (0.0, 0.0),
+        start: (0.0, 0.0),
+        chars: 0,
+        invisible_chars: 0,
+        image_area: 0.0,
+        horizontal: BTreeSet::new(),
+        vertical: BTreeSet::new(),
+        paths: 0,
+    };
+    let resources =
+        find_inherited(doc, page_id, b"Resources").and_then(|obj| resolve(doc, obj).as_dict().ok());
+    if let Ok(content) = doc.get_and_decode_page_content(page_id) {
+        scanner.scan(&content.operations, resources, 0);
+    }
+
+    let area = ((crop[2] - crop[0]) * (crop[3] - crop[1])).max(1.0);
+    PageFeatures {
+        chars: scanner.chars,
+        invisible_chars: scanner.invisible_chars,
+        text_density: scanner.chars as f32 / area * 10_000.0,
+        image_coverage: (scanner.image_area / area).min(1.0),
+        horizontal_rules: scanner.horizontal.len(),
+        vertical_rules: scanner.vertical.len(),
+        paths: scanner.paths,
+    }
+}
+
+/// Pick a label from a page's features.
+pub fn classify(features: &PageFeatures) -> PageKind {
+    let drawn = features.chars
+        + features.invisible_chars
+        + features.paths
+        + features.horizontal_rules
+        + features.vertical_rules;
+    if drawn == 0 && features.image_coverage < 0.01 {
+        return PageKind::Blank;
+    }
+    if features.image_coverage >= SCAN_COVERAGE && features.chars <= SCAN_MAX_CHARS {
+        return PageKind::Scan;
+    }
+    // A grid needs rows and columns; a page frame and a header rule don't
+    // make one, but a table ruled only between rows does.
+    if (features.horizontal_rules >= 3 && features.vertical_rules >= 3)
+        || features.horizontal_rules >= 5
+    {
+        return PageKind::Table;
+    }
+    if features.image_coverage >= FIGURE_COVERAGE
+        || (features.paths >= FIGURE_MIN_PATHS && features.text_density < FIGURE_MAX_DENSITY)
+        || (features.chars == 0 && features.paths > 0)
+    {
+        return PageKind::Figure;
+    }
+    PageKind::Text
+}
+
+/// A piece of the current path, in page coordinates.
+enum Segment {
+    Line((f32, f32), (f32, f32)),
+    /// Corners of a `re` rectangle, in order.
+    Rect([(f32, f32); 4]),
+    /// Curves are never rules.
+    Curve,
+}
+
+struct Scanner<'a> {
+    doc: &'a Document,
+    crop: Rect,
+    ctm: Matrix,
+    stack: Vec<(Matrix, i64)>,
+    render_mode: i64,
+    path: Vec<Segment>,
+    /// Current and subpath start points, in user space.
+    current: (f32, f32),
+    start: (f32, f32),
+    chars: usize,
+    invisible_chars: usize,
+    image_area: f32,
+    /// Rule positions, rounded to whole points.
+    horizontal: BTreeSet<i32>,
+    vertical: BTreeSet<i32>,
+    paths: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn scan(&mut self, operations: &[Operation], resources: Option<&'a Dictionary>, depth: usize) {
+        for op in operations {
+            let operands: Vec<f32> = op.operands.iter().filter_map(number).collect();
+            let arg = |i: usize| operands.get(i).copied().unwrap_or(0.0);
+            match op.operator.as_str() {
+                "q" => self.stack.push((self.ctm, self.render_mode)),
+                "Q" => {
+                    if let Some((ctm, render_mode)) = self.stack.pop() {
+                        self.ctm = ctm;
+                        self.render_mode = render_mode;
+                    }
+                }
+                "cm" if operands.len() == 6 => {
+                    let m = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                    self.ctm = multiply(&m, &self.ctm);
+                }
+
+                "m" => {
+                    self.current = (arg(0), arg(1));
+                    self.start = self.current;
+                }
+                "l" => {
+                    let to = (arg(0), arg(1));
+                    self.path.push(Segment::Line(
+                        apply(&self.ctm, self.current.0, self.current.1),
+                        apply(&self.ctm, to.0, to.1),
+                    ));
+                    self.current = to;
+                }
+                "c" => self.curve((arg(4), arg(5))),
+                "v" | "y" => self.curve((arg(2), arg(3))),
+                "h" if self.current != self.start => {
+                    self.path.push(Segment::Line(
+                        apply(&self.ctm, self.current.0, self.current.1),
+                        apply(&self.ctm, self.start.0, self.start.1),
+                    ));
+                    self.current = self.start;
+                }
+                "re" => {
+                    let (x, y, w, h) = (arg(0), arg(1), arg(2), arg(3));
+                    self.path.push(Segment::Rect([
+                        apply(&self.ctm, x, y),
+                        apply(&self.ctm, x + w, y),
+                        apply(&self.ctm, x + w, y + h),
+                        apply(&self.ctm, x, y + h),
+                    ]));
+                    self.current = (x, y);
+                    self.start = self.current;
+                }
+                "S" | "s" | "B" | "B*" | "b" | "b*" => self.paint(true),
+                "f" | "F" | "f*" => self.paint(false),
+                "n" => self.path.clear(),
+
+                "Tr" => self.render_mode = arg(0) as i64,
+                "Tj" | "'" | "\"" | "TJ" => {
+                    let chars = shown_chars(op.operands.last());
+                    if self.render_mode == 3 {
+                        self.invisible_chars += chars;
+                    } else {
+                        self.chars += chars;
+                    }
+                }
+
+                "BI" => self.image(),
+                "Do" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        self.draw_xobject(resources, name, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn curve(&mut self, to: (f32, f32)) {
+        self.path.push(Segment::Curve);
+        self.current = to;
+    }
+
+    /// Paint the current path: axis-aligned lines (stroked) and thin
+    /// rectangles (stroked or filled) are rules; anything else is a shape.
+    fn paint(&mut self, stroke: bool) {
+        let mut shape = false;
+        for segment in std::mem::take(&mut self.path) {
+            match segment {
+                Segment::Line(from, to) => {
+                    if !(stroke && self.add_rule(from, to)) {
+                        shape = true;
+                    }
+                }
+                Segment::Rect(corners) => {
+                    let (xs, ys) = (corners.map(|c| c.0), corners.map(|c| c.1));
+                    let width = xs.iter().fold(f32::MIN, |a, &b| a.max(b))
+                        - xs.iter().fold(f32::MAX, |a, &b| a.min(b));
+                    let height = ys.iter().fold(f32::MIN, |a, &b| a.max(b))
+                        - ys.iter().fold(f32::MAX, |a, &b| a.min(b));
+                    if width.min(height) <= MAX_RULE_WIDTH {
+                        // A rule drawn as a thin box, along its long side.
+                        let (a, b) = if width >= height {
+                            (corners[0], corners[1])
+                        } else {
+                            (corners[1], corners[2])
+                        };
+                        if !self.add_rule(a, b) {
+                            shape = true;
+                        }
+                    } else if stroke {
+                        // A cell border: each side is a rule.
+                        for (i, &corner) in corners.iter().enumerate() {
+                            self.add_rule(corner, corners[(i + 1) % 4]);
+                        }
+                    } else {
+                        shape = true;
+                    }
+                }
+                Segment::Curve => shape = true,
+            }
+        }
+        if shape {
+            self.paths += 1;
+        }
+    }
+
+    /// Record the line from `a` to `b` if it is a long enough horizontal or
+    /// vertical rule.
+    fn add_rule(&mut self, a: (f32, f32), b: (f32, f32)) -> bool {
+        let (dx, dy) = ((b.0 - a.0).abs(), (b.1 - a.1).abs());
+        if dy <= 0.5 && dx >= MIN_RULE_LENGTH {
+            self.horizontal.insert(a.1.round() as i32);
+            true
+        } else if dx <= 0.5 && dy >= MIN_RULE_LENGTH {
+            self.vertical.insert(a.0.round() as i32);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Add the area an image fills: the unit square under the current
+    /// transformation, clipped to the crop box.
+    fn image(&mut self) {
+        let corners =
+            [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(x, y)| apply(&self.ctm, x, y));
+        let (xs, ys) = (corners.map(|c| c.0), corners.map(|c| c.1));
+        let x0 = xs.iter().fold(f32::MAX, |a, &b| a.min(b)).max(self.crop[0]);
+        let x1 = xs.iter().fold(f32::MIN, |a, &b| a.max(b)).min(self.crop[2]);
+        let y0 = ys.iter().fold(f32::MAX, |a, &b| a.min(b)).max(self.crop[1]);
+        let y1 = ys.iter().fold(f32::MIN, |a, &b| a.max(b)).min(self.crop[3]);
+        if x1 > x0 && y1 > y0 {
+            self.image_area += (x1 - x0) * (y1 - y0);
+        }
+    }
+
+    fn draw_xobject(&mut self, resources: Option<&'a Dictionary>, name: &[u8], depth: usize) {
+        let doc = self.doc;
+        let Some(Object::Stream(stream)) = resource(doc, resources, b"XObject", name) else {
+            return;
+        };
+        let dict = &stream.dict;
+        match dict.get(b"Subtype") {
+            Ok(Object::Name(subtype)) if subtype == b"Image" => self.image(),
+            Ok(Object::Name(subtype)) if subtype == b"Form" => {
+                if depth >= MAX_FORM_DEPTH {
+                    return;
+                }
+                let Some(content) = stream_bytes(stream)
+                    .ok()
+                    .and_then(|bytes| Content::decode(&bytes).ok())
+                else {
+                    return;
+                };
+                let form_resources = dict
+                    .get(b"Resources")
+                    .ok()
+                    .and_then(|obj| resolve(doc, obj).as_dict().ok())
+                    .or(resources);
+                self.stack.push((self.ctm, self.render_mode));
+                if let Some(matrix) = form_matrix(doc, dict) {
+                    self.ctm = multiply(&matrix, &self.ctm);
+                }
+                // The form's path is its own.
+                let path = std::mem::take(&mut self.path);
+                self.scan(&content.operations, form_resources, depth + 1);
+                self.path = path;
+                if let Some((ctm, render_mode)) = self.stack.pop() {
+                    self.ctm = ctm;
+                    self.render_mode = render_mode;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A form XObject's `/Matrix`, if it has a valid one.
+fn form_matrix(doc: &Document, dict: &Dictionary) -> Option<Matrix> {
+    let items = resolve(doc, dict.get(b"Matrix").ok()?).as_array().ok()?;
+    let values: Vec<f32> = items.iter().filter_map(number).collect();
+    values.try_into().ok()
+}
+
+/// Non-space bytes in the string operand of a text-showing operator (an
+/// overestimate for multi-byte fonts).
+fn shown_chars(operand: Option<&Object>) -> usize {
+    match operand {
+        Some(Object::String(bytes, _)) => bytes.iter().filter(|b| !b.is_ascii_whitespace()).count(),
+        Some(Object::Array(items)) => items.iter().map(|item| shown_chars(Some(item))).sum(),
+        _ => 0,
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn apply(m: &Matrix, x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::{dictionary, Stream};
+
+    fn page_with(content: &str) -> (Document, ObjectId) {
+        let (mut doc, pages) = text_document(&["x"]);
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.into()));
+        doc.get_dictionary_mut(pages[0])
+            .unwrap()
+            .set("Contents", content_id);
+        (doc, pages[0])
+    }
+
+    #[test]
+    fn test_text_page() {
+        let (doc, _) = text_document(&["A line of running text on the page."]);
+        let class = &classify_pages(&doc, &[1])[0];
+        assert_eq!(class.kind, PageKind::Text);
+        assert_eq!(class.features.chars, 28);
+    }
+
+    #[test]
+    fn test_table_page() {
+        // A 4x3 grid of stroked cells.
+        let mut content = String::from("BT /F1 10 Tf 80 700 Td (Item) Tj ET ");
+        for row in 0..4 {
+            for col in 0..3 {
+                content.push_str(&format!("{} {} 100 20 re ", 72 + col * 100, 600 + row * 20));
+            }
+        }
+        content.push('S');
+        let (doc, page) = page_with(&content);
+        let features = page_features(&doc, page);
+        assert_eq!(features.horizontal_rules, 5);
+        assert_eq!(features.vertical_rules, 4);
+        assert_eq!(classify(&features), PageKind::Table);
+
+        // A page frame with a header rule is not a table.
+        let (doc, page) = page_with(
+            "BT /F1 10 Tf 80 700 Td (Body) Tj ET 36 36 540 720 re S 36 740 m 576 740 l S",
+        );
+        assert_eq!(classify(&page_features(&doc, page)), PageKind::Text);
+    }
+
+    #[test]
+    fn test_scan_and_figure_pages() {
+        let (mut doc, page) = page_with("q 612 0 0 792 0 0 cm /Im0 Do Q BT 3 Tr (OCR text) Tj ET");
+        let image = doc.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Image", "Width" => 1, "Height" => 1 },
+            vec![0],
+        ));
+        doc.get_dictionary_mut(page).unwrap().set(
+            "Resources",
+            dictionary! { "XObject" => dictionary! { "Im0" => image } },
+        );
+        let features = page_features(&doc, page);
+        assert_eq!(features.image_coverage, 1.0);
+        assert_eq!(features.invisible_chars, 7);
+        assert_eq!(classify(&features), PageKind::Scan);
+
+        let figure = PageFeatures {
+            chars: 40,
+            text_density: 0.8,
+            image_coverage: 0.45,
+            ..Default::default()
+        };
+        assert_eq!(classify(&figure), PageKind::Figure);
+        assert_eq!(classify(&PageFeatures::default()), PageKind::Blank);
+    }
+}
@@ -0,0 +1,501 @@
+//! Cleaning up extracted page text for particular kinds of documents.
+//!
+//! Extraction gives each page's text in layout order, with whatever the
+//! page prints around it. Profiles bundle the steps here that suit a kind
+//! of document: splitting side-by-side columns, removing running headers,
+//! footers, and page numbers, setting footnotes apart, and turning aligned
+//! table rows into tab-separated cells. All of them are heuristics that
+//! work on the text alone.
+
+use crate::pdf::text::PageText;
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// What to do with footnotes at the bottom of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Footnotes {
+    Keep,
+    /// Keep them after a `[footnotes]` line, so they can be told from the
+    /// body.
+    Mark,
+    Strip,
+}
+
+/// How to show rows of aligned columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tables {
+    /// As extracted, with the spacing of the layout.
+    Layout,
+    /// Cells separated by tabs.
+    Tsv,
+}
+
+/// Cleanup steps to apply to extracted text. The default changes nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractSettings {
+    /// Read side-by-side columns one after the other.
+    pub columns: bool,
+    /// Remove running headers and footers and page numbers.
+    pub boilerplate: bool,
+    pub footnotes: Footnotes,
+    pub tables: Tables,
+}
+
+impl Default for ExtractSettings {
+    fn default() -> Self {
+        ExtractSettings {
+            columns: false,
+            boilerplate: false,
+            footnotes: Footnotes::Keep,
+            tables: Tables::Layout,
+        }
+    }
+}
+
+/// The built-in profiles.
+pub const PROFILES: &[&str] = &["academic", "legal", "invoice", "book"];
+
+impl ExtractSettings {
+    /// The settings of a built-in profile.
+    pub fn profile(name: &str) -> Option<ExtractSettings> {
+        let settings = match name {
+            // Two-column papers with running heads and footnotes.
+            "academic" => ExtractSettings {
+                columns: true,
+                boilerplate: true,
+                footnotes: Footnotes::Mark,
+                tables: Tables::Layout,
+            },
+            // Footnotes in contracts and opinions carry substance; page
+            // headers (and Bates numbers) don't.
+            "legal" => ExtractSettings {
+                boilerplate: true,
+                footnotes: Footnotes::Mark,
+                ..Default::default()
+            },
+            // Line items are tables; footers may be legal fine print.
+            "invoice" => ExtractSettings {
+                tables: Tables::Tsv,
+                ..Default::default()
+            },
+            // Running heads and folios on every page.
+            "book" => ExtractSettings {
+                boilerplate: true,
+                footnotes: Footnotes::Mark,
+                ..Default::default()
+            },
+            _ => return None,
+        };
+        Some(settings)
+    }
+}
+
+impl FromStr for Footnotes {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "keep" => Ok(Footnotes::Keep),
+            "mark" => Ok(Footnotes::Mark),
+            "strip" => Ok(Footnotes::Strip),
+            _ => Err(format!(
+                "unknown footnote mode '{}' (expected keep, mark, or strip)",
+                s
+            )),
+        }
+    }
+}
+
+impl FromStr for Tables {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "layout" => Ok(Tables::Layout),
+            "tsv" => Ok(Tables::Tsv),
+            _ => Err(format!(
+                "unknown table mode '{}' (expected layout or tsv)",
+                s
+            )),
+        }
+    }
+}
+
+/// Apply `settings` to the text of `pages`. Running headers and footers are
+/// found by comparing the pages given, so pass all of a selection at once.
+pub fn apply(settings: &ExtractSettings, pages: &mut [PageText]) {
+    let mut pages: Vec<&mut PageText> = pages.iter_mut().filter(|p| p.error.is_none()).collect();
+    if settings.boilerplate {
+        let texts: Vec<&str> = pages.iter().map(|p| p.text.as_str()).collect();
+        let repeated = repeated_edge_lines(&texts);
+        for page in pages.iter_mut() {
+            page.text = strip_boilerplate(&page.text, &repeated);
+        }
+    }
+    for page in pages {
+        if settings.columns {
+            if let Some(text) = split_columns(&page.text) {
+                page.text = text;
+            }
+        }
+        if settings.footnotes != Footnotes::Keep {
+            page.text = handle_footnotes(&page.text, settings.footnotes);
+        }
+        if settings.tables == Tables::Tsv {
+            page.text = tabulate(&page.text);
+        }
+    }
+}
+
+/// Lines looked at for running headers and footers at each end of a page.
+const EDGE_LINES: usize = 2;
+
+/// A line with its digits replaced, so "Page 3" and "Page 4" agree.
+fn edge_key(line: &str) -> String {
+    line.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_digit() { '#' } else { c })
+        .collect()
+}
+
+/// Indices of the first and last few non-empty lines of a page; only the
+/// very first and last on short pages, whose middle is body text.
+fn edge_indices(lines: &[&str]) -> Vec<usize> {
+    let non_empty: Vec<usize> = (0..lines.len())
+        .filter(|&i| !lines[i].trim().is_empty())
+        .collect();
+    let take = if non_empty.len() > 2 * EDGE_LINES {
+        EDGE_LINES
+    } else {
+        non_empty.len().min(2) / 2
+    };
+    let mut edges = non_empty[..take].to_vec();
+    edges.extend_from_slice(&non_empty[non_empty.len() - take..]);
+    edges
+}
+
+/// Keys of edge lines that recur on at least half the pages (and at least
+/// three of them).
+fn repeated_edge_lines(texts: &[&str]) -> HashSet<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for text in texts {
+        let lines: Vec<&str> = text.lines().collect();
+        let keys: HashSet<String> = edge_indices(&lines)
+            .into_iter()
+            .map(|i| edge_key(lines[i]))
+            .collect();
+        for key in keys {
+            *counts.entry(key).or_default() += 1;
+        }
+    }
+    let threshold = (texts.len().div_ceil(2)).max(3);
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count >= threshold)
+        .map(|(key, _)| key)
+        .collect()
+}
+
+/// A line that is only a page number: `12`, `- 12 -`, `Page 3 of 10`, `xiv`.
+fn is_page_number(line: &str) -> bool {
+    static PAGE: OnceLock<Regex> = OnceLock::new();
+    static ROMAN: OnceLock<Regex> = OnceLock::new();
+    let page = PAGE.get_or_init(|| {
+        Regex::new(
+            r"^(?i)(?:page\s+)?[-–—]?\s*(?P<number>\d{1,5}|[ivxlcdm]{1,8})\s*[-–—]?(?:\s+of\s+\d{1,5})?$",
+        )
+        .unwrap()
+    });
+    // Well-formed numerals only, so words like "mild" don't count.
+    let roman = ROMAN.get_or_init(|| {
+        Regex::new(r"^m{0,3}(?:cm|cd|d?c{0,3})(?:xc|xl|l?x{0,3})(?:ix|iv|v?i{0,3})$").unwrap()
+    });
+    let Some(caps) = page.captures(line.trim()) else {
+        return false;
+    };
+    let number = &caps["number"];
+    number.bytes().all(|b| b.is_ascii_digit()) || roman.is_match(&number.to_lowercase())
+}
+
+/// Drop edge lines that are page numbers or in `repeated`.
+fn strip_boilerplate(text: &str, repeated: &HashSet<String>) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let edges: HashSet<usize> = edge_indices(&lines).into_iter().collect();
+    let kept: Vec<&str> = lines
+        .iter()
+        .enumerate()
+        .filter(|(i, line)| {
+            !(edges.contains(i) && (is_page_number(line) || repeated.contains(&edge_key(line))))
+        })
+        .map(|(_, line)| *line)
+        .collect();
+    join_lines(&kept, text)
+}
+
+/// `lines` joined by newlines, ending in one if `original` did.
+fn join_lines(lines: &[&str], original: &str) -> String {
+    let mut text = lines.join("\n");
+    if original.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
+
+/// Minimum run of spaces between two columns.
+const GUTTER: usize = 3;
+
+/// Rewrite a page whose lines hold two columns side by side so the left
+/// column comes before the right one, block by block between lines that
+/// span both. `None` if no gutter runs down the page.
+fn split_columns(text: &str) -> Option<String> {
+    let lines: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+    let width = lines.iter().map(Vec::len).max().unwrap_or(0);
+    let non_empty = lines
+        .iter()
+        .filter(|l| l.iter().any(|c| !c.is_whitespace()))
+        .count();
+
+    // How many lines have a gap (or leading space before text) at each
+    // position.
+    let mut coverage = vec![0; width];
+    for line in &lines {
+        for (start, end) in gaps(line) {
+            for count in &mut coverage[start..end] {
+                *count += 1;
+            }
+        }
+    }
+    let (gutter, &count) = coverage
+        .iter()
+        .enumerate()
+        .max_by_key(|&(i, count)| (*count, std::cmp::Reverse(i)))?;
+    if count < 3 || count * 5 < non_empty * 2 {
+        return None;
+    }
+
+    let mut out: Vec<String> = Vec::new();
+    let mut left: Vec<String> = Vec::new();
+    let mut right: Vec<String> = Vec::new();
+    let flush = |out: &mut Vec<String>, left: &mut Vec<String>, right: &mut Vec<String>| {
+        out.append(left);
+        out.append(right);
+    };
+    for line in &lines {
+        let splits = gaps(line)
+            .into_iter()
+            .any(|(start, end)| start <= gutter && gutter < end);
+        if splits || line.len() <= gutter {
+            let (l, r) = line.split_at(gutter.min(line.len()));
+            let l: String = l.iter().collect();
+            let r: String = r.iter().collect();
+            if !l.trim().is_empty() || r.trim().is_empty() {
+                left.push(l.trim_end().to_string());
+            }
+            if !r.trim().is_empty() {
+                right.push(r.trim().to_string());
+            }
+        } else {
+            // A heading or figure caption across both columns.
+            flush(&mut out, &mut left, &mut right);
+            out.push(line.iter().collect::<String>().trim_end().to_string());
+        }
+    }
+    flush(&mut out, &mut left, &mut right);
+    let out: Vec<&str> = out.iter().map(String::as_str).collect();
+    Some(join_lines(&out, text))
+}
+
+/// Runs of at least [`GUTTER`] spaces followed by text, as character
+/// position ranges; leading space counts.
+fn gaps(line: &[char]) -> Vec<(usize, usize)> {
+    let mut found = Vec::new();
+    let mut start = None;
+    for (i, c) in line.iter().enumerate() {
+        match (c.is_whitespace(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                if i - s >= GUTTER {
+                    found.push((s, i));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// A footnote's first line: a number, symbol, or superscript, then text.
+fn is_footnote_start(line: &str) -> bool {
+    static NOTE: OnceLock<Regex> = OnceLock::new();
+    NOTE.get_or_init(|| Regex::new(r"^\s*(?:\d{1,3}\.?|[*†‡§¶]+|[¹²³⁴⁵⁶⁷⁸⁹⁰]+)\s*\p{L}").unwrap())
+        .is_match(line)
+}
+
+/// A rule printed above footnotes.
+fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.chars().count() >= 5 && line.chars().all(|c| matches!(c, '_' | '-' | '—' | '─'))
+}
+
+/// Mark or strip the footnote block at the bottom of a page: the trailing
+/// lines from the first one that starts like a footnote, when a blank line
+/// or a rule separates them from the body.
+fn handle_footnotes(text: &str, mode: Footnotes) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let end = lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let mut start = None;
+    for i in (1..end).rev() {
+        let above = lines[i - 1];
+        if is_footnote_start(lines[i]) && (above.trim().is_empty() || is_separator(above)) {
+            start = Some(i);
+        }
+        // Footnotes are short; stop looking past the bottom third.
+        if end - i > (end / 3).max(6) {
+            break;
+        }
+    }
+    let Some(start) = start else {
+        return text.to_string();
+    };
+
+    let mut body_end = start;
+    while body_end > 0
+        && (lines[body_end - 1].trim().is_empty() || is_separator(lines[body_end - 1]))
+    {
+        body_end -= 1;
+    }
+    let mut kept: Vec<&str> = lines[..body_end].to_vec();
+    if mode == Footnotes::Mark {
+        kept.push("");
+        kept.push("[footnotes]");
+        kept.extend_from_slice(&lines[start..end]);
+    }
+    join_lines(&kept, text)
+}
+
+/// Replace the wide gaps in lines with two or more of them (table rows)
+/// by tabs.
+fn tabulate(text: &str) -> String {
+    static GAP: OnceLock<Regex> = OnceLock::new();
+    let gap = GAP.get_or_init(|| Regex::new(r"[ \t]{2,}|\t").unwrap());
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim();
+            if gap.find_iter(trimmed).count() < 2 {
+                return line.to_string();
+            }
+            gap.split(trimmed).collect::<Vec<_>>().join("\t")
+        })
+        .collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    join_lines(&lines, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(page: u32, text: &str) -> PageText {
+        PageText {
+            page,
+            text: text.to_string(),
+            error: None,
+            recovered: false,
+        }
+    }
+
+    #[test]
+    fn test_default_changes_nothing() {
+        let text = "Running head\nBody   text  here   x\n\n1 A note\n";
+        let mut pages = vec![page(1, text)];
+        apply(&ExtractSettings::default(), &mut pages);
+        assert_eq!(pages[0].text, text);
+    }
+
+    #[test]
+    fn test_strip_boilerplate() {
+        let mut pages: Vec<PageText> = (1..=4)
+            .map(|n| {
+                page(
+                    n,
+                    &format!(
+                        "ACME Annual Report 2024\nBody of page {}.\nPage {} of 4\n",
+                        n, n
+                    ),
+                )
+            })
+            .collect();
+        let settings = ExtractSettings {
+            boilerplate: true,
+            ..Default::default()
+        };
+        apply(&settings, &mut pages);
+        assert_eq!(pages[0].text, "Body of page 1.\n");
+        assert_eq!(pages[3].text, "Body of page 4.\n");
+
+        assert!(is_page_number("- 12 -"));
+        assert!(is_page_number("xiv"));
+        assert!(!is_page_number("12 Monkeys"));
+    }
+
+    #[test]
+    fn test_split_columns() {
+        let text = "\
+Title Across Both Columns
+The left column    And the right
+starts here and    column follows
+ends here.         after it.
+";
+        assert_eq!(
+            split_columns(text).unwrap(),
+            "Title Across Both Columns\nThe left column\nstarts here and\nends here.\n\
+             And the right\ncolumn follows\nafter it.\n"
+        );
+        assert_eq!(split_columns("one line of prose\nand another\n"), None);
+    }
+
+    #[test]
+    fn test_footnotes() {
+        let text = "Body text cites a source.\n\n1 Smith, Methods (2001).\n2 Ibid.\n";
+        assert_eq!(
+            handle_footnotes(text, Footnotes::Strip),
+            "Body text cites a source.\n"
+        );
+        assert_eq!(
+            handle_footnotes(text, Footnotes::Mark),
+            "Body text cites a source.\n\n[footnotes]\n1 Smith, Methods (2001).\n2 Ibid.\n"
+        );
+        // Without a gap above, numbered lines are part of the body.
+        let list = "Steps:\n1 Open it.\n2 Close it.\n";
+        assert_eq!(handle_footnotes(list, Footnotes::Strip), list);
+    }
+
+    #[test]
+    fn test_tabulate() {
+        let text = "Invoice 42\nWidget    2    $10.00\nNote:  thanks\n";
+        assert_eq!(
+            tabulate(text),
+            "Invoice 42\nWidget\t2\t$10.00\nNote:  thanks\n"
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        for name in PROFILES {
+            assert!(ExtractSettings::profile(name).is_some());
+        }
+        assert_eq!(ExtractSettings::profile("poetry"), None);
+        assert_eq!("mark".parse::<Footnotes>(), Ok(Footnotes::Mark));
+        assert!("bogus".parse::<Tables>().is_err());
+    }
+}
@@ -0,0 +1,367 @@
+//! Color space usage per page.
+//!
+//! Print providers ask which color models a file uses (RGB content in a
+//! CMYK job gets converted, possibly with visible shifts) and whether it
+//! contains spot colors, which print as separate inks. This walks each
+//! page's content (including form XObjects) and records the color spaces of
+//! color operators, images, and shadings.
+
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::pages::{find_inherited, resolve, resource, MAX_FORM_DEPTH};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Colorant names of process inks, which are not spot colors.
+const PROCESS_COLORANTS: &[&[u8]] = &[b"Cyan", b"Magenta", b"Yellow", b"Black", b"None", b"All"];
+
+/// The family of a color space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorSpace {
+    Gray,
+    Rgb,
+    Cmyk,
+    IccGray,
+    IccRgb,
+    IccCmyk,
+    /// CIE-based `CalGray`, `CalRGB`, and `Lab`.
+    Calibrated,
+    Separation,
+    DeviceN,
+    Pattern,
+}
+
+impl fmt::Display for ColorSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ColorSpace::Gray => "Gray",
+            ColorSpace::Rgb => "RGB",
+            ColorSpace::Cmyk => "CMYK",
+            ColorSpace::IccGray => "ICC Gray",
+            ColorSpace::IccRgb => "ICC RGB",
+            ColorSpace::IccCmyk => "ICC CMYK",
+            ColorSpace::Calibrated => "Calibrated",
+            ColorSpace::Separation => "Separation",
+            ColorSpace::DeviceN => "DeviceN",
+            ColorSpace::Pattern => "Pattern",
+        })
+    }
+}
+
+/// Color usage of one page.
+#[derive(Debug, Clone, Default)]
+pub struct PageColors {
+    /// Physical page number (1-indexed).
+    pub page: u32,
+    pub spaces: BTreeSet<ColorSpace>,
+    /// Names of spot colors (Separation and non-process DeviceN colorants).
+    pub spot_colors: BTreeSet<String>,
+}
+
+/// Report the color spaces used on every page.
+pub fn page_colors(doc: &Document) -> Vec<PageColors> {
+    doc.get_pages()
+        .into_iter()
+        .map(|(page, page_id)| {
+            let mut scanner = Scanner {
+                doc,
+                colors: PageColors {
+                    page,
+                    ..PageColors::default()
+                },
+                fill_set: false,
+                stroke_set: false,
+                stack: Vec::new(),
+            };
+            let resources = find_inherited(doc, page_id, b"Resources")
+                .and_then(|obj| resolve(doc, obj).as_dict().ok());
+            if let Ok(content) = doc.get_and_decode_page_content(page_id) {
+                scanner.scan(&content.operations, resources, 0);
+            }
+            scanner.colors
+        })
+        .collect()
+}
+
+struct Scanner<'a> {
+    doc: &'a Document,
+    colors: PageColors,
+    /// Whether the fill/stroke color was set in the current graphics state;
+    /// painting with an unset color uses the initial DeviceGray black.
+    fill_set: bool,
+    stroke_set: bool,
+    stack: Vec<(bool, bool)>,
+}
+
+impl<'a> Scanner<'a> {
+    fn scan(&mut self, operations: &[Operation], resources: Option<&'a Dictionary>, depth: usize) {
+        for op in operations {
+            match op.operator.as_str() {
+                "q" => self.stack.push((self.fill_set, self.stroke_set)),
+                "Q" => {
+                    if let Some((fill, stroke)) = self.stack.pop() {
+                        self.fill_set = fill;
+                        self.stroke_set = stroke;
+                    }
+                }
+                "g" | "G" => self.set_color(op, ColorSpace::Gray),
+                "rg" | "RG" => self.set_color(op, ColorSpace::Rgb),
+                "k" | "K" => self.set_color(op, ColorSpace::Cmyk),
+                "cs" | "CS" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        self.add_named_space(resources, name);
+                    }
+                    if op.operator == "cs" {
+                        self.fill_set = true;
+                    } else {
+                        self.stroke_set = true;
+                    }
+                }
+                "f" | "F" | "f*" => self.paint(true, false),
+                "S" | "s" => self.paint(false, true),
+                "B" | "B*" | "b" | "b*" => self.paint(true, true),
+                "Tj" | "TJ" | "'" | "\"" => self.paint(true, false),
+                "sh" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        let shading = resource(self.doc, resources, b"Shading", name).and_then(
+                            |obj| match obj {
+                                Object::Dictionary(dict) => Some(dict),
+                                Object::Stream(stream) => Some(&stream.dict),
+                                _ => None,
+                            },
+                        );
+                        if let Some(space) = shading.and_then(|dict| dict.get(b"ColorSpace").ok()) {
+                            self.add_space(resources, space);
+                        }
+                    }
+                }
+                "Do" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        self.draw_xobject(resources, name, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_color(&mut self, op: &Operation, space: ColorSpace) {
+        self.colors.spaces.insert(space);
+        if op.operator.chars().all(|c| c.is_ascii_lowercase()) {
+            self.fill_set = true;
+        } else {
+            self.stroke_set = true;
+        }
+    }
+
+    fn paint(&mut self, fill: bool, stroke: bool) {
+        if (fill && !self.fill_set) || (stroke && !self.stroke_set) {
+            self.colors.spaces.insert(ColorSpace::Gray);
+        }
+    }
+
+    fn draw_xobject(&mut self, resources: Option<&'a Dictionary>, name: &[u8], depth: usize) {
+        let Some(Object::Stream(stream)) = resource(self.doc, resources, b"XObject", name) else {
+            return;
+        };
+        let dict = &stream.dict;
+        match dict.get(b"Subtype") {
+            Ok(Object::Name(subtype)) if subtype == b"Image" => {
+                let is_mask = dict
+                    .get(b"ImageMask")
+                    .and_then(Object::as_bool)
+                    .unwrap_or(false);
+                if is_mask {
+                    // Stencil masks are painted with the fill color.
+                    self.paint(true, false);
+                } else if let Ok(space) = dict.get(b"ColorSpace") {
+                    self.add_space(resources, space);
+                }
+            }
+            Ok(Object::Name(subtype)) if subtype == b"Form" => {
+                if depth >= MAX_FORM_DEPTH {
+                    return;
+                }
+                let Some(content) = stream_bytes(stream)
+                    .ok()
+                    .and_then(|bytes| Content::decode(&bytes).ok())
+                else {
+                    return;
+                };
+                let form_resources = dict
+                    .get(b"Resources")
+                    .ok()
+                    .and_then(|obj| resolve(self.doc, obj).as_dict().ok())
+                    .or(resources);
+                self.stack.push((self.fill_set, self.stroke_set));
+                self.scan(&content.operations, form_resources, depth + 1);
+                if let Some((fill, stroke)) = self.stack.pop() {
+                    self.fill_set = fill;
+                    self.stroke_set = stroke;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Record a color space selected by name with `cs`/`CS`.
+    fn add_named_space(&mut self, resources: Option<&'a Dictionary>, name: &[u8]) {
+        if let Some(space) = device_space(name) {
+            self.colors.spaces.insert(space);
+        } else if let Some(space) = resource(self.doc, resources, b"ColorSpace", name) {
+            self.add_space(resources, space);
+        }
+    }
+
+    /// Record a color space object: a name or a `[/Family ...]` array.
+    fn add_space(&mut self, resources: Option<&'a Dictionary>, space: &'a Object) {
+        let doc = self.doc;
+        match resolve(doc, space) {
+            Object::Name(name) => self.add_named_space(resources, name),
+            Object::Array(items) => {
+                let Some(family) = items
+                    .first()
+                    .and_then(|obj| resolve(doc, obj).as_name().ok())
+                else {
+                    return;
+                };
+                match family {
+                    b"ICCBased" => {
+                        let components = items
+                            .get(1)
+                            .and_then(|obj| resolve(doc, obj).as_stream().ok())
+                            .and_then(|s| s.dict.get(b"N").ok())
+                            .and_then(|n| n.as_i64().ok());
+                        let space = match components {
+                            Some(1) => ColorSpace::IccGray,
+                            Some(4) => ColorSpace::IccCmyk,
+                            _ => ColorSpace::IccRgb,
+                        };
+                        self.colors.spaces.insert(space);
+                    }
+                    b"CalGray" | b"CalRGB" | b"Lab" => {
+                        self.colors.spaces.insert(ColorSpace::Calibrated);
+                    }
+                    b"Separation" => {
+                        self.colors.spaces.insert(ColorSpace::Separation);
+                        if let Some(Object::Name(colorant)) =
+                            items.get(1).map(|obj| resolve(doc, obj))
+                        {
+                            self.add_spot(colorant);
+                        }
+                    }
+                    b"DeviceN" => {
+                        self.colors.spaces.insert(ColorSpace::DeviceN);
+                        if let Some(Object::Array(colorants)) =
+                            items.get(1).map(|obj| resolve(doc, obj))
+                        {
+                            for colorant in colorants {
+                                if let Ok(name) = resolve(doc, colorant).as_name() {
+                                    self.add_spot(name);
+                                }
+                            }
+                        }
+                    }
+                    // Indexed colors are stored in terms of their base space.
+                    b"Indexed" => {
+                        if let Some(base) = items.get(1) {
+                            self.add_space(resources, base);
+                        }
+                    }
+                    b"Pattern" => {
+                        self.colors.spaces.insert(ColorSpace::Pattern);
+                    }
+                    name => {
+                        if let Some(space) = device_space(name) {
+                            self.colors.spaces.insert(space);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_spot(&mut self, colorant: &[u8]) {
+        if !PROCESS_COLORANTS.contains(&colorant) {
+            self.colors
+                .spot_colors
+                .insert(String::from_utf8_lossy(colorant).into_owned());
+        }
+    }
+}
+
+fn device_space(name: &[u8]) -> Option<ColorSpace> {
+    match name {
+        b"DeviceGray" | b"G" => Some(ColorSpace::Gray),
+        b"DeviceRGB" | b"RGB" => Some(ColorSpace::Rgb),
+        b"DeviceCMYK" | b"CMYK" => Some(ColorSpace::Cmyk),
+        b"Pattern" => Some(ColorSpace::Pattern),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn test_page_colors_finds_spot_colors() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        let separation = Object::Array(vec![
+            "Separation".into(),
+            Object::Name(b"PANTONE 185 C".to_vec()),
+            "DeviceCMYK".into(),
+            Object::Null,
+        ]);
+        let content = b"1 0 0 rg 0 0 10 10 re f /CS0 cs 1 scn 0 0 5 5 re f".to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page = doc.get_dictionary_mut(page_ids[0]).unwrap();
+        page.set("Contents", content_id);
+        page.set(
+            "Resources",
+            dictionary! { "ColorSpace" => dictionary! { "CS0" => separation } },
+        );
+
+        let colors = &page_colors(&doc)[0];
+        assert_eq!(
+            colors.spaces.iter().copied().collect::<Vec<_>>(),
+            vec![ColorSpace::Rgb, ColorSpace::Separation]
+        );
+        assert_eq!(
+            colors.spot_colors.iter().collect::<Vec<_>>(),
+            vec!["PANTONE 185 C"]
+        );
+    }
+
+    #[test]
+    fn test_page_colors_default_gray_and_images() {
+        let (mut doc, page_ids) = text_document(&["black text"]);
+        let profile = doc.add_object(Stream::new(dictionary! { "N" => 4 }, Vec::new()));
+        let image = doc.add_object(Stream::new(
+            dictionary! {
+                "Subtype" => "Image",
+                "ColorSpace" => vec!["ICCBased".into(), Object::Reference(profile)],
+            },
+            Vec::new(),
+        ));
+        let content = b"BT /F1 12 Tf (black text) Tj ET /Im0 Do".to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page = doc.get_dictionary_mut(page_ids[0]).unwrap();
+        page.set("Contents", content_id);
+        page.set(
+            "Resources",
+            dictionary! { "XObject" => dictionary! { "Im0" => image } },
+        );
+
+        let colors = &page_colors(&doc)[0];
+        assert_eq!(
+            colors.spaces.iter().copied().collect::<Vec<_>>(),
+            vec![ColorSpace::Gray, ColorSpace::IccCmyk]
+        );
+        assert!(colors.spot_colors.is_empty());
+    }
+}
@@ -1,14 +1,75 @@
-use crate::pdf::cache::{get_cached_pdf, CachedPdf};
+use crate::pdf::cache::{get_cached_pdf, get_cached_pdf_for_text, CachedPdf};
+use crate::pdf::page_labels::{extract_page_labels_from_doc, PageLabel};
+use crate::pdf::pages::materialize_inherited;
+use crate::pdf::portfolio::load_portfolio_item;
+use crate::pdf::revisions::load_revision;
+use crate::pdf::strings::decode_text_string;
+use crate::pdf::toc::{extract_toc_from_doc, TocEntry};
 use anyhow::{Context, Result};
-use lopdf::{Document, Object, ObjectId};
-use std::path::Path;
+use lopdf::{Dictionary, Document, Object, ObjectId, StringFormat};
+use md5::{Digest, Md5};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Options controlling how a document is written.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SaveOptions {
+    /// Produce identical bytes for identical inputs: drop unreachable objects,
+    /// renumber the rest in order, and derive the trailer `/ID` from the
+    /// content. `ModDate` is taken from `SOURCE_DATE_EPOCH` if set and
+    /// removed otherwise.
+    pub deterministic: bool,
+    pub document_id: DocumentId,
+    pub compression: Compression,
+    /// Linearize ("fast web view") the output so the first page can be
+    /// displayed before the whole file has downloaded. Requires `qpdf`.
+    pub linearize: bool,
+}
+
+/// What happens to the trailer `/ID` on output.
+///
+/// The ID is a pair: a permanent identifier assigned when the document was
+/// created, and one that changes with every revision. Signature validators
+/// and document management systems use it to tell "the same document,
+/// updated" from "a different document".
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocumentId {
+    /// Write a new ID: the output is a new document.
+    #[default]
+    Regenerate,
+    /// Keep the permanent identifier and replace only the second, as an
+    /// incremental update would. Documents without an ID get a new one.
+    Keep,
+}
+
+/// How streams and cross-reference data are encoded on output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// Leave streams as they are in the source document.
+    #[default]
+    Preserve,
+    /// Flate-compress eligible streams and write PDF 1.5 object streams and
+    /// a cross-reference stream.
+    Compress,
+    /// Decompress every stream and write a classic xref table (for debugging).
+    Uncompressed,
+}
 
 pub struct PdfDocument {
     cached: CachedPdf,
-    #[allow(dead_code)]
     pub path: String,
 }
 
+// Opened documents are handed to blocking tasks by the MCP server.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<PdfDocument>();
+};
+
 impl PdfDocument {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let path_str = path.as_ref().display().to_string();
@@ -19,6 +80,78 @@ impl PdfDocument {
         })
     }
 
+    /// Open a PDF for reading its text only, without image sample data (see
+    /// [`LoadMode::TextOnly`](crate::pdf::cache::LoadMode::TextOnly)).
+    ///
+    /// The result must not be used to write PDFs.
+    pub fn open_for_text<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path_str = path.as_ref().display().to_string();
+        let cached =
+            get_cached_pdf_for_text(&path).with_context(|| format!("cache PDF: {}", path_str))?;
+        Ok(PdfDocument {
+            cached,
+            path: path_str,
+        })
+    }
+
+    /// Open a PDF as of an earlier incremental-update revision (1-based).
+    ///
+    /// With `None`, this is the same as [`PdfDocument::open`].
+    pub fn open_at_version<P: AsRef<Path>>(path: P, version: Option<usize>) -> Result<Self> {
+        let Some(version) = version else {
+            return Self::open(path);
+        };
+        let cached = load_revision(&path, version)?;
+        Ok(Self::from_cached(cached, path))
+    }
+
+    /// Switch to a PDF packaged in this portfolio, if `item` is given.
+    ///
+    /// The item is looked up by its name in the portfolio or its file name.
+    pub fn select_portfolio_item(self, item: Option<&str>) -> Result<Self> {
+        let Some(item) = item else {
+            return Ok(self);
+        };
+        let cached = load_portfolio_item(self.doc(), item)
+            .with_context(|| format!("open portfolio item in {}", self.path))?;
+        Ok(PdfDocument {
+            cached,
+            path: format!("{} ({})", self.path, item),
+        })
+    }
+
+    /// Open a PDF held in memory, e.g. one fetched over the network or, in
+    /// WebAssembly, read in the browser.
+    ///
+    /// The document is not stored in the global cache; drop it to free it.
+    #[allow(dead_code)] // Library API; the CLI opens files by path.
+    pub fn open_bytes(bytes: &[u8]) -> Result<Self> {
+        let cached = CachedPdf::from_bytes(bytes)?;
+        Ok(Self::from_cached(cached, "(memory)"))
+    }
+
+    /// Open a PDF from a reader (a download, an object store response, ...)
+    /// positioned at the start of the file. Like [`PdfDocument::open_bytes`],
+    /// the document is not stored in the global cache.
+    #[allow(dead_code)] // Library API, like `open_bytes`.
+    pub fn open_reader<R: Read>(reader: R) -> Result<Self> {
+        let cached = CachedPdf::from_reader(reader)?;
+        Ok(Self::from_cached(cached, "(memory)"))
+    }
+
+    /// Wrap an already-loaded cache entry.
+    pub fn from_cached<P: AsRef<Path>>(cached: CachedPdf, path: P) -> Self {
+        PdfDocument {
+            cached,
+            path: path.as_ref().display().to_string(),
+        }
+    }
+
+    /// Get the cache entry backing this document (for per-page text access).
+    pub fn cached(&self) -> &CachedPdf {
+        &self.cached
+    }
+
     /// Get a reference to the underlying document.
     pub fn doc(&self) -> &Document {
         self.cached.document()
@@ -35,23 +168,34 @@ impl PdfDocument {
         pages
     }
 
+    /// Table of contents (bookmarks), nested.
+    pub fn toc(&self) -> Result<Vec<TocEntry>> {
+        extract_toc_from_doc(self.doc())
+    }
+
+    /// Printed label of every page, in page order.
+    #[allow(dead_code)] // Library API; commands read labels from the document.
+    pub fn page_labels(&self) -> Result<Vec<PageLabel>> {
+        extract_page_labels_from_doc(self.doc())
+    }
+
     /// Get metadata from the document info dictionary
     pub fn get_info(&self) -> PdfInfo {
         let mut info = PdfInfo::default();
         let doc = self.doc();
 
-        if let Ok(info_obj) = doc.trailer.get(b"Info") {
-            if let Object::Reference(info_ref) = info_obj {
-                if let Ok(Object::Dictionary(dict)) = doc.get_object(*info_ref) {
-                    info.title = get_string_from_dict(dict, b"Title");
-                    info.author = get_string_from_dict(dict, b"Author");
-                    info.creator = get_string_from_dict(dict, b"Creator");
-                    info.producer = get_string_from_dict(dict, b"Producer");
-                    info.creation_date = get_string_from_dict(dict, b"CreationDate");
-                    info.mod_date = get_string_from_dict(dict, b"ModDate");
-                    info.subject = get_string_from_dict(dict, b"Subject");
-                    info.keywords = get_string_from_dict(dict, b"Keywords");
-                }
+        if let Ok(Object::Reference(info_ref)) = doc.trailer.get(b"Info") {
+            if let Ok(Object::Dictionary(dict)) = doc.get_object(*info_ref) {
+                info.title = get_string_from_dict(dict, b"Title");
+                info.author = get_string_from_dict(dict, b"Author");
+                info.creator = get_string_from_dict(dict, b"Creator");
+                info.producer = get_string_from_dict(dict, b"Producer");
+                info.creation_date = get_string_from_dict(dict, b"CreationDate");
+                info.mod_date = get_string_from_dict(dict, b"ModDate");
+                info.created = info.creation_date.as_deref().and_then(PdfDate::parse);
+                info.modified = info.mod_date.as_deref().and_then(PdfDate::parse);
+                info.subject = get_string_from_dict(dict, b"Subject");
+                info.keywords = get_string_from_dict(dict, b"Keywords");
             }
         }
 
@@ -61,6 +205,10 @@ impl PdfDocument {
 
     /// Extract specific pages to a new document.
     ///
+    /// Each kept page gets its inherited attributes (resources, boxes,
+    /// rotation) copied onto it, so it renders the same even if a later
+    /// step moves it to another page tree.
+    ///
     /// Note: This operation requires cloning the document since it mutates
     /// the page tree to delete unwanted pages.
     pub fn extract_pages(&self, pages: &[u32]) -> Result<Document> {
@@ -75,6 +223,12 @@ impl PdfDocument {
             }
         }
 
+        for &(num, page_id) in &all_pages {
+            if pages.contains(&num) {
+                materialize_inherited(&mut new_doc, page_id);
+            }
+        }
+
         // Get page numbers to delete (pages NOT in our list)
         let pages_to_delete: Vec<u32> = all_pages
             .iter()
@@ -90,8 +244,74 @@ impl PdfDocument {
         Ok(new_doc)
     }
 
+    /// Build a new document whose pages appear in the given order.
+    ///
+    /// Pages may be omitted or repeated. Every page is moved directly under
+    /// the root `Pages` node with its inherited attributes copied onto it.
+    /// Repeats are new page dictionaries that share the original's content
+    /// and resources, so duplicating a page adds only a few bytes. Each
+    /// repeat gets its own copies of the page's annotations (see
+    /// [`copy_annotations`]).
+    pub fn arrange_pages(&self, order: &[u32]) -> Result<Document> {
+        let mut new_doc = self.doc().clone();
+        let all_pages = self.page_ids();
+        let total = all_pages.len() as u32;
+
+        for &page in order {
+            if page == 0 || page > total {
+                anyhow::bail!("Page {} is out of range (1-{})", page, total);
+            }
+        }
+
+        let root_pages_id = match new_doc.catalog()?.get(b"Pages") {
+            Ok(Object::Reference(id)) => *id,
+            _ => anyhow::bail!("Document catalog has no page tree"),
+        };
+
+        for &(_, page_id) in &all_pages {
+            materialize_inherited(&mut new_doc, page_id);
+        }
+
+        let mut used = vec![false; all_pages.len()];
+        let mut kids = Vec::with_capacity(order.len());
+        for &page in order {
+            let index = page as usize - 1;
+            let source_id = all_pages[index].1;
+            let page_id = if std::mem::replace(&mut used[index], true) {
+                let copy = new_doc.get_object(source_id)?.clone();
+                let copy_id = new_doc.add_object(copy);
+                copy_annotations(&mut new_doc, source_id, copy_id)?;
+                copy_id
+            } else {
+                source_id
+            };
+            new_doc
+                .get_dictionary_mut(page_id)?
+                .set("Parent", Object::Reference(root_pages_id));
+            kids.push(Object::Reference(page_id));
+        }
+
+        let root_pages = new_doc.get_dictionary_mut(root_pages_id)?;
+        root_pages.set("Count", Object::Integer(kids.len() as i64));
+        root_pages.set("Kids", Object::Array(kids));
+
+        // Drop pages that were left out and the now-empty intermediate nodes.
+        new_doc.prune_objects();
+
+        Ok(new_doc)
+    }
+
     /// Save to a file
     pub fn save<P: AsRef<Path>>(doc: &mut Document, path: P) -> Result<()> {
+        Self::save_with(doc, path, &SaveOptions::default())
+    }
+
+    /// Save to a file, applying the given output options first
+    pub fn save_with<P: AsRef<Path>>(
+        doc: &mut Document,
+        path: P,
+        options: &SaveOptions,
+    ) -> Result<()> {
         let path = path.as_ref();
 
         // Check if parent directory exists before attempting to save
@@ -101,10 +321,503 @@ impl PdfDocument {
             }
         }
 
-        doc.save(path)
+        let bytes = Self::to_bytes_with(doc, options)?;
+        write_atomic(path, &bytes)
             .with_context(|| format!("Failed to save PDF: {}", path.display()))?;
         Ok(())
     }
+
+    /// Serialize to an in-memory buffer instead of a file
+    pub fn to_bytes(doc: &mut Document) -> Result<Vec<u8>> {
+        Self::to_bytes_with(doc, &SaveOptions::default())
+    }
+
+    /// Serialize to an in-memory buffer, applying the given output options first
+    pub fn to_bytes_with(doc: &mut Document, options: &SaveOptions) -> Result<Vec<u8>> {
+        prepare_for_save(doc, options)?;
+        let mut buf = write_with_id(doc, options).context("Failed to serialize PDF")?;
+        if options.linearize {
+            buf = linearize(&buf, options.deterministic)?;
+        }
+        Ok(buf)
+    }
+}
+
+/// Give the page `copy_id`, a copy of `source_id`, its own `/Annots` array
+/// and annotation dictionaries, so editing an annotation on one page doesn't
+/// change the other. References between the page's annotations (`/P`,
+/// `/Popup`, `/Parent`, `/IRT`) are pointed at the copies. A copied widget
+/// whose field is its parent is added to the field's `/Kids`, so it shows
+/// the same value; widgets that are fields themselves are copied but not
+/// added to the form, since a field name can't appear twice.
+fn copy_annotations(doc: &mut Document, source_id: ObjectId, copy_id: ObjectId) -> Result<()> {
+    let annots = match doc.get_dictionary(copy_id)?.get(b"Annots") {
+        Ok(Object::Reference(id)) => doc.get_object(*id)?.as_array()?.clone(),
+        Ok(Object::Array(annots)) => annots.clone(),
+        _ => return Ok(()),
+    };
+
+    // Copy each annotation, remembering where the indirect ones went.
+    let mut copies = HashMap::from([(source_id, copy_id)]);
+    let mut new_annots = Vec::with_capacity(annots.len());
+    for annot in annots {
+        new_annots.push(match annot {
+            Object::Reference(id) => match doc.get_object(id) {
+                Ok(object @ Object::Dictionary(_)) => {
+                    let object = object.clone();
+                    let new_id = doc.add_object(object);
+                    copies.insert(id, new_id);
+                    Object::Reference(new_id)
+                }
+                _ => Object::Reference(id),
+            },
+            other => other,
+        });
+    }
+
+    let remap = |dict: &mut Dictionary| {
+        for key in [&b"P"[..], b"Popup", b"Parent", b"IRT"] {
+            if let Ok(Object::Reference(id)) = dict.get_mut(key) {
+                if let Some(new_id) = copies.get(id) {
+                    *id = *new_id;
+                }
+            }
+        }
+    };
+    let mut widgets = Vec::new();
+    for annot in &mut new_annots {
+        match annot {
+            Object::Reference(id) if copies.values().any(|new_id| new_id == id) => {
+                let dict = doc.get_dictionary_mut(*id)?;
+                remap(dict);
+                if dict.has(b"Parent") && !dict.has(b"T") {
+                    if let Ok(Object::Reference(parent)) = dict.get(b"Parent") {
+                        widgets.push((*parent, *id));
+                    }
+                }
+            }
+            Object::Dictionary(dict) => remap(dict),
+            _ => {}
+        }
+    }
+    for (parent, widget) in widgets {
+        if let Ok(Object::Array(kids)) = doc.get_dictionary_mut(parent)?.get_mut(b"Kids") {
+            kids.push(Object::Reference(widget));
+        }
+    }
+
+    doc.get_dictionary_mut(copy_id)?
+        .set("Annots", Object::Array(new_annots));
+    Ok(())
+}
+
+/// Apply output options to a document right before it is written.
+fn prepare_for_save(doc: &mut Document, options: &SaveOptions) -> Result<()> {
+    if options.deterministic {
+        doc.prune_objects();
+        doc.renumber_objects();
+
+        let source_date = match std::env::var("SOURCE_DATE_EPOCH") {
+            Ok(value) => Some(
+                value
+                    .trim()
+                    .parse::<i64>()
+                    .with_context(|| format!("Invalid SOURCE_DATE_EPOCH: {}", value))?,
+            ),
+            Err(_) => None,
+        };
+        if let Some(info) = info_dict_mut(doc) {
+            match source_date {
+                Some(secs) => info.set(
+                    "ModDate",
+                    Object::String(
+                        format_pdf_date_utc(secs).into_bytes(),
+                        StringFormat::Literal,
+                    ),
+                ),
+                None => {
+                    info.remove(b"ModDate");
+                }
+            }
+        }
+    }
+
+    match options.compression {
+        Compression::Preserve => {}
+        Compression::Compress => {
+            doc.compress();
+            // Object and cross-reference streams were introduced in PDF 1.5.
+            if doc.version.as_str() < "1.5" {
+                doc.version = "1.5".to_string();
+            }
+        }
+        Compression::Uncompressed => doc.decompress(),
+    }
+
+    Ok(())
+}
+
+/// Serialize a prepared document with its trailer `/ID` set per `options`.
+fn write_with_id(doc: &mut Document, options: &SaveOptions) -> Result<Vec<u8>> {
+    let permanent = match options.document_id {
+        DocumentId::Keep => permanent_id(doc),
+        DocumentId::Regenerate => None,
+    };
+    let mut buf = Vec::new();
+    let changing = if options.deterministic {
+        // Hash the file as it would be written without an ID, then write it
+        // again with one.
+        doc.trailer.remove(b"ID");
+        write_document(doc, &mut buf, options)?;
+        let digest = Md5::digest(&buf).to_vec();
+        buf.clear();
+        digest
+    } else {
+        fresh_id(doc)
+    };
+    let id = |bytes: Vec<u8>| Object::String(bytes, StringFormat::Hexadecimal);
+    let permanent = permanent.unwrap_or_else(|| changing.clone());
+    doc.trailer.set("ID", vec![id(permanent), id(changing)]);
+    write_document(doc, &mut buf, options)?;
+    Ok(buf)
+}
+
+/// The first (permanent) element of a document's trailer `/ID`.
+fn permanent_id(doc: &Document) -> Option<Vec<u8>> {
+    match doc.trailer.get(b"ID").ok()?.as_array().ok()?.first()? {
+        Object::String(bytes, _) if !bytes.is_empty() => Some(bytes.clone()),
+        _ => None,
+    }
+}
+
+/// A new, unique document identifier.
+///
+/// The spec suggests hashing the time, file location, and `/Info` entries;
+/// the time and location aren't available everywhere (WebAssembly), so a
+/// random seed and a counter take their place.
+fn fresh_id(doc: &Document) -> Vec<u8> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = Md5::new();
+    let seed = RandomState::new().hash_one(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.update(seed.to_le_bytes());
+    hasher.update(doc.max_id.to_le_bytes());
+    if let Ok(Object::Reference(info_id)) = doc.trailer.get(b"Info") {
+        if let Ok(info) = doc.get_dictionary(*info_id) {
+            for (key, value) in info.iter() {
+                hasher.update(key);
+                if let Object::String(bytes, _) = value {
+                    hasher.update(bytes);
+                }
+            }
+        }
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Serialize a prepared document, using object streams when compressing.
+fn write_document<W: Write>(
+    doc: &mut Document,
+    target: &mut W,
+    options: &SaveOptions,
+) -> Result<()> {
+    match options.compression {
+        Compression::Compress => doc.save_modern(target)?,
+        Compression::Preserve | Compression::Uncompressed => doc.save_to(target)?,
+    }
+    Ok(())
+}
+
+/// Write a file by writing a temporary sibling and renaming it into place.
+///
+/// Readers never observe a half-written file, and an existing file at `path`
+/// (possibly the input being edited in place) survives if anything fails.
+/// If `path` is a symlink, the file it points to is replaced and the link
+/// kept. A replaced file keeps its permissions.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<()> {
+    // Unique per call: the MCP server may write the same path concurrently.
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    let path = match std::fs::canonicalize(path) {
+        Ok(target) => target,
+        Err(_) => path.to_path_buf(),
+    };
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Not a file path: {}", path.display()))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(file_name);
+    tmp_name.push(format!(
+        ".inpdf-{}-{}.tmp",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = path.with_file_name(tmp_name);
+    let permissions = std::fs::metadata(&path).ok().map(|m| m.permissions());
+
+    let write = || -> std::io::Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        file.write_all(bytes)?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions)?;
+        }
+        // Without this, a crash soon after the rename can leave the new
+        // name pointing at a file whose data never reached the disk.
+        file.sync_all()
+    };
+    let result = write()
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))
+        .and_then(|()| {
+            std::fs::rename(&tmp_path, &path).with_context(|| {
+                format!(
+                    "Failed to rename {} to {}",
+                    tmp_path.display(),
+                    path.display()
+                )
+            })
+        });
+    if result.is_err() {
+        std::fs::remove_file(&tmp_path).ok();
+    }
+    result
+}
+
+/// Linearize serialized PDF bytes.
+///
+/// lopdf cannot write linearized files (they need a specific object order
+/// plus hint streams), so this hands the bytes to `qpdf`, which is widely
+/// packaged and produces spec-conformant hint tables.
+fn linearize(bytes: &[u8], deterministic: bool) -> Result<Vec<u8>> {
+    // Unique per call: the MCP server linearizes concurrently in one process.
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let prefix = std::env::temp_dir().join(format!(
+        "inpdf-linearize-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let input = RemoveOnDrop(prefix.with_extension("in.pdf"));
+    let output = RemoveOnDrop(prefix.with_extension("out.pdf"));
+
+    std::fs::write(&input.0, bytes)
+        .with_context(|| format!("Failed to write {}", input.0.display()))?;
+    let mut command = Command::new("qpdf");
+    command.arg("--linearize");
+    if deterministic {
+        command.arg("--deterministic-id");
+    }
+    let status = command.arg(&input.0).arg(&output.0).status();
+
+    // qpdf exits with 3 when it succeeded but emitted warnings.
+    match status {
+        Ok(status) if status.success() || status.code() == Some(3) => {}
+        Ok(status) => anyhow::bail!("qpdf --linearize failed ({})", status),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!("Linearization requires qpdf to be installed and on PATH")
+        }
+        Err(e) => return Err(e).context("Failed to run qpdf"),
+    }
+
+    std::fs::read(&output.0).with_context(|| format!("Failed to read {}", output.0.display()))
+}
+
+/// A temporary file, removed when this goes out of scope.
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+/// Get the document information dictionary for modification, if there is one.
+pub(crate) fn info_dict_mut(doc: &mut Document) -> Option<&mut Dictionary> {
+    let info_ref = match doc.trailer.get(b"Info") {
+        Ok(Object::Reference(id)) => Some(*id),
+        Ok(Object::Dictionary(_)) => None,
+        _ => return None,
+    };
+
+    match info_ref {
+        Some(id) => doc.get_dictionary_mut(id).ok(),
+        None => match doc.trailer.get_mut(b"Info") {
+            Ok(Object::Dictionary(dict)) => Some(dict),
+            _ => None,
+        },
+    }
+}
+
+/// Format a Unix timestamp as a PDF date string in UTC (`D:YYYYMMDDHHmmSSZ`).
+pub fn format_pdf_date_utc(unix_secs: i64) -> String {
+    let days = unix_secs.div_euclid(86_400);
+    let secs_of_day = unix_secs.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "D:{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Format a Unix timestamp as an ISO 8601 calendar date (`YYYY-MM-DD`) in UTC.
+pub fn format_date_utc(unix_secs: i64) -> String {
+    let (year, month, day) = civil_from_days(unix_secs.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Convert days since the Unix epoch to a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days_from_civil inverse.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// A date as written in PDF metadata: `D:YYYYMMDDHHmmSSOHH'mm'`.
+///
+/// Everything after the year is optional. Missing fields default to the
+/// start of their range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PdfDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// Offset from UTC in minutes, if the date gives one.
+    pub utc_offset: Option<i16>,
+}
+
+impl PdfDate {
+    /// Parse a PDF date. Also accepts the common deviations: no `D:`
+    /// prefix, an offset without (or with only one) apostrophe, and
+    /// trailing garbage after the offset. Returns `None` for strings that
+    /// don't start with a valid date.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let mut rest = text.strip_prefix("D:").unwrap_or(text).as_bytes();
+
+        let year = take_digits(&mut rest, 4)?;
+        let month = take_digits(&mut rest, 2).unwrap_or(1) as u8;
+        let day = take_digits(&mut rest, 2).unwrap_or(1) as u8;
+        let hour = take_digits(&mut rest, 2).unwrap_or(0) as u8;
+        let minute = take_digits(&mut rest, 2).unwrap_or(0) as u8;
+        let second = take_digits(&mut rest, 2).unwrap_or(0) as u8;
+
+        let valid = (1..=12).contains(&month)
+            && (1..=days_in_month(year, month)).contains(&day)
+            && hour < 24
+            && minute < 60
+            && second < 60;
+        if !valid {
+            return None;
+        }
+
+        let utc_offset = match rest.first() {
+            Some(b'Z') => Some(0),
+            Some(&sign @ (b'+' | b'-')) => {
+                rest = &rest[1..];
+                let hours = take_digits(&mut rest, 2)?;
+                if rest.first() == Some(&b'\'') {
+                    rest = &rest[1..];
+                }
+                let minutes = take_digits(&mut rest, 2).unwrap_or(0);
+                if hours > 23 || minutes > 59 {
+                    return None;
+                }
+                let offset = (hours * 60 + minutes) as i16;
+                Some(if sign == b'-' { -offset } else { offset })
+            }
+            _ => None,
+        };
+
+        Some(PdfDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            utc_offset,
+        })
+    }
+
+    /// Format as RFC 3339. A date without an offset gets `-00:00`, RFC
+    /// 3339's marker for an unknown local offset.
+    pub fn to_rfc3339(self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+            self.year,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+            self.offset_suffix()
+        )
+    }
+
+    fn offset_suffix(&self) -> String {
+        match self.utc_offset {
+            Some(0) => "Z".to_string(),
+            Some(offset) => format!(
+                "{}{:02}:{:02}",
+                if offset < 0 { '-' } else { '+' },
+                offset.unsigned_abs() / 60,
+                offset.unsigned_abs() % 60
+            ),
+            None => "-00:00".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for PdfDate {
+    /// `YYYY-MM-DD HH:MM:SS`, followed by the offset when known.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        if self.utc_offset.is_some() {
+            write!(f, " {}", self.offset_suffix())?;
+        }
+        Ok(())
+    }
+}
+
+/// Consume `len` ASCII digits from the front of `rest` as a number.
+fn take_digits(rest: &mut &[u8], len: usize) -> Option<u16> {
+    let (digits, tail) = (*rest).split_at_checked(len)?;
+    if !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *rest = tail;
+    Some(digits.iter().fold(0, |n, d| n * 10 + u16::from(d - b'0')))
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -115,35 +828,368 @@ pub struct PdfInfo {
     pub producer: Option<String>,
     pub creation_date: Option<String>,
     pub mod_date: Option<String>,
+    /// `creation_date`, parsed.
+    pub created: Option<PdfDate>,
+    /// `mod_date`, parsed.
+    pub modified: Option<PdfDate>,
     pub subject: Option<String>,
     pub keywords: Option<String>,
     pub page_count: u32,
 }
 
-fn get_string_from_dict(dict: &lopdf::Dictionary, key: &[u8]) -> Option<String> {
+pub(crate) fn get_string_from_dict(dict: &lopdf::Dictionary, key: &[u8]) -> Option<String> {
     dict.get(key).ok().and_then(|obj| match obj {
-        Object::String(bytes, _) => decode_pdf_string(bytes),
+        Object::String(bytes, _) => Some(decode_text_string(bytes)),
         _ => None,
     })
 }
 
-fn decode_pdf_string(bytes: &[u8]) -> Option<String> {
-    // Check for UTF-16 BOM
-    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
-        // UTF-16 BE
-        let u16_chars: Vec<u16> = bytes[2..]
-            .chunks(2)
-            .filter_map(|chunk| {
-                if chunk.len() == 2 {
-                    Some(u16::from_be_bytes([chunk[0], chunk[1]]))
-                } else {
-                    None
-                }
-            })
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{add_outline, text_document};
+    use lopdf::dictionary;
+
+    fn sample_document() -> Document {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal("Sample"),
+            "ModDate" => Object::string_literal("D:20240101120000Z"),
+        });
+        // An orphan that deterministic mode should drop.
+        doc.add_object(Object::Integer(42));
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        doc.trailer.set(
+            "ID",
+            vec![Object::string_literal("abc"), Object::string_literal("def")],
+        );
+        doc
+    }
+
+    #[test]
+    fn test_format_pdf_date_utc() {
+        assert_eq!(format_pdf_date_utc(0), "D:19700101000000Z");
+        assert_eq!(format_pdf_date_utc(951_782_400), "D:20000229000000Z");
+        assert_eq!(format_pdf_date_utc(1_700_000_000), "D:20231114221320Z");
+        assert_eq!(format_date_utc(951_782_400), "2000-02-29");
+    }
+
+    #[test]
+    fn test_parse_pdf_date() {
+        let date = PdfDate::parse("D:20230514090307+02'00'").unwrap();
+        assert_eq!(date.to_rfc3339(), "2023-05-14T09:03:07+02:00");
+        assert_eq!(date.to_string(), "2023-05-14 09:03:07 +02:00");
+
+        let rfc = |text: &str| PdfDate::parse(text).map(|d| d.to_rfc3339());
+        assert_eq!(rfc("D:20230514"), Some("2023-05-14T00:00:00-00:00".into()));
+        assert_eq!(rfc("D:2023"), Some("2023-01-01T00:00:00-00:00".into()));
+        assert_eq!(
+            rfc("D:19991231235959Z"),
+            Some("1999-12-31T23:59:59Z".into())
+        );
+        assert_eq!(
+            rfc("20230514090307-05'30"),
+            Some("2023-05-14T09:03:07-05:30".into())
+        );
+        assert_eq!(
+            rfc("D:20230514090307+0100"),
+            Some("2023-05-14T09:03:07+01:00".into())
+        );
+        assert_eq!(
+            rfc("D:20230514090307Z00'00'"),
+            Some("2023-05-14T09:03:07Z".into())
+        );
+        assert_eq!(rfc("D:20240229"), Some("2024-02-29T00:00:00-00:00".into()));
+
+        assert_eq!(PdfDate::parse(""), None);
+        assert_eq!(PdfDate::parse("D:20"), None);
+        assert_eq!(PdfDate::parse("D:20231301"), None);
+        assert_eq!(PdfDate::parse("D:20230229"), None);
+        assert_eq!(PdfDate::parse("Tue May 14 2023"), None);
+    }
+
+    #[test]
+    fn test_deterministic_save_is_stable() {
+        let options = SaveOptions {
+            deterministic: true,
+            ..Default::default()
+        };
+        let first = PdfDocument::to_bytes_with(&mut sample_document(), &options).unwrap();
+        let second = PdfDocument::to_bytes_with(&mut sample_document(), &options).unwrap();
+        assert_eq!(first, second);
+
+        // The ID is the hash of the same file written without one.
+        let mut doc = sample_document();
+        prepare_for_save(&mut doc, &options).unwrap();
+        doc.trailer.remove(b"ID");
+        let mut without_id = Vec::new();
+        write_document(&mut doc, &mut without_id, &options).unwrap();
+
+        let reloaded = Document::load_mem(&first).unwrap();
+        let [permanent, changing] = trailer_id(&reloaded);
+        assert_eq!(permanent, changing);
+        assert_eq!(permanent, Md5::digest(&without_id).to_vec());
+        assert!(!reloaded
+            .objects
+            .values()
+            .any(|object| matches!(object, Object::Integer(42))));
+    }
+
+    fn trailer_id(doc: &Document) -> [Vec<u8>; 2] {
+        let id = doc.trailer.get(b"ID").unwrap().as_array().unwrap();
+        [0, 1].map(|i| id[i].as_str().unwrap().to_vec())
+    }
+
+    #[test]
+    fn test_document_id() {
+        let save = |document_id| {
+            let options = SaveOptions {
+                document_id,
+                ..Default::default()
+            };
+            let bytes = PdfDocument::to_bytes_with(&mut sample_document(), &options).unwrap();
+            trailer_id(&Document::load_mem(&bytes).unwrap())
+        };
+
+        let [first, _] = save(DocumentId::Regenerate);
+        let [second, _] = save(DocumentId::Regenerate);
+        assert_ne!(first, b"abc");
+        assert_ne!(first, second);
+
+        let [permanent, changing] = save(DocumentId::Keep);
+        assert_eq!(permanent, b"abc");
+        assert_ne!(changing, b"def");
+
+        // Without an ID to keep, one is made up.
+        let mut doc = sample_document();
+        doc.trailer.remove(b"ID");
+        let options = SaveOptions {
+            document_id: DocumentId::Keep,
+            ..Default::default()
+        };
+        let bytes = PdfDocument::to_bytes_with(&mut doc, &options).unwrap();
+        let [permanent, _] = trailer_id(&Document::load_mem(&bytes).unwrap());
+        assert_eq!(permanent.len(), 16);
+    }
+
+    #[test]
+    fn test_open_bytes() {
+        let (mut source, _) = text_document(&["one", "two"]);
+        let bytes = PdfDocument::to_bytes(&mut source).unwrap();
+        let pdf = PdfDocument::open_bytes(&bytes).unwrap();
+        assert_eq!(pdf.page_count(), 2);
+        assert_eq!(pdf.path, "(memory)");
+        assert!(PdfDocument::open_bytes(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn test_open_reader_reads_outline_and_labels() {
+        let (mut source, page_ids) = text_document(&["one", "two"]);
+        add_outline(&mut source, &page_ids, &[("Intro", 0, 0), ("Body", 1, 0)]);
+        let bytes = PdfDocument::to_bytes(&mut source).unwrap();
+
+        let pdf = PdfDocument::open_reader(std::io::Cursor::new(bytes)).unwrap();
+        let titles: Vec<_> = pdf.toc().unwrap().into_iter().map(|e| e.title).collect();
+        assert_eq!(titles, ["Intro", "Body"]);
+        let labels: Vec<_> = pdf
+            .page_labels()
+            .unwrap()
+            .into_iter()
+            .map(|l| l.logical_label)
             .collect();
-        String::from_utf16(&u16_chars).ok()
-    } else {
-        // Try as Latin-1 / PDFDocEncoding (simplified)
-        Some(bytes.iter().map(|&b| b as char).collect())
+        assert_eq!(labels, ["1", "2"]);
+    }
+
+    #[test]
+    fn test_extract_pages_materializes_inherited_attributes() {
+        let (mut source, page_ids) = text_document(&["one", "two"]);
+        let pages_id = source
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        source
+            .get_dictionary_mut(pages_id)
+            .unwrap()
+            .set("Rotate", 90);
+        let pdf = PdfDocument::from_cached(CachedPdf::from_document(source), "test.pdf");
+
+        let extracted = pdf.extract_pages(&[2]).unwrap();
+        let page = extracted.get_dictionary(page_ids[1]).unwrap();
+        assert!(page.has(b"Resources"));
+        assert!(page.has(b"MediaBox"));
+        assert_eq!(page.get(b"Rotate").unwrap().as_i64().unwrap(), 90);
+    }
+
+    #[test]
+    fn test_arrange_pages_reorders_and_duplicates() {
+        let (source, page_ids) = text_document(&["one", "two", "three"]);
+        let pdf = PdfDocument::from_cached(CachedPdf::from_document(source), "test.pdf");
+
+        let arranged = pdf.arrange_pages(&[3, 1, 1]).unwrap();
+        let pages: Vec<ObjectId> = arranged.get_pages().into_values().collect();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0], page_ids[2]);
+        assert_eq!(pages[1], page_ids[0]);
+        assert_ne!(pages[2], page_ids[0], "repeat should be a new page object");
+
+        // The repeat shares content and carries the inherited resources.
+        let first = arranged.get_dictionary(pages[1]).unwrap();
+        let repeat = arranged.get_dictionary(pages[2]).unwrap();
+        assert_eq!(
+            first.get(b"Contents").unwrap(),
+            repeat.get(b"Contents").unwrap()
+        );
+        assert!(repeat.has(b"Resources"));
+        assert!(repeat.has(b"MediaBox"));
+
+        // Page two was dropped entirely.
+        assert!(arranged.get_object(page_ids[1]).is_err());
+    }
+
+    #[test]
+    fn test_arrange_pages_copies_annotations() {
+        let (mut source, page_ids) = text_document(&[""]);
+        let page_id = page_ids[0];
+        // A check box with one widget.
+        let field = source.new_object_id();
+        let widget = source.add_object(dictionary! {
+            "Parent" => field,
+            "Subtype" => "Widget",
+            "Rect" => vec![0.into(), 0.into(), 100.into(), 20.into()],
+        });
+        source.objects.insert(
+            field,
+            Object::Dictionary(dictionary! {
+                "T" => Object::string_literal("agree"),
+                "FT" => "Btn",
+                "Kids" => vec![Object::Reference(widget)],
+            }),
+        );
+        let note = source.add_object(dictionary! {
+            "Subtype" => "Text",
+            "P" => page_id,
+        });
+        let popup = source.add_object(dictionary! {
+            "Subtype" => "Popup",
+            "Parent" => note,
+            "P" => page_id,
+        });
+        source.get_dictionary_mut(note).unwrap().set("Popup", popup);
+        source
+            .get_dictionary_mut(page_id)
+            .unwrap()
+            .set("Annots", vec![note.into(), popup.into(), widget.into()]);
+        let pdf = PdfDocument::from_cached(CachedPdf::from_document(source), "test.pdf");
+
+        let arranged = pdf.arrange_pages(&[1, 1]).unwrap();
+        let pages: Vec<ObjectId> = arranged.get_pages().into_values().collect();
+        let annots = |page| -> Vec<ObjectId> {
+            let page = arranged.get_dictionary(page).unwrap();
+            let annots = page.get(b"Annots").unwrap().as_array().unwrap();
+            annots.iter().map(|a| a.as_reference().unwrap()).collect()
+        };
+        assert_eq!(annots(pages[0]), vec![note, popup, widget]);
+        let copies = annots(pages[1]);
+        assert!(copies.iter().all(|id| ![note, popup, widget].contains(id)));
+
+        let reference = |id, key: &[u8]| {
+            let dict = arranged.get_dictionary(id).unwrap();
+            dict.get(key).unwrap().as_reference().unwrap()
+        };
+        assert_eq!(reference(copies[0], b"P"), pages[1]);
+        assert_eq!(reference(copies[0], b"Popup"), copies[1]);
+        assert_eq!(reference(copies[1], b"Parent"), copies[0]);
+        assert_eq!(reference(copies[2], b"Parent"), field);
+
+        // The copied widget belongs to the same check box.
+        let kids = arranged
+            .get_dictionary(field)
+            .unwrap()
+            .get(b"Kids")
+            .unwrap();
+        assert_eq!(
+            kids.as_array().unwrap(),
+            &vec![Object::Reference(widget), Object::Reference(copies[2])]
+        );
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_file() {
+        let dir = std::env::temp_dir().join("inpdf_document_test");
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        let path = dir.join("atomic.txt");
+
+        std::fs::write(&path, b"old").expect("write original");
+        write_atomic(&path, b"new").expect("atomic write");
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+
+        // No temporary files should be left behind.
+        let leftovers = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .count();
+        assert_eq!(leftovers, 0);
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("inpdf-atomic-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let target = dir.join("target.pdf");
+        let link = dir.join("link.pdf");
+        std::fs::write(&target, b"old").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"new").unwrap();
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read(&target).unwrap(), b"new");
+        let mode = std::fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_compressed_save_round_trips() {
+        let options = SaveOptions {
+            compression: Compression::Compress,
+            ..Default::default()
+        };
+        let bytes = PdfDocument::to_bytes_with(&mut sample_document(), &options).unwrap();
+        assert!(bytes.starts_with(b"%PDF-1.5"));
+
+        let reloaded = Document::load_mem(&bytes).unwrap();
+        assert_eq!(reloaded.get_pages().len(), 1);
     }
 }
@@ -0,0 +1,499 @@
+//! Embedding fonts a document only names.
+//!
+//! Non-embedded simple fonts (Type1 and TrueType) are matched by name
+//! against TrueType/OpenType files in a directory, with metric-compatible
+//! substitutes (Liberation, Arial, Arimo, Nimbus, ...) for the standard 14
+//! fonts. The replacement is subset to the characters the pages show with
+//! fonttools' `pyftsubset`, which is widely packaged, and embedded with a
+//! descriptor built from its metrics. Existing `Widths` are kept, so the
+//! text doesn't move.
+//!
+//! Composite (`Type0`) fonts and symbolic fonts (Symbol, ZapfDingbats) are
+//! reported but not embedded: their codes don't map to characters a
+//! replacement could be looked up by.
+
+use crate::pdf::glyphs::page_glyphs;
+use crate::pdf::pages::{find_inherited, resolve};
+use crate::pdf::svg::Font;
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use walkdir::WalkDir;
+
+/// Substitute families for the standard 14 fonts, by normalized name.
+const SANS: &[&str] = &["liberationsans", "arial", "arimo", "nimbussans"];
+const SERIF: &[&str] = &["liberationserif", "timesnewroman", "tinos", "nimbusroman"];
+const MONO: &[&str] = &["liberationmono", "couriernew", "cousine", "nimbusmonops"];
+
+/// Font descriptor flag for fonts using the standard Latin character set.
+const NONSYMBOLIC: i64 = 1 << 5;
+const FIXED_PITCH: i64 = 1;
+const ITALIC: i64 = 1 << 6;
+const SYMBOLIC: i64 = 1 << 2;
+
+/// A font the document uses but doesn't embed.
+#[derive(Debug)]
+pub struct MissingFont {
+    pub id: ObjectId,
+    /// `BaseFont`, e.g. "Helvetica-Bold".
+    pub base_font: String,
+    /// Characters shown in it on the pages; every character its encoding
+    /// has if it's only used elsewhere (form XObjects, annotations).
+    pub chars: BTreeSet<char>,
+    /// Why it can't be embedded, if it can't.
+    pub unsupported: Option<&'static str>,
+}
+
+/// The TrueType and OpenType font files in a directory (and below), by
+/// normalized name.
+pub struct FontDir {
+    by_name: HashMap<String, PathBuf>,
+}
+
+impl FontDir {
+    /// Index the `.ttf` and `.otf` files under `dir`. Files that don't parse
+    /// are skipped.
+    pub fn scan(dir: &Path) -> Result<FontDir> {
+        if !dir.is_dir() {
+            anyhow::bail!("Font directory not found: {}", dir.display());
+        }
+        let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| {
+                        ext.eq_ignore_ascii_case("ttf") || ext.eq_ignore_ascii_case("otf")
+                    })
+            })
+            .collect();
+        paths.sort();
+
+        let mut by_name = HashMap::new();
+        for path in paths {
+            let Ok(data) = std::fs::read(&path) else {
+                continue;
+            };
+            let Ok(face) = ttf_parser::Face::parse(&data, 0) else {
+                continue;
+            };
+            let Some(postscript_name) = face_name(&face, ttf_parser::name_id::POST_SCRIPT_NAME)
+            else {
+                continue;
+            };
+            let mut keys = vec![normalize(&postscript_name)];
+            if let Some(family) = face_name(&face, ttf_parser::name_id::FAMILY) {
+                let style = face_name(&face, ttf_parser::name_id::SUBFAMILY).unwrap_or_default();
+                keys.push(normalize(&format!("{}{}", family, style)));
+                if normalize(&style) == "regular" {
+                    keys.push(normalize(&family));
+                }
+            }
+            for key in keys {
+                // "ArialMT" is also known as "Arial".
+                if let Some(short) = key.strip_suffix("psmt").or_else(|| key.strip_suffix("mt")) {
+                    by_name
+                        .entry(short.to_string())
+                        .or_insert_with(|| path.clone());
+                }
+                by_name.entry(key).or_insert_with(|| path.clone());
+            }
+        }
+        Ok(FontDir { by_name })
+    }
+
+    /// The file for a `BaseFont`: the same font, or a substitute for a
+    /// standard one.
+    pub fn find(&self, base_font: &str) -> Option<&Path> {
+        let name = normalize(strip_subset_tag(base_font));
+        std::iter::once(name.clone())
+            .chain(substitutes(&name))
+            .find_map(|key| self.by_name.get(&key).map(PathBuf::as_path))
+    }
+}
+
+/// Lowercase letters and digits only, so "Arial,Bold", "Arial-Bold", and
+/// "Arial Bold" agree.
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn strip_subset_tag(name: &str) -> &str {
+    match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 => rest,
+        _ => name,
+    }
+}
+
+/// Names of metric-compatible replacements for a standard font.
+fn substitutes(name: &str) -> Vec<String> {
+    let (families, style) = match name {
+        "helvetica" => (SANS, ""),
+        "helveticabold" => (SANS, "bold"),
+        "helveticaoblique" => (SANS, "italic"),
+        "helveticaboldoblique" => (SANS, "bolditalic"),
+        "timesroman" => (SERIF, ""),
+        "timesbold" => (SERIF, "bold"),
+        "timesitalic" => (SERIF, "italic"),
+        "timesbolditalic" => (SERIF, "bolditalic"),
+        "courier" => (MONO, ""),
+        "courierbold" => (MONO, "bold"),
+        "courieroblique" => (MONO, "italic"),
+        "courierboldoblique" => (MONO, "bolditalic"),
+        _ => return Vec::new(),
+    };
+    families
+        .iter()
+        .map(|family| format!("{}{}", family, style))
+        .collect()
+}
+
+fn face_name(face: &ttf_parser::Face, id: u16) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == id)
+        .find_map(|name| name.to_string())
+}
+
+/// Fonts used by the document that aren't embedded, in object order.
+pub fn missing_fonts(doc: &Document) -> Vec<MissingFont> {
+    let mut shown: HashMap<ObjectId, BTreeSet<char>> = HashMap::new();
+    for page_id in doc.get_pages().into_values() {
+        let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+            continue;
+        };
+        let ids = page_font_ids(doc, page_id);
+        for glyph in page_glyphs(doc, page_id, &content.operations) {
+            if let Some(&id) = ids.get(&glyph.font) {
+                shown.entry(id).or_default().extend(glyph.text.chars());
+            }
+        }
+    }
+
+    let mut missing = Vec::new();
+    for (&id, object) in &doc.objects {
+        let Ok(dict) = object.as_dict() else {
+            continue;
+        };
+        if !matches!(dict.get(b"Type"), Ok(Object::Name(n)) if n == b"Font") {
+            continue;
+        }
+        let subtype: &[u8] = match dict.get(b"Subtype") {
+            Ok(Object::Name(name)) => name.as_slice(),
+            _ => b"",
+        };
+        let base_font = match dict.get(b"BaseFont").map(|obj| resolve(doc, obj)) {
+            Ok(Object::Name(name)) => String::from_utf8_lossy(name).into_owned(),
+            _ => String::new(),
+        };
+        let unsupported = match subtype {
+            // Type3 glyphs are drawn by the PDF itself.
+            b"Type3" => continue,
+            b"Type0" => {
+                if descendant(doc, dict).is_none_or(|font| is_embedded(doc, font)) {
+                    continue;
+                }
+                Some("composite font")
+            }
+            _ => {
+                if is_embedded(doc, dict) {
+                    continue;
+                }
+                let name = normalize(strip_subset_tag(&base_font));
+                if name == "symbol" || name == "zapfdingbats" || is_symbolic(doc, dict) {
+                    Some("symbolic font")
+                } else {
+                    None
+                }
+            }
+        };
+
+        let chars = match shown.remove(&id) {
+            Some(chars) => chars,
+            None => {
+                let font = Font::load(doc, dict);
+                (32..=255u8)
+                    .filter_map(|code| {
+                        let encoding = font.encoding.as_ref()?;
+                        Document::decode_text(encoding, &[code]).ok()
+                    })
+                    .flat_map(|text| text.chars().collect::<Vec<_>>())
+                    .collect()
+            }
+        };
+        missing.push(MissingFont {
+            id,
+            base_font,
+            chars,
+            unsupported,
+        });
+    }
+    missing
+}
+
+/// Font resource names on a page, mapped to the font objects they refer to.
+fn page_font_ids(doc: &Document, page_id: ObjectId) -> HashMap<Vec<u8>, ObjectId> {
+    let fonts = find_inherited(doc, page_id, b"Resources")
+        .and_then(|obj| resolve(doc, obj).as_dict().ok())
+        .and_then(|resources| resources.get(b"Font").ok())
+        .and_then(|obj| resolve(doc, obj).as_dict().ok());
+    let Some(fonts) = fonts else {
+        return HashMap::new();
+    };
+    fonts
+        .iter()
+        .filter_map(|(name, obj)| match obj {
+            Object::Reference(id) => Some((name.clone(), *id)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn descendant<'a>(doc: &'a Document, dict: &'a Dictionary) -> Option<&'a Dictionary> {
+    let fonts = resolve(doc, dict.get(b"DescendantFonts").ok()?)
+        .as_array()
+        .ok()?;
+    resolve(doc, fonts.first()?).as_dict().ok()
+}
+
+fn descriptor<'a>(doc: &'a Document, dict: &'a Dictionary) -> Option<&'a Dictionary> {
+    resolve(doc, dict.get(b"FontDescriptor").ok()?)
+        .as_dict()
+        .ok()
+}
+
+fn is_embedded(doc: &Document, dict: &Dictionary) -> bool {
+    descriptor(doc, dict).is_some_and(|descriptor| {
+        [&b"FontFile"[..], b"FontFile2", b"FontFile3"]
+            .iter()
+            .any(|key| descriptor.has(key))
+    })
+}
+
+fn is_symbolic(doc: &Document, dict: &Dictionary) -> bool {
+    descriptor(doc, dict)
+        .and_then(|descriptor| descriptor.get(b"Flags").ok())
+        .and_then(|flags| resolve(doc, flags).as_i64().ok())
+        .is_some_and(|flags| flags & SYMBOLIC != 0)
+}
+
+/// Subset a font file to `chars` with `pyftsubset`.
+pub fn subset_font(path: &Path, chars: &BTreeSet<char>) -> Result<Vec<u8>> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let output = std::env::temp_dir().join(format!(
+        "inpdf-subset-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let unicodes: Vec<String> = chars
+        .iter()
+        .map(|&c| format!("U+{:04X}", c as u32))
+        .collect();
+
+    let status = Command::new("pyftsubset")
+        .arg(path)
+        .arg(format!("--unicodes={}", unicodes.join(",")))
+        .arg(format!("--output-file={}", output.display()))
+        // PDF shows glyphs by code, so layout tables aren't needed.
+        .arg("--layout-features=")
+        .arg("--notdef-outline")
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => anyhow::bail!("pyftsubset failed on {} ({})", path.display(), status),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!(
+                "Subsetting fonts requires pyftsubset (fonttools) to be installed and on PATH; \
+                 pass --no-subset to embed whole fonts"
+            )
+        }
+        Err(e) => return Err(e).context("Failed to run pyftsubset"),
+    }
+
+    let bytes =
+        std::fs::read(&output).with_context(|| format!("Failed to read {}", output.display()))?;
+    std::fs::remove_file(&output).ok();
+    Ok(bytes)
+}
+
+/// Embed `program` (a TrueType or OpenType font) as a missing font,
+/// returning its new `BaseFont`. `subset` marks the name with a subset tag,
+/// as readers expect for partial fonts.
+pub fn embed_font(
+    doc: &mut Document,
+    font: &MissingFont,
+    program: Vec<u8>,
+    subset: bool,
+) -> Result<String> {
+    let face = ttf_parser::Face::parse(&program, 0)
+        .map_err(|e| anyhow::anyhow!("Failed to parse font program: {}", e))?;
+    let postscript_name = face_name(&face, ttf_parser::name_id::POST_SCRIPT_NAME)
+        .unwrap_or_else(|| strip_subset_tag(&font.base_font).to_string());
+    let base_font = if subset {
+        format!(
+            "{}+{}",
+            subset_tag(&postscript_name, &font.chars),
+            postscript_name
+        )
+    } else {
+        postscript_name
+    };
+    let scale = 1000.0 / face.units_per_em() as f32;
+    let scaled = |value: f32| Object::Integer((value * scale).round() as i64);
+    // OpenType fonts with CFF outlines start with "OTTO".
+    let cff = program.starts_with(b"OTTO");
+
+    let dict = doc
+        .get_dictionary(font.id)
+        .context("Font object is not a dictionary")?;
+    let encoding = (!dict.has(b"Encoding")).then(|| {
+        // No encoding means the font's built-in one, StandardEncoding for
+        // Latin fonts; in ASCII it differs from WinAnsi only in quotes.
+        dictionary! {
+            "Type" => "Encoding",
+            "BaseEncoding" => "WinAnsiEncoding",
+            "Differences" => vec![
+                Object::Integer(39),
+                Object::Name(b"quoteright".to_vec()),
+                Object::Integer(96),
+                Object::Name(b"quoteleft".to_vec()),
+            ],
+        }
+    });
+    let widths = (!dict.has(b"Widths")).then(|| {
+        let decoder = Font::load(doc, dict);
+        (32..=255u8)
+            .map(|code| {
+                decoder
+                    .encoding
+                    .as_ref()
+                    .and_then(|encoding| Document::decode_text(encoding, &[code]).ok())
+                    .and_then(|text| text.chars().next())
+                    .and_then(|c| face.glyph_index(c))
+                    .and_then(|glyph| face.glyph_hor_advance(glyph))
+                    .map_or(Object::Integer(0), |width| scaled(width.into()))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut flags = NONSYMBOLIC;
+    if face.is_monospaced() {
+        flags |= FIXED_PITCH;
+    }
+    if face.is_italic() {
+        flags |= ITALIC;
+    }
+    let bbox = face.global_bounding_box();
+    let weight = face.weight().to_number() as i64;
+    let mut descriptor = dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => Object::Name(base_font.clone().into_bytes()),
+        "Flags" => flags,
+        "FontBBox" => [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max].map(|v| scaled(v.into())).to_vec(),
+        "ItalicAngle" => if face.is_italic() { -12i64 } else { 0 },
+        "Ascent" => scaled(face.ascender().into()),
+        "Descent" => scaled(face.descender().into()),
+        "CapHeight" => scaled(face.capital_height().unwrap_or(face.ascender()).into()),
+        // Readers only use StemV as a hint; estimate it from the weight.
+        "StemV" => 10 + 220 * (weight - 50).max(0) / 900,
+    };
+    let mut file = if cff {
+        Stream::new(dictionary! { "Subtype" => "OpenType" }, program)
+    } else {
+        let length = program.len() as i64;
+        Stream::new(dictionary! { "Length1" => length }, program)
+    };
+    // Compression is an optimization; keep the raw stream if it fails.
+    let _ = file.compress();
+    let file_id = doc.add_object(file);
+    descriptor.set(
+        if cff { "FontFile3" } else { "FontFile2" },
+        Object::Reference(file_id),
+    );
+    let descriptor_id = doc.add_object(descriptor);
+
+    let dict = doc
+        .get_dictionary_mut(font.id)
+        .context("Font object is not a dictionary")?;
+    dict.set("Subtype", if cff { "Type1" } else { "TrueType" });
+    dict.set("BaseFont", Object::Name(base_font.clone().into_bytes()));
+    dict.set("FontDescriptor", Object::Reference(descriptor_id));
+    if let Some(widths) = widths {
+        dict.set("FirstChar", Object::Integer(32));
+        dict.set("LastChar", Object::Integer(255));
+        dict.set("Widths", widths);
+    }
+    if let Some(encoding) = encoding {
+        dict.set("Encoding", encoding);
+    }
+    // Embedded OpenType fonts need PDF 1.6.
+    if cff && doc.version.as_str() < "1.6" {
+        doc.version = "1.6".to_string();
+    }
+    Ok(base_font)
+}
+
+/// A six-letter subset tag, the same for the same font and characters.
+fn subset_tag(name: &str, chars: &BTreeSet<char>) -> String {
+    // FNV-1a.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name
+        .bytes()
+        .chain(chars.iter().flat_map(|c| (*c as u32).to_le_bytes()))
+    {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 8)) % 26) as u8) as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            normalize(strip_subset_tag("ABCDEF+Arial,Bold")),
+            "arialbold"
+        );
+        assert_eq!(normalize("Times-Roman"), "timesroman");
+        assert_eq!(
+            substitutes("helveticaboldoblique")[0],
+            "liberationsansbolditalic"
+        );
+        assert!(substitutes("futura").is_empty());
+
+        let tag = subset_tag("LiberationSans", &BTreeSet::from(['a', 'b']));
+        assert_eq!(tag.len(), 6);
+        assert!(tag.chars().all(|c| c.is_ascii_uppercase()));
+        assert_eq!(
+            tag,
+            subset_tag("LiberationSans", &BTreeSet::from(['a', 'b']))
+        );
+        assert_ne!(tag, subset_tag("LiberationSans", &BTreeSet::from(['a'])));
+    }
+
+    #[test]
+    fn test_missing_fonts() {
+        let (doc, _) = text_document(&["Hello", "World"]);
+        let missing = missing_fonts(&doc);
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].base_font, "Helvetica");
+        assert_eq!(missing[0].unsupported, None);
+        assert_eq!(missing[0].chars.iter().collect::<String>(), "HWdelor");
+    }
+}
@@ -0,0 +1,752 @@
+//! Form data exchange files: FDF and XFDF.
+//!
+//! Both carry field values keyed by name, nesting fields by the components
+//! of their fully qualified names. FDF uses PDF object syntax; XFDF is its
+//! XML equivalent. Only field values are read and written; annotations and
+//! other FDF features are ignored.
+
+use crate::pdf::forms::FieldValue;
+use crate::pdf::strings::decode_text_string;
+use anyhow::{Context, Result};
+use lopdf::{Object, ObjectId, StringFormat};
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Deepest `Kids` nesting read from an FDF file. Its references are resolved
+/// without tracking visits, so this also ends a field that contains itself.
+const MAX_DEPTH: usize = 32;
+
+/// A field in the nested form used by both formats.
+struct Node<'a> {
+    name: &'a str,
+    value: Option<&'a FieldValue>,
+    children: Vec<Node<'a>>,
+}
+
+/// Nest fields by the period-separated parts of their names, keeping order.
+fn nest<'a>(fields: &'a [(String, Option<FieldValue>)]) -> Vec<Node<'a>> {
+    let mut roots: Vec<Node> = Vec::new();
+    for (name, value) in fields {
+        let mut level = &mut roots;
+        let mut parts = name.split('.').peekable();
+        while let Some(part) = parts.next() {
+            let index = match level.iter().position(|node| node.name == part) {
+                Some(index) => index,
+                None => {
+                    level.push(Node {
+                        name: part,
+                        value: None,
+                        children: Vec::new(),
+                    });
+                    level.len() - 1
+                }
+            };
+            if parts.peek().is_none() {
+                level[index].value = value.as_ref();
+            }
+            level = &mut level[index].children;
+        }
+    }
+    roots
+}
+
+/// Write field values as XFDF. `href` names the PDF the data belongs to.
+pub fn write_xfdf(fields: &[(String, Option<FieldValue>)], href: Option<&str>) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<xfdf xmlns=\"http://ns.adobe.com/xfdf/\" xml:space=\"preserve\">\n");
+    if let Some(href) = href {
+        let _ = writeln!(out, "  <f href=\"{}\"/>", escape(href));
+    }
+    out.push_str("  <fields>\n");
+    for node in nest(fields) {
+        write_xfdf_node(&mut out, &node, 2);
+    }
+    out.push_str("  </fields>\n");
+    out.push_str("</xfdf>\n");
+    out
+}
+
+fn write_xfdf_node(out: &mut String, node: &Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let values: Vec<&str> = match node.value {
+        Some(FieldValue::Text(text) | FieldValue::Name(text)) => vec![text.as_str()],
+        Some(FieldValue::List(items)) => items.iter().map(String::as_str).collect(),
+        None => Vec::new(),
+    };
+    if values.is_empty() && node.children.is_empty() {
+        let _ = writeln!(out, "{}<field name=\"{}\"/>", indent, escape(node.name));
+        return;
+    }
+    let _ = writeln!(out, "{}<field name=\"{}\">", indent, escape(node.name));
+    for value in values {
+        let _ = writeln!(out, "{}  <value>{}</value>", indent, escape(value));
+    }
+    for child in &node.children {
+        write_xfdf_node(out, child, depth + 1);
+    }
+    let _ = writeln!(out, "{}</field>", indent);
+}
+
+/// Read field values from XFDF. Fields with several `<value>` elements
+/// (multi-select lists) become [`FieldValue::List`].
+pub fn parse_xfdf(xml: &str) -> Result<Vec<(String, FieldValue)>> {
+    let mut reader = Reader::from_str(xml);
+    let mut fields = Vec::new();
+    // Open `<field>` elements: qualified name and values seen so far.
+    let mut stack: Vec<(String, Vec<String>)> = Vec::new();
+    let mut value: Option<String> = None;
+
+    loop {
+        match reader.read_event().context("parse XFDF")? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"field" => {
+                    let name = field_name(&e)?;
+                    let name = match stack.last() {
+                        Some((parent, _)) => format!("{}.{}", parent, name),
+                        None => name,
+                    };
+                    stack.push((name, Vec::new()));
+                }
+                b"value" if !stack.is_empty() => value = Some(String::new()),
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"value" => {
+                if let Some((_, values)) = stack.last_mut() {
+                    values.push(String::new());
+                }
+            }
+            Event::Text(text) => {
+                if let Some(value) = value.as_mut() {
+                    value.push_str(&text.unescape().context("parse XFDF")?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(value) = value.as_mut() {
+                    value.push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"value" => {
+                    if let (Some(text), Some((_, values))) = (value.take(), stack.last_mut()) {
+                        values.push(text);
+                    }
+                }
+                b"field" => {
+                    if let Some((name, mut values)) = stack.pop() {
+                        match values.len() {
+                            0 => {}
+                            1 => fields.push((name, FieldValue::Text(values.remove(0)))),
+                            _ => fields.push((name, FieldValue::List(values))),
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(fields)
+}
+
+fn field_name(element: &quick_xml::events::BytesStart) -> Result<String> {
+    let name = element
+        .try_get_attribute("name")
+        .context("parse XFDF")?
+        .context("XFDF <field> element without a name")?;
+    Ok(name.unescape_value().context("parse XFDF")?.into_owned())
+}
+
+/// Write field values as FDF.
+pub fn write_fdf(fields: &[(String, Option<FieldValue>)], file: Option<&str>) -> Vec<u8> {
+    let mut body = String::new();
+    body.push_str("<< /FDF << ");
+    if let Some(file) = file {
+        body.push_str("/F ");
+        body.push_str(&pdf_string(file));
+        body.push(' ');
+    }
+    body.push_str("/Fields [");
+    for node in nest(fields) {
+        write_fdf_node(&mut body, &node);
+    }
+    body.push_str("] >> >>");
+
+    let mut out = Vec::new();
+    out.extend_from_slice(b"%FDF-1.2\n%\xE2\xE3\xCF\xD3\n");
+    out.extend_from_slice(format!("1 0 obj\n{}\nendobj\n", body).as_bytes());
+    out.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+    out
+}
+
+fn write_fdf_node(out: &mut String, node: &Node) {
+    out.push_str("<< /T ");
+    out.push_str(&pdf_string(node.name));
+    match node.value {
+        Some(FieldValue::Text(text)) => {
+            out.push_str(" /V ");
+            out.push_str(&pdf_string(text));
+        }
+        Some(FieldValue::Name(name)) => {
+            out.push_str(" /V ");
+            out.push_str(&pdf_name(name));
+        }
+        Some(FieldValue::List(items)) => {
+            out.push_str(" /V [");
+            let items: Vec<String> = items.iter().map(|item| pdf_string(item)).collect();
+            out.push_str(&items.join(" "));
+            out.push(']');
+        }
+        None => {}
+    }
+    if !node.children.is_empty() {
+        out.push_str(" /Kids [");
+        for child in &node.children {
+            write_fdf_node(out, child);
+        }
+        out.push(']');
+    }
+    out.push_str(" >>");
+}
+
+/// Format a text string: literal if printable ASCII, else UTF-16BE hex.
+fn pdf_string(text: &str) -> String {
+    if text.bytes().all(|b| (0x20..0x7F).contains(&b)) {
+        let mut out = String::from("(");
+        for c in text.chars() {
+            if matches!(c, '(' | ')' | '\\') {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out.push(')');
+        out
+    } else {
+        let mut out = String::from("<FEFF");
+        for unit in text.encode_utf16() {
+            let _ = write!(out, "{:04X}", unit);
+        }
+        out.push('>');
+        out
+    }
+}
+
+fn pdf_name(name: &str) -> String {
+    let mut out = String::from("/");
+    for b in name.bytes() {
+        if b.is_ascii_graphic() && !b"()<>[]{}/%#".contains(&b) {
+            out.push(b as char);
+        } else {
+            let _ = write!(out, "#{:02X}", b);
+        }
+    }
+    out
+}
+
+/// Read field values from FDF.
+pub fn parse_fdf(bytes: &[u8]) -> Result<Vec<(String, FieldValue)>> {
+    let mut parser = Parser { bytes, pos: 0 };
+    let mut objects: HashMap<ObjectId, Object> = HashMap::new();
+    let mut trailer: Option<Object> = None;
+
+    loop {
+        parser.skip_whitespace();
+        if parser.pos >= bytes.len() {
+            break;
+        }
+        if parser.eat_keyword(b"trailer") {
+            trailer = Some(parser.object()?);
+        } else if parser.eat_keyword(b"xref") {
+            // Objects are found by scanning, so the table's offsets aren't
+            // needed; it runs up to the trailer.
+            parser.skip_until(b"trailer");
+        } else if parser.eat_keyword(b"startxref") {
+            parser.integer()?;
+        } else if parser.peek().is_some_and(|b| b.is_ascii_digit()) {
+            let number = parser.integer()?;
+            let generation = parser.integer()?;
+            parser.skip_whitespace();
+            if !parser.eat_keyword(b"obj") {
+                anyhow::bail!("FDF: expected 'obj' at offset {}", parser.pos);
+            }
+            let object = parser.object()?;
+            objects.insert((number as u32, generation as u16), object);
+            parser.skip_whitespace();
+            if parser.eat_keyword(b"stream") {
+                parser.skip_past(b"endstream");
+            }
+            parser.skip_whitespace();
+            parser.eat_keyword(b"endobj");
+        } else {
+            parser.skip_token();
+        }
+    }
+
+    let resolve = |obj: &'_ Object| -> Object {
+        match obj {
+            Object::Reference(id) => objects.get(id).cloned().unwrap_or(Object::Null),
+            other => other.clone(),
+        }
+    };
+    let trailer = trailer.context("FDF file has no trailer")?;
+    let root = trailer
+        .as_dict()
+        .ok()
+        .and_then(|t| t.get(b"Root").ok())
+        .map(&resolve)
+        .context("FDF trailer has no /Root")?;
+    let fdf = root
+        .as_dict()
+        .ok()
+        .and_then(|root| root.get(b"FDF").ok())
+        .map(&resolve)
+        .context("FDF catalog has no /FDF dictionary")?;
+    let list = fdf
+        .as_dict()
+        .ok()
+        .and_then(|fdf| fdf.get(b"Fields").ok())
+        .map(&resolve)
+        .unwrap_or(Object::Array(Vec::new()));
+
+    let mut fields = Vec::new();
+    for field in list.as_array().map(Vec::as_slice).unwrap_or_default() {
+        collect_fdf_field(&resolve, &resolve(field), "", 0, &mut fields);
+    }
+    Ok(fields)
+}
+
+fn collect_fdf_field(
+    resolve: &dyn Fn(&Object) -> Object,
+    field: &Object,
+    parent: &str,
+    depth: usize,
+    out: &mut Vec<(String, FieldValue)>,
+) {
+    let Ok(dict) = field.as_dict() else {
+        return;
+    };
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let partial = match dict.get(b"T").map(resolve) {
+        Ok(Object::String(bytes, _)) => decode_text_string(&bytes),
+        _ => String::new(),
+    };
+    let name = match (parent.is_empty(), partial.is_empty()) {
+        (true, _) => partial,
+        (false, true) => parent.to_string(),
+        (false, false) => format!("{}.{}", parent, partial),
+    };
+
+    let value = dict
+        .get(b"V")
+        .map(resolve)
+        .ok()
+        .and_then(|value| match value {
+            Object::String(bytes, _) => Some(FieldValue::Text(decode_text_string(&bytes))),
+            Object::Name(name) => Some(FieldValue::Name(
+                String::from_utf8_lossy(&name).into_owned(),
+            )),
+            Object::Array(items) => Some(FieldValue::List(
+                items
+                    .iter()
+                    .filter_map(|item| match resolve(item) {
+                        Object::String(bytes, _) => Some(decode_text_string(&bytes)),
+                        _ => None,
+                    })
+                    .collect(),
+            )),
+            _ => None,
+        });
+    if let Some(value) = value {
+        out.push((name.clone(), value));
+    }
+
+    if let Ok(Object::Array(kids)) = dict.get(b"Kids").map(resolve) {
+        for kid in &kids {
+            collect_fdf_field(resolve, &resolve(kid), &name, depth + 1, out);
+        }
+    }
+}
+
+/// A minimal reader for the PDF object syntax used in FDF files.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b) = self.peek() {
+            if b == b'%' {
+                while self.peek().is_some_and(|b| b != b'\n' && b != b'\r') {
+                    self.pos += 1;
+                }
+            } else if is_whitespace(b) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn skip_token(&mut self) {
+        self.pos += 1;
+        while self
+            .peek()
+            .is_some_and(|b| !is_whitespace(b) && !is_delimiter(b))
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_past(&mut self, marker: &[u8]) {
+        self.skip_until(marker);
+        self.pos = (self.pos + marker.len()).min(self.bytes.len());
+    }
+
+    /// Move to the next occurrence of `marker`, or to the end.
+    fn skip_until(&mut self, marker: &[u8]) {
+        match self.bytes[self.pos..]
+            .windows(marker.len())
+            .position(|window| window == marker)
+        {
+            Some(offset) => self.pos += offset,
+            None => self.pos = self.bytes.len(),
+        }
+    }
+
+    /// Consume `keyword` if it is the next token.
+    fn eat_keyword(&mut self, keyword: &[u8]) -> bool {
+        let end = self.pos + keyword.len();
+        let matches = self.bytes.get(self.pos..end) == Some(keyword)
+            && self
+                .bytes
+                .get(end)
+                .is_none_or(|&b| is_whitespace(b) || is_delimiter(b));
+        if matches {
+            self.pos = end;
+        }
+        matches
+    }
+
+    fn integer(&mut self) -> Result<i64> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos])?
+            .parse()
+            .with_context(|| format!("FDF: expected an integer at offset {}", start))
+    }
+
+    fn object(&mut self) -> Result<Object> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let Some(b) = self.peek() else {
+            anyhow::bail!("FDF: unexpected end of file");
+        };
+        match b {
+            b'<' if self.bytes.get(self.pos + 1) == Some(&b'<') => {
+                self.pos += 2;
+                let mut dict = lopdf::Dictionary::new();
+                loop {
+                    self.skip_whitespace();
+                    if self.bytes[self.pos..].starts_with(b">>") {
+                        self.pos += 2;
+                        return Ok(Object::Dictionary(dict));
+                    }
+                    let Object::Name(key) = self.object()? else {
+                        anyhow::bail!("FDF: expected a name key at offset {}", self.pos);
+                    };
+                    let value = self.object()?;
+                    dict.set(key, value);
+                }
+            }
+            b'<' => {
+                self.pos += 1;
+                let mut digits = Vec::new();
+                loop {
+                    match self.peek() {
+                        Some(b'>') => break,
+                        Some(b) if b.is_ascii_hexdigit() => digits.push(b),
+                        Some(_) => {}
+                        None => anyhow::bail!("FDF: unterminated hex string at offset {}", start),
+                    }
+                    self.pos += 1;
+                }
+                self.pos += 1;
+                if digits.len() % 2 == 1 {
+                    digits.push(b'0');
+                }
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+                    .collect();
+                Ok(Object::String(bytes, StringFormat::Hexadecimal))
+            }
+            b'(' => self.literal_string(),
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Object::Array(items));
+                        }
+                        Some(_) => items.push(self.object()?),
+                        None => anyhow::bail!("FDF: unterminated array at offset {}", start),
+                    }
+                }
+            }
+            b'/' => {
+                self.pos += 1;
+                let mut name = Vec::new();
+                while let Some(b) = self.peek() {
+                    if is_whitespace(b) || is_delimiter(b) {
+                        break;
+                    }
+                    if b == b'#' {
+                        let hex = self.bytes.get(self.pos + 1..self.pos + 3);
+                        if let Some(byte) = hex
+                            .and_then(|hex| std::str::from_utf8(hex).ok())
+                            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        {
+                            name.push(byte);
+                            self.pos += 3;
+                            continue;
+                        }
+                    }
+                    name.push(b);
+                    self.pos += 1;
+                }
+                Ok(Object::Name(name))
+            }
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.number_or_reference(),
+            _ => {
+                for (keyword, object) in [
+                    (&b"true"[..], Object::Boolean(true)),
+                    (b"false", Object::Boolean(false)),
+                    (b"null", Object::Null),
+                ] {
+                    if self.eat_keyword(keyword) {
+                        return Ok(object);
+                    }
+                }
+                anyhow::bail!("FDF: unexpected token at offset {}", start)
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<Object> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = Vec::new();
+        let mut depth = 0;
+        loop {
+            let Some(b) = self.peek() else {
+                anyhow::bail!("FDF: unterminated string at offset {}", start);
+            };
+            self.pos += 1;
+            match b {
+                b'(' => {
+                    depth += 1;
+                    out.push(b);
+                }
+                b')' if depth == 0 => break,
+                b')' => {
+                    depth -= 1;
+                    out.push(b);
+                }
+                b'\\' => {
+                    let Some(escaped) = self.peek() else {
+                        continue;
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        b'n' => out.push(b'\n'),
+                        b'r' => out.push(b'\r'),
+                        b't' => out.push(b'\t'),
+                        b'b' => out.push(0x08),
+                        b'f' => out.push(0x0C),
+                        b'0'..=b'7' => {
+                            let mut value = (escaped - b'0') as u32;
+                            for _ in 0..2 {
+                                match self.peek() {
+                                    Some(d @ b'0'..=b'7') => {
+                                        value = value * 8 + (d - b'0') as u32;
+                                        self.pos += 1;
+                                    }
+                                    _ => break,
+                                }
+                            }
+                            out.push(value as u8);
+                        }
+                        // A backslash before a line break continues the line.
+                        b'\r' => {
+                            if self.peek() == Some(b'\n') {
+                                self.pos += 1;
+                            }
+                        }
+                        b'\n' => {}
+                        other => out.push(other),
+                    }
+                }
+                _ => out.push(b),
+            }
+        }
+        Ok(Object::String(out, StringFormat::Literal))
+    }
+
+    fn number_or_reference(&mut self) -> Result<Object> {
+        let start = self.pos;
+        self.pos += 1;
+        while self.peek().is_some_and(|b| b.is_ascii_digit() || b == b'.') {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos])?;
+        if text.contains('.') {
+            let value: f32 = text
+                .parse()
+                .with_context(|| format!("FDF: invalid number at offset {}", start))?;
+            return Ok(Object::Real(value));
+        }
+        let value: i64 = text
+            .parse()
+            .with_context(|| format!("FDF: invalid number at offset {}", start))?;
+
+        // `N G R` is an indirect reference.
+        let saved = self.pos;
+        self.skip_whitespace();
+        if self.peek().is_some_and(|b| b.is_ascii_digit()) {
+            if let Ok(generation) = self.integer() {
+                self.skip_whitespace();
+                if self.eat_keyword(b"R") {
+                    return Ok(Object::Reference((value as u32, generation as u16)));
+                }
+            }
+        }
+        self.pos = saved;
+        Ok(Object::Integer(value))
+    }
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<(String, Option<FieldValue>)> {
+        vec![
+            (
+                "applicant.name".to_string(),
+                Some(FieldValue::Text("Ada (née Byron) & co".to_string())),
+            ),
+            (
+                "applicant.city".to_string(),
+                Some(FieldValue::Text("Zürich".to_string())),
+            ),
+            (
+                "agree".to_string(),
+                Some(FieldValue::Name("Yes".to_string())),
+            ),
+            (
+                "colors".to_string(),
+                Some(FieldValue::List(vec![
+                    "red".to_string(),
+                    "blue".to_string(),
+                ])),
+            ),
+            ("notes".to_string(), None),
+        ]
+    }
+
+    fn filled(fields: Vec<(String, Option<FieldValue>)>) -> Vec<(String, FieldValue)> {
+        fields
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)))
+            .collect()
+    }
+
+    #[test]
+    fn test_xfdf_round_trip() {
+        let xml = write_xfdf(&sample(), Some("form.pdf"));
+        assert!(xml.contains("<field name=\"applicant\">\n      <field name=\"name\">"));
+        assert!(xml.contains("<f href=\"form.pdf\"/>"));
+
+        // Button states come back as text, which filling accepts for buttons.
+        let mut expected = filled(sample());
+        expected[2].1 = FieldValue::Text("Yes".to_string());
+        assert_eq!(parse_xfdf(&xml).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_fdf_round_trip() {
+        let fdf = write_fdf(&sample(), Some("form.pdf"));
+        assert!(fdf.starts_with(b"%FDF-1.2"));
+        assert_eq!(parse_fdf(&fdf).unwrap(), filled(sample()));
+    }
+
+    #[test]
+    fn test_parse_fdf_with_indirect_fields() {
+        let fdf = b"%FDF-1.2
+1 0 obj << /FDF << /Fields 2 0 R >> >> endobj
+2 0 obj [ << /T (name) /V (Line\\one \\(1\\)) >> << /T (n\\303) /V /Off >> ] endobj
+trailer << /Root 1 0 R >>
+%%EOF";
+        assert_eq!(
+            parse_fdf(fdf).unwrap(),
+            vec![
+                (
+                    "name".to_string(),
+                    FieldValue::Text("Lineone (1)".to_string())
+                ),
+                ("n\u{c3}".to_string(), FieldValue::Name("Off".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_fdf_with_xref_and_startxref() {
+        let fdf = b"%FDF-1.2
+%\xe2\xe3\xcf\xd3
+1 0 obj
+<< /FDF << /Fields [ << /T (city) /V (Oslo) >> ] >> >>
+endobj
+xref
+0 2
+0000000000 65535 f\r
+0000000015 00000 n\r
+trailer
+<< /Size 2 /Root 1 0 R >>
+startxref
+86
+%%EOF
+";
+        assert_eq!(
+            parse_fdf(fdf).unwrap(),
+            vec![("city".to_string(), FieldValue::Text("Oslo".to_string()))]
+        );
+    }
+}
@@ -0,0 +1,538 @@
+//! Locating figures and their descriptions.
+//!
+//! In tagged PDFs, figures are `Figure` structure elements carrying alternate
+//! text, with captions in `Caption` elements inside or next to them. Untagged
+//! PDFs only have the images drawn on each page and caption lines such as
+//! "Figure 3: ..." in the page text, which are paired with the image nearest
+//! to them on the page.
+
+use crate::pdf::cache::CachedPdf;
+use crate::pdf::pages::{inherited_attribute, number, resolve, Rect};
+use crate::pdf::structure::{struct_tree, StructKid, StructNode};
+use crate::pdf::tagged::{shown_text, TextResolver};
+use crate::pdf::text::PageTextProvider;
+use anyhow::Result;
+use lopdf::content::Operation;
+use lopdf::{Document, Encoding, Object, ObjectId};
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Caption lines: "Figure 3: ...", "Fig. 2a. ...", "FIGURE 4.1 - ...".
+/// Requiring punctuation after the number skips references in running text
+/// such as "Figure 3 shows".
+static CAPTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\s*(figure|fig\.)\s*\d+[a-z]?(\.\d+)*\s*[.:\-–—]\s*\S")
+        .expect("valid caption pattern")
+});
+
+/// Images smaller than this (in pixels, either side) are assumed to be
+/// decoration such as bullets and rules rather than figures.
+const MIN_IMAGE_SIZE: i64 = 32;
+
+/// Shown text within this distance (in points) of a line's baseline
+/// continues that line.
+const LINE_TOLERANCE: f32 = 1.0;
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// A figure on a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    /// Physical page number (1-indexed).
+    pub page: u32,
+    /// Alternate description from the structure tree.
+    pub alt: Option<String>,
+    pub caption: Option<String>,
+    /// Name of the image XObject drawn, for figures found in untagged PDFs.
+    pub image: Option<String>,
+}
+
+/// Find the figures in a PDF, in page order.
+///
+/// Uses the structure tree if it contains any figures, and otherwise pairs
+/// images drawn on each page with caption lines in that page's text. Images
+/// come in drawing order, followed by captions no image was paired with.
+pub fn find_figures(cached: &CachedPdf) -> Result<Vec<Figure>> {
+    let doc = cached.document();
+    let figures = tagged_figures(doc);
+    if !figures.is_empty() {
+        return Ok(figures);
+    }
+
+    let mut figures = Vec::new();
+    for (page_num, page_id) in doc.get_pages() {
+        let operations = doc
+            .get_and_decode_page_content(page_id)
+            .map(|content| content.operations)
+            .unwrap_or_default();
+        let images = drawn_images(doc, page_id, &operations);
+        // Pages whose text cannot be extracted still list their images.
+        let captions: Vec<String> = PageTextProvider::new(cached)
+            .page(page_num)
+            .map(|text| {
+                text.lines()
+                    .filter(|line| CAPTION.is_match(line))
+                    .map(normalize)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Where each caption is, from the text line that starts with its
+        // "Figure N:" label.
+        let lines = text_lines(doc, page_id, &operations);
+        let caption_heights: Vec<Option<f32>> = captions
+            .iter()
+            .map(|caption| {
+                let label = squash(CAPTION.find(caption)?.as_str());
+                lines
+                    .iter()
+                    .find(|(_, text)| squash(text).starts_with(&label))
+                    .map(|(y, _)| *y)
+            })
+            .collect();
+        let image_bounds: Vec<Rect> = images.iter().map(|(_, bounds)| *bounds).collect();
+        let caption_of = pair_captions(&image_bounds, &caption_heights);
+
+        for ((name, _), caption) in images.into_iter().zip(&caption_of) {
+            figures.push(Figure {
+                page: page_num,
+                alt: None,
+                caption: caption.map(|i| captions[i].clone()),
+                image: Some(name),
+            });
+        }
+        for (i, caption) in captions.into_iter().enumerate() {
+            if !caption_of.contains(&Some(i)) {
+                figures.push(Figure {
+                    page: page_num,
+                    alt: None,
+                    caption: Some(caption),
+                    image: None,
+                });
+            }
+        }
+    }
+    Ok(figures)
+}
+
+/// For each image, the index of its caption: the nearest one vertically,
+/// taking the closest image-caption pairs first. Captions whose position is
+/// unknown (`None`) aren't paired.
+fn pair_captions(images: &[Rect], captions: &[Option<f32>]) -> Vec<Option<usize>> {
+    let mut pairs = Vec::new();
+    for (i, image) in images.iter().enumerate() {
+        for (j, y) in captions.iter().enumerate() {
+            let Some(y) = *y else {
+                continue;
+            };
+            let distance = (image[1] - y).max(y - image[3]).max(0.0);
+            pairs.push((distance, i, j));
+        }
+    }
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut caption_of = vec![None; images.len()];
+    let mut taken = vec![false; captions.len()];
+    for (_, i, j) in pairs {
+        if caption_of[i].is_none() && !taken[j] {
+            caption_of[i] = Some(j);
+            taken[j] = true;
+        }
+    }
+    caption_of
+}
+
+/// Figures described by `Figure` structure elements.
+///
+/// Elements whose page cannot be determined are skipped.
+pub fn tagged_figures(doc: &Document) -> Vec<Figure> {
+    let tree = struct_tree(doc);
+    let page_numbers: HashMap<ObjectId, u32> = doc
+        .get_pages()
+        .into_iter()
+        .map(|(num, id)| (id, num))
+        .collect();
+    let mut collector = Collector {
+        text: TextResolver::new(doc),
+        page_numbers,
+        figures: Vec::new(),
+    };
+    collector.visit(&tree.iter().collect::<Vec<_>>());
+    collector.figures
+}
+
+struct Collector<'a> {
+    text: TextResolver<'a>,
+    page_numbers: HashMap<ObjectId, u32>,
+    figures: Vec<Figure>,
+}
+
+impl Collector<'_> {
+    fn visit(&mut self, siblings: &[&StructNode]) {
+        for (i, node) in siblings.iter().enumerate() {
+            if !node.is(b"Figure") {
+                self.visit(&elements(node));
+                continue;
+            }
+
+            let Some(&page) = node.first_page().and_then(|id| self.page_numbers.get(&id)) else {
+                continue;
+            };
+            let caption = match elements(node)
+                .into_iter()
+                .find(|child| child.is(b"Caption"))
+            {
+                Some(child) => Some(self.text.text_of(child)),
+                None => self.adjacent_caption(siblings, i),
+            };
+            self.figures.push(Figure {
+                page,
+                alt: node
+                    .alt
+                    .as_deref()
+                    .or(node.actual_text.as_deref())
+                    .map(normalize),
+                caption: caption
+                    .map(|text| normalize(&text))
+                    .filter(|text| !text.is_empty()),
+                image: None,
+            });
+        }
+    }
+
+    /// A caption directly after (or else before) the figure: a `Caption`
+    /// element, or a paragraph that reads like a caption.
+    fn adjacent_caption(&mut self, siblings: &[&StructNode], i: usize) -> Option<String> {
+        let candidates = [
+            siblings.get(i + 1),
+            i.checked_sub(1).and_then(|j| siblings.get(j)),
+        ];
+        for sibling in candidates.into_iter().flatten() {
+            if sibling.is(b"Caption") {
+                return Some(self.text.text_of(sibling));
+            }
+            if sibling.is(b"P") {
+                let text = self.text.text_of(sibling);
+                if CAPTION.is_match(&text) {
+                    return Some(text);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn elements(node: &StructNode) -> Vec<&StructNode> {
+    node.children
+        .iter()
+        .filter_map(|kid| match kid {
+            StructKid::Element(child) => Some(child),
+            StructKid::Content { .. } => None,
+        })
+        .collect()
+}
+
+/// Names and bounds (in default user space) of image XObjects drawn by a
+/// page's content stream, in drawing order. Images inside form XObjects are
+/// not included.
+fn drawn_images(
+    doc: &Document,
+    page_id: ObjectId,
+    operations: &[Operation],
+) -> Vec<(String, Rect)> {
+    let resources = inherited_attribute(doc, page_id, b"Resources");
+    let xobjects = resources
+        .as_ref()
+        .and_then(|res| match resolve(doc, res) {
+            Object::Dictionary(dict) => dict.get(b"XObject").ok(),
+            _ => None,
+        })
+        .and_then(|obj| resolve(doc, obj).as_dict().ok());
+    let Some(xobjects) = xobjects else {
+        return Vec::new();
+    };
+
+    let mut images = Vec::new();
+    let mut ctm: Matrix = IDENTITY;
+    let mut stack = Vec::new();
+    for op in operations {
+        match op.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(IDENTITY),
+            "cm" if op.operands.len() == 6 => {
+                let arg = |i: usize| number(&op.operands[i]).unwrap_or(0.0);
+                let m = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                ctm = multiply(&m, &ctm);
+            }
+            _ => {}
+        }
+        if op.operator != "Do" {
+            continue;
+        }
+        let Some(Object::Name(name)) = op.operands.first() else {
+            continue;
+        };
+        let Ok(Object::Stream(stream)) = xobjects.get(name).map(|obj| resolve(doc, obj)) else {
+            continue;
+        };
+        let dict = &stream.dict;
+        let is_image = matches!(dict.get(b"Subtype"), Ok(Object::Name(n)) if n == b"Image");
+        let size = |key: &[u8]| dict.get(key).and_then(Object::as_i64).unwrap_or(0);
+        if is_image && size(b"Width") >= MIN_IMAGE_SIZE && size(b"Height") >= MIN_IMAGE_SIZE {
+            // Images fill the unit square of the current transformation.
+            let corners =
+                [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)].map(|(x, y)| apply(&ctm, x, y));
+            let xs = corners.map(|c| c.0);
+            let ys = corners.map(|c| c.1);
+            let min = |v: [f32; 4]| v.into_iter().fold(f32::INFINITY, f32::min);
+            let max = |v: [f32; 4]| v.into_iter().fold(f32::NEG_INFINITY, f32::max);
+            let bounds = [min(xs), min(ys), max(xs), max(ys)];
+            images.push((String::from_utf8_lossy(name).into_owned(), bounds));
+        }
+    }
+    images
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The text lines a page's content stream shows, as the height of each
+/// line's baseline (in default user space) and its text, in drawing order.
+/// Text inside form XObjects is not included.
+fn text_lines(doc: &Document, page_id: ObjectId, operations: &[Operation]) -> Vec<(f32, String)> {
+    let fonts = doc.get_page_fonts(page_id).unwrap_or_default();
+    let mut encodings: HashMap<Vec<u8>, Option<Encoding>> = HashMap::new();
+    let mut font: Option<Vec<u8>> = None;
+
+    let mut lines: Vec<(f32, String)> = Vec::new();
+    let mut ctm: Matrix = IDENTITY;
+    let mut stack = Vec::new();
+    // The text matrix and the start of the current line.
+    let (mut tm, mut tlm): (Matrix, Matrix) = (IDENTITY, IDENTITY);
+    let mut leading = 0.0;
+    // Move to the start of the next line, offset from the current one.
+    let next_line = |tlm: &mut Matrix, tx: f32, ty: f32| {
+        *tlm = multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], tlm);
+        *tlm
+    };
+    for op in operations {
+        let arg = |i: usize| op.operands.get(i).and_then(number).unwrap_or(0.0);
+        match op.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(IDENTITY),
+            "cm" if op.operands.len() == 6 => {
+                let m = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                ctm = multiply(&m, &ctm);
+            }
+            "BT" => (tm, tlm) = (IDENTITY, IDENTITY),
+            "Tf" => {
+                if let Some(Object::Name(name)) = op.operands.first() {
+                    font = Some(name.clone());
+                }
+            }
+            "TL" => leading = arg(0),
+            "Td" => tm = next_line(&mut tlm, arg(0), arg(1)),
+            "TD" => {
+                leading = -arg(1);
+                tm = next_line(&mut tlm, arg(0), arg(1));
+            }
+            "Tm" if op.operands.len() == 6 => {
+                tlm = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                tm = tlm;
+            }
+            "T*" => tm = next_line(&mut tlm, 0.0, -leading),
+            "Tj" | "TJ" | "'" | "\"" => {
+                if matches!(op.operator.as_str(), "'" | "\"") {
+                    tm = next_line(&mut tlm, 0.0, -leading);
+                }
+                let encoding = font.as_ref().and_then(|name| {
+                    encodings
+                        .entry(name.clone())
+                        .or_insert_with(|| {
+                            fonts
+                                .get(name)
+                                .and_then(|font| font.get_font_encoding(doc).ok())
+                        })
+                        .as_ref()
+                });
+                let Some(encoding) = encoding else {
+                    continue;
+                };
+
+                let text = shown_text(op, encoding);
+                let (_, y) = apply(&multiply(&tm, &ctm), 0.0, 0.0);
+                match lines.last_mut() {
+                    Some((line_y, line)) if (*line_y - y).abs() <= LINE_TOLERANCE => {
+                        line.push_str(&text)
+                    }
+                    _ => lines.push((y, text)),
+                }
+            }
+            _ => {}
+        }
+    }
+    lines
+}
+
+/// Lowercase `text` without whitespace, for comparing text that may have
+/// been spaced differently.
+fn squash(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn apply(m: &Matrix, x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn test_caption_pattern() {
+        let caption = &*CAPTION;
+        assert!(caption.is_match("Figure 3: Throughput under load"));
+        assert!(caption.is_match("Fig. 2a. Setup"));
+        assert!(caption.is_match("FIGURE 4.1 - Overview"));
+        assert!(!caption.is_match("Figure 3 shows the throughput"));
+        assert!(!caption.is_match("As seen in Figure 3: nothing"));
+    }
+
+    #[test]
+    fn test_tagged_figures_read_alt_and_caption() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        let content = b"/Figure <</MCID 0>> BDC EMC
+/Caption <</MCID 1>> BDC BT /F1 10 Tf 72 400 Td (Figure 1: Sales by region) Tj ET EMC";
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+        doc.get_dictionary_mut(page_ids[0])
+            .unwrap()
+            .set("Contents", content_id);
+
+        let figure = doc.add_object(dictionary! {
+            "S" => "Figure",
+            "Alt" => Object::string_literal("A  bar chart"),
+            "K" => 0,
+        });
+        let caption = doc.add_object(dictionary! { "S" => "Caption", "K" => 1 });
+        let document = doc.add_object(dictionary! {
+            "S" => "Document",
+            "Pg" => page_ids[0],
+            "K" => vec![Object::Reference(figure), Object::Reference(caption)],
+        });
+        let root = doc.add_object(dictionary! { "Type" => "StructTreeRoot", "K" => document });
+        doc.catalog_mut().unwrap().set("StructTreeRoot", root);
+
+        assert_eq!(
+            tagged_figures(&doc),
+            vec![Figure {
+                page: 1,
+                alt: Some("A bar chart".to_string()),
+                caption: Some("Figure 1: Sales by region".to_string()),
+                image: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_drawn_images_skips_small_and_forms() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        let image = |doc: &mut Document, size: i64| {
+            doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Image",
+                    "Width" => size,
+                    "Height" => size,
+                },
+                Vec::new(),
+            ))
+        };
+        let photo = image(&mut doc, 400);
+        let bullet = image(&mut doc, 8);
+        let form = doc.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Form" },
+            Vec::new(),
+        ));
+        let content = b"/Bullet Do /Form Do q 200 0 0 200 72 400 cm /Photo Do Q".to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page = doc.get_dictionary_mut(page_ids[0]).unwrap();
+        page.set("Contents", content_id);
+        page.set(
+            "Resources",
+            dictionary! {
+                "XObject" => dictionary! { "Photo" => photo, "Bullet" => bullet, "Form" => form },
+            },
+        );
+
+        let operations = doc
+            .get_and_decode_page_content(page_ids[0])
+            .unwrap()
+            .operations;
+        assert_eq!(
+            drawn_images(&doc, page_ids[0], &operations),
+            vec![("Photo".to_string(), [72.0, 400.0, 272.0, 600.0])]
+        );
+    }
+
+    #[test]
+    fn test_text_lines() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        let content =
+            b"q 1 0 0 1 0 100 cm BT /F1 10 Tf 12 TL 72 500 Td (Figure) Tj ( 2: Results) Tj \
+                        T* (shown here) Tj ET Q BT /F1 10 Tf 72 90 Td [(Page) -300 (1)] TJ ET"
+                .to_vec();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        doc.get_dictionary_mut(page_ids[0])
+            .unwrap()
+            .set("Contents", content_id);
+
+        let operations = doc
+            .get_and_decode_page_content(page_ids[0])
+            .unwrap()
+            .operations;
+        assert_eq!(
+            text_lines(&doc, page_ids[0], &operations),
+            vec![
+                (600.0, "Figure 2: Results".to_string()),
+                (588.0, "shown here".to_string()),
+                (90.0, "Page 1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pair_captions_by_distance() {
+        // A photo at the top with its caption below it, an uncaptioned logo
+        // in the middle, and a chart at the bottom with its caption above.
+        let images = [
+            [72.0, 600.0, 300.0, 750.0],
+            [72.0, 400.0, 120.0, 450.0],
+            [72.0, 80.0, 300.0, 250.0],
+        ];
+        let captions = [Some(585.0), Some(265.0), None];
+        assert_eq!(
+            pair_captions(&images, &captions),
+            vec![Some(0), None, Some(1)]
+        );
+    }
+}
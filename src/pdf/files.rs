@@ -0,0 +1,132 @@
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Recursively find PDF files under a directory, sorted by path.
+///
+/// Files are matched by extension (case-insensitive). Unreadable entries are
+/// skipped rather than aborting the whole walk.
+pub fn find_pdfs<P: AsRef<Path>>(root: P) -> Vec<PathBuf> {
+    find_pdfs_to_depth(root, usize::MAX)
+}
+
+/// Like [`find_pdfs`], but only `max_depth` levels down (1 = the files
+/// directly in `root`).
+pub fn find_pdfs_to_depth<P: AsRef<Path>>(root: P, max_depth: usize) -> Vec<PathBuf> {
+    let mut pdfs: Vec<PathBuf> = WalkDir::new(root)
+        .max_depth(max_depth)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_pdf(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect();
+    pdfs.sort();
+    pdfs
+}
+
+/// Whether a path has a `.pdf` extension.
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// `file.pdf` -> `file.pdf.<extension>`, so a sidecar sorts next to its PDF.
+pub fn sidecar_path(pdf: &Path, extension: &str) -> PathBuf {
+    let mut name = pdf.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Whether `sidecar` exists and was written no earlier than `pdf` changed.
+pub fn is_up_to_date(pdf: &Path, sidecar: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(pdf), modified(sidecar)) {
+        (Some(pdf), Some(sidecar)) => sidecar >= pdf,
+        _ => false,
+    }
+}
+
+/// The page texts in `pdf`'s plain-text sidecar (`file.pdf.txt`, as written
+/// by `inpdf textify`), or `None` if there is none or it is older than the
+/// PDF. Each page's text has its trailing whitespace trimmed.
+pub fn read_text_sidecar(pdf: &Path) -> Option<Vec<String>> {
+    let sidecar = sidecar_path(pdf, "txt");
+    if !is_up_to_date(pdf, &sidecar) {
+        return None;
+    }
+    let text = std::fs::read_to_string(sidecar).ok()?;
+    // Every page, the last included, ends with a newline and a form feed.
+    let pages = text.strip_suffix("\n\x0c")?.split("\n\x0c");
+    Some(pages.map(str::to_string).collect())
+}
+
+/// Match `path` (relative, with `/` separators) against a glob: `*` matches
+/// within one path component, `**` across components, and `?` one
+/// character. A pattern without a `/` is matched against the file name.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let path = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    glob_match_chars(&pattern, &path)
+}
+
+fn glob_match_chars(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // `**/` also matches no directories at all.
+            glob_match_chars(rest, path)
+                || (0..path.len())
+                    .filter(|&i| path[i] == '/')
+                    .any(|i| glob_match_chars(rest, &path[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| glob_match_chars(rest, &path[i..])),
+        ['*', rest @ ..] => (0..=path.len())
+            .take_while(|&i| i == 0 || path[i - 1] != '/')
+            .any(|i| glob_match_chars(rest, &path[i..])),
+        ['?', rest @ ..] => {
+            matches!(path.first(), Some(&c) if c != '/') && glob_match_chars(rest, &path[1..])
+        }
+        [c, rest @ ..] => path.first() == Some(c) && glob_match_chars(rest, &path[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_text_sidecar() {
+        let dir = std::env::temp_dir().join(format!("inpdf-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pdf = dir.join("a.pdf");
+        std::fs::write(&pdf, "").unwrap();
+        assert_eq!(read_text_sidecar(&pdf), None);
+
+        std::fs::write(sidecar_path(&pdf, "txt"), "one\ntwo\n\x0c\n\x0cthree\n\x0c").unwrap();
+        assert_eq!(
+            read_text_sidecar(&pdf).unwrap(),
+            vec!["one\ntwo", "", "three"]
+        );
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.pdf", "reports/2024/q3.pdf"));
+        assert!(glob_match("*invoice*", "a/Big-invoice-7.pdf"));
+        assert!(!glob_match("*invoice*", "invoices/a.pdf"));
+        assert!(glob_match("reports/*.pdf", "reports/q3.pdf"));
+        assert!(!glob_match("reports/*.pdf", "reports/2024/q3.pdf"));
+        assert!(glob_match("reports/**/*.pdf", "reports/2024/q3.pdf"));
+        assert!(glob_match("reports/**/*.pdf", "reports/q3.pdf"));
+        assert!(glob_match("**/q?.pdf", "a/b/q3.pdf"));
+        assert!(!glob_match("q?.pdf", "q10.pdf"));
+    }
+}
@@ -0,0 +1,444 @@
+//! Guessing which tool (and which scanner) produced a PDF, for triaging
+//! problem documents by source.
+//!
+//! The `/Info` Producer and Creator strings are the strongest evidence, then
+//! the XMP packet's tool entries (including `xmpMM:History`, which keeps
+//! earlier tools after a file is resaved). When those are missing or
+//! rewritten, font names and structural habits still point at a family of
+//! generators.
+
+use crate::pdf::inspect::{document_stats, stream_bytes, XrefKind};
+use crate::pdf::structure::struct_tree_root;
+use crate::pdf::PdfDocument;
+use lopdf::Object;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::BTreeSet;
+
+/// Whether a guess is about the software that wrote the file or the device
+/// that scanned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuessKind {
+    Generator,
+    Scanner,
+}
+
+/// One candidate source and what points at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Guess {
+    pub kind: GuessKind,
+    pub tool: String,
+    /// Where the evidence came from, e.g. `Producer "pdfTeX-1.40.25"`.
+    pub evidence: Vec<String>,
+    /// Strings naming the tool count for more than fonts or structure.
+    pub strong: bool,
+}
+
+/// Everything gathered about a document's origin.
+#[derive(Debug, Clone, Default)]
+pub struct Fingerprint {
+    pub producer: Option<String>,
+    pub creator: Option<String>,
+    /// `xmp:CreatorTool`, `pdf:Producer`, and `stEvt:softwareAgent` values.
+    pub xmp_tools: Vec<String>,
+    /// `tiff:Make` and `tiff:Model`, which some scanners record.
+    pub xmp_device: Vec<String>,
+    /// Font names with subset prefixes removed, sorted.
+    pub fonts: Vec<String>,
+    pub subset_fonts: usize,
+    /// Structural habits, e.g. "object streams" or "JBIG2 images".
+    pub traits: Vec<String>,
+    /// Most likely first within each kind.
+    pub guesses: Vec<Guess>,
+}
+
+/// Substrings (lowercase) of tool strings and the tool they identify.
+const TOOLS: &[(&str, &str, GuessKind)] = &[
+    ("pdftex", "LaTeX (pdfTeX)", GuessKind::Generator),
+    ("xetex", "LaTeX (XeTeX)", GuessKind::Generator),
+    ("xdvipdfmx", "LaTeX (XeTeX)", GuessKind::Generator),
+    ("luatex", "LaTeX (LuaTeX)", GuessKind::Generator),
+    ("dvipdfm", "LaTeX (dvipdfmx)", GuessKind::Generator),
+    ("latex", "LaTeX", GuessKind::Generator),
+    ("microsoft® word", "Microsoft Word", GuessKind::Generator),
+    ("microsoft word", "Microsoft Word", GuessKind::Generator),
+    ("microsoft® excel", "Microsoft Excel", GuessKind::Generator),
+    ("microsoft excel", "Microsoft Excel", GuessKind::Generator),
+    (
+        "microsoft® powerpoint",
+        "Microsoft PowerPoint",
+        GuessKind::Generator,
+    ),
+    (
+        "microsoft powerpoint",
+        "Microsoft PowerPoint",
+        GuessKind::Generator,
+    ),
+    (
+        "microsoft: print to pdf",
+        "Microsoft Print to PDF",
+        GuessKind::Generator,
+    ),
+    ("libreoffice", "LibreOffice", GuessKind::Generator),
+    ("openoffice", "OpenOffice", GuessKind::Generator),
+    ("acrobat pdfmaker", "Adobe PDFMaker", GuessKind::Generator),
+    (
+        "acrobat distiller",
+        "Adobe Acrobat Distiller",
+        GuessKind::Generator,
+    ),
+    ("indesign", "Adobe InDesign", GuessKind::Generator),
+    ("illustrator", "Adobe Illustrator", GuessKind::Generator),
+    ("photoshop", "Adobe Photoshop", GuessKind::Generator),
+    ("framemaker", "Adobe FrameMaker", GuessKind::Generator),
+    ("acrobat", "Adobe Acrobat", GuessKind::Generator),
+    (
+        "adobe pdf library",
+        "Adobe PDF Library",
+        GuessKind::Generator,
+    ),
+    ("google docs", "Google Docs", GuessKind::Generator),
+    ("skia/pdf", "Chrome (Skia)", GuessKind::Generator),
+    ("quartz pdfcontext", "macOS Quartz", GuessKind::Generator),
+    ("mac os x", "macOS Quartz", GuessKind::Generator),
+    ("ghostscript", "Ghostscript", GuessKind::Generator),
+    ("wkhtmltopdf", "wkhtmltopdf", GuessKind::Generator),
+    ("prince", "Prince", GuessKind::Generator),
+    ("weasyprint", "WeasyPrint", GuessKind::Generator),
+    ("itext", "iText", GuessKind::Generator),
+    ("reportlab", "ReportLab", GuessKind::Generator),
+    ("tcpdf", "TCPDF", GuessKind::Generator),
+    ("fpdf", "FPDF", GuessKind::Generator),
+    ("pdfsharp", "PDFsharp", GuessKind::Generator),
+    ("aspose", "Aspose", GuessKind::Generator),
+    ("cairo", "cairo", GuessKind::Generator),
+    ("pdfium", "PDFium", GuessKind::Generator),
+    ("qpdf", "qpdf", GuessKind::Generator),
+    ("inpdf", "inpdf", GuessKind::Generator),
+    ("abbyy", "ABBYY FineReader", GuessKind::Generator),
+    ("omnipage", "OmniPage", GuessKind::Generator),
+    ("scansnap", "Fujitsu ScanSnap", GuessKind::Scanner),
+    (
+        "paperstream",
+        "Fujitsu/Ricoh PaperStream",
+        GuessKind::Scanner,
+    ),
+    ("fujitsu", "Fujitsu", GuessKind::Scanner),
+    ("canon", "Canon", GuessKind::Scanner),
+    ("ricoh", "Ricoh", GuessKind::Scanner),
+    ("konica minolta", "Konica Minolta", GuessKind::Scanner),
+    ("xerox", "Xerox", GuessKind::Scanner),
+    ("hp digital sending", "HP", GuessKind::Scanner),
+    ("hp scan", "HP", GuessKind::Scanner),
+    ("epson", "Epson", GuessKind::Scanner),
+    ("brother", "Brother", GuessKind::Scanner),
+    ("kodak", "Kodak", GuessKind::Scanner),
+    ("kyocera", "Kyocera", GuessKind::Scanner),
+    ("sharp", "Sharp", GuessKind::Scanner),
+    ("lexmark", "Lexmark", GuessKind::Scanner),
+];
+
+/// Font name prefixes (lowercase) typical of one family of generators.
+const FONT_HINTS: &[(&str, &str)] = &[
+    ("cmr", "LaTeX"),
+    ("cmmi", "LaTeX"),
+    ("cmsy", "LaTeX"),
+    ("lmroman", "LaTeX"),
+    ("calibri", "Microsoft Office"),
+    ("cambria", "Microsoft Office"),
+    ("aptos", "Microsoft Office"),
+    ("liberation", "LibreOffice"),
+    ("dejavu", "LibreOffice"),
+    ("glyphlessfont", "Tesseract OCR"),
+];
+
+/// Gather the evidence about where `doc` came from. `raw` is the file's
+/// bytes, for structure lopdf doesn't keep after parsing.
+pub fn fingerprint(doc: &PdfDocument, raw: &[u8]) -> Fingerprint {
+    let info = doc.get_info();
+    let pdf = doc.doc();
+    let mut fp = Fingerprint {
+        producer: info.producer,
+        creator: info.creator,
+        ..Default::default()
+    };
+
+    if let Some(xmp) = xmp_packet(doc) {
+        for name in ["xmp:CreatorTool", "pdf:Producer", "stEvt:softwareAgent"] {
+            for value in xmp_values(&xmp, name) {
+                if !fp.xmp_tools.contains(&value) {
+                    fp.xmp_tools.push(value);
+                }
+            }
+        }
+        for name in ["tiff:Make", "tiff:Model"] {
+            fp.xmp_device.extend(xmp_values(&xmp, name));
+        }
+    }
+
+    let mut fonts = BTreeSet::new();
+    let mut images_by_filter = BTreeSet::new();
+    let mut image_count = 0;
+    for obj in pdf.objects.values() {
+        match obj {
+            Object::Dictionary(dict) if matches!(dict.get(b"Type"), Ok(Object::Name(n)) if n == b"Font") => {
+                if let Ok(Object::Name(name)) = dict.get(b"BaseFont") {
+                    let name = String::from_utf8_lossy(name);
+                    let (subset, name) = strip_subset_prefix(&name);
+                    fp.subset_fonts += subset as usize;
+                    fonts.insert(name.to_string());
+                }
+            }
+            Object::Stream(stream) if matches!(stream.dict.get(b"Subtype"), Ok(Object::Name(n)) if n == b"Image") =>
+            {
+                image_count += 1;
+                if let Ok(Object::Name(filter)) = stream.dict.get(b"Filter") {
+                    images_by_filter.insert(String::from_utf8_lossy(filter).into_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+    fp.fonts = fonts.into_iter().collect();
+
+    let stats = document_stats(pdf, raw);
+    fp.traits.push(format!("PDF {}", stats.version));
+    if stats.xref_kind == Some(XrefKind::Stream) {
+        fp.traits.push("xref stream".to_string());
+    }
+    if stats.objects_by_type.contains_key("ObjStm") {
+        fp.traits.push("object streams".to_string());
+    }
+    if stats.incremental_updates > 0 {
+        fp.traits.push(format!(
+            "{} incremental update(s)",
+            stats.incremental_updates
+        ));
+    }
+    if struct_tree_root(pdf).is_some() {
+        fp.traits.push("tagged".to_string());
+    }
+    for (filter, name) in [
+        ("JBIG2Decode", "JBIG2 images"),
+        ("CCITTFaxDecode", "CCITT fax images"),
+        ("JPXDecode", "JPEG 2000 images"),
+    ] {
+        if images_by_filter.contains(filter) {
+            fp.traits.push(name.to_string());
+        }
+    }
+    if fp.fonts.is_empty() && image_count >= info.page_count as usize && info.page_count > 0 {
+        fp.traits.push("image-only pages".to_string());
+    }
+
+    fp.guesses = guess(&fp);
+    fp
+}
+
+/// Weigh the evidence in `fp` into guesses, strongest first.
+fn guess(fp: &Fingerprint) -> Vec<Guess> {
+    let mut guesses: Vec<Guess> = Vec::new();
+    let mut add = |kind, tool: &str, evidence: String, strong| match guesses
+        .iter_mut()
+        .find(|g| g.tool == tool)
+    {
+        Some(guess) => {
+            if !guess.evidence.contains(&evidence) {
+                guess.evidence.push(evidence);
+            }
+            guess.strong |= strong;
+        }
+        None => guesses.push(Guess {
+            kind,
+            tool: tool.to_string(),
+            evidence: vec![evidence],
+            strong,
+        }),
+    };
+
+    let strings = [("Producer", &fp.producer), ("Creator", &fp.creator)]
+        .into_iter()
+        .filter_map(|(field, value)| Some((field, value.as_deref()?)))
+        .chain(fp.xmp_tools.iter().map(|tool| ("XMP", tool.as_str())))
+        .chain(
+            fp.xmp_device
+                .iter()
+                .map(|device| ("XMP device", device.as_str())),
+        );
+    for (field, value) in strings {
+        let lower = value.to_lowercase();
+        // The first (most specific) match names the tool.
+        if let Some(&(_, tool, kind)) = TOOLS.iter().find(|(needle, _, _)| lower.contains(needle)) {
+            add(kind, tool, format!("{} \"{}\"", field, value), true);
+        }
+    }
+
+    for font in &fp.fonts {
+        let lower = font.to_lowercase();
+        if let Some(&(_, tool)) = FONT_HINTS
+            .iter()
+            .find(|(prefix, _)| lower.starts_with(prefix))
+        {
+            add(GuessKind::Generator, tool, format!("font {}", font), false);
+        }
+    }
+
+    let has = |name: &str| fp.traits.iter().any(|t| t == name);
+    for bilevel in ["JBIG2 images", "CCITT fax images"] {
+        if has(bilevel) {
+            add(
+                GuessKind::Scanner,
+                "a document scanner",
+                bilevel.to_string(),
+                false,
+            );
+        }
+    }
+    if has("image-only pages") {
+        add(
+            GuessKind::Scanner,
+            "a document scanner",
+            "image-only pages".to_string(),
+            false,
+        );
+    }
+
+    // Named scanners make the generic one redundant.
+    if guesses
+        .iter()
+        .any(|g| g.kind == GuessKind::Scanner && g.strong)
+    {
+        guesses.retain(|g| g.kind != GuessKind::Scanner || g.strong);
+    }
+    guesses.sort_by_key(|g| {
+        (
+            g.kind == GuessKind::Scanner,
+            !g.strong,
+            std::cmp::Reverse(g.evidence.len()),
+        )
+    });
+    guesses
+}
+
+/// The catalog's XMP metadata stream as text.
+fn xmp_packet(doc: &PdfDocument) -> Option<String> {
+    let pdf = doc.doc();
+    let metadata = pdf.catalog().ok()?.get(b"Metadata").ok()?;
+    let stream = match metadata {
+        Object::Reference(id) => pdf.get_object(*id).ok()?.as_stream().ok()?,
+        Object::Stream(stream) => stream,
+        _ => return None,
+    };
+    let bytes = stream_bytes(stream).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Values of the XMP property `name` (a qualified name such as
+/// `xmp:CreatorTool`), written either as an attribute or as an element,
+/// including `rdf:li` items inside it. Malformed XMP yields what was read
+/// before the error.
+fn xmp_values(xmp: &str, name: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xmp);
+    let mut values = Vec::new();
+    // How deep inside a `name` element the reader is.
+    let mut inside = 0;
+    while let Ok(event) = reader.read_event() {
+        match event {
+            Event::Start(e) => {
+                attribute_values(&e, name, &mut values);
+                if inside > 0 || e.name().as_ref() == name.as_bytes() {
+                    inside += 1;
+                }
+            }
+            Event::Empty(e) => attribute_values(&e, name, &mut values),
+            Event::Text(text) if inside > 0 => {
+                if let Ok(text) = text.unescape() {
+                    if !text.trim().is_empty() {
+                        values.push(text.trim().to_string());
+                    }
+                }
+            }
+            Event::End(_) if inside > 0 => inside -= 1,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    values
+}
+
+fn attribute_values(element: &BytesStart, name: &str, values: &mut Vec<String>) {
+    for attribute in element.attributes().flatten() {
+        if attribute.key.as_ref() == name.as_bytes() {
+            if let Ok(value) = attribute.unescape_value() {
+                values.push(value.into_owned());
+            }
+        }
+    }
+}
+
+/// `ABCDEF+Calibri` -> `(true, "Calibri")`.
+fn strip_subset_prefix(name: &str) -> (bool, &str) {
+    match name.split_once('+') {
+        Some((tag, rest)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => {
+            (true, rest)
+        }
+        _ => (false, name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xmp_values() {
+        let xmp = r##"<rdf:Description xmp:CreatorTool="Microsoft&#174; Word" pdf:Producer="Acrobat Distiller 11.0">
+            <xmpMM:History><rdf:Seq>
+              <rdf:li stEvt:action="saved" stEvt:softwareAgent="Adobe InDesign 18.0"/>
+            </rdf:Seq></xmpMM:History>
+            <tiff:Model>ScanSnap iX1600</tiff:Model>
+            <dc:title><rdf:Alt><rdf:li xml:lang="x-default">A &amp; B</rdf:li></rdf:Alt></dc:title>
+        </rdf:Description>"##;
+        assert_eq!(xmp_values(xmp, "xmp:CreatorTool"), ["Microsoft® Word"]);
+        assert_eq!(xmp_values(xmp, "pdf:Producer"), ["Acrobat Distiller 11.0"]);
+        assert_eq!(
+            xmp_values(xmp, "stEvt:softwareAgent"),
+            ["Adobe InDesign 18.0"]
+        );
+        assert_eq!(xmp_values(xmp, "tiff:Model"), ["ScanSnap iX1600"]);
+        assert_eq!(xmp_values(xmp, "dc:title"), ["A & B"]);
+        assert!(xmp_values(xmp, "tiff:Make").is_empty());
+    }
+
+    #[test]
+    fn test_guess() {
+        let fp = Fingerprint {
+            producer: Some("pdfTeX-1.40.25".to_string()),
+            fonts: vec!["CMR10".to_string(), "Helvetica".to_string()],
+            traits: vec!["JBIG2 images".to_string()],
+            ..Default::default()
+        };
+        let guesses = guess(&fp);
+        assert_eq!(guesses[0].tool, "LaTeX (pdfTeX)");
+        assert!(guesses[0].strong);
+        assert_eq!(guesses[1].tool, "LaTeX");
+        assert_eq!(guesses[1].evidence, ["font CMR10"]);
+        assert_eq!(guesses[2].kind, GuessKind::Scanner);
+
+        let fp = Fingerprint {
+            creator: Some("ScanSnap Manager #iX500".to_string()),
+            traits: vec!["image-only pages".to_string()],
+            ..Default::default()
+        };
+        let guesses = guess(&fp);
+        assert_eq!(guesses.len(), 1);
+        assert_eq!(guesses[0].tool, "Fujitsu ScanSnap");
+    }
+
+    #[test]
+    fn test_strip_subset_prefix() {
+        assert_eq!(strip_subset_prefix("ABCDEF+Calibri"), (true, "Calibri"));
+        assert_eq!(strip_subset_prefix("Calibri"), (false, "Calibri"));
+        assert_eq!(strip_subset_prefix("Abcdef+X"), (false, "Abcdef+X"));
+    }
+}
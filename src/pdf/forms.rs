@@ -0,0 +1,362 @@
+//! Interactive form (AcroForm) fields: reading and filling values.
+//!
+//! Fields form a tree under the catalog's `AcroForm` `Fields`; a field's
+//! fully qualified name joins the partial names (`T`) of its ancestors with
+//! periods. Terminal fields carry the value (`V`) and are drawn by one or
+//! more widget annotations, which may be merged into the field dictionary.
+
+use crate::pdf::pages::resolve;
+use crate::pdf::strings::{decode_text_string, encode_text_string};
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{HashMap, HashSet};
+
+/// Deepest `Kids` level of the field tree read. Field names rarely nest more
+/// than a few parts (`page1.address.city`).
+const MAX_DEPTH: usize = 32;
+
+/// Field flag (`Ff`) bit marking a button as a push button.
+const FLAG_PUSHBUTTON: i64 = 1 << 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    /// Check boxes and radio buttons, whose value is the name of the
+    /// selected appearance state (e.g. `Yes`) or `Off`.
+    Button,
+    /// List and combo boxes.
+    Choice,
+    Signature,
+}
+
+/// A field value, as stored in the form or read from a data file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldValue {
+    Text(String),
+    /// A name object, as used by buttons.
+    Name(String),
+    /// Several selected options of a multi-select list.
+    List(Vec<String>),
+}
+
+impl FieldValue {
+    /// The value as plain text, with multiple selections joined by `sep`.
+    pub fn to_text(&self, sep: &str) -> String {
+        match self {
+            FieldValue::Text(text) | FieldValue::Name(text) => text.clone(),
+            FieldValue::List(items) => items.join(sep),
+        }
+    }
+}
+
+/// A terminal form field.
+#[derive(Debug, Clone)]
+pub struct FormField {
+    /// Fully qualified name, e.g. `applicant.address.city`.
+    pub name: String,
+    pub kind: FieldKind,
+    pub value: Option<FieldValue>,
+    pub id: ObjectId,
+    /// Widget annotations drawing the field (possibly the field itself).
+    pub widgets: Vec<ObjectId>,
+}
+
+/// List the terminal fields of the document's form, in tree order.
+///
+/// Push buttons are skipped, since they hold no value. Returns an empty
+/// list if the document has no form.
+pub fn form_fields(doc: &Document) -> Vec<FormField> {
+    let fields = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"AcroForm").ok())
+        .and_then(|form| resolve(doc, form).as_dict().ok())
+        .and_then(|form| form.get(b"Fields").ok())
+        .and_then(|fields| resolve(doc, fields).as_array().ok());
+
+    let mut out = Vec::new();
+    let mut seen = HashSet::new();
+    for field in fields.into_iter().flatten() {
+        if let Ok(id) = field.as_reference() {
+            collect_fields(doc, id, "", None, 0, &mut seen, &mut out);
+        }
+    }
+    out
+}
+
+/// Inheritable attributes passed down the field tree.
+#[derive(Clone, Copy)]
+struct Inherited<'a> {
+    kind: Option<&'a [u8]>,
+    flags: i64,
+    value: Option<&'a Object>,
+}
+
+fn collect_fields<'a>(
+    doc: &'a Document,
+    id: ObjectId,
+    parent_name: &str,
+    inherited: Option<Inherited<'a>>,
+    depth: usize,
+    seen: &mut HashSet<ObjectId>,
+    out: &mut Vec<FormField>,
+) {
+    if depth > MAX_DEPTH || !seen.insert(id) {
+        return;
+    }
+    let Ok(dict) = doc.get_dictionary(id) else {
+        return;
+    };
+
+    let name = match dict.get(b"T").map(|t| resolve(doc, t)) {
+        Ok(Object::String(bytes, _)) => {
+            let partial = decode_text_string(bytes);
+            if parent_name.is_empty() {
+                partial
+            } else {
+                format!("{}.{}", parent_name, partial)
+            }
+        }
+        _ => parent_name.to_string(),
+    };
+    let parent = inherited.unwrap_or(Inherited {
+        kind: None,
+        flags: 0,
+        value: None,
+    });
+    let inherited = Inherited {
+        kind: dict
+            .get(b"FT")
+            .ok()
+            .and_then(|ft| resolve(doc, ft).as_name().ok())
+            .or(parent.kind),
+        flags: dict
+            .get(b"Ff")
+            .ok()
+            .and_then(|ff| resolve(doc, ff).as_i64().ok())
+            .unwrap_or(parent.flags),
+        value: dict.get(b"V").ok().or(parent.value),
+    };
+
+    let kids: Vec<ObjectId> = dict
+        .get(b"Kids")
+        .ok()
+        .and_then(|kids| resolve(doc, kids).as_array().ok())
+        .map(|kids| {
+            kids.iter()
+                .filter_map(|kid| kid.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    // Kids with partial names are child fields; kids without are widgets.
+    let has_child_fields = kids
+        .iter()
+        .any(|&kid| doc.get_dictionary(kid).is_ok_and(|kid| kid.has(b"T")));
+    if has_child_fields {
+        for kid in kids {
+            collect_fields(doc, kid, &name, Some(inherited), depth + 1, seen, out);
+        }
+        return;
+    }
+
+    let kind = match inherited.kind {
+        Some(b"Tx") => FieldKind::Text,
+        Some(b"Btn") if inherited.flags & FLAG_PUSHBUTTON != 0 => return,
+        Some(b"Btn") => FieldKind::Button,
+        Some(b"Ch") => FieldKind::Choice,
+        Some(b"Sig") => FieldKind::Signature,
+        _ => return,
+    };
+    // A signature field's value is the signature dictionary itself.
+    let value = match kind {
+        FieldKind::Signature => None,
+        _ => inherited.value.and_then(|value| field_value(doc, value)),
+    };
+    let widgets = if kids.is_empty() { vec![id] } else { kids };
+
+    out.push(FormField {
+        name,
+        kind,
+        value,
+        id,
+        widgets,
+    });
+}
+
+fn field_value(doc: &Document, value: &Object) -> Option<FieldValue> {
+    match resolve(doc, value) {
+        Object::String(bytes, _) => Some(FieldValue::Text(decode_text_string(bytes))),
+        Object::Name(name) => Some(FieldValue::Name(String::from_utf8_lossy(name).into_owned())),
+        Object::Array(items) => Some(FieldValue::List(
+            items
+                .iter()
+                .filter_map(|item| match resolve(doc, item) {
+                    Object::String(bytes, _) => Some(decode_text_string(bytes)),
+                    _ => None,
+                })
+                .collect(),
+        )),
+        _ => None,
+    }
+}
+
+/// Set field values by fully qualified name. Returns the number of fields
+/// changed.
+///
+/// Viewers are asked to regenerate appearances (`NeedAppearances`), and the
+/// stale appearances of text and choice fields are removed so the new
+/// values show everywhere.
+pub fn fill_fields(doc: &mut Document, values: &[(String, FieldValue)]) -> Result<usize> {
+    let fields: HashMap<String, FormField> = form_fields(doc)
+        .into_iter()
+        .map(|field| (field.name.clone(), field))
+        .collect();
+    if fields.is_empty() {
+        anyhow::bail!("The document has no fillable form fields");
+    }
+
+    for (name, value) in values {
+        let field = fields.get(name).with_context(|| {
+            let mut names: Vec<&str> = fields.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            format!("No form field '{}' (fields: {})", name, names.join(", "))
+        })?;
+        set_value(doc, field, value)?;
+    }
+
+    let form = doc
+        .catalog()?
+        .get(b"AcroForm")
+        .and_then(Object::as_reference)
+        .ok();
+    let form = match form {
+        Some(id) => doc.get_dictionary_mut(id)?,
+        None => doc
+            .catalog_mut()?
+            .get_mut(b"AcroForm")
+            .and_then(Object::as_dict_mut)?,
+    };
+    form.set("NeedAppearances", true);
+
+    Ok(values.len())
+}
+
+fn set_value(doc: &mut Document, field: &FormField, value: &FieldValue) -> Result<()> {
+    let stored = match (field.kind, value) {
+        (FieldKind::Signature, _) => {
+            anyhow::bail!(
+                "Field '{}' is a signature field and cannot be filled",
+                field.name
+            )
+        }
+        (FieldKind::Text, value) => encode_text_string(&value.to_text("\n")),
+        (FieldKind::Button, FieldValue::List(_)) => {
+            anyhow::bail!(
+                "Field '{}' is a button and takes a single value",
+                field.name
+            )
+        }
+        (FieldKind::Button, FieldValue::Text(name) | FieldValue::Name(name)) => {
+            Object::Name(name.as_bytes().to_vec())
+        }
+        (FieldKind::Choice, FieldValue::List(items)) => {
+            Object::Array(items.iter().map(|item| encode_text_string(item)).collect())
+        }
+        (FieldKind::Choice, FieldValue::Text(text) | FieldValue::Name(text)) => {
+            encode_text_string(text)
+        }
+    };
+
+    if let Object::Name(state) = &stored {
+        // Show the matching "on" appearance of each check box or radio
+        // button widget, and "Off" on the others.
+        for &widget in &field.widgets {
+            let has_state = doc
+                .get_dictionary(widget)
+                .ok()
+                .and_then(|w| appearance_states(doc, w))
+                .is_some_and(|states| states.contains(state));
+            let shown = if has_state {
+                state.clone()
+            } else {
+                b"Off".to_vec()
+            };
+            doc.get_dictionary_mut(widget)?
+                .set("AS", Object::Name(shown));
+        }
+    } else {
+        for &widget in &field.widgets {
+            doc.get_dictionary_mut(widget)?.remove(b"AP");
+        }
+    }
+    doc.get_dictionary_mut(field.id)?.set("V", stored);
+    Ok(())
+}
+
+/// The appearance state names of a button widget (keys of `AP` `N`).
+fn appearance_states(doc: &Document, widget: &Dictionary) -> Option<Vec<Vec<u8>>> {
+    let ap = resolve(doc, widget.get(b"AP").ok()?).as_dict().ok()?;
+    let normal = resolve(doc, ap.get(b"N").ok()?).as_dict().ok()?;
+    Some(normal.iter().map(|(key, _)| key.clone()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::form_document;
+
+    #[test]
+    fn test_form_fields_qualified_names_and_values() {
+        let (doc, _) = form_document();
+        let fields = form_fields(&doc);
+        let summary: Vec<_> = fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.kind, f.value.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "applicant.name",
+                    FieldKind::Text,
+                    Some(FieldValue::Text("Ada".to_string()))
+                ),
+                ("applicant.city", FieldKind::Text, None),
+                (
+                    "agree",
+                    FieldKind::Button,
+                    Some(FieldValue::Name("Off".to_string()))
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_fields_sets_values_and_states() {
+        let (mut doc, agree_widget) = form_document();
+        let values = vec![
+            (
+                "applicant.city".to_string(),
+                FieldValue::Text("Zürich".to_string()),
+            ),
+            ("agree".to_string(), FieldValue::Text("Yes".to_string())),
+        ];
+        assert_eq!(fill_fields(&mut doc, &values).unwrap(), 2);
+
+        let fields = form_fields(&doc);
+        assert_eq!(
+            fields[1].value,
+            Some(FieldValue::Text("Zürich".to_string()))
+        );
+        assert_eq!(fields[2].value, Some(FieldValue::Name("Yes".to_string())));
+        let widget = doc.get_dictionary(agree_widget).unwrap();
+        assert_eq!(widget.get(b"AS").unwrap(), &Object::Name(b"Yes".to_vec()));
+
+        let err = fill_fields(
+            &mut doc,
+            &[("nope".to_string(), FieldValue::Text(String::new()))],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("No form field 'nope'"));
+    }
+}
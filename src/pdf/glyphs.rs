@@ -0,0 +1,440 @@
+//! Finding and editing text in content streams, glyph by glyph.
+//!
+//! Extracted page text doesn't record which operators drew it, so text is
+//! found by decoding each text-showing operator glyph by glyph and searching
+//! the decoded glyphs, ignoring whitespace. Edits rewrite the operators as
+//! `TJ` arrays whose spacing keeps the text around them where it was.
+//!
+//! Only the page's own content stream is read: text in form XObjects is not
+//! seen.
+
+use crate::pdf::pages::{number, Rect};
+use crate::pdf::svg::Font;
+use anyhow::{Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashMap;
+use std::ops::Range;
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Glyph extent above and below the baseline, in font size units. Font
+/// bounding boxes aren't consulted, so boxes are sized for typical text.
+const ASCENT: f32 = 0.8;
+const DESCENT: f32 = 0.2;
+
+/// A glyph drawn by a text-showing operator.
+pub(crate) struct Glyph {
+    /// Index of the operator in the page content.
+    pub(crate) op: usize,
+    /// Index of the string among a `TJ` operator's elements (0 otherwise).
+    pub(crate) item: usize,
+    /// Byte range of the glyph's code in that string.
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// The character code, as the font's encoding reads it.
+    pub(crate) code: u32,
+    pub(crate) text: String,
+    /// Name of the font resource it is shown in.
+    pub(crate) font: Vec<u8>,
+    pub(crate) font_size: f32,
+    pub(crate) char_spacing: f32,
+    pub(crate) word_spacing: f32,
+    /// The `TJ` adjustment that moves the text position as far as the
+    /// glyph does.
+    pub(crate) spacing: f32,
+    /// Bounds in default user space.
+    pub(crate) rect: Rect,
+}
+
+/// What to do with a glyph when its operator is rewritten.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Edit {
+    Keep,
+    /// Leave a gap as wide as the glyph.
+    Remove,
+    /// Show these codes (in the same font) instead, followed by a `TJ`
+    /// adjustment.
+    Replace(Vec<u8>, f32),
+}
+
+/// Text state that `q`/`Q` save and restore.
+#[derive(Clone)]
+struct State {
+    ctm: Matrix,
+    font: Option<Vec<u8>>,
+    font_size: f32,
+    char_spacing: f32,
+    word_spacing: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+    rise: f32,
+}
+
+/// Every glyph the page's own content stream draws, in order. Strings in
+/// fonts the page doesn't define are skipped, since they can't be measured.
+pub(crate) fn page_glyphs(
+    doc: &Document,
+    page_id: ObjectId,
+    operations: &[Operation],
+) -> Vec<Glyph> {
+    let font_dicts = doc.get_page_fonts(page_id).unwrap_or_default();
+    let mut fonts: HashMap<Vec<u8>, Option<Font>> = HashMap::new();
+
+    let mut glyphs = Vec::new();
+    let mut state = State {
+        ctm: IDENTITY,
+        font: None,
+        font_size: 0.0,
+        char_spacing: 0.0,
+        word_spacing: 0.0,
+        horizontal_scaling: 1.0,
+        leading: 0.0,
+        rise: 0.0,
+    };
+    let mut stack = Vec::new();
+    let mut text_matrix = IDENTITY;
+    let mut line_matrix = IDENTITY;
+
+    for (i, op) in operations.iter().enumerate() {
+        let operands: Vec<f32> = op.operands.iter().filter_map(number).collect();
+        let arg = |i: usize| operands.get(i).copied().unwrap_or(0.0);
+        let mut move_line = |tx: f32, ty: f32| {
+            line_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], &line_matrix);
+            text_matrix = line_matrix;
+        };
+        let shown: Vec<(usize, &Object)> = match op.operator.as_str() {
+            "q" => {
+                stack.push(state.clone());
+                continue;
+            }
+            "Q" => {
+                if let Some(saved) = stack.pop() {
+                    state = saved;
+                }
+                continue;
+            }
+            "cm" if operands.len() == 6 => {
+                let m = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                state.ctm = multiply(&m, &state.ctm);
+                continue;
+            }
+            "BT" => {
+                text_matrix = IDENTITY;
+                line_matrix = IDENTITY;
+                continue;
+            }
+            "Tf" => {
+                state.font = match op.operands.first() {
+                    Some(Object::Name(name)) => Some(name.clone()),
+                    _ => None,
+                };
+                state.font_size = arg(0);
+                continue;
+            }
+            "Tc" => {
+                state.char_spacing = arg(0);
+                continue;
+            }
+            "Tw" => {
+                state.word_spacing = arg(0);
+                continue;
+            }
+            "Tz" => {
+                state.horizontal_scaling = arg(0) / 100.0;
+                continue;
+            }
+            "TL" => {
+                state.leading = arg(0);
+                continue;
+            }
+            "Ts" => {
+                state.rise = arg(0);
+                continue;
+            }
+            "Td" => {
+                move_line(arg(0), arg(1));
+                continue;
+            }
+            "TD" => {
+                state.leading = -arg(1);
+                move_line(arg(0), arg(1));
+                continue;
+            }
+            "Tm" if operands.len() == 6 => {
+                text_matrix = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                line_matrix = text_matrix;
+                continue;
+            }
+            "T*" => {
+                move_line(0.0, -state.leading);
+                continue;
+            }
+            "Tj" => op.operands.first().map(|s| (0, s)).into_iter().collect(),
+            "'" => {
+                move_line(0.0, -state.leading);
+                op.operands.first().map(|s| (0, s)).into_iter().collect()
+            }
+            "\"" => {
+                state.word_spacing = arg(0);
+                state.char_spacing = arg(1);
+                move_line(0.0, -state.leading);
+                op.operands.get(2).map(|s| (0, s)).into_iter().collect()
+            }
+            "TJ" => match op.operands.first() {
+                Some(Object::Array(items)) => items.iter().enumerate().collect(),
+                _ => continue,
+            },
+            _ => continue,
+        };
+
+        let font = state.font.as_ref().and_then(|name| {
+            fonts
+                .entry(name.clone())
+                .or_insert_with(|| font_dicts.get(name).map(|dict| Font::load(doc, dict)))
+                .as_ref()
+        });
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+        for (item, object) in shown {
+            let bytes = match object {
+                Object::String(bytes, _) => bytes,
+                _ => {
+                    let tx = -number(object).unwrap_or(0.0) / 1000.0 * size * scaling;
+                    text_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &text_matrix);
+                    continue;
+                }
+            };
+            let (Some(font), Some(font_name)) = (font, &state.font) else {
+                continue;
+            };
+            let code_len = if font.two_byte { 2 } else { 1 };
+            for (start, code) in (0..bytes.len()).step_by(code_len).zip(font.codes(bytes)) {
+                let end = (start + code_len).min(bytes.len());
+                let width = font.width(code) / 1000.0 * size;
+                let mut advance = width + state.char_spacing;
+                if code == 32 && !font.two_byte {
+                    advance += state.word_spacing;
+                }
+                let m = multiply(&text_matrix, &state.ctm);
+                let bottom = state.rise - DESCENT * size;
+                let top = state.rise + ASCENT * size;
+                let corners = [(0.0, bottom), (width * scaling, bottom), (0.0, top)]
+                    .map(|(x, y)| apply(&m, x, y));
+                let far = apply(&m, width * scaling, top);
+                let xs = corners.iter().map(|p| p.0).chain([far.0]);
+                let ys = corners.iter().map(|p| p.1).chain([far.1]);
+                glyphs.push(Glyph {
+                    op: i,
+                    item,
+                    start,
+                    end,
+                    code,
+                    text: font
+                        .encoding
+                        .as_ref()
+                        .and_then(|encoding| {
+                            Document::decode_text(encoding, &bytes[start..end]).ok()
+                        })
+                        .unwrap_or_default(),
+                    font: font_name.clone(),
+                    font_size: size,
+                    char_spacing: state.char_spacing,
+                    word_spacing: state.word_spacing,
+                    spacing: tj_spacing(advance, size),
+                    rect: [
+                        xs.clone().fold(f32::INFINITY, f32::min),
+                        ys.clone().fold(f32::INFINITY, f32::min),
+                        xs.fold(f32::NEG_INFINITY, f32::max),
+                        ys.fold(f32::NEG_INFINITY, f32::max),
+                    ],
+                });
+                let tx = advance * scaling;
+                text_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &text_matrix);
+            }
+        }
+    }
+    glyphs
+}
+
+/// The `TJ` adjustment moving the text position by `advance` (in unscaled
+/// text space units) at the given font size.
+pub(crate) fn tj_spacing(advance: f32, font_size: f32) -> f32 {
+    if font_size == 0.0 {
+        0.0
+    } else {
+        -advance / font_size * 1000.0
+    }
+}
+
+/// The glyphs showing each occurrence of `value`, whitespace ignored, in
+/// order. Whitespace glyphs inside an occurrence are part of it.
+pub(crate) fn find_all(glyphs: &[Glyph], value: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let chars: Vec<(char, usize)> = glyphs
+        .iter()
+        .enumerate()
+        .flat_map(|(i, glyph)| {
+            glyph
+                .text
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(move |c| (c, i))
+        })
+        .collect();
+
+    let mut found = Vec::new();
+    let mut at = 0;
+    while at + needle.len() <= chars.len() {
+        let window = &chars[at..at + needle.len()];
+        if window.iter().map(|&(c, _)| c).eq(needle.iter().copied()) {
+            found.push(window[0].1..window[needle.len() - 1].1 + 1);
+            at += needle.len();
+        } else {
+            at += 1;
+        }
+    }
+    found
+}
+
+/// Rebuild the operators that draw edited glyphs.
+pub(crate) fn rewrite(
+    operations: Vec<Operation>,
+    glyphs: &[Glyph],
+    edits: &[Edit],
+) -> Vec<Operation> {
+    let mut by_op: HashMap<usize, Vec<(&Glyph, &Edit)>> = HashMap::new();
+    for (glyph, edit) in glyphs.iter().zip(edits) {
+        by_op.entry(glyph.op).or_default().push((glyph, edit));
+    }
+    by_op.retain(|_, glyphs| glyphs.iter().any(|&(_, edit)| *edit != Edit::Keep));
+
+    let mut out = Vec::with_capacity(operations.len());
+    for (i, op) in operations.into_iter().enumerate() {
+        match by_op.get(&i) {
+            Some(glyphs) => out.extend(edited(op, glyphs)),
+            None => out.push(op),
+        }
+    }
+    out
+}
+
+/// Give a page a new content stream. The old streams are left for
+/// [`Document::prune_objects`], since other pages may share them.
+pub(crate) fn set_page_content(
+    doc: &mut Document,
+    page_id: ObjectId,
+    operations: Vec<Operation>,
+) -> Result<()> {
+    let bytes = Content { operations }
+        .encode()
+        .context("Failed to encode page content")?;
+    let mut stream = Stream::new(Dictionary::new(), bytes);
+    // Compression is an optimization; keep the raw stream if it fails.
+    let _ = stream.compress();
+    let stream_id = doc.add_object(stream);
+    doc.get_dictionary_mut(page_id)
+        .context("Page object is not a dictionary")?
+        .set("Contents", Object::Reference(stream_id));
+    Ok(())
+}
+
+/// Replace a text-showing operator by a `TJ` with the edits applied, after
+/// whatever else the operator did (moving to the next line, setting
+/// spacing).
+fn edited(op: Operation, glyphs: &[(&Glyph, &Edit)]) -> Vec<Operation> {
+    let mut operands = op.operands.into_iter();
+    let (mut ops, items) = match op.operator.as_str() {
+        "TJ" => match operands.next() {
+            Some(Object::Array(items)) => (Vec::new(), items),
+            _ => (Vec::new(), Vec::new()),
+        },
+        "'" => (
+            vec![Operation::new("T*", vec![])],
+            operands.take(1).collect(),
+        ),
+        "\"" => {
+            let word_spacing = operands.next().unwrap_or(Object::Integer(0));
+            let char_spacing = operands.next().unwrap_or(Object::Integer(0));
+            let ops = vec![
+                Operation::new("Tw", vec![word_spacing]),
+                Operation::new("Tc", vec![char_spacing]),
+                Operation::new("T*", vec![]),
+            ];
+            (ops, operands.take(1).collect())
+        }
+        _ => (Vec::new(), operands.take(1).collect()),
+    };
+
+    let mut array = Vec::new();
+    for (item, object) in items.into_iter().enumerate() {
+        let Object::String(bytes, format) = object else {
+            array.push(object);
+            continue;
+        };
+        let mut kept = Vec::new();
+        let gap = |array: &mut Vec<Object>, kept: &mut Vec<u8>, spacing: f32| {
+            if !kept.is_empty() {
+                array.push(Object::String(std::mem::take(kept), format));
+            }
+            match array.last_mut() {
+                Some(Object::Real(last)) => *last += spacing,
+                _ => array.push(Object::Real(spacing)),
+            }
+        };
+        for &(glyph, edit) in glyphs.iter().filter(|(glyph, _)| glyph.item == item) {
+            match edit {
+                Edit::Keep => kept.extend_from_slice(&bytes[glyph.start..glyph.end]),
+                Edit::Remove => gap(&mut array, &mut kept, glyph.spacing),
+                Edit::Replace(codes, adjust) => {
+                    kept.extend_from_slice(codes);
+                    if *adjust != 0.0 {
+                        gap(&mut array, &mut kept, *adjust);
+                    }
+                }
+            }
+        }
+        if !kept.is_empty() {
+            array.push(Object::String(kept, format));
+        }
+    }
+    ops.push(Operation::new("TJ", vec![Object::Array(array)]));
+    ops
+}
+
+/// The transformation `a` followed by `b`.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn apply(m: &Matrix, x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_find_all() {
+        let (doc, pages) = text_document(&["ACME Corp and ACMECorp, not ACME"]);
+        let content = doc.get_and_decode_page_content(pages[0]).unwrap();
+        let glyphs = page_glyphs(&doc, pages[0], &content.operations);
+        assert_eq!(glyphs.len(), 32);
+        assert_eq!(find_all(&glyphs, "ACME Corp"), [0..9, 14..22]);
+        assert!(find_all(&glyphs, " ").is_empty());
+    }
+}
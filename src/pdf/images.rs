@@ -0,0 +1,243 @@
+//! Embedding raster images as Image XObjects.
+//!
+//! JPEG files are embedded unchanged with `DCTDecode`, since PDF readers
+//! decode them natively. PNG files are decoded and re-compressed with
+//! `FlateDecode`, with any alpha channel split out into a soft mask.
+
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+use std::io::Cursor;
+
+/// An image added to a document.
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddedImage {
+    pub id: ObjectId,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Add an image (JPEG or PNG, detected from its signature) to `doc`.
+pub fn add_image(doc: &mut Document, bytes: &[u8]) -> Result<EmbeddedImage> {
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        add_jpeg(doc, bytes)
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        add_png(doc, bytes)
+    } else {
+        anyhow::bail!("Unsupported image format (expected JPEG or PNG)")
+    }
+}
+
+/// JPEG header fields needed to describe the image to a PDF reader.
+#[derive(Debug, PartialEq, Eq)]
+struct JpegInfo {
+    width: u32,
+    height: u32,
+    components: u8,
+    /// Adobe APP14 marker present; its CMYK data is stored inverted.
+    adobe: bool,
+}
+
+fn parse_jpeg(bytes: &[u8]) -> Result<JpegInfo> {
+    let mut pos = 2;
+    let mut adobe = false;
+    while pos + 4 <= bytes.len() {
+        if bytes[pos] != 0xFF {
+            anyhow::bail!("Malformed JPEG: expected marker at offset {}", pos);
+        }
+        let marker = bytes[pos + 1];
+        // Fill bytes and standalone markers carry no length.
+        if marker == 0xFF {
+            pos += 1;
+            continue;
+        }
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            pos += 2;
+            continue;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = bytes
+            .get(pos + 4..pos + 2 + len)
+            .context("Malformed JPEG: truncated segment")?;
+
+        if marker == 0xEE && segment.starts_with(b"Adobe") {
+            adobe = true;
+        }
+        // SOF0-SOF15, excluding DHT (C4), JPG (C8), and DAC (CC).
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            if segment.len() < 6 {
+                anyhow::bail!("Malformed JPEG: short frame header");
+            }
+            return Ok(JpegInfo {
+                height: u16::from_be_bytes([segment[1], segment[2]]) as u32,
+                width: u16::from_be_bytes([segment[3], segment[4]]) as u32,
+                components: segment[5],
+                adobe,
+            });
+        }
+        if marker == 0xDA {
+            break;
+        }
+        pos += 2 + len;
+    }
+    anyhow::bail!("Malformed JPEG: no frame header found")
+}
+
+fn add_jpeg(doc: &mut Document, bytes: &[u8]) -> Result<EmbeddedImage> {
+    let info = parse_jpeg(bytes)?;
+    let color_space = match info.components {
+        1 => "DeviceGray",
+        3 => "DeviceRGB",
+        4 => "DeviceCMYK",
+        n => anyhow::bail!("Unsupported JPEG with {} color components", n),
+    };
+
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => info.width as i64,
+        "Height" => info.height as i64,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8,
+        "Filter" => "DCTDecode",
+    };
+    if info.components == 4 && info.adobe {
+        dict.set(
+            "Decode",
+            Object::Array([1, 0, 1, 0, 1, 0, 1, 0].map(Object::Integer).to_vec()),
+        );
+    }
+
+    // Already compressed; mark the stream so lopdf won't try again.
+    let stream = Stream::new(dict, bytes.to_vec()).with_compression(false);
+    Ok(EmbeddedImage {
+        id: doc.add_object(stream),
+        width: info.width,
+        height: info.height,
+    })
+}
+
+fn add_png(doc: &mut Document, bytes: &[u8]) -> Result<EmbeddedImage> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    // Expand palettes, low bit depths, and tRNS chunks to 8-bit samples.
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().context("Failed to read PNG header")?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let frame = reader
+        .next_frame(&mut buf)
+        .context("Failed to decode PNG")?;
+    let data = &buf[..frame.buffer_size()];
+
+    let (color_space, channels, has_alpha) = match frame.color_type {
+        png::ColorType::Grayscale => ("DeviceGray", 1, false),
+        png::ColorType::GrayscaleAlpha => ("DeviceGray", 1, true),
+        png::ColorType::Rgb => ("DeviceRGB", 3, false),
+        png::ColorType::Rgba => ("DeviceRGB", 3, true),
+        png::ColorType::Indexed => anyhow::bail!("Unexpected indexed PNG after expansion"),
+    };
+
+    let (color, alpha) = if has_alpha {
+        split_alpha(data, channels)
+    } else {
+        (data.to_vec(), Vec::new())
+    };
+
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => frame.width as i64,
+        "Height" => frame.height as i64,
+        "ColorSpace" => color_space,
+        "BitsPerComponent" => 8,
+    };
+
+    // A fully opaque alpha channel needs no mask.
+    if has_alpha && alpha.iter().any(|&a| a != 255) {
+        let mut mask = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => frame.width as i64,
+                "Height" => frame.height as i64,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            alpha,
+        );
+        mask.compress().context("Failed to compress PNG alpha")?;
+        dict.set("SMask", doc.add_object(mask));
+    }
+
+    let mut stream = Stream::new(dict, color);
+    stream.compress().context("Failed to compress PNG data")?;
+    Ok(EmbeddedImage {
+        id: doc.add_object(stream),
+        width: frame.width,
+        height: frame.height,
+    })
+}
+
+/// Split interleaved color+alpha samples into separate color and alpha planes.
+fn split_alpha(data: &[u8], channels: usize) -> (Vec<u8>, Vec<u8>) {
+    let pixels = data.len() / (channels + 1);
+    let mut color = Vec::with_capacity(pixels * channels);
+    let mut alpha = Vec::with_capacity(pixels);
+    for pixel in data.chunks_exact(channels + 1) {
+        color.extend_from_slice(&pixel[..channels]);
+        alpha.push(pixel[channels]);
+    }
+    (color, alpha)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::inspect::stream_bytes;
+
+    /// Minimal JPEG header: SOI, APP0 stub, SOF0 for a 3x2 RGB image.
+    const JPEG_HEADER: &[u8] = &[
+        0xFF, 0xD8, // SOI
+        0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0 with two payload bytes
+        0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x02, 0x00, 0x03, 0x03, // SOF0
+        0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01,
+    ];
+
+    #[test]
+    fn test_parse_jpeg_frame_header() {
+        let info = parse_jpeg(JPEG_HEADER).unwrap();
+        assert_eq!(
+            info,
+            JpegInfo {
+                width: 3,
+                height: 2,
+                components: 3,
+                adobe: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_png_alpha_becomes_smask() {
+        let mut bytes = Vec::new();
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgba);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer
+                .write_image_data(&[255, 0, 0, 255, 0, 0, 255, 128])
+                .unwrap();
+        }
+
+        let mut doc = Document::with_version("1.5");
+        let image = add_image(&mut doc, &bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+
+        // lopdf leaves streams this small uncompressed.
+        let samples = |stream| stream_bytes(stream).unwrap();
+        let stream = doc.get_object(image.id).unwrap().as_stream().unwrap();
+        let mask_id = stream.dict.get(b"SMask").unwrap().as_reference().unwrap();
+        let mask = doc.get_object(mask_id).unwrap().as_stream().unwrap();
+        assert_eq!(samples(mask), vec![255, 128]);
+        assert_eq!(samples(stream), vec![255, 0, 0, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,145 @@
+//! Copying objects between documents.
+
+use crate::pdf::pages::{find_inherited, INHERITABLE_ATTRIBUTES};
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::HashMap;
+
+/// Copies objects from a source document into a target document.
+///
+/// Every indirect object reachable from what is imported is copied once and
+/// given a fresh ID in the target; repeated imports reuse earlier copies, so
+/// shared resources (fonts, images) are not duplicated.
+pub struct Importer<'a> {
+    source: &'a Document,
+    id_map: HashMap<ObjectId, ObjectId>,
+}
+
+impl<'a> Importer<'a> {
+    pub fn new(source: &'a Document) -> Self {
+        Importer {
+            source,
+            id_map: HashMap::new(),
+        }
+    }
+
+    /// The document objects are copied from.
+    pub fn source(&self) -> &'a Document {
+        self.source
+    }
+
+    /// Source object IDs imported so far, mapped to their IDs in the target.
+    pub fn id_map(&self) -> &HashMap<ObjectId, ObjectId> {
+        &self.id_map
+    }
+
+    /// Import an indirect object, returning its ID in the target.
+    pub fn import_id(&mut self, target: &mut Document, id: ObjectId) -> ObjectId {
+        if let Some(&new_id) = self.id_map.get(&id) {
+            return new_id;
+        }
+
+        // Reserve the new ID before recursing so reference cycles terminate.
+        let new_id = target.new_object_id();
+        self.id_map.insert(id, new_id);
+
+        let source = self.source;
+        let copied = match source.get_object(id) {
+            Ok(obj) => self.import_value(target, obj),
+            Err(_) => Object::Null,
+        };
+        target.objects.insert(new_id, copied);
+        new_id
+    }
+
+    /// Import a page as a standalone page dictionary without a parent.
+    ///
+    /// Attributes the page inherits in the source (resources, boxes,
+    /// rotation) are copied onto it, since it won't have the same ancestors
+    /// in the target. Attach it with [`DocumentBuilder::push_page`].
+    ///
+    /// [`DocumentBuilder::push_page`]: crate::pdf::builder::DocumentBuilder::push_page
+    pub fn import_page(&mut self, target: &mut Document, page_id: ObjectId) -> Result<ObjectId> {
+        let source = self.source;
+        let page = source.get_dictionary(page_id)?;
+        let new_id = self.import_id(target, page_id);
+
+        for &key in INHERITABLE_ATTRIBUTES {
+            if page.has(key) {
+                continue;
+            }
+            if let Some(value) = find_inherited(source, page_id, key) {
+                let value = self.import_value(target, value);
+                target.get_dictionary_mut(new_id)?.set(key.to_vec(), value);
+            }
+        }
+        Ok(new_id)
+    }
+
+    /// Deep-copy a direct value, importing any objects it references.
+    ///
+    /// `Parent` entries of page tree nodes are dropped: they point back into
+    /// the source page tree and would drag the whole tree along. Callers
+    /// importing pages attach them to a new parent themselves.
+    pub fn import_value(&mut self, target: &mut Document, obj: &Object) -> Object {
+        match obj {
+            Object::Reference(id) => Object::Reference(self.import_id(target, *id)),
+            Object::Array(items) => Object::Array(
+                items
+                    .iter()
+                    .map(|item| self.import_value(target, item))
+                    .collect(),
+            ),
+            Object::Dictionary(dict) => Object::Dictionary(self.import_dictionary(target, dict)),
+            Object::Stream(stream) => {
+                let mut stream = stream.clone();
+                stream.dict = self.import_dictionary(target, &stream.dict);
+                Object::Stream(stream)
+            }
+            other => other.clone(),
+        }
+    }
+
+    fn import_dictionary(&mut self, target: &mut Document, dict: &Dictionary) -> Dictionary {
+        let is_page_node = matches!(
+            dict.get(b"Type"),
+            Ok(Object::Name(name)) if name == b"Page" || name == b"Pages"
+        );
+
+        let mut copied = Dictionary::new();
+        for (key, value) in dict.iter() {
+            if is_page_node && key.as_slice() == b"Parent" {
+                continue;
+            }
+            copied.set(key.clone(), self.import_value(target, value));
+        }
+        copied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_import_page_materializes_inherited_attributes() {
+        let (source, page_ids) = text_document(&["one", "two"]);
+        let mut target = Document::with_version("1.5");
+        let mut importer = Importer::new(&source);
+
+        let first = importer.import_page(&mut target, page_ids[0]).unwrap();
+        let second = importer.import_page(&mut target, page_ids[1]).unwrap();
+
+        let first = target.get_dictionary(first).unwrap();
+        let second = target.get_dictionary(second).unwrap();
+        assert!(!first.has(b"Parent"));
+        assert!(first.has(b"MediaBox"));
+        // The shared resources dictionary is imported once.
+        assert_eq!(
+            first.get(b"Resources").unwrap(),
+            second.get(b"Resources").unwrap()
+        );
+        assert!(first.get(b"Resources").unwrap().as_reference().is_ok());
+    }
+}
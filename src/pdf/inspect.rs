@@ -0,0 +1,348 @@
+//! Human-readable rendering of raw PDF objects and content streams.
+//!
+//! Used by the `debug` commands to look at a file's structure without
+//! reaching for external tools like qpdf.
+
+use anyhow::{Context, Result};
+use lopdf::content::{Content, Operation};
+use lopdf::{Document, Object, StringFormat};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Render an object in PDF syntax, with dictionaries spread over multiple lines.
+pub fn format_object(obj: &Object) -> String {
+    let mut out = String::new();
+    write_object(&mut out, obj, 0, true);
+    out
+}
+
+/// Render an object in PDF syntax on a single line.
+pub fn format_object_inline(obj: &Object) -> String {
+    let mut out = String::new();
+    write_object(&mut out, obj, 0, false);
+    out
+}
+
+fn write_object(out: &mut String, obj: &Object, indent: usize, multiline: bool) {
+    match obj {
+        Object::Null => out.push_str("null"),
+        Object::Boolean(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Object::Integer(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        Object::Real(r) => {
+            let _ = write!(out, "{}", r);
+        }
+        Object::Name(name) => {
+            out.push('/');
+            out.push_str(&String::from_utf8_lossy(name));
+        }
+        Object::String(bytes, StringFormat::Hexadecimal) => {
+            out.push('<');
+            for b in bytes {
+                let _ = write!(out, "{:02X}", b);
+            }
+            out.push('>');
+        }
+        Object::String(bytes, StringFormat::Literal) => {
+            out.push('(');
+            for &b in bytes {
+                match b {
+                    b'(' | b')' | b'\\' => {
+                        out.push('\\');
+                        out.push(b as char);
+                    }
+                    b'\n' => out.push_str("\\n"),
+                    b'\r' => out.push_str("\\r"),
+                    b'\t' => out.push_str("\\t"),
+                    0x20..=0x7E => out.push(b as char),
+                    _ => {
+                        let _ = write!(out, "\\{:03o}", b);
+                    }
+                }
+            }
+            out.push(')');
+        }
+        Object::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                write_object(out, item, indent, multiline);
+            }
+            out.push(']');
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict, indent, multiline),
+        Object::Stream(stream) => {
+            write_dictionary(out, &stream.dict, indent, multiline);
+            let _ = write!(out, " stream ({} bytes)", stream.content.len());
+        }
+        Object::Reference((id, generation)) => {
+            let _ = write!(out, "{} {} R", id, generation);
+        }
+    }
+}
+
+fn write_dictionary(out: &mut String, dict: &lopdf::Dictionary, indent: usize, multiline: bool) {
+    if !multiline || dict.is_empty() {
+        out.push_str("<<");
+        for (key, value) in dict.iter() {
+            let _ = write!(out, " /{} ", String::from_utf8_lossy(key));
+            write_object(out, value, indent, false);
+        }
+        out.push_str(" >>");
+        return;
+    }
+
+    out.push_str("<<\n");
+    for (key, value) in dict.iter() {
+        let _ = write!(
+            out,
+            "{}/{} ",
+            " ".repeat(indent + 2),
+            String::from_utf8_lossy(key)
+        );
+        write_object(out, value, indent + 2, true);
+        out.push('\n');
+    }
+    out.push_str(&" ".repeat(indent));
+    out.push_str(">>");
+}
+
+/// Get the decoded bytes of a stream, applying its filters if it has any.
+pub fn stream_bytes(stream: &lopdf::Stream) -> Result<Vec<u8>> {
+    if stream.dict.has(b"Filter") {
+        stream
+            .decompressed_content()
+            .context("Failed to decode stream")
+    } else {
+        Ok(stream.content.clone())
+    }
+}
+
+/// Decode the content stream(s) of a page (1-indexed).
+pub fn page_content(doc: &Document, page: u32) -> Result<Content> {
+    let pages = doc.get_pages();
+    let page_id = *pages
+        .get(&page)
+        .with_context(|| format!("Page {} is out of range (1-{})", page, pages.len()))?;
+    doc.get_and_decode_page_content(page_id)
+        .with_context(|| format!("Failed to decode content stream of page {}", page))
+}
+
+/// Render content stream operations one per line, indented by nesting level.
+///
+/// Graphics state saves (`q`/`Q`), text objects (`BT`/`ET`), and marked
+/// content (`BMC`/`BDC`/`EMC`) each add a level of indentation.
+pub fn format_operations(operations: &[Operation]) -> String {
+    let mut out = String::new();
+    let mut depth: usize = 0;
+
+    for op in operations {
+        if matches!(op.operator.as_str(), "Q" | "ET" | "EMC") {
+            depth = depth.saturating_sub(1);
+        }
+
+        out.push_str(&"  ".repeat(depth));
+        for operand in &op.operands {
+            out.push_str(&format_object_inline(operand));
+            out.push(' ');
+        }
+        out.push_str(&op.operator);
+        out.push('\n');
+
+        if matches!(op.operator.as_str(), "q" | "BT" | "BMC" | "BDC") {
+            depth += 1;
+        }
+    }
+
+    out
+}
+
+/// Kind of cross-reference section a file revision ends with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XrefKind {
+    Table,
+    Stream,
+}
+
+/// Structural statistics about a PDF file.
+#[derive(Debug, Default)]
+pub struct DocumentStats {
+    pub version: String,
+    pub xref_kind: Option<XrefKind>,
+    /// Number of incremental updates appended after the original revision.
+    pub incremental_updates: usize,
+    pub object_count: usize,
+    /// Objects counted by `/Type` (or by object kind for untyped objects).
+    pub objects_by_type: BTreeMap<String, usize>,
+    /// Streams counted by filter name; unfiltered streams count as "none".
+    pub filters: BTreeMap<String, usize>,
+    /// The two trailer `/ID` entries, hex-encoded.
+    pub trailer_ids: Vec<String>,
+}
+
+/// Gather structural statistics from a parsed document and its raw bytes.
+///
+/// The raw bytes are needed for facts lopdf doesn't retain after parsing,
+/// such as how many revisions the file contains.
+pub fn document_stats(doc: &Document, raw: &[u8]) -> DocumentStats {
+    let mut stats = DocumentStats {
+        version: doc.version.clone(),
+        object_count: doc.objects.len(),
+        ..Default::default()
+    };
+
+    let startxrefs = find_all(raw, b"startxref");
+    stats.incremental_updates = startxrefs.len().saturating_sub(1);
+    stats.xref_kind = startxrefs
+        .last()
+        .and_then(|&pos| parse_startxref(raw, pos))
+        .map(|offset| {
+            if raw
+                .get(offset..)
+                .is_some_and(|rest| rest.starts_with(b"xref"))
+            {
+                XrefKind::Table
+            } else {
+                XrefKind::Stream
+            }
+        });
+
+    for obj in doc.objects.values() {
+        let (dict, kind) = match obj {
+            Object::Dictionary(dict) => (Some(dict), "dictionary"),
+            Object::Stream(stream) => (Some(&stream.dict), "stream"),
+            Object::Array(_) => (None, "array"),
+            Object::String(..) => (None, "string"),
+            Object::Name(_) => (None, "name"),
+            Object::Integer(_) | Object::Real(_) => (None, "number"),
+            Object::Boolean(_) => (None, "boolean"),
+            Object::Null => (None, "null"),
+            Object::Reference(_) => (None, "reference"),
+        };
+
+        let type_name = match dict.map(|d| d.get(b"Type")) {
+            Some(Ok(Object::Name(name))) => String::from_utf8_lossy(name).into_owned(),
+            _ => format!("({})", kind),
+        };
+        *stats.objects_by_type.entry(type_name).or_default() += 1;
+
+        if let Object::Stream(stream) = obj {
+            match stream.dict.get(b"Filter") {
+                Ok(Object::Name(name)) => {
+                    *stats
+                        .filters
+                        .entry(String::from_utf8_lossy(name).into_owned())
+                        .or_default() += 1;
+                }
+                Ok(Object::Array(names)) => {
+                    for name in names {
+                        if let Object::Name(name) = name {
+                            *stats
+                                .filters
+                                .entry(String::from_utf8_lossy(name).into_owned())
+                                .or_default() += 1;
+                        }
+                    }
+                }
+                _ => *stats.filters.entry("none".to_string()).or_default() += 1,
+            }
+        }
+    }
+
+    if let Ok(Object::Array(ids)) = doc.trailer.get(b"ID") {
+        for id in ids {
+            if let Object::String(bytes, _) = id {
+                stats
+                    .trailer_ids
+                    .push(bytes.iter().map(|b| format!("{:02x}", b)).collect());
+            }
+        }
+    }
+
+    stats
+}
+
+/// Find every occurrence of `needle` in `haystack`.
+pub fn find_all(haystack: &[u8], needle: &[u8]) -> Vec<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return Vec::new();
+    }
+    haystack
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, window)| *window == needle)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Parse the byte offset following a `startxref` keyword at `pos`.
+fn parse_startxref(raw: &[u8], pos: usize) -> Option<usize> {
+    let rest = raw.get(pos + b"startxref".len()..)?;
+    let digits: String = rest
+        .iter()
+        .skip_while(|b| b.is_ascii_whitespace())
+        .take_while(|b| b.is_ascii_digit())
+        .map(|&b| b as char)
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    #[test]
+    fn test_format_inline() {
+        let obj = Object::Array(vec![
+            Object::Integer(1),
+            Object::Name(b"XYZ".to_vec()),
+            Object::Reference((4, 0)),
+            Object::String(b"a(b)".to_vec(), StringFormat::Literal),
+            Object::String(vec![0xFE, 0xFF], StringFormat::Hexadecimal),
+        ]);
+        assert_eq!(
+            format_object_inline(&obj),
+            "[1 /XYZ 4 0 R (a\\(b\\)) <FEFF>]"
+        );
+    }
+
+    #[test]
+    fn test_format_dictionary_multiline() {
+        let obj = Object::Dictionary(dictionary! {
+            "Type" => "Page",
+            "Parent" => Object::Reference((2, 0)),
+        });
+        assert_eq!(
+            format_object(&obj),
+            "<<\n  /Type /Page\n  /Parent 2 0 R\n>>"
+        );
+    }
+
+    #[test]
+    fn test_find_all_and_startxref() {
+        let raw = b"%PDF-1.4\nxref\nstartxref\n9\n%%EOF\nstartxref\n  123\n%%EOF\n";
+        let positions = find_all(raw, b"startxref");
+        assert_eq!(positions.len(), 2);
+        assert_eq!(parse_startxref(raw, positions[0]), Some(9));
+        assert_eq!(parse_startxref(raw, positions[1]), Some(123));
+        assert!(raw[9..].starts_with(b"xref"));
+    }
+
+    #[test]
+    fn test_format_operations_indents() {
+        let ops = vec![
+            Operation::new("q", vec![]),
+            Operation::new("BT", vec![]),
+            Operation::new("Tj", vec![Object::string_literal("Hi")]),
+            Operation::new("ET", vec![]),
+            Operation::new("Q", vec![]),
+        ];
+        assert_eq!(format_operations(&ops), "q\n  BT\n    (Hi) Tj\n  ET\nQ\n");
+    }
+}
@@ -0,0 +1,235 @@
+//! Page geometry and standard font metrics for generated documents.
+
+use anyhow::Result;
+
+/// Points per millimetre.
+const MM: f32 = 72.0 / 25.4;
+
+/// Named paper sizes as `(name, width, height)` in points, portrait.
+const PAPER_SIZES: &[(&str, f32, f32)] = &[
+    ("a3", 297.0 * MM, 420.0 * MM),
+    ("a4", 210.0 * MM, 297.0 * MM),
+    ("a5", 148.0 * MM, 210.0 * MM),
+    ("letter", 612.0, 792.0),
+    ("legal", 612.0, 1008.0),
+];
+
+/// A page size in points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageSize {
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PageSize {
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+
+    /// Parse a paper name ("a4", "letter", optionally suffixed with
+    /// "-landscape") or explicit dimensions in points ("595x842").
+    pub fn parse(s: &str) -> Result<Self> {
+        let lower = s.trim().to_ascii_lowercase();
+        let (name, landscape) = match lower.strip_suffix("-landscape") {
+            Some(name) => (name, true),
+            None => (lower.as_str(), false),
+        };
+
+        let size = if let Some(&(_, width, height)) =
+            PAPER_SIZES.iter().find(|(paper, _, _)| *paper == name)
+        {
+            PageSize { width, height }
+        } else if let Some((w, h)) = name.split_once('x') {
+            let width: f32 = w.trim().parse().map_err(|_| invalid(s))?;
+            let height: f32 = h.trim().parse().map_err(|_| invalid(s))?;
+            if width <= 0.0 || height <= 0.0 {
+                return Err(invalid(s));
+            }
+            PageSize { width, height }
+        } else {
+            return Err(invalid(s));
+        };
+
+        Ok(if landscape {
+            PageSize {
+                width: size.height,
+                height: size.width,
+            }
+        } else {
+            size
+        })
+    }
+}
+
+/// Parse a length with an optional unit ("3mm", "0.125in", "0.5cm", "9pt")
+/// into points. A bare number is in points.
+pub fn parse_length(s: &str) -> Result<f32> {
+    let lower = s.trim().to_ascii_lowercase();
+    let (number, scale) = [("mm", MM), ("cm", 10.0 * MM), ("in", 72.0), ("pt", 1.0)]
+        .iter()
+        .find_map(|&(unit, scale)| lower.strip_suffix(unit).map(|n| (n, scale)))
+        .unwrap_or((lower.as_str(), 1.0));
+    match number.trim().parse::<f32>() {
+        Ok(value) if value >= 0.0 && value.is_finite() => Ok(value * scale),
+        _ => anyhow::bail!(
+            "Invalid length '{}': expected a number with an optional unit (mm, cm, in, pt)",
+            s
+        ),
+    }
+}
+
+/// The standard Type 1 fonts used for generated text. Every PDF reader
+/// provides these, so nothing needs to be embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StandardFont {
+    Helvetica,
+    HelveticaBold,
+    Courier,
+}
+
+impl StandardFont {
+    pub const ALL: [StandardFont; 3] = [
+        StandardFont::Helvetica,
+        StandardFont::HelveticaBold,
+        StandardFont::Courier,
+    ];
+
+    pub fn base_font(self) -> &'static str {
+        match self {
+            StandardFont::Helvetica => "Helvetica",
+            StandardFont::HelveticaBold => "Helvetica-Bold",
+            StandardFont::Courier => "Courier",
+        }
+    }
+
+    /// Advance width of a WinAnsi-encoded byte, in thousandths of the font size.
+    ///
+    /// ASCII and the common punctuation in 0x80-0x9F come from the Adobe
+    /// AFM files; other accented letters use a typical lowercase width,
+    /// which is close enough for line breaking.
+    pub fn char_width(self, byte: u8) -> u16 {
+        let table = match self {
+            StandardFont::Courier => return 600,
+            StandardFont::Helvetica => &HELVETICA_WIDTHS,
+            StandardFont::HelveticaBold => &HELVETICA_BOLD_WIDTHS,
+        };
+        match byte {
+            32..=126 => table[(byte - 32) as usize],
+            0x85 | 0x97 => 1000,
+            0x95 => 350,
+            0x96 => 556,
+            _ => 556,
+        }
+    }
+
+    /// Width of WinAnsi-encoded text at the given size, in points.
+    pub fn text_width(self, text: &[u8], size: f32) -> f32 {
+        let units: u32 = text.iter().map(|&b| self.char_width(b) as u32).sum();
+        units as f32 * size / 1000.0
+    }
+}
+
+#[rustfmt::skip]
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[rustfmt::skip]
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Characters WinAnsiEncoding places in 0x80-0x9F, where Latin-1 has controls.
+#[rustfmt::skip]
+const WIN_ANSI_EXTRAS: &[(char, u8)] = &[
+    ('€', 0x80), ('‚', 0x82), ('ƒ', 0x83), ('„', 0x84), ('…', 0x85), ('†', 0x86),
+    ('‡', 0x87), ('ˆ', 0x88), ('‰', 0x89), ('Š', 0x8A), ('‹', 0x8B), ('Œ', 0x8C),
+    ('Ž', 0x8E), ('‘', 0x91), ('’', 0x92), ('“', 0x93), ('”', 0x94), ('•', 0x95),
+    ('–', 0x96), ('—', 0x97), ('˜', 0x98), ('™', 0x99), ('š', 0x9A), ('›', 0x9B),
+    ('œ', 0x9C), ('ž', 0x9E), ('Ÿ', 0x9F),
+];
+
+/// Encode text as WinAnsiEncoding bytes, replacing unsupported characters
+/// with `?`.
+pub fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '\t' => b' ',
+            _ => WIN_ANSI_EXTRAS
+                .iter()
+                .find(|(extra, _)| *extra == c)
+                .map_or(b'?', |&(_, byte)| byte),
+        })
+        .collect()
+}
+
+fn invalid(s: &str) -> anyhow::Error {
+    let names: Vec<&str> = PAPER_SIZES.iter().map(|(name, _, _)| *name).collect();
+    anyhow::anyhow!(
+        "Invalid page size '{}': expected {} or WIDTHxHEIGHT in points",
+        s,
+        names.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_sizes() {
+        let a4 = PageSize::parse("A4").unwrap();
+        assert!((a4.width - 595.28).abs() < 0.01);
+        assert!((a4.height - 841.89).abs() < 0.01);
+        assert_eq!(PageSize::parse("letter").unwrap(), PageSize::LETTER);
+    }
+
+    #[test]
+    fn test_landscape_and_explicit() {
+        let size = PageSize::parse("letter-landscape").unwrap();
+        assert_eq!((size.width, size.height), (792.0, 612.0));
+        let size = PageSize::parse("400x300").unwrap();
+        assert_eq!((size.width, size.height), (400.0, 300.0));
+        assert!(PageSize::parse("b7").is_err());
+        assert!(PageSize::parse("0x10").is_err());
+    }
+
+    #[test]
+    fn test_parse_length() {
+        assert!((parse_length("3mm").unwrap() - 8.504).abs() < 0.001);
+        assert_eq!(parse_length("0.5in").unwrap(), 36.0);
+        assert_eq!(parse_length("9").unwrap(), 9.0);
+        assert_eq!(parse_length(" 9 PT").unwrap(), 9.0);
+        assert!(parse_length("-1mm").is_err());
+        assert!(parse_length("mm").is_err());
+    }
+
+    #[test]
+    fn test_text_width() {
+        // "Hi" in Helvetica: H = 722, i = 222.
+        let width = StandardFont::Helvetica.text_width(b"Hi", 10.0);
+        assert!((width - 9.44).abs() < 0.001);
+        assert_eq!(StandardFont::Courier.text_width(b"abc", 10.0), 18.0);
+    }
+
+    #[test]
+    fn test_encode_win_ansi() {
+        assert_eq!(
+            encode_win_ansi("caf\u{e9} \u{2014} \u{2022}"),
+            b"caf\xe9 \x97 \x95"
+        );
+        assert_eq!(encode_win_ansi("\u{3b1}"), b"?");
+    }
+}
@@ -0,0 +1,381 @@
+//! Repairing link annotations after pages are removed or copied between
+//! documents.
+//!
+//! A link names its target page by object reference, either directly or
+//! through a named destination in the catalog. Extracting pages leaves
+//! links to the pages that were left out pointing at nothing, and merging
+//! doesn't carry over the source's named destinations, so links that use
+//! them stop working.
+
+use crate::pdf::pages::resolve;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::HashMap;
+
+/// Deepest name tree node searched, which also stops cyclic trees.
+const MAX_TREE_DEPTH: usize = 32;
+
+/// What to do with links whose target page is not in the output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DanglingLinks {
+    /// Remove the link annotation.
+    Drop,
+    /// Keep the link as it is, and report its page.
+    Flag,
+}
+
+/// The document pages were copied from, for links whose named
+/// destinations only it can resolve.
+pub struct LinkSource<'a> {
+    pub doc: &'a Document,
+    /// Source object IDs to the IDs of their copies.
+    pub ids: &'a HashMap<ObjectId, ObjectId>,
+}
+
+/// What [`fix_links`] changed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LinkFixup {
+    /// Links whose named destination was replaced by an explicit one.
+    pub rewritten: usize,
+    /// Links removed because their target page is gone.
+    pub dropped: usize,
+    /// Pages (1-indexed) with links kept pointing at pages that are gone.
+    pub flagged: Vec<u32>,
+}
+
+impl LinkFixup {
+    pub fn extend(&mut self, other: LinkFixup) {
+        self.rewritten += other.rewritten;
+        self.dropped += other.dropped;
+        self.flagged.extend(other.flagged);
+    }
+}
+
+/// Where a link goes, as far as fixing it is concerned.
+enum Target {
+    /// A page in the output, or somewhere this pass doesn't touch (a URI,
+    /// another file, a name that never resolved).
+    Fine,
+    /// A page in the output, once the named destination is replaced by
+    /// this explicit one.
+    Rewrite(Vec<Object>),
+    /// A page that is not in the output.
+    Gone,
+}
+
+/// Check the link annotations on `pages` (page number and ID), which are
+/// also the only pages their links may still point to.
+///
+/// Links to any other page are dropped or flagged. Named destinations
+/// that `doc` can't resolve are looked up in `source` and replaced by
+/// explicit destinations to the copied page.
+pub fn fix_links(
+    doc: &mut Document,
+    pages: &[(u32, ObjectId)],
+    source: Option<&LinkSource>,
+    dangling: DanglingLinks,
+) -> LinkFixup {
+    let mut fixup = LinkFixup::default();
+    for &(page_number, page_id) in pages {
+        let annots = match doc
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Annots"))
+        {
+            Ok(annots) => match resolve(doc, annots) {
+                Object::Array(annots) => annots.clone(),
+                _ => continue,
+            },
+            Err(_) => continue,
+        };
+
+        let dropped_before = fixup.dropped;
+        let mut kept = Vec::with_capacity(annots.len());
+        let mut rewrites = Vec::new();
+        let mut flagged = false;
+        for annot in annots {
+            let dict = match &annot {
+                Object::Reference(id) => doc.get_dictionary(*id).ok(),
+                Object::Dictionary(dict) => Some(dict),
+                _ => None,
+            };
+            let target = match dict {
+                Some(dict) if is_link(dict) => link_target(doc, dict, pages, source),
+                _ => Target::Fine,
+            };
+            match (target, dangling) {
+                (Target::Fine, _) => kept.push(annot),
+                (Target::Rewrite(dest), _) => {
+                    rewrites.push((kept.len(), dest));
+                    kept.push(annot);
+                }
+                (Target::Gone, DanglingLinks::Drop) => fixup.dropped += 1,
+                (Target::Gone, DanglingLinks::Flag) => {
+                    flagged = true;
+                    kept.push(annot);
+                }
+            }
+        }
+        if flagged {
+            fixup.flagged.push(page_number);
+        }
+
+        if fixup.dropped == dropped_before && rewrites.is_empty() {
+            continue;
+        }
+        fixup.rewritten += rewrites.len();
+        for (index, dest) in rewrites {
+            let dict = match &mut kept[index] {
+                Object::Reference(id) => doc.get_dictionary_mut(*id).ok(),
+                Object::Dictionary(dict) => Some(dict),
+                _ => None,
+            };
+            if let Some(dict) = dict {
+                dict.remove(b"A");
+                dict.set("Dest", dest);
+            }
+        }
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            if kept.is_empty() {
+                page.remove(b"Annots");
+            } else {
+                page.set("Annots", kept);
+            }
+        }
+    }
+    fixup
+}
+
+/// [`fix_links`] for every page of a document cut down from a larger one,
+/// e.g. by [`PdfDocument::extract_pages`]. Objects only dropped links
+/// referred to, such as the removed pages themselves, are pruned.
+///
+/// [`PdfDocument::extract_pages`]: crate::pdf::PdfDocument::extract_pages
+pub fn fix_extracted_links(doc: &mut Document, dangling: DanglingLinks) -> LinkFixup {
+    let pages: Vec<(u32, ObjectId)> = doc.get_pages().into_iter().collect();
+    let fixup = fix_links(doc, &pages, None, dangling);
+    if fixup.dropped > 0 {
+        doc.prune_objects();
+    }
+    fixup
+}
+
+fn is_link(annot: &Dictionary) -> bool {
+    matches!(annot.get(b"Subtype"), Ok(Object::Name(subtype)) if subtype == b"Link")
+}
+
+fn link_target(
+    doc: &Document,
+    link: &Dictionary,
+    pages: &[(u32, ObjectId)],
+    source: Option<&LinkSource>,
+) -> Target {
+    let dest = match link.get(b"Dest") {
+        Ok(dest) => resolve(doc, dest),
+        Err(_) => {
+            let action = link
+                .get(b"A")
+                .ok()
+                .and_then(|action| resolve(doc, action).as_dict().ok());
+            let Some(action) = action else {
+                return Target::Fine;
+            };
+            if !matches!(action.get(b"S"), Ok(Object::Name(s)) if s == b"GoTo") {
+                return Target::Fine;
+            }
+            match action.get(b"D") {
+                Ok(dest) => resolve(doc, dest),
+                Err(_) => return Target::Fine,
+            }
+        }
+    };
+    let is_output_page = |id: &ObjectId| pages.iter().any(|&(_, page_id)| page_id == *id);
+
+    let name = match dest {
+        Object::Name(name) | Object::String(name, _) => name,
+        Object::Array(dest) => {
+            return match dest.first() {
+                Some(Object::Reference(id)) if !is_output_page(id) => Target::Gone,
+                _ => Target::Fine,
+            };
+        }
+        _ => return Target::Fine,
+    };
+    if let Some(dest) = named_destination(doc, name) {
+        return match dest.first() {
+            Some(Object::Reference(id)) if !is_output_page(id) => Target::Gone,
+            _ => Target::Fine,
+        };
+    }
+    let Some(source) = source else {
+        return Target::Fine;
+    };
+    let Some(mut dest) = named_destination(source.doc, name) else {
+        return Target::Fine;
+    };
+    match dest.first().and_then(|page| page.as_reference().ok()) {
+        Some(id) => match source.ids.get(&id) {
+            Some(copy) if is_output_page(copy) => {
+                dest[0] = Object::Reference(*copy);
+                Target::Rewrite(dest)
+            }
+            _ => Target::Gone,
+        },
+        None => Target::Fine,
+    }
+}
+
+/// The explicit destination array a named destination stands for, from
+/// the catalog's `Names` tree or the older `Dests` dictionary.
+pub(crate) fn named_destination(doc: &Document, name: &[u8]) -> Option<Vec<Object>> {
+    let catalog = doc.catalog().ok()?;
+    let from_tree = catalog
+        .get(b"Names")
+        .ok()
+        .and_then(|names| resolve(doc, names).as_dict().ok())
+        .and_then(|names| names.get(b"Dests").ok())
+        .and_then(|tree| search_name_tree(doc, resolve(doc, tree), name, 0));
+    let dest = from_tree.or_else(|| {
+        catalog
+            .get(b"Dests")
+            .ok()
+            .and_then(|dests| resolve(doc, dests).as_dict().ok())
+            .and_then(|dests| dests.get(name).ok())
+    })?;
+
+    // Either the array itself or a dictionary holding it under `D`.
+    let dest = match resolve(doc, dest) {
+        Object::Dictionary(dict) => resolve(doc, dict.get(b"D").ok()?),
+        dest => dest,
+    };
+    match dest {
+        Object::Array(dest) => Some(dest.clone()),
+        _ => None,
+    }
+}
+
+fn search_name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Object,
+    name: &[u8],
+    depth: usize,
+) -> Option<&'a Object> {
+    if depth > MAX_TREE_DEPTH {
+        return None;
+    }
+    let node = node.as_dict().ok()?;
+    if let Ok(Object::Array(names)) = node.get(b"Names") {
+        for pair in names.chunks_exact(2) {
+            if matches!(&pair[0], Object::String(key, _) if key == name) {
+                return Some(&pair[1]);
+            }
+        }
+    }
+    if let Ok(Object::Array(kids)) = node.get(b"Kids") {
+        for kid in kids {
+            if let Some(found) = search_name_tree(doc, resolve(doc, kid), name, depth + 1) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::import::Importer;
+    use crate::pdf::testing::text_document;
+    use lopdf::dictionary;
+
+    fn link(dest: Object) -> Object {
+        Object::Dictionary(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+            "Dest" => dest,
+        })
+    }
+
+    #[test]
+    fn test_links_to_removed_pages() {
+        let (mut doc, page_ids) = text_document(&["one", "two", "three"]);
+        let uri = Object::Dictionary(dictionary! {
+            "Subtype" => "Link",
+            "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com/") },
+        });
+        doc.get_dictionary_mut(page_ids[0]).unwrap().set(
+            "Annots",
+            vec![
+                link(vec![page_ids[1].into(), "Fit".into()].into()),
+                link(vec![page_ids[2].into(), "Fit".into()].into()),
+                uri,
+            ],
+        );
+        // As if page three had been extracted away.
+        let kept = [(1, page_ids[0]), (2, page_ids[1])];
+
+        let mut flagged = doc.clone();
+        let fixup = fix_links(&mut flagged, &kept, None, DanglingLinks::Flag);
+        assert_eq!(fixup.flagged, vec![1]);
+        assert_eq!(fixup.dropped, 0);
+
+        let fixup = fix_links(&mut doc, &kept, None, DanglingLinks::Drop);
+        assert_eq!(fixup.dropped, 1);
+        let annots = doc
+            .get_dictionary(page_ids[0])
+            .unwrap()
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        assert_eq!(annots.len(), 2);
+        assert!(annots[1].as_dict().unwrap().has(b"A"));
+    }
+
+    #[test]
+    fn test_named_destinations_from_source() {
+        let (mut source, page_ids) = text_document(&["one", "two"]);
+        let names = dictionary! {
+            "Dests" => dictionary! {
+                "Names" => vec![
+                    Object::string_literal("chapter-2"),
+                    vec![page_ids[1].into(), "Fit".into()].into(),
+                ],
+            },
+        };
+        source.catalog_mut().unwrap().set("Names", names);
+        source
+            .get_dictionary_mut(page_ids[0])
+            .unwrap()
+            .set("Annots", vec![link(Object::string_literal("chapter-2"))]);
+
+        // Copy the pages into a document without the names.
+        let mut target = Document::with_version("1.5");
+        let mut importer = Importer::new(&source);
+        let copies: Vec<(u32, ObjectId)> = page_ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| (i as u32 + 1, importer.import_page(&mut target, id).unwrap()))
+            .collect();
+        let link_source = LinkSource {
+            doc: &source,
+            ids: importer.id_map(),
+        };
+        let fixup = fix_links(
+            &mut target,
+            &copies,
+            Some(&link_source),
+            DanglingLinks::Drop,
+        );
+        assert_eq!(fixup.rewritten, 1);
+
+        let annots = target
+            .get_dictionary(copies[0].1)
+            .unwrap()
+            .get(b"Annots")
+            .unwrap()
+            .as_array()
+            .unwrap();
+        let dest = annots[0].as_dict().unwrap().get(b"Dest").unwrap();
+        assert_eq!(dest.as_array().unwrap()[0], Object::Reference(copies[1].1));
+    }
+}
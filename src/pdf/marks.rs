@@ -0,0 +1,189 @@
+//! Printer's marks: crop and registration marks drawn outside the trim box.
+//!
+//! Each page is enlarged around its trim box to make room for the bleed and
+//! the marks, and gets explicit `TrimBox` and `BleedBox` entries so imposition
+//! and preflight tools know where to cut.
+
+use crate::pdf::pages::{page_box, rect_object, Rect};
+use crate::pdf::xobject::{add_content, Layer};
+use anyhow::{Context, Result};
+use lopdf::{Document, ObjectId};
+use std::fmt::Write;
+
+/// Length of each crop mark line, in points.
+const MARK_LENGTH: f32 = 18.0;
+
+/// Space between the bleed edge and the start of the marks, so they never
+/// print on the bleed.
+const MARK_OFFSET: f32 = 3.0;
+
+/// Radius of the circle in a registration target.
+const TARGET_RADIUS: f32 = 5.0;
+
+/// Stroke width for marks; hairlines are the usual choice.
+const LINE_WIDTH: f32 = 0.25;
+
+/// Which marks to draw, and how far artwork extends past the trim.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marks {
+    pub crop: bool,
+    pub registration: bool,
+    /// Bleed in points.
+    pub bleed: f32,
+}
+
+impl Marks {
+    /// Distance from the trim edge to the outer edge of the page.
+    fn margin(&self) -> f32 {
+        if self.crop || self.registration {
+            self.bleed + MARK_OFFSET + MARK_LENGTH
+        } else {
+            self.bleed
+        }
+    }
+}
+
+/// Add marks to a page, enlarging its MediaBox and setting TrimBox and
+/// BleedBox.
+///
+/// The trim is the page's existing TrimBox, which defaults to its CropBox, so
+/// running this on an already-marked page keeps the same trim.
+pub fn add_marks(doc: &mut Document, page_id: ObjectId, marks: &Marks) -> Result<()> {
+    let trim = page_box(doc, page_id, b"TrimBox");
+    let bleed = grow(trim, marks.bleed);
+    let media = grow(trim, marks.margin());
+
+    let page = doc
+        .get_dictionary_mut(page_id)
+        .context("Page object is not a dictionary")?;
+    page.set("MediaBox", rect_object(media));
+    // A CropBox (possibly inherited) would hide the marks.
+    page.set("CropBox", rect_object(media));
+    page.set("BleedBox", rect_object(bleed));
+    page.set("TrimBox", rect_object(trim));
+
+    let ops = mark_ops(trim, marks);
+    if !ops.is_empty() {
+        add_content(doc, page_id, ops.into_bytes(), Layer::Over)?;
+    }
+    Ok(())
+}
+
+/// Content stream operators drawing the marks around `trim`.
+///
+/// Marks are stroked in 100% of all four process inks, so they appear on
+/// every separation.
+fn mark_ops(trim: Rect, marks: &Marks) -> String {
+    if !marks.crop && !marks.registration {
+        return String::new();
+    }
+    let [llx, lly, urx, ury] = trim;
+    let near = marks.bleed + MARK_OFFSET;
+    let far = near + MARK_LENGTH;
+
+    let mut ops = format!("\nq {} w 1 1 1 1 K\n", LINE_WIDTH);
+    if marks.crop {
+        for (x, dx) in [(llx, -1.0), (urx, 1.0)] {
+            for (y, dy) in [(lly, -1.0), (ury, 1.0)] {
+                line(&mut ops, x + dx * near, y, x + dx * far, y);
+                line(&mut ops, x, y + dy * near, x, y + dy * far);
+            }
+        }
+    }
+    if marks.registration {
+        let middle = (near + far) / 2.0;
+        let (cx, cy) = ((llx + urx) / 2.0, (lly + ury) / 2.0);
+        for (x, y) in [
+            (cx, lly - middle),
+            (cx, ury + middle),
+            (llx - middle, cy),
+            (urx + middle, cy),
+        ] {
+            target(&mut ops, x, y);
+        }
+    }
+    ops.push_str("Q\n");
+    ops
+}
+
+fn line(ops: &mut String, x1: f32, y1: f32, x2: f32, y2: f32) {
+    let _ = writeln!(ops, "{} {} m {} {} l S", x1, y1, x2, y2);
+}
+
+/// A registration target: a circle with crosshairs reaching past it.
+fn target(ops: &mut String, x: f32, y: f32) {
+    let r = TARGET_RADIUS;
+    // Each quarter circle is a Bézier curve from one axis point to the next.
+    const K: f32 = 0.5523;
+    let axes = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0), (1.0, 0.0)];
+    let _ = write!(ops, "{} {} m", x + r, y);
+    for pair in axes.windows(2) {
+        let [(px, py), (qx, qy)] = [pair[0], pair[1]];
+        let _ = write!(
+            ops,
+            " {} {} {} {} {} {} c",
+            x + r * (px + K * qx),
+            y + r * (py + K * qy),
+            x + r * (qx + K * px),
+            y + r * (qy + K * py),
+            x + r * qx,
+            y + r * qy
+        );
+    }
+    ops.push_str(" S\n");
+    let reach = r + 3.0;
+    line(ops, x - reach, y, x + reach, y);
+    line(ops, x, y - reach, x, y + reach);
+}
+
+fn grow(rect: Rect, by: f32) -> Rect {
+    [rect[0] - by, rect[1] - by, rect[2] + by, rect[3] + by]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_add_marks_sets_boxes() {
+        let (mut doc, pages) = text_document(&["page"]);
+        let marks = Marks {
+            crop: true,
+            registration: true,
+            bleed: 9.0,
+        };
+        add_marks(&mut doc, pages[0], &marks).unwrap();
+
+        let margin = 9.0 + MARK_OFFSET + MARK_LENGTH;
+        let [llx, lly, urx, ury] = page_box(&doc, pages[0], b"TrimBox");
+        assert_eq!(
+            page_box(&doc, pages[0], b"BleedBox"),
+            [llx - 9.0, lly - 9.0, urx + 9.0, ury + 9.0]
+        );
+        assert_eq!(
+            page_box(&doc, pages[0], b"MediaBox"),
+            [llx - margin, lly - margin, urx + margin, ury + margin]
+        );
+
+        let content = String::from_utf8(doc.get_page_content(pages[0]).unwrap()).unwrap();
+        // Eight crop mark lines, plus two crosshair lines per target.
+        assert_eq!(content.matches(" l S").count(), 8 + 4 * 2);
+        assert_eq!(content.matches(" c S").count(), 4);
+        assert_eq!(content.matches(" c").count(), 4 * 4);
+        assert!(content.contains("Tj"), "original content kept");
+    }
+
+    #[test]
+    fn test_crop_marks_stay_outside_bleed() {
+        let marks = Marks {
+            crop: true,
+            registration: false,
+            bleed: 9.0,
+        };
+        let ops = mark_ops([0.0, 0.0, 100.0, 200.0], &marks);
+        // The lower-left horizontal mark runs left from just past the bleed.
+        assert!(ops.contains("-12 0 m -30 0 l S"), "{}", ops);
+        assert!(ops.contains("100 212 m 100 230 l S"), "{}", ops);
+    }
+}
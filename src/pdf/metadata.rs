@@ -0,0 +1,163 @@
+//! The document information dictionary (`/Info`) of generated files.
+//!
+//! Outputs built from a copy of an input (extract) start with the input's
+//! metadata, and outputs built from scratch (merge) start with none. A
+//! [`MetadataPolicy`] makes that choice explicit, and every output gets a
+//! `ModDate` and `Producer` that describe the file actually written.
+
+use crate::pdf::document::{format_pdf_date_utc, info_dict_mut};
+use crate::pdf::pages::resolve;
+use crate::pdf::strings::encode_text_string;
+use lopdf::{Dictionary, Document, Object, StringFormat};
+
+/// What goes in an output's `/Info` besides `ModDate` and `Producer`.
+#[derive(Default)]
+pub struct MetadataPolicy<'a> {
+    /// Replace the output's metadata with this document's.
+    pub copy_from: Option<&'a Document>,
+    /// Start from empty metadata.
+    pub clear: bool,
+    pub title: Option<String>,
+}
+
+/// The `Producer` written to outputs.
+pub fn producer() -> String {
+    format!("inpdf {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Rewrite `doc`'s `/Info` according to `policy`, with `ModDate` set to
+/// `now` (Unix seconds).
+///
+/// Replacing or clearing the metadata, or setting a title, also removes the
+/// catalog's XMP `/Metadata` stream, which would otherwise contradict the
+/// new values (viewers prefer XMP when both are present).
+pub fn apply_metadata(doc: &mut Document, policy: &MetadataPolicy, now: i64) {
+    let replacement = if policy.clear {
+        Some(Dictionary::new())
+    } else {
+        policy.copy_from.map(info_of)
+    };
+    if replacement.is_some() || policy.title.is_some() {
+        if let Ok(catalog) = doc.catalog_mut() {
+            catalog.remove(b"Metadata");
+        }
+    }
+
+    if info_dict_mut(doc).is_none() {
+        let id = doc.add_object(Dictionary::new());
+        doc.trailer.set("Info", id);
+    }
+    let info = info_dict_mut(doc).expect("Info was just added");
+    if let Some(replacement) = replacement {
+        *info = replacement;
+    }
+    if let Some(title) = &policy.title {
+        info.set("Title", encode_text_string(title));
+    }
+    info.set(
+        "ModDate",
+        Object::String(format_pdf_date_utc(now).into_bytes(), StringFormat::Literal),
+    );
+    info.set("Producer", encode_text_string(&producer()));
+}
+
+/// A document's `/Info` entries, with references resolved so they can be
+/// written into another document.
+fn info_of(doc: &Document) -> Dictionary {
+    let info = match doc.trailer.get(b"Info").map(|info| resolve(doc, info)) {
+        Ok(Object::Dictionary(info)) => info,
+        _ => return Dictionary::new(),
+    };
+    let mut copied = Dictionary::new();
+    for (key, value) in info.iter() {
+        let value = resolve(doc, value);
+        // Only plain values; anything nested has no meaning in /Info.
+        if matches!(
+            value,
+            Object::String(..) | Object::Name(_) | Object::Integer(_) | Object::Boolean(_)
+        ) {
+            copied.set(key.clone(), value.clone());
+        }
+    }
+    copied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::document::get_string_from_dict;
+    use crate::pdf::testing::text_document;
+    use lopdf::dictionary;
+
+    fn info(doc: &Document) -> &Dictionary {
+        let id = doc.trailer.get(b"Info").unwrap().as_reference().unwrap();
+        doc.get_dictionary(id).unwrap()
+    }
+
+    fn with_info(entries: Dictionary) -> Document {
+        let (mut doc, _) = text_document(&["x"]);
+        let id = doc.add_object(entries);
+        doc.trailer.set("Info", id);
+        doc
+    }
+
+    #[test]
+    fn test_apply_metadata() {
+        // A new document gets an Info dictionary.
+        let (mut doc, _) = text_document(&["x"]);
+        apply_metadata(&mut doc, &MetadataPolicy::default(), 0);
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"ModDate").as_deref(),
+            Some("D:19700101000000Z")
+        );
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"Producer"),
+            Some(producer())
+        );
+
+        // Existing entries are kept unless cleared or replaced.
+        let original = dictionary! {
+            "Title" => Object::string_literal("Original"),
+            "Author" => Object::string_literal("Ada"),
+        };
+        let mut doc = with_info(original.clone());
+        apply_metadata(&mut doc, &MetadataPolicy::default(), 0);
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"Author").as_deref(),
+            Some("Ada")
+        );
+
+        let mut doc = with_info(original.clone());
+        let policy = MetadataPolicy {
+            clear: true,
+            title: Some("Excerpt".to_string()),
+            ..Default::default()
+        };
+        apply_metadata(&mut doc, &policy, 0);
+        assert_eq!(get_string_from_dict(info(&doc), b"Author"), None);
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"Title").as_deref(),
+            Some("Excerpt")
+        );
+
+        let source = with_info(dictionary! {
+            "Title" => Object::string_literal("Source"),
+            "Subject" => Object::string_literal("Reports"),
+        });
+        let mut doc = with_info(original);
+        let policy = MetadataPolicy {
+            copy_from: Some(&source),
+            ..Default::default()
+        };
+        apply_metadata(&mut doc, &policy, 0);
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"Title").as_deref(),
+            Some("Source")
+        );
+        assert_eq!(
+            get_string_from_dict(info(&doc), b"Subject").as_deref(),
+            Some("Reports")
+        );
+        assert_eq!(get_string_from_dict(info(&doc), b"Author"), None);
+    }
+}
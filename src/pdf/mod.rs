@@ -1,8 +1,51 @@
+pub mod accessibility;
+pub mod annotations;
+pub mod attachments;
+pub mod boxes;
+pub mod builder;
 pub mod cache;
+pub mod classify;
+pub mod cleanup;
+pub mod colors;
 pub mod document;
+pub mod embed;
+pub mod fdf;
+pub mod figures;
+pub mod files;
+pub mod fingerprint;
+pub mod forms;
+pub mod glyphs;
+pub mod images;
+pub mod import;
+pub mod inspect;
+pub mod layout;
+pub mod links;
+pub mod marks;
+pub mod metadata;
+pub mod ocr;
+pub mod orientation;
 pub mod page_labels;
+pub mod pages;
+pub mod pii;
+pub mod portfolio;
+pub mod printed_toc;
+pub mod quality;
+pub mod recover;
+pub mod redact;
+pub mod render;
+pub mod replace;
+pub mod revisions;
+pub mod strings;
+pub mod structure;
+pub mod svg;
+pub mod tagged;
+#[cfg(test)]
+pub mod testing;
 pub mod text;
 pub mod toc;
+pub mod typeset;
+pub mod verify;
+pub mod xobject;
 
 #[allow(unused_imports)]
 pub use cache::{get_cached_pdf, CachedPdf};
@@ -0,0 +1,270 @@
+//! Optical character recognition with tesseract.
+//!
+//! Pages are rendered with pdftoppm (see [`crate::pdf::render`]) and piped
+//! to the `tesseract` command, which can write plain text or keep word
+//! coordinates and confidences as hOCR, ALTO XML, or TSV. Coordinates are
+//! in pixels of the rendered page, at [`OcrOptions::dpi`].
+
+use crate::pdf::render::render_page;
+use crate::pdf::text::GrepMatch;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub struct OcrOptions {
+    /// Tesseract language(s), e.g. "eng" or "deu+eng".
+    pub language: String,
+    /// Resolution pages are rendered at; tesseract works best around 300.
+    pub dpi: u32,
+}
+
+impl Default for OcrOptions {
+    fn default() -> Self {
+        OcrOptions {
+            language: "eng".to_string(),
+            dpi: 300,
+        }
+    }
+}
+
+/// What tesseract writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OcrOutput {
+    Text,
+    Hocr,
+    Alto,
+    Tsv,
+}
+
+impl OcrOutput {
+    /// The tesseract config file selecting this output.
+    fn config(self) -> Option<&'static str> {
+        match self {
+            OcrOutput::Text => None,
+            OcrOutput::Hocr => Some("hocr"),
+            OcrOutput::Alto => Some("alto"),
+            OcrOutput::Tsv => Some("tsv"),
+        }
+    }
+
+    /// File extension for saved results.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OcrOutput::Text => "txt",
+            OcrOutput::Hocr => "hocr",
+            OcrOutput::Alto => "xml",
+            OcrOutput::Tsv => "tsv",
+        }
+    }
+}
+
+/// Recognize the text of one page (1-indexed).
+pub fn ocr_page(path: &Path, page: u32, output: OcrOutput, options: &OcrOptions) -> Result<String> {
+    let png = render_page(path, page, options.dpi)?.to_png()?;
+    let what = format!("page {} of {}", page, path.display());
+    tesseract(png, output, None, options, &what)
+}
+
+/// Recognize a single character in an image, such as one glyph cut out of
+/// a rendered page. Returns an empty string if tesseract sees none.
+pub fn ocr_glyph(png: Vec<u8>, options: &OcrOptions) -> Result<String> {
+    // Page segmentation mode 10 treats the image as one character.
+    let text = tesseract(png, OcrOutput::Text, Some("10"), options, "a glyph")?;
+    Ok(text.trim().to_string())
+}
+
+fn tesseract(
+    png: Vec<u8>,
+    output: OcrOutput,
+    psm: Option<&str>,
+    options: &OcrOptions,
+    what: &str,
+) -> Result<String> {
+    let mut command = Command::new("tesseract");
+    command
+        .args(["stdin", "stdout"])
+        .args(["-l", &options.language])
+        .args(["--dpi", &options.dpi.to_string()]);
+    if let Some(psm) = psm {
+        command.args(["--psm", psm]);
+    }
+    if let Some(config) = output.config() {
+        command.arg(config);
+    }
+    let child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+            "OCR requires tesseract (https://github.com/tesseract-ocr/tesseract) to be installed and on PATH"
+        ),
+        Err(e) => return Err(e).context("Failed to run tesseract"),
+    };
+
+    // Write from another thread so a full stdout pipe can't deadlock us.
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&png));
+    let result = child
+        .wait_with_output()
+        .context("Failed to run tesseract")?;
+    writer
+        .join()
+        .expect("tesseract writer panicked")
+        .context("Failed to send the image to tesseract")?;
+
+    if !result.status.success() {
+        anyhow::bail!(
+            "tesseract failed on {}: {}",
+            what,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&result.stdout).into_owned())
+}
+
+/// A recognized word.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcrWord {
+    pub text: String,
+    /// Recognition confidence, 0-100.
+    pub confidence: f32,
+}
+
+/// Group the words of tesseract's TSV output into lines.
+pub fn parse_tsv(tsv: &str) -> Vec<Vec<OcrWord>> {
+    let mut lines: Vec<Vec<OcrWord>> = Vec::new();
+    let mut current_line = None;
+    // Columns: level page_num block_num par_num line_num word_num left top
+    // width height conf text. Level 5 rows are words.
+    for row in tsv.lines().skip(1) {
+        let fields: Vec<&str> = row.split('\t').collect();
+        if fields.len() < 12 || fields[0] != "5" {
+            continue;
+        }
+        let text = fields[11].trim();
+        let Ok(confidence) = fields[10].parse::<f32>() else {
+            continue;
+        };
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+        let line = (fields[2], fields[3], fields[4]);
+        if current_line != Some(line) {
+            lines.push(Vec::new());
+            current_line = Some(line);
+        }
+        lines.last_mut().expect("line pushed").push(OcrWord {
+            text: text.to_string(),
+            confidence,
+        });
+    }
+    lines
+}
+
+/// OCR `pages` and search the recognized lines, recording the confidence
+/// of the least certain word in each match. At most `max_per_page` matches
+/// are kept from any one page.
+pub fn grep_ocr(
+    path: &Path,
+    pages: &[u32],
+    pattern: &Regex,
+    max_results: usize,
+    max_per_page: Option<usize>,
+    options: &OcrOptions,
+) -> Result<Vec<GrepMatch>> {
+    let mut matches = Vec::new();
+    for &page in pages {
+        let tsv = ocr_page(path, page, OcrOutput::Tsv, options)?;
+        let page_limit = matches
+            .len()
+            .saturating_add(max_per_page.unwrap_or(usize::MAX));
+        // Character offset of the line in the recognized lines joined by
+        // newlines.
+        let mut line_start = 0;
+        'lines: for (line_idx, words) in parse_tsv(&tsv).iter().enumerate() {
+            let text = words
+                .iter()
+                .map(|word| word.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" ");
+            for mat in pattern.find_iter(&text) {
+                if matches.len() >= page_limit {
+                    break 'lines;
+                }
+                let before = text[..mat.start()].chars().count() as u32;
+                matches.push(GrepMatch {
+                    page,
+                    line_number: line_idx as u32 + 1,
+                    text: text.clone(),
+                    match_start: mat.start() as u32,
+                    match_end: mat.end() as u32,
+                    page_start: line_start + before,
+                    page_end: line_start + before + mat.as_str().chars().count() as u32,
+                    attachment: None,
+                    ocr_confidence: Some(span_confidence(words, mat.start(), mat.end())),
+                    recovered: false,
+                });
+                if matches.len() >= max_results {
+                    return Ok(matches);
+                }
+            }
+            line_start += text.chars().count() as u32 + 1;
+        }
+    }
+    Ok(matches)
+}
+
+/// The lowest confidence among the words overlapping bytes `start..end` of
+/// the words joined with single spaces.
+fn span_confidence(words: &[OcrWord], start: usize, end: usize) -> f32 {
+    let mut offset = 0;
+    let mut lowest = f32::INFINITY;
+    for word in words {
+        let word_end = offset + word.text.len();
+        if offset < end.max(start + 1) && word_end > start {
+            lowest = lowest.min(word.confidence);
+        }
+        offset = word_end + 1;
+    }
+    if lowest.is_finite() {
+        lowest
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t2550\t3300\t-1\t
+4\t1\t1\t1\t1\t0\t300\t300\t900\t40\t-1\t
+5\t1\t1\t1\t1\t1\t300\t300\t200\t40\t96.5\tInvoice
+5\t1\t1\t1\t1\t2\t520\t300\t120\t40\t41.2\tT0tal
+5\t1\t1\t1\t2\t1\t300\t360\t200\t40\t90\tDue
+";
+
+    #[test]
+    fn test_parse_tsv() {
+        let lines = parse_tsv(TSV);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0][1].text, "T0tal");
+        assert_eq!(lines[0][1].confidence, 41.2);
+        assert_eq!(lines[1][0].text, "Due");
+    }
+
+    #[test]
+    fn test_span_confidence() {
+        let words = &parse_tsv(TSV)[0];
+        // "Invoice T0tal"
+        assert_eq!(span_confidence(words, 0, 7), 96.5);
+        assert_eq!(span_confidence(words, 3, 10), 41.2);
+        assert_eq!(span_confidence(words, 8, 13), 41.2);
+    }
+}
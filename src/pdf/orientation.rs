@@ -0,0 +1,195 @@
+//! Detecting sideways and upside-down pages from the direction of their text.
+//!
+//! Each glyph run's baseline direction is taken from the text and
+//! transformation matrices, so this works for born-digital pages and for
+//! scans with an OCR text layer (usually invisible text). Scans without a
+//! text layer have nothing to measure and are reported as such. Text inside
+//! form XObjects is not counted.
+
+use crate::pdf::pages::{find_inherited, number, resolve};
+use anyhow::{Context, Result};
+use lopdf::{Document, Object, ObjectId};
+
+/// Fewest characters needed before trusting a page's text direction.
+const MIN_CHARS: usize = 20;
+
+/// Share of characters that must run in the dominant direction.
+const MIN_SHARE: f32 = 0.66;
+
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// What a page's text says about how it should be turned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    /// Text reads upright with the page's current `/Rotate`.
+    Upright,
+    /// Setting `/Rotate` to this value would make the text upright.
+    Rotate(i64),
+    /// Too little text, or text running in mixed directions, to tell.
+    Unsure { chars: usize, share: f32 },
+}
+
+/// Characters drawn in each baseline direction, in user space: index 0 runs
+/// left to right, 1 bottom to top, 2 right to left, 3 top to bottom.
+pub fn text_directions(doc: &Document, page_id: ObjectId) -> Result<[usize; 4]> {
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .context("Failed to decode page content")?;
+
+    let mut counts = [0; 4];
+    let mut ctm = IDENTITY;
+    let mut stack = Vec::new();
+    let mut text_matrix = IDENTITY;
+    for op in &content.operations {
+        let operands: Vec<f32> = op.operands.iter().filter_map(number).collect();
+        match op.operator.as_str() {
+            "q" => stack.push(ctm),
+            "Q" => ctm = stack.pop().unwrap_or(IDENTITY),
+            "cm" if operands.len() == 6 => {
+                ctm = multiply(&operands[..6].try_into().expect("six operands"), &ctm)
+            }
+            "BT" => text_matrix = IDENTITY,
+            // Td, TD, and T* only translate, which doesn't change direction.
+            "Tm" if operands.len() == 6 => {
+                text_matrix = operands[..6].try_into().expect("six operands")
+            }
+            "Tj" | "'" | "\"" | "TJ" => {
+                let chars = shown_chars(op.operands.last());
+                if chars > 0 {
+                    let m = multiply(&text_matrix, &ctm);
+                    counts[quadrant(m[0], m[1])] += chars;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(counts)
+}
+
+/// Work out the `/Rotate` that makes a page's text upright.
+pub fn page_orientation(doc: &Document, page_id: ObjectId) -> Result<Orientation> {
+    let counts = text_directions(doc, page_id)?;
+    let rotate = find_inherited(doc, page_id, b"Rotate")
+        .and_then(|obj| resolve(doc, obj).as_i64().ok())
+        .unwrap_or(0);
+    Ok(orientation(counts, rotate))
+}
+
+fn orientation(counts: [usize; 4], rotate: i64) -> Orientation {
+    let chars: usize = counts.iter().sum();
+    let (dominant, &most) = counts
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, count)| count)
+        .expect("four directions");
+    let share = if chars == 0 {
+        0.0
+    } else {
+        most as f32 / chars as f32
+    };
+    if chars < MIN_CHARS || share < MIN_SHARE {
+        return Orientation::Unsure { chars, share };
+    }
+
+    // Text running counterclockwise by some angle reads upright once the
+    // page is shown turned clockwise by the same angle.
+    let wanted = dominant as i64 * 90;
+    if wanted == rotate.rem_euclid(360) {
+        Orientation::Upright
+    } else {
+        Orientation::Rotate(wanted)
+    }
+}
+
+/// The direction, to the nearest quarter turn counterclockwise, of the
+/// vector (dx, dy).
+fn quadrant(dx: f32, dy: f32) -> usize {
+    if dx.abs() >= dy.abs() {
+        if dx >= 0.0 {
+            0
+        } else {
+            2
+        }
+    } else if dy > 0.0 {
+        1
+    } else {
+        3
+    }
+}
+
+/// Characters in the string operand of a text-showing operator.
+///
+/// Multi-byte fonts make this an overestimate, but only the relative
+/// counts between directions matter.
+fn shown_chars(operand: Option<&Object>) -> usize {
+    match operand {
+        Some(Object::String(bytes, _)) => bytes.iter().filter(|b| !b.is_ascii_whitespace()).count(),
+        Some(Object::Array(items)) => items.iter().map(|item| shown_chars(Some(item))).sum(),
+        _ => 0,
+    }
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::Stream;
+
+    fn page_with(content: &str) -> (Document, ObjectId) {
+        let (mut doc, pages) = crate::pdf::testing::text_document(&["x"]);
+        let content_id = doc.add_object(Stream::new(lopdf::Dictionary::new(), content.into()));
+        doc.get_dictionary_mut(pages[0])
+            .unwrap()
+            .set("Contents", content_id);
+        (doc, pages[0])
+    }
+
+    #[test]
+    fn test_sideways_text() {
+        // A scan placed sideways: text runs bottom to top.
+        let (doc, page) = page_with(
+            "q 0 1 -1 0 612 0 cm BT /F1 12 Tf 72 72 Td (The quick brown fox jumps) Tj ET Q",
+        );
+        assert_eq!(text_directions(&doc, page).unwrap(), [0, 21, 0, 0]);
+        assert_eq!(
+            page_orientation(&doc, page).unwrap(),
+            Orientation::Rotate(90)
+        );
+
+        // Upside down, via the text matrix.
+        let (doc, page) =
+            page_with("BT -1 0 0 -1 500 700 Tm [(The quick) -250 (brown fox jumps)] TJ ET");
+        assert_eq!(
+            page_orientation(&doc, page).unwrap(),
+            Orientation::Rotate(180)
+        );
+    }
+
+    #[test]
+    fn test_orientation_thresholds() {
+        assert_eq!(orientation([100, 0, 0, 0], 0), Orientation::Upright);
+        assert_eq!(orientation([0, 0, 0, 100], 90), Orientation::Rotate(270));
+        // Already corrected.
+        assert_eq!(orientation([0, 100, 0, 0], -270), Orientation::Upright);
+        assert!(matches!(
+            orientation([5, 0, 0, 0], 0),
+            Orientation::Unsure { chars: 5, .. }
+        ));
+        assert!(matches!(
+            orientation([50, 50, 0, 0], 0),
+            Orientation::Unsure { .. }
+        ));
+    }
+}
@@ -1,8 +1,13 @@
 use crate::pdf::cache::get_cached_pdf;
+use crate::pdf::strings::decode_text_string;
 use anyhow::{Context, Result};
-use lopdf::{Document, Object};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId};
+use std::collections::HashSet;
 use std::path::Path;
 
+/// Deepest number tree node followed.
+const MAX_TREE_DEPTH: usize = 32;
+
 #[derive(Debug, Clone)]
 pub struct PageLabel {
     pub physical_page: u32,
@@ -57,7 +62,12 @@ pub fn extract_page_labels_from_doc(doc: &Document) -> Result<Vec<PageLabel>> {
     };
 
     // PageLabels uses a number tree structure
-    let ranges = parse_number_tree(doc, page_labels_dict)?;
+    let ranges = parse_number_tree(
+        doc,
+        page_labels_dict,
+        &mut HashSet::from([page_labels_ref]),
+        0,
+    )?;
 
     // Generate labels for all pages
     let mut labels = Vec::new();
@@ -72,8 +82,118 @@ pub fn extract_page_labels_from_doc(doc: &Document) -> Result<Vec<PageLabel>> {
     Ok(labels)
 }
 
-fn parse_number_tree(doc: &Document, dict: &lopdf::Dictionary) -> Result<Vec<PageLabelRange>> {
+/// Find the physical pages whose logical label matches `label`.
+///
+/// Labels are not guaranteed to be unique (e.g., each appendix may restart at
+/// "1"), so all matches are returned. An exact match is preferred; if there is
+/// none, labels are compared case-insensitively so "XII" finds "xii".
+pub fn resolve_label(labels: &[PageLabel], label: &str) -> Vec<u32> {
+    let label = label.trim();
+    let exact: Vec<u32> = labels
+        .iter()
+        .filter(|l| l.logical_label == label)
+        .map(|l| l.physical_page)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+
+    labels
+        .iter()
+        .filter(|l| l.logical_label.eq_ignore_ascii_case(label))
+        .map(|l| l.physical_page)
+        .collect()
+}
+
+/// Label `count` pages newly inserted at the front of the document with
+/// lowercase roman numerals, as for front matter, and shift the existing
+/// ranges so the original pages keep their labels.
+///
+/// Documents without page labels get explicit decimal labels for the
+/// original pages, so "page 1" still means the first original page.
+pub fn prepend_label_range(doc: &mut Document, count: u32) -> Result<()> {
+    // The root counts as visited, so a tree that lists itself as a kid
+    // isn't read twice.
+    let mut seen = HashSet::new();
+    let tree = match doc.catalog()?.get(b"PageLabels") {
+        Ok(Object::Reference(id)) => {
+            seen.insert(*id);
+            doc.get_dictionary(*id).ok().cloned()
+        }
+        Ok(Object::Dictionary(dict)) => Some(dict.clone()),
+        _ => None,
+    };
+
+    let mut entries = Vec::new();
+    if let Some(tree) = tree {
+        collect_nums(doc, &tree, &mut entries, &mut seen, 0);
+    }
+    if entries.is_empty() {
+        entries.push((0, Object::Dictionary(dictionary! { "S" => "D" })));
+    }
+    entries.sort_by_key(|(start, _)| *start);
+
+    let mut nums = vec![
+        Object::Integer(0),
+        Object::Dictionary(dictionary! { "S" => "r" }),
+    ];
+    for (start, label) in entries {
+        nums.push(Object::Integer(start + count as i64));
+        nums.push(label);
+    }
+
+    let tree_id = doc.add_object(dictionary! { "Nums" => nums });
+    doc.catalog_mut()?.set("PageLabels", tree_id);
+    Ok(())
+}
+
+/// Collect the raw `[start, label]` pairs of a number tree, flattening kids.
+fn collect_nums(
+    doc: &Document,
+    node: &Dictionary,
+    out: &mut Vec<(i64, Object)>,
+    seen: &mut HashSet<ObjectId>,
+    depth: usize,
+) {
+    if depth > MAX_TREE_DEPTH {
+        return;
+    }
+    if let Ok(Object::Array(nums)) = node.get(b"Nums") {
+        for pair in nums.chunks_exact(2) {
+            if let Object::Integer(start) = pair[0] {
+                out.push((start, pair[1].clone()));
+            }
+        }
+    }
+    if let Ok(Object::Array(kids)) = node.get(b"Kids") {
+        for kid in kids {
+            let Ok(id) = kid.as_reference() else {
+                continue;
+            };
+            // Each kid is read once, however often (or cyclically) it is
+            // referenced.
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Ok(kid) = doc.get_dictionary(id) {
+                collect_nums(doc, kid, out, seen, depth + 1);
+            }
+        }
+    }
+}
+
+/// Parse a number tree's label ranges. Like [`collect_nums`], reads each
+/// kid once.
+fn parse_number_tree(
+    doc: &Document,
+    dict: &lopdf::Dictionary,
+    seen: &mut HashSet<ObjectId>,
+    depth: usize,
+) -> Result<Vec<PageLabelRange>> {
     let mut ranges = Vec::new();
+    if depth > MAX_TREE_DEPTH {
+        return Ok(ranges);
+    }
 
     // Check for Nums array (leaf node)
     if let Ok(Object::Array(nums)) = dict.get(b"Nums") {
@@ -84,8 +204,11 @@ fn parse_number_tree(doc: &Document, dict: &lopdf::Dictionary) -> Result<Vec<Pag
     if let Ok(Object::Array(kids)) = dict.get(b"Kids") {
         for kid in kids {
             if let Object::Reference(kid_ref) = kid {
+                if !seen.insert(*kid_ref) {
+                    continue;
+                }
                 if let Ok(kid_dict) = doc.get_dictionary(*kid_ref) {
-                    let child_ranges = parse_number_tree(doc, kid_dict)?;
+                    let child_ranges = parse_number_tree(doc, kid_dict, seen, depth + 1)?;
                     ranges.extend(child_ranges);
                 }
             }
@@ -136,12 +259,13 @@ fn parse_nums_array(
         };
 
         let prefix = match label_dict.get(b"P") {
-            Ok(Object::String(bytes, _)) => decode_pdf_string(bytes),
+            Ok(Object::String(bytes, _)) => decode_text_string(bytes),
             _ => String::new(),
         };
 
+        // Must be at least 1; out-of-range values fall back to the default.
         let start_value = match label_dict.get(b"St") {
-            Ok(Object::Integer(n)) => *n as u32,
+            Ok(Object::Integer(n)) => u32::try_from(*n).ok().filter(|&n| n >= 1).unwrap_or(1),
             _ => 1,
         };
 
@@ -171,7 +295,7 @@ fn compute_label(ranges: &[PageLabelRange], page_index: u32) -> String {
         });
 
     let offset = page_index - range.start_page;
-    let value = range.start_value + offset;
+    let value = range.start_value.saturating_add(offset);
 
     let number_part = match range.style {
         LabelStyle::Decimal => value.to_string(),
@@ -186,8 +310,10 @@ fn compute_label(ranges: &[PageLabelRange], page_index: u32) -> String {
 }
 
 fn to_roman(mut n: u32) -> String {
-    if n == 0 {
-        return "0".to_string();
+    // Roman numerals stop at 3999; past that, a run of thousands of Ms
+    // would only bloat every label.
+    if n == 0 || n > 3999 {
+        return n.to_string();
     }
 
     let values = [
@@ -245,25 +371,6 @@ fn generate_default_labels(total_pages: u32) -> Result<Vec<PageLabel>> {
         .collect())
 }
 
-fn decode_pdf_string(bytes: &[u8]) -> String {
-    // Check for UTF-16 BOM
-    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
-        let u16_chars: Vec<u16> = bytes[2..]
-            .chunks(2)
-            .filter_map(|chunk| {
-                if chunk.len() == 2 {
-                    Some(u16::from_be_bytes([chunk[0], chunk[1]]))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        String::from_utf16_lossy(&u16_chars)
-    } else {
-        bytes.iter().map(|&b| b as char).collect()
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,6 +384,38 @@ mod tests {
         assert_eq!(to_roman(1999), "MCMXCIX");
     }
 
+    #[test]
+    fn test_prepend_label_range() {
+        let (mut doc, _) = crate::pdf::testing::text_document(&["a", "b"]);
+        prepend_label_range(&mut doc, 1).unwrap();
+        // Only the labels changed, so the two existing pages now read as one
+        // page of front matter followed by the original first page.
+        let labels: Vec<String> = extract_page_labels_from_doc(&doc)
+            .unwrap()
+            .into_iter()
+            .map(|l| l.logical_label)
+            .collect();
+        assert_eq!(labels, vec!["i", "1"]);
+    }
+
+    #[test]
+    fn test_resolve_label() {
+        let labels: Vec<PageLabel> = ["i", "ii", "1", "2", "A-1", "1"]
+            .iter()
+            .enumerate()
+            .map(|(i, l)| PageLabel {
+                physical_page: i as u32 + 1,
+                logical_label: l.to_string(),
+            })
+            .collect();
+
+        assert_eq!(resolve_label(&labels, "ii"), vec![2]);
+        assert_eq!(resolve_label(&labels, "II"), vec![2]);
+        assert_eq!(resolve_label(&labels, "1"), vec![3, 6]);
+        assert_eq!(resolve_label(&labels, "a-1"), vec![5]);
+        assert!(resolve_label(&labels, "xii").is_empty());
+    }
+
     #[test]
     fn test_to_alpha() {
         assert_eq!(to_alpha(1), "A");
@@ -284,4 +423,100 @@ mod tests {
         assert_eq!(to_alpha(27), "AA");
         assert_eq!(to_alpha(28), "AB");
     }
+
+    #[test]
+    fn test_prepend_label_range_reads_shared_kids_once() {
+        let (mut doc, _) = crate::pdf::testing::text_document(&["a", "b", "c"]);
+        let tree_id = doc.new_object_id();
+        let kid = doc.add_object(dictionary! {
+            "Kids" => vec![tree_id.into()],
+            "Nums" => vec![1.into(), dictionary! { "S" => "r" }.into()],
+        });
+        // Both the root and the kid appear twice, and refer to each other.
+        doc.objects.insert(
+            tree_id,
+            Object::Dictionary(dictionary! {
+                "Kids" => vec![kid.into(), kid.into(), tree_id.into()],
+                "Nums" => vec![0.into(), dictionary! { "S" => "D" }.into()],
+            }),
+        );
+        doc.catalog_mut().unwrap().set("PageLabels", tree_id);
+
+        prepend_label_range(&mut doc, 1).unwrap();
+        let tree = match doc.catalog().unwrap().get(b"PageLabels").unwrap() {
+            Object::Reference(id) => doc.get_dictionary(*id).unwrap(),
+            other => other.as_dict().unwrap(),
+        };
+        let starts: Vec<i64> = tree
+            .get(b"Nums")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|n| n.as_i64().ok())
+            .collect();
+        assert_eq!(starts, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_corrupt_number_tree() {
+        let (mut doc, _) = crate::pdf::testing::text_document(&["a", "b", "c"]);
+        let tree_id = doc.new_object_id();
+        // The tree is its own kid, and the labels start past the largest
+        // Roman numeral.
+        doc.objects.insert(
+            tree_id,
+            Object::Dictionary(dictionary! {
+                "Kids" => vec![tree_id.into()],
+                "Nums" => vec![
+                    0.into(),
+                    dictionary! { "S" => "R", "St" => i64::from(u32::MAX) - 1 }.into(),
+                ],
+            }),
+        );
+        doc.catalog_mut().unwrap().set("PageLabels", tree_id);
+        let labels: Vec<String> = extract_page_labels_from_doc(&doc)
+            .unwrap()
+            .into_iter()
+            .map(|l| l.logical_label)
+            .collect();
+        assert_eq!(labels, vec!["4294967294", "4294967295", "4294967295"]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_number_trees_label_every_page(
+            nodes in proptest::collection::vec(
+                (
+                    proptest::collection::vec(0usize..6, 0..3),
+                    proptest::collection::vec((-5i64..10, "[DrRaAx]", proptest::num::i64::ANY), 0..4),
+                ),
+                1..6,
+            )
+        ) {
+            let (mut doc, _) = crate::pdf::testing::text_document(&["a"; 8]);
+            let ids: Vec<_> = nodes.iter().map(|_| doc.new_object_id()).collect();
+            for (id, (kids, nums)) in ids.iter().zip(&nodes) {
+                let kids: Vec<Object> = kids.iter().map(|&k| ids[k % ids.len()].into()).collect();
+                let nums: Vec<Object> = nums
+                    .iter()
+                    .flat_map(|(start, style, st)| {
+                        [
+                            Object::Integer(*start),
+                            dictionary! { "S" => style.as_str(), "St" => *st }.into(),
+                        ]
+                    })
+                    .collect();
+                doc.objects.insert(
+                    *id,
+                    Object::Dictionary(dictionary! { "Kids" => kids, "Nums" => nums }),
+                );
+            }
+            doc.catalog_mut().unwrap().set("PageLabels", ids[0]);
+            let labels = extract_page_labels_from_doc(&doc).unwrap();
+            proptest::prop_assert_eq!(labels.len(), 8);
+            // At most MMMDCCCLXXXVIII, or a number.
+            proptest::prop_assert!(labels.iter().all(|l| l.logical_label.len() <= 15));
+        }
+    }
 }
@@ -0,0 +1,142 @@
+//! Page dictionary helpers: inherited attributes, resources, and page boxes.
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+/// Page attributes that may be inherited from ancestor `Pages` nodes.
+pub const INHERITABLE_ATTRIBUTES: &[&[u8]] = &[b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Maximum `Parent` chain length followed, guarding against cyclic page trees.
+const MAX_TREE_DEPTH: usize = 64;
+
+/// Deepest nesting of form XObjects followed when walking page content.
+/// Nothing tracks which forms are already being drawn, so this is also what
+/// stops a form that (directly or not) draws itself.
+pub const MAX_FORM_DEPTH: usize = 16;
+
+/// Find a page attribute as written, following the `Parent` chain if the
+/// page itself doesn't define it. The value may be a reference.
+pub fn find_inherited<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node_id = page_id;
+    for _ in 0..MAX_TREE_DEPTH {
+        let dict = doc.get_dictionary(node_id).ok()?;
+        if let Ok(value) = dict.get(key) {
+            return Some(value);
+        }
+        node_id = match dict.get(b"Parent") {
+            Ok(Object::Reference(parent)) => *parent,
+            _ => return None,
+        };
+    }
+    None
+}
+
+/// Look up a page attribute, following the `Parent` chain if the page itself
+/// doesn't define it. References are resolved before returning.
+pub fn inherited_attribute(doc: &Document, page_id: ObjectId, key: &[u8]) -> Option<Object> {
+    find_inherited(doc, page_id, key).map(|value| resolve(doc, value).clone())
+}
+
+/// Copy inherited attributes onto the page itself, so that it keeps its
+/// resources, size, and rotation when moved under a different parent.
+///
+/// Values are copied as written, so a shared `Resources` reference stays a
+/// reference rather than being duplicated per page.
+pub fn materialize_inherited(doc: &mut Document, page_id: ObjectId) {
+    let missing: Vec<(&[u8], Object)> = INHERITABLE_ATTRIBUTES
+        .iter()
+        .filter(|key| doc.get_dictionary(page_id).is_ok_and(|page| !page.has(key)))
+        .filter_map(|&key| find_inherited(doc, page_id, key).map(|value| (key, value.clone())))
+        .collect();
+
+    if let Ok(page) = doc.get_dictionary_mut(page_id) {
+        for (key, value) in missing {
+            page.set(key.to_vec(), value);
+        }
+    }
+}
+
+/// Follow a reference (if any) to the object it points at.
+pub fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> &'a Object {
+    match obj {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(obj),
+        _ => obj,
+    }
+}
+
+/// Look up a named resource (e.g. a font or XObject) in a category of a
+/// resource dictionary.
+pub fn resource<'a>(
+    doc: &'a Document,
+    resources: Option<&'a Dictionary>,
+    category: &[u8],
+    name: &[u8],
+) -> Option<&'a Object> {
+    let category = resolve(doc, resources?.get(category).ok()?)
+        .as_dict()
+        .ok()?;
+    Some(resolve(doc, category.get(name).ok()?))
+}
+
+/// Read a numeric object as `f32`.
+pub fn number(obj: &Object) -> Option<f32> {
+    match obj {
+        Object::Integer(i) => Some(*i as f32),
+        Object::Real(r) => Some(*r),
+        _ => None,
+    }
+}
+
+/// A page box rectangle as `[llx, lly, urx, ury]`.
+pub type Rect = [f32; 4];
+
+/// Read a rectangle array, normalizing so that the first corner is lower-left.
+pub fn rect(doc: &Document, obj: &Object) -> Option<Rect> {
+    let Object::Array(items) = resolve(doc, obj) else {
+        return None;
+    };
+    if items.len() != 4 {
+        return None;
+    }
+    let mut values = [0.0; 4];
+    for (value, item) in values.iter_mut().zip(items) {
+        *value = number(resolve(doc, item))?;
+    }
+    Some([
+        values[0].min(values[2]),
+        values[1].min(values[3]),
+        values[0].max(values[2]),
+        values[1].max(values[3]),
+    ])
+}
+
+/// Get a page's MediaBox, defaulting to US Letter if none is present.
+pub fn media_box(doc: &Document, page_id: ObjectId) -> Rect {
+    inherited_attribute(doc, page_id, b"MediaBox")
+        .and_then(|obj| rect(doc, &obj))
+        .unwrap_or([0.0, 0.0, 612.0, 792.0])
+}
+
+/// Get a page box (e.g., `TrimBox`), falling back as the PDF spec prescribes:
+/// Bleed/Trim/ArtBox default to the CropBox, which defaults to the MediaBox.
+pub fn page_box(doc: &Document, page_id: ObjectId, key: &[u8]) -> Rect {
+    let media = media_box(doc, page_id);
+    if key == b"MediaBox" {
+        return media;
+    }
+    let crop = inherited_attribute(doc, page_id, b"CropBox")
+        .and_then(|obj| rect(doc, &obj))
+        .unwrap_or(media);
+    if key == b"CropBox" {
+        return crop;
+    }
+    doc.get_dictionary(page_id)
+        .ok()
+        .and_then(|dict| dict.get(key).ok())
+        .and_then(|obj| rect(doc, obj))
+        .unwrap_or(crop)
+}
+
+/// Convert a rectangle into a PDF array object.
+pub fn rect_object(rect: Rect) -> Object {
+    Object::Array(rect.iter().map(|&v| Object::Real(v)).collect())
+}
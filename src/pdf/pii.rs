@@ -0,0 +1,170 @@
+//! Patterns for personal data (email addresses, phone numbers, IBANs, card
+//! numbers) in page text, the discovery step before redaction.
+//!
+//! All patterns are searched in one pass as a single alternation (see
+//! [`combined_regex`]), so OCR and text extraction run once; each hit is then
+//! assigned to the first pattern that matches all of it and whose checksum,
+//! if it has one, is valid.
+
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// A kind of personal data and how to find it.
+#[derive(Debug, Clone)]
+pub struct PiiPattern {
+    /// Short name reported for hits, e.g. "email".
+    pub kind: String,
+    pub regex: Regex,
+    /// The same pattern anchored at both ends, to classify hits.
+    anchored: Regex,
+    /// Rejects hits with a bad checksum or the wrong number of digits.
+    validate: Option<fn(&str) -> bool>,
+}
+
+impl PiiPattern {
+    pub fn new(kind: &str, pattern: &str) -> Result<Self> {
+        Ok(PiiPattern {
+            kind: kind.to_string(),
+            regex: Regex::new(pattern)
+                .with_context(|| format!("Invalid pattern for {}: {}", kind, pattern))?,
+            anchored: Regex::new(&format!("^(?:{})$", pattern))
+                .with_context(|| format!("Invalid pattern for {}: {}", kind, pattern))?,
+            validate: None,
+        })
+    }
+
+    fn validated(mut self, validate: fn(&str) -> bool) -> Self {
+        self.validate = Some(validate);
+        self
+    }
+
+    /// Whether all of `text` is a valid hit for this pattern.
+    pub fn matches(&self, text: &str) -> bool {
+        self.anchored.is_match(text) && self.validate.is_none_or(|validate| validate(text))
+    }
+}
+
+/// The built-in patterns, most specific first.
+pub fn builtin_patterns() -> Vec<PiiPattern> {
+    let pattern = |kind, pattern| PiiPattern::new(kind, pattern).expect("built-in pattern");
+    vec![
+        pattern("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}"),
+        pattern(
+            "iban",
+            r"\b[A-Z]{2}[0-9]{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b",
+        )
+        .validated(iban_valid),
+        pattern("credit_card", r"\b[0-9](?:[ -]?[0-9]){12,18}\b").validated(luhn_valid),
+        pattern(
+            "phone",
+            r"(?:\+[0-9]{1,3}[ .-]?(?:\([0-9]{1,4}\)[ .-]?)?[0-9]{1,4}(?:[ .-]?[0-9]{2,4}){2,4}|\([0-9]{3}\) ?[0-9]{3}[-. ][0-9]{4}|\b[0-9]{3}[-.][0-9]{3}[-.][0-9]{4})\b",
+        )
+        .validated(phone_valid),
+    ]
+}
+
+/// One regular expression matching any of `patterns`.
+pub fn combined_regex(patterns: &[PiiPattern]) -> Result<Regex> {
+    let alternation = patterns
+        .iter()
+        .map(|p| format!("(?:{})", p.regex.as_str()))
+        .collect::<Vec<_>>()
+        .join("|");
+    Regex::new(&alternation).context("Failed to combine PII patterns")
+}
+
+/// The first pattern `text` (a hit of [`combined_regex`]) is valid for.
+pub fn classify<'a>(patterns: &'a [PiiPattern], text: &str) -> Option<&'a PiiPattern> {
+    patterns.iter().find(|pattern| pattern.matches(text))
+}
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+/// Card numbers have 13 to 19 digits and a valid Luhn check digit.
+fn luhn_valid(text: &str) -> bool {
+    let digits = digits(text);
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// IBANs are 15 to 34 characters and pass the ISO 7064 mod-97 check.
+fn iban_valid(text: &str) -> bool {
+    let iban: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let value = match c.to_digit(36) {
+            Some(value) => value,
+            None => return false,
+        };
+        // Letters count as two digits (A = 10 ... Z = 35).
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+/// Phone numbers have 7 to 15 digits (E.164 allows at most 15).
+fn phone_valid(text: &str) -> bool {
+    (7..=15).contains(&digits(text).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert!(luhn_valid("4111 1111 1111 1111"));
+        assert!(luhn_valid("5500-0000-0000-0004"));
+        assert!(!luhn_valid("4111 1111 1111 1112"));
+        assert!(!luhn_valid("4111"));
+
+        assert!(iban_valid("GB82 WEST 1234 5698 7654 32"));
+        assert!(iban_valid("DE89370400440532013000"));
+        assert!(!iban_valid("GB82 WEST 1234 5698 7654 33"));
+    }
+
+    #[test]
+    fn test_classify() {
+        let patterns = builtin_patterns();
+        let combined = combined_regex(&patterns).unwrap();
+        let text = "Mail jane.doe@example.co.uk or call +44 20 7946 0958. \
+                    Pay GB82 WEST 1234 5698 7654 32 with 4111 1111 1111 1111. \
+                    US office: (212) 555-0199. Invoice 2024-01-15, ref 1234 5678 9012 3456.";
+        let found: Vec<(&str, &str)> = combined
+            .find_iter(text)
+            .filter_map(|m| Some((classify(&patterns, m.as_str())?.kind.as_str(), m.as_str())))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("email", "jane.doe@example.co.uk"),
+                ("phone", "+44 20 7946 0958"),
+                ("iban", "GB82 WEST 1234 5698 7654 32"),
+                ("credit_card", "4111 1111 1111 1111"),
+                ("phone", "(212) 555-0199"),
+            ]
+        );
+    }
+}
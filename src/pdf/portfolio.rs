@@ -0,0 +1,233 @@
+//! PDF portfolios (collections).
+//!
+//! A portfolio is a container PDF whose catalog has a `Collection`
+//! dictionary; the documents it packages are embedded files listed in the
+//! `EmbeddedFiles` name tree. Its own pages are usually just a cover sheet
+//! ("this document is a portfolio"), so useful work happens on the items.
+
+use crate::pdf::cache::CachedPdf;
+use crate::pdf::document::get_string_from_dict;
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::pages::resolve;
+use crate::pdf::strings::decode_text_string;
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object, Stream};
+use std::collections::HashSet;
+
+/// Deepest `EmbeddedFiles` name tree level read. Real name trees are two or
+/// three levels deep; revisited nodes are skipped separately.
+const MAX_DEPTH: usize = 32;
+
+/// A file packaged in a portfolio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortfolioItem {
+    /// Key in the `EmbeddedFiles` name tree.
+    pub name: String,
+    /// File name from the file specification (`UF`, else `F`).
+    pub file_name: String,
+    pub description: Option<String>,
+    /// Uncompressed size in bytes, if recorded.
+    pub size: Option<i64>,
+}
+
+impl PortfolioItem {
+    pub fn is_pdf(&self) -> bool {
+        self.file_name.to_ascii_lowercase().ends_with(".pdf")
+    }
+}
+
+/// Whether the document is a portfolio (has a `Collection` dictionary).
+pub fn is_portfolio(doc: &Document) -> bool {
+    doc.catalog()
+        .is_ok_and(|catalog| catalog.has(b"Collection"))
+}
+
+/// List the embedded files, in name tree order.
+pub fn portfolio_items(doc: &Document) -> Vec<PortfolioItem> {
+    embedded_files(doc)
+        .into_iter()
+        .map(|(name, spec)| {
+            let size = embedded_stream(doc, spec)
+                .and_then(|stream| stream.dict.get(b"Params").ok())
+                .and_then(|params| resolve(doc, params).as_dict().ok())
+                .and_then(|params| params.get(b"Size").ok())
+                .and_then(|size| resolve(doc, size).as_i64().ok());
+            PortfolioItem {
+                file_name: file_name(spec).unwrap_or_else(|| name.clone()),
+                description: get_string_from_dict(spec, b"Desc"),
+                size,
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Load a PDF packaged in the document, by name tree key or file name.
+pub fn load_portfolio_item(doc: &Document, name: &str) -> Result<CachedPdf> {
+    let files = embedded_files(doc);
+    let (_, spec) = files
+        .iter()
+        .find(|(key, _)| key == name)
+        .or_else(|| {
+            files
+                .iter()
+                .find(|(_, spec)| file_name(spec).as_deref() == Some(name))
+        })
+        .with_context(|| {
+            let names: Vec<&str> = files.iter().map(|(key, _)| key.as_str()).collect();
+            if names.is_empty() {
+                format!(
+                    "No portfolio item '{}' (the file has no embedded files)",
+                    name
+                )
+            } else {
+                format!(
+                    "No portfolio item '{}' (available: {})",
+                    name,
+                    names.join(", ")
+                )
+            }
+        })?;
+
+    let stream = embedded_stream(doc, spec)
+        .with_context(|| format!("Portfolio item '{}' has no embedded data", name))?;
+    let bytes = stream_bytes(stream)?;
+    CachedPdf::from_bytes(&bytes).with_context(|| format!("parse portfolio item '{}'", name))
+}
+
+/// Entries of the `EmbeddedFiles` name tree: key and file specification.
+pub(crate) fn embedded_files(doc: &Document) -> Vec<(String, &Dictionary)> {
+    let root = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Names").ok())
+        .and_then(|names| resolve(doc, names).as_dict().ok())
+        .and_then(|names| names.get(b"EmbeddedFiles").ok());
+    let mut entries = Vec::new();
+    if let Some(root) = root {
+        collect_names(doc, root, 0, &mut HashSet::new(), &mut entries);
+    }
+    entries
+}
+
+fn collect_names<'a>(
+    doc: &'a Document,
+    node: &'a Object,
+    depth: usize,
+    seen: &mut HashSet<lopdf::ObjectId>,
+    out: &mut Vec<(String, &'a Dictionary)>,
+) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    if let Object::Reference(id) = node {
+        if !seen.insert(*id) {
+            return;
+        }
+    }
+    let Ok(dict) = resolve(doc, node).as_dict() else {
+        return;
+    };
+
+    if let Ok(names) = dict.get(b"Names").map(|names| resolve(doc, names)) {
+        if let Ok(names) = names.as_array() {
+            for pair in names.chunks(2) {
+                let [key, value] = pair else {
+                    continue;
+                };
+                let Object::String(key, _) = resolve(doc, key) else {
+                    continue;
+                };
+                if let Ok(spec) = resolve(doc, value).as_dict() {
+                    out.push((decode_text_string(key), spec));
+                }
+            }
+        }
+    }
+    if let Ok(kids) = dict.get(b"Kids").map(|kids| resolve(doc, kids)) {
+        if let Ok(kids) = kids.as_array() {
+            for kid in kids {
+                collect_names(doc, kid, depth + 1, seen, out);
+            }
+        }
+    }
+}
+
+pub(crate) fn file_name(spec: &Dictionary) -> Option<String> {
+    get_string_from_dict(spec, b"UF").or_else(|| get_string_from_dict(spec, b"F"))
+}
+
+/// The embedded file stream of a file specification (`EF` `UF` or `F`).
+pub(crate) fn embedded_stream<'a>(doc: &'a Document, spec: &'a Dictionary) -> Option<&'a Stream> {
+    let ef = resolve(doc, spec.get(b"EF").ok()?).as_dict().ok()?;
+    let file = ef.get(b"UF").or_else(|_| ef.get(b"F")).ok()?;
+    resolve(doc, file).as_stream().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::dictionary;
+
+    fn portfolio() -> Document {
+        let (mut inner, _) = text_document(&["inside", "the portfolio"]);
+        let mut bytes = Vec::new();
+        inner.save_to(&mut bytes).unwrap();
+
+        let (mut doc, _) = text_document(&["cover"]);
+        let file = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "EmbeddedFile",
+                "Params" => dictionary! { "Size" => bytes.len() as i64 },
+            },
+            bytes,
+        ));
+        let spec = doc.add_object(dictionary! {
+            "Type" => "Filespec",
+            "F" => Object::string_literal("report.pdf"),
+            "Desc" => Object::string_literal("Annual report"),
+            "EF" => dictionary! { "F" => file },
+        });
+        let leaf = doc.add_object(dictionary! {
+            "Names" => vec![Object::string_literal("0001"), Object::Reference(spec)],
+        });
+        let catalog = doc.catalog_mut().unwrap();
+        catalog.set("Collection", dictionary! { "Type" => "Collection" });
+        catalog.set(
+            "Names",
+            dictionary! {
+                "EmbeddedFiles" => dictionary! { "Kids" => vec![Object::Reference(leaf)] },
+            },
+        );
+        doc
+    }
+
+    #[test]
+    fn test_portfolio_items() {
+        let doc = portfolio();
+        assert!(is_portfolio(&doc));
+
+        let items = portfolio_items(&doc);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].name, "0001");
+        assert_eq!(items[0].file_name, "report.pdf");
+        assert_eq!(items[0].description.as_deref(), Some("Annual report"));
+        assert!(items[0].size.is_some_and(|size| size > 0));
+        assert!(items[0].is_pdf());
+    }
+
+    #[test]
+    fn test_load_portfolio_item_by_key_or_file_name() {
+        let doc = portfolio();
+        for name in ["0001", "report.pdf"] {
+            let item = load_portfolio_item(&doc, name).unwrap();
+            assert_eq!(item.document().get_pages().len(), 2);
+        }
+
+        let Err(err) = load_portfolio_item(&doc, "missing") else {
+            panic!("loaded a missing item");
+        };
+        assert!(err.to_string().contains("available: 0001"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Reading a table of contents from the document's own printed contents
+//! pages, for books (often scans) that have one but no bookmarks.
+
+use crate::pdf::cache::CachedPdf;
+use crate::pdf::page_labels::{extract_page_labels_from_doc, resolve_label};
+use crate::pdf::text::PageTextProvider;
+use crate::pdf::toc::TocEntry;
+use anyhow::Result;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Pages searched for a printed table of contents when none are given.
+pub const DEFAULT_SCAN_PAGES: u32 = 30;
+
+/// An entry as printed: its title, nesting level, and page label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedEntry {
+    pub title: String,
+    pub level: u32,
+    pub label: String,
+}
+
+/// Find the printed contents pages among `pages` and turn their entries
+/// into an outline.
+///
+/// Contents pages are recognized by lines ending in a page number, usually
+/// after dot leaders; the first run of consecutive such pages is used.
+/// Printed page numbers are mapped to physical pages through the page
+/// labels, preferring pages after the contents. Entries whose number
+/// matches no label have no page.
+pub fn detect_printed_toc(cached: &CachedPdf, pages: &[u32]) -> Result<Vec<TocEntry>> {
+    let provider = PageTextProvider::new(cached);
+
+    let mut toc_pages = Vec::new();
+    let mut printed = Vec::new();
+    for page in provider.pages(pages) {
+        if page.error.is_none() {
+            if let Some(entries) = parse_toc_page(&page.text) {
+                toc_pages.push(page.page);
+                printed.extend(entries);
+                continue;
+            }
+        }
+        if !toc_pages.is_empty() {
+            break;
+        }
+    }
+    let Some(&last_toc_page) = toc_pages.last() else {
+        return Ok(Vec::new());
+    };
+
+    let labels = extract_page_labels_from_doc(cached.document())?;
+    let flat = printed.into_iter().map(|entry| {
+        let candidates = resolve_label(&labels, &entry.label);
+        let page = candidates
+            .iter()
+            .copied()
+            .find(|&p| p > last_toc_page)
+            .or_else(|| candidates.first().copied());
+        (entry.title, page, entry.level)
+    });
+    Ok(nest(flat))
+}
+
+/// The entries on a page, if it looks like a contents page.
+fn parse_toc_page(text: &str) -> Option<Vec<PrintedEntry>> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let entries: Vec<PrintedEntry> = lines.iter().filter_map(|l| parse_toc_line(l)).collect();
+
+    let has_heading = lines.iter().take(3).any(|line| {
+        let line = line.trim().to_lowercase();
+        line == "contents" || line == "table of contents"
+    });
+    let enough = if has_heading { 3 } else { 5 };
+    (entries.len() >= enough && entries.len() * 2 >= lines.len()).then_some(entries)
+}
+
+/// Parse a line like `2.1 Getting Started ........ 17` or `Preface   ix`.
+fn parse_toc_line(line: &str) -> Option<PrintedEntry> {
+    static LINE: OnceLock<Regex> = OnceLock::new();
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let line_re = LINE.get_or_init(|| {
+        // A title, then dot leaders or a wide gap, then an arabic or roman
+        // page number.
+        Regex::new(
+            r"^\s*(?P<title>\S.*?)(?:\s*(?:[.·…]\s*){2,}|\s{2,}|\t)(?P<page>\d{1,5}|[ivxlcdm]{1,8}|[IVXLCDM]{1,8})\s*$",
+        )
+        .unwrap()
+    });
+    let number_re = NUMBER.get_or_init(|| Regex::new(r"^(\d+(?:\.\d+)*)\.?\s").unwrap());
+
+    let caps = line_re.captures(line)?;
+    let title = caps["title"].trim_end_matches(['.', '·', '…', ' ']);
+    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !title.chars().any(char::is_alphabetic) {
+        return None;
+    }
+    // "2.1.3 Results" nests two levels below "2 Methods".
+    let level = number_re
+        .captures(&title)
+        .map_or(0, |num| num[1].matches('.').count() as u32);
+
+    Some(PrintedEntry {
+        title,
+        level,
+        label: caps["page"].to_string(),
+    })
+}
+
+/// Build the entry tree from `(title, page, level)` in reading order; an
+/// entry nests under the nearest preceding one with a lower level.
+fn nest(flat: impl IntoIterator<Item = (String, Option<u32>, u32)>) -> Vec<TocEntry> {
+    let mut roots: Vec<TocEntry> = Vec::new();
+    // Printed levels of the entries the next one may nest under.
+    let mut open: Vec<u32> = Vec::new();
+    for (title, page, level) in flat {
+        while open.last().is_some_and(|&l| l >= level) {
+            open.pop();
+        }
+        let mut siblings = &mut roots;
+        for _ in 0..open.len() {
+            siblings = &mut siblings.last_mut().unwrap().children;
+        }
+        siblings.push(TocEntry {
+            title,
+            page,
+            external: None,
+            level: open.len() as u32,
+            children: Vec::new(),
+        });
+        open.push(level);
+    }
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::toc::flatten_toc;
+
+    #[test]
+    fn test_parse_toc_line() {
+        assert_eq!(
+            parse_toc_line("2.1 Getting Started . . . . . . 17"),
+            Some(PrintedEntry {
+                title: "2.1 Getting Started".to_string(),
+                level: 1,
+                label: "17".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_toc_line("Preface.............ix").map(|e| (e.title, e.label)),
+            Some(("Preface".to_string(), "ix".to_string()))
+        );
+        assert_eq!(
+            parse_toc_line("Chapter 3   The Sea   45").map(|e| (e.title, e.label)),
+            Some(("Chapter 3 The Sea".to_string(), "45".to_string()))
+        );
+        assert_eq!(parse_toc_line("In 1998 the company grew by 12"), None);
+        assert_eq!(parse_toc_line(". . . . 12"), None);
+    }
+
+    #[test]
+    fn test_parse_toc_page() {
+        let toc = "Contents\nPreface ..... vii\n1 Introduction ..... 1\n\
+                   1.1 Scope ..... 3\n2 Methods ..... 9\n";
+        assert_eq!(parse_toc_page(toc).unwrap().len(), 4);
+
+        let prose = "Chapter 1\nIt was a dark night.\nThe wind howled.\n\
+                     She waited . . . 3\nNothing came.\n";
+        assert!(parse_toc_page(prose).is_none());
+    }
+
+    #[test]
+    fn test_nest() {
+        let toc = nest([
+            ("Preface".to_string(), Some(7), 0),
+            ("1 Intro".to_string(), Some(11), 0),
+            ("1.1 Scope".to_string(), Some(12), 1),
+            ("1.1.1 Limits".to_string(), None, 2),
+            ("2 Methods".to_string(), Some(20), 0),
+            // A skipped level nests one deeper, not two.
+            ("2.1.1 Data".to_string(), Some(21), 2),
+            ("2.1.2 Models".to_string(), Some(23), 2),
+        ]);
+        assert_eq!(toc.len(), 3);
+        assert_eq!(toc[1].children[0].children[0].title, "1.1.1 Limits");
+        assert_eq!(toc[2].children.len(), 2);
+        let levels: Vec<u32> = flatten_toc(&toc).iter().map(|e| e.level).collect();
+        assert_eq!(levels, vec![0, 0, 1, 2, 0, 1, 1]);
+    }
+}
@@ -0,0 +1,132 @@
+//! Heuristic scoring of extracted text, to spot pages whose text layer is
+//! garbage (broken font encodings, bad OCR) and should be OCRed again or
+//! read with another backend.
+
+/// Below this score, a page's text is probably not worth using.
+pub const LOW_QUALITY: f32 = 0.5;
+
+/// How trustworthy a page's extracted text looks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextQuality {
+    /// 0 (garbage) to 1 (clean); `None` when there is no text to judge.
+    pub score: Option<f32>,
+    /// Whitespace-separated tokens.
+    pub tokens: usize,
+    /// Share of tokens that look like words or numbers.
+    pub recognizable: f32,
+    /// U+FFFD, private-use, and control characters, which extraction
+    /// produces for glyphs it could not map to Unicode.
+    pub replacement_chars: usize,
+    /// Tokens that look like encoding noise, e.g. `c#8k$` or `lllll`.
+    pub garbled: usize,
+}
+
+/// Score extracted text.
+pub fn text_quality(text: &str) -> TextQuality {
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    let replacement_chars = text.chars().filter(|&c| is_replacement(c)).count();
+    let mut tokens = 0;
+    let mut recognizable = 0;
+    let mut garbled = 0;
+    for token in text.split_whitespace() {
+        tokens += 1;
+        if is_garbled(token) {
+            garbled += 1;
+        } else if is_recognizable(token) {
+            recognizable += 1;
+        }
+    }
+
+    let share = |n: usize, of: usize| if of == 0 { 0.0 } else { n as f32 / of as f32 };
+    let score = (chars > 0).then(|| {
+        (share(recognizable, tokens) * (1.0 - share(replacement_chars, chars))).clamp(0.0, 1.0)
+    });
+    TextQuality {
+        score,
+        tokens,
+        recognizable: share(recognizable, tokens),
+        replacement_chars,
+        garbled,
+    }
+}
+
+fn is_replacement(c: char) -> bool {
+    c == '\u{FFFD}'
+        || ('\u{E000}'..='\u{F8FF}').contains(&c)
+        || (c.is_control() && !c.is_whitespace())
+}
+
+/// A word (letters, with inner apostrophes or hyphens) or a number, once
+/// surrounding punctuation is stripped.
+fn is_recognizable(token: &str) -> bool {
+    let core = token.trim_matches(|c: char| !c.is_alphanumeric());
+    if core.is_empty() {
+        // Bullets, dashes, and other lone punctuation are neither good nor
+        // bad; counting them as recognizable keeps lists from scoring low.
+        return !token.chars().any(is_replacement);
+    }
+    if core.chars().count() > 30 {
+        return false;
+    }
+    let is_word = core
+        .chars()
+        .all(|c| c.is_alphabetic() || matches!(c, '\'' | '\u{2019}' | '-'));
+    let is_number = core
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | ':' | '/' | '-'));
+    is_word || is_number
+}
+
+fn is_garbled(token: &str) -> bool {
+    let chars: Vec<char> = token.chars().collect();
+    // The same letter four or more times running.
+    let repeated = chars
+        .windows(4)
+        .any(|w| w[0].is_alphabetic() && w.iter().all(|&c| c == w[0]));
+    // Letters broken up by symbols that don't occur inside words (or
+    // addresses and identifiers).
+    let core = token.trim_matches(|c: char| !c.is_alphanumeric());
+    let symbols_inside = core.chars().any(char::is_alphabetic)
+        && core.chars().any(|c| {
+            !c.is_alphanumeric()
+                && !matches!(
+                    c,
+                    '\'' | '\u{2019}' | '-' | '.' | ',' | '/' | '&' | '@' | '_' | ':'
+                )
+        });
+    // Letters and digits alternating, as in "l0r3m".
+    let switches = core
+        .chars()
+        .collect::<Vec<_>>()
+        .windows(2)
+        .filter(|w| w[0].is_alphabetic() != w[1].is_alphabetic())
+        .count();
+    repeated || symbols_inside || (switches >= 3 && core.chars().any(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text_scores_high() {
+        let quality = text_quality(
+            "The committee's report, published 12/03/2024, covers 3 regions - north and south.",
+        );
+        assert!(quality.score.unwrap() > 0.9, "{:?}", quality);
+        assert_eq!(quality.garbled, 0);
+    }
+
+    #[test]
+    fn test_garbage_scores_low() {
+        let quality = text_quality("Tk#e c$8m \u{FFFD}\u{FFFD}\u{FFFD} l0r3m1 iiiii qx^z%");
+        assert!(quality.score.unwrap() < LOW_QUALITY, "{:?}", quality);
+        assert_eq!(quality.replacement_chars, 3);
+        assert_eq!(quality.garbled, 5);
+    }
+
+    #[test]
+    fn test_empty_text_has_no_score() {
+        assert_eq!(text_quality("  \n").score, None);
+    }
+}
@@ -0,0 +1,588 @@
+//! Recovering text from fonts without a Unicode mapping.
+//!
+//! Type3 fonts, and simple fonts whose `Differences` rename glyphs, need a
+//! `ToUnicode` CMap for their text to be extracted; without one, pages come
+//! out empty or as noise. Their glyph names often still say what they
+//! draw: standard names (`a`, `eacute`, `fi`), `uniXXXX` and `uXXXX[XX]`
+//! names, and the numbered names TeX and Ghostscript give Type3 glyphs
+//! (`a65`, `c65`, `char65`). This reads a page's glyphs by those names and
+//! lays them out in lines again.
+//!
+//! Glyphs without a usable name come out as U+FFFD, unless [`ocr_glyphs`]
+//! recognized them from the rendered page. Either way the result is a best
+//! effort: [`CachedPdf`](crate::pdf::CachedPdf) only uses it for pages whose
+//! extracted text is worse, and marks those pages as recovered.
+
+use crate::pdf::glyphs::{page_glyphs, Glyph};
+use crate::pdf::ocr::{ocr_glyph, OcrOptions};
+use crate::pdf::pages::{find_inherited, page_box, resolve};
+use crate::pdf::render::{render_page, Raster};
+use anyhow::{Context, Result};
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// How much of a page's text was recovered from glyph names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryStats {
+    /// Glyphs in unmapped fonts whose text was found (by name or by OCR).
+    pub mapped: usize,
+    /// Glyphs in unmapped fonts shown as U+FFFD.
+    pub unmapped: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredText {
+    pub text: String,
+    pub stats: RecoveryStats,
+}
+
+/// Text recognized for glyphs without a usable name, by font resource name
+/// and character code.
+pub type GlyphTexts = HashMap<(Vec<u8>, u32), String>;
+
+/// Glyph names by character code, for a font that needs them.
+type GlyphNames = BTreeMap<u32, Vec<u8>>;
+
+/// Recover a page's text from glyph names, filling in `ocr` results for
+/// glyphs without one. `None` if the page doesn't use fonts that need it.
+pub fn recover_page_text(
+    doc: &Document,
+    page_id: ObjectId,
+    ocr: &GlyphTexts,
+) -> Option<RecoveredText> {
+    let fonts = unmapped_fonts(doc, page_id);
+    if fonts.is_empty() {
+        return None;
+    }
+    let content = doc.get_and_decode_page_content(page_id).ok()?;
+    let glyphs = page_glyphs(doc, page_id, &content.operations);
+
+    let mut stats = RecoveryStats {
+        mapped: 0,
+        unmapped: 0,
+    };
+    let mut text = String::new();
+    let mut previous: Option<&Glyph> = None;
+    for glyph in &glyphs {
+        let shown = match fonts.get(&glyph.font) {
+            Some(font) => match glyph_text(font, glyph, ocr) {
+                Some(shown) => {
+                    stats.mapped += 1;
+                    shown
+                }
+                None => {
+                    stats.unmapped += 1;
+                    "\u{FFFD}".to_string()
+                }
+            },
+            None => glyph.text.clone(),
+        };
+        if shown.is_empty() {
+            continue;
+        }
+        if let Some(previous) = previous {
+            text.push_str(separator(previous, glyph));
+        }
+        text.push_str(&shown);
+        previous = Some(glyph);
+    }
+    if stats.mapped + stats.unmapped == 0 {
+        return None;
+    }
+    text.push('\n');
+    Some(RecoveredText { text, stats })
+}
+
+/// A font on the page whose text extraction can't decode.
+struct UnmappedFont {
+    names: GlyphNames,
+    /// Type3 fonts have no base encoding to fall back on, and may use
+    /// numbered glyph names.
+    type3: bool,
+}
+
+/// The page's fonts that have no `ToUnicode` CMap but either are Type3 or
+/// rename glyphs with `Differences`, by resource name.
+fn unmapped_fonts(doc: &Document, page_id: ObjectId) -> HashMap<Vec<u8>, UnmappedFont> {
+    doc.get_page_fonts(page_id)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, dict)| !dict.has(b"ToUnicode"))
+        .filter_map(|(name, dict)| {
+            let type3 = matches!(dict.get(b"Subtype"), Ok(Object::Name(n)) if n == b"Type3");
+            let names = differences(doc, dict);
+            (type3 || !names.is_empty()).then_some((name, UnmappedFont { names, type3 }))
+        })
+        .collect()
+}
+
+/// The glyph names an encoding dictionary's `Differences` array assigns.
+fn differences(doc: &Document, font: &Dictionary) -> GlyphNames {
+    let mut names = GlyphNames::new();
+    let array = font
+        .get(b"Encoding")
+        .ok()
+        .and_then(|obj| resolve(doc, obj).as_dict().ok())
+        .and_then(|encoding| encoding.get(b"Differences").ok())
+        .and_then(|obj| resolve(doc, obj).as_array().ok());
+    let mut code = 0;
+    for item in array.into_iter().flatten() {
+        match resolve(doc, item) {
+            Object::Integer(start) => code = *start as u32,
+            Object::Name(name) => {
+                names.insert(code, name.clone());
+                code += 1;
+            }
+            _ => {}
+        }
+    }
+    names
+}
+
+/// A glyph's text, if its name (or OCR) tells.
+fn glyph_text(font: &UnmappedFont, glyph: &Glyph, ocr: &GlyphTexts) -> Option<String> {
+    let named = match font.names.get(&glyph.code) {
+        Some(name) => glyph_name_text(name, font.type3),
+        // Codes the differences don't rename keep the base encoding's
+        // meaning, which extraction already decoded.
+        None if !font.type3 => Some(glyph.text.clone()).filter(|text| !text.is_empty()),
+        None => None,
+    };
+    named.or_else(|| {
+        ocr.get(&(glyph.font.clone(), glyph.code))
+            .filter(|text| !text.is_empty())
+            .cloned()
+    })
+}
+
+/// What goes between two glyphs drawn one after the other: a line break if
+/// the second is on another line, a space if there is a gap, else nothing.
+fn separator(previous: &Glyph, glyph: &Glyph) -> &'static str {
+    let height = (glyph.rect[3] - glyph.rect[1])
+        .max(previous.rect[3] - previous.rect[1])
+        .max(1.0);
+    if (glyph.rect[1] - previous.rect[1]).abs() > height / 2.0 || glyph.rect[0] < previous.rect[0] {
+        "\n"
+    } else if glyph.rect[0] - previous.rect[2] > height * 0.15
+        && !previous.text.ends_with(char::is_whitespace)
+        && !glyph.text.starts_with(char::is_whitespace)
+    {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// The text a glyph name stands for, following the Adobe Glyph List
+/// conventions: suffixes after `.` are variants (`a.sc`), `_` joins
+/// ligature components (`f_f_i`), and `uniXXXX` and `uXXXX` name code
+/// points. With `numbered`, `a65`, `c65`, and `char65` are read as the
+/// ASCII character with that code.
+pub fn glyph_name_text(name: &[u8], numbered: bool) -> Option<String> {
+    let name = std::str::from_utf8(name).ok()?;
+    let name = name.split('.').next().unwrap_or_default();
+    if name.is_empty() {
+        // ".notdef", or only a suffix.
+        return None;
+    }
+    if name.contains('_') {
+        return name
+            .split('_')
+            .map(|part| glyph_name_text(part.as_bytes(), numbered))
+            .collect();
+    }
+    if let Some(hex) = name.strip_prefix("uni") {
+        if !hex.is_empty() && hex.len() % 4 == 0 {
+            if let Some(text) = (0..hex.len())
+                .step_by(4)
+                .map(|i| code_point(&hex[i..i + 4]))
+                .collect::<Option<String>>()
+            {
+                return Some(text);
+            }
+        }
+    }
+    if let Some(hex) = name.strip_prefix('u') {
+        if (4..=6).contains(&hex.len()) {
+            if let Some(c) = code_point(hex) {
+                return Some(c.to_string());
+            }
+        }
+    }
+    if name.len() == 1 && name.as_bytes()[0].is_ascii_alphabetic() {
+        return Some(name.to_string());
+    }
+    if let Some(&(_, text)) = NAMES.iter().find(|(n, _)| *n == name) {
+        return Some(text.to_string());
+    }
+    if let Some(i) = LATIN1.iter().position(|&n| n == name) {
+        return char::from_u32(0xC0 + i as u32).map(String::from);
+    }
+    if numbered {
+        let digits = name.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+        let prefix = &name[..name.len() - digits.len()];
+        if matches!(prefix, "a" | "c" | "char") {
+            if let Ok(code @ 33..=126) = digits.parse::<u8>() {
+                return Some((code as char).to_string());
+            }
+        }
+    }
+    None
+}
+
+/// The character with the given hexadecimal code, if it's one (surrogates
+/// aren't).
+fn code_point(hex: &str) -> Option<char> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(hex, 16).ok()?)
+}
+
+/// Standard glyph names outside the letters and Latin-1 letters.
+const NAMES: &[(&str, &str)] = &[
+    ("space", " "),
+    ("exclam", "!"),
+    ("quotedbl", "\""),
+    ("numbersign", "#"),
+    ("dollar", "$"),
+    ("percent", "%"),
+    ("ampersand", "&"),
+    ("quotesingle", "'"),
+    ("quoteright", "\u{2019}"),
+    ("parenleft", "("),
+    ("parenright", ")"),
+    ("asterisk", "*"),
+    ("plus", "+"),
+    ("comma", ","),
+    ("hyphen", "-"),
+    ("period", "."),
+    ("slash", "/"),
+    ("zero", "0"),
+    ("one", "1"),
+    ("two", "2"),
+    ("three", "3"),
+    ("four", "4"),
+    ("five", "5"),
+    ("six", "6"),
+    ("seven", "7"),
+    ("eight", "8"),
+    ("nine", "9"),
+    ("colon", ":"),
+    ("semicolon", ";"),
+    ("less", "<"),
+    ("equal", "="),
+    ("greater", ">"),
+    ("question", "?"),
+    ("at", "@"),
+    ("bracketleft", "["),
+    ("backslash", "\\"),
+    ("bracketright", "]"),
+    ("asciicircum", "^"),
+    ("underscore", "_"),
+    ("grave", "`"),
+    ("quoteleft", "\u{2018}"),
+    ("braceleft", "{"),
+    ("bar", "|"),
+    ("braceright", "}"),
+    ("asciitilde", "~"),
+    ("quotedblleft", "\u{201C}"),
+    ("quotedblright", "\u{201D}"),
+    ("quotesinglbase", "\u{201A}"),
+    ("quotedblbase", "\u{201E}"),
+    ("guillemotleft", "\u{AB}"),
+    ("guillemotright", "\u{BB}"),
+    ("guilsinglleft", "\u{2039}"),
+    ("guilsinglright", "\u{203A}"),
+    ("endash", "\u{2013}"),
+    ("emdash", "\u{2014}"),
+    ("bullet", "\u{2022}"),
+    ("ellipsis", "\u{2026}"),
+    ("dagger", "\u{2020}"),
+    ("daggerdbl", "\u{2021}"),
+    ("perthousand", "\u{2030}"),
+    ("minus", "\u{2212}"),
+    ("fi", "fi"),
+    ("fl", "fl"),
+    ("ff", "ff"),
+    ("ffi", "ffi"),
+    ("ffl", "ffl"),
+    ("dotlessi", "\u{131}"),
+    ("lslash", "\u{142}"),
+    ("Lslash", "\u{141}"),
+    ("oe", "\u{153}"),
+    ("OE", "\u{152}"),
+    ("scaron", "\u{161}"),
+    ("Scaron", "\u{160}"),
+    ("zcaron", "\u{17E}"),
+    ("Zcaron", "\u{17D}"),
+    ("Ydieresis", "\u{178}"),
+    ("exclamdown", "\u{A1}"),
+    ("cent", "\u{A2}"),
+    ("sterling", "\u{A3}"),
+    ("yen", "\u{A5}"),
+    ("Euro", "\u{20AC}"),
+    ("section", "\u{A7}"),
+    ("copyright", "\u{A9}"),
+    ("registered", "\u{AE}"),
+    ("trademark", "\u{2122}"),
+    ("degree", "\u{B0}"),
+    ("plusminus", "\u{B1}"),
+    ("paragraph", "\u{B6}"),
+    ("periodcentered", "\u{B7}"),
+    ("questiondown", "\u{BF}"),
+];
+
+/// Glyph names of U+00C0 to U+00FF, in order.
+const LATIN1: [&str; 64] = [
+    "Agrave",
+    "Aacute",
+    "Acircumflex",
+    "Atilde",
+    "Adieresis",
+    "Aring",
+    "AE",
+    "Ccedilla",
+    "Egrave",
+    "Eacute",
+    "Ecircumflex",
+    "Edieresis",
+    "Igrave",
+    "Iacute",
+    "Icircumflex",
+    "Idieresis",
+    "Eth",
+    "Ntilde",
+    "Ograve",
+    "Oacute",
+    "Ocircumflex",
+    "Otilde",
+    "Odieresis",
+    "multiply",
+    "Oslash",
+    "Ugrave",
+    "Uacute",
+    "Ucircumflex",
+    "Udieresis",
+    "Yacute",
+    "Thorn",
+    "germandbls",
+    "agrave",
+    "aacute",
+    "acircumflex",
+    "atilde",
+    "adieresis",
+    "aring",
+    "ae",
+    "ccedilla",
+    "egrave",
+    "eacute",
+    "ecircumflex",
+    "edieresis",
+    "igrave",
+    "iacute",
+    "icircumflex",
+    "idieresis",
+    "eth",
+    "ntilde",
+    "ograve",
+    "oacute",
+    "ocircumflex",
+    "otilde",
+    "odieresis",
+    "divide",
+    "oslash",
+    "ugrave",
+    "uacute",
+    "ucircumflex",
+    "udieresis",
+    "yacute",
+    "thorn",
+    "ydieresis",
+];
+
+/// Recognize the glyphs on a page (1-indexed) that have no usable name,
+/// one image per distinct glyph cut from the rendered page. Rotated pages
+/// are skipped.
+pub fn ocr_glyphs(
+    path: &Path,
+    doc: &Document,
+    page_num: u32,
+    options: &OcrOptions,
+) -> Result<GlyphTexts> {
+    let mut found = GlyphTexts::new();
+    let Some(&page_id) = doc.get_pages().get(&page_num) else {
+        return Ok(found);
+    };
+    let rotated = find_inherited(doc, page_id, b"Rotate")
+        .and_then(|obj| resolve(doc, obj).as_i64().ok())
+        .is_some_and(|rotate| rotate % 360 != 0);
+    let fonts = unmapped_fonts(doc, page_id);
+    if rotated || fonts.is_empty() {
+        return Ok(found);
+    }
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .context("Failed to decode page content")?;
+
+    let mut wanted: Vec<Glyph> = Vec::new();
+    for glyph in page_glyphs(doc, page_id, &content.operations) {
+        let Some(font) = fonts.get(&glyph.font) else {
+            continue;
+        };
+        let unseen = !wanted
+            .iter()
+            .any(|other| other.font == glyph.font && other.code == glyph.code);
+        if unseen && glyph_text(font, &glyph, &found).is_none() {
+            wanted.push(glyph);
+        }
+    }
+    if wanted.is_empty() {
+        return Ok(found);
+    }
+
+    let page = render_page(path, page_num, options.dpi)?;
+    let crop = page_box(doc, page_id, b"CropBox");
+    let scale = options.dpi as f32 / 72.0;
+    for glyph in wanted {
+        let [x0, y0, x1, y1] = glyph.rect;
+        let left = ((x0 - crop[0]) * scale).floor().max(0.0) as u32;
+        let top = ((crop[3] - y1) * scale).floor().max(0.0) as u32;
+        let right = ((x1 - crop[0]) * scale).ceil().max(0.0) as u32;
+        let bottom = ((crop[3] - y0) * scale).ceil().max(0.0) as u32;
+        let Some(image) = cut(&page, left, top, right, bottom) else {
+            continue;
+        };
+        let text = ocr_glyph(image.to_png()?, options)?;
+        found.insert((glyph.font, glyph.code), text);
+    }
+    Ok(found)
+}
+
+/// The pixels in `left..right` and `top..bottom`, on a white margin as
+/// wide as the glyph is high, which tesseract needs to find it.
+fn cut(page: &Raster, left: u32, top: u32, right: u32, bottom: u32) -> Option<Raster> {
+    let right = right.min(page.width);
+    let bottom = bottom.min(page.height);
+    if left >= right || top >= bottom {
+        return None;
+    }
+    let margin = bottom - top;
+    let width = right - left + 2 * margin;
+    let height = bottom - top + 2 * margin;
+    let mut pixels = vec![255; (width * height * 3) as usize];
+    for y in top..bottom {
+        for x in left..right {
+            let pixel = page.get(x, y)?;
+            let i = (((y - top + margin) * width + (x - left + margin)) * 3) as usize;
+            pixels[i..i + 3].copy_from_slice(&pixel);
+        }
+    }
+    Some(Raster {
+        width,
+        height,
+        pixels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn test_glyph_name_text() {
+        let text = |name: &str, numbered| glyph_name_text(name.as_bytes(), numbered);
+        assert_eq!(text("a", false).as_deref(), Some("a"));
+        assert_eq!(text("eacute", false).as_deref(), Some("é"));
+        assert_eq!(text("ydieresis", false).as_deref(), Some("ÿ"));
+        assert_eq!(text("a.sc", false).as_deref(), Some("a"));
+        assert_eq!(text("f_f_i", false).as_deref(), Some("ffi"));
+        assert_eq!(text("uni00410042", false).as_deref(), Some("AB"));
+        assert_eq!(text("u1F600", false).as_deref(), Some("😀"));
+        assert_eq!(text("uniD800", false), None);
+        assert_eq!(text("zero", false).as_deref(), Some("0"));
+        assert_eq!(text(".notdef", false), None);
+        assert_eq!(text("a65", false), None);
+        assert_eq!(text("a65", true).as_deref(), Some("A"));
+        assert_eq!(text("char48", true).as_deref(), Some("0"));
+        assert_eq!(text("g65", true), None);
+        assert_eq!(text("c300", true), None);
+    }
+
+    /// A page in a Type3 font whose codes 1-5 are named `a72`, `e`,
+    /// `l`, `o`, and `g9`, showing "Hello" twice, once per line.
+    fn type3_document() -> (Document, ObjectId) {
+        let mut doc = Document::with_version("1.5");
+        let font = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type3",
+            "FontMatrix" => vec![0.001.into(), 0.into(), 0.into(), 0.001.into(), 0.into(), 0.into()],
+            "FontBBox" => vec![0.into(), 0.into(), 1000.into(), 1000.into()],
+            "FirstChar" => 1,
+            "LastChar" => 5,
+            "Widths" => vec![600.into(), 500.into(), 250.into(), 500.into(), 500.into()],
+            "Encoding" => dictionary! {
+                "Type" => "Encoding",
+                "Differences" => vec![
+                    1.into(),
+                    Object::Name(b"a72".to_vec()),
+                    Object::Name(b"e".to_vec()),
+                    Object::Name(b"l".to_vec()),
+                    Object::Name(b"o".to_vec()),
+                    Object::Name(b"g9".to_vec()),
+                ],
+            },
+            "CharProcs" => dictionary! {},
+        });
+        let content = b"BT /T3 10 Tf 72 720 Td (\x01\x02\x03\x03\x04) Tj \
+                        0 -14 Td (\x01\x02\x03\x03\x04\x05) Tj ET"
+            .to_vec();
+        let content = doc.add_object(Stream::new(dictionary! {}, content));
+        let pages = doc.new_object_id();
+        let page = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages,
+            "Contents" => content,
+            "Resources" => dictionary! { "Font" => dictionary! { "T3" => font } },
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        doc.objects.insert(
+            pages,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![Object::Reference(page)],
+                "Count" => 1,
+            }),
+        );
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        doc.trailer.set("Root", catalog);
+        (doc, page)
+    }
+
+    #[test]
+    fn test_recover_type3_page() {
+        let (doc, page) = type3_document();
+        let recovered = recover_page_text(&doc, page, &GlyphTexts::new()).unwrap();
+        assert_eq!(recovered.text, "Hello\nHello\u{FFFD}\n");
+        assert_eq!(
+            recovered.stats,
+            RecoveryStats {
+                mapped: 10,
+                unmapped: 1
+            }
+        );
+
+        let ocr = GlyphTexts::from([((b"T3".to_vec(), 5), "!".to_string())]);
+        let recovered = recover_page_text(&doc, page, &ocr).unwrap();
+        assert_eq!(recovered.text, "Hello\nHello!\n");
+        assert_eq!(recovered.stats.unmapped, 0);
+    }
+
+    #[test]
+    fn test_standard_fonts_need_no_recovery() {
+        let (doc, pages) = crate::pdf::testing::text_document(&["Hello"]);
+        assert_eq!(recover_page_text(&doc, pages[0], &GlyphTexts::new()), None);
+    }
+}
@@ -0,0 +1,131 @@
+//! Removing text from pages, for redaction.
+//!
+//! Text is found glyph by glyph (see [`crate::pdf::glyphs`]) and deleted from
+//! the content stream, with spacing of the same width in its place so the
+//! text around it doesn't move, and a black box is drawn where it was.
+//! Invisible text counts too, so on a scan with an OCR layer the box also
+//! covers the words in the image.
+//!
+//! Only the page's own content is rewritten: text in form XObjects,
+//! annotations, the outline, and metadata is left alone, as are scans
+//! without a text layer.
+
+use crate::pdf::glyphs::{find_all, page_glyphs, rewrite, set_page_content, Edit};
+use crate::pdf::pages::Rect;
+use crate::pdf::xobject::{add_content, Layer};
+use anyhow::{Context, Result};
+use lopdf::{Document, ObjectId};
+use std::fmt::Write;
+
+/// Text to remove from a page.
+#[derive(Debug, Clone)]
+pub struct Redaction {
+    pub value: String,
+    /// Which occurrence of `value` to remove, counting from 0 in the order
+    /// the page draws its text.
+    pub occurrence: usize,
+}
+
+/// Remove text from a page and cover where it was. Returns the boxes drawn
+/// for each redaction, in default user space; redactions that weren't found
+/// get none.
+pub fn redact_page(
+    doc: &mut Document,
+    page_id: ObjectId,
+    redactions: &[Redaction],
+) -> Result<Vec<Vec<Rect>>> {
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .context("Failed to decode page content")?;
+    let glyphs = page_glyphs(doc, page_id, &content.operations);
+
+    let mut edits = vec![Edit::Keep; glyphs.len()];
+    let mut boxes = Vec::new();
+    for redaction in redactions {
+        let found = find_all(&glyphs, &redaction.value)
+            .into_iter()
+            .nth(redaction.occurrence)
+            .unwrap_or_default();
+        for i in found.clone() {
+            edits[i] = Edit::Remove;
+        }
+        boxes.push(merge_boxes(found.map(|i| glyphs[i].rect)));
+    }
+    if !edits.contains(&Edit::Remove) {
+        return Ok(boxes);
+    }
+
+    let operations = rewrite(content.operations, &glyphs, &edits);
+    set_page_content(doc, page_id, operations)?;
+
+    let mut ops = String::from("\nq 0 g\n");
+    for &[x0, y0, x1, y1] in boxes.iter().flatten() {
+        let _ = writeln!(ops, "{} {} {} {} re f", x0, y0, x1 - x0, y1 - y0);
+    }
+    ops.push_str("Q\n");
+    add_content(doc, page_id, ops.into_bytes(), Layer::Over)?;
+    Ok(boxes)
+}
+
+/// Combine glyph boxes into one box per line.
+fn merge_boxes(rects: impl Iterator<Item = Rect>) -> Vec<Rect> {
+    let mut boxes: Vec<Rect> = Vec::new();
+    for rect in rects {
+        match boxes.last_mut() {
+            Some(last) if rect[1] < last[3] && last[1] < rect[3] => {
+                *last = [
+                    last[0].min(rect[0]),
+                    last[1].min(rect[1]),
+                    last[2].max(rect[2]),
+                    last[3].max(rect[3]),
+                ];
+            }
+            _ => boxes.push(rect),
+        }
+    }
+    boxes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    fn redaction(value: &str, occurrence: usize) -> Redaction {
+        Redaction {
+            value: value.to_string(),
+            occurrence,
+        }
+    }
+
+    #[test]
+    fn test_redact_page() {
+        let (mut doc, pages) = text_document(&["Call 555-0199 or 555-0199"]);
+        let boxes = redact_page(&mut doc, pages[0], &[redaction("555-0199", 1)]).unwrap();
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].len(), 1);
+        let [x0, y0, x1, y1] = boxes[0][0];
+        assert!(
+            x0 > 72.0 + 80.0 && x1 > x0,
+            "second number boxed: {:?}",
+            boxes
+        );
+        assert!(y0 < 720.0 && y1 > 720.0);
+
+        let content = String::from_utf8(doc.get_page_content(pages[0]).unwrap()).unwrap();
+        assert_eq!(content.matches("555-0199").count(), 1);
+        assert!(content.contains("(Call 555-0199 or )"));
+        // One gap as wide as the number: 3 fives, a hyphen, 4 more digits.
+        assert!(content.contains("-4225"));
+        assert!(content.contains(" re f"));
+    }
+
+    #[test]
+    fn test_redact_page_not_found() {
+        let (mut doc, pages) = text_document(&["Nothing to see"]);
+        let before = doc.get_page_content(pages[0]).unwrap();
+        let boxes = redact_page(&mut doc, pages[0], &[redaction("555-0199", 0)]).unwrap();
+        assert_eq!(boxes, [Vec::<Rect>::new()]);
+        assert_eq!(doc.get_page_content(pages[0]).unwrap(), before);
+    }
+}
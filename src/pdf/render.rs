@@ -0,0 +1,135 @@
+//! Rasterizing pages.
+//!
+//! Neither lopdf nor pdf-extract can paint pages, so this hands the file to
+//! poppler's `pdftoppm`, which is widely packaged, and decodes the PNG it
+//! writes.
+
+use anyhow::{Context, Result};
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// An 8-bit RGB image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, three bytes per pixel.
+    pub pixels: Vec<u8>,
+}
+
+impl Raster {
+    /// The pixel at (x, y), if it is inside the image.
+    pub fn get(&self, x: u32, y: u32) -> Option<[u8; 3]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 3;
+        Some([self.pixels[i], self.pixels[i + 1], self.pixels[i + 2]])
+    }
+
+    pub fn to_png(&self) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder
+            .write_header()
+            .context("Failed to write PNG header")?;
+        writer
+            .write_image_data(&self.pixels)
+            .context("Failed to encode PNG")?;
+        writer.finish().context("Failed to finish PNG")?;
+        Ok(bytes)
+    }
+
+    pub fn from_png(bytes: &[u8]) -> Result<Raster> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().context("Failed to read PNG header")?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let frame = reader
+            .next_frame(&mut buf)
+            .context("Failed to decode PNG")?;
+        let data = &buf[..frame.buffer_size()];
+
+        let pixels = match frame.color_type {
+            png::ColorType::Rgb => data.to_vec(),
+            png::ColorType::Rgba => data
+                .chunks_exact(4)
+                .flat_map(|p| [p[0], p[1], p[2]])
+                .collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g]).collect(),
+            png::ColorType::GrayscaleAlpha => data
+                .chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0]])
+                .collect(),
+            png::ColorType::Indexed => anyhow::bail!("Unexpected indexed PNG after expansion"),
+        };
+        Ok(Raster {
+            width: frame.width,
+            height: frame.height,
+            pixels,
+        })
+    }
+}
+
+/// Render one page (1-indexed) of the PDF at `path` at `dpi` dots per inch.
+pub fn render_page(path: &Path, page: u32, dpi: u32) -> Result<Raster> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let prefix = std::env::temp_dir().join(format!(
+        "inpdf-render-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let status = Command::new("pdftoppm")
+        .arg("-png")
+        .args(["-r", &dpi.to_string()])
+        .args(["-f", &page.to_string(), "-l", &page.to_string()])
+        .arg("-singlefile")
+        .arg(path)
+        .arg(&prefix)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => anyhow::bail!(
+            "pdftoppm failed on page {} of {} ({})",
+            page,
+            path.display(),
+            status
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            anyhow::bail!(
+                "Rendering pages requires pdftoppm (poppler-utils) to be installed and on PATH"
+            )
+        }
+        Err(e) => return Err(e).context("Failed to run pdftoppm"),
+    }
+
+    let mut output = prefix.into_os_string();
+    output.push(".png");
+    let bytes = std::fs::read(&output)
+        .with_context(|| format!("Failed to read {}", Path::new(&output).display()))?;
+    std::fs::remove_file(&output).ok();
+    Raster::from_png(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_png_round_trip() {
+        let raster = Raster {
+            width: 2,
+            height: 1,
+            pixels: vec![255, 0, 0, 10, 20, 30],
+        };
+        let decoded = Raster::from_png(&raster.to_png().unwrap()).unwrap();
+        assert_eq!(decoded, raster);
+        assert_eq!(decoded.get(1, 0), Some([10, 20, 30]));
+        assert_eq!(decoded.get(2, 0), None);
+    }
+}
@@ -0,0 +1,206 @@
+//! Replacing text in content streams.
+//!
+//! Occurrences are found glyph by glyph (see [`crate::pdf::glyphs`]) and the
+//! replacement is shown in the same font, followed by spacing that makes up
+//! the difference in width, so later text on the line stays where it was.
+//! That is only safe when the font can show the replacement: a simple font
+//! whose encoding has a code for each of its characters (and, for an
+//! embedded subset, a glyph), and an occurrence drawn by one operator.
+//! Other occurrences are left alone and reported.
+
+use crate::pdf::glyphs::{find_all, page_glyphs, rewrite, set_page_content, tj_spacing, Edit};
+use crate::pdf::svg::Font;
+use anyhow::{Context, Result};
+use lopdf::{Document, ObjectId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Why an occurrence was left alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Unsafe {
+    /// Composite fonts' codes can't be worked out from text.
+    CompositeFont { font: String },
+    /// The font's encoding has no code for a character of the replacement.
+    Unencodable { font: String, char: char },
+    /// The font is an embedded subset without the character's glyph.
+    MissingGlyph { font: String, char: char },
+    /// The occurrence is drawn by more than one operator.
+    SpansOperators,
+}
+
+impl fmt::Display for Unsafe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unsafe::CompositeFont { font } => write!(f, "font {} is a composite font", font),
+            Unsafe::Unencodable { font, char } => {
+                write!(f, "font {} has no code for {:?}", font, char)
+            }
+            Unsafe::MissingGlyph { font, char } => {
+                write!(f, "font {} is a subset without {:?}", font, char)
+            }
+            Unsafe::SpansOperators => write!(f, "the text is split across operators"),
+        }
+    }
+}
+
+/// What replacing text on one page did.
+#[derive(Debug, Default)]
+pub struct PageReplacements {
+    pub replaced: usize,
+    /// Occurrences left alone, and why.
+    pub skipped: Vec<Unsafe>,
+}
+
+/// A font, with codes for the characters its encoding can show.
+struct Encoder<'a> {
+    font: Font<'a>,
+    codes: HashMap<char, u8>,
+}
+
+impl<'a> Encoder<'a> {
+    fn new(font: Font<'a>) -> Self {
+        let mut codes = HashMap::new();
+        if let (false, Some(encoding)) = (font.two_byte, &font.encoding) {
+            // Later codes first, so the lowest code for a character wins.
+            for code in (0..=255u8).rev() {
+                let Ok(text) = Document::decode_text(encoding, &[code]) else {
+                    continue;
+                };
+                let mut chars = text.chars();
+                if let (Some(c), None) = (chars.next(), chars.next()) {
+                    codes.insert(c, code);
+                }
+            }
+        }
+        Encoder { font, codes }
+    }
+
+    fn encode(&self, name: &str, text: &str) -> Result<Vec<u8>, Unsafe> {
+        if self.font.two_byte {
+            return Err(Unsafe::CompositeFont {
+                font: name.to_string(),
+            });
+        }
+        text.chars()
+            .map(|c| {
+                let &code = self.codes.get(&c).ok_or_else(|| Unsafe::Unencodable {
+                    font: name.to_string(),
+                    char: c,
+                })?;
+                if !self.font.has_glyph(code as u32) {
+                    return Err(Unsafe::MissingGlyph {
+                        font: name.to_string(),
+                        char: c,
+                    });
+                }
+                Ok(code)
+            })
+            .collect()
+    }
+}
+
+/// Replace every occurrence of `find` on a page (whitespace ignored, as in
+/// [`find_all`]) with `with`, where that is safe.
+pub fn replace_page(
+    doc: &mut Document,
+    page_id: ObjectId,
+    find: &str,
+    with: &str,
+) -> Result<PageReplacements> {
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .context("Failed to decode page content")?;
+    let glyphs = page_glyphs(doc, page_id, &content.operations);
+    let occurrences = find_all(&glyphs, find);
+    let mut result = PageReplacements::default();
+    if occurrences.is_empty() {
+        return Ok(result);
+    }
+
+    let mut edits = vec![Edit::Keep; glyphs.len()];
+    // Fonts borrow the document, which is changed below.
+    {
+        let font_dicts = doc.get_page_fonts(page_id).unwrap_or_default();
+        let mut encoders: HashMap<&[u8], Encoder> = HashMap::new();
+        for range in occurrences {
+            let first = &glyphs[range.start];
+            if glyphs[range.end - 1].op != first.op {
+                result.skipped.push(Unsafe::SpansOperators);
+                continue;
+            }
+            // Glyphs are only found in fonts the page defines.
+            let Some(&dict) = font_dicts.get(&first.font) else {
+                continue;
+            };
+            let encoder = encoders
+                .entry(first.font.as_slice())
+                .or_insert_with(|| Encoder::new(Font::load(doc, dict)));
+            let name = String::from_utf8_lossy(&first.font);
+            let codes = match encoder.encode(&name, with) {
+                Ok(codes) => codes,
+                Err(reason) => {
+                    result.skipped.push(reason);
+                    continue;
+                }
+            };
+
+            let advance: f32 = codes
+                .iter()
+                .map(|&code| {
+                    let width = encoder.font.width(code as u32) / 1000.0 * first.font_size;
+                    let word_spacing = if code == 32 { first.word_spacing } else { 0.0 };
+                    width + first.char_spacing + word_spacing
+                })
+                .sum();
+            // The rest of the occurrence leaves gaps as wide as its glyphs, so
+            // only the first glyph's width needs making up.
+            let adjust = first.spacing - tj_spacing(advance, first.font_size);
+            edits[range.start] = Edit::Replace(codes, adjust);
+            for edit in &mut edits[range.start + 1..range.end] {
+                *edit = Edit::Remove;
+            }
+            result.replaced += 1;
+        }
+    }
+
+    if result.replaced > 0 {
+        let operations = rewrite(content.operations, &glyphs, &edits);
+        set_page_content(doc, page_id, operations)?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_replace_page() {
+        let (mut doc, pages) = text_document(&["Bill ACME Corp for ACME Corp work"]);
+        let result = replace_page(&mut doc, pages[0], "ACME Corp", "Example Inc").unwrap();
+        assert_eq!(result.replaced, 2);
+        assert!(result.skipped.is_empty());
+
+        let content = String::from_utf8(doc.get_page_content(pages[0]).unwrap()).unwrap();
+        assert!(!content.contains("ACME"));
+        assert!(content.contains("(Bill Example Inc)"));
+        assert_eq!(content.matches("Example Inc").count(), 2);
+    }
+
+    #[test]
+    fn test_replace_page_unencodable() {
+        let (mut doc, pages) = text_document(&["Bill ACME Corp"]);
+        let before = doc.get_page_content(pages[0]).unwrap();
+        let result = replace_page(&mut doc, pages[0], "ACME", "\u{4e2d}").unwrap();
+        assert_eq!(result.replaced, 0);
+        assert_eq!(
+            result.skipped,
+            [Unsafe::Unencodable {
+                font: "F1".to_string(),
+                char: '\u{4e2d}'
+            }]
+        );
+        assert_eq!(doc.get_page_content(pages[0]).unwrap(), before);
+    }
+}
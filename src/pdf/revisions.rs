@@ -0,0 +1,80 @@
+//! Incremental-update revisions.
+//!
+//! Each incremental save appends new objects, a new xref section, and a new
+//! `%%EOF` marker to the file. Truncating the file after an earlier marker
+//! therefore yields the document as it was at that revision.
+//!
+//! Linearized files also carry two `%%EOF` markers even when they were never
+//! updated, so their first "revision" is just the first-page section.
+
+use crate::pdf::cache::CachedPdf;
+use crate::pdf::inspect::find_all;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    /// 1-based revision number; 1 is the original document.
+    pub number: usize,
+    /// Byte length of the file as of this revision.
+    pub end_offset: usize,
+}
+
+/// Locate the revisions in raw PDF bytes by their `%%EOF` markers.
+pub fn find_revisions(raw: &[u8]) -> Vec<Revision> {
+    find_all(raw, b"%%EOF")
+        .into_iter()
+        .enumerate()
+        .map(|(i, pos)| {
+            let mut end = pos + b"%%EOF".len();
+            // Include the end-of-line that terminates the marker.
+            if raw.get(end) == Some(&b'\r') {
+                end += 1;
+            }
+            if raw.get(end) == Some(&b'\n') {
+                end += 1;
+            }
+            Revision {
+                number: i + 1,
+                end_offset: end,
+            }
+        })
+        .collect()
+}
+
+/// Load the document as of a given revision (1-based).
+pub fn load_revision<P: AsRef<Path>>(path: P, number: usize) -> Result<CachedPdf> {
+    let path = path.as_ref();
+    let raw = std::fs::read(path).with_context(|| format!("read PDF: {}", path.display()))?;
+    let revisions = find_revisions(&raw);
+
+    let revision = number
+        .checked_sub(1)
+        .and_then(|i| revisions.get(i))
+        .with_context(|| {
+            format!(
+                "Version {} does not exist ({} has {} revision(s))",
+                number,
+                path.display(),
+                revisions.len()
+            )
+        })?;
+
+    CachedPdf::from_bytes(&raw[..revision.end_offset])
+        .with_context(|| format!("parse revision {} of {}", number, path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_revisions() {
+        let raw = b"%PDF-1.4\nbody\n%%EOF\nupdate\n%%EOF\r\nmore\n%%EOF";
+        let revisions = find_revisions(raw);
+        assert_eq!(revisions.len(), 3);
+        assert_eq!(&raw[..revisions[0].end_offset], b"%PDF-1.4\nbody\n%%EOF\n");
+        assert!(raw[..revisions[1].end_offset].ends_with(b"%%EOF\r\n"));
+        assert_eq!(revisions[2].end_offset, raw.len());
+    }
+}
@@ -0,0 +1,106 @@
+//! PDF text strings (titles, metadata, labels, field values, ...).
+//!
+//! A text string is UTF-16BE or, since PDF 2.0, UTF-8 when it starts with
+//! the matching byte order mark, and PDFDocEncoding otherwise.
+
+use lopdf::{Object, StringFormat};
+
+/// Decode a text string. Invalid sequences become U+FFFD rather than
+/// failing, and language escapes (`ESC lang ESC`) in Unicode strings are
+/// dropped.
+pub fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        strip_language_escapes(String::from_utf16_lossy(&units))
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        strip_language_escapes(String::from_utf8_lossy(utf8).into_owned())
+    } else if let Some(utf16) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        // Not allowed by the spec, but some producers write little-endian.
+        let units: Vec<u16> = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        strip_language_escapes(String::from_utf16_lossy(&units))
+    } else {
+        bytes.iter().map(|&b| pdf_doc_char(b)).collect()
+    }
+}
+
+/// Encode a text string: printable ASCII as a literal string, anything else
+/// as UTF-16BE with a byte order mark.
+pub fn encode_text_string(text: &str) -> Object {
+    if text.bytes().all(|b| (0x20..0x7F).contains(&b)) {
+        Object::string_literal(text)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+/// Map a PDFDocEncoding byte to its character (PDF 32000-1, Annex D).
+///
+/// It matches Latin-1 except for 0x18–0x1F and 0x80–0xA0. The few
+/// undefined codes are kept as their Latin-1 characters, which is what
+/// producers that wrote Latin-1 meant.
+fn pdf_doc_char(b: u8) -> char {
+    const LOW: [char; 8] = ['˘', 'ˇ', 'ˆ', '˙', '˝', '˛', '˚', '˜'];
+    const HIGH: [char; 33] = [
+        '•', '†', '‡', '…', '—', '–', 'ƒ', '⁄', '‹', '›', '−', '‰', '„', '“', '”', '‘', '’', '‚',
+        '™', 'ﬁ', 'ﬂ', 'Ł', 'Œ', 'Š', 'Ÿ', 'Ž', 'ı', 'ł', 'œ', 'š', 'ž', '\u{9F}', '€',
+    ];
+    match b {
+        0x18..=0x1F => LOW[(b - 0x18) as usize],
+        0x80..=0xA0 => HIGH[(b - 0x80) as usize],
+        _ => b as char,
+    }
+}
+
+fn strip_language_escapes(text: String) -> String {
+    if !text.contains('\u{1B}') {
+        return text;
+    }
+    text.split('\u{1B}')
+        .enumerate()
+        .filter(|(i, _)| i % 2 == 0)
+        .map(|(_, part)| part)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_pdf_doc_encoding() {
+        assert_eq!(
+            decode_text_string(b"\x8dQuoted\x8e \x84 \x80 item \x92 \xa0 caf\xe9"),
+            "“Quoted” — • item ™ € café"
+        );
+    }
+
+    #[test]
+    fn test_decode_unicode_forms() {
+        assert_eq!(decode_text_string(b"\xfe\xff\x00H\x00\xe9"), "Hé");
+        assert_eq!(decode_text_string(b"\xef\xbb\xbfH\xc3\xa9"), "Hé");
+        assert_eq!(
+            decode_text_string(b"\xfe\xff\x00\x1b\x00e\x00n\x00\x1b\x00H\x00i"),
+            "Hi"
+        );
+    }
+
+    #[test]
+    fn test_encode_round_trips() {
+        for text in ["Plain", "Résumé — “draft”"] {
+            let Object::String(bytes, _) = encode_text_string(text) else {
+                panic!("expected a string");
+            };
+            assert_eq!(decode_text_string(&bytes), text);
+        }
+    }
+}
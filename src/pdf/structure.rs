@@ -0,0 +1,254 @@
+//! Logical structure trees of tagged PDFs.
+//!
+//! Tagged documents describe their reading order and semantics (headings,
+//! paragraphs, figures, tables) in a tree of structure elements rooted at the
+//! catalog's `StructTreeRoot`. Leaves point at marked content on pages by
+//! marked-content ID (MCID).
+
+use crate::pdf::document::get_string_from_dict;
+use crate::pdf::pages::resolve;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::HashSet;
+
+/// Deepest structure element nesting read. Tagged documents nest much more
+/// than field trees (lists in table cells in sections), hence the high limit.
+const MAX_DEPTH: usize = 256;
+
+/// Maximum `RoleMap` chain followed when resolving a custom type.
+const MAX_ROLE_CHAIN: usize = 16;
+
+/// A structure element and its children, in logical order.
+#[derive(Debug, Clone)]
+pub struct StructNode {
+    /// The element type after applying the document's `RoleMap`, e.g.
+    /// `Figure` for a custom `Chart`.
+    pub standard_type: Vec<u8>,
+    /// Alternate description (`Alt`), e.g. for figures.
+    pub alt: Option<String>,
+    /// Replacement text for the whole subtree (`ActualText`).
+    pub actual_text: Option<String>,
+    /// The page this element's content is on (`Pg`, possibly inherited).
+    pub page: Option<ObjectId>,
+    pub children: Vec<StructKid>,
+}
+
+/// A child of a structure element.
+#[derive(Debug, Clone)]
+pub enum StructKid {
+    Element(StructNode),
+    /// Marked content with the given MCID in a page's content stream.
+    Content {
+        page: Option<ObjectId>,
+        mcid: i64,
+    },
+}
+
+impl StructNode {
+    /// Visit this element and all descendant elements depth-first, in
+    /// logical order, with their depth below this node.
+    pub fn walk<'a>(&'a self, visit: &mut impl FnMut(&'a StructNode, usize)) {
+        self.walk_at(0, visit);
+    }
+
+    fn walk_at<'a>(&'a self, depth: usize, visit: &mut impl FnMut(&'a StructNode, usize)) {
+        visit(self, depth);
+        for kid in &self.children {
+            if let StructKid::Element(node) = kid {
+                node.walk_at(depth + 1, visit);
+            }
+        }
+    }
+
+    /// The first page any content of this element appears on.
+    pub fn first_page(&self) -> Option<ObjectId> {
+        self.children
+            .iter()
+            .find_map(|kid| match kid {
+                StructKid::Element(node) => node.first_page(),
+                StructKid::Content { page, .. } => *page,
+            })
+            .or(self.page)
+    }
+
+    /// Whether the element has a standard type (after role mapping) of `ty`.
+    pub fn is(&self, ty: &[u8]) -> bool {
+        self.standard_type == ty
+    }
+}
+
+/// Get the catalog's `StructTreeRoot`, if the document has one.
+pub fn struct_tree_root(doc: &Document) -> Option<&Dictionary> {
+    let root = doc.catalog().ok()?.get(b"StructTreeRoot").ok()?;
+    resolve(doc, root).as_dict().ok()
+}
+
+/// Read the document's structure tree, returning the top-level elements.
+///
+/// Elements reachable more than once (malformed trees) are included once.
+/// Returns an empty list for untagged documents.
+pub fn struct_tree(doc: &Document) -> Vec<StructNode> {
+    let Some(root) = struct_tree_root(doc) else {
+        return Vec::new();
+    };
+    let mut reader = TreeReader {
+        doc,
+        role_map: root
+            .get(b"RoleMap")
+            .ok()
+            .and_then(|m| resolve(doc, m).as_dict().ok()),
+        seen: HashSet::new(),
+    };
+    let mut kids = Vec::new();
+    if let Ok(k) = root.get(b"K") {
+        reader.read_kids(k, None, 0, &mut kids);
+    }
+    kids.into_iter()
+        .filter_map(|kid| match kid {
+            StructKid::Element(node) => Some(node),
+            StructKid::Content { .. } => None,
+        })
+        .collect()
+}
+
+struct TreeReader<'a> {
+    doc: &'a Document,
+    role_map: Option<&'a Dictionary>,
+    seen: HashSet<ObjectId>,
+}
+
+impl TreeReader<'_> {
+    fn read_kids(
+        &mut self,
+        kids: &Object,
+        page: Option<ObjectId>,
+        depth: usize,
+        out: &mut Vec<StructKid>,
+    ) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        match kids {
+            Object::Array(items) => {
+                for item in items {
+                    self.read_kids(item, page, depth, out);
+                }
+            }
+            Object::Integer(mcid) => out.push(StructKid::Content { page, mcid: *mcid }),
+            Object::Reference(id) => {
+                if !self.seen.insert(*id) {
+                    return;
+                }
+                if let Ok(obj) = self.doc.get_object(*id) {
+                    self.read_kids(obj, page, depth, out);
+                }
+            }
+            Object::Dictionary(dict) => {
+                let page = match dict.get(b"Pg") {
+                    Ok(Object::Reference(id)) => Some(*id),
+                    _ => page,
+                };
+                if let Ok(Object::Name(raw_type)) = dict.get(b"S") {
+                    out.push(StructKid::Element(
+                        self.read_element(dict, raw_type, page, depth),
+                    ));
+                } else if let Ok(Object::Integer(mcid)) = dict.get(b"MCID") {
+                    // Marked-content reference, possibly on a different page.
+                    out.push(StructKid::Content { page, mcid: *mcid });
+                }
+                // Object references (OBJR) point at annotations and XObjects
+                // rather than text, and are skipped.
+            }
+            _ => {}
+        }
+    }
+
+    fn read_element(
+        &mut self,
+        dict: &Dictionary,
+        raw_type: &[u8],
+        page: Option<ObjectId>,
+        depth: usize,
+    ) -> StructNode {
+        let mut children = Vec::new();
+        if let Ok(k) = dict.get(b"K") {
+            self.read_kids(k, page, depth + 1, &mut children);
+        }
+        StructNode {
+            standard_type: standard_type(self.doc, self.role_map, raw_type),
+            alt: get_string_from_dict(dict, b"Alt"),
+            actual_text: get_string_from_dict(dict, b"ActualText"),
+            page,
+            children,
+        }
+    }
+}
+
+/// Map a (possibly custom) structure type to a standard one via the RoleMap.
+pub fn standard_type(doc: &Document, role_map: Option<&Dictionary>, raw_type: &[u8]) -> Vec<u8> {
+    let mut current = raw_type.to_vec();
+    let Some(role_map) = role_map else {
+        return current;
+    };
+    for _ in 0..MAX_ROLE_CHAIN {
+        let next = match role_map.get(&current) {
+            Ok(Object::Name(name)) => name.clone(),
+            Ok(Object::Reference(id)) => match doc.get_object(*id) {
+                Ok(Object::Name(name)) => name.clone(),
+                _ => break,
+            },
+            _ => break,
+        };
+        if next == current {
+            break;
+        }
+        current = next;
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{add_struct_tree, text_document};
+
+    #[test]
+    fn test_struct_tree_applies_role_map() {
+        let (mut doc, _) = text_document(&["one"]);
+        add_struct_tree(&mut doc);
+
+        let tree = struct_tree(&doc);
+        let mut seen = Vec::new();
+        for node in &tree {
+            node.walk(&mut |node, depth| {
+                seen.push((
+                    String::from_utf8_lossy(&node.standard_type).into_owned(),
+                    depth,
+                ));
+            });
+        }
+
+        // The custom `Chart` is mapped to `Figure`.
+        let expected = [("Document", 0), ("H1", 1), ("Figure", 1), ("Figure", 1)];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(ty, depth)| (ty.to_string(), *depth))
+            .collect();
+        assert_eq!(seen, expected);
+    }
+
+    #[test]
+    fn test_struct_tree_reads_content_and_alt() {
+        let (mut doc, _) = text_document(&["one"]);
+        add_struct_tree(&mut doc);
+
+        let tree = struct_tree(&doc);
+        let StructKid::Element(figure) = &tree[0].children[1] else {
+            panic!("expected figure element");
+        };
+        assert_eq!(figure.alt.as_deref(), Some("A bar chart"));
+        assert!(matches!(
+            figure.children[..],
+            [StructKid::Content { mcid: 0, .. }]
+        ));
+    }
+}
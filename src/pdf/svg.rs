@@ -0,0 +1,1014 @@
+//! Rendering page content as SVG.
+//!
+//! Content stream operators are translated one by one: paths become `<path>`
+//! elements (with clipping paths as `<clipPath>`), text becomes `<text>`
+//! positioned glyph run by glyph run, and images become embedded `<image>`s.
+//! Fonts are not embedded; text is set in a similar system font, so its
+//! appearance is approximate but it stays selectable and editable.
+//!
+//! Not supported: shadings, patterns (drawn as gray), soft masks, inline
+//! images, and blend modes.
+
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::layout::StandardFont;
+use crate::pdf::pages::{find_inherited, number, page_box, resolve, resource, MAX_FORM_DEPTH};
+use anyhow::{Context, Result};
+use base64::Engine;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// Stroke width used for PDF's zero-width ("thinnest possible") lines.
+const HAIRLINE_WIDTH: f32 = 0.25;
+
+/// An affine transformation `[a b c d e f]`, applied to row vectors as in
+/// the PDF specification.
+type Matrix = [f32; 6];
+
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Render a page (1-indexed) as a standalone SVG document.
+pub fn page_to_svg(doc: &Document, page_num: u32) -> Result<String> {
+    let pages = doc.get_pages();
+    let &page_id = pages
+        .get(&page_num)
+        .with_context(|| format!("Page {} is out of range (1-{})", page_num, pages.len()))?;
+
+    let crop = page_box(doc, page_id, b"CropBox");
+    let rotate = find_inherited(doc, page_id, b"Rotate")
+        .and_then(|obj| resolve(doc, obj).as_i64().ok())
+        .unwrap_or(0);
+    let (page_matrix, width, height) = page_matrix(crop, rotate);
+
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .with_context(|| format!("decode content of page {}", page_num))?;
+    let resources =
+        find_inherited(doc, page_id, b"Resources").and_then(|obj| resolve(doc, obj).as_dict().ok());
+
+    let mut renderer = Renderer {
+        doc,
+        out: String::new(),
+        state: GraphicsState::new(page_matrix),
+        stack: Vec::new(),
+        path: String::new(),
+        current: (0.0, 0.0),
+        pending_clip: None,
+        clip_count: 0,
+        text_matrix: IDENTITY,
+        line_matrix: IDENTITY,
+        fonts: HashMap::new(),
+    };
+    renderer.run(&content.operations, resources, 0);
+
+    let mut svg = String::new();
+    svg.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\" \
+         width=\"{w}pt\" height=\"{h}pt\" viewBox=\"0 0 {w} {h}\">",
+        w = num(width),
+        h = num(height)
+    );
+    svg.push_str(&renderer.out);
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// The transformation from default user space to SVG coordinates (origin
+/// at the top left, y pointing down), honoring the page's `Rotate`. Returns
+/// the matrix and the displayed width and height.
+fn page_matrix(crop: [f32; 4], rotate: i64) -> (Matrix, f32, f32) {
+    let [x0, y0, x1, y1] = crop;
+    let (w, h) = (x1 - x0, y1 - y0);
+    let origin = [1.0, 0.0, 0.0, 1.0, -x0, -y0];
+    let (flip, width, height) = match rotate.rem_euclid(360) {
+        90 => ([0.0, 1.0, 1.0, 0.0, 0.0, 0.0], h, w),
+        180 => ([-1.0, 0.0, 0.0, 1.0, w, 0.0], w, h),
+        270 => ([0.0, -1.0, -1.0, 0.0, h, w], h, w),
+        _ => ([1.0, 0.0, 0.0, -1.0, 0.0, h], w, h),
+    };
+    (multiply(&origin, &flip), width, height)
+}
+
+/// The transformation `a` followed by `b`.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+        a[4] * b[0] + a[5] * b[2] + b[4],
+        a[4] * b[1] + a[5] * b[3] + b[5],
+    ]
+}
+
+fn apply(m: &Matrix, x: f32, y: f32) -> (f32, f32) {
+    (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5])
+}
+
+#[derive(Clone)]
+struct GraphicsState<'a> {
+    /// Current transformation matrix, from user space to SVG coordinates.
+    ctm: Matrix,
+    fill: [f32; 3],
+    stroke: [f32; 3],
+    fill_alpha: f32,
+    stroke_alpha: f32,
+    line_width: f32,
+    line_cap: i64,
+    line_join: i64,
+    miter_limit: f32,
+    dash: Vec<f32>,
+    dash_phase: f32,
+    /// ID of the clip path in effect.
+    clip: Option<usize>,
+    font: Option<Rc<Font<'a>>>,
+    font_size: f32,
+    char_spacing: f32,
+    word_spacing: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+    rise: f32,
+    render_mode: i64,
+}
+
+impl GraphicsState<'_> {
+    fn new(ctm: Matrix) -> Self {
+        GraphicsState {
+            ctm,
+            fill: [0.0; 3],
+            stroke: [0.0; 3],
+            fill_alpha: 1.0,
+            stroke_alpha: 1.0,
+            line_width: 1.0,
+            line_cap: 0,
+            line_join: 0,
+            miter_limit: 10.0,
+            dash: Vec::new(),
+            dash_phase: 0.0,
+            clip: None,
+            font: None,
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+            render_mode: 0,
+        }
+    }
+}
+
+/// What a font needs to decode and advance over shown strings.
+pub(crate) struct Font<'a> {
+    pub(crate) encoding: Option<Encoding<'a>>,
+    /// Composite (`Type0`) fonts use two-byte codes.
+    pub(crate) two_byte: bool,
+    /// Embedded subsets only have glyphs for the characters the document
+    /// used.
+    subset: bool,
+    /// Glyph widths by character code, in thousandths of the font size.
+    widths: HashMap<u32, f32>,
+    standard: Option<StandardFont>,
+    missing_width: f32,
+    family: String,
+    bold: bool,
+    italic: bool,
+}
+
+impl<'a> Font<'a> {
+    pub(crate) fn load(doc: &'a Document, dict: &'a Dictionary) -> Self {
+        let base_font = match dict.get(b"BaseFont").map(|obj| resolve(doc, obj)) {
+            Ok(Object::Name(name)) => String::from_utf8_lossy(name).into_owned(),
+            _ => String::new(),
+        };
+        // Drop the subset tag of embedded subsets, e.g. "ABCDEF+Calibri".
+        let (name, subset) = match base_font.split_once('+') {
+            Some((tag, rest)) if tag.len() == 6 => (rest.to_string(), true),
+            _ => (base_font, false),
+        };
+        let two_byte = matches!(dict.get(b"Subtype"), Ok(Object::Name(n)) if n == b"Type0");
+
+        let mut widths = HashMap::new();
+        let mut missing_width = 500.0;
+        if two_byte {
+            let descendant = dict
+                .get(b"DescendantFonts")
+                .ok()
+                .and_then(|obj| resolve(doc, obj).as_array().ok())
+                .and_then(|fonts| fonts.first())
+                .and_then(|obj| resolve(doc, obj).as_dict().ok());
+            if let Some(descendant) = descendant {
+                missing_width = dict_number(doc, descendant, b"DW").unwrap_or(1000.0);
+                if let Some(w) = descendant
+                    .get(b"W")
+                    .ok()
+                    .and_then(|obj| resolve(doc, obj).as_array().ok())
+                {
+                    read_cid_widths(doc, w, &mut widths);
+                }
+            }
+        } else {
+            let first = dict_number(doc, dict, b"FirstChar").unwrap_or(0.0) as u32;
+            if let Some(w) = dict
+                .get(b"Widths")
+                .ok()
+                .and_then(|obj| resolve(doc, obj).as_array().ok())
+            {
+                // Type3 widths are in glyph space, which FontMatrix maps to
+                // text space; other fonts use thousandths.
+                let scale = dict
+                    .get(b"FontMatrix")
+                    .ok()
+                    .and_then(|obj| resolve(doc, obj).as_array().ok())
+                    .and_then(|matrix| matrix.first())
+                    .and_then(|a| number(resolve(doc, a)))
+                    .map_or(1.0, |a| a * 1000.0);
+                for (i, width) in w.iter().enumerate() {
+                    if let Some(width) = number(resolve(doc, width)) {
+                        widths.insert(first + i as u32, width * scale);
+                    }
+                }
+            }
+            if let Some(descriptor) = dict
+                .get(b"FontDescriptor")
+                .ok()
+                .and_then(|obj| resolve(doc, obj).as_dict().ok())
+            {
+                if let Some(width) = dict_number(doc, descriptor, b"MissingWidth") {
+                    missing_width = width;
+                }
+            }
+        }
+
+        let lower = name.to_ascii_lowercase();
+        let bold = lower.contains("bold") || lower.contains("black") || lower.contains("heavy");
+        let standard = if !widths.is_empty() {
+            None
+        } else if lower.starts_with("courier") {
+            Some(StandardFont::Courier)
+        } else if bold {
+            Some(StandardFont::HelveticaBold)
+        } else {
+            Some(StandardFont::Helvetica)
+        };
+
+        Font {
+            encoding: dict.get_font_encoding(doc).ok(),
+            two_byte,
+            subset,
+            widths,
+            standard,
+            missing_width,
+            family: name
+                .split(['-', ','])
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            bold,
+            italic: lower.contains("italic") || lower.contains("oblique"),
+        }
+    }
+
+    /// Character codes in a shown string.
+    pub(crate) fn codes(&self, bytes: &[u8]) -> Vec<u32> {
+        if self.two_byte {
+            bytes
+                .chunks(2)
+                .map(|pair| pair.iter().fold(0, |code, &b| (code << 8) | b as u32))
+                .collect()
+        } else {
+            bytes.iter().map(|&b| b as u32).collect()
+        }
+    }
+
+    pub(crate) fn width(&self, code: u32) -> f32 {
+        if let Some(&width) = self.widths.get(&code) {
+            return width;
+        }
+        match (self.standard, u8::try_from(code)) {
+            (Some(font), Ok(byte)) => font.char_width(byte) as f32,
+            _ => self.missing_width,
+        }
+    }
+
+    /// Whether showing `code` draws its glyph, as far as the widths tell:
+    /// subsets give unused codes no width (or zero).
+    pub(crate) fn has_glyph(&self, code: u32) -> bool {
+        !self.subset || self.widths.get(&code).is_some_and(|&width| width > 0.0)
+    }
+
+    /// A CSS `font-family` list: the font's own name, then a generic family.
+    fn css_family(&self) -> String {
+        let lower = self.family.to_ascii_lowercase();
+        let generic = if lower.contains("courier") || lower.contains("mono") {
+            "monospace"
+        } else if lower.contains("times") || (lower.contains("serif") && !lower.contains("sans")) {
+            "serif"
+        } else {
+            "sans-serif"
+        };
+        if self.family.is_empty() {
+            generic.to_string()
+        } else {
+            format!("'{}', {}", self.family.replace('\'', ""), generic)
+        }
+    }
+}
+
+/// Read a CIDFont `W` array: `c [w1 w2 ...]` and `c_first c_last w` runs.
+fn read_cid_widths(doc: &Document, w: &[Object], widths: &mut HashMap<u32, f32>) {
+    let mut i = 0;
+    while i + 1 < w.len() {
+        let Some(first) = number(resolve(doc, &w[i])) else {
+            break;
+        };
+        let first = first as u32;
+        match resolve(doc, &w[i + 1]) {
+            Object::Array(run) => {
+                for (offset, width) in run.iter().enumerate() {
+                    if let Some(width) = number(resolve(doc, width)) {
+                        widths.insert(first + offset as u32, width);
+                    }
+                }
+                i += 2;
+            }
+            last => {
+                let (Some(last), Some(width)) = (
+                    number(last),
+                    w.get(i + 2).and_then(|obj| number(resolve(doc, obj))),
+                ) else {
+                    break;
+                };
+                for code in first..=(last as u32).min(first + 0xFFFF) {
+                    widths.insert(code, width);
+                }
+                i += 3;
+            }
+        }
+    }
+}
+
+fn dict_number(doc: &Document, dict: &Dictionary, key: &[u8]) -> Option<f32> {
+    dict.get(key).ok().and_then(|obj| number(resolve(doc, obj)))
+}
+
+struct Renderer<'a> {
+    doc: &'a Document,
+    out: String,
+    state: GraphicsState<'a>,
+    stack: Vec<GraphicsState<'a>>,
+    /// Path under construction, in SVG coordinates.
+    path: String,
+    /// Current point, in user space.
+    current: (f32, f32),
+    /// Fill rule of a `W`/`W*` waiting for the next painting operator.
+    pending_clip: Option<&'static str>,
+    clip_count: usize,
+    text_matrix: Matrix,
+    line_matrix: Matrix,
+    fonts: HashMap<ObjectId, Rc<Font<'a>>>,
+}
+
+impl<'a> Renderer<'a> {
+    fn run(&mut self, operations: &[Operation], resources: Option<&'a Dictionary>, depth: usize) {
+        for op in operations {
+            let operands: Vec<f32> = op.operands.iter().filter_map(number).collect();
+            let arg = |i: usize| operands.get(i).copied().unwrap_or(0.0);
+            match op.operator.as_str() {
+                // Graphics state
+                "q" => self.stack.push(self.state.clone()),
+                "Q" => {
+                    if let Some(state) = self.stack.pop() {
+                        self.state = state;
+                    }
+                }
+                "cm" if operands.len() == 6 => {
+                    let m = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                    self.state.ctm = multiply(&m, &self.state.ctm);
+                }
+                "w" => self.state.line_width = arg(0),
+                "J" => self.state.line_cap = arg(0) as i64,
+                "j" => self.state.line_join = arg(0) as i64,
+                "M" => self.state.miter_limit = arg(0),
+                "d" => {
+                    if let Some(Object::Array(dash)) = op.operands.first() {
+                        self.state.dash = dash.iter().filter_map(number).collect();
+                        self.state.dash_phase = op.operands.get(1).and_then(number).unwrap_or(0.0);
+                    }
+                }
+                "gs" => self.ext_g_state(op, resources),
+
+                // Color
+                "g" => self.state.fill = gray(arg(0)),
+                "G" => self.state.stroke = gray(arg(0)),
+                "rg" => self.state.fill = [arg(0), arg(1), arg(2)],
+                "RG" => self.state.stroke = [arg(0), arg(1), arg(2)],
+                "k" => self.state.fill = cmyk(arg(0), arg(1), arg(2), arg(3)),
+                "K" => self.state.stroke = cmyk(arg(0), arg(1), arg(2), arg(3)),
+                "cs" => self.state.fill = [0.0; 3],
+                "CS" => self.state.stroke = [0.0; 3],
+                "sc" | "scn" => self.state.fill = components(op, &operands),
+                "SC" | "SCN" => self.state.stroke = components(op, &operands),
+
+                // Path construction
+                "m" => {
+                    self.current = (arg(0), arg(1));
+                    self.path_op('M', &[self.current]);
+                }
+                "l" => {
+                    self.current = (arg(0), arg(1));
+                    self.path_op('L', &[self.current]);
+                }
+                "c" => {
+                    self.path_op('C', &[(arg(0), arg(1)), (arg(2), arg(3)), (arg(4), arg(5))]);
+                    self.current = (arg(4), arg(5));
+                }
+                "v" => {
+                    self.path_op('C', &[self.current, (arg(0), arg(1)), (arg(2), arg(3))]);
+                    self.current = (arg(2), arg(3));
+                }
+                "y" => {
+                    self.path_op('C', &[(arg(0), arg(1)), (arg(2), arg(3)), (arg(2), arg(3))]);
+                    self.current = (arg(2), arg(3));
+                }
+                "h" => self.path.push('Z'),
+                "re" => {
+                    let (x, y, w, h) = (arg(0), arg(1), arg(2), arg(3));
+                    self.path_op('M', &[(x, y)]);
+                    self.path_op('L', &[(x + w, y), (x + w, y + h), (x, y + h)]);
+                    self.path.push('Z');
+                    self.current = (x, y);
+                }
+
+                // Path painting
+                "S" => self.paint(false, true, "nonzero"),
+                "s" => {
+                    self.path.push('Z');
+                    self.paint(false, true, "nonzero");
+                }
+                "f" | "F" => self.paint(true, false, "nonzero"),
+                "f*" => self.paint(true, false, "evenodd"),
+                "B" => self.paint(true, true, "nonzero"),
+                "B*" => self.paint(true, true, "evenodd"),
+                "b" => {
+                    self.path.push('Z');
+                    self.paint(true, true, "nonzero");
+                }
+                "b*" => {
+                    self.path.push('Z');
+                    self.paint(true, true, "evenodd");
+                }
+                "n" => self.paint(false, false, "nonzero"),
+                "W" => self.pending_clip = Some("nonzero"),
+                "W*" => self.pending_clip = Some("evenodd"),
+
+                // Text
+                "BT" => {
+                    self.text_matrix = IDENTITY;
+                    self.line_matrix = IDENTITY;
+                }
+                "Tf" => {
+                    self.state.font = match op.operands.first() {
+                        Some(Object::Name(name)) => self.font(resources, name),
+                        _ => None,
+                    };
+                    self.state.font_size = arg(0);
+                }
+                "Tc" => self.state.char_spacing = arg(0),
+                "Tw" => self.state.word_spacing = arg(0),
+                "Tz" => self.state.horizontal_scaling = arg(0) / 100.0,
+                "TL" => self.state.leading = arg(0),
+                "Ts" => self.state.rise = arg(0),
+                "Tr" => self.state.render_mode = arg(0) as i64,
+                "Td" => self.move_line(arg(0), arg(1)),
+                "TD" => {
+                    self.state.leading = -arg(1);
+                    self.move_line(arg(0), arg(1));
+                }
+                "Tm" if operands.len() == 6 => {
+                    self.text_matrix = [arg(0), arg(1), arg(2), arg(3), arg(4), arg(5)];
+                    self.line_matrix = self.text_matrix;
+                }
+                "T*" => self.move_line(0.0, -self.state.leading),
+                "Tj" => self.show_operand(op.operands.first()),
+                "'" => {
+                    self.move_line(0.0, -self.state.leading);
+                    self.show_operand(op.operands.first());
+                }
+                "\"" => {
+                    self.state.word_spacing = arg(0);
+                    self.state.char_spacing = arg(1);
+                    self.move_line(0.0, -self.state.leading);
+                    self.show_operand(op.operands.get(2));
+                }
+                "TJ" => {
+                    let Some(Object::Array(items)) = op.operands.first() else {
+                        continue;
+                    };
+                    for item in items {
+                        match item {
+                            Object::String(bytes, _) => self.show(bytes),
+                            _ => {
+                                let adjust = number(item).unwrap_or(0.0);
+                                let tx = -adjust / 1000.0
+                                    * self.state.font_size
+                                    * self.state.horizontal_scaling;
+                                self.advance(tx);
+                            }
+                        }
+                    }
+                }
+
+                // XObjects
+                "Do" => {
+                    if let Some(Object::Name(name)) = op.operands.first() {
+                        self.draw_xobject(resources, name, depth);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn path_op(&mut self, command: char, points: &[(f32, f32)]) {
+        self.path.push(command);
+        for (i, &(x, y)) in points.iter().enumerate() {
+            let (x, y) = apply(&self.state.ctm, x, y);
+            if i > 0 {
+                self.path.push(' ');
+            }
+            let _ = write!(self.path, "{} {}", num(x), num(y));
+        }
+    }
+
+    fn paint(&mut self, fill: bool, stroke: bool, rule: &str) {
+        let path = std::mem::take(&mut self.path);
+        if path.is_empty() {
+            self.pending_clip = None;
+            return;
+        }
+
+        if fill || stroke {
+            let state = &self.state;
+            let mut attrs = String::new();
+            if fill {
+                let _ = write!(attrs, " fill=\"{}\"", hex(state.fill));
+                if rule == "evenodd" {
+                    attrs.push_str(" fill-rule=\"evenodd\"");
+                }
+                if state.fill_alpha < 1.0 {
+                    let _ = write!(attrs, " fill-opacity=\"{}\"", num(state.fill_alpha));
+                }
+            } else {
+                attrs.push_str(" fill=\"none\"");
+            }
+            if stroke {
+                attrs.push_str(&self.stroke_attributes());
+            }
+            let clip = self.clip_attribute();
+            let _ = writeln!(self.out, "<path d=\"{}\"{}{}/>", path, attrs, clip);
+        }
+
+        if let Some(clip_rule) = self.pending_clip.take() {
+            self.clip_count += 1;
+            let id = self.clip_count;
+            // Nested clips intersect with the one already in effect.
+            let parent = self.clip_attribute();
+            let _ = writeln!(
+                self.out,
+                "<clipPath id=\"clip{}\"{}><path d=\"{}\" clip-rule=\"{}\"/></clipPath>",
+                id, parent, path, clip_rule
+            );
+            self.state.clip = Some(id);
+        }
+    }
+
+    fn stroke_attributes(&self) -> String {
+        let state = &self.state;
+        let ctm = &state.ctm;
+        let scale = (ctm[0] * ctm[3] - ctm[1] * ctm[2]).abs().sqrt();
+        let width = if state.line_width > 0.0 {
+            state.line_width * scale
+        } else {
+            HAIRLINE_WIDTH
+        };
+        let mut attrs = format!(
+            " stroke=\"{}\" stroke-width=\"{}\"",
+            hex(state.stroke),
+            num(width)
+        );
+        match state.line_cap {
+            1 => attrs.push_str(" stroke-linecap=\"round\""),
+            2 => attrs.push_str(" stroke-linecap=\"square\""),
+            _ => {}
+        }
+        match state.line_join {
+            1 => attrs.push_str(" stroke-linejoin=\"round\""),
+            2 => attrs.push_str(" stroke-linejoin=\"bevel\""),
+            _ => {
+                if state.miter_limit != 4.0 {
+                    let _ = write!(attrs, " stroke-miterlimit=\"{}\"", num(state.miter_limit));
+                }
+            }
+        }
+        if !state.dash.is_empty() && state.dash.iter().any(|&d| d > 0.0) {
+            let dash: Vec<String> = state.dash.iter().map(|&d| num(d * scale)).collect();
+            let _ = write!(attrs, " stroke-dasharray=\"{}\"", dash.join(" "));
+            if state.dash_phase != 0.0 {
+                let _ = write!(
+                    attrs,
+                    " stroke-dashoffset=\"{}\"",
+                    num(state.dash_phase * scale)
+                );
+            }
+        }
+        if state.stroke_alpha < 1.0 {
+            let _ = write!(attrs, " stroke-opacity=\"{}\"", num(state.stroke_alpha));
+        }
+        attrs
+    }
+
+    fn clip_attribute(&self) -> String {
+        match self.state.clip {
+            Some(id) => format!(" clip-path=\"url(#clip{})\"", id),
+            None => String::new(),
+        }
+    }
+
+    fn ext_g_state(&mut self, op: &Operation, resources: Option<&'a Dictionary>) {
+        let Some(Object::Name(name)) = op.operands.first() else {
+            return;
+        };
+        let Some(params) =
+            resource(self.doc, resources, b"ExtGState", name).and_then(|obj| obj.as_dict().ok())
+        else {
+            return;
+        };
+        if let Some(width) = dict_number(self.doc, params, b"LW") {
+            self.state.line_width = width;
+        }
+        if let Some(alpha) = dict_number(self.doc, params, b"ca") {
+            self.state.fill_alpha = alpha;
+        }
+        if let Some(alpha) = dict_number(self.doc, params, b"CA") {
+            self.state.stroke_alpha = alpha;
+        }
+    }
+
+    fn font(&mut self, resources: Option<&'a Dictionary>, name: &[u8]) -> Option<Rc<Font<'a>>> {
+        let doc = self.doc;
+        let fonts = resources?.get(b"Font").ok().map(|obj| resolve(doc, obj))?;
+        let entry = fonts.as_dict().ok()?.get(name).ok()?;
+        if let Object::Reference(id) = entry {
+            if let Some(font) = self.fonts.get(id) {
+                return Some(font.clone());
+            }
+            let font = Rc::new(Font::load(doc, doc.get_dictionary(*id).ok()?));
+            self.fonts.insert(*id, font.clone());
+            return Some(font);
+        }
+        Some(Rc::new(Font::load(doc, entry.as_dict().ok()?)))
+    }
+
+    fn move_line(&mut self, tx: f32, ty: f32) {
+        self.line_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, ty], &self.line_matrix);
+        self.text_matrix = self.line_matrix;
+    }
+
+    fn advance(&mut self, tx: f32) {
+        self.text_matrix = multiply(&[1.0, 0.0, 0.0, 1.0, tx, 0.0], &self.text_matrix);
+    }
+
+    fn show_operand(&mut self, operand: Option<&Object>) {
+        if let Some(Object::String(bytes, _)) = operand {
+            self.show(bytes);
+        }
+    }
+
+    /// Emit a `<text>` element for a shown string and advance past it.
+    fn show(&mut self, bytes: &[u8]) {
+        let Some(font) = self.state.font.clone() else {
+            return;
+        };
+        let state = &self.state;
+        let size = state.font_size;
+        let scaling = state.horizontal_scaling;
+
+        // Glyph space is flipped so that SVG's downward y points up in text
+        // space, then placed by the text and current transformation matrices.
+        let glyphs = [scaling, 0.0, 0.0, -1.0, 0.0, state.rise];
+        let matrix = multiply(&multiply(&glyphs, &self.text_matrix), &state.ctm);
+
+        let text = font
+            .encoding
+            .as_ref()
+            .and_then(|encoding| Document::decode_text(encoding, bytes).ok())
+            .unwrap_or_default();
+        // Invisible text (e.g. an OCR layer) and clipping-only text are not drawn.
+        let visible = !matches!(state.render_mode, 3 | 7);
+        if visible && !text.trim().is_empty() {
+            let mut attrs = format!(" font-family=\"{}\"", escape(&font.css_family()));
+            if font.bold {
+                attrs.push_str(" font-weight=\"bold\"");
+            }
+            if font.italic {
+                attrs.push_str(" font-style=\"italic\"");
+            }
+            let fill = matches!(state.render_mode, 0 | 2 | 4 | 6);
+            let stroke = matches!(state.render_mode, 1 | 2 | 5 | 6);
+            if fill {
+                let _ = write!(attrs, " fill=\"{}\"", hex(state.fill));
+                if state.fill_alpha < 1.0 {
+                    let _ = write!(attrs, " fill-opacity=\"{}\"", num(state.fill_alpha));
+                }
+            } else {
+                attrs.push_str(" fill=\"none\"");
+            }
+            if stroke {
+                attrs.push_str(&self.stroke_attributes());
+            }
+            attrs.push_str(&self.clip_attribute());
+            let _ = writeln!(
+                self.out,
+                "<text transform=\"matrix({})\" font-size=\"{}\"{} xml:space=\"preserve\">{}</text>",
+                matrix.map(num).join(" "),
+                num(size),
+                attrs,
+                escape(&text)
+            );
+        }
+
+        let state = &self.state;
+        let mut tx = 0.0;
+        for code in font.codes(bytes) {
+            let mut advance = font.width(code) / 1000.0 * size + state.char_spacing;
+            if code == 32 && !font.two_byte {
+                advance += state.word_spacing;
+            }
+            tx += advance * scaling;
+        }
+        self.advance(tx);
+    }
+
+    fn draw_xobject(&mut self, resources: Option<&'a Dictionary>, name: &[u8], depth: usize) {
+        let doc = self.doc;
+        let Some(Object::Stream(stream)) = resource(doc, resources, b"XObject", name) else {
+            return;
+        };
+        match stream.dict.get(b"Subtype") {
+            Ok(Object::Name(subtype)) if subtype == b"Form" => {
+                if depth >= MAX_FORM_DEPTH {
+                    return;
+                }
+                let Ok(bytes) = stream_bytes(stream) else {
+                    return;
+                };
+                let Ok(content) = Content::decode(&bytes) else {
+                    return;
+                };
+                let form_resources = stream
+                    .dict
+                    .get(b"Resources")
+                    .ok()
+                    .and_then(|obj| resolve(doc, obj).as_dict().ok())
+                    .or(resources);
+
+                self.stack.push(self.state.clone());
+                if let Some(Object::Array(m)) =
+                    stream.dict.get(b"Matrix").ok().map(|obj| resolve(doc, obj))
+                {
+                    let m: Vec<f32> = m.iter().filter_map(number).collect();
+                    if let [a, b, c, d, e, f] = m[..] {
+                        self.state.ctm = multiply(&[a, b, c, d, e, f], &self.state.ctm);
+                    }
+                }
+                let path = std::mem::take(&mut self.path);
+                self.run(&content.operations, form_resources, depth + 1);
+                self.path = path;
+                if let Some(state) = self.stack.pop() {
+                    self.state = state;
+                }
+            }
+            Ok(Object::Name(subtype)) if subtype == b"Image" => {
+                let Some((mime, data)) = image_data(doc, stream) else {
+                    let _ = writeln!(
+                        self.out,
+                        "<!-- image {} not converted -->",
+                        escape(&String::from_utf8_lossy(name)).replace("--", "- -")
+                    );
+                    return;
+                };
+                // Images occupy the unit square, with their first row at the top.
+                let matrix = multiply(&[1.0, 0.0, 0.0, -1.0, 0.0, 1.0], &self.state.ctm);
+                let clip = self.clip_attribute();
+                let _ = writeln!(
+                    self.out,
+                    "<image width=\"1\" height=\"1\" preserveAspectRatio=\"none\" \
+                     transform=\"matrix({})\"{} xlink:href=\"data:{};base64,{}\"/>",
+                    matrix.map(num).join(" "),
+                    clip,
+                    mime,
+                    base64::engine::general_purpose::STANDARD.encode(data)
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Encode an image XObject for embedding: JPEG data as is, and 8-bit gray
+/// or RGB samples as PNG. Returns the MIME type and bytes.
+fn image_data(doc: &Document, stream: &lopdf::Stream) -> Option<(&'static str, Vec<u8>)> {
+    let dict = &stream.dict;
+    let filters: Vec<&[u8]> = match dict.get(b"Filter").ok().map(|obj| resolve(doc, obj)) {
+        Some(Object::Name(name)) => vec![name.as_slice()],
+        Some(Object::Array(names)) => names.iter().filter_map(|n| n.as_name().ok()).collect(),
+        _ => Vec::new(),
+    };
+    if filters == [b"DCTDecode".as_slice()] {
+        return Some(("image/jpeg", stream.content.clone()));
+    }
+
+    let width = dict_number(doc, dict, b"Width")? as u32;
+    let height = dict_number(doc, dict, b"Height")? as u32;
+    if dict_number(doc, dict, b"BitsPerComponent") != Some(8.0) {
+        return None;
+    }
+    let color = match dict.get(b"ColorSpace").ok().map(|obj| resolve(doc, obj)) {
+        Some(Object::Name(name)) if name == b"DeviceGray" => png::ColorType::Grayscale,
+        Some(Object::Name(name)) if name == b"DeviceRGB" => png::ColorType::Rgb,
+        Some(Object::Array(space))
+            if space.first().and_then(|n| n.as_name().ok()) == Some(b"ICCBased".as_slice()) =>
+        {
+            let profile = space.get(1).map(|obj| resolve(doc, obj))?;
+            let components = profile
+                .as_stream()
+                .ok()
+                .and_then(|s| dict_number(doc, &s.dict, b"N"));
+            match components.map(|n| n as u32) {
+                Some(1) => png::ColorType::Grayscale,
+                Some(3) => png::ColorType::Rgb,
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+    let samples = stream_bytes(stream).ok()?;
+    let channels = if color == png::ColorType::Rgb { 3 } else { 1 };
+    if samples.len() != (width * height * channels) as usize {
+        return None;
+    }
+
+    let mut encoded = Vec::new();
+    let mut encoder = png::Encoder::new(&mut encoded, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&samples))
+        .ok()?;
+    Some(("image/png", encoded))
+}
+
+fn gray(level: f32) -> [f32; 3] {
+    [level; 3]
+}
+
+fn cmyk(c: f32, m: f32, y: f32, k: f32) -> [f32; 3] {
+    [
+        (1.0 - c) * (1.0 - k),
+        (1.0 - m) * (1.0 - k),
+        (1.0 - y) * (1.0 - k),
+    ]
+}
+
+/// A color from `sc`/`scn` operands, interpreted by component count.
+/// Patterns (named operands) are shown as mid-gray.
+fn components(op: &Operation, values: &[f32]) -> [f32; 3] {
+    if matches!(op.operands.last(), Some(Object::Name(_))) {
+        return gray(0.5);
+    }
+    match *values {
+        [g] => gray(g),
+        [r, g, b] => [r, g, b],
+        [c, m, y, k] => cmyk(c, m, y, k),
+        _ => [0.0; 3],
+    }
+}
+
+fn hex(color: [f32; 3]) -> String {
+    let [r, g, b] = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Format a coordinate compactly, with at most three decimals.
+fn num(value: f32) -> String {
+    let s = format!("{:.3}", value);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            // Control characters are not allowed in XML.
+            c if c.is_control() && c != '\t' && c != '\n' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+    use lopdf::{dictionary, Stream};
+
+    fn set_content(doc: &mut Document, page_id: ObjectId, content: &[u8]) {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+        doc.get_dictionary_mut(page_id)
+            .unwrap()
+            .set("Contents", content_id);
+    }
+
+    #[test]
+    fn test_page_to_svg_paths() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        set_content(
+            &mut doc,
+            page_ids[0],
+            b"1 0 0 RG 2 w 10 10 m 100 10 l S 0 0 1 rg 2 0 0 2 0 0 cm 10 10 5 5 re f",
+        );
+
+        let svg = page_to_svg(&doc, 1).unwrap();
+        assert!(svg.contains("viewBox=\"0 0 612 792\""));
+        assert!(svg.contains(
+            "<path d=\"M10 782L100 782\" fill=\"none\" stroke=\"#ff0000\" stroke-width=\"2\" \
+             stroke-miterlimit=\"10\"/>"
+        ));
+        assert!(svg.contains("<path d=\"M20 772L30 772 30 762 20 762Z\" fill=\"#0000ff\"/>"));
+    }
+
+    #[test]
+    fn test_page_to_svg_text() {
+        let (doc, _) = text_document(&["Fish & Chips"]);
+        let svg = page_to_svg(&doc, 1).unwrap();
+        assert!(svg.contains(
+            "<text transform=\"matrix(1 0 0 1 72 72)\" font-size=\"12\" \
+             font-family=\"'Helvetica', sans-serif\" fill=\"#000000\" \
+             xml:space=\"preserve\">Fish &amp; Chips</text>"
+        ));
+    }
+
+    #[test]
+    fn test_page_to_svg_clips() {
+        let (mut doc, page_ids) = text_document(&[""]);
+        set_content(
+            &mut doc,
+            page_ids[0],
+            b"q 0 0 50 50 re W n 0 0 100 100 re f Q 0 0 1 1 re f",
+        );
+
+        let svg = page_to_svg(&doc, 1).unwrap();
+        assert!(svg.contains(
+            "<clipPath id=\"clip1\"><path d=\"M0 792L50 792 50 742 0 742Z\" \
+             clip-rule=\"nonzero\"/></clipPath>"
+        ));
+        assert!(svg.contains("fill=\"#000000\" clip-path=\"url(#clip1)\"/>"));
+        // The clip ends with the graphics state it was set in.
+        assert!(svg.contains("<path d=\"M0 792L1 792 1 791 0 791Z\" fill=\"#000000\"/>"));
+    }
+
+    #[test]
+    fn test_page_matrix_rotation() {
+        let (m, w, h) = page_matrix([0.0, 0.0, 600.0, 800.0], 90);
+        assert_eq!((w, h), (800.0, 600.0));
+        // The top-left corner ends up top-right when rotated clockwise.
+        assert_eq!(apply(&m, 0.0, 800.0), (800.0, 0.0));
+
+        let (m, _, _) = page_matrix([10.0, 20.0, 610.0, 820.0], 0);
+        assert_eq!(apply(&m, 10.0, 820.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_num() {
+        assert_eq!(num(1.0), "1");
+        assert_eq!(num(0.5), "0.5");
+        assert_eq!(num(-0.0001), "0");
+        assert_eq!(num(2.12345), "2.123");
+    }
+}
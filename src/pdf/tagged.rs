@@ -0,0 +1,632 @@
+//! Text extraction driven by the logical structure tree of tagged PDFs.
+//!
+//! Instead of reconstructing reading order from glyph positions, the text of
+//! each marked-content sequence is collected by MCID and then emitted in the
+//! order of the structure tree: headings become `#` lines, list items `- `
+//! lines, table rows `|`-separated cells, and paragraphs plain lines
+//! separated by blank lines. Content marked as an `Artifact` (running
+//! headers and footers, page numbers) is left out.
+
+use crate::pdf::inspect::stream_bytes;
+use crate::pdf::pages::{
+    find_inherited, inherited_attribute, number, resolve, resource, MAX_FORM_DEPTH,
+};
+use crate::pdf::structure::{struct_tree, StructKid, StructNode};
+use anyhow::Result;
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Encoding, Object, ObjectId};
+use std::collections::{BTreeMap, HashMap};
+
+/// `TJ` adjustments (in thousandths of a text space unit) more negative than
+/// this are treated as word spaces.
+const TJ_SPACE_THRESHOLD: f32 = -250.0;
+
+/// Structure types whose content forms one block of text.
+const PARAGRAPH_TYPES: &[&[u8]] = &[
+    b"P",
+    b"Caption",
+    b"BlockQuote",
+    b"Note",
+    b"Code",
+    b"TOCI",
+    b"Index",
+    b"Formula",
+    b"Title",
+    b"Lbl",
+];
+
+/// Extract text per page (1-indexed) by following the structure tree.
+///
+/// Returns `None` if the document has no structure tree. Pages with no
+/// tagged content are absent from the map, so callers can fall back to
+/// layout-based extraction for them; so are pages that show text from form
+/// XObjects, whose marked content isn't read. A block that continues onto
+/// another page is split where its content moves there.
+pub fn extract_tagged_text(doc: &Document) -> Option<BTreeMap<u32, String>> {
+    let tree = struct_tree(doc);
+    if tree.is_empty() {
+        return None;
+    }
+
+    let page_ids = doc.get_pages();
+    let page_numbers: HashMap<ObjectId, u32> =
+        page_ids.iter().map(|(&num, &id)| (id, num)).collect();
+    let mut extractor = Extractor {
+        text: TextResolver::new(doc),
+        blocks: Vec::new(),
+    };
+    for node in &tree {
+        extractor.emit(node);
+    }
+
+    let mut pages: BTreeMap<u32, Vec<Block>> = BTreeMap::new();
+    for block in extractor.blocks {
+        if let Some(&num) = block.page.and_then(|id| page_numbers.get(&id)) {
+            pages.entry(num).or_default().push(block);
+        }
+    }
+    pages.retain(|num, _| !shows_form_text(doc, page_ids[num]));
+    Some(
+        pages
+            .into_iter()
+            .map(|(num, blocks)| (num, render_blocks(&blocks)))
+            .collect(),
+    )
+}
+
+/// Collect the text of each marked-content sequence on a page, by MCID.
+///
+/// Text inside `Artifact` sequences is skipped, as is text outside any
+/// sequence with an MCID. Form XObjects are not descended into.
+pub fn marked_content_text(doc: &Document, page_id: ObjectId) -> Result<HashMap<i64, String>> {
+    let content = doc.get_and_decode_page_content(page_id)?;
+    let fonts = doc.get_page_fonts(page_id)?;
+    let mut encodings: HashMap<Vec<u8>, Option<Encoding>> = HashMap::new();
+
+    let mut texts: HashMap<i64, String> = HashMap::new();
+    let mut stack: Vec<Marked> = Vec::new();
+    let mut font: Option<Vec<u8>> = None;
+    let mut line_break = false;
+
+    for op in &content.operations {
+        match op.operator.as_str() {
+            "BMC" => stack.push(marked(op, None)),
+            "BDC" => stack.push(marked(op, properties(doc, page_id, op))),
+            "EMC" => {
+                stack.pop();
+            }
+            "Tf" => {
+                if let Some(Object::Name(name)) = op.operands.first() {
+                    font = Some(name.clone());
+                }
+            }
+            "Td" | "TD" | "T*" | "Tm" => line_break = true,
+            "Tj" | "'" | "\"" | "TJ" => {
+                if matches!(op.operator.as_str(), "'" | "\"") {
+                    line_break = true;
+                }
+                if stack.contains(&Marked::Artifact) {
+                    continue;
+                }
+                let Some(mcid) = stack.iter().rev().find_map(|m| match m {
+                    Marked::Mcid(mcid) => Some(*mcid),
+                    _ => None,
+                }) else {
+                    continue;
+                };
+                let encoding = font.as_ref().and_then(|name| {
+                    encodings
+                        .entry(name.clone())
+                        .or_insert_with(|| {
+                            fonts
+                                .get(name)
+                                .and_then(|font| font.get_font_encoding(doc).ok())
+                        })
+                        .as_ref()
+                });
+                let Some(encoding) = encoding else {
+                    continue;
+                };
+
+                let shown = shown_text(op, encoding);
+                let text = texts.entry(mcid).or_default();
+                if line_break && !text.is_empty() && !text.ends_with(char::is_whitespace) {
+                    text.push(' ');
+                }
+                line_break = false;
+                text.push_str(&shown);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(texts)
+}
+
+/// Whether a page's content draws a form XObject that shows text.
+fn shows_form_text(doc: &Document, page_id: ObjectId) -> bool {
+    let Ok(content) = doc.get_and_decode_page_content(page_id) else {
+        return false;
+    };
+    let resources =
+        find_inherited(doc, page_id, b"Resources").and_then(|obj| resolve(doc, obj).as_dict().ok());
+    draws_text_form(doc, &content.operations, resources, 0)
+}
+
+fn draws_text_form<'a>(
+    doc: &'a Document,
+    operations: &[Operation],
+    resources: Option<&'a Dictionary>,
+    depth: usize,
+) -> bool {
+    operations.iter().any(|op| {
+        if op.operator != "Do" || depth >= MAX_FORM_DEPTH {
+            return false;
+        }
+        let Some(Object::Name(name)) = op.operands.first() else {
+            return false;
+        };
+        let Some(Object::Stream(stream)) = resource(doc, resources, b"XObject", name) else {
+            return false;
+        };
+        let is_form = stream
+            .dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .is_ok_and(|subtype| subtype == b"Form");
+        let Some(content) = stream_bytes(stream)
+            .ok()
+            .and_then(|bytes| Content::decode(&bytes).ok())
+            .filter(|_| is_form)
+        else {
+            return false;
+        };
+        let form_resources = stream
+            .dict
+            .get(b"Resources")
+            .ok()
+            .and_then(|obj| resolve(doc, obj).as_dict().ok())
+            .or(resources);
+        content
+            .operations
+            .iter()
+            .any(|op| matches!(op.operator.as_str(), "Tj" | "TJ" | "'" | "\""))
+            || draws_text_form(doc, &content.operations, form_resources, depth + 1)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marked {
+    Mcid(i64),
+    Artifact,
+    Other,
+}
+
+fn marked(op: &Operation, properties: Option<&Dictionary>) -> Marked {
+    if let Some(Object::Name(tag)) = op.operands.first() {
+        if tag == b"Artifact" {
+            return Marked::Artifact;
+        }
+    }
+    match properties.and_then(|p| p.get(b"MCID").ok()) {
+        Some(Object::Integer(mcid)) => Marked::Mcid(*mcid),
+        _ => Marked::Other,
+    }
+}
+
+/// The property list of a `BDC` operator: inline, or named in the page's
+/// `Properties` resources.
+fn properties<'a>(
+    doc: &'a Document,
+    page_id: ObjectId,
+    op: &'a Operation,
+) -> Option<&'a Dictionary> {
+    match op.operands.get(1)? {
+        Object::Dictionary(dict) => Some(dict),
+        Object::Name(name) => {
+            let resources = inherited_attribute(doc, page_id, b"Resources")?;
+            let Object::Dictionary(resources) = resources else {
+                return None;
+            };
+            let id = resources
+                .get(b"Properties")
+                .ok()
+                .and_then(|props| match props {
+                    Object::Dictionary(props) => props.get(name).ok()?.as_reference().ok(),
+                    Object::Reference(id) => doc
+                        .get_dictionary(*id)
+                        .ok()?
+                        .get(name)
+                        .ok()?
+                        .as_reference()
+                        .ok(),
+                    _ => None,
+                })?;
+            doc.get_dictionary(id).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Decode the strings shown by a text-showing operator.
+pub(crate) fn shown_text(op: &Operation, encoding: &Encoding) -> String {
+    let mut out = String::new();
+    let decode = |bytes: &[u8]| Document::decode_text(encoding, bytes).unwrap_or_default();
+    match op.operator.as_str() {
+        "TJ" => {
+            let Some(Object::Array(items)) = op.operands.first() else {
+                return out;
+            };
+            for item in items {
+                match item {
+                    Object::String(bytes, _) => out.push_str(&decode(bytes)),
+                    Object::Integer(_) | Object::Real(_) => {
+                        let adjust = number(item).unwrap_or(0.0);
+                        if adjust < TJ_SPACE_THRESHOLD && !out.ends_with(' ') {
+                            out.push(' ');
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {
+            if let Some(Object::String(bytes, _)) = op.operands.last() {
+                out.push_str(&decode(bytes));
+            }
+        }
+    }
+    out
+}
+
+/// A unit of extracted text.
+struct Block {
+    page: Option<ObjectId>,
+    kind: BlockKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlockKind {
+    Heading(usize),
+    Paragraph,
+    /// `labeled` if the item carries its own label (`Lbl`), e.g. "1.".
+    ListItem {
+        labeled: bool,
+    },
+    TableRow,
+}
+
+struct Extractor<'a> {
+    text: TextResolver<'a>,
+    blocks: Vec<Block>,
+}
+
+impl Extractor<'_> {
+    /// Emit blocks for an element, recursing into grouping elements.
+    fn emit(&mut self, node: &StructNode) {
+        let ty = node.standard_type.as_slice();
+        if ty == b"Artifact" {
+            return;
+        }
+
+        let kind = if let Some(level) = heading_level(ty) {
+            Some(BlockKind::Heading(level))
+        } else if ty == b"LI" {
+            let labeled = node
+                .children
+                .iter()
+                .any(|kid| matches!(kid, StructKid::Element(child) if child.is(b"Lbl")));
+            Some(BlockKind::ListItem { labeled })
+        } else if ty == b"TR" {
+            Some(BlockKind::TableRow)
+        } else if PARAGRAPH_TYPES.contains(&ty) {
+            Some(BlockKind::Paragraph)
+        } else if ty == b"Figure" {
+            if let Some(alt) = node.alt.as_ref().or(node.actual_text.as_ref()) {
+                self.push(
+                    node.first_page(),
+                    BlockKind::Paragraph,
+                    format!("[Figure: {}]", alt.trim()),
+                );
+            }
+            return;
+        } else {
+            None
+        };
+
+        if kind == Some(BlockKind::TableRow) {
+            let cells: Vec<String> = node
+                .children
+                .iter()
+                .filter_map(|kid| match kid {
+                    StructKid::Element(cell) => Some(self.text.text_of(cell)),
+                    StructKid::Content { .. } => None,
+                })
+                .collect();
+            self.push(
+                node.first_page(),
+                BlockKind::TableRow,
+                format!("| {} |", cells.join(" | ")),
+            );
+            return;
+        }
+        if let Some(kind) = kind {
+            // The part on each later page continues as a plain paragraph.
+            let pieces = self.text.page_pieces(node);
+            for (i, (page, text)) in pieces.into_iter().enumerate() {
+                let kind = if i == 0 { kind } else { BlockKind::Paragraph };
+                self.push(page, kind, text);
+            }
+            return;
+        }
+
+        // Grouping element (Document, Sect, Div, L, Table, ...). Loose
+        // content directly inside it is gathered into paragraphs between
+        // its child elements.
+        let mut loose = Vec::new();
+        for kid in &node.children {
+            match kid {
+                StructKid::Element(child) => {
+                    self.flush_loose(&mut loose);
+                    self.emit(child);
+                }
+                StructKid::Content { page, mcid } => {
+                    let page = page.or(node.page);
+                    let piece = self.text.content_text(page, *mcid);
+                    add_page_piece(&mut loose, page, &piece);
+                }
+            }
+        }
+        self.flush_loose(&mut loose);
+    }
+
+    fn flush_loose(&mut self, loose: &mut Vec<(Option<ObjectId>, String)>) {
+        for (page, text) in loose.drain(..) {
+            self.push(page, BlockKind::Paragraph, text);
+        }
+    }
+
+    fn push(&mut self, page: Option<ObjectId>, kind: BlockKind, text: String) {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if text.is_empty() || text == "| |" {
+            return;
+        }
+        self.blocks.push(Block { page, kind, text });
+    }
+}
+
+/// Resolves the text of structure elements from page content.
+pub struct TextResolver<'a> {
+    doc: &'a Document,
+    /// Marked-content text per page, loaded on first use.
+    content: HashMap<ObjectId, HashMap<i64, String>>,
+}
+
+impl<'a> TextResolver<'a> {
+    pub fn new(doc: &'a Document) -> Self {
+        TextResolver {
+            doc,
+            content: HashMap::new(),
+        }
+    }
+
+    /// All text in an element's subtree, in logical order.
+    pub fn text_of(&mut self, node: &StructNode) -> String {
+        let mut text = String::new();
+        for (_, piece) in self.page_pieces(node) {
+            append_piece(&mut text, &piece);
+        }
+        text
+    }
+
+    /// The text of an element's subtree in logical order, split where its
+    /// content moves to another page.
+    fn page_pieces(&mut self, node: &StructNode) -> Vec<(Option<ObjectId>, String)> {
+        let mut pieces = Vec::new();
+        self.collect_pieces(node, &mut pieces);
+        pieces
+    }
+
+    fn collect_pieces(&mut self, node: &StructNode, pieces: &mut Vec<(Option<ObjectId>, String)>) {
+        if let Some(actual) = &node.actual_text {
+            add_page_piece(pieces, node.first_page(), actual);
+            return;
+        }
+        if node.is(b"Artifact") {
+            return;
+        }
+        for kid in &node.children {
+            match kid {
+                StructKid::Element(child) => self.collect_pieces(child, pieces),
+                StructKid::Content { page, mcid } => {
+                    let page = page.or(node.page);
+                    let piece = self.content_text(page, *mcid);
+                    add_page_piece(pieces, page, &piece);
+                }
+            }
+        }
+    }
+
+    fn content_text(&mut self, page: Option<ObjectId>, mcid: i64) -> String {
+        let Some(page) = page else {
+            return String::new();
+        };
+        let doc = self.doc;
+        self.content
+            .entry(page)
+            .or_insert_with(|| marked_content_text(doc, page).unwrap_or_default())
+            .get(&mcid)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Join pieces of text, adding a space only where neither side has one.
+fn append_piece(text: &mut String, piece: &str) {
+    if piece.is_empty() {
+        return;
+    }
+    if !text.is_empty()
+        && !text.ends_with(char::is_whitespace)
+        && !piece.starts_with(char::is_whitespace)
+    {
+        text.push(' ');
+    }
+    text.push_str(piece);
+}
+
+/// Add text from `page`, joining it to the last piece if that is from the
+/// same page.
+fn add_page_piece(
+    pieces: &mut Vec<(Option<ObjectId>, String)>,
+    page: Option<ObjectId>,
+    piece: &str,
+) {
+    match pieces.last_mut() {
+        Some((last, text)) if *last == page => append_piece(text, piece),
+        _ if piece.is_empty() => {}
+        _ => pieces.push((page, piece.to_string())),
+    }
+}
+
+fn heading_level(ty: &[u8]) -> Option<usize> {
+    match ty {
+        b"H" => Some(1),
+        [b'H', digit @ b'1'..=b'6'] => Some((digit - b'0') as usize),
+        _ => None,
+    }
+}
+
+fn render_blocks(blocks: &[Block]) -> String {
+    let mut out = String::new();
+    let mut previous: Option<BlockKind> = None;
+    for block in blocks {
+        if let Some(previous) = previous {
+            // Consecutive list items and table rows stay together.
+            let tight = matches!(
+                (previous, block.kind),
+                (BlockKind::ListItem { .. }, BlockKind::ListItem { .. })
+                    | (BlockKind::TableRow, BlockKind::TableRow)
+            );
+            out.push_str(if tight { "\n" } else { "\n\n" });
+        }
+        match block.kind {
+            BlockKind::Heading(level) => {
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            BlockKind::ListItem { labeled: false } => out.push_str("- "),
+            BlockKind::ListItem { labeled: true } => {
+                // Show bullet labels as Markdown bullets; keep "1." etc.
+                if let Some(rest) = block.text.strip_prefix(['•', '◦', '▪', '–']) {
+                    out.push('-');
+                    out.push_str(rest);
+                    previous = Some(block.kind);
+                    continue;
+                }
+            }
+            BlockKind::Paragraph | BlockKind::TableRow => {}
+        }
+        out.push_str(&block.text);
+        previous = Some(block.kind);
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{tagged_document, text_document};
+    use lopdf::{dictionary, Stream};
+
+    /// Two pages, with a paragraph running from the first onto the second.
+    fn split_paragraph_document() -> (Document, Vec<ObjectId>) {
+        let (mut doc, pages) = text_document(&["", ""]);
+        let contents = [
+            "/P <</MCID 0>> BDC BT /F1 12 Tf 72 700 Td (Starts here) Tj ET EMC",
+            "/P <</MCID 0>> BDC BT /F1 12 Tf 72 700 Td (and ends here.) Tj ET EMC",
+        ];
+        for (&page, content) in pages.iter().zip(contents) {
+            let id = doc.add_object(Stream::new(dictionary! {}, content.as_bytes().to_vec()));
+            doc.get_dictionary_mut(page).unwrap().set("Contents", id);
+        }
+        let mcr = |page: ObjectId| Object::Dictionary(dictionary! { "Pg" => page, "MCID" => 0 });
+        let paragraph = doc.add_object(dictionary! {
+            "S" => "P",
+            "K" => vec![mcr(pages[0]), mcr(pages[1])],
+        });
+        let root = doc.add_object(dictionary! {
+            "Type" => "StructTreeRoot",
+            "K" => paragraph,
+        });
+        doc.catalog_mut().unwrap().set("StructTreeRoot", root);
+        (doc, pages)
+    }
+
+    #[test]
+    fn test_marked_content_text_skips_artifacts() {
+        let (doc, page_id) = tagged_document();
+        let texts = marked_content_text(&doc, page_id).unwrap();
+        assert_eq!(texts.get(&0).map(String::as_str), Some("Results"));
+        assert_eq!(
+            texts.get(&1).map(String::as_str),
+            Some("First line second line")
+        );
+        assert!(texts.values().all(|t| !t.contains("Page 7")));
+    }
+
+    #[test]
+    fn test_extract_tagged_text_follows_structure_order() {
+        let (doc, _) = tagged_document();
+        let pages = extract_tagged_text(&doc).unwrap();
+        assert_eq!(
+            pages[&1],
+            "# Results\n\nFirst line second line\n\n- apples\n- pears\n\n| a | b |\n"
+        );
+    }
+
+    #[test]
+    fn test_block_across_pages_is_split() {
+        let (doc, _) = split_paragraph_document();
+        let pages = extract_tagged_text(&doc).unwrap();
+        assert_eq!(pages[&1], "Starts here\n");
+        assert_eq!(pages[&2], "and ends here.\n");
+    }
+
+    #[test]
+    fn test_page_with_form_text_is_left_to_layout() {
+        let (mut doc, pages) = split_paragraph_document();
+        assert!(extract_tagged_text(&doc).unwrap().contains_key(&2));
+
+        let form = doc.add_object(Stream::new(
+            dictionary! { "Type" => "XObject", "Subtype" => "Form" },
+            b"BT /F1 12 Tf 72 600 Td (In a form) Tj ET".to_vec(),
+        ));
+        let Some(Object::Reference(resources)) = find_inherited(&doc, pages[1], b"Resources")
+        else {
+            panic!("test pages share their resources");
+        };
+        let resources = *resources;
+        doc.get_dictionary_mut(resources)
+            .unwrap()
+            .set("XObject", dictionary! { "Fm0" => form });
+        let content = doc.get_page_content(pages[1]).unwrap();
+        let content = [content, b" /Fm0 Do".to_vec()].concat();
+        let content = doc.add_object(Stream::new(dictionary! {}, content));
+        doc.get_dictionary_mut(pages[1])
+            .unwrap()
+            .set("Contents", content);
+
+        let tagged = extract_tagged_text(&doc).unwrap();
+        assert!(tagged.contains_key(&1));
+        assert!(!tagged.contains_key(&2));
+    }
+
+    #[test]
+    fn test_untagged_document_has_no_tagged_text() {
+        let (doc, _) = crate::pdf::testing::text_document(&["plain"]);
+        assert!(extract_tagged_text(&doc).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+//! In-memory PDF builders shared by unit tests.
+
+use lopdf::{dictionary, Document, Object, ObjectId, Stream};
+
+/// Build a document with one US Letter page per entry, each showing the
+/// given text in Helvetica. Returns the document and its page IDs in order.
+pub fn text_document(pages: &[&str]) -> (Document, Vec<ObjectId>) {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+
+    let mut page_ids = Vec::new();
+    for text in pages {
+        let content = format!("BT /F1 12 Tf 72 720 Td ({}) Tj ET", text);
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+        page_ids.push(doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        }));
+    }
+
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => page_ids.iter().map(|&id| Object::Reference(id)).collect::<Vec<_>>(),
+            "Count" => page_ids.len() as i64,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    (doc, page_ids)
+}
+
+/// Add a structure tree with a custom `Chart` type mapped to `Figure`.
+pub fn add_struct_tree(doc: &mut Document) {
+    let figure = doc.add_object(dictionary! {
+        "S" => "Figure",
+        "Alt" => Object::string_literal("A bar chart"),
+        "K" => 0,
+    });
+    let chart = doc.add_object(dictionary! { "S" => "Chart", "K" => 1 });
+    let heading = doc.add_object(dictionary! {
+        "S" => "H1",
+        "K" => vec![Object::Dictionary(dictionary! { "Type" => "MCR", "MCID" => 2 })],
+    });
+    let document = doc.add_object(dictionary! {
+        "S" => "Document",
+        "K" => vec![
+            Object::Reference(heading),
+            Object::Reference(figure),
+            Object::Reference(chart),
+        ],
+    });
+    let root = doc.add_object(dictionary! {
+        "Type" => "StructTreeRoot",
+        "K" => document,
+        "RoleMap" => dictionary! { "Chart" => "Figure" },
+    });
+    let catalog = doc.catalog_mut().unwrap();
+    catalog.set("StructTreeRoot", root);
+    catalog.set("MarkInfo", dictionary! { "Marked" => true });
+}
+
+/// Build a one-page tagged document with a heading, a two-line paragraph, a
+/// list, a table row, and an artifact footer. Returns it and the page's ID.
+pub fn tagged_document() -> (Document, ObjectId) {
+    let content = b"/Artifact BMC BT /F1 9 Tf 72 30 Td (Page 7) Tj ET EMC
+/H1 <</MCID 0>> BDC BT /F1 18 Tf 72 700 Td (Results) Tj ET EMC
+/P <</MCID 1>> BDC BT /F1 12 Tf 72 670 Td (First line) Tj 0 -14 Td (second line) Tj ET EMC
+/LI <</MCID 2>> BDC BT 72 620 Td (apples) Tj ET EMC
+/LI <</MCID 3>> BDC BT 72 606 Td [(pe) -20 (ars)] TJ ET EMC
+/TD <</MCID 4>> BDC BT 72 580 Td (a) Tj ET EMC
+/TD <</MCID 5>> BDC BT 144 580 Td (b) Tj ET EMC";
+
+    let (mut doc, page_ids) = text_document(&[""]);
+    let page_id = page_ids[0];
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+    doc.get_dictionary_mut(page_id)
+        .unwrap()
+        .set("Contents", content_id);
+
+    let element = |doc: &mut Document, ty: &str, k: Object| {
+        Object::Reference(doc.add_object(dictionary! { "S" => ty, "K" => k }))
+    };
+    let heading = element(&mut doc, "H1", Object::Integer(0));
+    let paragraph = element(&mut doc, "P", Object::Integer(1));
+    let body = element(&mut doc, "LBody", Object::Integer(2));
+    let first = element(&mut doc, "LI", Object::Array(vec![body]));
+    let second = element(&mut doc, "LI", Object::Integer(3));
+    let list = element(&mut doc, "L", Object::Array(vec![first, second]));
+    let a = element(&mut doc, "TD", Object::Integer(4));
+    let b = element(&mut doc, "TD", Object::Integer(5));
+    let row = element(&mut doc, "TR", Object::Array(vec![a, b]));
+    let table = element(&mut doc, "Table", Object::Array(vec![row]));
+    let document = doc.add_object(dictionary! {
+        "S" => "Document",
+        "Pg" => page_id,
+        "K" => vec![heading, paragraph, list, table],
+    });
+    let root = doc.add_object(dictionary! {
+        "Type" => "StructTreeRoot",
+        "K" => document,
+    });
+    doc.catalog_mut().unwrap().set("StructTreeRoot", root);
+
+    (doc, page_id)
+}
+
+/// Build a one-page document with a form: text fields `applicant.name`
+/// (filled with "Ada") and `applicant.city`, and an unchecked check box
+/// `agree` with a separate widget. Returns it and the check box widget's ID.
+pub fn form_document() -> (Document, ObjectId) {
+    let (mut doc, _) = text_document(&[""]);
+    let rect = || vec![0.into(), 0.into(), 100.into(), 20.into()];
+
+    let parent = doc.new_object_id();
+    let name = doc.add_object(dictionary! {
+        "T" => Object::string_literal("name"),
+        "V" => Object::string_literal("Ada"),
+        "Parent" => parent,
+        "Subtype" => "Widget",
+        "Rect" => rect(),
+    });
+    let city = doc.add_object(dictionary! {
+        "T" => Object::string_literal("city"),
+        "Parent" => parent,
+        "Subtype" => "Widget",
+        "Rect" => rect(),
+    });
+    doc.objects.insert(
+        parent,
+        Object::Dictionary(dictionary! {
+            "T" => Object::string_literal("applicant"),
+            "FT" => "Tx",
+            "Kids" => vec![Object::Reference(name), Object::Reference(city)],
+        }),
+    );
+
+    let on = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+    let off = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+    let agree = doc.new_object_id();
+    let widget = doc.add_object(dictionary! {
+        "Parent" => agree,
+        "Subtype" => "Widget",
+        "Rect" => rect(),
+        "AS" => "Off",
+        "AP" => dictionary! { "N" => dictionary! { "Yes" => on, "Off" => off } },
+    });
+    doc.objects.insert(
+        agree,
+        Object::Dictionary(dictionary! {
+            "T" => Object::string_literal("agree"),
+            "FT" => "Btn",
+            "V" => "Off",
+            "Kids" => vec![Object::Reference(widget)],
+        }),
+    );
+
+    doc.catalog_mut().unwrap().set(
+        "AcroForm",
+        dictionary! {
+            "Fields" => vec![Object::Reference(parent), Object::Reference(agree)],
+        },
+    );
+    (doc, widget)
+}
+
+/// Give a document an outline from `(title, page index, level)` entries in
+/// reading order, each level nesting under the entry before it. Items are
+/// open, and each points at its page with a `/Fit` destination.
+pub fn add_outline(doc: &mut Document, page_ids: &[ObjectId], entries: &[(&str, usize, u32)]) {
+    let outlines_id = doc.new_object_id();
+    let ids: Vec<ObjectId> = entries.iter().map(|_| doc.new_object_id()).collect();
+
+    let parent_of = |i: usize| {
+        (0..i)
+            .rev()
+            .find(|&j| entries[j].2 < entries[i].2)
+            .map_or(outlines_id, |j| ids[j])
+    };
+    let children_of = |parent: ObjectId| -> Vec<usize> {
+        (0..entries.len())
+            .filter(|&i| parent_of(i) == parent)
+            .collect()
+    };
+
+    for (i, &(title, page, _)) in entries.iter().enumerate() {
+        let siblings = children_of(parent_of(i));
+        let at = siblings.iter().position(|&s| s == i).unwrap();
+        let mut item = dictionary! {
+            "Title" => Object::string_literal(title),
+            "Parent" => parent_of(i),
+            "Dest" => vec![page_ids[page].into(), "Fit".into()],
+        };
+        if at > 0 {
+            item.set("Prev", ids[siblings[at - 1]]);
+        }
+        if let Some(&next) = siblings.get(at + 1) {
+            item.set("Next", ids[next]);
+        }
+        let children = children_of(ids[i]);
+        if let (Some(&first), Some(&last)) = (children.first(), children.last()) {
+            item.set("First", ids[first]);
+            item.set("Last", ids[last]);
+            let descendants = entries[i + 1..]
+                .iter()
+                .take_while(|e| e.2 > entries[i].2)
+                .count();
+            item.set("Count", descendants as i64);
+        }
+        doc.objects.insert(ids[i], Object::Dictionary(item));
+    }
+
+    let top = children_of(outlines_id);
+    doc.objects.insert(
+        outlines_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => ids[top[0]],
+            "Last" => ids[*top.last().unwrap()],
+            "Count" => entries.len() as i64,
+        }),
+    );
+    doc.catalog_mut().unwrap().set("Outlines", outlines_id);
+}
@@ -1,15 +1,92 @@
-use crate::pdf::cache::get_cached_pdf;
-use anyhow::{Context, Result};
-use std::path::Path;
+use crate::pdf::attachments::{attachments, AttachmentData};
+use crate::pdf::cache::CachedPdf;
+use crate::pdf::page_labels::extract_page_labels_from_doc;
+use crate::pdf::toc::{extract_toc_from_doc, outline_path};
+use crate::pdf::PdfDocument;
+use anyhow::Result;
+use lopdf::Document;
+use rayon::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::time::Instant;
 
-/// Extract text from specific pages of a PDF.
+/// The one source of page text for every consumer (read-pages, grep,
+/// figure captions, ...), so changes to how text is obtained or cleaned up
+/// apply to all of them.
 ///
-/// Uses the per-page text cache to avoid re-extracting text that was
-/// already processed (e.g., by a previous grep operation).
-pub fn extract_text_pages<P: AsRef<Path>>(path: P, pages: &[u32]) -> Result<Vec<PageText>> {
-    let path = path.as_ref();
-    let cached = get_cached_pdf(path).with_context(|| format!("cache PDF: {}", path.display()))?;
-    let total_pages = cached.document().get_pages().len() as u32;
+/// Text comes from the document's per-page cache ([`CachedPdf::page_text`]);
+/// extraction errors, and panics that pdf-extract raises on some malformed
+/// content streams, become per-page error messages.
+#[derive(Clone, Copy)]
+pub struct PageTextProvider<'a> {
+    cached: &'a CachedPdf,
+}
+
+impl<'a> PageTextProvider<'a> {
+    pub fn new(cached: &'a CachedPdf) -> Self {
+        PageTextProvider { cached }
+    }
+
+    pub fn page_count(&self) -> u32 {
+        self.cached.document().get_pages().len() as u32
+    }
+
+    /// One page's text (1-indexed).
+    pub fn page(&self, page_num: u32) -> Result<Arc<String>, String> {
+        match panic::catch_unwind(AssertUnwindSafe(|| self.cached.page_text(page_num))) {
+            Ok(Ok(text)) => Ok(text),
+            Ok(Err(err)) => Err(format!("extract text from page {}: {}", page_num, err)),
+            Err(payload) => {
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_else(|| "unknown error".to_string());
+                Err(format!("extract text from page {}: {}", page_num, message))
+            }
+        }
+    }
+
+    /// Several pages, extracted in parallel and returned in the order given.
+    pub fn pages(&self, pages: &[u32]) -> Vec<PageText> {
+        pages
+            .par_iter()
+            .map(|&page_num| {
+                let (text, error) = match self.page(page_num) {
+                    Ok(text) => (text.to_string(), None),
+                    Err(error) => (String::new(), Some(error)),
+                };
+                PageText {
+                    page: page_num,
+                    text,
+                    error,
+                    recovered: self.cached.recovery(page_num).is_some(),
+                }
+            })
+            .collect()
+    }
+
+    /// Every page in order, extracted a batch at a time in parallel, so a
+    /// consumer that stops early doesn't extract the whole document.
+    pub fn iter(&self) -> impl Iterator<Item = PageText> + 'a {
+        let provider = *self;
+        let total = self.page_count();
+        let batch = rayon::current_num_threads().max(1) as u32;
+        (0..total.div_ceil(batch)).flat_map(move |i| {
+            let first = i * batch + 1;
+            let pages: Vec<u32> = (first..=(first + batch - 1).min(total)).collect();
+            provider.pages(&pages)
+        })
+    }
+}
+
+/// Extract text from specific pages of an open PDF.
+///
+/// Uses the document's per-page text cache to avoid re-extracting text that
+/// was already processed (e.g., by a previous grep operation). A page that
+/// fails is reported in its result rather than failing the others.
+pub fn extract_text_pages(doc: &PdfDocument, pages: &[u32]) -> Result<Vec<PageText>> {
+    let total_pages = doc.page_count();
 
     // Validate page numbers.
     for &page in pages {
@@ -18,26 +95,50 @@ pub fn extract_text_pages<P: AsRef<Path>>(path: P, pages: &[u32]) -> Result<Vec<
         }
     }
 
-    let mut results = Vec::new();
-
-    // Extract text for each requested page using the text cache.
-    for &page_num in pages {
-        let text = cached
-            .page_text(page_num)
-            .with_context(|| format!("extract text from page {}", page_num))?;
-        results.push(PageText {
-            page: page_num,
-            text: text.to_string(),
-        });
-    }
-
-    Ok(results)
+    Ok(PageTextProvider::new(doc.cached()).pages(pages))
 }
 
 #[derive(Debug, Clone)]
 pub struct PageText {
     pub page: u32,
+    /// Extracted text; empty if extraction failed.
     pub text: String,
+    /// Why the page's text could not be extracted.
+    pub error: Option<String>,
+    /// The text was recovered from glyph names (see `pdf::recover`), so it
+    /// may be incomplete or wrong.
+    pub recovered: bool,
+}
+
+/// Prefix each page's text with its logical page label and enclosing TOC section.
+///
+/// Produces a header line like `[p. 42 | Part II > Chapter 5]` so that long
+/// extractions remain self-describing once split into chunks downstream.
+pub fn annotate_sections(doc: &Document, texts: &mut [PageText]) -> Result<()> {
+    let toc = extract_toc_from_doc(doc)?;
+    let labels = extract_page_labels_from_doc(doc)?;
+
+    for page_text in texts.iter_mut().filter(|t| t.error.is_none()) {
+        let label = labels
+            .get(page_text.page as usize - 1)
+            .map(|l| l.logical_label.as_str())
+            .unwrap_or_default();
+        let chain = outline_path(&toc, page_text.page);
+        let section = chain
+            .iter()
+            .map(|e| e.title.as_str())
+            .collect::<Vec<_>>()
+            .join(" > ");
+
+        let header = if section.is_empty() {
+            format!("[p. {}]", label)
+        } else {
+            format!("[p. {} | {}]", label, section)
+        };
+        page_text.text = format!("{}\n{}", header, page_text.text);
+    }
+
+    Ok(())
 }
 
 /// Search for a pattern in PDF text, returning matches with page numbers and context.
@@ -45,51 +146,376 @@ pub struct PageText {
 /// Uses the per-page text cache to avoid re-extracting text. This benefits MCP
 /// sessions where multiple grep operations may search the same PDF, or where
 /// grep is followed by reading specific pages.
-pub fn grep_pdf<P: AsRef<Path>>(
-    path: P,
+pub fn grep_cached(
+    cached: &CachedPdf,
     pattern: &regex::Regex,
     max_results: usize,
 ) -> Result<Vec<GrepMatch>> {
-    let path = path.as_ref();
-    let cached = get_cached_pdf(path).with_context(|| format!("cache PDF: {}", path.display()))?;
-    let total_pages = cached.document().get_pages().len() as u32;
+    grep_cached_until(cached, pattern, max_results, None, None)
+}
+
+/// Like [`grep_cached`], but keep at most `max_per_page` matches from any
+/// one page, so a page full of boilerplate doesn't use up `max_results`,
+/// and fail once `deadline` has passed. The deadline is checked between
+/// pages.
+pub fn grep_cached_until(
+    cached: &CachedPdf,
+    pattern: &regex::Regex,
+    max_results: usize,
+    max_per_page: Option<usize>,
+    deadline: Option<Instant>,
+) -> Result<Vec<GrepMatch>> {
+    let provider = PageTextProvider::new(cached);
+    let total_pages = provider.page_count();
 
     let mut matches = Vec::new();
 
-    // Extract and search each page individually for correct page attribution.
-    // Text is cached per-page, so subsequent searches or reads are cheap.
-    for page_num in 1..=total_pages {
-        let page_text = match cached.page_text(page_num) {
-            Ok(text) => text,
-            Err(_) => continue, // Skip pages that fail to extract
-        };
+    // Search each page individually for correct page attribution. Text is
+    // cached per-page, so subsequent searches or reads are cheap.
+    for page in provider.iter() {
+        let page_num = page.page;
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            anyhow::bail!(
+                "Search timed out after {} of {} pages",
+                page_num - 1,
+                total_pages
+            );
+        }
+        if page.error.is_some() {
+            continue; // Skip pages that fail to extract
+        }
+        let limit = (max_results - matches.len()).min(max_per_page.unwrap_or(usize::MAX));
+        matches.extend(grep_page(
+            page_num,
+            &page.text,
+            pattern,
+            limit,
+            page.recovered,
+        ));
+        if matches.len() >= max_results {
+            return Ok(matches);
+        }
+    }
 
-        for (line_idx, line) in page_text.lines().enumerate() {
-            let line_number = line_idx as u32 + 1;
-            for mat in pattern.find_iter(line) {
-                matches.push(GrepMatch {
-                    page: page_num,
-                    line_number,
-                    text: line.to_string(),
-                    match_start: mat.start() as u32,
-                    match_end: mat.end() as u32,
-                });
-
-                if matches.len() >= max_results {
-                    return Ok(matches);
-                }
+    Ok(matches)
+}
+
+/// Like [`grep_cached_until`], but over page texts that were already
+/// extracted (page 1 first), such as those in a text sidecar. Pages after
+/// `deadline` are not searched.
+pub fn grep_page_texts(
+    pages: &[String],
+    pattern: &regex::Regex,
+    max_results: usize,
+    max_per_page: Option<usize>,
+    deadline: Option<Instant>,
+) -> Vec<GrepMatch> {
+    let mut matches = Vec::new();
+    for (i, text) in pages.iter().enumerate() {
+        if matches.len() >= max_results
+            || deadline.is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            break;
+        }
+        let limit = (max_results - matches.len()).min(max_per_page.unwrap_or(usize::MAX));
+        matches.extend(grep_page(i as u32 + 1, text, pattern, limit, false));
+    }
+    matches
+}
+
+/// Up to `limit` matches of `pattern` in one page's text, line by line.
+pub(crate) fn grep_page(
+    page: u32,
+    text: &str,
+    pattern: &regex::Regex,
+    limit: usize,
+    recovered: bool,
+) -> Vec<GrepMatch> {
+    let mut chars = CharOffsets::new(text);
+    let mut matches = Vec::new();
+    for (line_idx, (offset, line)) in lines_with_offsets(text).enumerate() {
+        for mat in pattern.find_iter(line) {
+            if matches.len() >= limit {
+                return matches;
             }
+            matches.push(GrepMatch {
+                page,
+                line_number: line_idx as u32 + 1,
+                text: line.to_string(),
+                match_start: mat.start() as u32,
+                match_end: mat.end() as u32,
+                page_start: chars.at(offset + mat.start()),
+                page_end: chars.at(offset + mat.end()),
+                attachment: None,
+                ocr_confidence: None,
+                recovered,
+            });
         }
     }
+    matches
+}
 
+/// Search the document's attachments: attached PDFs page by page (and
+/// their own attachments, a few levels deep) and text files such as XML
+/// line by line. Other attachments are skipped.
+pub fn grep_attachments(
+    cached: &CachedPdf,
+    pattern: &regex::Regex,
+    max_results: usize,
+) -> Result<Vec<GrepMatch>> {
+    let mut matches = Vec::new();
+    grep_attachments_into(cached.document(), pattern, max_results, "", 0, &mut matches);
     Ok(matches)
 }
 
+/// Maximum depth of attachments inside attachments searched.
+const MAX_ATTACHMENT_DEPTH: usize = 4;
+
+fn grep_attachments_into(
+    doc: &Document,
+    pattern: &regex::Regex,
+    max_results: usize,
+    prefix: &str,
+    depth: usize,
+    matches: &mut Vec<GrepMatch>,
+) {
+    if depth >= MAX_ATTACHMENT_DEPTH {
+        return;
+    }
+    for attachment in attachments(doc) {
+        if matches.len() >= max_results {
+            return;
+        }
+        let name = format!("{}{}", prefix, attachment.file_name);
+        match attachment.data {
+            AttachmentData::Pdf(bytes) => {
+                // Unparseable attachments are skipped like unreadable pages.
+                let Ok(inner) = CachedPdf::from_bytes(&bytes) else {
+                    continue;
+                };
+                let remaining = max_results - matches.len();
+                if let Ok(found) = grep_cached(&inner, pattern, remaining) {
+                    matches.extend(found.into_iter().map(|m| GrepMatch {
+                        attachment: Some(name.clone()),
+                        ..m
+                    }));
+                }
+                let prefix = format!("{}/", name);
+                grep_attachments_into(
+                    inner.document(),
+                    pattern,
+                    max_results,
+                    &prefix,
+                    depth + 1,
+                    matches,
+                );
+            }
+            AttachmentData::Text(text) => {
+                let mut chars = CharOffsets::new(&text);
+                for (line_idx, (offset, line)) in lines_with_offsets(&text).enumerate() {
+                    for mat in pattern.find_iter(line) {
+                        matches.push(GrepMatch {
+                            page: 0,
+                            line_number: line_idx as u32 + 1,
+                            text: line.to_string(),
+                            match_start: mat.start() as u32,
+                            match_end: mat.end() as u32,
+                            page_start: chars.at(offset + mat.start()),
+                            page_end: chars.at(offset + mat.end()),
+                            attachment: Some(name.clone()),
+                            ocr_confidence: None,
+                            recovered: false,
+                        });
+                        if matches.len() >= max_results {
+                            return;
+                        }
+                    }
+                }
+            }
+            AttachmentData::Binary => {}
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GrepMatch {
     pub page: u32,
     pub line_number: u32,
     pub text: String,
+    /// Byte offsets of the match in `text`.
     pub match_start: u32,
     pub match_end: u32,
+    /// Character offsets of the match in the page's full extracted text
+    /// (the attachment's text for text attachments, the recognized lines
+    /// joined by newlines for OCR matches).
+    pub page_start: u32,
+    pub page_end: u32,
+    /// The attachment the match is in (nested ones joined by `/`), or
+    /// `None` for the document itself. Text attachments have page 0.
+    pub attachment: Option<String>,
+    /// For matches in OCR output, the recognition confidence (0-100) of
+    /// the least certain word in the match.
+    pub ocr_confidence: Option<f32>,
+    /// The page's text was recovered from glyph names (see
+    /// `pdf::recover`), so the match is low-confidence.
+    pub recovered: bool,
+}
+
+/// The lines of `text`, as [`str::lines`] splits them, with the byte
+/// offset each starts at.
+pub(crate) fn lines_with_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, raw| {
+        let start = *offset;
+        *offset += raw.len();
+        let line = match raw.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line),
+            None => raw,
+        };
+        Some((start, line))
+    })
+}
+
+/// Converts byte offsets into a text to character offsets, counting on
+/// from the previous offset when they come in increasing order (as matches
+/// do) rather than from the start each time.
+pub(crate) struct CharOffsets<'a> {
+    text: &'a str,
+    byte: usize,
+    chars: usize,
+}
+
+impl<'a> CharOffsets<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        CharOffsets {
+            text,
+            byte: 0,
+            chars: 0,
+        }
+    }
+
+    pub(crate) fn at(&mut self, byte: usize) -> u32 {
+        if byte < self.byte {
+            self.byte = 0;
+            self.chars = 0;
+        }
+        self.chars += self.text[self.byte..byte].chars().count();
+        self.byte = byte;
+        self.chars as u32
+    }
+}
+
+/// Part of a page's text around a match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub text: String,
+    /// Character offset of the snippet's first character in the page text.
+    pub start: u32,
+}
+
+/// Up to `window` characters either side of the characters `start..end`
+/// of `text`, crossing line breaks.
+pub fn snippet(text: &str, start: u32, end: u32, window: u32) -> Snippet {
+    let from = start.saturating_sub(window);
+    let to = end.saturating_add(window);
+    Snippet {
+        text: text
+            .chars()
+            .skip(from as usize)
+            .take((to - from) as usize)
+            .collect(),
+        start: from,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_provider_iterates_pages_in_order() {
+        let words = ["alpha", "beta", "gamma", "delta", "epsilon"];
+        let (doc, _) = text_document(&words);
+        let cached = CachedPdf::from_document(doc);
+        let provider = PageTextProvider::new(&cached);
+
+        let pages: Vec<PageText> = provider.iter().collect();
+        assert_eq!(pages.len(), words.len());
+        for (i, (page, word)) in pages.iter().zip(words).enumerate() {
+            assert_eq!(page.page, i as u32 + 1);
+            assert!(page.error.is_none());
+            assert!(page.text.contains(word));
+        }
+
+        let picked = provider.pages(&[4, 2]);
+        assert_eq!(picked[0].page, 4);
+        assert!(picked[1].text.contains("beta"));
+    }
+
+    #[test]
+    fn test_grep_cached_stops_at_max_results() {
+        let (doc, _) = text_document(&["one match", "two match", "three match"]);
+        let cached = CachedPdf::from_document(doc);
+        let regex = regex::Regex::new("match").unwrap();
+
+        let matches = grep_cached(&cached, &regex, 2).unwrap();
+        let pages: Vec<u32> = matches.iter().map(|m| m.page).collect();
+        assert_eq!(pages, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_grep_cached_max_per_page() {
+        let (doc, _) = text_document(&["match match match", "none", "match"]);
+        let cached = CachedPdf::from_document(doc);
+        let regex = regex::Regex::new("match").unwrap();
+
+        let matches = grep_cached_until(&cached, &regex, 10, Some(2), None).unwrap();
+        let pages: Vec<u32> = matches.iter().map(|m| m.page).collect();
+        assert_eq!(pages, vec![1, 1, 3]);
+    }
+
+    #[test]
+    fn test_match_page_offsets() {
+        let (doc, _) = text_document(&["one match, two match"]);
+        let cached = CachedPdf::from_document(doc);
+        let page = cached.page_text(1).unwrap();
+        let regex = regex::Regex::new("match").unwrap();
+
+        let matches = grep_cached(&cached, &regex, 10).unwrap();
+        assert_eq!(matches.len(), 2);
+        for m in &matches {
+            let found: String = page
+                .chars()
+                .skip(m.page_start as usize)
+                .take((m.page_end - m.page_start) as usize)
+                .collect();
+            assert_eq!(found, "match");
+        }
+        assert!(matches[1].page_start > matches[0].page_end);
+    }
+
+    #[test]
+    fn test_lines_with_offsets() {
+        let text = "héllo\r\nwörld\n\nend";
+        let lines: Vec<_> = lines_with_offsets(text).collect();
+        assert_eq!(lines, [(0, "héllo"), (8, "wörld"), (15, ""), (16, "end")]);
+        assert_eq!(
+            lines.iter().map(|(_, line)| *line).collect::<Vec<_>>(),
+            text.lines().collect::<Vec<_>>()
+        );
+
+        let mut chars = CharOffsets::new(text);
+        assert_eq!(chars.at(8), 7);
+        assert_eq!(chars.at(16), 14);
+        assert_eq!(chars.at(3), 2);
+    }
+
+    #[test]
+    fn test_snippet() {
+        let text = "first line\nsecond line";
+        let s = snippet(text, 11, 17, 4);
+        assert_eq!(s.text, "ine\nsecond lin");
+        assert_eq!(s.start, 7);
+        assert_eq!(snippet(text, 0, 5, 100).text, text);
+    }
 }
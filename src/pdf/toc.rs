@@ -1,16 +1,58 @@
 use crate::pdf::cache::get_cached_pdf;
+use crate::pdf::pages::resolve;
+use crate::pdf::portfolio::file_name;
+use crate::pdf::strings::{decode_text_string, encode_text_string};
 use anyhow::{Context, Result};
-use lopdf::{Document, Object, ObjectId};
+use lopdf::{dictionary, Document, Object, ObjectId};
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::path::Path;
 
 #[derive(Debug, Clone)]
 pub struct TocEntry {
     pub title: String,
     pub page: Option<u32>,
+    /// Where the entry leads instead, if outside this document.
+    pub external: Option<ExternalTarget>,
     pub level: u32,
     pub children: Vec<TocEntry>,
 }
 
+/// An outline entry's target outside the document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalTarget {
+    /// A page (1-indexed) or named destination in another PDF (`GoToR`).
+    RemoteFile {
+        file: String,
+        page: Option<u32>,
+        name: Option<String>,
+    },
+    /// A file or application to open (`Launch`).
+    Launch { file: String },
+    /// A web address (`URI`).
+    Uri(String),
+}
+
+impl fmt::Display for ExternalTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalTarget::RemoteFile { file, page, name } => {
+                write!(f, "{}", file)?;
+                if let Some(page) = page {
+                    write!(f, ", p. {}", page)?;
+                }
+                if let Some(name) = name {
+                    write!(f, "#{}", name)?;
+                }
+                Ok(())
+            }
+            ExternalTarget::Launch { file } => write!(f, "launch {}", file),
+            ExternalTarget::Uri(uri) => write!(f, "{}", uri),
+        }
+    }
+}
+
 /// Extract table of contents / bookmarks from a PDF.
 pub fn extract_toc<P: AsRef<Path>>(path: P) -> Result<Vec<TocEntry>> {
     let path = path.as_ref();
@@ -46,21 +88,30 @@ pub fn extract_toc_from_doc(doc: &Document) -> Result<Vec<TocEntry>> {
     };
 
     // Parse the outline tree
-    let entries = parse_outline_items(doc, first_ref, &page_map, 0)?;
+    let entries = parse_outline_items(doc, first_ref, &page_map, 0, &mut HashSet::new())?;
 
     Ok(entries)
 }
 
+/// Parse a chain of sibling outline items and their children.
+///
+/// `seen` holds every item visited so far: broken files can link `Next` or
+/// `First` back to an earlier item, and following such a link would loop
+/// forever, so the chain is cut there instead.
 fn parse_outline_items(
     doc: &Document,
     first_id: ObjectId,
     page_map: &[(ObjectId, u32)],
     level: u32,
+    seen: &mut HashSet<ObjectId>,
 ) -> Result<Vec<TocEntry>> {
     let mut entries = Vec::new();
     let mut current_id = Some(first_id);
 
     while let Some(id) = current_id {
+        if !seen.insert(id) {
+            break;
+        }
         let dict = match doc.get_dictionary(id) {
             Ok(d) => d,
             Err(_) => break,
@@ -68,17 +119,17 @@ fn parse_outline_items(
 
         // Get title
         let title = match dict.get(b"Title") {
-            Ok(Object::String(bytes, _)) => decode_pdf_string(bytes),
+            Ok(Object::String(bytes, _)) => decode_text_string(bytes),
             _ => "Untitled".to_string(),
         };
 
         // Get destination page
-        let page = get_destination_page(doc, dict, page_map);
+        let (page, external) = get_destination(doc, dict, page_map);
 
         // Get children
         let children = match dict.get(b"First") {
             Ok(Object::Reference(child_ref)) => {
-                parse_outline_items(doc, *child_ref, page_map, level + 1)?
+                parse_outline_items(doc, *child_ref, page_map, level + 1, seen)?
             }
             _ => Vec::new(),
         };
@@ -86,6 +137,7 @@ fn parse_outline_items(
         entries.push(TocEntry {
             title,
             page,
+            external,
             level,
             children,
         });
@@ -100,41 +152,80 @@ fn parse_outline_items(
     Ok(entries)
 }
 
-fn get_destination_page(
+fn get_destination(
     doc: &Document,
     dict: &lopdf::Dictionary,
     page_map: &[(ObjectId, u32)],
-) -> Option<u32> {
+) -> (Option<u32>, Option<ExternalTarget>) {
     // Try Dest first (direct destination)
     if let Ok(dest) = dict.get(b"Dest") {
-        return resolve_destination(doc, dest, page_map);
+        return (resolve_destination(doc, dest, page_map), None);
     }
 
-    // Try A (action) - for GoTo actions
-    if let Ok(Object::Reference(action_ref)) = dict.get(b"A") {
-        if let Ok(action_dict) = doc.get_dictionary(*action_ref) {
-            if let Ok(Object::Name(action_type)) = action_dict.get(b"S") {
-                if action_type == b"GoTo" {
-                    if let Ok(dest) = action_dict.get(b"D") {
-                        return resolve_destination(doc, dest, page_map);
+    // Otherwise an action, by reference or inline
+    let Some(action) = dict
+        .get(b"A")
+        .ok()
+        .and_then(|a| resolve(doc, a).as_dict().ok())
+    else {
+        return (None, None);
+    };
+    let Ok(Object::Name(action_type)) = action.get(b"S") else {
+        return (None, None);
+    };
+    match action_type.as_slice() {
+        b"GoTo" => {
+            let page = action
+                .get(b"D")
+                .ok()
+                .and_then(|dest| resolve_destination(doc, dest, page_map));
+            (page, None)
+        }
+        b"GoToR" => {
+            let target = file_spec_name(doc, action.get(b"F").ok()).map(|file| {
+                let (page, name) = match action.get(b"D").map(|d| resolve(doc, d)) {
+                    // Remote explicit destinations give a 0-based page number.
+                    Ok(Object::Array(arr)) => match arr.first() {
+                        Some(Object::Integer(n)) if *n >= 0 => (Some(*n as u32 + 1), None),
+                        _ => (None, None),
+                    },
+                    Ok(Object::String(name, _) | Object::Name(name)) => {
+                        (None, Some(decode_text_string(name)))
                     }
-                }
-            }
+                    _ => (None, None),
+                };
+                ExternalTarget::RemoteFile { file, page, name }
+            });
+            (None, target)
         }
-    }
-
-    // Also check for inline action dictionary
-    if let Ok(Object::Dictionary(action_dict)) = dict.get(b"A") {
-        if let Ok(Object::Name(action_type)) = action_dict.get(b"S") {
-            if action_type == b"GoTo" {
-                if let Ok(dest) = action_dict.get(b"D") {
-                    return resolve_destination(doc, dest, page_map);
-                }
-            }
+        b"Launch" => {
+            // Launch may give the file only in a platform-specific dictionary.
+            let win = action
+                .get(b"Win")
+                .ok()
+                .and_then(|w| resolve(doc, w).as_dict().ok())
+                .and_then(|w| w.get(b"F").ok());
+            let file = file_spec_name(doc, action.get(b"F").ok().or(win));
+            (None, file.map(|file| ExternalTarget::Launch { file }))
         }
+        b"URI" => {
+            let uri = match action.get(b"URI").map(|u| resolve(doc, u)) {
+                Ok(Object::String(uri, _)) => Some(ExternalTarget::Uri(decode_text_string(uri))),
+                _ => None,
+            };
+            (None, uri)
+        }
+        _ => (None, None),
     }
+}
 
-    None
+/// The file a file specification (a string or a dictionary) names.
+fn file_spec_name(doc: &Document, spec: Option<&Object>) -> Option<String> {
+    match resolve(doc, spec?) {
+        Object::String(name, _) => Some(decode_text_string(name)),
+        Object::Dictionary(dict) => file_name(dict),
+        _ => None,
+    }
 }
 
 fn resolve_destination(doc: &Document, dest: &Object, page_map: &[(ObjectId, u32)]) -> Option<u32> {
@@ -241,27 +332,6 @@ fn build_page_map(doc: &Document) -> Vec<(ObjectId, u32)> {
     pages.into_iter().map(|(num, id)| (id, num)).collect()
 }
 
-fn decode_pdf_string(bytes: &[u8]) -> String {
-    // Check for UTF-16 BOM
-    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
-        // UTF-16 BE
-        let u16_chars: Vec<u16> = bytes[2..]
-            .chunks(2)
-            .filter_map(|chunk| {
-                if chunk.len() == 2 {
-                    Some(u16::from_be_bytes([chunk[0], chunk[1]]))
-                } else {
-                    None
-                }
-            })
-            .collect();
-        String::from_utf16_lossy(&u16_chars)
-    } else {
-        // PDFDocEncoding / Latin-1 (simplified)
-        bytes.iter().map(|&b| b as char).collect()
-    }
-}
-
 /// Flatten TOC entries into a simple list with indentation info
 pub fn flatten_toc(entries: &[TocEntry]) -> Vec<FlatTocEntry> {
     let mut result = Vec::new();
@@ -274,15 +344,734 @@ fn flatten_toc_recursive(entries: &[TocEntry], result: &mut Vec<FlatTocEntry>) {
         result.push(FlatTocEntry {
             title: entry.title.clone(),
             page: entry.page,
+            external: entry.external.clone(),
             level: entry.level,
         });
         flatten_toc_recursive(&entry.children, result);
     }
 }
 
+/// Trim an outline to what a caller asked for.
+///
+/// `max_depth` keeps entries above that depth (1 = top level only).
+/// `pattern` keeps entries whose title matches, together with their
+/// ancestors (so each match stays in context) and their sub-entries.
+pub fn filter_toc(
+    entries: &[TocEntry],
+    max_depth: Option<u32>,
+    pattern: Option<&Regex>,
+) -> Vec<TocEntry> {
+    entries
+        .iter()
+        .filter(|entry| max_depth.is_none_or(|depth| entry.level < depth))
+        .filter_map(|entry| {
+            let matched = pattern.is_none_or(|re| re.is_match(&entry.title));
+            // Once an entry matches, its whole subtree is kept.
+            let children = filter_toc(
+                &entry.children,
+                max_depth,
+                if matched { None } else { pattern },
+            );
+            (matched || !children.is_empty()).then(|| TocEntry {
+                title: entry.title.clone(),
+                page: entry.page,
+                external: entry.external.clone(),
+                level: entry.level,
+                children,
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct FlatTocEntry {
     pub title: String,
     pub page: Option<u32>,
+    pub external: Option<ExternalTarget>,
     pub level: u32,
 }
+
+/// Find the chain of outline entries that contain a physical page.
+///
+/// An entry is considered to span from its own page until the next entry at
+/// the same or a shallower level begins. The result is ordered from the
+/// outermost entry (e.g., "Part II") to the innermost (e.g., "5.3 Results").
+/// Entries without a resolvable page are ignored.
+pub fn outline_path(entries: &[TocEntry], page: u32) -> Vec<FlatTocEntry> {
+    let mut stack: Vec<FlatTocEntry> = Vec::new();
+
+    for entry in flatten_toc(entries) {
+        let Some(entry_page) = entry.page else {
+            continue;
+        };
+        if entry_page > page {
+            break;
+        }
+        stack.retain(|e| e.level < entry.level);
+        stack.push(entry);
+    }
+
+    stack
+}
+
+/// Outline entries with a page whose title is `heading`, ignoring case and
+/// spacing, or if there are none, whose title contains it. Entries come in
+/// outline order.
+pub fn find_heading(entries: &[TocEntry], heading: &str) -> Vec<FlatTocEntry> {
+    let normalize = |s: &str| {
+        s.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_lowercase()
+    };
+    let heading = normalize(heading);
+    let candidates: Vec<(String, FlatTocEntry)> = flatten_toc(entries)
+        .into_iter()
+        .filter(|entry| entry.page.is_some())
+        .map(|entry| (normalize(&entry.title), entry))
+        .collect();
+    let exact: Vec<FlatTocEntry> = candidates
+        .iter()
+        .filter(|(title, _)| *title == heading)
+        .map(|(_, entry)| entry.clone())
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    candidates
+        .into_iter()
+        .filter(|(title, _)| title.contains(&heading))
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+/// A change to make to the outline with [`edit_toc`]. Patterns are tested
+/// against each entry's full title.
+#[derive(Debug, Clone)]
+pub enum TocEdit {
+    /// Replace matching titles; `$1` etc. refer to the pattern's groups.
+    Rename { pattern: Regex, title: String },
+    /// Remove matching entries along with their sub-entries.
+    Delete(Regex),
+    /// Point matching entries at a physical page (1-indexed).
+    Retarget { pattern: Regex, page: u32 },
+}
+
+impl TocEdit {
+    fn pattern(&self) -> &Regex {
+        match self {
+            TocEdit::Rename { pattern, .. }
+            | TocEdit::Delete(pattern)
+            | TocEdit::Retarget { pattern, .. } => pattern,
+        }
+    }
+}
+
+/// How many entries [`edit_toc`] changed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TocEditSummary {
+    pub renamed: usize,
+    pub deleted: usize,
+    pub retargeted: usize,
+}
+
+/// An outline item as stored in the document, for rewriting the tree.
+struct OutlineNode {
+    id: ObjectId,
+    title: String,
+    open: bool,
+    children: Vec<OutlineNode>,
+}
+
+/// Rename, delete, and re-point outline entries in place.
+///
+/// Every edit is matched against the titles as they were before editing.
+/// Other item attributes (color, style, actions of entries that aren't
+/// retargeted) are kept. Fails if an edit matches no entry, so a mistyped
+/// pattern doesn't go unnoticed.
+pub fn edit_toc(doc: &mut Document, edits: &[TocEdit]) -> Result<TocEditSummary> {
+    let outlines_id = match doc.catalog()?.get(b"Outlines") {
+        Ok(Object::Reference(id)) => *id,
+        _ => anyhow::bail!("Document has no table of contents"),
+    };
+    let first = match doc.get_dictionary(outlines_id)?.get(b"First") {
+        Ok(Object::Reference(id)) => Some(*id),
+        _ => None,
+    };
+    let mut nodes = match first {
+        Some(first) => read_outline_nodes(doc, first, &mut HashSet::new())?,
+        None => Vec::new(),
+    };
+
+    for edit in edits {
+        if !any_title_matches(&nodes, edit.pattern()) {
+            anyhow::bail!(
+                "No table of contents entry matches '{}'",
+                edit.pattern().as_str()
+            );
+        }
+    }
+
+    let pages = doc.get_pages();
+    let mut summary = TocEditSummary::default();
+    let mut removed = Vec::new();
+    apply_toc_edits(doc, &mut nodes, edits, &pages, &mut summary, &mut removed)?;
+    for id in removed {
+        doc.objects.remove(&id);
+    }
+
+    if nodes.is_empty() {
+        doc.objects.remove(&outlines_id);
+        doc.catalog_mut()?.remove(b"Outlines");
+        return Ok(summary);
+    }
+    let visible = link_outline_nodes(doc, outlines_id, &nodes)?;
+    let outlines = doc.get_dictionary_mut(outlines_id)?;
+    outlines.set("First", nodes[0].id);
+    outlines.set("Last", nodes[nodes.len() - 1].id);
+    outlines.set("Count", visible);
+
+    Ok(summary)
+}
+
+/// Replace the document's outline with `entries`, each pointing at its
+/// page with a `/Fit` destination. Entries without a page get no
+/// destination. Top-level entries are left open.
+pub fn write_toc(doc: &mut Document, entries: &[TocEntry]) -> Result<()> {
+    let pages = doc.get_pages();
+    if let Ok(&Object::Reference(old)) = doc.catalog()?.get(b"Outlines") {
+        let first = match doc.get_dictionary(old).and_then(|d| d.get(b"First")) {
+            Ok(&Object::Reference(first)) => Some(first),
+            _ => None,
+        };
+        // Items of a damaged old outline stay behind, unreachable.
+        let nodes = first
+            .and_then(|first| read_outline_nodes(doc, first, &mut HashSet::new()).ok())
+            .unwrap_or_default();
+        let mut ids = vec![old];
+        for node in &nodes {
+            collect_outline_ids(node, &mut ids);
+        }
+        for id in ids {
+            doc.objects.remove(&id);
+        }
+        doc.catalog_mut()?.remove(b"Outlines");
+    }
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let outlines_id = doc.new_object_id();
+    let nodes = add_outline_items(doc, entries, &pages, true);
+    let visible = link_outline_nodes(doc, outlines_id, &nodes)?;
+    doc.objects.insert(
+        outlines_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Outlines",
+            "First" => nodes[0].id,
+            "Last" => nodes[nodes.len() - 1].id,
+            "Count" => visible,
+        }),
+    );
+    doc.catalog_mut()?.set("Outlines", outlines_id);
+    Ok(())
+}
+
+/// Add an item object per entry, ready for [`link_outline_nodes`].
+fn add_outline_items(
+    doc: &mut Document,
+    entries: &[TocEntry],
+    pages: &BTreeMap<u32, ObjectId>,
+    open: bool,
+) -> Vec<OutlineNode> {
+    entries
+        .iter()
+        .map(|entry| {
+            let mut item = dictionary! { "Title" => encode_text_string(&entry.title) };
+            if let Some(&page_id) = entry.page.and_then(|page| pages.get(&page)) {
+                item.set("Dest", vec![page_id.into(), "Fit".into()]);
+            }
+            let id = doc.add_object(item);
+            OutlineNode {
+                id,
+                title: entry.title.clone(),
+                open,
+                children: add_outline_items(doc, &entry.children, pages, false),
+            }
+        })
+        .collect()
+}
+
+/// Read a chain of sibling outline items, cutting it where a link leads
+/// back to an item already read (see [`parse_outline_items`]). Relinking
+/// the nodes afterwards repairs such cycles.
+fn read_outline_nodes(
+    doc: &Document,
+    first_id: ObjectId,
+    seen: &mut HashSet<ObjectId>,
+) -> Result<Vec<OutlineNode>> {
+    let mut nodes = Vec::new();
+    let mut current_id = Some(first_id);
+
+    while let Some(id) = current_id {
+        if !seen.insert(id) {
+            break;
+        }
+        let dict = doc
+            .get_dictionary(id)
+            .with_context(|| format!("Outline item {} {} is not a dictionary", id.0, id.1))?;
+        let title = match dict.get(b"Title") {
+            Ok(Object::String(bytes, _)) => decode_text_string(bytes),
+            _ => "Untitled".to_string(),
+        };
+        let open = matches!(dict.get(b"Count"), Ok(Object::Integer(n)) if *n > 0);
+        let children = match dict.get(b"First") {
+            Ok(Object::Reference(child)) => read_outline_nodes(doc, *child, seen)?,
+            _ => Vec::new(),
+        };
+        current_id = match dict.get(b"Next") {
+            Ok(Object::Reference(next)) => Some(*next),
+            _ => None,
+        };
+        nodes.push(OutlineNode {
+            id,
+            title,
+            open,
+            children,
+        });
+    }
+
+    Ok(nodes)
+}
+
+fn any_title_matches(nodes: &[OutlineNode], pattern: &Regex) -> bool {
+    nodes
+        .iter()
+        .any(|node| pattern.is_match(&node.title) || any_title_matches(&node.children, pattern))
+}
+
+fn apply_toc_edits(
+    doc: &mut Document,
+    nodes: &mut Vec<OutlineNode>,
+    edits: &[TocEdit],
+    pages: &BTreeMap<u32, ObjectId>,
+    summary: &mut TocEditSummary,
+    removed: &mut Vec<ObjectId>,
+) -> Result<()> {
+    let mut kept = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        let deleted = edits
+            .iter()
+            .any(|edit| matches!(edit, TocEdit::Delete(re) if re.is_match(&node.title)));
+        if deleted {
+            summary.deleted += 1;
+            collect_outline_ids(&node, removed);
+            continue;
+        }
+
+        for edit in edits {
+            match edit {
+                TocEdit::Rename { pattern, title } if pattern.is_match(&node.title) => {
+                    let title = pattern.replace(&node.title, title.as_str());
+                    doc.get_dictionary_mut(node.id)?
+                        .set("Title", encode_text_string(&title));
+                    summary.renamed += 1;
+                }
+                TocEdit::Retarget { pattern, page } if pattern.is_match(&node.title) => {
+                    let page_id = *pages.get(page).with_context(|| {
+                        format!("Page {} is out of range (1-{})", page, pages.len())
+                    })?;
+                    let item = doc.get_dictionary_mut(node.id)?;
+                    item.remove(b"A");
+                    item.set("Dest", vec![page_id.into(), "Fit".into()]);
+                    summary.retargeted += 1;
+                }
+                _ => {}
+            }
+        }
+
+        apply_toc_edits(doc, &mut node.children, edits, pages, summary, removed)?;
+        kept.push(node);
+    }
+    *nodes = kept;
+    Ok(())
+}
+
+fn collect_outline_ids(node: &OutlineNode, ids: &mut Vec<ObjectId>) {
+    ids.push(node.id);
+    for child in &node.children {
+        collect_outline_ids(child, ids);
+    }
+}
+
+/// Rewrite the sibling and parent links of `nodes` under `parent`,
+/// returning how many of them (and their descendants) are visible.
+fn link_outline_nodes(doc: &mut Document, parent: ObjectId, nodes: &[OutlineNode]) -> Result<i64> {
+    let mut visible = 0;
+    for (i, node) in nodes.iter().enumerate() {
+        let descendants = link_outline_nodes(doc, node.id, &node.children)?;
+        let item = doc.get_dictionary_mut(node.id)?;
+        item.set("Parent", parent);
+        match i.checked_sub(1) {
+            Some(prev) => item.set("Prev", nodes[prev].id),
+            None => {
+                item.remove(b"Prev");
+            }
+        }
+        match nodes.get(i + 1) {
+            Some(next) => item.set("Next", next.id),
+            None => {
+                item.remove(b"Next");
+            }
+        }
+        match (node.children.first(), node.children.last()) {
+            (Some(first), Some(last)) => {
+                item.set("First", first.id);
+                item.set("Last", last.id);
+                // Closed items record their would-be visible count as negative.
+                item.set("Count", if node.open { descendants } else { -descendants });
+            }
+            _ => {
+                item.remove(b"First");
+                item.remove(b"Last");
+                item.remove(b"Count");
+            }
+        }
+        visible += 1 + if node.open { descendants } else { 0 };
+    }
+    Ok(visible)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{add_outline, text_document};
+
+    fn entry(title: &str, page: u32, level: u32, children: Vec<TocEntry>) -> TocEntry {
+        TocEntry {
+            title: title.to_string(),
+            page: Some(page),
+            external: None,
+            level,
+            children,
+        }
+    }
+
+    fn titles(path: &[FlatTocEntry]) -> Vec<&str> {
+        path.iter().map(|e| e.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_find_heading() {
+        let toc = vec![
+            entry("Introduction", 1, 0, vec![]),
+            entry(
+                "Results",
+                4,
+                0,
+                vec![entry("Conclusions  drawn", 7, 1, vec![])],
+            ),
+            entry("Conclusion", 9, 0, vec![]),
+        ];
+
+        let found = find_heading(&toc, "conclusion");
+        assert_eq!(titles(&found), ["Conclusion"]);
+        assert_eq!(found[0].page, Some(9));
+
+        assert_eq!(
+            titles(&find_heading(&toc, "conclusions drawn")),
+            ["Conclusions  drawn"]
+        );
+        assert_eq!(titles(&find_heading(&toc, "sult")), ["Results"]);
+        assert!(find_heading(&toc, "Appendix").is_empty());
+    }
+
+    #[test]
+    fn test_outline_path() {
+        let toc = vec![
+            entry("Preface", 1, 0, vec![]),
+            entry(
+                "Part II",
+                10,
+                0,
+                vec![
+                    entry(
+                        "Chapter 5",
+                        10,
+                        1,
+                        vec![entry("5.3 Results", 14, 2, vec![])],
+                    ),
+                    entry("Chapter 6", 20, 1, vec![]),
+                ],
+            ),
+        ];
+
+        assert_eq!(titles(&outline_path(&toc, 5)), vec!["Preface"]);
+        assert_eq!(
+            titles(&outline_path(&toc, 12)),
+            vec!["Part II", "Chapter 5"]
+        );
+        assert_eq!(
+            titles(&outline_path(&toc, 15)),
+            vec!["Part II", "Chapter 5", "5.3 Results"]
+        );
+        assert_eq!(
+            titles(&outline_path(&toc, 25)),
+            vec!["Part II", "Chapter 6"]
+        );
+    }
+
+    #[test]
+    fn test_outline_path_before_first_entry() {
+        let toc = vec![entry("Chapter 1", 3, 0, vec![])];
+        assert!(outline_path(&toc, 1).is_empty());
+    }
+
+    #[test]
+    fn test_filter_toc() {
+        let toc = vec![
+            entry(
+                "Title 1",
+                1,
+                0,
+                vec![
+                    entry("Chapter 1", 1, 1, vec![entry("§ 101", 2, 2, vec![])]),
+                    entry(
+                        "Chapter 2 Taxation",
+                        5,
+                        1,
+                        vec![entry("§ 201", 5, 2, vec![])],
+                    ),
+                ],
+            ),
+            entry("Title 2", 9, 0, vec![]),
+        ];
+
+        let top = filter_toc(&toc, Some(1), None);
+        assert_eq!(titles(&flatten_toc(&top)), vec!["Title 1", "Title 2"]);
+
+        let re = Regex::new("(?i)tax").unwrap();
+        let matched = filter_toc(&toc, None, Some(&re));
+        assert_eq!(
+            titles(&flatten_toc(&matched)),
+            vec!["Title 1", "Chapter 2 Taxation", "§ 201"]
+        );
+
+        let both = filter_toc(&toc, Some(2), Some(&re));
+        assert_eq!(
+            titles(&flatten_toc(&both)),
+            vec!["Title 1", "Chapter 2 Taxation"]
+        );
+    }
+
+    #[test]
+    fn test_edit_toc() {
+        let (mut doc, page_ids) = text_document(&["a", "b", "c", "d"]);
+        add_outline(
+            &mut doc,
+            &page_ids,
+            &[
+                ("Intro", 0, 0),
+                ("Chapter 1", 1, 0),
+                ("1.1 Setup", 1, 1),
+                ("Appendix A", 2, 0),
+                ("A.1 Tables", 2, 1),
+                ("Appendix B", 3, 0),
+            ],
+        );
+
+        let edits = [
+            TocEdit::Rename {
+                pattern: Regex::new("^Chapter (\\d+)$").unwrap(),
+                title: "Chapter $1: Basics".to_string(),
+            },
+            TocEdit::Delete(Regex::new("^Appendix.*$").unwrap()),
+            TocEdit::Retarget {
+                pattern: Regex::new("^1\\.1 Setup$").unwrap(),
+                page: 3,
+            },
+        ];
+        let summary = edit_toc(&mut doc, &edits).unwrap();
+        assert_eq!(
+            summary,
+            TocEditSummary {
+                renamed: 1,
+                deleted: 2,
+                retargeted: 1,
+            }
+        );
+
+        let toc = extract_toc_from_doc(&doc).unwrap();
+        let flat = flatten_toc(&toc);
+        assert_eq!(
+            titles(&flat),
+            vec!["Intro", "Chapter 1: Basics", "1.1 Setup"]
+        );
+        assert_eq!(flat[2].page, Some(3));
+
+        let outlines = doc
+            .get_dictionary(
+                doc.catalog()
+                    .unwrap()
+                    .get(b"Outlines")
+                    .unwrap()
+                    .as_reference()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(outlines.get(b"Count").unwrap().as_i64().unwrap(), 3);
+        let last = outlines.get(b"Last").unwrap().as_reference().unwrap();
+        assert!(doc.get_dictionary(last).unwrap().get(b"Next").is_err());
+
+        let missing = [TocEdit::Delete(Regex::new("^Nope$").unwrap())];
+        assert!(edit_toc(&mut doc, &missing).is_err());
+    }
+
+    #[test]
+    fn test_write_toc_replaces_outline() {
+        let (mut doc, page_ids) = text_document(&["a", "b", "c"]);
+        add_outline(&mut doc, &page_ids, &[("Old", 0, 0)]);
+
+        let toc = vec![
+            entry("Preface", 1, 0, vec![]),
+            entry("1 Intro", 2, 0, vec![entry("1.1 Scope", 3, 1, vec![])]),
+        ];
+        write_toc(&mut doc, &toc).unwrap();
+
+        let flat = flatten_toc(&extract_toc_from_doc(&doc).unwrap());
+        assert_eq!(titles(&flat), vec!["Preface", "1 Intro", "1.1 Scope"]);
+        assert_eq!(flat[2].page, Some(3));
+        assert_eq!(flat[2].level, 1);
+        assert!(!doc.objects.values().any(|obj| {
+            matches!(obj.as_dict().and_then(|d| d.get(b"Title")), Ok(Object::String(t, _)) if t == b"Old")
+        }));
+    }
+
+    #[test]
+    fn test_external_targets() {
+        let (mut doc, page_ids) = text_document(&["a"]);
+        add_outline(
+            &mut doc,
+            &page_ids,
+            &[
+                ("Local", 0, 0),
+                ("Remote", 0, 0),
+                ("Web", 0, 0),
+                ("Tool", 0, 0),
+            ],
+        );
+        let actions = [
+            dictionary! {
+                "S" => "GoToR",
+                "F" => dictionary! { "Type" => "Filespec", "UF" => Object::string_literal("vol2.pdf") },
+                "D" => vec![4.into(), "Fit".into()],
+            },
+            dictionary! { "S" => "URI", "URI" => Object::string_literal("https://example.com/") },
+            dictionary! { "S" => "Launch", "Win" => dictionary! { "F" => Object::string_literal("calc.exe") } },
+        ];
+        let toc = extract_toc_from_doc(&doc).unwrap();
+        let ids: Vec<ObjectId> = doc
+            .objects
+            .iter()
+            .filter(|(_, obj)| obj.as_dict().is_ok_and(|d| d.has(b"Title")))
+            .map(|(&id, _)| id)
+            .collect();
+        assert_eq!(ids.len(), toc.len());
+        for (id, action) in ids.into_iter().skip(1).zip(actions) {
+            let item = doc.get_dictionary_mut(id).unwrap();
+            item.remove(b"Dest");
+            item.set("A", action);
+        }
+
+        let toc = extract_toc_from_doc(&doc).unwrap();
+        assert_eq!(toc[0].page, Some(1));
+        assert_eq!(toc[0].external, None);
+        assert_eq!(toc[1].page, None);
+        assert_eq!(
+            toc[1].external,
+            Some(ExternalTarget::RemoteFile {
+                file: "vol2.pdf".to_string(),
+                page: Some(5),
+                name: None,
+            })
+        );
+        assert_eq!(
+            toc[2].external.as_ref().map(ToString::to_string).as_deref(),
+            Some("https://example.com/")
+        );
+        assert_eq!(
+            toc[3].external,
+            Some(ExternalTarget::Launch {
+                file: "calc.exe".to_string()
+            })
+        );
+    }
+
+    /// A document whose outline items are linked by `links`: for each item,
+    /// the indexes of its `Next` and `First` items, which may loop back.
+    fn linked_outline(links: &[(Option<usize>, Option<usize>)]) -> Document {
+        let (mut doc, page_ids) = text_document(&["a", "b"]);
+        let ids: Vec<ObjectId> = links.iter().map(|_| doc.new_object_id()).collect();
+        for (i, &(next, first)) in links.iter().enumerate() {
+            let mut item = dictionary! {
+                "Title" => Object::string_literal(format!("Item {}", i)),
+                "Dest" => vec![page_ids[i % 2].into(), "Fit".into()],
+            };
+            if let Some(next) = next {
+                item.set("Next", ids[next]);
+            }
+            if let Some(first) = first {
+                item.set("First", ids[first]);
+            }
+            doc.objects.insert(ids[i], Object::Dictionary(item));
+        }
+        let outlines_id = doc.add_object(dictionary! { "Type" => "Outlines", "First" => ids[0] });
+        doc.catalog_mut().unwrap().set("Outlines", outlines_id);
+        doc
+    }
+
+    #[test]
+    fn test_outline_cycles() {
+        // A self-referencing `Next`, and a child whose `Next` is its parent.
+        let doc = linked_outline(&[(Some(0), Some(1)), (Some(0), None)]);
+        let flat = flatten_toc(&extract_toc_from_doc(&doc).unwrap());
+        assert_eq!(titles(&flat), vec!["Item 0", "Item 1"]);
+
+        let mut doc = doc;
+        write_toc(&mut doc, &[entry("New", 1, 0, vec![])]).unwrap();
+        let flat = flatten_toc(&extract_toc_from_doc(&doc).unwrap());
+        assert_eq!(titles(&flat), vec!["New"]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_outline_links_terminate(
+            links in proptest::collection::vec(
+                (proptest::option::of(0usize..12), proptest::option::of(0usize..12)),
+                1..12,
+            )
+        ) {
+            let n = links.len();
+            let links: Vec<_> = links
+                .into_iter()
+                .map(|(next, first)| (next.map(|i| i % n), first.map(|i| i % n)))
+                .collect();
+            let mut doc = linked_outline(&links);
+            let flat = flatten_toc(&extract_toc_from_doc(&doc).unwrap());
+            // Each item appears at most once.
+            let mut seen = titles(&flat);
+            seen.sort();
+            seen.dedup();
+            proptest::prop_assert_eq!(seen.len(), flat.len());
+            proptest::prop_assert!(flat.len() <= n);
+
+            let edits = [TocEdit::Rename {
+                pattern: Regex::new("^Item 0$").unwrap(),
+                title: "First".to_string(),
+            }];
+            edit_toc(&mut doc, &edits).unwrap();
+            let flat = flatten_toc(&extract_toc_from_doc(&doc).unwrap());
+            proptest::prop_assert_eq!(flat[0].title.as_str(), "First");
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! A small typesetter for generated pages (cover sheets, separators, notes).
+//!
+//! Text is laid out in the standard Helvetica and Courier fonts with greedy
+//! line breaking, flowing onto new pages as needed.
+
+use crate::pdf::builder::DocumentBuilder;
+use crate::pdf::layout::{encode_win_ansi, PageSize, StandardFont};
+use crate::pdf::pages::rect_object;
+use anyhow::Result;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Dictionary, Object, ObjectId, StringFormat};
+
+/// A unit of content to lay out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    /// A bold heading; level 1 is largest.
+    Heading { level: u8, text: String },
+    /// Wrapped text followed by paragraph spacing.
+    Paragraph(String),
+    /// Wrapped text with no extra spacing (a line of plain text).
+    Line(String),
+    /// One line's worth of vertical space.
+    Blank,
+    /// A list item with a hanging indent; `depth` is the nesting level.
+    ListItem {
+        depth: usize,
+        marker: String,
+        text: String,
+    },
+    /// Monospaced lines, broken only where they exceed the page width.
+    Code(Vec<String>),
+    /// A table-of-contents style entry: text, dot leaders, and right-aligned
+    /// trailing text (e.g., a page number). If `link` is set, the entry is a
+    /// clickable link to that destination.
+    Leader {
+        depth: usize,
+        text: String,
+        trailing: String,
+        link: Option<Object>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct TypesetOptions {
+    pub page_size: PageSize,
+    /// Margin on all four sides, in points.
+    pub margin: f32,
+    /// Body text size, in points. Headings and code scale from this.
+    pub font_size: f32,
+}
+
+impl Default for TypesetOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::LETTER,
+            margin: 72.0,
+            font_size: 11.0,
+        }
+    }
+}
+
+/// Points of indentation per list nesting level.
+const LIST_INDENT: f32 = 18.0;
+
+/// Lay out blocks onto new pages appended to `builder`, returning the number
+/// of pages added. At least one page is always added.
+pub fn typeset(
+    builder: &mut DocumentBuilder,
+    blocks: &[Block],
+    options: &TypesetOptions,
+) -> Result<usize> {
+    let mut pager = Pager::new(builder, options);
+    let size = options.font_size;
+    let width = pager.text_width();
+
+    for block in blocks {
+        match block {
+            Block::Heading { level, text } => {
+                let heading_size = size
+                    * match level {
+                        1 => 1.8,
+                        2 => 1.45,
+                        _ => 1.2,
+                    };
+                let leading = heading_size * 1.25;
+                // Keep headings with at least a couple of lines that follow.
+                pager.keep_space(leading + size * 4.0)?;
+                pager.space(size * 0.8);
+                for line in wrap(text, StandardFont::HelveticaBold, heading_size, width) {
+                    pager.line(
+                        StandardFont::HelveticaBold,
+                        heading_size,
+                        0.0,
+                        &line,
+                        leading,
+                    )?;
+                }
+                pager.space(size * 0.4);
+            }
+            Block::Paragraph(text) | Block::Line(text) => {
+                for line in wrap(text, StandardFont::Helvetica, size, width) {
+                    pager.line(StandardFont::Helvetica, size, 0.0, &line, size * 1.35)?;
+                }
+                if matches!(block, Block::Paragraph(_)) {
+                    pager.space(size * 0.6);
+                }
+            }
+            Block::Blank => pager.space(size * 1.35),
+            Block::ListItem {
+                depth,
+                marker,
+                text,
+            } => {
+                let indent = LIST_INDENT * (*depth as f32 + 1.0);
+                let marker_width =
+                    StandardFont::Helvetica.text_width(&encode_win_ansi(marker), size);
+                let marker_x = (indent - marker_width - size * 0.4).max(0.0);
+                let lines = wrap(text, StandardFont::Helvetica, size, width - indent);
+                for (i, line) in lines.iter().enumerate() {
+                    pager.line(StandardFont::Helvetica, size, indent, line, size * 1.35)?;
+                    if i == 0 {
+                        pager.draw(StandardFont::Helvetica, size, marker_x, marker);
+                    }
+                }
+                pager.space(size * 0.2);
+            }
+            Block::Code(lines) => {
+                let code_size = size * 0.85;
+                let columns = (width / StandardFont::Courier.text_width(b" ", code_size)) as usize;
+                for line in lines {
+                    for chunk in hard_wrap(line, columns.max(1)) {
+                        pager.line(
+                            StandardFont::Courier,
+                            code_size,
+                            0.0,
+                            &chunk,
+                            code_size * 1.25,
+                        )?;
+                    }
+                }
+                pager.space(size * 0.6);
+            }
+            Block::Leader {
+                depth,
+                text,
+                trailing,
+                link,
+            } => {
+                let font = StandardFont::Helvetica;
+                let leading = size * 1.35;
+                let indent = LIST_INDENT * *depth as f32;
+                let trailing_width = font.text_width(&encode_win_ansi(trailing), size);
+                let gap = size;
+                let lines = wrap(text, font, size, width - indent - trailing_width - gap);
+                for (i, line) in lines.iter().enumerate() {
+                    pager.line(font, size, indent, line, leading)?;
+                    if i + 1 == lines.len() && !trailing.is_empty() {
+                        let line_end = indent + font.text_width(&encode_win_ansi(line), size);
+                        let dots_start = line_end + size * 0.5;
+                        let dots_end = width - trailing_width - size * 0.5;
+                        let dot_width = font.text_width(b".", size);
+                        let dots = ((dots_end - dots_start) / dot_width).max(0.0) as usize;
+                        pager.draw(font, size, dots_start, &".".repeat(dots));
+                        pager.draw(font, size, width - trailing_width, trailing);
+                    }
+                    if let Some(dest) = link {
+                        pager.link(indent, width, size, dest);
+                    }
+                }
+            }
+        }
+    }
+
+    pager.finish()
+}
+
+/// Break text into lines no wider than `max_width`, splitting at spaces and
+/// breaking words that are too long to fit on a line of their own.
+pub fn wrap(text: &str, font: StandardFont, size: f32, max_width: f32) -> Vec<String> {
+    let fits = |s: &str| font.text_width(&encode_win_ansi(s), size) <= max_width;
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if fits(&candidate) {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        // Break an overlong word wherever it runs out of room.
+        for c in word.chars() {
+            current.push(c);
+            if !fits(&current) && current.chars().count() > 1 {
+                current.pop();
+                lines.push(std::mem::replace(&mut current, c.to_string()));
+            }
+        }
+    }
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Split a line into chunks of at most `columns` characters.
+fn hard_wrap(line: &str, columns: usize) -> Vec<String> {
+    let chars: Vec<char> = line.replace('\t', "    ").chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars.chunks(columns).map(|c| c.iter().collect()).collect()
+}
+
+fn font_name(font: StandardFont) -> &'static str {
+    match font {
+        StandardFont::Helvetica => "F1",
+        StandardFont::HelveticaBold => "F2",
+        StandardFont::Courier => "F3",
+    }
+}
+
+/// Tracks the current page's content and the vertical cursor.
+struct Pager<'a> {
+    builder: &'a mut DocumentBuilder,
+    options: &'a TypesetOptions,
+    fonts_id: ObjectId,
+    ops: Vec<Operation>,
+    annots: Vec<Object>,
+    /// Baseline of the most recent line.
+    y: f32,
+    pages: usize,
+}
+
+impl<'a> Pager<'a> {
+    fn new(builder: &'a mut DocumentBuilder, options: &'a TypesetOptions) -> Self {
+        let mut fonts = Dictionary::new();
+        for font in StandardFont::ALL {
+            let id = builder.doc_mut().add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => font.base_font(),
+                "Encoding" => "WinAnsiEncoding",
+            });
+            fonts.set(font_name(font), id);
+        }
+        let fonts_id = builder.doc_mut().add_object(fonts);
+        Self {
+            builder,
+            options,
+            fonts_id,
+            ops: Vec::new(),
+            annots: Vec::new(),
+            y: options.page_size.height - options.margin,
+            pages: 0,
+        }
+    }
+
+    fn text_width(&self) -> f32 {
+        self.options.page_size.width - 2.0 * self.options.margin
+    }
+
+    fn at_top(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Start a new page unless `height` points remain on this one.
+    fn keep_space(&mut self, height: f32) -> Result<()> {
+        if !self.at_top() && self.y - height < self.options.margin {
+            self.new_page()?;
+        }
+        Ok(())
+    }
+
+    /// Move the cursor down, unless at the top of a page.
+    fn space(&mut self, amount: f32) {
+        if !self.at_top() {
+            self.y -= amount;
+        }
+    }
+
+    /// Advance by `leading` and draw a line of text at `x` from the margin.
+    fn line(
+        &mut self,
+        font: StandardFont,
+        size: f32,
+        x: f32,
+        text: &str,
+        leading: f32,
+    ) -> Result<()> {
+        self.keep_space(leading)?;
+        self.y -= leading;
+        self.draw(font, size, x, text);
+        Ok(())
+    }
+
+    /// Draw text on the current baseline without moving the cursor.
+    fn draw(&mut self, font: StandardFont, size: f32, x: f32, text: &str) {
+        let x = self.options.margin + x;
+        self.ops.extend([
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec![font_name(font).into(), Object::Real(size)]),
+            Operation::new("Td", vec![Object::Real(x), Object::Real(self.y)]),
+            Operation::new(
+                "Tj",
+                vec![Object::String(encode_win_ansi(text), StringFormat::Literal)],
+            ),
+            Operation::new("ET", vec![]),
+        ]);
+    }
+
+    /// Make the current line, from `x0` to `x1` past the margin, a link.
+    fn link(&mut self, x0: f32, x1: f32, size: f32, dest: &Object) {
+        let margin = self.options.margin;
+        let rect = [
+            margin + x0,
+            self.y - size * 0.25,
+            margin + x1,
+            self.y + size * 0.9,
+        ];
+        self.annots.push(Object::Dictionary(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => rect_object(rect),
+            "Border" => vec![0.into(), 0.into(), 0.into()],
+            "Dest" => dest.clone(),
+        }));
+    }
+
+    fn new_page(&mut self) -> Result<()> {
+        let content = Content {
+            operations: std::mem::take(&mut self.ops),
+        }
+        .encode()?;
+        let size = self.options.page_size;
+        let page_id = self.builder.add_page(
+            [0.0, 0.0, size.width, size.height],
+            dictionary! { "Font" => self.fonts_id },
+            content,
+        );
+        if !self.annots.is_empty() {
+            let annots = std::mem::take(&mut self.annots);
+            self.builder
+                .doc_mut()
+                .get_dictionary_mut(page_id)?
+                .set("Annots", annots);
+        }
+        self.pages += 1;
+        self.y = size.height - self.options.margin;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<usize> {
+        if !self.at_top() || self.pages == 0 {
+            self.new_page()?;
+        }
+        Ok(self.pages)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_breaks_at_spaces() {
+        // Each "aaaa" is 4 * 556 = 2224 units, 22.24pt at size 10.
+        let lines = wrap("aaaa aaaa aaaa", StandardFont::Helvetica, 10.0, 50.0);
+        assert_eq!(lines, vec!["aaaa aaaa", "aaaa"]);
+    }
+
+    #[test]
+    fn test_wrap_splits_long_words() {
+        let lines = wrap("aaaaaaaaaa", StandardFont::Helvetica, 10.0, 23.0);
+        assert_eq!(lines, vec!["aaaa", "aaaa", "aa"]);
+    }
+
+    #[test]
+    fn test_typeset_flows_onto_new_pages() {
+        let mut builder = DocumentBuilder::new();
+        let blocks = vec![Block::Line("line".to_string()); 200];
+        let pages = typeset(&mut builder, &blocks, &TypesetOptions::default()).unwrap();
+        assert!(pages > 1);
+
+        let doc = builder.finish().unwrap();
+        assert_eq!(doc.get_pages().len(), pages);
+        let content = doc.get_page_content(doc.get_pages()[&1]).unwrap();
+        assert!(String::from_utf8_lossy(&content).contains("(line) Tj"));
+    }
+
+    #[test]
+    fn test_leader_links_to_destination() {
+        let mut builder = DocumentBuilder::new();
+        let dest = Object::Array(vec![Object::Reference((99, 0)), "Fit".into()]);
+        let blocks = vec![Block::Leader {
+            depth: 0,
+            text: "Introduction".to_string(),
+            trailing: "7".to_string(),
+            link: Some(dest.clone()),
+        }];
+        typeset(&mut builder, &blocks, &TypesetOptions::default()).unwrap();
+
+        let doc = builder.finish().unwrap();
+        let page_id = doc.get_pages()[&1];
+        let page = doc.get_dictionary(page_id).unwrap();
+        let annots = page.get(b"Annots").unwrap().as_array().unwrap();
+        assert_eq!(annots.len(), 1);
+        let link = annots[0].as_dict().unwrap();
+        assert_eq!(link.get(b"Dest").unwrap(), &dest);
+
+        let content = doc.get_page_content(page_id).unwrap();
+        let content = String::from_utf8_lossy(&content);
+        assert!(content.contains("(Introduction) Tj"));
+        assert!(content.contains("...."));
+        assert!(content.contains("(7) Tj"));
+    }
+
+    #[test]
+    fn test_typeset_empty_adds_blank_page() {
+        let mut builder = DocumentBuilder::new();
+        assert_eq!(
+            typeset(&mut builder, &[], &TypesetOptions::default()).unwrap(),
+            1
+        );
+    }
+}
@@ -0,0 +1,753 @@
+//! Structural checks of a whole document.
+//!
+//! Viewers are forgiving, so a file that opens fine can still have streams
+//! that don't decode, a page tree whose counts are wrong, or outline and
+//! name trees that loop. [`verify_document`] walks all of it once and
+//! reports what it finds, as a sanity check after writing a file.
+
+use crate::pdf::links::named_destination;
+use crate::pdf::pages::{find_inherited, resolve};
+use flate2::read::ZlibDecoder;
+use lopdf::xref::XrefEntry;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// Deepest tree node visited, which also bounds recursion on hostile files.
+const MAX_TREE_DEPTH: usize = 64;
+
+/// Filters that can be decoded here; streams using others (the image
+/// codecs) are counted but not decoded.
+const DECODABLE_FILTERS: &[&[u8]] = &[b"FlateDecode", b"Fl", b"LZWDecode", b"LZW"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Content is lost or unreachable: a reader will show something wrong.
+    Error,
+    /// The file breaks the spec in a way most readers tolerate.
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error: {}", self.message),
+            Severity::Warning => write!(f, "warning: {}", self.message),
+        }
+    }
+}
+
+/// What [`verify_document`] checked and found.
+#[derive(Debug, Default, Clone)]
+pub struct VerifyReport {
+    pub objects: usize,
+    pub streams: usize,
+    /// Streams left undecoded because lopdf has no decoder for their
+    /// filter (JPEG, JPEG 2000, CCITT, JBIG2 images).
+    pub streams_not_decoded: usize,
+    pub pages: usize,
+    pub outline_items: usize,
+    /// Entries in all name and number trees.
+    pub tree_entries: usize,
+    pub problems: Vec<Problem>,
+}
+
+impl VerifyReport {
+    pub fn errors(&self) -> usize {
+        self.problems
+            .iter()
+            .filter(|problem| problem.severity == Severity::Error)
+            .count()
+    }
+
+    fn error(&mut self, message: String) {
+        self.problems.push(Problem {
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, message: String) {
+        self.problems.push(Problem {
+            severity: Severity::Warning,
+            message,
+        });
+    }
+}
+
+/// Check every object, stream, the page tree, the outline, and the name and
+/// number trees of `doc`.
+pub fn verify_document(doc: &Document) -> VerifyReport {
+    let mut report = VerifyReport::default();
+    check_objects(doc, &mut report);
+    check_streams(doc, &mut report);
+    let pages = check_page_tree(doc, &mut report);
+    check_outline(doc, &pages, &mut report);
+    check_trees(doc, &mut report);
+    report
+}
+
+fn show(id: ObjectId) -> String {
+    format!("{} {} R", id.0, id.1)
+}
+
+/// Objects the cross-reference table lists but that couldn't be parsed, and
+/// references to objects that don't exist.
+fn check_objects(doc: &Document, report: &mut VerifyReport) {
+    report.objects = doc.objects.len();
+
+    // Object streams are unpacked on load, so their containers may be gone.
+    let containers: HashSet<u32> = doc
+        .reference_table
+        .entries
+        .values()
+        .filter_map(|entry| match entry {
+            XrefEntry::Compressed { container, .. } => Some(*container),
+            _ => None,
+        })
+        .collect();
+    for (&number, entry) in &doc.reference_table.entries {
+        let id = match *entry {
+            XrefEntry::Normal { generation, .. } => (number, generation),
+            XrefEntry::Compressed { .. } => (number, 0),
+            _ => continue,
+        };
+        if !containers.contains(&number) && !doc.objects.contains_key(&id) {
+            report.error(format!("object {} could not be read", show(id)));
+        }
+    }
+
+    // Readers treat these as null, so they're only worth a warning, once
+    // per missing object.
+    let mut missing = BTreeMap::new();
+    for (&owner, object) in &doc.objects {
+        find_missing(doc, object, owner, &mut missing);
+    }
+    for (target, owner) in missing {
+        report.warning(format!(
+            "object {} refers to missing object {}",
+            show(owner),
+            show(target)
+        ));
+    }
+}
+
+fn find_missing(
+    doc: &Document,
+    object: &Object,
+    owner: ObjectId,
+    missing: &mut BTreeMap<ObjectId, ObjectId>,
+) {
+    match object {
+        Object::Reference(id) if !doc.objects.contains_key(id) => {
+            missing.entry(*id).or_insert(owner);
+        }
+        Object::Array(items) => {
+            for item in items {
+                find_missing(doc, item, owner, missing);
+            }
+        }
+        Object::Dictionary(dict) => {
+            for (_, value) in dict.iter() {
+                find_missing(doc, value, owner, missing);
+            }
+        }
+        Object::Stream(stream) => {
+            for (_, value) in stream.dict.iter() {
+                find_missing(doc, value, owner, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn check_streams(doc: &Document, report: &mut VerifyReport) {
+    for (&id, object) in &doc.objects {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        report.streams += 1;
+        let filters: Vec<&[u8]> = match stream.dict.get(b"Filter") {
+            Ok(Object::Name(name)) => vec![name.as_slice()],
+            Ok(Object::Array(names)) => names.iter().filter_map(|n| n.as_name().ok()).collect(),
+            _ => continue,
+        };
+        if filters.is_empty() {
+            continue;
+        }
+        if !filters.iter().all(|f| DECODABLE_FILTERS.contains(f)) {
+            report.streams_not_decoded += 1;
+            continue;
+        }
+        // lopdf keeps whatever it could inflate from a corrupt Flate stream
+        // without saying so, so check those separately.
+        let result = match filters[0] {
+            b"FlateDecode" | b"Fl" if !stream.content.is_empty() => inflate(&stream.content),
+            _ => Ok(()),
+        }
+        .and_then(|()| {
+            stream
+                .decompressed_content()
+                .map(drop)
+                .map_err(|e| e.to_string())
+        });
+        if let Err(e) = result {
+            report.error(format!("stream {} does not decode: {}", show(id), e));
+        }
+    }
+}
+
+fn inflate(data: &[u8]) -> Result<(), String> {
+    let mut decoder = ZlibDecoder::new(data);
+    std::io::copy(&mut decoder, &mut std::io::sink())
+        .map(drop)
+        .map_err(|e| e.to_string())
+}
+
+/// Walk the page tree from the catalog, then parse each page's content.
+/// Returns the pages found, in order.
+fn check_page_tree(doc: &Document, report: &mut VerifyReport) -> Vec<ObjectId> {
+    let mut pages = Vec::new();
+    let Ok(catalog) = doc.catalog() else {
+        report.error("the trailer has no document catalog (Root)".to_string());
+        return pages;
+    };
+    let Ok(&Object::Reference(root)) = catalog.get(b"Pages") else {
+        report.error("the catalog has no page tree (Pages)".to_string());
+        return pages;
+    };
+    walk_pages(doc, root, None, &mut HashSet::new(), &mut pages, report, 0);
+    report.pages = pages.len();
+
+    for (i, &page_id) in pages.iter().enumerate() {
+        if find_inherited(doc, page_id, b"MediaBox").is_none() {
+            report.error(format!("page {} has no MediaBox", i + 1));
+        }
+        if let Err(e) = doc.get_and_decode_page_content(page_id) {
+            report.error(format!("page {}: content does not parse: {}", i + 1, e));
+        }
+    }
+    pages
+}
+
+/// Visit a page tree node, returning the number of pages under it.
+fn walk_pages(
+    doc: &Document,
+    id: ObjectId,
+    parent: Option<ObjectId>,
+    seen: &mut HashSet<ObjectId>,
+    pages: &mut Vec<ObjectId>,
+    report: &mut VerifyReport,
+    depth: usize,
+) -> i64 {
+    if !seen.insert(id) {
+        report.error(format!("page tree node {} is reached twice", show(id)));
+        return 0;
+    }
+    if depth > MAX_TREE_DEPTH {
+        report.error(format!(
+            "page tree is more than {} levels deep",
+            MAX_TREE_DEPTH
+        ));
+        return 0;
+    }
+    let Ok(node) = doc.get_dictionary(id) else {
+        report.error(format!("page tree node {} is not a dictionary", show(id)));
+        return 0;
+    };
+    if let Some(parent) = parent {
+        if !matches!(node.get(b"Parent"), Ok(&Object::Reference(p)) if p == parent) {
+            report.warning(format!(
+                "page tree node {} does not point back to its parent",
+                show(id)
+            ));
+        }
+    }
+
+    let kind = node.get(b"Type").and_then(Object::as_name).ok();
+    match kind {
+        Some(b"Pages") => {}
+        None if node.has(b"Kids") => {}
+        Some(b"Page") | None => {
+            pages.push(id);
+            return 1;
+        }
+        Some(other) => {
+            report.error(format!(
+                "page tree node {} has Type /{}",
+                show(id),
+                String::from_utf8_lossy(other)
+            ));
+            return 0;
+        }
+    }
+
+    let Ok(Object::Array(kids)) = node.get(b"Kids").map(|kids| resolve(doc, kids)) else {
+        report.error(format!("page tree node {} has no Kids array", show(id)));
+        return 0;
+    };
+    let mut count = 0;
+    for kid in kids {
+        match kid {
+            &Object::Reference(kid) => {
+                count += walk_pages(doc, kid, Some(id), seen, pages, report, depth + 1)
+            }
+            _ => report.error(format!(
+                "page tree node {} has a kid that is not a reference",
+                show(id)
+            )),
+        }
+    }
+    match node
+        .get(b"Count")
+        .map(|n| resolve(doc, n))
+        .and_then(Object::as_i64)
+    {
+        Ok(declared) if declared == count => {}
+        Ok(declared) => report.warning(format!(
+            "page tree node {} has Count {} but {} page(s)",
+            show(id),
+            declared,
+            count
+        )),
+        Err(_) => report.warning(format!("page tree node {} has no Count", show(id))),
+    }
+    count
+}
+
+fn check_outline(doc: &Document, pages: &[ObjectId], report: &mut VerifyReport) {
+    let Some(outlines) = doc
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Outlines").ok())
+    else {
+        return;
+    };
+    let root_id = outlines.as_reference().ok();
+    let Ok(root) = resolve(doc, outlines).as_dict() else {
+        report.error("the outline root is not a dictionary".to_string());
+        return;
+    };
+    let pages: HashSet<ObjectId> = pages.iter().copied().collect();
+    let mut seen = HashSet::new();
+    // (parent, its dictionary) for each level still to walk.
+    let mut levels = vec![(root_id, root)];
+    while let Some((parent_id, parent)) = levels.pop() {
+        let mut next = parent.get(b"First").ok();
+        let mut previous = None;
+        while let Some(&Object::Reference(id)) = next {
+            if !seen.insert(id) {
+                report.error(format!("outline item {} is reached twice", show(id)));
+                break;
+            }
+            let Ok(item) = doc.get_dictionary(id) else {
+                report.error(format!("outline item {} is not a dictionary", show(id)));
+                break;
+            };
+            report.outline_items += 1;
+
+            let title = match item.get(b"Title").map(|t| resolve(doc, t)) {
+                Ok(Object::String(title, _)) => String::from_utf8_lossy(title).into_owned(),
+                _ => {
+                    report.error(format!("outline item {} has no Title", show(id)));
+                    show(id)
+                }
+            };
+            if let Some(parent_id) = parent_id {
+                if !matches!(item.get(b"Parent"), Ok(&Object::Reference(p)) if p == parent_id) {
+                    report.warning(format!(
+                        "outline item \"{}\" does not point back to its parent",
+                        title
+                    ));
+                }
+            }
+            if let Some(previous) = previous {
+                if !matches!(item.get(b"Prev"), Ok(&Object::Reference(p)) if p == previous) {
+                    report.warning(format!("outline item \"{}\" has the wrong Prev", title));
+                }
+            }
+            if let Some(problem) = destination_problem(doc, item, &pages) {
+                report.warning(format!("outline item \"{}\" {}", title, problem));
+            }
+
+            if item.has(b"First") {
+                levels.push((Some(id), item));
+            }
+            previous = Some(id);
+            next = item.get(b"Next").ok();
+        }
+        if let (Some(last), Ok(&Object::Reference(declared))) = (previous, parent.get(b"Last")) {
+            if last != declared {
+                report.warning(format!(
+                    "outline node {} has the wrong Last",
+                    parent_id.map_or("root".to_string(), show)
+                ));
+            }
+        }
+    }
+}
+
+/// Why an outline item's destination doesn't lead to a page here, if it
+/// has one that should.
+fn destination_problem(
+    doc: &Document,
+    item: &Dictionary,
+    pages: &HashSet<ObjectId>,
+) -> Option<String> {
+    let dest = match item.get(b"Dest") {
+        Ok(dest) => resolve(doc, dest),
+        Err(_) => {
+            let action = resolve(doc, item.get(b"A").ok()?).as_dict().ok()?;
+            if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+                return None;
+            }
+            resolve(doc, action.get(b"D").ok()?)
+        }
+    };
+    let dest = match dest {
+        Object::Name(name) | Object::String(name, _) => match named_destination(doc, name) {
+            Some(dest) => dest,
+            None => {
+                return Some(format!(
+                    "uses named destination \"{}\", which does not exist",
+                    String::from_utf8_lossy(name)
+                ))
+            }
+        },
+        Object::Array(dest) => dest.clone(),
+        _ => return Some("has a malformed destination".to_string()),
+    };
+    match dest.first() {
+        Some(Object::Reference(page)) if pages.contains(page) => None,
+        Some(Object::Reference(_)) => Some("points to an object that is not a page".to_string()),
+        Some(Object::Integer(_)) => None,
+        _ => Some("has a malformed destination".to_string()),
+    }
+}
+
+/// A name tree (string keys) or number tree (integer keys).
+#[derive(Clone, Copy)]
+enum TreeKind {
+    Name,
+    Number,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+    Number(i64),
+    Name(Vec<u8>),
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Number(n) => write!(f, "{}", n),
+            Key::Name(name) => write!(f, "\"{}\"", String::from_utf8_lossy(name)),
+        }
+    }
+}
+
+/// The catalog's name trees (`Names`), page labels, and the structure
+/// tree's parent tree.
+fn check_trees(doc: &Document, report: &mut VerifyReport) {
+    let Ok(catalog) = doc.catalog() else {
+        return;
+    };
+    if let Ok(names) = catalog.get(b"Names") {
+        match resolve(doc, names).as_dict() {
+            Ok(names) => {
+                for (key, tree) in names.iter() {
+                    let label = format!("{} name tree", String::from_utf8_lossy(key));
+                    check_tree(doc, tree, TreeKind::Name, &label, report);
+                }
+            }
+            Err(_) => report.error("the catalog's Names is not a dictionary".to_string()),
+        }
+    }
+    if let Ok(dests) = catalog.get(b"Dests") {
+        if resolve(doc, dests).as_dict().is_err() {
+            report.error("the catalog's Dests is not a dictionary".to_string());
+        }
+    }
+    if let Ok(labels) = catalog.get(b"PageLabels") {
+        check_tree(doc, labels, TreeKind::Number, "page label tree", report);
+    }
+    let parent_tree = catalog
+        .get(b"StructTreeRoot")
+        .ok()
+        .and_then(|root| resolve(doc, root).as_dict().ok())
+        .and_then(|root| root.get(b"ParentTree").ok());
+    if let Some(parent_tree) = parent_tree {
+        check_tree(
+            doc,
+            parent_tree,
+            TreeKind::Number,
+            "structure parent tree",
+            report,
+        );
+    }
+}
+
+fn check_tree(
+    doc: &Document,
+    root: &Object,
+    kind: TreeKind,
+    label: &str,
+    report: &mut VerifyReport,
+) {
+    let mut walk = TreeWalk {
+        doc,
+        kind,
+        label,
+        seen: HashSet::new(),
+        previous: None,
+    };
+    walk.node(root, true, report, 0);
+}
+
+struct TreeWalk<'a> {
+    doc: &'a Document,
+    kind: TreeKind,
+    label: &'a str,
+    seen: HashSet<ObjectId>,
+    /// The last key visited, to check that keys are in order.
+    previous: Option<Key>,
+}
+
+impl TreeWalk<'_> {
+    /// Visit a node, returning the first and last keys under it.
+    fn node(
+        &mut self,
+        node: &Object,
+        is_root: bool,
+        report: &mut VerifyReport,
+        depth: usize,
+    ) -> Option<(Key, Key)> {
+        if let Object::Reference(id) = node {
+            if !self.seen.insert(*id) {
+                report.error(format!(
+                    "{}: node {} is reached twice",
+                    self.label,
+                    show(*id)
+                ));
+                return None;
+            }
+        }
+        if depth > MAX_TREE_DEPTH {
+            report.error(format!(
+                "{}: more than {} levels deep",
+                self.label, MAX_TREE_DEPTH
+            ));
+            return None;
+        }
+        let Ok(dict) = resolve(self.doc, node).as_dict() else {
+            report.error(format!("{}: a node is not a dictionary", self.label));
+            return None;
+        };
+
+        let mut bounds: Option<(Key, Key)> = None;
+
+        let entries_key: &[u8] = match self.kind {
+            TreeKind::Name => b"Names",
+            TreeKind::Number => b"Nums",
+        };
+        if let Ok(entries) = dict.get(entries_key) {
+            let Ok(entries) = resolve(self.doc, entries).as_array() else {
+                report.error(format!("{}: a node's entries are not an array", self.label));
+                return None;
+            };
+            if entries.len() % 2 != 0 {
+                report.error(format!(
+                    "{}: a node has an odd number of entries",
+                    self.label
+                ));
+            }
+            for pair in entries.chunks_exact(2) {
+                let key = match (self.kind, resolve(self.doc, &pair[0])) {
+                    (TreeKind::Name, Object::String(key, _)) => Key::Name(key.clone()),
+                    (TreeKind::Number, Object::Integer(key)) => Key::Number(*key),
+                    _ => {
+                        report.error(format!("{}: a key has the wrong type", self.label));
+                        continue;
+                    }
+                };
+                report.tree_entries += 1;
+                if self
+                    .previous
+                    .as_ref()
+                    .is_some_and(|previous| key <= *previous)
+                {
+                    report.warning(format!("{}: key {} is out of order", self.label, key));
+                }
+                self.previous = Some(key.clone());
+                extend(&mut bounds, key.clone(), key);
+            }
+        }
+
+        if let Ok(kids) = dict.get(b"Kids") {
+            let Ok(kids) = resolve(self.doc, kids).as_array() else {
+                report.error(format!("{}: a node's Kids is not an array", self.label));
+                return bounds;
+            };
+            for kid in kids {
+                if let Some((first, last)) = self.node(kid, false, report, depth + 1) {
+                    extend(&mut bounds, first, last);
+                }
+            }
+        }
+
+        // Limits let readers skip subtrees; wrong ones hide entries.
+        if !is_root {
+            let limits = dict
+                .get(b"Limits")
+                .ok()
+                .and_then(|limits| resolve(self.doc, limits).as_array().ok());
+            let declared = limits.and_then(|limits| match (self.kind, limits.as_slice()) {
+                (TreeKind::Name, [Object::String(low, _), Object::String(high, _)]) => {
+                    Some((Key::Name(low.clone()), Key::Name(high.clone())))
+                }
+                (TreeKind::Number, [Object::Integer(low), Object::Integer(high)]) => {
+                    Some((Key::Number(*low), Key::Number(*high)))
+                }
+                _ => None,
+            });
+            match (&declared, &bounds) {
+                (Some(declared), Some(actual)) if declared != actual => report.warning(format!(
+                    "{}: Limits [{} {}] don't match the keys [{} {}]",
+                    self.label, declared.0, declared.1, actual.0, actual.1
+                )),
+                (None, Some(_)) => {
+                    report.warning(format!("{}: a node has no valid Limits", self.label))
+                }
+                _ => {}
+            }
+        }
+        bounds
+    }
+}
+
+/// Grow a node's key range by the keys that follow.
+fn extend(bounds: &mut Option<(Key, Key)>, first: Key, last: Key) {
+    match bounds {
+        Some((_, end)) => *end = last,
+        None => *bounds = Some((first, last)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::{add_outline, text_document};
+    use lopdf::{dictionary, Stream};
+
+    fn messages(report: &VerifyReport) -> Vec<String> {
+        report.problems.iter().map(Problem::to_string).collect()
+    }
+
+    #[test]
+    fn test_verify_clean_document() {
+        let (mut doc, page_ids) = text_document(&["one", "two"]);
+        add_outline(&mut doc, &page_ids, &[("Intro", 0, 0), ("Body", 1, 0)]);
+        let report = verify_document(&doc);
+        assert_eq!(messages(&report), Vec::<String>::new());
+        assert_eq!(report.pages, 2);
+        assert_eq!(report.outline_items, 2);
+    }
+
+    #[test]
+    fn test_verify_reports_problems() {
+        let (mut doc, page_ids) = text_document(&["one", "two"]);
+        add_outline(&mut doc, &page_ids, &[("Intro", 0, 0), ("Body", 1, 0)]);
+
+        // Count one too many, a stream that isn't really Flate, a link to
+        // nowhere, an outline loop, and unsorted names with wrong Limits.
+        let pages_id = doc
+            .catalog()
+            .unwrap()
+            .get(b"Pages")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        doc.get_dictionary_mut(pages_id).unwrap().set("Count", 3);
+        let content_id = doc
+            .get_dictionary(page_ids[1])
+            .unwrap()
+            .get(b"Contents")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        doc.objects.insert(
+            content_id,
+            Object::Stream(Stream::new(
+                dictionary! { "Filter" => "FlateDecode" },
+                b"not deflate".to_vec(),
+            )),
+        );
+        doc.get_dictionary_mut(page_ids[0])
+            .unwrap()
+            .set("Thumb", Object::Reference((999, 0)));
+
+        let outlines_id = doc
+            .catalog()
+            .unwrap()
+            .get(b"Outlines")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let first = doc
+            .get_dictionary(outlines_id)
+            .unwrap()
+            .get(b"First")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        let last = doc
+            .get_dictionary(outlines_id)
+            .unwrap()
+            .get(b"Last")
+            .unwrap()
+            .as_reference()
+            .unwrap();
+        doc.get_dictionary_mut(last).unwrap().set("Next", first);
+
+        let leaf = doc.add_object(dictionary! {
+            "Names" => vec![
+                Object::string_literal("b"), Object::Null,
+                Object::string_literal("a"), Object::Null,
+            ],
+            "Limits" => vec![Object::string_literal("a"), Object::string_literal("b")],
+        });
+        let names = dictionary! { "EmbeddedFiles" => dictionary! { "Kids" => vec![leaf.into()] } };
+        doc.catalog_mut().unwrap().set("Names", names);
+
+        let report = verify_document(&doc);
+        let messages = messages(&report);
+        let expected = [
+            format!(
+                "warning: object {} refers to missing object 999 0 R",
+                show(page_ids[0])
+            ),
+            format!("error: stream {} does not decode", show(content_id)),
+            format!("warning: page tree node {} has Count 3 but 2 page(s)", show(pages_id)),
+            format!("error: outline item {} is reached twice", show(first)),
+            "warning: EmbeddedFiles name tree: key \"a\" is out of order".to_string(),
+            "warning: EmbeddedFiles name tree: Limits [\"a\" \"b\"] don't match the keys [\"b\" \"a\"]"
+                .to_string(),
+        ];
+        for expected in expected {
+            assert!(
+                messages.iter().any(|m| m.starts_with(&expected)),
+                "missing {:?} in {:#?}",
+                expected,
+                messages
+            );
+        }
+        assert_eq!(report.errors(), 2);
+    }
+}
@@ -0,0 +1,233 @@
+//! Form XObjects: reusing whole pages as drawable objects.
+//!
+//! Turning a page into a Form XObject lets it be drawn onto other pages
+//! (overlays, letterheads, thumbnails) while its fonts and images are shared
+//! rather than duplicated.
+
+use crate::pdf::import::Importer;
+use crate::pdf::pages::{inherited_attribute, page_box, rect_object, Rect};
+use anyhow::{Context, Result};
+use lopdf::{dictionary, Dictionary, Document, Object, ObjectId, Stream};
+use std::collections::HashMap;
+
+/// Where drawn content goes relative to a page's existing content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Under,
+    Over,
+}
+
+/// Convert a page of the importer's source document into a Form XObject in
+/// `target`, returning its ID and bounding box (the page's CropBox).
+pub fn page_to_form(
+    importer: &mut Importer,
+    target: &mut Document,
+    page_id: ObjectId,
+) -> Result<(ObjectId, Rect)> {
+    let source = importer.source();
+    let content = source
+        .get_page_content(page_id)
+        .context("Failed to read page content")?;
+    let bbox = page_box(source, page_id, b"CropBox");
+    let resources = match inherited_attribute(source, page_id, b"Resources") {
+        Some(resources) => importer.import_value(target, &resources),
+        None => Object::Dictionary(Dictionary::new()),
+    };
+
+    let mut stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => rect_object(bbox),
+            "Resources" => resources,
+        },
+        content,
+    );
+    // Compression is an optimization; keep the raw stream if it fails.
+    let _ = stream.compress();
+
+    Ok((target.add_object(stream), bbox))
+}
+
+/// Draw a Form XObject on a page with the given transformation matrix.
+pub fn place_form(
+    doc: &mut Document,
+    page_id: ObjectId,
+    form_id: ObjectId,
+    matrix: [f32; 6],
+    layer: Layer,
+) -> Result<()> {
+    let name = add_xobject_resource(doc, page_id, form_id)?;
+    let ops = format!(
+        "\nq {} {} {} {} {} {} cm /{} Do Q\n",
+        matrix[0], matrix[1], matrix[2], matrix[3], matrix[4], matrix[5], name
+    );
+    add_content(doc, page_id, ops.into_bytes(), layer)
+}
+
+/// Register an XObject in a page's resources under a fresh name.
+///
+/// Resources are copied onto the page itself first, so dictionaries shared
+/// with other pages (directly or through inheritance) are left untouched.
+pub fn add_xobject_resource(
+    doc: &mut Document,
+    page_id: ObjectId,
+    xobject_id: ObjectId,
+) -> Result<String> {
+    let mut resources = match inherited_attribute(doc, page_id, b"Resources") {
+        Some(Object::Dictionary(dict)) => dict,
+        _ => Dictionary::new(),
+    };
+    let mut xobjects = match resources.get(b"XObject") {
+        Ok(Object::Dictionary(dict)) => dict.clone(),
+        Ok(Object::Reference(id)) => doc
+            .get_dictionary(*id)
+            .cloned()
+            .unwrap_or_else(|_| Dictionary::new()),
+        _ => Dictionary::new(),
+    };
+
+    let name = (0..)
+        .map(|i| format!("InpdfX{}", i))
+        .find(|name| !xobjects.has(name.as_bytes()))
+        .expect("unbounded name search");
+    xobjects.set(name.as_bytes().to_vec(), Object::Reference(xobject_id));
+    resources.set("XObject", Object::Dictionary(xobjects));
+
+    doc.get_dictionary_mut(page_id)
+        .context("Page object is not a dictionary")?
+        .set("Resources", Object::Dictionary(resources));
+    Ok(name)
+}
+
+/// Add a content stream beneath or above a page's existing content.
+///
+/// When drawing over, the existing content is wrapped in `q`/`Q` so any
+/// graphics state it leaves behind doesn't leak into the new content.
+/// Streams in a `Contents` array are concatenated by readers, so added
+/// streams start with a newline to keep tokens from running together.
+pub fn add_content(
+    doc: &mut Document,
+    page_id: ObjectId,
+    ops: Vec<u8>,
+    layer: Layer,
+) -> Result<()> {
+    let mut contents = content_refs(doc, page_id);
+    let new_id = doc.add_object(Stream::new(Dictionary::new(), ops));
+
+    match layer {
+        Layer::Under => contents.insert(0, Object::Reference(new_id)),
+        Layer::Over => {
+            let save_id = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+            let restore_id = doc.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec()));
+            contents.insert(0, Object::Reference(save_id));
+            contents.push(Object::Reference(restore_id));
+            contents.push(Object::Reference(new_id));
+        }
+    }
+
+    doc.get_dictionary_mut(page_id)
+        .context("Page object is not a dictionary")?
+        .set("Contents", Object::Array(contents));
+    Ok(())
+}
+
+/// A page's content streams as a list of references.
+fn content_refs(doc: &Document, page_id: ObjectId) -> Vec<Object> {
+    let Ok(page) = doc.get_dictionary(page_id) else {
+        return Vec::new();
+    };
+    match page.get(b"Contents") {
+        Ok(Object::Reference(id)) => match doc.get_object(*id) {
+            Ok(Object::Array(items)) => items.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        Ok(Object::Array(items)) => items.clone(),
+        _ => Vec::new(),
+    }
+}
+
+/// Draw the pages of `overlay` beneath or above the pages of `target`.
+///
+/// A single-page overlay is repeated on every page; otherwise page N of the
+/// overlay is drawn on page N of the target, and target pages beyond the end
+/// of the overlay are left alone. Returns the number of pages modified.
+pub fn overlay_document(target: &mut Document, overlay: &Document, layer: Layer) -> Result<usize> {
+    let overlay_pages: Vec<ObjectId> = overlay.get_pages().into_values().collect();
+    if overlay_pages.is_empty() {
+        anyhow::bail!("Overlay document has no pages");
+    }
+    let target_pages: Vec<ObjectId> = target.get_pages().into_values().collect();
+
+    let mut importer = Importer::new(overlay);
+    let mut forms: HashMap<ObjectId, (ObjectId, Rect)> = HashMap::new();
+    let mut modified = 0;
+
+    for (i, &page_id) in target_pages.iter().enumerate() {
+        let overlay_page = if overlay_pages.len() == 1 {
+            overlay_pages[0]
+        } else if let Some(&page) = overlay_pages.get(i) {
+            page
+        } else {
+            break;
+        };
+
+        let (form_id, bbox) = match forms.get(&overlay_page) {
+            Some(&form) => form,
+            None => {
+                let form = page_to_form(&mut importer, target, overlay_page)?;
+                forms.insert(overlay_page, form);
+                form
+            }
+        };
+
+        // Align the overlay's lower-left corner with the target page's.
+        let target_box = page_box(target, page_id, b"CropBox");
+        let matrix = [
+            1.0,
+            0.0,
+            0.0,
+            1.0,
+            target_box[0] - bbox[0],
+            target_box[1] - bbox[1],
+        ];
+        place_form(target, page_id, form_id, matrix, layer)?;
+        modified += 1;
+    }
+
+    Ok(modified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pdf::testing::text_document;
+
+    #[test]
+    fn test_overlay_single_page_repeats() {
+        let (mut target, target_pages) = text_document(&["one", "two", "three"]);
+        let (letterhead, _) = text_document(&["LETTERHEAD"]);
+
+        let modified = overlay_document(&mut target, &letterhead, Layer::Under).unwrap();
+        assert_eq!(modified, 3);
+
+        for page_id in target_pages {
+            let content = String::from_utf8(target.get_page_content(page_id).unwrap()).unwrap();
+            let draw = content.find("/InpdfX0 Do").expect("overlay drawn");
+            let own = content.find("Tj").expect("original content kept");
+            assert!(draw < own, "letterhead should be drawn first");
+        }
+    }
+
+    #[test]
+    fn test_overlay_over_wraps_original() {
+        let (mut target, target_pages) = text_document(&["page"]);
+        let (stamp, _) = text_document(&["STAMP"]);
+
+        overlay_document(&mut target, &stamp, Layer::Over).unwrap();
+
+        let content = String::from_utf8(target.get_page_content(target_pages[0]).unwrap()).unwrap();
+        assert!(content.starts_with("q\n"));
+        assert!(content.trim_end().ends_with("/InpdfX0 Do Q"));
+    }
+}
@@ -0,0 +1,192 @@
+//! Python bindings, built with `maturin build` (see pyproject.toml).
+//!
+//! ```python
+//! import inpdf
+//! doc = inpdf.open("report.pdf")
+//! doc.grep(r"revenue", case_insensitive=True)
+//! ```
+//!
+//! Parsing and extraction run with the GIL released, so threads in the
+//! calling program keep running while a large document is read.
+
+use crate::page_range::expand_page_ranges;
+use crate::pdf::links::{fix_extracted_links, DanglingLinks};
+use crate::pdf::text::{extract_text_pages, grep_cached};
+use crate::pdf::toc::flatten_toc;
+use crate::pdf::PdfDocument;
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use regex::RegexBuilder;
+use std::path::PathBuf;
+
+fn runtime_error(err: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", err))
+}
+
+/// An open PDF. Create one with `inpdf.open(path)`.
+#[pyclass(name = "Document", module = "inpdf", frozen)]
+struct PyDocument {
+    doc: PdfDocument,
+}
+
+#[pymethods]
+impl PyDocument {
+    #[getter]
+    fn path(&self) -> &str {
+        &self.doc.path
+    }
+
+    #[getter]
+    fn page_count(&self) -> u32 {
+        self.doc.page_count()
+    }
+
+    /// Document metadata as a dict. `created` and `modified` are RFC 3339
+    /// strings; `creation_date` is the raw PDF date.
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let info = self.doc.get_info();
+        let dict = PyDict::new(py);
+        dict.set_item("path", &self.doc.path)?;
+        dict.set_item("page_count", info.page_count)?;
+        dict.set_item("title", info.title)?;
+        dict.set_item("author", info.author)?;
+        dict.set_item("creator", info.creator)?;
+        dict.set_item("producer", info.producer)?;
+        dict.set_item("creation_date", info.creation_date)?;
+        dict.set_item("created", info.created.map(|date| date.to_rfc3339()))?;
+        dict.set_item("modified", info.modified.map(|date| date.to_rfc3339()))?;
+        dict.set_item("subject", info.subject)?;
+        dict.set_item("keywords", info.keywords)?;
+        Ok(dict)
+    }
+
+    /// Bookmarks in document order, as dicts with `title`, `page` (None for
+    /// entries pointing outside the file), `external`, and `level`.
+    fn toc<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let entries = py.allow_threads(|| self.doc.toc()).map_err(runtime_error)?;
+        flatten_toc(&entries)
+            .into_iter()
+            .map(|entry| {
+                let dict = PyDict::new(py);
+                dict.set_item("title", entry.title)?;
+                dict.set_item("page", entry.page)?;
+                dict.set_item("external", entry.external.map(|t| t.to_string()))?;
+                dict.set_item("level", entry.level)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// The printed label of every page; `page_labels()[0]` is page 1's.
+    fn page_labels(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        let labels = py
+            .allow_threads(|| self.doc.page_labels())
+            .map_err(runtime_error)?;
+        Ok(labels.into_iter().map(|l| l.logical_label).collect())
+    }
+
+    /// Text of the pages in `pages` (e.g. "1-5,10,15-end"), as dicts with
+    /// `page`, `text`, and `error` (set when a page's text could not be
+    /// extracted).
+    fn read_pages<'py>(&self, py: Python<'py>, pages: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let page_list = expand_page_ranges(pages, self.doc.page_count())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        let texts = py
+            .allow_threads(|| extract_text_pages(&self.doc, &page_list))
+            .map_err(runtime_error)?;
+        texts
+            .into_iter()
+            .map(|text| {
+                let dict = PyDict::new(py);
+                dict.set_item("page", text.page)?;
+                dict.set_item("text", text.text)?;
+                dict.set_item("error", text.error)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// Lines matching the regular expression `pattern`, as dicts with
+    /// `page`, `line_number`, `text`, `match_start` and `match_end` (byte
+    /// offsets in `text`), and `page_start` and `page_end` (character
+    /// offsets in the page's full text).
+    #[pyo3(signature = (pattern, case_insensitive = false, max_results = 100))]
+    fn grep<'py>(
+        &self,
+        py: Python<'py>,
+        pattern: &str,
+        case_insensitive: bool,
+        max_results: usize,
+    ) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| PyValueError::new_err(format!("Invalid regex: {}", e)))?;
+        let matches = py
+            .allow_threads(|| grep_cached(self.doc.cached(), &regex, max_results))
+            .map_err(runtime_error)?;
+        matches
+            .into_iter()
+            .map(|m| {
+                let dict = PyDict::new(py);
+                dict.set_item("page", m.page)?;
+                dict.set_item("line_number", m.line_number)?;
+                dict.set_item("text", m.text)?;
+                dict.set_item("match_start", m.match_start)?;
+                dict.set_item("match_end", m.match_end)?;
+                dict.set_item("page_start", m.page_start)?;
+                dict.set_item("page_end", m.page_end)?;
+                Ok(dict)
+            })
+            .collect()
+    }
+
+    /// Write the pages in `pages` to a new PDF at `output`, dropping links
+    /// to pages left out. Returns the number of pages written.
+    fn extract(&self, py: Python<'_>, pages: &str, output: PathBuf) -> PyResult<usize> {
+        let page_list = expand_page_ranges(pages, self.doc.page_count())
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+        py.allow_threads(|| {
+            let mut new_doc = self.doc.extract_pages(&page_list)?;
+            fix_extracted_links(&mut new_doc, DanglingLinks::Drop);
+            PdfDocument::save(&mut new_doc, &output)
+        })
+        .map_err(runtime_error)?;
+        Ok(page_list.len())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<inpdf.Document {:?} ({} pages)>",
+            self.doc.path,
+            self.doc.page_count()
+        )
+    }
+}
+
+/// Open the PDF at `path`.
+#[pyfunction]
+fn open(py: Python<'_>, path: PathBuf) -> PyResult<PyDocument> {
+    let doc = py
+        .allow_threads(|| PdfDocument::open(&path))
+        .map_err(runtime_error)?;
+    Ok(PyDocument { doc })
+}
+
+/// Open a PDF from its contents (`bytes`), e.g. a download.
+#[pyfunction]
+fn open_bytes(py: Python<'_>, data: &[u8]) -> PyResult<PyDocument> {
+    let doc = py
+        .allow_threads(|| PdfDocument::open_bytes(data))
+        .map_err(runtime_error)?;
+    Ok(PyDocument { doc })
+}
+
+#[pymodule]
+fn inpdf(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyDocument>()?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+    m.add_function(wrap_pyfunction!(open_bytes, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,236 @@
+//! Reading PDFs from `http://` and `https://` URLs, and from `s3://` object
+//! storage with the `s3` feature.
+//!
+//! Downloads are kept in a per-user cache directory, keyed by URL, so
+//! running several commands against the same hosted document fetches it
+//! once.
+
+use crate::pdf::document::write_atomic;
+use anyhow::{bail, Context, Result};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// How long a download is reused before it is fetched again.
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub struct DownloadOptions {
+    /// Refuse documents larger than this many bytes.
+    pub max_bytes: u64,
+    /// Fetch again even if a recent download is cached.
+    pub refresh: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        DownloadOptions {
+            max_bytes: 100 << 20,
+            refresh: false,
+        }
+    }
+}
+
+/// Is `path` an `http(s)://` URL rather than a file?
+pub fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://"))
+}
+
+/// Is `path` an `s3://bucket/key` object?
+pub fn is_s3_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|s| s.starts_with("s3://"))
+}
+
+/// Return a local file for `path`: the path itself for files, or the
+/// cached download for URLs and S3 objects.
+pub fn fetch_if_url(path: &Path, options: &DownloadOptions) -> Result<PathBuf> {
+    if !is_url(path) {
+        return fetch_if_s3(path, options);
+    }
+    let url = path.to_str().expect("is_url checked for UTF-8");
+    let cached = cache_dir().join(cache_file_name(url));
+
+    let fresh = std::fs::metadata(&cached)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < CACHE_TTL);
+    if fresh && !options.refresh {
+        return Ok(cached);
+    }
+
+    let bytes = download(url, options.max_bytes)?;
+    save_download(&cached, &bytes)?;
+    Ok(cached)
+}
+
+/// Like [`fetch_if_url`], but only for `s3://` objects; everything else is
+/// returned as is.
+pub fn fetch_if_s3(path: &Path, options: &DownloadOptions) -> Result<PathBuf> {
+    if !is_s3_url(path) {
+        return Ok(path.to_path_buf());
+    }
+    let url = path.to_str().expect("is_s3_url checked for UTF-8");
+    fetch_s3(url, options)
+}
+
+#[cfg(not(feature = "s3"))]
+fn fetch_s3(url: &str, _options: &DownloadOptions) -> Result<PathBuf> {
+    bail!("Reading {} requires inpdf built with the s3 feature", url)
+}
+
+/// Fetch an S3 object into the download cache, reusing the cached copy
+/// while the object is unchanged.
+///
+/// Credentials and region come from the usual `AWS_*` environment
+/// variables.
+#[cfg(feature = "s3")]
+fn fetch_s3(url: &str, options: &DownloadOptions) -> Result<PathBuf> {
+    use object_store::aws::AmazonS3Builder;
+    use object_store::path::Path as ObjectPath;
+    use object_store::ObjectStore;
+
+    let (bucket, key) = url
+        .strip_prefix("s3://")
+        .and_then(|rest| rest.split_once('/'))
+        .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+        .with_context(|| format!("Expected s3://bucket/key, got {}", url))?;
+    let store = AmazonS3Builder::from_env()
+        .with_bucket_name(bucket)
+        .build()
+        .context("Failed to configure S3")?;
+    let location = ObjectPath::from(key);
+
+    let meta =
+        block_on(store.head(&location)).with_context(|| format!("Failed to look up {}", url))?;
+    let size = meta.size as u64;
+    if size > options.max_bytes {
+        return Err(too_large(url, options.max_bytes));
+    }
+
+    let cached = cache_dir().join(cache_file_name(url));
+    let unchanged = std::fs::metadata(&cached).is_ok_and(|local| {
+        local.len() == size
+            && local
+                .modified()
+                .is_ok_and(|modified| modified >= SystemTime::from(meta.last_modified))
+    });
+    if unchanged && !options.refresh {
+        return Ok(cached);
+    }
+
+    let bytes = block_on(async { store.get(&location).await?.bytes().await })
+        .with_context(|| format!("Failed to download {}", url))?;
+    save_download(&cached, &bytes)?;
+    Ok(cached)
+}
+
+/// Wait for an object store request from synchronous code.
+///
+/// Commands and MCP tools run on threads of the multi-threaded tokio
+/// runtime (or its blocking pool), where `Handle::block_on` alone would
+/// panic.
+#[cfg(feature = "s3")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+fn save_download(cached: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = cached.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    write_atomic(cached, bytes)
+        .with_context(|| format!("Failed to cache download in {}", cached.display()))
+}
+
+fn too_large(url: &str, max_bytes: u64) -> anyhow::Error {
+    anyhow::anyhow!(
+        "{} is larger than the {} MB download limit (raise it with --max-download)",
+        url,
+        max_bytes >> 20
+    )
+}
+
+fn download(url: &str, max_bytes: u64) -> Result<Vec<u8>> {
+    let response = ureq::get(url)
+        .call()
+        .with_context(|| format!("Failed to fetch {}", url))?;
+    let declared = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    if declared.is_some_and(|len| len > max_bytes) {
+        return Err(too_large(url, max_bytes));
+    }
+
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(max_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("Failed to download {}", url))?;
+    if bytes.len() as u64 > max_bytes {
+        return Err(too_large(url, max_bytes));
+    }
+    // Readers tolerate some junk before the header, so look a little way in.
+    if !bytes[..bytes.len().min(1024)]
+        .windows(5)
+        .any(|w| w == b"%PDF-")
+    {
+        bail!("{} did not return a PDF", url);
+    }
+    Ok(bytes)
+}
+
+fn cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("inpdf")
+        .join("downloads")
+}
+
+/// `https://host/papers/attention.pdf?x=1` -> `<hash>-attention.pdf`, so
+/// different URLs never share a file but the name stays recognizable.
+fn cache_file_name(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name: String = path
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        .collect();
+    let name = if name.is_empty() {
+        "download.pdf".to_string()
+    } else {
+        name
+    };
+    format!("{:016x}-{}", hasher.finish(), name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file_name() {
+        let name = cache_file_name("https://example.org/papers/attention.pdf?download=1");
+        assert!(name.ends_with("-attention.pdf"), "{}", name);
+        assert!(cache_file_name("https://example.org/").ends_with("-download.pdf"));
+        assert_ne!(
+            cache_file_name("https://a.example/x.pdf"),
+            cache_file_name("https://b.example/x.pdf")
+        );
+        assert!(is_url(Path::new("https://example.org/a.pdf")));
+        assert!(!is_url(Path::new("papers/https.pdf")));
+        assert!(is_s3_url(Path::new("s3://bucket/reports/q3.pdf")));
+        assert!(cache_file_name("s3://bucket/reports/q3.pdf").ends_with("-q3.pdf"));
+    }
+}
@@ -0,0 +1,600 @@
+//! Semantic search over a PDF's text.
+//!
+//! `inpdf index embed` splits each page's text into chunks, embeds them, and
+//! saves the vectors in a sidecar index (`file.pdf.embeddings.json`).
+//! Searching embeds the query with the same model and ranks chunks by cosine
+//! similarity, which finds paraphrases a regular expression misses.
+//!
+//! Embeddings come from an OpenAI-compatible HTTP endpoint (Ollama,
+//! llama.cpp, vLLM, ...) or a local ONNX model, and need inpdf built with
+//! the `semantic` feature. Chunking, the index, and ranking don't.
+
+use crate::commands::manifest::sha256_file;
+use crate::pdf::document::write_atomic;
+use crate::pdf::text::{extract_text_pages, grep_cached, GrepMatch};
+use crate::pdf::PdfDocument;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Chunks sent to the embedder per request.
+const BATCH_SIZE: usize = 32;
+
+/// Turns text into vectors.
+pub trait Embedder: Send + Sync + std::fmt::Debug {
+    /// Names the model, so an index is only searched with the model that
+    /// built it.
+    fn model(&self) -> &str;
+
+    /// One vector per text, in order.
+    fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// Where embeddings come from.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(not(feature = "semantic"), allow(dead_code))]
+pub struct EmbedderConfig {
+    /// An OpenAI-compatible `/v1/embeddings` URL.
+    pub endpoint: Option<String>,
+    /// The model to ask the endpoint for.
+    pub model: String,
+    /// A directory with `model.onnx` and `tokenizer.json`.
+    pub onnx_model: Option<PathBuf>,
+}
+
+impl EmbedderConfig {
+    pub fn connect(&self) -> Result<Arc<dyn Embedder>> {
+        #[cfg(feature = "semantic")]
+        {
+            match (&self.endpoint, &self.onnx_model) {
+                (Some(url), None) => {
+                    Ok(Arc::new(endpoint::EndpointEmbedder::new(url, &self.model)))
+                }
+                (None, Some(dir)) => Ok(Arc::new(onnx::OnnxEmbedder::load(dir)?)),
+                (Some(_), Some(_)) => {
+                    bail!("Use only one of --embedding-endpoint and --onnx-model")
+                }
+                (None, None) => {
+                    bail!("Semantic search needs --embedding-endpoint URL or --onnx-model DIR")
+                }
+            }
+        }
+        #[cfg(not(feature = "semantic"))]
+        bail!("Semantic search requires inpdf built with the semantic feature")
+    }
+}
+
+/// A PDF's chunks and their embeddings.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Index {
+    pub model: String,
+    /// SHA-256 of the PDF the index was built from, to notice when it
+    /// changes.
+    pub source_sha256: String,
+    pub chunks: Vec<IndexedChunk>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexedChunk {
+    pub page: u32,
+    pub text: String,
+    /// Normalized to unit length, so similarity is a dot product.
+    pub vector: Vec<f32>,
+}
+
+/// A chunk that matched a query.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub page: u32,
+    /// Cosine similarity to the query, from -1 to 1.
+    pub score: f32,
+    pub text: String,
+}
+
+/// A page found by [`Index::hybrid_search`].
+#[derive(Debug, Clone)]
+pub struct HybridHit {
+    pub page: u32,
+    /// Reciprocal rank fusion score; only meaningful relative to other hits.
+    pub score: f32,
+    /// Lines on the page that matched the pattern.
+    pub exact_matches: usize,
+    /// Similarity of the page's best chunk to the query, if it was ranked.
+    pub similarity: Option<f32>,
+    /// The first matching line, or else the best chunk.
+    pub snippet: String,
+}
+
+/// Damps the weight of top ranks in reciprocal rank fusion; 60 is the
+/// usual choice.
+const RRF_K: f32 = 60.0;
+
+/// `file.pdf` -> `file.pdf.embeddings.json`, next to the PDF.
+pub fn index_path(pdf: &Path) -> PathBuf {
+    let mut name = pdf.as_os_str().to_owned();
+    name.push(".embeddings.json");
+    PathBuf::from(name)
+}
+
+/// Chunk and embed every page of `pdf`.
+pub fn build_index(pdf: &Path, embedder: &dyn Embedder, chunk_chars: usize) -> Result<Index> {
+    let doc = PdfDocument::open_for_text(pdf)?;
+    let pages: Vec<u32> = (1..=doc.page_count()).collect();
+    let mut chunks: Vec<(u32, String)> = Vec::new();
+    for page in extract_text_pages(&doc, &pages)? {
+        if let Some(error) = &page.error {
+            eprintln!("Warning: page {}: {}", page.page, error);
+        }
+        for text in chunk_text(&page.text, chunk_chars) {
+            chunks.push((page.page, text));
+        }
+    }
+
+    let mut indexed = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(BATCH_SIZE) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = embedder.embed(&texts)?;
+        if vectors.len() != texts.len() {
+            bail!(
+                "The embedder returned {} vectors for {} chunks",
+                vectors.len(),
+                texts.len()
+            );
+        }
+        for ((page, text), vector) in batch.iter().zip(vectors) {
+            indexed.push(IndexedChunk {
+                page: *page,
+                text: text.clone(),
+                vector: normalized(vector),
+            });
+        }
+    }
+
+    Ok(Index {
+        model: embedder.model().to_string(),
+        source_sha256: sha256_file(pdf)?.1,
+        chunks: indexed,
+    })
+}
+
+impl Index {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        write_atomic(path, json.as_bytes())
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Load the index for `pdf` from `path`, warning if `pdf` has changed
+    /// since it was built.
+    pub fn load(path: &Path, pdf: &Path) -> Result<Index> {
+        let json = std::fs::read_to_string(path).with_context(|| {
+            format!(
+                "No embeddings index at {}; build one with `inpdf index embed {}`",
+                path.display(),
+                pdf.display()
+            )
+        })?;
+        let index: Index = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if sha256_file(pdf)?.1 != index.source_sha256 {
+            eprintln!(
+                "Warning: {} changed since {} was built; rebuild it with `inpdf index embed`",
+                pdf.display(),
+                path.display()
+            );
+        }
+        Ok(index)
+    }
+
+    /// The `top` chunks most similar to `query`.
+    pub fn search(&self, embedder: &dyn Embedder, query: &str, top: usize) -> Result<Vec<Hit>> {
+        if embedder.model() != self.model {
+            bail!(
+                "The index was built with {}, not {}; search with the same model or rebuild it",
+                self.model,
+                embedder.model()
+            );
+        }
+        let query = embedder
+            .embed(&[query.to_string()])?
+            .pop()
+            .context("The embedder returned no vector for the query")?;
+        Ok(self.nearest(&normalized(query), top))
+    }
+
+    /// Pages matching `pattern` exactly and pages whose chunks are close to
+    /// `query`, merged into one list by reciprocal rank fusion.
+    pub fn hybrid_search(
+        &self,
+        embedder: &dyn Embedder,
+        pdf: &Path,
+        query: &str,
+        pattern: &Regex,
+        top: usize,
+    ) -> Result<Vec<HybridHit>> {
+        let doc = PdfDocument::open_for_text(pdf)?;
+        let matches = grep_cached(doc.cached(), pattern, usize::MAX)?;
+        let hits = self.search(embedder, query, self.chunks.len())?;
+        Ok(fuse(&matches, &hits, top))
+    }
+
+    fn nearest(&self, query: &[f32], top: usize) -> Vec<Hit> {
+        let mut hits: Vec<Hit> = self
+            .chunks
+            .iter()
+            .map(|chunk| Hit {
+                page: chunk.page,
+                score: chunk.vector.iter().zip(query).map(|(a, b)| a * b).sum(),
+                text: chunk.text.clone(),
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top);
+        hits
+    }
+}
+
+/// Rank pages by exact match count and by best chunk similarity, then
+/// order them by the sum of `1 / (RRF_K + rank)` over both rankings, so a
+/// page near the top of either (or high in both) comes first.
+fn fuse(matches: &[GrepMatch], hits: &[Hit], top: usize) -> Vec<HybridHit> {
+    let mut pages: BTreeMap<u32, HybridHit> = BTreeMap::new();
+    for m in matches.iter().filter(|m| m.attachment.is_none()) {
+        let hit = pages.entry(m.page).or_insert_with(|| HybridHit {
+            page: m.page,
+            score: 0.0,
+            exact_matches: 0,
+            similarity: None,
+            snippet: m.text.trim().to_string(),
+        });
+        hit.exact_matches += 1;
+    }
+    let mut by_matches: Vec<(u32, usize)> = pages
+        .values()
+        .map(|hit| (hit.page, hit.exact_matches))
+        .collect();
+    by_matches.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    for (rank, (page, _)) in by_matches.iter().enumerate() {
+        pages.get_mut(page).unwrap().score += 1.0 / (RRF_K + rank as f32 + 1.0);
+    }
+
+    // Hits come best first; a page ranks by its best chunk.
+    let mut rank = 0;
+    for chunk in hits {
+        let hit = pages.entry(chunk.page).or_insert_with(|| HybridHit {
+            page: chunk.page,
+            score: 0.0,
+            exact_matches: 0,
+            similarity: None,
+            snippet: chunk.text.clone(),
+        });
+        if hit.similarity.is_none() {
+            hit.similarity = Some(chunk.score);
+            rank += 1;
+            hit.score += 1.0 / (RRF_K + rank as f32);
+        }
+    }
+
+    let mut fused: Vec<HybridHit> = pages.into_values().collect();
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.page.cmp(&b.page)));
+    fused.truncate(top);
+    fused
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
+}
+
+/// Split a page's text into chunks of at most `max_chars` characters,
+/// breaking between paragraphs where possible and between words otherwise.
+/// Whitespace within a paragraph is collapsed.
+fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut flush = |current: &mut String| {
+        if !current.is_empty() {
+            chunks.push(std::mem::take(current));
+        }
+    };
+
+    for paragraph in text.split("\n\n") {
+        let words: Vec<&str> = paragraph.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        let paragraph = words.join(" ");
+        let len = paragraph.chars().count();
+        if !current.is_empty() && current.chars().count() + 2 + len <= max_chars {
+            current.push_str("\n\n");
+            current.push_str(&paragraph);
+            continue;
+        }
+        flush(&mut current);
+        if len <= max_chars {
+            current = paragraph;
+            continue;
+        }
+        for word in words {
+            if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > max_chars
+            {
+                flush(&mut current);
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+    }
+    flush(&mut current);
+    chunks
+}
+
+#[cfg(feature = "semantic")]
+mod endpoint {
+    use super::Embedder;
+    use anyhow::{Context, Result};
+    use serde::Deserialize;
+
+    /// An OpenAI-compatible embeddings endpoint. Sends
+    /// `INPDF_EMBEDDING_API_KEY`, if set, as a bearer token.
+    #[derive(Debug)]
+    pub struct EndpointEmbedder {
+        url: String,
+        model: String,
+        api_key: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    struct Response {
+        data: Vec<Embedding>,
+    }
+
+    #[derive(Deserialize)]
+    struct Embedding {
+        index: usize,
+        embedding: Vec<f32>,
+    }
+
+    impl EndpointEmbedder {
+        pub fn new(url: &str, model: &str) -> Self {
+            EndpointEmbedder {
+                url: url.to_string(),
+                model: model.to_string(),
+                api_key: std::env::var("INPDF_EMBEDDING_API_KEY").ok(),
+            }
+        }
+    }
+
+    impl Embedder for EndpointEmbedder {
+        fn model(&self) -> &str {
+            &self.model
+        }
+
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            let mut request = ureq::post(&self.url);
+            if let Some(key) = &self.api_key {
+                request = request.set("Authorization", &format!("Bearer {}", key));
+            }
+            let response: Response = request
+                .send_json(serde_json::json!({ "model": self.model, "input": texts }))
+                .with_context(|| format!("Failed to get embeddings from {}", self.url))?
+                .into_json()
+                .with_context(|| format!("Unexpected embeddings response from {}", self.url))?;
+            let mut data = response.data;
+            data.sort_by_key(|embedding| embedding.index);
+            Ok(data
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect())
+        }
+    }
+}
+
+#[cfg(feature = "semantic")]
+mod onnx {
+    use super::Embedder;
+    use anyhow::{anyhow, Context, Result};
+    use ort::session::Session;
+    use ort::value::Tensor;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use tokenizers::Tokenizer;
+
+    /// Most tokens per chunk; BERT-style embedding models stop at 512.
+    const MAX_TOKENS: usize = 512;
+
+    /// A sentence-embedding model exported to ONNX, with its Hugging Face
+    /// `tokenizer.json`. Token embeddings are mean-pooled unless the model
+    /// already returns one vector per input.
+    pub struct OnnxEmbedder {
+        model: String,
+        session: Mutex<Session>,
+        tokenizer: Tokenizer,
+        wants_type_ids: bool,
+    }
+
+    impl std::fmt::Debug for OnnxEmbedder {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("OnnxEmbedder")
+                .field("model", &self.model)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl OnnxEmbedder {
+        pub fn load(dir: &Path) -> Result<Self> {
+            let model_path = dir.join("model.onnx");
+            let session = Session::builder()?
+                .commit_from_file(&model_path)
+                .with_context(|| format!("Failed to load {}", model_path.display()))?;
+            let tokenizer_path = dir.join("tokenizer.json");
+            let tokenizer = Tokenizer::from_file(&tokenizer_path)
+                .map_err(|e| anyhow!("Failed to load {}: {}", tokenizer_path.display(), e))?;
+            let wants_type_ids = session
+                .inputs
+                .iter()
+                .any(|input| input.name == "token_type_ids");
+            let name = dir.file_name().unwrap_or(dir.as_os_str()).to_string_lossy();
+            Ok(OnnxEmbedder {
+                model: format!("onnx:{}", name),
+                session: Mutex::new(session),
+                tokenizer,
+                wants_type_ids,
+            })
+        }
+
+        fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+            let encoding = self
+                .tokenizer
+                .encode(text, true)
+                .map_err(|e| anyhow!("Failed to tokenize: {}", e))?;
+            let len = encoding.get_ids().len().min(MAX_TOKENS);
+            let to_i64 = |values: &[u32]| -> Vec<i64> {
+                values[..len].iter().map(|&v| i64::from(v)).collect()
+            };
+            let mask = to_i64(encoding.get_attention_mask());
+
+            let mut inputs = ort::inputs![
+                "input_ids" => Tensor::from_array(([1, len], to_i64(encoding.get_ids())))?,
+                "attention_mask" => Tensor::from_array(([1, len], mask.clone()))?,
+            ];
+            if self.wants_type_ids {
+                inputs.push((
+                    "token_type_ids".into(),
+                    Tensor::from_array(([1, len], to_i64(encoding.get_type_ids())))?.into(),
+                ));
+            }
+
+            let mut session = self.session.lock().unwrap();
+            let outputs = session.run(inputs)?;
+            let (shape, data) = outputs[0].try_extract_tensor::<f32>()?;
+            match **shape {
+                // [batch, hidden]: already pooled.
+                [1, _] => Ok(data.to_vec()),
+                // [batch, tokens, hidden]: mean over the attended tokens.
+                [1, tokens, hidden] => {
+                    let (tokens, hidden) = (tokens as usize, hidden as usize);
+                    let mut pooled = vec![0.0; hidden];
+                    let mut count = 0.0;
+                    for token in 0..tokens.min(len) {
+                        if mask[token] == 0 {
+                            continue;
+                        }
+                        let row = &data[token * hidden..(token + 1) * hidden];
+                        pooled.iter_mut().zip(row).for_each(|(p, x)| *p += x);
+                        count += 1.0;
+                    }
+                    if count > 0.0 {
+                        pooled.iter_mut().for_each(|p| *p /= count);
+                    }
+                    Ok(pooled)
+                }
+                ref other => Err(anyhow!("Unexpected model output shape {:?}", other)),
+            }
+        }
+    }
+
+    impl Embedder for OnnxEmbedder {
+        fn model(&self) -> &str {
+            &self.model
+        }
+
+        fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            texts.iter().map(|text| self.embed_one(text)).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunk_text() {
+        let text = "First paragraph\nwraps here.\n\nSecond one.\n\n\n\nThird paragraph is long";
+        assert_eq!(
+            chunk_text(text, 40),
+            vec![
+                "First paragraph wraps here.\n\nSecond one.",
+                "Third paragraph is long"
+            ]
+        );
+        assert_eq!(
+            chunk_text(text, 12),
+            vec![
+                "First",
+                "paragraph",
+                "wraps here.",
+                "Second one.",
+                "Third",
+                "paragraph is",
+                "long"
+            ]
+        );
+        assert!(chunk_text(" \n\n ", 100).is_empty());
+    }
+
+    #[test]
+    fn test_nearest() {
+        let chunk = |page, vector: Vec<f32>| IndexedChunk {
+            page,
+            text: format!("page {}", page),
+            vector: normalized(vector),
+        };
+        let index = Index {
+            model: "test".to_string(),
+            source_sha256: String::new(),
+            chunks: vec![
+                chunk(1, vec![1.0, 0.0]),
+                chunk(2, vec![0.6, 0.8]),
+                chunk(3, vec![0.0, -1.0]),
+            ],
+        };
+        let hits = index.nearest(&normalized(vec![0.0, 2.0]), 2);
+        assert_eq!(hits.iter().map(|h| h.page).collect::<Vec<_>>(), [2, 1]);
+        assert!((hits[0].score - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fuse() {
+        let grep_match = |page, text: &str| GrepMatch {
+            page,
+            line_number: 1,
+            text: text.to_string(),
+            match_start: 0,
+            match_end: 0,
+            page_start: 0,
+            page_end: 0,
+            attachment: None,
+            ocr_confidence: None,
+            recovered: false,
+        };
+        let hit = |page, score| Hit {
+            page,
+            score,
+            text: format!("chunk on page {}", page),
+        };
+        let matches = [
+            grep_match(3, " token expires "),
+            grep_match(3, "token again"),
+            grep_match(7, "token"),
+        ];
+        let hits = [hit(5, 0.9), hit(3, 0.7), hit(3, 0.6), hit(9, 0.2)];
+
+        let fused = fuse(&matches, &hits, 3);
+        // Page 3 is high in both rankings; 5 tops the semantic one and 7 is
+        // second by matches, so 5 edges it out.
+        assert_eq!(fused.iter().map(|h| h.page).collect::<Vec<_>>(), [3, 5, 7]);
+        assert_eq!(fused[0].exact_matches, 2);
+        assert_eq!(fused[0].similarity, Some(0.7));
+        assert_eq!(fused[0].snippet, "token expires");
+        assert_eq!(fused[1].snippet, "chunk on page 5");
+    }
+}